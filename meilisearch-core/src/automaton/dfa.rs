@@ -5,6 +5,10 @@ static LEVDIST0: OnceCell<LevBuilder> = OnceCell::new();
 static LEVDIST1: OnceCell<LevBuilder> = OnceCell::new();
 static LEVDIST2: OnceCell<LevBuilder> = OnceCell::new();
 
+/// The queries up to this length only tolerate a transposition of two characters,
+/// any other typo is too much of a change for such short words.
+const TRANSPOSITIONS_ONLY_MAX_LEN: usize = 4;
+
 #[derive(Copy, Clone)]
 enum PrefixSetting {
     Prefix,
@@ -15,13 +19,21 @@ fn build_dfa_with_setting(query: &str, setting: PrefixSetting) -> DFA {
     use PrefixSetting::{NoPrefix, Prefix};
 
     match query.len() {
-        0..=4 => {
+        0..=1 => {
             let builder = LEVDIST0.get_or_init(|| LevBuilder::new(0, true));
             match setting {
                 Prefix => builder.build_prefix_dfa(query),
                 NoPrefix => builder.build_dfa(query),
             }
         }
+        // the short queries are not searched with a DFA, only their transpositions are looked up
+        2..=TRANSPOSITIONS_ONLY_MAX_LEN => {
+            let builder = LEVDIST1.get_or_init(|| LevBuilder::new(1, true));
+            match setting {
+                Prefix => builder.build_prefix_dfa(query),
+                NoPrefix => builder.build_dfa(query),
+            }
+        }
         5..=8 => {
             let builder = LEVDIST1.get_or_init(|| LevBuilder::new(1, true));
             match setting {
@@ -51,3 +63,14 @@ pub fn build_exact_dfa(query: &str) -> DFA {
     let builder = LEVDIST0.get_or_init(|| LevBuilder::new(0, true));
     builder.build_dfa(query)
 }
+
+pub fn build_exact_prefix_dfa(query: &str) -> DFA {
+    let builder = LEVDIST0.get_or_init(|| LevBuilder::new(0, true));
+    builder.build_prefix_dfa(query)
+}
+
+/// Whether the words matched by the DFA of the query must only be kept when they are exact
+/// or a transposition of the query.
+pub fn tolerates_only_transpositions(query: &str) -> bool {
+    query.len() <= TRANSPOSITIONS_ONLY_MAX_LEN
+}
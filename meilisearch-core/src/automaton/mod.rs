@@ -2,7 +2,7 @@ mod dfa;
 
 use meilisearch_tokenizer::is_cjk;
 
-pub use self::dfa::{build_dfa, build_prefix_dfa, build_exact_dfa};
+pub use self::dfa::{build_dfa, build_prefix_dfa, build_exact_dfa, build_exact_prefix_dfa, tolerates_only_transpositions};
 
 pub fn normalize_str(string: &str) -> String {
     let mut string = string.to_lowercase();
@@ -13,3 +13,66 @@ pub fn normalize_str(string: &str) -> String {
 
     string
 }
+
+/// Whether the word is the query with two adjacent characters swapped, like `the` for `hte`,
+/// only the start of the word is compared to a prefix query.
+pub fn is_transposition(query: &str, word: &[u8], prefix: bool) -> bool {
+    let word = match std::str::from_utf8(word) {
+        Ok(word) => word,
+        Err(_) => return false,
+    };
+
+    let query: Vec<_> = query.chars().collect();
+    let mut word: Vec<_> = word.chars().collect();
+    if prefix {
+        word.truncate(query.len());
+    }
+
+    if query.len() != word.len() {
+        return false;
+    }
+
+    match query.iter().zip(&word).position(|(q, w)| q != w) {
+        Some(i) if i + 1 < query.len() => {
+            query[i] == word[i + 1] && query[i + 1] == word[i] && query[i + 2..] == word[i + 2..]
+        }
+        _ => false,
+    }
+}
+
+/// Returns the words made by swapping two adjacent characters of the query, without duplicates.
+pub fn transpositions(query: &str) -> Vec<String> {
+    let chars: Vec<_> = query.chars().collect();
+    let mut words = Vec::new();
+
+    for i in 1..chars.len() {
+        if chars[i - 1] == chars[i] {
+            continue;
+        }
+
+        let mut word = chars.clone();
+        word.swap(i - 1, i);
+        let word: String = word.into_iter().collect();
+        if !words.contains(&word) {
+            words.push(word);
+        }
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transpositions_of_short_words() {
+        assert_eq!(transpositions("hte"), vec!["the".to_string(), "het".to_string()]);
+        assert_eq!(transpositions("aab"), vec!["aba".to_string()]);
+        assert_eq!(transpositions("a"), Vec::<String>::new());
+
+        for word in transpositions("fast") {
+            assert!(is_transposition("fast", word.as_bytes(), false));
+        }
+    }
+}
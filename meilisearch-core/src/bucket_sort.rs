@@ -4,26 +4,37 @@ use std::mem;
 use std::ops::Deref;
 use std::ops::Range;
 use std::rc::Rc;
+use std::slice;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::fmt;
 
 use compact_arena::{SmallArena, Idx32, mk_arena};
 use log::debug;
+use meilisearch_query::DEFAULT_MIN_PREFIX_LENGTH;
 use meilisearch_types::DocIndex;
+use once_cell::unsync::OnceCell;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
 use sdset::{Set, SetBuf, exponential_search, SetOperation, Counter, duo::OpBuilder};
 use slice_group_by::{GroupBy, GroupByMut};
 
 use crate::error::Error;
-use crate::criterion::{Criteria, Context, ContextMut};
+use crate::criterion::{Criteria, Context, ContextMut, SortKey};
 use crate::distinct_map::{BufferedDistinctMap, DistinctMap};
 use crate::raw_document::RawDocument;
 use crate::{database::MainT, reordered_attrs::ReorderedAttrs};
-use crate::{store, Document, DocumentId, MResult};
-use crate::query_tree::{create_query_tree, traverse_query_tree};
-use crate::query_tree::{Operation, QueryResult, QueryKind, QueryId, PostingsKey};
+use crate::{store, CancellationToken, Document, DocumentId, MResult, Number};
+use crate::query_tree::{adjacent_attributes, create_query_tree, query_prefix, query_words, traverse_query_tree};
+use crate::query_tree::{query_segmentations, QueryResult, QuerySplit, PostingsKey};
+use crate::{Operation, QueryKind, QueryId};
 use crate::query_tree::Context as QTContext;
 
+/// The number of candidates on which the facet values are counted when the count doesn't
+/// have to be exhaustive, the counts are then extrapolated to all of the candidates.
+pub const FACET_COUNT_SAMPLE_SIZE: usize = 10_000;
+const FACET_COUNT_SAMPLE_SEED: u64 = 42;
+
 #[derive(Debug, Default)]
 pub struct SortResult {
     pub documents: Vec<Document>,
@@ -31,47 +42,160 @@ pub struct SortResult {
     pub exhaustive_nb_hit: bool,
     pub facets: Option<HashMap<String, HashMap<String, usize>>>,
     pub exhaustive_facet_count: Option<bool>,
+    /// The number of documents in each geohash cell of the requested precision.
+    pub geo_distribution: Option<HashMap<String, usize>>,
+    /// Whether the search cutoff has been reached, the documents
+    /// have then only been partially sorted by the criteria.
+    pub degraded: bool,
+    /// The last words of the query that have been ignored
+    /// because no document contained all of the query words.
+    pub ignored_words: Vec<String>,
+    /// The query words that are not indexed and have been searched split in two words.
+    pub segmentations: Vec<QuerySplit>,
+    /// The time spent in each phase of the search, in the order they ran.
+    pub timings: Vec<SearchTiming>,
+    /// The sort key of the last document returned, to continue the search after it.
+    pub last_sort_key: Option<Vec<SortKey>>,
+}
+
+/// The time spent in a phase of the search, the phases don't overlap.
+#[derive(Debug, Clone)]
+pub struct SearchTiming {
+    pub phase: String,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+/// Times the successive phases of a search, a phase ends when the next one starts.
+#[derive(Default)]
+struct Phases {
+    timings: Vec<SearchTiming>,
+    current: Option<(String, Instant)>,
+}
+
+impl Phases {
+    fn start(&mut self, phase: impl Into<String>) {
+        self.end();
+        self.current = Some((phase.into(), Instant::now()));
+    }
+
+    fn end(&mut self) {
+        if let Some((phase, start)) = self.current.take() {
+            self.timings.push(SearchTiming { phase, start, duration: start.elapsed() });
+        }
+    }
+
+    fn finish(mut self) -> Vec<SearchTiming> {
+        self.end();
+        self.timings
+    }
+}
+
+type QueryTree = (Operation, HashMap<QueryId, Range<usize>>);
+
+/// Traverses the query tree of the query, removing the last words of the query one by
+/// one while no document contains all of the remaining words, if the index allows it.
+///
+/// The trees are lazily built into the `trees` slots, one per relaxation level,
+/// the returned tree is the one of the level that matched documents, along with
+/// the words that were removed from the query to find them.
+fn traverse_relaxed_query_tree<'o, 'txn>(
+    reader: &'txn heed::RoTxn<MainT>,
+    context: &QTContext,
+    query: &str,
+    words: &[String],
+    trees: &'o [OnceCell<QueryTree>],
+    phases: &mut Phases,
+) -> MResult<(&'o QueryTree, QueryResult<'o, 'txn>, Vec<String>)>
+{
+    let mut level = 0;
+    loop {
+        phases.start("query tree");
+        let tree = trees[level].get_or_try_init(|| {
+            if level == 0 {
+                create_query_tree(reader, context, query)
+            } else {
+                // the beginning of the query is kept as it is, not rebuilt from its words
+                let query = query_prefix(reader, context, query, words.len() - level);
+                create_query_tree(reader, context, query)
+            }
+        })?;
+
+        phases.start("traversal");
+        let result = traverse_query_tree(reader, context, &tree.0)?;
+        if !result.docids.is_empty() || level + 1 >= trees.len() {
+            let ignored_words = words[words.len() - level..].to_vec();
+            if !ignored_words.is_empty() {
+                debug!("ignored words {:?}", ignored_words);
+            }
+            return Ok((tree, result, ignored_words));
+        }
+
+        level += 1;
+    }
 }
 
 pub fn bucket_sort<'c, FI>(
     reader: &heed::RoTxn<MainT>,
     query: &str,
     range: Range<usize>,
+    search_after: Option<Vec<SortKey>>,
     facets_docids: Option<SetBuf<DocumentId>>,
     facet_count_docids: Option<HashMap<String, HashMap<String, Cow<Set<DocumentId>>>>>,
+    exhaustive_facets_count: bool,
+    geo_count_docids: Option<HashMap<String, Cow<Set<DocumentId>>>>,
     filter: Option<FI>,
     criteria: Criteria<'c>,
     searchable_attrs: Option<ReorderedAttrs>,
+    deadline: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+    max_total_hits: Option<usize>,
+    sample: Option<(usize, u64)>,
     main_store: store::Main,
     postings_lists_store: store::PostingsLists,
     documents_fields_counts_store: store::DocumentsFieldsCounts,
-    synonyms_store: store::Synonyms,
+    synonyms_store: Option<store::Synonyms>,
+    concatenate_ngrams: bool,
+    split_words: bool,
+    keyboard_typos: bool,
+    numeric_tolerance: bool,
     prefix_documents_cache_store: store::PrefixDocumentsCache,
     prefix_postings_lists_cache_store: store::PrefixPostingsListsCache,
 ) -> MResult<SortResult>
 where
     FI: Fn(DocumentId) -> bool,
 {
-    // We delegate the filter work to the distinct query builder,
+    // We delegate the filter and the sample work to the distinct query builder,
     // specifying a distinct rule that has no effect.
-    if filter.is_some() {
+    if filter.is_some() || sample.is_some() {
         let distinct = |_| None;
         let distinct_size = 1;
         return bucket_sort_with_distinct(
             reader,
             query,
             range,
+            search_after,
             facets_docids,
             facet_count_docids,
+            exhaustive_facets_count,
+            geo_count_docids,
             filter,
             distinct,
             distinct_size,
             criteria,
             searchable_attrs,
+            deadline,
+            cancellation,
+            max_total_hits,
+            sample,
             main_store,
             postings_lists_store,
             documents_fields_counts_store,
             synonyms_store,
+            concatenate_ngrams,
+            split_words,
+            keyboard_typos,
+            numeric_tolerance,
             prefix_documents_cache_store,
             prefix_postings_lists_cache_store,
         );
@@ -92,9 +216,31 @@ where
         synonyms: synonyms_store,
         postings_lists: postings_lists_store,
         prefix_postings_lists: prefix_postings_lists_cache_store,
+        concatenate_ngrams,
+        split_words,
+        keyboard_typos: keyboard_typos && main_store.keyboard_typos(reader)?.unwrap_or(false),
+        keyboard_layout: main_store.keyboard_layout(reader)?.map(Into::into).unwrap_or_default(),
+        optional_words: main_store.optional_words(reader)?.unwrap_or(false),
+        numeric_tolerance: if numeric_tolerance { main_store.numeric_tolerance(reader)? } else { None },
+        adjacent_attributes: adjacent_attributes(reader, main_store)?,
+        documents_fields_counts: documents_fields_counts_store,
+        normalize_units: main_store.normalize_units(reader)?.unwrap_or(false),
+        min_prefix_length: main_store.min_prefix_length(reader)?.unwrap_or(DEFAULT_MIN_PREFIX_LENGTH),
+        query_normalization: main_store.query_normalization(reader)?,
+        cancellation: cancellation.clone(),
     };
 
-    let (operation, mapping) = create_query_tree(reader, &context, query)?;
+    let query = &context.normalize_query(query);
+    let mut phases = Phases::default();
+    let words = query_words(reader, &context, query);
+    let levels = if context.optional_words { words.len().max(1) } else { 1 };
+    let trees: Vec<_> = (0..levels).map(|_| OnceCell::new()).collect();
+    let ((operation, mapping), query_result, ignored_words) =
+        traverse_relaxed_query_tree(reader, &context, query, &words, &trees, &mut phases)?;
+    phases.start("facets");
+    let searched_words = &words[..words.len() - ignored_words.len()];
+    result.segmentations = query_segmentations(reader, &context, searched_words)?;
+    result.ignored_words = ignored_words;
     debug!("operation:\n{:?}", operation);
     debug!("mapping:\n{:?}", mapping);
 
@@ -102,17 +248,25 @@ where
         match operation {
             Operation::And(ops) => ops.iter().for_each(|op| recurs_operation(map, op)),
             Operation::Or(ops) => ops.iter().for_each(|op| recurs_operation(map, op)),
+            Operation::Not(_) => (),
             Operation::Query(query) => { map.insert(query.id, &query.kind); },
         }
     }
 
     let mut queries_kinds = HashMap::new();
-    recurs_operation(&mut queries_kinds, &operation);
+    recurs_operation(&mut queries_kinds, operation);
 
-    let QueryResult { mut docids, queries } = traverse_query_tree(reader, &context, &operation)?;
+    let QueryResult { mut docids, queries } = query_result;
     debug!("found {} documents", docids.len());
     debug!("number of postings {:?}", queries.len());
 
+    // the facet values documents are already restricted to the ones matching the facet filters
+    if let Some(f) = facet_count_docids {
+        let exhaustive = exhaustive_facets_count || docids.len() <= FACET_COUNT_SAMPLE_SIZE;
+        result.exhaustive_facet_count = Some(exhaustive);
+        result.facets = Some(if exhaustive { facet_count(f, &docids) } else { sampled_facet_count(f, &docids) });
+    }
+
     if let Some(facets_docids) = facets_docids {
         let intersection = sdset::duo::OpBuilder::new(docids.as_ref(), facets_docids.as_set())
             .intersection()
@@ -120,12 +274,11 @@ where
         docids = Cow::Owned(intersection);
     }
 
-    if let Some(f) = facet_count_docids {
-        // hardcoded value, until approximation optimization
-        result.exhaustive_facet_count = Some(true);
-        result.facets = Some(facet_count(f, &docids));
+    if let Some(g) = geo_count_docids {
+        result.geo_distribution = Some(docids_count(g, &docids));
     }
 
+    phases.start("matches");
     let before = Instant::now();
     mk_arena!(arena);
     let mut bare_matches = cleanup_bare_matches(&mut arena, &docids, queries);
@@ -144,22 +297,44 @@ where
         before_raw_documents_building.elapsed(),
     );
 
+    if let Some(key) = &search_after {
+        phases.start("search after");
+        retain_after_key(reader, &mut raw_documents, &criteria, key, &mut arena, mapping, documents_fields_counts_store)?;
+    }
+
     let before_criterion_loop = Instant::now();
     let proximity_count = AtomicUsize::new(0);
 
     let mut groups = vec![raw_documents.as_mut_slice()];
 
     'criteria: for criterion in criteria.as_ref() {
+        phases.start(format!("ranking {}", criterion.name()));
         let tmp_groups = mem::replace(&mut groups, Vec::new());
         let mut documents_seen = 0;
 
         for mut group in tmp_groups {
+            CancellationToken::check(cancellation.as_ref())?;
+
+            if deadline.map_or(false, |d| Instant::now() >= d) {
+                debug!("search cutoff reached during the {:?} criterion", criterion.name());
+                result.degraded = true;
+                break 'criteria;
+            }
+
+            // if this group is entirely before the requested range,
+            // push it without sorting and splitting it
+            if documents_seen + group.len() <= range.start {
+                documents_seen += group.len();
+                groups.push(group);
+                continue;
+            }
+
             let before_criterion_preparation = Instant::now();
 
             let ctx = ContextMut {
                 reader,
                 postings_lists: &mut arena,
-                query_mapping: &mapping,
+                query_mapping: mapping,
                 documents_fields_counts_store,
             };
 
@@ -168,7 +343,7 @@ where
 
             let ctx = Context {
                 postings_lists: &arena,
-                query_mapping: &mapping,
+                query_mapping: mapping,
             };
 
             let before_criterion_sort = Instant::now();
@@ -190,9 +365,19 @@ where
         }
     }
 
+    phases.start("documents");
+    break_ties(&mut groups, range.start);
+
     debug!("criterion loop took {:.02?}", before_criterion_loop.elapsed());
     debug!("proximity evaluation called {} times", proximity_count.load(Ordering::Relaxed));
 
+    let last = range.end.min(raw_documents.len());
+    if last > range.start {
+        let document = &mut raw_documents[last - 1];
+        let key = document_sort_key(reader, document, &criteria, &mut arena, mapping, documents_fields_counts_store)?;
+        result.last_sort_key = Some(key);
+    }
+
     let schema = main_store.schema(reader)?.ok_or(Error::SchemaMissing)?;
     let iter = raw_documents.into_iter().skip(range.start).take(range.len());
     let iter = iter.map(|rd| Document::from_raw(rd, &queries_kinds, &arena, searchable_attrs.as_ref(), &schema));
@@ -200,8 +385,10 @@ where
 
     debug!("bucket sort took {:.02?}", before_bucket_sort.elapsed());
 
+    result.timings = phases.finish();
     result.documents = documents;
     result.nb_hits = docids.len();
+    result.exhaustive_nb_hit = true;
 
     Ok(result)
 }
@@ -210,17 +397,28 @@ pub fn bucket_sort_with_distinct<'c, FI, FD>(
     reader: &heed::RoTxn<MainT>,
     query: &str,
     range: Range<usize>,
+    search_after: Option<Vec<SortKey>>,
     facets_docids: Option<SetBuf<DocumentId>>,
     facet_count_docids: Option<HashMap<String, HashMap<String, Cow<Set<DocumentId>>>>>,
+    exhaustive_facets_count: bool,
+    geo_count_docids: Option<HashMap<String, Cow<Set<DocumentId>>>>,
     filter: Option<FI>,
     distinct: FD,
     distinct_size: usize,
     criteria: Criteria<'c>,
     searchable_attrs: Option<ReorderedAttrs>,
+    deadline: Option<Instant>,
+    cancellation: Option<CancellationToken>,
+    max_total_hits: Option<usize>,
+    sample: Option<(usize, u64)>,
     main_store: store::Main,
     postings_lists_store: store::PostingsLists,
     documents_fields_counts_store: store::DocumentsFieldsCounts,
-    synonyms_store: store::Synonyms,
+    synonyms_store: Option<store::Synonyms>,
+    concatenate_ngrams: bool,
+    split_words: bool,
+    keyboard_typos: bool,
+    numeric_tolerance: bool,
     _prefix_documents_cache_store: store::PrefixDocumentsCache,
     prefix_postings_lists_cache_store: store::PrefixPostingsListsCache,
 ) -> MResult<SortResult>
@@ -243,9 +441,31 @@ where
         synonyms: synonyms_store,
         postings_lists: postings_lists_store,
         prefix_postings_lists: prefix_postings_lists_cache_store,
+        concatenate_ngrams,
+        split_words,
+        keyboard_typos: keyboard_typos && main_store.keyboard_typos(reader)?.unwrap_or(false),
+        keyboard_layout: main_store.keyboard_layout(reader)?.map(Into::into).unwrap_or_default(),
+        optional_words: main_store.optional_words(reader)?.unwrap_or(false),
+        numeric_tolerance: if numeric_tolerance { main_store.numeric_tolerance(reader)? } else { None },
+        adjacent_attributes: adjacent_attributes(reader, main_store)?,
+        documents_fields_counts: documents_fields_counts_store,
+        normalize_units: main_store.normalize_units(reader)?.unwrap_or(false),
+        min_prefix_length: main_store.min_prefix_length(reader)?.unwrap_or(DEFAULT_MIN_PREFIX_LENGTH),
+        query_normalization: main_store.query_normalization(reader)?,
+        cancellation: cancellation.clone(),
     };
 
-    let (operation, mapping) = create_query_tree(reader, &context, query)?;
+    let query = &context.normalize_query(query);
+    let mut phases = Phases::default();
+    let words = query_words(reader, &context, query);
+    let levels = if context.optional_words { words.len().max(1) } else { 1 };
+    let trees: Vec<_> = (0..levels).map(|_| OnceCell::new()).collect();
+    let ((operation, mapping), query_result, ignored_words) =
+        traverse_relaxed_query_tree(reader, &context, query, &words, &trees, &mut phases)?;
+    phases.start("facets");
+    let searched_words = &words[..words.len() - ignored_words.len()];
+    result.segmentations = query_segmentations(reader, &context, searched_words)?;
+    result.ignored_words = ignored_words;
     debug!("operation:\n{:?}", operation);
     debug!("mapping:\n{:?}", mapping);
 
@@ -253,17 +473,25 @@ where
         match operation {
             Operation::And(ops) => ops.iter().for_each(|op| recurs_operation(map, op)),
             Operation::Or(ops) => ops.iter().for_each(|op| recurs_operation(map, op)),
+            Operation::Not(_) => (),
             Operation::Query(query) => { map.insert(query.id, &query.kind); },
         }
     }
 
     let mut queries_kinds = HashMap::new();
-    recurs_operation(&mut queries_kinds, &operation);
+    recurs_operation(&mut queries_kinds, operation);
 
-    let QueryResult { mut docids, queries } = traverse_query_tree(reader, &context, &operation)?;
+    let QueryResult { mut docids, queries } = query_result;
     debug!("found {} documents", docids.len());
     debug!("number of postings {:?}", queries.len());
 
+    // the facet values documents are already restricted to the ones matching the facet filters
+    if let Some(f) = facet_count_docids {
+        let exhaustive = exhaustive_facets_count || docids.len() <= FACET_COUNT_SAMPLE_SIZE;
+        result.exhaustive_facet_count = Some(exhaustive);
+        result.facets = Some(if exhaustive { facet_count(f, &docids) } else { sampled_facet_count(f, &docids) });
+    }
+
     if let Some(facets_docids) = facets_docids {
         let intersection = OpBuilder::new(docids.as_ref(), facets_docids.as_set())
             .intersection()
@@ -271,12 +499,35 @@ where
         docids = Cow::Owned(intersection);
     }
 
-    if let Some(f) = facet_count_docids {
-        // hardcoded value, until approximation optimization
-        result.exhaustive_facet_count = Some(true);
-        result.facets = Some(facet_count(f, &docids));
+    if let Some(g) = geo_count_docids {
+        result.geo_distribution = Some(docids_count(g, &docids));
     }
 
+    // the sample is drawn from the candidates passing the filter, only the
+    // sampled documents are then ranked and the range applies to them
+    let mut range = range;
+    let mut sampled_nb_hits = None;
+    if let Some((size, seed)) = sample {
+        phases.start("sample");
+        let candidates: Vec<_> = match &filter {
+            Some(filter) => docids.iter().copied().filter(|&id| (filter)(id)).collect(),
+            None => docids.to_vec(),
+        };
+        // the generator must draw the same numbers whatever the platform and the version of rand
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let amount = size.min(candidates.len());
+        let mut sampled: Vec<_> = rand::seq::index::sample(&mut rng, candidates.len(), amount)
+            .into_iter()
+            .map(|i| candidates[i])
+            .collect();
+        sampled.sort_unstable();
+
+        sampled_nb_hits = Some(candidates.len());
+        range = range.start.min(sampled.len())..range.end.min(sampled.len());
+        docids = Cow::Owned(SetBuf::new_unchecked(sampled));
+    }
+
+    phases.start("matches");
     let before = Instant::now();
     mk_arena!(arena);
     let mut bare_matches = cleanup_bare_matches(&mut arena, &docids, queries);
@@ -293,6 +544,11 @@ where
         before_raw_documents_building.elapsed(),
     );
 
+    if let Some(key) = &search_after {
+        phases.start("search after");
+        retain_after_key(reader, &mut raw_documents, &criteria, key, &mut arena, mapping, documents_fields_counts_store)?;
+    }
+
     let mut groups = vec![raw_documents.as_mut_slice()];
     let mut key_cache = HashMap::new();
 
@@ -304,11 +560,20 @@ where
     let mut distinct_raw_offset = 0;
 
     'criteria: for criterion in criteria.as_ref() {
+        phases.start(format!("ranking {}", criterion.name()));
         let tmp_groups = mem::replace(&mut groups, Vec::new());
         let mut buf_distinct = BufferedDistinctMap::new(&mut distinct_map);
         let mut documents_seen = 0;
 
         for mut group in tmp_groups {
+            CancellationToken::check(cancellation.as_ref())?;
+
+            if deadline.map_or(false, |d| Instant::now() >= d) {
+                debug!("search cutoff reached during the {:?} criterion", criterion.name());
+                result.degraded = true;
+                break 'criteria;
+            }
+
             // if this group does not overlap with the requested range,
             // push it without sorting and splitting it
             if documents_seen + group.len() < distinct_raw_offset {
@@ -320,7 +585,7 @@ where
             let ctx = ContextMut {
                 reader,
                 postings_lists: &mut arena,
-                query_mapping: &mapping,
+                query_mapping: mapping,
                 documents_fields_counts_store,
             };
 
@@ -330,7 +595,7 @@ where
 
             let ctx = Context {
                 postings_lists: &arena,
-                query_mapping: &mapping,
+                query_mapping: mapping,
             };
 
             let before_criterion_sort = Instant::now();
@@ -383,26 +648,70 @@ where
         }
     }
 
+    phases.start("documents");
+    break_ties(&mut groups, distinct_raw_offset);
+
+    // the candidates must all be filtered and distinguished to know the number of hits,
+    // the count can be stopped once it is considered large enough
+    let (nb_hits, exhaustive_nb_hit) = {
+        let mut nb_hits = 0;
+        let mut distinct_counts = HashMap::new();
+        let mut exhaustive = true;
+
+        for &id in docids.as_slice() {
+            if max_total_hits.map_or(false, |max| nb_hits >= max) {
+                exhaustive = false;
+                break;
+            }
+
+            let filter_accepted = match &filter {
+                Some(filter) => *filter_map.entry(id).or_insert_with(|| (filter)(id)),
+                None => true,
+            };
+
+            if filter_accepted {
+                let key = key_cache.entry(id).or_insert_with(|| (distinct)(id).map(Rc::new));
+                match key {
+                    Some(key) => {
+                        let count = distinct_counts.entry(key.clone()).or_insert(0);
+                        if *count < distinct_size {
+                            *count += 1;
+                            nb_hits += 1;
+                        }
+                    }
+                    None => nb_hits += 1,
+                }
+            }
+        }
+
+        (nb_hits, exhaustive)
+    };
+
     // once we classified the documents related to the current
     // automatons we save that as the next valid result
     let mut seen = BufferedDistinctMap::new(&mut distinct_map);
     let schema = main_store.schema(reader)?.ok_or(Error::SchemaMissing)?;
 
-    let mut documents = Vec::with_capacity(range.len());
-    for raw_document in raw_documents.into_iter().skip(distinct_raw_offset) {
+    let mut documents = Vec::with_capacity(range.len().min(raw_documents.len()));
+    for mut raw_document in raw_documents.into_iter().skip(distinct_raw_offset) {
+        // the documents have not all been seen by the criterion loop if the
+        // search cutoff has been reached, the missing values are computed here
         let filter_accepted = match &filter {
-            Some(_) => filter_map.remove(&raw_document.id).unwrap(),
+            Some(filter) => filter_map.remove(&raw_document.id).unwrap_or_else(|| (filter)(raw_document.id)),
             None => true,
         };
 
         if filter_accepted {
-            let key = key_cache.remove(&raw_document.id).unwrap();
+            let key = key_cache.remove(&raw_document.id)
+                .unwrap_or_else(|| (distinct)(raw_document.id).map(Rc::new));
             let distinct_accepted = match key {
                 Some(key) => seen.register(key),
                 None => seen.register_without_key(),
             };
 
             if distinct_accepted && seen.len() > range.start {
+                let key = document_sort_key(reader, &mut raw_document, &criteria, &mut arena, mapping, documents_fields_counts_store)?;
+                result.last_sort_key = Some(key);
                 documents.push(Document::from_raw(raw_document, &queries_kinds, &arena, searchable_attrs.as_ref(), &schema));
                 if documents.len() == range.len() {
                     break;
@@ -410,12 +719,118 @@ where
             }
         }
     }
+    result.timings = phases.finish();
     result.documents = documents;
-    result.nb_hits = docids.len();
+    match sampled_nb_hits {
+        Some(nb_hits) => {
+            result.nb_hits = nb_hits;
+            result.exhaustive_nb_hit = true;
+        }
+        None => {
+            result.nb_hits = nb_hits;
+            result.exhaustive_nb_hit = exhaustive_nb_hit;
+        }
+    }
 
     Ok(result)
 }
 
+/// The keys of the document for every criterion followed by its id, the ties are broken
+/// by the ids, a search continued after a document only returns the ones sorted after it.
+fn document_sort_key<'tag, 'txn>(
+    reader: &heed::RoTxn<MainT>,
+    document: &mut RawDocument<'_, 'tag>,
+    criteria: &Criteria,
+    arena: &mut SmallArena<'tag, PostingsListView<'txn>>,
+    mapping: &HashMap<QueryId, Range<usize>>,
+    documents_fields_counts_store: store::DocumentsFieldsCounts,
+) -> MResult<Vec<SortKey>>
+{
+    let mut key = Vec::with_capacity(criteria.as_ref().len() + 1);
+    for criterion in criteria.as_ref() {
+        let ctx = ContextMut {
+            reader,
+            postings_lists: &mut *arena,
+            query_mapping: mapping,
+            documents_fields_counts_store,
+        };
+        criterion.prepare(ctx, slice::from_mut(document))?;
+
+        let ctx = Context {
+            postings_lists: &*arena,
+            query_mapping: mapping,
+        };
+        key.push(criterion.sort_key(&ctx, document));
+    }
+    key.push(SortKey::Number(Number::Unsigned(document.id.0)));
+    Ok(key)
+}
+
+/// Removes the documents sorted before the document of the key and the document itself,
+/// the criteria are evaluated one after the other on the documents still tied with it.
+fn retain_after_key<'r, 'tag, 'txn>(
+    reader: &heed::RoTxn<MainT>,
+    documents: &mut Vec<RawDocument<'r, 'tag>>,
+    criteria: &Criteria,
+    key: &[SortKey],
+    arena: &mut SmallArena<'tag, PostingsListView<'txn>>,
+    mapping: &HashMap<QueryId, Range<usize>>,
+    documents_fields_counts_store: store::DocumentsFieldsCounts,
+) -> MResult<()>
+{
+    let (id_key, criteria_keys) = match key.split_last() {
+        Some(keys) => keys,
+        None => return Ok(()),
+    };
+
+    let mut tied = mem::replace(documents, Vec::new());
+    for (criterion, key) in criteria.as_ref().iter().zip(criteria_keys) {
+        if tied.is_empty() {
+            break;
+        }
+
+        let ctx = ContextMut {
+            reader,
+            postings_lists: &mut *arena,
+            query_mapping: mapping,
+            documents_fields_counts_store,
+        };
+        criterion.prepare(ctx, &mut tied)?;
+
+        let ctx = Context {
+            postings_lists: &*arena,
+            query_mapping: mapping,
+        };
+
+        let mut still_tied = Vec::new();
+        for document in tied {
+            let document_key = criterion.sort_key(&ctx, &document);
+            if document_key > *key {
+                documents.push(document);
+            } else if document_key == *key {
+                still_tied.push(document);
+            }
+        }
+        tied = still_tied;
+    }
+
+    documents.extend(tied.into_iter().filter(|d| SortKey::Number(Number::Unsigned(d.id.0)) > *id_key));
+    Ok(())
+}
+
+/// Sorts by id the documents that all of the criteria consider equal, the groups before
+/// the offset aside, so that the documents of the pages never depend on the criteria given,
+/// a document can't then be seen on two pages or on none.
+fn break_ties(groups: &mut [&mut [RawDocument]], offset: usize) {
+    let mut documents_seen = 0;
+    for group in groups.iter_mut() {
+        documents_seen += group.len();
+        if documents_seen > offset && group.len() > 1 {
+            group.sort_unstable_by_key(|document| document.id);
+        }
+    }
+}
+
 fn cleanup_bare_matches<'tag, 'txn>(
     arena: &mut SmallArena<'tag, PostingsListView<'txn>>,
     docids: &Set<DocumentId>,
@@ -425,7 +840,7 @@ fn cleanup_bare_matches<'tag, 'txn>(
     let docidslen = docids.len() as f32;
     let mut bare_matches = Vec::new();
 
-    for (PostingsKey { query, input, distance, is_exact }, matches) in queries {
+    for (PostingsKey { query, input, distance, is_exact, is_transposition }, matches) in queries {
         let postings_list_view = PostingsListView::original(Rc::from(input), Rc::new(matches));
         let pllen = postings_list_view.len() as f32;
 
@@ -442,6 +857,7 @@ fn cleanup_bare_matches<'tag, 'txn>(
                         query_index: query.id,
                         distance,
                         is_exact,
+                        is_transposition,
                         postings_list: posting_list_index,
                     };
 
@@ -473,6 +889,7 @@ fn cleanup_bare_matches<'tag, 'txn>(
                         query_index: query.id,
                         distance,
                         is_exact,
+                        is_transposition,
                         postings_list: posting_list_index,
                     };
 
@@ -494,9 +911,17 @@ pub struct BareMatch<'tag> {
     pub query_index: usize,
     pub distance: u8,
     pub is_exact: bool,
+    pub is_transposition: bool,
     pub postings_list: Idx32<'tag>,
 }
 
+impl BareMatch<'_> {
+    /// The cost of the typos of the match in half typos, a transposition costs a half.
+    pub fn typo_cost(&self) -> u8 {
+        self.distance * 2 - self.is_transposition as u8
+    }
+}
+
 impl fmt::Debug for BareMatch<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("BareMatch")
@@ -504,6 +929,7 @@ impl fmt::Debug for BareMatch<'_> {
             .field("query_index", &self.query_index)
             .field("distance", &self.distance)
             .field("is_exact", &self.is_exact)
+            .field("is_transposition", &self.is_transposition)
             .finish()
     }
 }
@@ -616,14 +1042,86 @@ fn facet_count(
 ) -> HashMap<String, HashMap<String, usize>> {
     let mut facets_counts = HashMap::with_capacity(facet_docids.len());
     for (key, doc_map) in facet_docids {
-        let mut count_map = HashMap::with_capacity(doc_map.len());
-        for (value, docids) in doc_map {
-            let mut counter = Counter::new();
-            let op = OpBuilder::new(docids.as_ref(), candidate_docids).intersection();
-            SetOperation::<DocumentId>::extend_collection(op, &mut counter);
-            count_map.insert(value, counter.0);
-        }
-        facets_counts.insert(key, count_map);
+        facets_counts.insert(key, docids_count(doc_map, candidate_docids));
     }
     facets_counts
 }
+
+/// Counts the facet values on randomly drawn candidates and scales the counts up to all of them,
+/// evenly spaced candidates would follow any periodicity of the documents ids.
+///
+/// The generator is always seeded the same, the same candidates give the same counts.
+fn sampled_facet_count(
+    facet_docids: HashMap<String, HashMap<String, Cow<Set<DocumentId>>>>,
+    candidate_docids: &Set<DocumentId>,
+) -> HashMap<String, HashMap<String, usize>> {
+    let mut rng = ChaCha8Rng::seed_from_u64(FACET_COUNT_SAMPLE_SEED);
+    let amount = FACET_COUNT_SAMPLE_SIZE.min(candidate_docids.len());
+    let mut sample: Vec<_> = rand::seq::index::sample(&mut rng, candidate_docids.len(), amount)
+        .into_iter()
+        .map(|i| candidate_docids[i])
+        .collect();
+    sample.sort_unstable();
+    let sample = SetBuf::new_unchecked(sample);
+
+    let mut counts = facet_count(facet_docids, &sample);
+    for count in counts.values_mut().flat_map(HashMap::values_mut) {
+        *count = (*count * candidate_docids.len() + sample.len() / 2) / sample.len();
+    }
+    counts
+}
+
+fn docids_count(
+    doc_map: HashMap<String, Cow<Set<DocumentId>>>,
+    candidate_docids: &Set<DocumentId>,
+) -> HashMap<String, usize> {
+    let mut count_map = HashMap::with_capacity(doc_map.len());
+    for (value, docids) in doc_map {
+        let mut counter = Counter::new();
+        let op = OpBuilder::new(docids.as_ref(), candidate_docids).intersection();
+        SetOperation::<DocumentId>::extend_collection(op, &mut counter);
+        count_map.insert(value, counter.0);
+    }
+    count_map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampled_facet_counts_are_extrapolated() {
+        let candidates = SetBuf::new_unchecked((0..25_000).map(DocumentId).collect());
+        let evens = SetBuf::new_unchecked((0..25_000).step_by(2).map(DocumentId).collect());
+        let rare = SetBuf::new_unchecked(vec![DocumentId(1)]);
+
+        let mut values = HashMap::new();
+        values.insert("even".to_string(), Cow::Borrowed(evens.as_set()));
+        values.insert("rare".to_string(), Cow::Borrowed(rare.as_set()));
+        let mut facets = HashMap::new();
+        facets.insert("parity".to_string(), values);
+
+        let counts = sampled_facet_count(facets, &candidates);
+        let even = counts["parity"]["even"];
+        assert!(even > 12_000 && even < 13_000, "{}", even);
+        // a value is either missed or counted for all the documents it stands for
+        let rare = counts["parity"]["rare"];
+        assert!(rare == 0 || rare == 3, "{}", rare);
+    }
+
+    #[test]
+    fn sampled_facet_counts_ignore_the_periodicity_of_the_ids() {
+        let candidates = SetBuf::new_unchecked((0..25_000).map(DocumentId).collect());
+        // one candidate out of three is kept by evenly spaced samples of 10 000 candidates
+        let thirds = SetBuf::new_unchecked((0..25_000).step_by(3).map(DocumentId).collect());
+
+        let mut values = HashMap::new();
+        values.insert("third".to_string(), Cow::Borrowed(thirds.as_set()));
+        let mut facets = HashMap::new();
+        facets.insert("period".to_string(), values);
+
+        let counts = sampled_facet_count(facets, &candidates);
+        let third = counts["period"]["third"];
+        assert!(third > 7_900 && third < 8_800, "{}", third);
+    }
+}
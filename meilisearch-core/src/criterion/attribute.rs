@@ -1,8 +1,10 @@
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use meilisearch_schema::Schema;
 use slice_group_by::GroupBy;
 use crate::{RawDocument, MResult};
 use crate::bucket_sort::SimpleMatch;
-use super::{Criterion, Context, ContextMut, prepare_bare_matches};
+use super::{Criterion, Context, ContextMut, SortKey, prepare_bare_matches};
 
 pub struct Attribute;
 
@@ -20,18 +22,87 @@ impl Criterion for Attribute {
     }
 
     fn evaluate(&self, _ctx: &Context, lhs: &RawDocument, rhs: &RawDocument) -> Ordering {
-        #[inline]
-        fn sum_of_attribute(matches: &[SimpleMatch]) -> usize {
-            let mut sum_of_attribute = 0;
-            for group in matches.linear_group_by_key(|bm| bm.query_index) {
-                sum_of_attribute += group[0].attribute as usize;
-            }
-            sum_of_attribute
-        }
-
         let lhs = sum_of_attribute(&lhs.processed_matches);
         let rhs = sum_of_attribute(&rhs.processed_matches);
 
         lhs.cmp(&rhs)
     }
+
+    fn sort_key(&self, _ctx: &Context, document: &RawDocument) -> SortKey {
+        SortKey::unsigned(sum_of_attribute(&document.processed_matches) as u64)
+    }
+}
+
+#[inline]
+fn sum_of_attribute(matches: &[SimpleMatch]) -> usize {
+    let mut sum_of_attribute = 0;
+    for group in matches.linear_group_by_key(|bm| bm.query_index) {
+        sum_of_attribute += group[0].attribute as usize;
+    }
+    sum_of_attribute
+}
+
+/// The attribute criterion scaled by the weights of the attributes.
+///
+/// Every query word is scored with the biggest weight of the attributes it matches,
+/// attributes without weight have a weight of one. Documents with the biggest
+/// sum of scores come first, ties are broken like the [`Attribute`] criterion.
+pub struct WeightedAttribute {
+    weights: HashMap<u16, u32>,
+}
+
+impl WeightedAttribute {
+    pub fn new(schema: &Schema, weights: &BTreeMap<String, u32>) -> WeightedAttribute {
+        let weights = weights
+            .iter()
+            .filter_map(|(name, weight)| {
+                let field_id = schema.id(name)?;
+                let indexed_pos = schema.is_indexed(field_id)?;
+                Some((indexed_pos.0, *weight))
+            })
+            .collect();
+
+        WeightedAttribute { weights }
+    }
+
+    fn weight(&self, attribute: u16) -> u32 {
+        self.weights.get(&attribute).cloned().unwrap_or(1)
+    }
+
+    fn sum_of_weights(&self, matches: &[SimpleMatch]) -> u64 {
+        let mut sum_of_weights = 0;
+        for group in matches.linear_group_by_key(|bm| bm.query_index) {
+            let weight = group.iter().map(|bm| self.weight(bm.attribute)).max().unwrap_or(0);
+            sum_of_weights += u64::from(weight);
+        }
+        sum_of_weights
+    }
+}
+
+impl Criterion for WeightedAttribute {
+    fn name(&self) -> &str { "attribute" }
+
+    fn prepare<'h, 'p, 'tag, 'txn, 'q, 'r>(
+        &self,
+        ctx: ContextMut<'h, 'p, 'tag, 'txn, 'q>,
+        documents: &mut [RawDocument<'r, 'tag>],
+    ) -> MResult<()>
+    {
+        prepare_bare_matches(documents, ctx.postings_lists, ctx.query_mapping);
+        Ok(())
+    }
+
+    fn evaluate(&self, ctx: &Context, lhs: &RawDocument, rhs: &RawDocument) -> Ordering {
+        let lhs_weights = self.sum_of_weights(&lhs.processed_matches);
+        let rhs_weights = self.sum_of_weights(&rhs.processed_matches);
+
+        rhs_weights.cmp(&lhs_weights).then_with(|| Attribute.evaluate(ctx, lhs, rhs))
+    }
+
+    fn sort_key(&self, ctx: &Context, document: &RawDocument) -> SortKey {
+        SortKey::Tuple(vec![
+            SortKey::unsigned(self.sum_of_weights(&document.processed_matches)).reversed(),
+            Attribute.sort_key(ctx, document),
+        ])
+    }
 }
@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 use crate::RawDocument;
-use super::{Criterion, Context};
+use super::{Criterion, Context, SortKey};
 
 pub struct DocumentId;
 
@@ -13,4 +13,8 @@ impl Criterion for DocumentId {
 
         lhs.cmp(rhs)
     }
+
+    fn sort_key(&self, _ctx: &Context, document: &RawDocument) -> SortKey {
+        SortKey::unsigned(document.id.0)
+    }
 }
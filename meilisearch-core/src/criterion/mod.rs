@@ -1,15 +1,19 @@
 use std::cmp::{self, Ordering};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Range;
 
 use compact_arena::SmallArena;
+use log::error;
+use meilisearch_schema::Schema;
 use sdset::SetBuf;
+use serde::{Deserialize, Serialize};
 use slice_group_by::GroupBy;
 
 use crate::bucket_sort::{SimpleMatch, PostingsListView};
 use crate::database::MainT;
-use crate::query_tree::QueryId;
-use crate::{store, RawDocument, MResult};
+use crate::settings::RankingRule;
+use crate::QueryId;
+use crate::{store, Number, RankedMap, RawDocument, MResult};
 
 mod typo;
 mod words;
@@ -19,15 +23,19 @@ mod words_position;
 mod exactness;
 mod document_id;
 mod sort_by_attr;
+mod sort_by_expr;
+mod sort_by_value;
 
 pub use self::typo::Typo;
 pub use self::words::Words;
 pub use self::proximity::Proximity;
-pub use self::attribute::Attribute;
+pub use self::attribute::{Attribute, WeightedAttribute};
 pub use self::words_position::WordsPosition;
 pub use self::exactness::Exactness;
 pub use self::document_id::DocumentId;
 pub use self::sort_by_attr::SortByAttr;
+pub use self::sort_by_expr::{Operator, RankingExpr, RankingExprError, SortByExpr};
+pub use self::sort_by_value::SortByValue;
 
 pub trait Criterion {
     fn name(&self) -> &str;
@@ -48,6 +56,14 @@ pub trait Criterion {
         rhs: &RawDocument<'r, 'tag>,
     ) -> Ordering;
 
+    /// The value of the document compared by `evaluate`, the keys of two
+    /// documents are ordered like `evaluate` orders the documents.
+    fn sort_key<'p, 'tag, 'txn, 'q, 'r>(
+        &self,
+        ctx: &Context<'p, 'tag, 'txn, 'q>,
+        document: &RawDocument<'r, 'tag>,
+    ) -> SortKey;
+
     #[inline]
     fn eq<'p, 'tag, 'txn, 'q, 'r>(
         &self,
@@ -60,6 +76,55 @@ pub trait Criterion {
     }
 }
 
+/// The value of a document compared by a criterion, a search can be continued
+/// after a document with the keys of all the criteria for this document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortKey {
+    Number(Number),
+    String(String),
+    /// Sorted in the reverse order of the key it holds.
+    Reversed(Box<SortKey>),
+    /// Sorted by the first key, then by the next ones.
+    Tuple(Vec<SortKey>),
+}
+
+impl SortKey {
+    pub(crate) fn unsigned(n: impl Into<u64>) -> SortKey {
+        SortKey::Number(Number::Unsigned(n.into()))
+    }
+
+    pub(crate) fn reversed(self) -> SortKey {
+        SortKey::Reversed(Box::new(self))
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SortKey::Number(_) => 0,
+            SortKey::String(_) => 1,
+            SortKey::Reversed(_) => 2,
+            SortKey::Tuple(_) => 3,
+        }
+    }
+}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &SortKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &SortKey) -> Ordering {
+        match (self, other) {
+            (SortKey::Number(lhs), SortKey::Number(rhs)) => lhs.cmp(rhs),
+            (SortKey::String(lhs), SortKey::String(rhs)) => lhs.cmp(rhs),
+            (SortKey::Reversed(lhs), SortKey::Reversed(rhs)) => rhs.cmp(lhs),
+            (SortKey::Tuple(lhs), SortKey::Tuple(rhs)) => lhs.cmp(rhs),
+            (lhs, rhs) => lhs.rank().cmp(&rhs.rank()),
+        }
+    }
+}
+
 pub struct ContextMut<'h, 'p, 'tag, 'txn, 'q> {
     pub reader: &'h heed::RoTxn<MainT>,
     pub postings_lists: &'p mut SmallArena<'tag, PostingsListView<'txn>>,
@@ -107,6 +172,53 @@ impl<'a> CriteriaBuilder<'a> {
         self.inner.push(Box::new(criterion));
     }
 
+    /// Pushes the criterion of a ranking rule, the attribute criterion is weighted by the
+    /// `attribute_weights` when given. The rules sorting by an attribute that isn't ranked or
+    /// by an invalid expression are logged and skipped.
+    pub fn push_ranking_rule(
+        &mut self,
+        rule: &RankingRule,
+        ranked_map: &'a RankedMap,
+        schema: &Schema,
+        attribute_weights: Option<&BTreeMap<String, u32>>,
+    ) {
+        fn boxed<'a, C: Criterion + 'a>(criterion: C) -> Box<dyn Criterion + 'a> {
+            Box::new(criterion)
+        }
+
+        let criterion = match rule {
+            RankingRule::Typo => Ok(boxed(Typo)),
+            RankingRule::Words => Ok(boxed(Words)),
+            RankingRule::Proximity => Ok(boxed(Proximity)),
+            RankingRule::Attribute => match attribute_weights {
+                Some(weights) => Ok(boxed(WeightedAttribute::new(schema, weights))),
+                None => Ok(boxed(Attribute)),
+            },
+            RankingRule::WordsPosition => Ok(boxed(WordsPosition)),
+            RankingRule::Exactness => Ok(boxed(Exactness)),
+            RankingRule::Asc(field) | RankingRule::AscNullsLast(field) => {
+                SortByAttr::lower_is_better(ranked_map, schema, field).map(boxed).map_err(|e| e.to_string())
+            }
+            RankingRule::Desc(field) | RankingRule::DescNullsLast(field) => {
+                SortByAttr::higher_is_better(ranked_map, schema, field).map(boxed).map_err(|e| e.to_string())
+            }
+            RankingRule::AscNullsFirst(field) => SortByAttr::lower_is_better(ranked_map, schema, field)
+                .map(|rule| boxed(rule.nulls_first()))
+                .map_err(|e| e.to_string()),
+            RankingRule::DescNullsFirst(field) => SortByAttr::higher_is_better(ranked_map, schema, field)
+                .map(|rule| boxed(rule.nulls_first()))
+                .map_err(|e| e.to_string()),
+            RankingRule::Expr(expr) => RankingExpr::parse(expr)
+                .map_err(|e| e.to_string())
+                .and_then(|expr| SortByExpr::new(ranked_map, schema, &expr).map(boxed).map_err(|e| e.to_string())),
+        };
+
+        match criterion {
+            Ok(criterion) => self.inner.push(criterion),
+            Err(e) => error!("Error during criteria builder; {}", e),
+        }
+    }
+
     pub fn build(self) -> Criteria<'a> {
         Criteria { inner: self.inner }
     }
@@ -154,10 +266,11 @@ fn prepare_query_distances<'a, 'tag, 'txn>(
 
             for index in range {
                 let index = index as usize;
+                let cost = m.typo_cost();
                 processed[index] = match processed[index] {
-                    Some(distance) if distance > m.distance => Some(m.distance),
-                    Some(distance) => Some(distance),
-                    None => Some(m.distance),
+                    Some(previous) if previous > cost => Some(cost),
+                    Some(previous) => Some(previous),
+                    None => Some(cost),
                 };
             }
         }
@@ -3,7 +3,7 @@ use std::error::Error;
 use std::fmt;
 use meilisearch_schema::{Schema, FieldId};
 use crate::{RankedMap, RawDocument};
-use super::{Criterion, Context};
+use super::{Criterion, Context, SortKey};
 
 /// An helper struct that permit to sort documents by
 /// some of their stored attributes.
@@ -43,6 +43,7 @@ pub struct SortByAttr<'a> {
     ranked_map: &'a RankedMap,
     field_id: FieldId,
     reversed: bool,
+    nulls_first: bool,
 }
 
 impl<'a> SortByAttr<'a> {
@@ -81,8 +82,16 @@ impl<'a> SortByAttr<'a> {
             ranked_map,
             field_id,
             reversed,
+            nulls_first: false,
         })
     }
+
+    /// Sorts the documents missing the attribute before the other ones,
+    /// they are sorted after them by default.
+    pub fn nulls_first(mut self) -> SortByAttr<'a> {
+        self.nulls_first = true;
+        self
+    }
 }
 
 impl Criterion for SortByAttr<'_> {
@@ -103,11 +112,26 @@ impl Criterion for SortByAttr<'_> {
                     order
                 }
             }
+            (None, Some(_)) if self.nulls_first => Ordering::Less,
+            (Some(_), None) if self.nulls_first => Ordering::Greater,
             (None, Some(_)) => Ordering::Greater,
             (Some(_), None) => Ordering::Less,
             (None, None) => Ordering::Equal,
         }
     }
+
+    fn sort_key(&self, _ctx: &Context, document: &RawDocument) -> SortKey {
+        // the documents missing the attribute are sorted by a first key
+        let (present, missing) = if self.nulls_first { (1, 0) } else { (0, 1) };
+        match self.ranked_map.get(document.id, self.field_id) {
+            Some(value) => {
+                let value = SortKey::Number(value);
+                let value = if self.reversed { value.reversed() } else { value };
+                SortKey::Tuple(vec![SortKey::unsigned(present as u64), value])
+            }
+            None => SortKey::Tuple(vec![SortKey::unsigned(missing as u64)]),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -0,0 +1,341 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use meilisearch_schema::{Schema, FieldId};
+use ordered_float::OrderedFloat;
+
+use crate::{DocumentId, MResult, Number, RankedMap, RawDocument};
+use super::sort_by_attr::SortByAttrError;
+use super::{Criterion, Context, ContextMut, SortKey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// An arithmetic expression over the ranked attributes of the documents,
+/// e.g. `popularity * 0.3 + rating * 0.7`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RankingExpr {
+    Number(f64),
+    Attribute(String),
+    Neg(Box<RankingExpr>),
+    Binary(Operator, Box<RankingExpr>, Box<RankingExpr>),
+}
+
+/// An invalid expression, `range` delimits the faulty bytes of the expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankingExprError {
+    pub range: Range<usize>,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for RankingExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.reason)
+    }
+}
+
+impl std::error::Error for RankingExprError {}
+
+impl RankingExpr {
+    /// Parses an expression made of numbers, attribute names, parentheses and the
+    /// `+`, `-`, `*` and `/` operators, with the usual precedence.
+    ///
+    /// The attribute names of an expression can only contain ascii alphanumeric
+    /// characters and underscores, a dash is always read as a subtraction.
+    pub fn parse(s: &str) -> Result<RankingExpr, RankingExprError> {
+        let mut parser = Parser { input: s, pos: 0 };
+        let expr = parser.expr()?;
+        parser.skip_whitespaces();
+        if parser.pos != s.len() {
+            return Err(parser.error_at_next_char("unexpected character"));
+        }
+        Ok(expr)
+    }
+
+    /// The names of the attributes used by the expression.
+    pub fn attributes(&self) -> Vec<&str> {
+        let mut attributes = Vec::new();
+        self.collect_attributes(&mut attributes);
+        attributes.sort_unstable();
+        attributes.dedup();
+        attributes
+    }
+
+    fn collect_attributes<'a>(&'a self, attributes: &mut Vec<&'a str>) {
+        match self {
+            RankingExpr::Number(_) => (),
+            RankingExpr::Attribute(name) => attributes.push(name),
+            RankingExpr::Neg(expr) => expr.collect_attributes(attributes),
+            RankingExpr::Binary(_, lhs, rhs) => {
+                lhs.collect_attributes(attributes);
+                rhs.collect_attributes(attributes);
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespaces(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespaces();
+        self.input[self.pos..].chars().next()
+    }
+
+    fn error_at_next_char(&self, reason: &'static str) -> RankingExprError {
+        let len = self.input[self.pos..].chars().next().map_or(0, char::len_utf8);
+        RankingExprError { range: self.pos..self.pos + len, reason }
+    }
+
+    fn expr(&mut self) -> Result<RankingExpr, RankingExprError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek() {
+                Some('+') => Operator::Add,
+                Some('-') => Operator::Sub,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = RankingExpr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn term(&mut self) -> Result<RankingExpr, RankingExprError> {
+        let mut lhs = self.factor()?;
+        loop {
+            let op = match self.peek() {
+                Some('*') => Operator::Mul,
+                Some('/') => Operator::Div,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.factor()?;
+            lhs = RankingExpr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn factor(&mut self) -> Result<RankingExpr, RankingExprError> {
+        match self.peek() {
+            Some('-') => {
+                self.pos += 1;
+                Ok(RankingExpr::Neg(Box::new(self.factor()?)))
+            }
+            Some('(') => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                match self.peek() {
+                    Some(')') => {
+                        self.pos += 1;
+                        Ok(expr)
+                    }
+                    _ => Err(self.error_at_next_char("missing closing parenthesis")),
+                }
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let start = self.pos;
+                let len = self.input[start..]
+                    .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                    .unwrap_or(self.input.len() - start);
+                self.pos += len;
+                match self.input[start..self.pos].parse() {
+                    Ok(number) => Ok(RankingExpr::Number(number)),
+                    Err(_) => Err(RankingExprError { range: start..self.pos, reason: "invalid number" }),
+                }
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                let start = self.pos;
+                let len = self.input[start..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(self.input.len() - start);
+                self.pos += len;
+                Ok(RankingExpr::Attribute(self.input[start..self.pos].to_string()))
+            }
+            Some(_) => Err(self.error_at_next_char("expected a number, an attribute name or a parenthesis")),
+            None => Err(RankingExprError { range: self.pos..self.pos, reason: "unexpected end of expression" }),
+        }
+    }
+}
+
+enum Compiled {
+    Number(f64),
+    Attribute(FieldId),
+    Neg(Box<Compiled>),
+    Binary(Operator, Box<Compiled>, Box<Compiled>),
+}
+
+impl Compiled {
+    fn new(expr: &RankingExpr, schema: &Schema) -> Result<Compiled, SortByAttrError> {
+        let compiled = match expr {
+            RankingExpr::Number(number) => Compiled::Number(*number),
+            RankingExpr::Attribute(name) => {
+                let field_id = schema.id(name).ok_or(SortByAttrError::AttributeNotFound)?;
+                if !schema.is_ranked(field_id) {
+                    return Err(SortByAttrError::AttributeNotRegisteredForRanking);
+                }
+                Compiled::Attribute(field_id)
+            }
+            RankingExpr::Neg(expr) => Compiled::Neg(Box::new(Compiled::new(expr, schema)?)),
+            RankingExpr::Binary(op, lhs, rhs) => {
+                let lhs = Compiled::new(lhs, schema)?;
+                let rhs = Compiled::new(rhs, schema)?;
+                Compiled::Binary(*op, Box::new(lhs), Box::new(rhs))
+            }
+        };
+        Ok(compiled)
+    }
+
+    fn eval(&self, ranked_map: &RankedMap, document: &RawDocument) -> Option<f64> {
+        match self {
+            Compiled::Number(number) => Some(*number),
+            Compiled::Attribute(field_id) => match ranked_map.get(document.id, *field_id)? {
+                Number::Unsigned(n) => Some(n as f64),
+                Number::Signed(n) => Some(n as f64),
+                Number::Float(OrderedFloat(n)) => Some(n),
+                Number::Null => None,
+            },
+            Compiled::Neg(expr) => expr.eval(ranked_map, document).map(|n| -n),
+            Compiled::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(ranked_map, document)?;
+                let rhs = rhs.eval(ranked_map, document)?;
+                let result = match op {
+                    Operator::Add => lhs + rhs,
+                    Operator::Sub => lhs - rhs,
+                    Operator::Mul => lhs * rhs,
+                    Operator::Div => lhs / rhs,
+                };
+                // a division of zero by zero has no score
+                if result.is_nan() { None } else { Some(result) }
+            }
+        }
+    }
+}
+
+/// Sorts the documents by the score computed with an expression,
+/// the higher the score the better the document.
+///
+/// The documents missing one of the attributes of the expression have no score
+/// and are sorted after the other ones.
+pub struct SortByExpr<'a> {
+    ranked_map: &'a RankedMap,
+    expr: Compiled,
+    scores: RefCell<HashMap<DocumentId, Option<OrderedFloat<f64>>>>,
+}
+
+impl<'a> SortByExpr<'a> {
+    pub fn new(
+        ranked_map: &'a RankedMap,
+        schema: &Schema,
+        expr: &RankingExpr,
+    ) -> Result<SortByExpr<'a>, SortByAttrError> {
+        let expr = Compiled::new(expr, schema)?;
+        Ok(SortByExpr { ranked_map, expr, scores: RefCell::new(HashMap::new()) })
+    }
+
+    fn score(&self, document: &RawDocument) -> Option<OrderedFloat<f64>> {
+        match self.scores.borrow().get(&document.id) {
+            Some(score) => *score,
+            None => self.expr.eval(self.ranked_map, document).map(OrderedFloat),
+        }
+    }
+}
+
+impl Criterion for SortByExpr<'_> {
+    fn name(&self) -> &str {
+        "sort by expression"
+    }
+
+    fn prepare<'h, 'p, 'tag, 'txn, 'q, 'r>(
+        &self,
+        _ctx: ContextMut<'h, 'p, 'tag, 'txn, 'q>,
+        documents: &mut [RawDocument<'r, 'tag>],
+    ) -> MResult<()>
+    {
+        let mut scores = self.scores.borrow_mut();
+        for document in documents.iter() {
+            let score = || self.expr.eval(self.ranked_map, document).map(OrderedFloat);
+            scores.entry(document.id).or_insert_with(score);
+        }
+
+        Ok(())
+    }
+
+    fn evaluate(&self, _ctx: &Context, lhs: &RawDocument, rhs: &RawDocument) -> Ordering {
+        match (self.score(lhs), self.score(rhs)) {
+            (Some(lhs), Some(rhs)) => rhs.cmp(&lhs),
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    fn sort_key(&self, _ctx: &Context, document: &RawDocument) -> SortKey {
+        match self.score(document) {
+            Some(value) => {
+                let value = SortKey::Number(Number::Float(value)).reversed();
+                SortKey::Tuple(vec![SortKey::unsigned(0u64), value])
+            }
+            None => SortKey::Tuple(vec![SortKey::unsigned(1u64)]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute(name: &str) -> Box<RankingExpr> {
+        Box::new(RankingExpr::Attribute(name.to_string()))
+    }
+
+    #[test]
+    fn parse_with_precedence() {
+        let expr = RankingExpr::parse("popularity * 0.3 + rating*0.7").unwrap();
+        let expected = RankingExpr::Binary(
+            Operator::Add,
+            Box::new(RankingExpr::Binary(Operator::Mul, attribute("popularity"), Box::new(RankingExpr::Number(0.3)))),
+            Box::new(RankingExpr::Binary(Operator::Mul, attribute("rating"), Box::new(RankingExpr::Number(0.7)))),
+        );
+        assert_eq!(expr, expected);
+        assert_eq!(expr.attributes(), vec!["popularity", "rating"]);
+
+        let expr = RankingExpr::parse("-(a - b) / 2").unwrap();
+        let expected = RankingExpr::Binary(
+            Operator::Div,
+            Box::new(RankingExpr::Neg(Box::new(RankingExpr::Binary(Operator::Sub, attribute("a"), attribute("b"))))),
+            Box::new(RankingExpr::Number(2.0)),
+        );
+        assert_eq!(expr, expected);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = RankingExpr::parse("rating * ").unwrap_err();
+        assert_eq!(error.range, 9..9);
+
+        let error = RankingExpr::parse("(rating + 2").unwrap_err();
+        assert_eq!(error.reason, "missing closing parenthesis");
+
+        let error = RankingExpr::parse("rating $ 2").unwrap_err();
+        assert_eq!(error.range, 7..8);
+
+        let error = RankingExpr::parse("1.2.3").unwrap_err();
+        assert_eq!(error.range, 0..5);
+    }
+}
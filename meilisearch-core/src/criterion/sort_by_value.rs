@@ -0,0 +1,194 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate};
+use meilisearch_schema::{FieldId, Schema};
+use serde_json::Value;
+
+use crate::automaton::normalize_str;
+use crate::settings::FieldType;
+use crate::{store, DocumentId, MResult, Number, RawDocument};
+use super::sort_by_attr::SortByAttrError;
+use super::{Criterion, Context, ContextMut, SortKey};
+
+/// A value of a document sorted by a [`SortByValue`], the numbers are sorted
+/// before the dates, themselves sorted before the strings.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue {
+    Number(Number),
+    /// The milliseconds since the epoch.
+    Date(i64),
+    /// The lowercased and deunicoded string, "Éclair" is sorted as "eclair".
+    String(String),
+}
+
+impl SortValue {
+    fn from_value(value: Value, field_type: Option<FieldType>) -> Option<SortValue> {
+        match value {
+            Value::Bool(boolean) => Some(SortValue::Number(Number::Unsigned(boolean as u64))),
+            Value::Number(number) => match (number.as_i64(), number.as_u64(), number.as_f64()) {
+                (Some(n), _, _) => Some(SortValue::Number(Number::Signed(n))),
+                (_, Some(n), _) => Some(SortValue::Number(Number::Unsigned(n))),
+                (_, _, Some(n)) => Some(SortValue::Number(Number::Float(n.into()))),
+                (None, None, None) => None,
+            },
+            Value::String(string) => {
+                if field_type == Some(FieldType::Date) {
+                    if let Some(timestamp) = date_timestamp(&string) {
+                        return Some(SortValue::Date(timestamp));
+                    }
+                }
+                if field_type != Some(FieldType::String) {
+                    if let Ok(number) = Number::from_str(&string) {
+                        return Some(SortValue::Number(number));
+                    }
+                }
+                Some(SortValue::String(normalize_str(&string)))
+            }
+            Value::Null | Value::Array(_) | Value::Object(_) => None,
+        }
+    }
+
+    /// The numbers are sorted before the dates, themselves sorted before the strings.
+    fn sort_key(&self) -> SortKey {
+        match self {
+            SortValue::Number(number) => SortKey::Tuple(vec![SortKey::unsigned(0u64), SortKey::Number(*number)]),
+            SortValue::Date(timestamp) => {
+                SortKey::Tuple(vec![SortKey::unsigned(1u64), SortKey::Number(Number::Signed(*timestamp))])
+            }
+            SortValue::String(string) => SortKey::Tuple(vec![SortKey::unsigned(2u64), SortKey::String(string.clone())]),
+        }
+    }
+}
+
+fn date_timestamp(string: &str) -> Option<i64> {
+    match DateTime::parse_from_rfc3339(string) {
+        Ok(date) => Some(date.timestamp_millis()),
+        Err(_) => {
+            let date = NaiveDate::parse_from_str(string, "%Y-%m-%d").ok()?;
+            Some(date.and_hms(0, 0, 0).timestamp_millis())
+        }
+    }
+}
+
+/// Sorts the documents by the stored values of an attribute, that doesn't have to be ranked
+/// by the index, the strings are compared regardless of their case and diacritics and the
+/// dates, the strings of the attributes declared as dates, chronologically.
+///
+/// The documents missing the attribute are sorted after the other ones.
+pub struct SortByValue {
+    documents_fields: store::DocumentsFields,
+    field_id: FieldId,
+    field_type: Option<FieldType>,
+    reversed: bool,
+    values: RefCell<HashMap<DocumentId, Option<SortValue>>>,
+}
+
+impl SortByValue {
+    pub fn new(
+        documents_fields: store::DocumentsFields,
+        schema: &Schema,
+        field_types: Option<&BTreeMap<String, FieldType>>,
+        attr_name: &str,
+        reversed: bool,
+    ) -> Result<SortByValue, SortByAttrError> {
+        let field_id = match schema.id(attr_name) {
+            Some(field_id) => field_id,
+            None => return Err(SortByAttrError::AttributeNotFound),
+        };
+
+        let field_type = field_types.and_then(|types| types.get(attr_name)).copied();
+
+        Ok(SortByValue {
+            documents_fields,
+            field_id,
+            field_type,
+            reversed,
+            values: RefCell::new(HashMap::new()),
+        })
+    }
+}
+
+impl Criterion for SortByValue {
+    fn name(&self) -> &str {
+        "sort by value"
+    }
+
+    fn prepare<'h, 'p, 'tag, 'txn, 'q, 'r>(
+        &self,
+        ctx: ContextMut<'h, 'p, 'tag, 'txn, 'q>,
+        documents: &mut [RawDocument<'r, 'tag>],
+    ) -> MResult<()>
+    {
+        let mut values = self.values.borrow_mut();
+        for document in documents.iter() {
+            if values.contains_key(&document.id) {
+                continue;
+            }
+
+            let value = match self.documents_fields.document_attribute(ctx.reader, document.id, self.field_id)? {
+                Some(bytes) => serde_json::from_slice(&bytes).ok().and_then(|v| SortValue::from_value(v, self.field_type)),
+                None => None,
+            };
+            values.insert(document.id, value);
+        }
+
+        Ok(())
+    }
+
+    fn evaluate(&self, _ctx: &Context, lhs: &RawDocument, rhs: &RawDocument) -> Ordering {
+        let values = self.values.borrow();
+        let lhs = values.get(&lhs.id).and_then(Option::as_ref);
+        let rhs = values.get(&rhs.id).and_then(Option::as_ref);
+
+        match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) => {
+                let order = lhs.cmp(rhs);
+                if self.reversed {
+                    order.reverse()
+                } else {
+                    order
+                }
+            }
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    fn sort_key(&self, _ctx: &Context, document: &RawDocument) -> SortKey {
+        let values = self.values.borrow();
+        match values.get(&document.id).and_then(Option::as_ref) {
+            Some(value) => {
+                let value = value.sort_key();
+                let value = if self.reversed { value.reversed() } else { value };
+                SortKey::Tuple(vec![SortKey::unsigned(0u64), value])
+            }
+            None => SortKey::Tuple(vec![SortKey::unsigned(1u64)]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn typed_sort_values() {
+        let value = |value, field_type| SortValue::from_value(value, field_type).unwrap();
+
+        assert_eq!(value(json!("Émeraude"), None), SortValue::String("emeraude".to_string()));
+        assert_eq!(value(json!("42"), None), SortValue::Number(Number::Unsigned(42)));
+        assert_eq!(value(json!("42"), Some(FieldType::String)), SortValue::String("42".to_string()));
+        assert!(value(json!(-3), None) < value(json!(2.5), None));
+
+        let older = value(json!("2019-12-31T23:00:00+00:00"), Some(FieldType::Date));
+        let newer = value(json!("2020-01-01"), Some(FieldType::Date));
+        assert!(older < newer);
+        assert!(newer < value(json!("adidas"), None));
+    }
+}
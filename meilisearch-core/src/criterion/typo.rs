@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 use crate::{RawDocument, MResult};
-use super::{Criterion, Context, ContextMut, prepare_query_distances};
+use super::{Criterion, Context, ContextMut, SortKey, prepare_query_distances};
 
 pub struct Typo;
 
@@ -18,38 +18,46 @@ impl Criterion for Typo {
     }
 
     fn evaluate(&self, _ctx: &Context, lhs: &RawDocument, rhs: &RawDocument) -> Ordering {
-        // This function is a wrong logarithmic 10 function.
-        // It is safe to panic on input number higher than 3,
-        // the number of typos is never bigger than that.
-        #[inline]
-        fn custom_log10(n: u8) -> f32 {
-            match n {
-                0 => 0.0,     // log(1)
-                1 => 0.30102, // log(2)
-                2 => 0.47712, // log(3)
-                3 => 0.60205, // log(4)
-                _ => panic!("invalid number"),
-            }
-        }
+        let lhs = compute_typos(&lhs.processed_distances);
+        let rhs = compute_typos(&rhs.processed_distances);
 
-        #[inline]
-        fn compute_typos(distances: &[Option<u8>]) -> usize {
-            let mut number_words: usize = 0;
-            let mut sum_typos = 0.0;
+        lhs.cmp(&rhs).reverse()
+    }
 
-            for distance in distances {
-                if let Some(distance) = distance {
-                    sum_typos += custom_log10(*distance);
-                    number_words += 1;
-                }
-            }
+    fn sort_key(&self, _ctx: &Context, document: &RawDocument) -> SortKey {
+        SortKey::unsigned(compute_typos(&document.processed_distances) as u64).reversed()
+    }
+}
 
-            (number_words as f32 / (sum_typos + 1.0) * 1000.0) as usize
-        }
+// This function is a wrong logarithmic 10 function of the typos plus one.
+// The costs are in half typos, a transposition costing a half.
+// It is safe to panic on input number higher than 6,
+// the number of typos is never bigger than 3.
+#[inline]
+fn custom_log10(n: u8) -> f32 {
+    match n {
+        0 => 0.0,     // log(1)
+        1 => 0.17609, // log(1.5)
+        2 => 0.30102, // log(2)
+        3 => 0.39794, // log(2.5)
+        4 => 0.47712, // log(3)
+        5 => 0.54406, // log(3.5)
+        6 => 0.60205, // log(4)
+        _ => panic!("invalid number"),
+    }
+}
 
-        let lhs = compute_typos(&lhs.processed_distances);
-        let rhs = compute_typos(&rhs.processed_distances);
+#[inline]
+fn compute_typos(distances: &[Option<u8>]) -> usize {
+    let mut number_words: usize = 0;
+    let mut sum_typos = 0.0;
 
-        lhs.cmp(&rhs).reverse()
+    for cost in distances {
+        if let Some(cost) = cost {
+            sum_typos += custom_log10(*cost);
+            number_words += 1;
+        }
     }
+
+    (number_words as f32 / (sum_typos + 1.0) * 1000.0) as usize
 }
@@ -1,6 +1,6 @@
 use std::cmp::Ordering;
 use crate::{RawDocument, MResult};
-use super::{Criterion, Context, ContextMut, prepare_query_distances};
+use super::{Criterion, Context, ContextMut, SortKey, prepare_query_distances};
 
 pub struct Words;
 
@@ -18,14 +18,18 @@ impl Criterion for Words {
     }
 
     fn evaluate(&self, _ctx: &Context, lhs: &RawDocument, rhs: &RawDocument) -> Ordering {
-        #[inline]
-        fn number_of_query_words(distances: &[Option<u8>]) -> usize {
-            distances.iter().cloned().filter(Option::is_some).count()
-        }
-
         let lhs = number_of_query_words(&lhs.processed_distances);
         let rhs = number_of_query_words(&rhs.processed_distances);
 
         lhs.cmp(&rhs).reverse()
     }
+
+    fn sort_key(&self, _ctx: &Context, document: &RawDocument) -> SortKey {
+        SortKey::unsigned(number_of_query_words(&document.processed_distances) as u64).reversed()
+    }
+}
+
+#[inline]
+fn number_of_query_words(distances: &[Option<u8>]) -> usize {
+    distances.iter().cloned().filter(Option::is_some).count()
 }
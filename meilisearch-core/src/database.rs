@@ -1,35 +1,62 @@
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::HashSet;
 use std::fs::File;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::{fs, thread};
 
 use crossbeam_channel::{Receiver, Sender};
-use heed::types::{Str, Unit};
+use heed::types::{ByteSlice, Str};
 use heed::{CompactionOption, Result as ZResult};
 use log::debug;
 use meilisearch_schema::Schema;
 
+use crate::encryption::{Cipher, ENCRYPTION_KEY_LEN};
+use crate::write_queue::{QueuedTxn, WriteQueues, WriteQueuesStats};
 use crate::{store, update, Index, MResult};
 
 pub type BoxUpdateFn = Box<dyn Fn(&str, update::ProcessedUpdateResult) + Send + Sync + 'static>;
 type ArcSwapFn = arc_swap::ArcSwapOption<BoxUpdateFn>;
+type ArcSwapUid = arc_swap::ArcSwap<String>;
 
 pub struct MainT;
 pub struct UpdateT;
 
+pub enum UpdateGroupOutcome {
+    /// The results of the updates of the group, they have all been applied or none if one failed.
+    Processed(Vec<(String, update::ProcessedUpdateResult)>),
+    /// Nothing has been applied, the index has enqueued updates that must be processed first.
+    PendingUpdates(String),
+    IndexNotFound(String),
+}
+
 pub struct Database {
     env: heed::Env,
     update_env: heed::Env,
     common_store: heed::PolyDatabase,
-    indexes_store: heed::Database<Str, Unit>,
-    indexes: RwLock<HashMap<String, (Index, thread::JoinHandle<MResult<()>>)>>,
+    update_common_store: heed::PolyDatabase,
+    /// The indexes uids associated with the name of their stores, the stores
+    /// of the indexes created before any renaming are named after their uids.
+    indexes_store: heed::Database<Str, Str>,
+    indexes: RwLock<HashMap<String, (Index, Arc<ArcSwapUid>, UpdateLoop)>>,
     update_fn: Arc<ArcSwapFn>,
+    stopping: Arc<AtomicBool>,
+    cipher: Option<&'static Cipher>,
+    index_max_data_size: Option<u64>,
+    write_queues: WriteQueues,
 }
 
 pub struct DatabaseOptions {
     pub main_map_size: usize,
     pub update_map_size: usize,
+    pub max_readers: u32,
+    /// The key encrypting the stored documents, the database must always be opened with
+    /// the key it has been created with. The indexed words are not encrypted.
+    pub encryption_key: Option<[u8; ENCRYPTION_KEY_LEN]>,
+    /// The data size, in bytes, from which the documents additions to the indexes without
+    /// a quota of their own fail, the indexes have no quota when not set.
+    pub index_max_data_size: Option<u64>,
 }
 
 impl Default for DatabaseOptions {
@@ -37,6 +64,9 @@ impl Default for DatabaseOptions {
         DatabaseOptions {
             main_map_size: 100 * 1024 * 1024 * 1024, //100Gb
             update_map_size: 100 * 1024 * 1024 * 1024, //100Gb
+            max_readers: 126, // the lmdb default
+            encryption_key: None,
+            index_max_data_size: None,
         }
     }
 }
@@ -56,90 +86,172 @@ macro_rules! r#break_try {
 pub enum UpdateEvent {
     NewUpdate,
     MustClear,
+    MustStop,
 }
 
 pub type UpdateEvents = Receiver<UpdateEvent>;
 pub type UpdateEventsEmitter = Sender<UpdateEvent>;
 
+/// The thread processing the updates of an index.
+struct UpdateLoop {
+    handle: thread::JoinHandle<MResult<()>>,
+    running: Arc<AtomicBool>,
+}
+
+/// Marks the update loop as stopped when dropped, the loop may have returned or panicked.
+struct RunningGuard(Arc<AtomicBool>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_update_loop(
+    receiver: UpdateEvents,
+    env: heed::Env,
+    update_env: heed::Env,
+    index_uid: Arc<ArcSwapUid>,
+    update_fn: Arc<ArcSwapFn>,
+    index: Index,
+    stopping: Arc<AtomicBool>,
+    write_queues: WriteQueues,
+) -> UpdateLoop {
+    let running = Arc::new(AtomicBool::new(true));
+    let guard = RunningGuard(running.clone());
+
+    let handle = thread::spawn(move || {
+        let _guard = guard;
+        update_awaiter(receiver, env, update_env, index_uid, update_fn, index, stopping, write_queues)
+    });
+
+    UpdateLoop { handle, running }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn update_awaiter(
     receiver: UpdateEvents,
     env: heed::Env,
     update_env: heed::Env,
-    index_uid: &str,
+    index_uid: Arc<ArcSwapUid>,
     update_fn: Arc<ArcSwapFn>,
     index: Index,
+    stopping: Arc<AtomicBool>,
+    write_queues: WriteQueues,
 ) -> MResult<()> {
     let mut receiver = receiver.into_iter();
+    let mut priority_streak = 0;
     while let Some(event) = receiver.next() {
 
         // if we receive a *MustClear* event, clear the index and break the loop
         if let UpdateEvent::MustClear = event {
-            let mut writer = env.typed_write_txn::<MainT>()?;
-            let mut update_writer = update_env.typed_write_txn::<UpdateT>()?;
+            let mut writer = write_queues.main_write_txn(&env)?;
+            let mut update_writer = write_queues.update_write_txn(&update_env)?;
 
             store::clear(&mut writer, &mut update_writer, &index)?;
 
             writer.commit()?;
             update_writer.commit()?;
 
-            debug!("store {} cleared", index_uid);
+            debug!("store {} cleared", index_uid.load());
 
             break
         }
 
+        // if we receive a *MustStop* event, leave the remaining updates
+        // in the store, they will be processed on the next start
+        if let UpdateEvent::MustStop = event {
+            break
+        }
+
         loop {
             // We instantiate a *write* transaction to *block* the thread
             // until the *other*, notifiying, thread commits
-            let result = update_env.typed_write_txn::<UpdateT>();
+            let result = write_queues.update_write_txn(&update_env);
             let update_reader = break_try!(result, "LMDB read transaction (update) begin failed");
 
-            // retrieve the update that needs to be processed
-            let result = index.updates.first_update(&update_reader);
-            let (update_id, update) = match break_try!(result, "pop front update failed") {
-                Some(value) => value,
-                None => {
-                    debug!("no more updates");
-                    break;
-                }
-            };
+            // stop between two updates if the database is closing, the
+            // update that is being processed is always committed first
+            if stopping.load(Ordering::SeqCst) {
+                debug!("update loop of {} stopping", index_uid.load());
+                break;
+            }
+
+            // retrieve the updates that need to be processed, the following ones
+            // are only read to be merged with the first one when possible
+            let result = env.typed_read_txn::<MainT>();
+            let main_reader = break_try!(result, "LMDB read transaction (main) begin failed");
+            let result = update::next_updates(&main_reader, &update_reader, &index, &mut priority_streak);
+            main_reader.abort();
+            let updates = break_try!(result, "pop front update failed");
+            if updates.is_empty() {
+                debug!("no more updates");
+                break;
+            }
 
             // do not keep the reader for too long
             update_reader.abort();
 
-            // instantiate a transaction to touch to the main env
-            let result = env.typed_write_txn::<MainT>();
+            // consecutive settings updates are applied in a single transaction
+            // to reindex the documents only once
+            let result = write_queues.main_write_txn(&env);
             let mut main_writer = break_try!(result, "LMDB nested write transaction failed");
 
-            // try to apply the update to the database using the main transaction
-            let result = update::update_task(&mut main_writer, &index, update_id, update);
-            let status = break_try!(result, "update task failed");
+            let statuses = match update::settings_batch_task(&mut main_writer, &index, &updates) {
+                Some(statuses) => {
+                    break_try!(main_writer.commit(), "commit nested transaction failed");
+                    statuses
+                }
+                None => {
+                    main_writer.abort();
+                    let (update_id, update) = updates.into_iter().next().unwrap();
+
+                    // instantiate a transaction to touch to the main env
+                    let result = write_queues.main_write_txn(&env);
+                    let mut main_writer = break_try!(result, "LMDB nested write transaction failed");
 
-            // commit the main transaction if the update was successful, abort it otherwise
-            if status.error.is_none() {
-                break_try!(main_writer.commit(), "commit nested transaction failed");
-            } else {
-                main_writer.abort()
-            }
+                    // try to apply the update to the database using the main transaction
+                    let result = update::update_task(&mut main_writer, &index, update_id, update);
+                    let status = break_try!(result, "update task failed");
+
+                    // commit the main transaction if the update was successful, abort it otherwise
+                    if status.error.is_none() {
+                        break_try!(main_writer.commit(), "commit nested transaction failed");
+                    } else {
+                        main_writer.abort()
+                    }
 
-            // now that the update has been processed we can instantiate
-            // a transaction to move the result to the updates-results store
-            let result = update_env.typed_write_txn::<UpdateT>();
+                    vec![status]
+                }
+            };
+
+            // now that the updates have been processed we can instantiate
+            // a transaction to move the results to the updates-results store
+            let result = write_queues.update_write_txn(&update_env);
             let mut update_writer = break_try!(result, "LMDB write transaction begin failed");
 
-            // definitely remove the update from the updates store
-            index.updates.del_update(&mut update_writer, update_id)?;
+            for status in &statuses {
+                // definitely remove the update from the updates store
+                index.updates.del_update(&mut update_writer, status.update_id)?;
+                index.updates_priorities.del_priority(&mut update_writer, status.update_id)?;
 
-            // write the result of the updates-results store
-            let updates_results = index.updates_results;
-            let result = updates_results.put_update_result(&mut update_writer, update_id, &status);
+                // write the result of the updates-results store
+                let updates_results = index.updates_results;
+                let result = updates_results.put_update_result(&mut update_writer, status.update_id, status);
+                break_try!(result, "update result store commit failed");
+            }
 
             // always commit the main transaction, even if the update was unsuccessful
-            break_try!(result, "update result store commit failed");
             break_try!(update_writer.commit(), "update transaction commit failed");
 
-            // call the user callback when the update and the result are written consistently
+            // call the user callback when the updates and the results are written consistently
             if let Some(ref callback) = *update_fn.load() {
-                (callback)(index_uid, status);
+                // the index may have been renamed while the updates were processed
+                let index_uid = index_uid.load();
+                for status in statuses {
+                    (callback)(&index_uid, status);
+                }
             }
         }
     }
@@ -149,6 +261,43 @@ fn update_awaiter(
     Ok(())
 }
 
+const ENCRYPTION_CHECK_KEY: &str = "encryption-check";
+
+/// Makes sure the database is opened with the key it has been created with, an encrypted
+/// value is stored on the first opening of an encrypted database to check it afterward.
+fn check_encryption_key(
+    env: &heed::Env,
+    common_store: heed::PolyDatabase,
+    indexes_store: heed::Database<Str, Str>,
+    cipher: Option<&Cipher>,
+) -> MResult<()> {
+    let mut writer = env.typed_write_txn::<MainT>()?;
+    let check = common_store.get::<_, Str, ByteSlice>(&writer, ENCRYPTION_CHECK_KEY)?.map(<[u8]>::to_vec);
+
+    match (cipher, check) {
+        (Some(cipher), Some(check)) => {
+            if cipher.decrypt(ENCRYPTION_CHECK_KEY.as_bytes(), &check).is_none() {
+                return Err(crate::Error::EncryptionKeyMismatch);
+            }
+        }
+        (Some(cipher), None) => {
+            // the documents of the existing indexes have been stored in clear
+            if indexes_store.first(&writer)?.is_some() {
+                return Err(crate::Error::EncryptionKeyMismatch);
+            }
+            let check = cipher
+                .encrypt(ENCRYPTION_CHECK_KEY.as_bytes(), ENCRYPTION_CHECK_KEY.as_bytes())
+                .ok_or(heed::Error::Encoding)?;
+            common_store.put::<_, Str, ByteSlice>(&mut writer, ENCRYPTION_CHECK_KEY, &check)?;
+        }
+        (None, Some(_)) => return Err(crate::Error::EncryptionKeyMismatch),
+        (None, None) => (),
+    }
+
+    writer.commit()?;
+    Ok(())
+}
+
 impl Database {
     pub fn open_or_create(path: impl AsRef<Path>, options: DatabaseOptions) -> MResult<Database> {
         let main_path = path.as_ref().join("main");
@@ -157,34 +306,45 @@ impl Database {
         fs::create_dir_all(&main_path)?;
         let env = heed::EnvOpenOptions::new()
             .map_size(options.main_map_size)
+            .max_readers(options.max_readers)
             .max_dbs(3000)
             .open(main_path)?;
 
         fs::create_dir_all(&update_path)?;
         let update_env = heed::EnvOpenOptions::new()
             .map_size(options.update_map_size)
+            .max_readers(options.max_readers)
             .max_dbs(3000)
             .open(update_path)?;
 
         let common_store = env.create_poly_database(Some("common"))?;
-        let indexes_store = env.create_database::<Str, Unit>(Some("indexes"))?;
+        let update_common_store = update_env.create_poly_database(Some("common"))?;
+        let indexes_store = env.create_database::<Str, Str>(Some("indexes"))?;
         let update_fn = Arc::new(ArcSwapFn::empty());
+        let stopping = Arc::new(AtomicBool::new(false));
+        let write_queues = WriteQueues::default();
+
+        // the cipher lives as long as the indexes stores that are copied around
+        let cipher = options.encryption_key.map(|key| &*Box::leak(Box::new(Cipher::new(&key))));
+        check_encryption_key(&env, common_store, indexes_store, cipher)?;
 
         // list all indexes that needs to be opened
         let mut must_open = Vec::new();
         let reader = env.read_txn()?;
         for result in indexes_store.iter(&reader)? {
-            let (index_uid, _) = result?;
-            must_open.push(index_uid.to_owned());
+            let (index_uid, store_name) = result?;
+            let store_name = if store_name.is_empty() { index_uid } else { store_name };
+            must_open.push((index_uid.to_owned(), store_name.to_owned()));
         }
 
         reader.abort();
 
         // open the previously aggregated indexes
         let mut indexes = HashMap::new();
-        for index_uid in must_open {
+        for (index_uid, store_name) in must_open {
             let (sender, receiver) = crossbeam_channel::unbounded();
-            let index = match store::open(&env, &update_env, &index_uid, sender.clone())? {
+            let max_data_size = options.index_max_data_size;
+            let index = match store::open(&env, &update_env, &store_name, sender.clone(), cipher, max_data_size)? {
                 Some(index) => index,
                 None => {
                     log::warn!(
@@ -195,28 +355,23 @@ impl Database {
                 }
             };
 
-            let env_clone = env.clone();
-            let update_env_clone = update_env.clone();
-            let index_clone = index.clone();
-            let name_clone = index_uid.clone();
-            let update_fn_clone = update_fn.clone();
-
-            let handle = thread::spawn(move || {
-                update_awaiter(
-                    receiver,
-                    env_clone,
-                    update_env_clone,
-                    &name_clone,
-                    update_fn_clone,
-                    index_clone,
-                )
-            });
+            let shared_uid = Arc::new(ArcSwapUid::from_pointee(index_uid.clone()));
+            let update_loop = spawn_update_loop(
+                receiver,
+                env.clone(),
+                update_env.clone(),
+                shared_uid.clone(),
+                update_fn.clone(),
+                index.clone(),
+                stopping.clone(),
+                write_queues.clone(),
+            );
 
             // send an update notification to make sure that
             // possible pre-boot updates are consumed
             sender.send(UpdateEvent::NewUpdate).unwrap();
 
-            let result = indexes.insert(index_uid, (index, handle));
+            let result = indexes.insert(index_uid, (index, shared_uid, update_loop));
             assert!(
                 result.is_none(),
                 "The index should not have been already open"
@@ -227,9 +382,14 @@ impl Database {
             env,
             update_env,
             common_store,
+            update_common_store,
             indexes_store,
             indexes: RwLock::new(indexes),
             update_fn,
+            stopping,
+            cipher,
+            index_max_data_size: options.index_max_data_size,
+            write_queues,
         })
     }
 
@@ -248,51 +408,71 @@ impl Database {
         match indexes_lock.entry(name.to_owned()) {
             Entry::Occupied(_) => Err(crate::Error::IndexAlreadyExists),
             Entry::Vacant(entry) => {
+                let reader = self.env.typed_read_txn::<MainT>()?;
+                let store_name = self.unused_store_name(&reader, name)?;
+                reader.abort();
+
                 let (sender, receiver) = crossbeam_channel::unbounded();
-                let index = store::create(&self.env, &self.update_env, name, sender)?;
+                let (cipher, max_data_size) = (self.cipher, self.index_max_data_size);
+                let index = store::create(&self.env, &self.update_env, &store_name, sender, cipher, max_data_size)?;
 
-                let mut writer = self.env.typed_write_txn::<MainT>()?;
-                self.indexes_store.put(&mut writer, name, &())?;
+                let mut writer = self.main_write_txn()?;
+                self.indexes_store.put(&mut writer, name, &store_name)?;
 
                 index.main.put_name(&mut writer, name)?;
                 index.main.put_created_at(&mut writer)?;
                 index.main.put_updated_at(&mut writer)?;
                 index.main.put_schema(&mut writer, &Schema::new())?;
 
-                let env_clone = self.env.clone();
-                let update_env_clone = self.update_env.clone();
-                let index_clone = index.clone();
-                let name_clone = name.to_owned();
-                let update_fn_clone = self.update_fn.clone();
-
-                let handle = thread::spawn(move || {
-                    update_awaiter(
-                        receiver,
-                        env_clone,
-                        update_env_clone,
-                        &name_clone,
-                        update_fn_clone,
-                        index_clone,
-                    )
-                });
+                let shared_uid = Arc::new(ArcSwapUid::from_pointee(name.to_owned()));
+                let update_loop = spawn_update_loop(
+                    receiver,
+                    self.env.clone(),
+                    self.update_env.clone(),
+                    shared_uid.clone(),
+                    self.update_fn.clone(),
+                    index.clone(),
+                    self.stopping.clone(),
+                    self.write_queues.clone(),
+                );
 
                 writer.commit()?;
-                entry.insert((index.clone(), handle));
+                entry.insert((index.clone(), shared_uid, update_loop));
 
                 Ok(index)
             }
         }
     }
 
+    /// The stores of a renamed index keep their name, a new index cannot always be named after its uid.
+    fn unused_store_name(&self, reader: &heed::RoTxn<MainT>, name: &str) -> MResult<String> {
+        let mut used = HashSet::new();
+        for result in self.indexes_store.iter(reader)? {
+            let (index_uid, store_name) = result?;
+            let store_name = if store_name.is_empty() { index_uid } else { store_name };
+            used.insert(store_name.to_owned());
+        }
+
+        let mut store_name = name.to_owned();
+        for i in 1.. {
+            if !used.contains(&store_name) {
+                break;
+            }
+            store_name = format!("{}-{}", name, i);
+        }
+
+        Ok(store_name)
+    }
+
     pub fn delete_index(&self, name: impl AsRef<str>) -> MResult<bool> {
         let name = name.as_ref();
         let mut indexes_lock = self.indexes.write().unwrap();
 
         match indexes_lock.remove_entry(name) {
-            Some((name, (index, handle))) => {
+            Some((name, (index, _, update_loop))) => {
                 // remove the index name from the list of indexes
                 // and clear all the LMDB dbi
-                let mut writer = self.env.write_txn()?;
+                let mut writer = self.main_write_txn()?;
                 self.indexes_store.delete(&mut writer, &name)?;
                 writer.commit()?;
 
@@ -302,7 +482,7 @@ impl Database {
                 drop(indexes_lock);
 
                 // join the update loop thread to ensure it is stopped
-                handle.join().unwrap()?;
+                update_loop.handle.join().unwrap()?;
 
                 Ok(true)
             }
@@ -310,6 +490,190 @@ impl Database {
         }
     }
 
+    /// Creates an index holding a copy of the documents and the settings of another one,
+    /// the stores are copied as they are rather than the documents being reindexed.
+    ///
+    /// The updates still enqueued in the copied index are not copied,
+    /// returns `None` if there is no index with this name.
+    pub fn copy_index(&self, name: impl AsRef<str>, new_name: impl AsRef<str>) -> MResult<Option<Index>> {
+        let (name, new_name) = (name.as_ref(), new_name.as_ref());
+        if self.open_index(name).is_none() {
+            return Ok(None);
+        }
+
+        let index = self.create_index(new_name)?;
+
+        let reader = self.env.typed_read_txn::<MainT>()?;
+        let store_name = |uid: &str| -> MResult<String> {
+            match self.indexes_store.get(&reader, uid)? {
+                Some(store_name) if !store_name.is_empty() => Ok(store_name.to_owned()),
+                _ => Ok(uid.to_owned()),
+            }
+        };
+        let (source, target) = (store_name(name)?, store_name(new_name)?);
+        reader.abort();
+
+        // the copy is a new index, named after its uid until it's renamed
+        let mut writer = self.main_write_txn()?;
+        let result = store::copy(&self.env, &source, &self.env, &target, &mut writer).and_then(|()| {
+            index.main.put_name(&mut writer, new_name)?;
+            index.main.put_created_at(&mut writer)?;
+            index.main.put_updated_at(&mut writer)?;
+            Ok(())
+        });
+
+        match result {
+            Ok(()) => writer.commit()?,
+            Err(error) => {
+                writer.abort();
+                self.delete_index(new_name)?;
+                return Err(error);
+            }
+        }
+
+        Ok(Some(index))
+    }
+
+    /// Mounts the index with the same uid of the database at the given path, an artifact built
+    /// by another instance that is opened read-only, and can be shared by several ones.
+    ///
+    /// The stores of the index must live in the environment of this database to be read with the
+    /// others, the artifact is copied once and its copy is only replaced by a new version of it.
+    /// A local index with the same uid that has not been mounted is never replaced.
+    ///
+    /// Returns `None` if there is no index with this name in the artifact.
+    pub fn mount_index(&self, name: impl AsRef<str>, path: impl AsRef<Path>) -> MResult<Option<Index>> {
+        let name = name.as_ref();
+        let artifact_path = path.as_ref().join("main");
+        let artifact = artifact_identity(&artifact_path)?;
+
+        let mut options = heed::EnvOpenOptions::new();
+        options.max_dbs(3000);
+        // Safety: the artifact is only read, the writes are refused by lmdb itself
+        unsafe { options.flag(heed::flags::Flags::MdbRdOnly) };
+        let artifact_env = options.open(&artifact_path)?;
+
+        let artifact_store = match artifact_env.open_database::<Str, Str>(Some("indexes"))? {
+            Some(indexes_store) => {
+                let reader = artifact_env.typed_read_txn::<MainT>()?;
+                let store_name = match indexes_store.get(&reader, name)? {
+                    Some(store_name) if !store_name.is_empty() => store_name.to_owned(),
+                    Some(_) => name.to_owned(),
+                    None => return Ok(None),
+                };
+                reader.abort();
+                store_name
+            }
+            None => return Ok(None),
+        };
+
+        if let Some(index) = self.open_index(name) {
+            let reader = self.env.typed_read_txn::<MainT>()?;
+            let mounted = index.main.mounted_artifact(&reader)?;
+            reader.abort();
+
+            match mounted {
+                Some(mounted) if mounted == artifact => return Ok(Some(index)),
+                // only the copy of a previous version of an artifact is replaced
+                Some(_) => self.delete_index(name)?,
+                None => return Err(crate::Error::IndexAlreadyExists),
+            };
+        }
+
+        let index = self.create_index(name)?;
+
+        let reader = self.env.typed_read_txn::<MainT>()?;
+        let store_name = match self.indexes_store.get(&reader, name)? {
+            Some(store_name) if !store_name.is_empty() => store_name.to_owned(),
+            _ => name.to_owned(),
+        };
+        reader.abort();
+
+        let mut writer = self.main_write_txn()?;
+        let result = store::copy(&artifact_env, &artifact_store, &self.env, &store_name, &mut writer)
+            .and_then(|()| Ok(index.main.put_mounted_artifact(&mut writer, &artifact)?));
+        match result {
+            Ok(()) => writer.commit()?,
+            Err(error) => {
+                writer.abort();
+                self.delete_index(name)?;
+                return Err(error);
+            }
+        }
+
+        Ok(Some(index))
+    }
+
+    /// Gives a new uid to an index, the documents are not reindexed as the stores keep their names.
+    /// The name of the index is also changed when it is its uid, as given by default.
+    ///
+    /// The update loop of the index reports the updates processed from now on under the new uid.
+    pub fn rename_index(&self, name: impl AsRef<str>, new_name: impl AsRef<str>) -> MResult<bool> {
+        self.rename_index_with(name, new_name, |_| Ok(()))
+    }
+
+    /// Like `rename_index`, `f` is given the transaction renaming the index to also move what
+    /// is stored under its uid, the index is not renamed if it fails.
+    pub fn rename_index_with<F>(&self, name: impl AsRef<str>, new_name: impl AsRef<str>, f: F) -> MResult<bool>
+    where
+        F: FnOnce(&mut heed::RwTxn<MainT>) -> MResult<()>,
+    {
+        let (name, new_name) = (name.as_ref(), new_name.as_ref());
+        let mut indexes_lock = self.indexes.write().unwrap();
+
+        if indexes_lock.contains_key(new_name) {
+            return Err(crate::Error::IndexAlreadyExists);
+        }
+
+        let index = match indexes_lock.get(name) {
+            Some((index, ..)) => index.clone(),
+            None => return Ok(false),
+        };
+
+        let mut writer = self.main_write_txn()?;
+        let store_name = match self.indexes_store.get(&writer, name)? {
+            Some(store_name) if !store_name.is_empty() => store_name.to_owned(),
+            _ => name.to_owned(),
+        };
+        self.indexes_store.delete(&mut writer, name)?;
+        self.indexes_store.put(&mut writer, new_name, &store_name)?;
+        if index.main.name(&writer)?.as_deref() == Some(name) {
+            index.main.put_name(&mut writer, new_name)?;
+        }
+        index.main.put_updated_at(&mut writer)?;
+        f(&mut *writer)?;
+        writer.commit()?;
+
+        if let Some((index, shared_uid, update_loop)) = indexes_lock.remove(name) {
+            shared_uid.store(Arc::new(new_name.to_owned()));
+            indexes_lock.insert(new_name.to_owned(), (index, shared_uid, update_loop));
+        }
+
+        Ok(true)
+    }
+
+    /// Stops the update loops of all the indexes once their current update is committed.
+    ///
+    /// The updates that are still enqueued are kept and processed on the next opening
+    /// of the database, the indexes can no longer be updated once this method returns.
+    pub fn close(&self) -> MResult<()> {
+        self.stopping.store(true, Ordering::SeqCst);
+
+        let mut indexes_lock = self.indexes.write().unwrap();
+        let mut handles = Vec::new();
+        for (_, (index, _, update_loop)) in indexes_lock.drain() {
+            let _ = index.updates_notifier.send(UpdateEvent::MustStop);
+            handles.push(update_loop.handle);
+        }
+        drop(indexes_lock);
+
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+
+        Ok(())
+    }
+
     pub fn set_update_callback(&self, update_fn: BoxUpdateFn) {
         let update_fn = Some(Arc::new(update_fn));
         self.update_fn.swap(update_fn);
@@ -323,16 +687,97 @@ impl Database {
         self.env.typed_read_txn::<MainT>()
     }
 
-    pub fn main_write_txn(&self) -> heed::Result<heed::RwTxn<MainT>> {
-        self.env.typed_write_txn::<MainT>()
+    /// Opens a write transaction on the main environment once the ones requested before,
+    /// by the update loops too, are done. It can wait for a whole import to be processed.
+    pub fn main_write_txn(&self) -> heed::Result<QueuedTxn<'_, MainT>> {
+        self.write_queues.main_write_txn(&self.env)
+    }
+
+    /// Like `main_write_txn` but waits for the turn of the transaction without blocking the thread.
+    pub async fn main_write_txn_async(&self) -> heed::Result<QueuedTxn<'_, MainT>> {
+        self.write_queues.main_write_txn_async(&self.env).await
     }
 
     pub fn update_read_txn(&self) -> heed::Result<heed::RoTxn<UpdateT>> {
         self.update_env.typed_read_txn::<UpdateT>()
     }
 
-    pub fn update_write_txn(&self) -> heed::Result<heed::RwTxn<UpdateT>> {
-        self.update_env.typed_write_txn::<UpdateT>()
+    pub fn update_write_txn(&self) -> heed::Result<QueuedTxn<'_, UpdateT>> {
+        self.write_queues.update_write_txn(&self.update_env)
+    }
+
+    pub async fn update_write_txn_async(&self) -> heed::Result<QueuedTxn<'_, UpdateT>> {
+        self.write_queues.update_write_txn_async(&self.update_env).await
+    }
+
+    /// The waits for the write transactions of each environment.
+    pub fn write_queues_stats(&self) -> WriteQueuesStats {
+        self.write_queues.stats()
+    }
+
+    /// Encrypts bytes stored outside of the environments with the key of the database,
+    /// they are returned as is if the database is not encrypted. The associated data
+    /// must be given back to `open_sealed` to decrypt them.
+    pub fn seal(&self, associated_data: &[u8], bytes: Vec<u8>) -> heed::Result<Vec<u8>> {
+        match self.cipher {
+            Some(cipher) => cipher.encrypt(associated_data, &bytes).ok_or(heed::Error::Encoding),
+            None => Ok(bytes),
+        }
+    }
+
+    /// Decrypts bytes encrypted by `seal` with the same associated data.
+    pub fn open_sealed(&self, associated_data: &[u8], bytes: Vec<u8>) -> heed::Result<Vec<u8>> {
+        match self.cipher {
+            Some(cipher) => cipher.decrypt(associated_data, &bytes).ok_or(heed::Error::Decoding),
+            None => Ok(bytes),
+        }
+    }
+
+    /// Applies updates to several indexes at once, they become visible together or not at all.
+    ///
+    /// The updates are not enqueued but applied right away, in a single main transaction.
+    /// Nothing is applied if one of the indexes has enqueued updates, which would otherwise
+    /// be applied after the group although they were sent before it.
+    pub fn apply_update_group(&self, group: Vec<(String, update::UpdateData)>) -> MResult<UpdateGroupOutcome> {
+        let mut indexes = Vec::with_capacity(group.len());
+        let mut updates = Vec::with_capacity(group.len());
+        for (index_uid, data) in group {
+            match self.open_index(&index_uid) {
+                Some(index) => indexes.push((index_uid, index)),
+                None => return Ok(UpdateGroupOutcome::IndexNotFound(index_uid)),
+            }
+            updates.push(data);
+        }
+
+        // the main transaction is taken before the update one, like the update loops do,
+        // the update loops cannot start processing another update while they are kept
+        // and the updates being processed stay in the updates store until they are done
+        let mut writer = self.main_write_txn()?;
+        let mut update_writer = self.update_write_txn()?;
+        for (index_uid, index) in &indexes {
+            if index.updates.first_update(&update_writer)?.is_some() {
+                return Ok(UpdateGroupOutcome::PendingUpdates(index_uid.clone()));
+            }
+        }
+
+        let group = indexes.iter().map(|(_, index)| index).zip(updates).collect();
+        let statuses = update::apply_update_group(&mut writer, &mut update_writer, group)?;
+
+        if statuses.iter().all(|status| status.error.is_none()) {
+            writer.commit()?;
+        } else {
+            writer.abort();
+        }
+        update_writer.commit()?;
+
+        let results: Vec<_> = indexes.into_iter().map(|(uid, _)| uid).zip(statuses).collect();
+        if let Some(ref callback) = *self.update_fn.load() {
+            for (index_uid, status) in &results {
+                (callback)(index_uid, status.clone());
+            }
+        }
+
+        Ok(UpdateGroupOutcome::Processed(results))
     }
 
     pub fn copy_and_compact_to_path<P: AsRef<Path>>(&self, path: P) -> ZResult<(File, File)> {
@@ -362,9 +807,36 @@ impl Database {
         indexes.keys().cloned().collect()
     }
 
+    /// Returns the uids of the indexes whose update loop is not running anymore,
+    /// their updates are not processed until the database is reopened.
+    pub fn stopped_update_loops(&self) -> Vec<String> {
+        let indexes = self.indexes.read().unwrap();
+        indexes
+            .iter()
+            .filter(|(_, (_, _, update_loop))| !update_loop.running.load(Ordering::SeqCst))
+            .map(|(uid, _)| uid.clone())
+            .collect()
+    }
+
     pub fn common_store(&self) -> heed::PolyDatabase {
         self.common_store
     }
+
+    /// A store of the update environment, its entries can be written in the
+    /// same transaction as the updates they are related to.
+    pub fn update_common_store(&self) -> heed::PolyDatabase {
+        self.update_common_store
+    }
+}
+
+/// Identifies a version of an artifact by the size and the modification date of its data file.
+fn artifact_identity(path: &Path) -> MResult<String> {
+    let metadata = fs::metadata(path.join("data.mdb"))?;
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |modified| modified.as_nanos());
+    Ok(format!("{}-{}", metadata.len(), modified))
 }
 
 #[cfg(test)]
@@ -374,7 +846,7 @@ mod tests {
     use crate::bucket_sort::SortResult;
     use crate::criterion::{self, CriteriaBuilder};
     use crate::update::{ProcessedUpdateResult, UpdateStatus};
-    use crate::settings::Settings;
+    use crate::settings::{Settings, SettingsUpdate};
     use crate::{Document, DocumentId};
     use serde::de::IgnoredAny;
     use std::sync::mpsc;
@@ -783,6 +1255,13 @@ mod tests {
             .document(&reader, None, DocumentId(8_367_468_610_878_465_872))
             .unwrap();
         assert!(document.is_some());
+
+        // only the requested fields are read
+        let attributes: HashSet<&str> = ["name", "unknown"].iter().copied().collect();
+        let document: Option<serde_json::Value> = index
+            .document(&reader, Some(&attributes), DocumentId(7_900_334_843_754_999_545))
+            .unwrap();
+        assert_eq!(document, Some(serde_json::json!({ "name": "Marvin" })));
     }
 
     #[test]
@@ -1093,4 +1572,356 @@ mod tests {
         );
         assert_matches!(iter.next(), None);
     }
+
+    #[test]
+    fn consecutive_settings_updates() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let db = &database;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let mut writer = db.main_write_txn().unwrap();
+        index.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        writer.commit().unwrap();
+
+        let updates = [
+            r#"{ "searchableAttributes": ["name", "description"] }"#,
+            r#"{ "stopWords": ["the"] }"#,
+            r#"{ "displayedAttributes": ["name"] }"#,
+        ];
+
+        // enqueue the updates in the same transaction for them to be processed together
+        let mut update_writer = db.update_write_txn().unwrap();
+        let mut update_ids = Vec::new();
+        for data in updates.iter() {
+            let settings: Settings = serde_json::from_str(data).unwrap();
+            let update_id = index.settings_update(&mut update_writer, settings.into_update().unwrap()).unwrap();
+            update_ids.push(update_id);
+        }
+        update_writer.commit().unwrap();
+
+        // block until the transactions are processed
+        let last_update_id = *update_ids.last().unwrap();
+        let _ = receiver.into_iter().find(|id| *id == last_update_id);
+
+        let update_reader = db.update_read_txn().unwrap();
+        let mut processed_at = Vec::new();
+        for update_id in update_ids {
+            let result = index.update_status(&update_reader, update_id).unwrap();
+            match result {
+                Some(UpdateStatus::Processed { content }) => {
+                    assert!(content.error.is_none());
+                    processed_at.push(content.processed_at);
+                }
+                other => panic!("unexpected update status {:?}", other),
+            }
+        }
+        processed_at.dedup();
+        assert_eq!(processed_at.len(), 1);
+
+        let reader = db.main_read_txn().unwrap();
+        let stop_words = index.main.stop_words_fst(&reader).unwrap().unwrap();
+        assert!(stop_words.contains("the"));
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        let displayed: HashSet<_> = std::iter::once("name").collect();
+        assert_eq!(schema.displayed_name(), displayed);
+    }
+
+    #[test]
+    fn close_keeps_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        database.create_index("test").unwrap();
+        database.close().unwrap();
+        assert!(database.open_index("test").is_none());
+        drop(database);
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        assert!(database.open_index("test").is_some());
+    }
+
+    #[test]
+    fn stopped_update_loops() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let index = database.create_index("test").unwrap();
+        database.create_index("other").unwrap();
+        assert!(database.stopped_update_loops().is_empty());
+
+        index.updates_notifier.send(UpdateEvent::MustStop).unwrap();
+        for _ in 0..100 {
+            if !database.stopped_update_loops().is_empty() {
+                break;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(database.stopped_update_loops(), vec!["test".to_string()]);
+    }
+
+    #[test]
+    fn rename_index() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let index = database.create_index("test").unwrap();
+        database.create_index("other").unwrap();
+
+        let mut writer = database.main_write_txn().unwrap();
+        index.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        index.main.put_name(&mut writer, "test").unwrap();
+        writer.commit().unwrap();
+
+        assert_matches!(database.rename_index("test", "other"), Err(crate::Error::IndexAlreadyExists));
+        let failed = database.rename_index_with("test", "renamed", |_| Err(crate::Error::MissingPrimaryKey));
+        assert_matches!(failed, Err(crate::Error::MissingPrimaryKey));
+        assert!(database.open_index("renamed").is_none());
+        assert!(!database.rename_index("unknown", "renamed").unwrap());
+        assert!(database.rename_index("test", "renamed").unwrap());
+        assert!(database.open_index("test").is_none());
+
+        // the stores of the renamed index are not reused
+        let index = database.create_index("test").unwrap();
+        let reader = database.main_read_txn().unwrap();
+        assert_eq!(index.main.schema(&reader).unwrap().unwrap().primary_key(), None);
+        reader.abort();
+
+        database.close().unwrap();
+        drop(database);
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let index = database.open_index("renamed").unwrap();
+        let reader = database.main_read_txn().unwrap();
+        let schema = index.main.schema(&reader).unwrap().unwrap();
+        assert_eq!(schema.primary_key(), Some("id"));
+        assert_eq!(index.main.name(&reader).unwrap().as_deref(), Some("renamed"));
+    }
+
+    #[test]
+    fn mount_index() {
+        let artifact_dir = tempfile::tempdir().unwrap();
+        let artifact = Database::open_or_create(artifact_dir.path(), DatabaseOptions::default()).unwrap();
+        let index = artifact.create_index("movies").unwrap();
+        let mut writer = artifact.main_write_txn().unwrap();
+        index.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        writer.commit().unwrap();
+        artifact.close().unwrap();
+        drop(artifact);
+
+        let dir = tempfile::tempdir().unwrap();
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        assert!(database.mount_index("series", artifact_dir.path()).unwrap().is_none());
+
+        // a local index is never replaced by a mounted one
+        database.create_index("movies").unwrap();
+        assert_matches!(database.mount_index("movies", artifact_dir.path()), Err(crate::Error::IndexAlreadyExists));
+        database.delete_index("movies").unwrap();
+
+        let index = database.mount_index("movies", artifact_dir.path()).unwrap().unwrap();
+        let reader = database.main_read_txn().unwrap();
+        assert_eq!(index.main.schema(&reader).unwrap().unwrap().primary_key(), Some("id"));
+        assert!(index.main.mounted_artifact(&reader).unwrap().is_some());
+        reader.abort();
+
+        // the copy of the same version of the artifact is kept
+        assert!(database.mount_index("movies", artifact_dir.path()).unwrap().is_some());
+    }
+
+    #[test]
+    fn distinct_values_are_stored_per_document() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let index = database.create_index("test").unwrap();
+
+        let documents = serde_json::from_value(serde_json::json!([
+            { "id": 1, "brand": "acme" },
+            { "id": 2, "brand": "acme" },
+            { "id": 3 },
+        ])).unwrap();
+        let mut writer = database.main_write_txn().unwrap();
+        index.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        index.main.put_distinct_attribute(&mut writer, "brand").unwrap();
+        update::apply_documents_addition(&mut writer, &index, documents).unwrap();
+
+        let id = |id: &str| update::compute_document_id(id).unwrap();
+        let acme = index.distinct_values.distinct_value(&writer, id("1")).unwrap();
+        assert!(acme.is_some());
+        assert_eq!(index.distinct_values.distinct_value(&writer, id("2")).unwrap(), acme);
+        assert_eq!(index.distinct_values.distinct_value(&writer, id("3")).unwrap(), None);
+
+        update::apply_documents_deletion(&mut writer, &index, vec![id("1")]).unwrap();
+        assert_eq!(index.distinct_values.distinct_value(&writer, id("1")).unwrap(), None);
+
+        // the values of the indexes of older databases are computed from the documents
+        index.distinct_values.clear(&mut writer).unwrap();
+        update::compute_distinct_values(&mut writer, &index).unwrap();
+        assert_eq!(index.distinct_values.distinct_value(&writer, id("2")).unwrap(), acme);
+        assert_eq!(index.distinct_values.distinct_value(&writer, id("1")).unwrap(), None);
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    fn documents_additions_fail_over_the_quota() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let options = DatabaseOptions { index_max_data_size: Some(100), ..DatabaseOptions::default() };
+        let database = Database::open_or_create(dir.path(), options).unwrap();
+        let index = database.create_index("test").unwrap();
+
+        let documents = || serde_json::from_value(serde_json::json!([{ "id": 1 }])).unwrap();
+        let mut writer = database.main_write_txn().unwrap();
+        index.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        update::apply_documents_addition(&mut writer, &index, documents()).unwrap();
+
+        index.main.put_data_size(&mut writer, 100).unwrap();
+        let result = update::apply_documents_addition(&mut writer, &index, documents());
+        assert_matches!(result, Err(crate::Error::QuotaExceeded { max_data_size: 100 }));
+
+        // the quota of the index replaces the one of the database
+        index.main.put_max_data_size(&mut writer, 200).unwrap();
+        update::apply_documents_addition(&mut writer, &index, documents()).unwrap();
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    fn facet_values_are_displayed_like_in_the_documents() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let index = database.create_index("test").unwrap();
+
+        let mut schema = Schema::with_primary_key("id");
+        let genre = schema.insert_and_index("genre").unwrap();
+        let documents = serde_json::from_value(serde_json::json!([
+            { "id": 1, "genre": "Sci-Fi" },
+            { "id": 2, "genre": "sci-fi" },
+        ])).unwrap();
+        let mut writer = database.main_write_txn().unwrap();
+        index.main.put_schema(&mut writer, &schema).unwrap();
+        index.main.put_attributes_for_faceting(&mut writer, sdset::Set::new_unchecked(&[genre])).unwrap();
+        update::apply_documents_addition(&mut writer, &index, documents).unwrap();
+
+        let key = crate::facets::FacetKey::new(genre, "SCI-FI".to_string());
+        assert_eq!(index.facets.facet_document_ids(&writer, &key).unwrap().unwrap().len(), 2);
+        assert_eq!(index.facets.display_value(&writer, &key).unwrap(), Some("Sci-Fi"));
+
+        // the facets of the indexes of older databases are indexed again from the documents
+        index.facets.clear(&mut writer).unwrap();
+        update::compute_facets(&mut writer, &index).unwrap();
+        assert_eq!(index.facets.facet_document_ids(&writer, &key).unwrap().unwrap().len(), 2);
+        assert!(index.facets.display_value(&writer, &key).unwrap().is_some());
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    fn update_groups_are_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let products = database.create_index("products").unwrap();
+        let offers = database.create_index("offers").unwrap();
+
+        let mut writer = database.main_write_txn().unwrap();
+        products.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        offers.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        writer.commit().unwrap();
+
+        let documents = |value: serde_json::Value| serde_json::from_value(value).unwrap();
+        let group = vec![
+            ("products".to_string(), update::UpdateData::DocumentsAddition(documents(serde_json::json!([{ "id": 1 }])))),
+            ("offers".to_string(), update::UpdateData::DocumentsAddition(documents(serde_json::json!([{ "id": 1 }, { "id": 2 }])))),
+        ];
+        match database.apply_update_group(group).unwrap() {
+            UpdateGroupOutcome::Processed(results) => assert!(results.iter().all(|(_, status)| status.error.is_none())),
+            _ => panic!("the group must have been applied"),
+        }
+
+        // the second update has no document id, the first one is rolled back
+        let group = vec![
+            ("products".to_string(), update::UpdateData::DocumentsAddition(documents(serde_json::json!([{ "id": 2 }])))),
+            ("offers".to_string(), update::UpdateData::DocumentsAddition(documents(serde_json::json!([{ "name": "free" }])))),
+        ];
+        let results = match database.apply_update_group(group).unwrap() {
+            UpdateGroupOutcome::Processed(results) => results,
+            _ => panic!("the group must have been processed"),
+        };
+        assert!(results.iter().all(|(_, status)| status.error.is_some()));
+
+        let reader = database.main_read_txn().unwrap();
+        assert_eq!(products.main.number_of_documents(&reader).unwrap(), 1);
+        assert_eq!(offers.main.number_of_documents(&reader).unwrap(), 2);
+
+        let update_reader = database.update_read_txn().unwrap();
+        assert_eq!(results[0].0, "products");
+        let status = products.update_status(&update_reader, results[0].1.update_id).unwrap();
+        assert_matches!(status, Some(UpdateStatus::Failed { .. }));
+
+        let group = vec![("unknown".to_string(), update::UpdateData::ClearAll)];
+        assert_matches!(database.apply_update_group(group), Ok(UpdateGroupOutcome::IndexNotFound(_)));
+    }
+
+    #[test]
+    fn priority_updates_jump_ahead() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let index = database.create_index("test").unwrap();
+        let mut writer = database.main_write_txn().unwrap();
+        index.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        writer.commit().unwrap();
+
+        let addition = |writer: &mut heed::RwTxn<UpdateT>, mut addition: update::DocumentsAddition<_>, id| {
+            addition.update_document(serde_json::json!({ "id": id }));
+            addition.finalize(writer).unwrap()
+        };
+
+        // the updates are never committed, the update loop therefore never processes them
+        let main_reader = database.main_read_txn().unwrap();
+        let mut writer = database.update_write_txn().unwrap();
+        let bulk_id = addition(&mut writer, index.documents_addition(), 1);
+        addition(&mut writer, index.documents_addition(), 2);
+        let second_id = addition(&mut writer, index.documents_partial_addition(), 3);
+        index.prioritize_update(&mut writer, second_id).unwrap();
+
+        let mut streak = 0;
+        let updates = update::next_updates(&main_reader, &writer, &index, &mut streak).unwrap();
+        let ids: Vec<_> = updates.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![second_id]);
+        assert_eq!(streak, 1);
+
+        // the oldest update is processed once too many priority updates have been processed
+        let mut streak = update::MAX_PRIORITY_STREAK;
+        let updates = update::next_updates(&main_reader, &writer, &index, &mut streak).unwrap();
+        let ids: Vec<_> = updates.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![bulk_id]);
+        assert_eq!(streak, 0);
+
+        // an update of a document changed by an older update waits for it
+        let dependent_id = addition(&mut writer, index.documents_partial_addition(), 2);
+        index.updates_priorities.del_priority(&mut writer, second_id).unwrap();
+        index.prioritize_update(&mut writer, dependent_id).unwrap();
+        let mut streak = 0;
+        let updates = update::next_updates(&main_reader, &writer, &index, &mut streak).unwrap();
+        assert_eq!(updates[0].0, bulk_id);
+        assert_eq!(streak, 0);
+
+        // as well as an update that may change any document
+        let settings_id = index.settings_update(&mut writer, SettingsUpdate::default()).unwrap();
+        index.updates_priorities.del_priority(&mut writer, dependent_id).unwrap();
+        index.prioritize_update(&mut writer, settings_id).unwrap();
+        let updates = update::next_updates(&main_reader, &writer, &index, &mut streak).unwrap();
+        assert_eq!(updates[0].0, bulk_id);
+
+        writer.abort();
+    }
 }
@@ -0,0 +1,115 @@
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The length of the keys encrypting the stored values.
+pub const ENCRYPTION_KEY_LEN: usize = 32;
+
+/// Encrypts the values written to disk with ChaCha20-Poly1305, a random nonce is
+/// generated for every value and stored in front of it.
+///
+/// The associated data, e.g. the key of the value in its store, must be given back
+/// to decrypt the value, a value moved under another key cannot be decrypted.
+pub struct Cipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl Cipher {
+    pub fn new(key: &[u8; ENCRYPTION_KEY_LEN]) -> Cipher {
+        // the key has the length of the ChaCha20-Poly1305 keys, it cannot be refused
+        let key = UnboundKey::new(&CHACHA20_POLY1305, key).unwrap();
+        Cipher { key: LessSafeKey::new(key), rng: SystemRandom::new() }
+    }
+
+    pub fn encrypt(&self, associated_data: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng.fill(&mut nonce).ok()?;
+
+        let mut in_out = value.to_vec();
+        let aad = Aad::from(associated_data);
+        self.key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), aad, &mut in_out).ok()?;
+
+        let mut encrypted = Vec::with_capacity(NONCE_LEN + in_out.len());
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&in_out);
+        Some(encrypted)
+    }
+
+    /// Returns `None` if the value has been tampered with or encrypted with another key.
+    pub fn decrypt(&self, associated_data: &[u8], encrypted: &[u8]) -> Option<Vec<u8>> {
+        if encrypted.len() < NONCE_LEN {
+            return None;
+        }
+
+        let (nonce, value) = encrypted.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut in_out = value.to_vec();
+        let aad = Aad::from(associated_data);
+        let len = self.key.open_in_place(nonce, aad, &mut in_out).ok()?.len();
+
+        in_out.truncate(len);
+        Some(in_out)
+    }
+}
+
+/// Serializes a value in JSON, encrypted with its key if the database is encrypted.
+pub(crate) fn seal_json<T: Serialize>(cipher: Option<&Cipher>, key: &[u8], value: &T) -> heed::Result<Vec<u8>> {
+    let bytes = serde_json::to_vec(value).map_err(|_| heed::Error::Encoding)?;
+    match cipher {
+        Some(cipher) => cipher.encrypt(key, &bytes).ok_or(heed::Error::Encoding),
+        None => Ok(bytes),
+    }
+}
+
+/// Reads back a value written by `seal_json` under the same key.
+pub(crate) fn open_json<T: DeserializeOwned>(cipher: Option<&Cipher>, key: &[u8], bytes: &[u8]) -> heed::Result<T> {
+    let result = match cipher {
+        Some(cipher) => {
+            let bytes = cipher.decrypt(key, bytes).ok_or(heed::Error::Decoding)?;
+            serde_json::from_slice(&bytes)
+        }
+        None => serde_json::from_slice(bytes),
+    };
+    result.map_err(|_| heed::Error::Decoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypted_values_round_trip() {
+        let cipher = Cipher::new(&[7; ENCRYPTION_KEY_LEN]);
+        let value = br#"{"title":"Carol"}"#;
+
+        let encrypted = cipher.encrypt(b"key", value).unwrap();
+        assert!(!encrypted.windows(value.len()).any(|window| window == value));
+        assert_ne!(encrypted, cipher.encrypt(b"key", value).unwrap());
+        assert_eq!(cipher.decrypt(b"key", &encrypted).unwrap(), value);
+
+        assert_eq!(cipher.decrypt(b"other key", &encrypted), None);
+        assert_eq!(Cipher::new(&[8; ENCRYPTION_KEY_LEN]).decrypt(b"key", &encrypted), None);
+
+        let mut tampered = encrypted;
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(cipher.decrypt(b"key", &tampered), None);
+    }
+
+    #[test]
+    fn sealed_json_values_round_trip() {
+        let cipher = Cipher::new(&[7; ENCRYPTION_KEY_LEN]);
+        let value = serde_json::json!({ "title": "Carol" });
+
+        let sealed = seal_json(Some(&cipher), b"key", &value).unwrap();
+        assert!(!sealed.windows(5).any(|window| window == b"Carol"));
+        assert_eq!(open_json::<serde_json::Value>(Some(&cipher), b"key", &sealed).unwrap(), value);
+        assert!(open_json::<serde_json::Value>(Some(&cipher), b"other key", &sealed).is_err());
+
+        // the values of the databases that are not encrypted are plain JSON
+        let plain = seal_json(None, b"key", &value).unwrap();
+        assert_eq!(plain, serde_json::to_vec(&value).unwrap());
+        assert_eq!(open_json::<serde_json::Value>(None, b"key", &plain).unwrap(), value);
+    }
+}
@@ -2,6 +2,7 @@ use crate::serde::{DeserializerError, SerializerError};
 use serde_json::Error as SerdeJsonError;
 use pest::error::Error as PestError;
 use crate::filters::Rule;
+use crate::settings::FieldType;
 use std::{error, fmt, io};
 
 pub use bincode::Error as BincodeError;
@@ -30,6 +31,12 @@ pub enum Error {
     UnsupportedOperation(UnsupportedOperation),
     FilterParseError(PestError<Rule>),
     FacetError(FacetError),
+    Cancelled,
+    EncryptionKeyMismatch,
+    InvalidFieldType { document_id: String, attribute: String, expected: FieldType },
+    InvalidFieldOperation { document_id: String, attribute: String, reason: String },
+    InvalidAttributeAlias { alias: String, reason: String },
+    QuotaExceeded { max_data_size: u64 },
 }
 
 impl From<io::Error> for Error {
@@ -135,6 +142,22 @@ impl fmt::Display for Error {
             UnsupportedOperation(op) => write!(f, "unsupported operation; {}", op),
             FilterParseError(e) => write!(f, "error parsing filter; {}", e),
             FacetError(e) => write!(f, "error processing facet filter: {}", e),
+            Cancelled => write!(f, "the search has been cancelled"),
+            EncryptionKeyMismatch => write!(f, "the encryption key is not the one the database has been written with"),
+            InvalidFieldType { document_id, attribute, expected } => write!(
+                f,
+                "the attribute {:?} of the document {} must be of type {}",
+                attribute, document_id, expected.name(),
+            ),
+            InvalidFieldOperation { document_id, attribute, reason } => write!(
+                f,
+                "invalid operation on the attribute {:?} of the document {}: {}",
+                attribute, document_id, reason,
+            ),
+            InvalidAttributeAlias { alias, reason } => write!(f, "invalid attribute alias {:?}: {}", alias, reason),
+            QuotaExceeded { max_data_size } => {
+                write!(f, "the index exceeded its data size quota of {} bytes", max_data_size)
+            }
         }
     }
 }
@@ -197,7 +220,7 @@ impl fmt::Display for FacetError {
             InvalidFormat(found) => write!(f, "invalid facet: {}, facets should be \"facetName:facetValue\"", found),
             AttributeNotFound(attr) => write!(f, "unknown {:?} attribute", attr),
             AttributeNotSet { found, expected } => write!(f, "`{}` is not set as a faceted attribute. available facet attributes: {}", found, expected.join(", ")),
-            InvalidDocumentAttribute(attr) => write!(f, "invalid document attribute {}, accepted types: String and [String]", attr),
+            InvalidDocumentAttribute(attr) => write!(f, "invalid document attribute {}, accepted types: String, Boolean, geographic point, [String] and [Boolean]", attr),
         }
     }
 }
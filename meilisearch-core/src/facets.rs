@@ -1,20 +1,26 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
 use std::ops::Deref;
 
 use cow_utils::CowUtils;
+use deunicode::deunicode_with_tofu;
 use either::Either;
 use heed::types::{Str, OwnedType};
 use indexmap::IndexMap;
+use sdset::SetBuf;
 use serde_json::Value;
 
 use meilisearch_schema::{FieldId, Schema};
+use meilisearch_tokenizer::is_cjk;
 use meilisearch_types::DocumentId;
 
 use crate::database::MainT;
 use crate::error::{FacetError, Error};
+use crate::geohash::{self, GEOHASH_MAX_PRECISION};
+use crate::settings::resolve_attribute_alias;
 use crate::store::BEU16;
+use crate::MResult;
 
 /// Data structure used to represent a boolean expression in the form of nested arrays.
 /// Values in the outer array are and-ed together, values in the inner arrays are or-ed together.
@@ -35,6 +41,16 @@ impl FacetFilter {
         schema: &Schema,
         attributes_for_faceting: &[FieldId],
     ) -> Result<Self, FacetError> {
+        Self::from_str_with_aliases(s, schema, attributes_for_faceting, &BTreeMap::new())
+    }
+
+    /// Parses the filter where the attributes can also be named by their aliases.
+    pub fn from_str_with_aliases(
+        s: &str,
+        schema: &Schema,
+        attributes_for_faceting: &[FieldId],
+        aliases: &BTreeMap<String, String>,
+    ) -> Result<Self, FacetError> {
 
         let parsed = serde_json::from_str::<Value>(s).map_err(|e| FacetError::ParsingError(e.to_string()))?;
         let mut filter = Vec::new();
@@ -46,7 +62,7 @@ impl FacetFilter {
                 for expr in and_exprs {
                     match expr {
                         Value::String(s) => {
-                            let key = FacetKey::from_str( &s, schema, attributes_for_faceting)?;
+                            let key = FacetKey::from_str(&s, schema, attributes_for_faceting, aliases)?;
                             filter.push(Either::Right(key));
                         }
                         Value::Array(or_exprs) => {
@@ -57,7 +73,7 @@ impl FacetFilter {
                             for expr in or_exprs {
                                 match expr {
                                     Value::String(s) => {
-                                        let key = FacetKey::from_str( &s, schema, attributes_for_faceting)?;
+                                        let key = FacetKey::from_str(&s, schema, attributes_for_faceting, aliases)?;
                                         inner.push(key);
                                     }
                                     bad_value => return Err(FacetError::unexpected_token(&["String"], bad_value)),
@@ -73,21 +89,61 @@ impl FacetFilter {
             bad_value => Err(FacetError::unexpected_token(&["Array"], bad_value)),
         }
     }
+
+    /// The ids of the documents matching the filter, `None` when a facet value doesn't exist.
+    pub fn documents_ids(&self, reader: &heed::RoTxn<MainT>, index: &crate::Index) -> MResult<Option<SetBuf<DocumentId>>> {
+        crate::query_builder::facet_filter_docids(reader, index, self.iter())
+    }
 }
 
+/// The geohash cells are stored with the values of the attributes, starting with this
+/// character that is removed from the values of the documents and of the filters.
+const GEOHASH_CELL_PREFIX: char = '\u{0}';
+
 #[derive(Debug, Eq, PartialEq, Hash)]
 #[repr(C)]
 pub struct FacetKey(FieldId, String);
 
+/// Normalizes a facet value so that it matches regardless of its case and diacritics,
+/// "Sci-Fi" and "Électro" are stored and searched as "sci-fi" and "electro".
+pub fn normalize_facet_value(value: &str) -> Cow<str> {
+    let lower = value.cow_to_lowercase();
+    if lower.is_ascii() || lower.contains(is_cjk) {
+        return lower;
+    }
+
+    let unidecoded = deunicode_with_tofu(&lower, "");
+    if unidecoded.is_empty() {
+        lower
+    } else {
+        Cow::Owned(unidecoded)
+    }
+}
+
 impl FacetKey {
-    pub fn new(field_id: FieldId, value: String) -> Self {
-        let value = match value.cow_to_lowercase() {
+    pub fn new(field_id: FieldId, mut value: String) -> Self {
+        value.retain(|c| c != GEOHASH_CELL_PREFIX);
+        let value = match normalize_facet_value(&value) {
                 Cow::Borrowed(_) => value,
                 Cow::Owned(s) => s,
         };
         Self(field_id, value)
     }
 
+    /// The key of a geohash cell of the geographic points of the attribute.
+    pub fn geohash_cell(field_id: FieldId, cell: &str) -> Self {
+        Self(field_id, format!("{}{}", GEOHASH_CELL_PREFIX, cell))
+    }
+
+    /// The geohash cell of the key, `None` when it is a value of the documents.
+    pub fn as_geohash_cell(&self) -> Option<&str> {
+        if self.1.starts_with(GEOHASH_CELL_PREFIX) {
+            Some(&self.1[GEOHASH_CELL_PREFIX.len_utf8()..])
+        } else {
+            None
+        }
+    }
+
     pub fn key(&self) -> FieldId {
         self.0
     }
@@ -101,6 +157,7 @@ impl FacetKey {
         s: &str,
         schema: &Schema,
         attributes_for_faceting: &[FieldId],
+        aliases: &BTreeMap<String, String>,
     ) -> Result<Self, FacetError> {
         let mut split = s.splitn(2, ':');
         let key = split
@@ -108,7 +165,7 @@ impl FacetKey {
             .ok_or_else(|| FacetError::InvalidFormat(s.to_string()))?
             .trim();
         let field_id = schema
-            .id(key)
+            .id(resolve_attribute_alias(aliases, key))
             .ok_or_else(|| FacetError::AttributeNotFound(key.to_string()))?;
 
         if !attributes_for_faceting.contains(&field_id) {
@@ -124,6 +181,10 @@ impl FacetKey {
             .next()
             .ok_or_else(|| FacetError::InvalidFormat(s.to_string()))?
             .trim();
+        // the geohash cells are filtered with `geohash(u09)`
+        if value.starts_with("geohash(") && value.ends_with(')') {
+            return Ok(Self::geohash_cell(field_id, &value["geohash(".len()..value.len() - 1]));
+        }
         // unquoting the string if need be:
         let mut indices = value.char_indices();
         let value =  match (indices.next(), indices.last()) {
@@ -161,29 +222,66 @@ impl<'a> heed::BytesDecode<'a> for FacetKey {
     }
 }
 
+/// The documents of every facet value, with the value like it is written in the first
+/// document found, to be displayed in the facets counts.
+pub type FacetMap = HashMap<FacetKey, (String, Vec<DocumentId>)>;
+
 pub fn add_to_facet_map(
-    facet_map: &mut HashMap<FacetKey, Vec<DocumentId>>,
+    facet_map: &mut FacetMap,
     field_id: FieldId,
     value: Value,
     document_id: DocumentId,
 ) -> Result<(), FacetError> {
     let value = match value {
         Value::String(s) => s,
+        // booleans are faceted as the "true" and "false" strings
+        Value::Bool(b) => b.to_string(),
+        // geographic points are faceted as the geohash cells containing them
+        Value::Object(ref object) => match geo_point(object) {
+            Some((lat, lng)) => {
+                for precision in 1..=GEOHASH_MAX_PRECISION {
+                    let cell = geohash::encode(lat, lng, precision);
+                    let key = FacetKey::geohash_cell(field_id, &cell);
+                    facet_map.entry(key).or_insert_with(|| (cell, Vec::new())).1.push(document_id);
+                }
+                return Ok(());
+            }
+            None => return Err(FacetError::InvalidDocumentAttribute(value.to_string())),
+        },
         // ignore null
         Value::Null => return Ok(()),
         value => return Err(FacetError::InvalidDocumentAttribute(value.to_string())),
     };
-    let key = FacetKey::new(field_id, value);
-    facet_map.entry(key).or_insert_with(Vec::new).push(document_id);
+    let key = FacetKey::new(field_id, value.clone());
+    facet_map.entry(key).or_insert_with(|| (value, Vec::new())).1.push(document_id);
     Ok(())
 }
 
+/// Reads a geographic point written as `{ "lat": 48.8566, "lng": 2.3522 }`,
+/// the coordinates can also be written as strings.
+pub(crate) fn geo_point(object: &serde_json::Map<String, Value>) -> Option<(f64, f64)> {
+    let coordinate = |name: &str| match object.get(name)? {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    };
+
+    let lat = coordinate("lat")?;
+    let lng = coordinate("lng")?;
+
+    if object.len() == 2 && lat >= -90.0 && lat <= 90.0 && lng >= -180.0 && lng <= 180.0 {
+        Some((lat, lng))
+    } else {
+        None
+    }
+}
+
 pub fn facet_map_from_docids(
     rtxn: &heed::RoTxn<MainT>,
     index: &crate::Index,
     document_ids: &[DocumentId],
     attributes_for_facetting: &[FieldId],
-) -> Result<HashMap<FacetKey, Vec<DocumentId>>, Error> {
+) -> Result<FacetMap, Error> {
     let mut facet_map = HashMap::new();
     for document_id in document_ids {
         for result in index
@@ -192,7 +290,7 @@ pub fn facet_map_from_docids(
         {
             let (field_id, bytes) = result?;
             if attributes_for_facetting.contains(&field_id) {
-                match serde_json::from_slice(bytes)? {
+                match serde_json::from_slice(&bytes)? {
                     Value::Array(values) => {
                         for v in values {
                             add_to_facet_map(&mut facet_map, field_id, v, *document_id)?;
@@ -210,7 +308,7 @@ pub fn facet_map_from_docs(
     schema: &Schema,
     documents: &HashMap<DocumentId, IndexMap<String, Value>>,
     attributes_for_facetting: &[FieldId],
-) -> Result<HashMap<FacetKey, Vec<DocumentId>>, Error> {
+) -> Result<FacetMap, Error> {
     let mut facet_map = HashMap::new();
     let attributes_for_facetting = attributes_for_facetting
         .iter()
@@ -245,38 +343,57 @@ mod test {
         let id = schema.insert_and_index("hello").unwrap();
         let facet_list = [schema.id("hello").unwrap()];
         assert_eq!(
-            FacetKey::from_str("hello:12", &schema, &facet_list).unwrap(),
+            FacetKey::from_str("hello:12", &schema, &facet_list, &BTreeMap::new()).unwrap(),
             FacetKey::new(id, "12".to_string())
         );
         assert_eq!(
-            FacetKey::from_str("hello:\"foo bar\"", &schema, &facet_list).unwrap(),
+            FacetKey::from_str("hello:\"foo bar\"", &schema, &facet_list, &BTreeMap::new()).unwrap(),
             FacetKey::new(id, "foo bar".to_string())
         );
         assert_eq!(
-            FacetKey::from_str("hello:'foo bar'", &schema, &facet_list).unwrap(),
+            FacetKey::from_str("hello:'foo bar'", &schema, &facet_list, &BTreeMap::new()).unwrap(),
             FacetKey::new(id, "foo bar".to_string())
         );
         // weird case
         assert_eq!(
-            FacetKey::from_str("hello:blabla:machin", &schema, &facet_list).unwrap(),
+            FacetKey::from_str("hello:blabla:machin", &schema, &facet_list, &BTreeMap::new()).unwrap(),
             FacetKey::new(id, "blabla:machin".to_string())
         );
 
         assert_eq!(
-            FacetKey::from_str("hello:\"\"", &schema, &facet_list).unwrap(),
+            FacetKey::from_str("hello:\"\"", &schema, &facet_list, &BTreeMap::new()).unwrap(),
             FacetKey::new(id, "".to_string())
         );
 
         assert_eq!(
-            FacetKey::from_str("hello:'", &schema, &facet_list).unwrap(),
+            FacetKey::from_str("hello:'", &schema, &facet_list, &BTreeMap::new()).unwrap(),
             FacetKey::new(id, "'".to_string())
         );
         assert_eq!(
-            FacetKey::from_str("hello:''", &schema, &facet_list).unwrap(),
+            FacetKey::from_str("hello:''", &schema, &facet_list, &BTreeMap::new()).unwrap(),
             FacetKey::new(id, "".to_string())
         );
-        assert!(FacetKey::from_str("hello", &schema, &facet_list).is_err());
-        assert!(FacetKey::from_str("toto:12", &schema, &facet_list).is_err());
+        assert!(FacetKey::from_str("hello", &schema, &facet_list, &BTreeMap::new()).is_err());
+        assert!(FacetKey::from_str("toto:12", &schema, &facet_list, &BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn facet_keys_resolve_the_aliases() {
+        let mut schema = Schema::new();
+        let id = schema.insert_and_index("writer").unwrap();
+        let facet_list = [id];
+        let mut aliases = BTreeMap::new();
+        aliases.insert("author".to_string(), "writer".to_string());
+
+        assert_eq!(
+            FacetKey::from_str("author:Tolkien", &schema, &facet_list, &aliases).unwrap(),
+            FacetKey::new(id, "tolkien".to_string())
+        );
+        assert_eq!(
+            FacetKey::from_str("writer:Tolkien", &schema, &facet_list, &aliases).unwrap(),
+            FacetKey::new(id, "tolkien".to_string())
+        );
+        assert!(FacetKey::from_str("author:Tolkien", &schema, &facet_list, &BTreeMap::new()).is_err());
     }
 
     #[test]
@@ -350,4 +467,50 @@ mod test {
         assert!(FacetFilter::from_str("[]", &schema, &facet_list).is_err());
         assert!(FacetFilter::from_str("[\"hello:12\", []]", &schema, &facet_list).is_err());
     }
+
+    #[test]
+    fn normalized_facet_values() {
+        let id = FieldId(0);
+        assert_eq!(FacetKey::new(id, "Sci-Fi".to_string()), FacetKey::new(id, "sci-fi".to_string()));
+        assert_eq!(FacetKey::new(id, "Électro".to_string()).value(), "electro");
+        assert_eq!(FacetKey::new(id, "東京".to_string()).value(), "東京");
+    }
+
+    #[test]
+    fn boolean_facet_values() {
+        let field_id = FieldId(0);
+        let mut facet_map = HashMap::new();
+        add_to_facet_map(&mut facet_map, field_id, Value::Bool(true), DocumentId(0)).unwrap();
+        add_to_facet_map(&mut facet_map, field_id, Value::Bool(false), DocumentId(1)).unwrap();
+        add_to_facet_map(&mut facet_map, field_id, Value::String("True".to_string()), DocumentId(2)).unwrap();
+
+        let (display, documents_ids) = &facet_map[&FacetKey::new(field_id, "true".to_string())];
+        assert_eq!((display.as_str(), documents_ids), ("true", &vec![DocumentId(0), DocumentId(2)]));
+        assert_eq!(facet_map[&FacetKey::new(field_id, "false".to_string())].1, vec![DocumentId(1)]);
+    }
+
+    #[test]
+    fn geohash_cells_are_not_facet_values() {
+        let field_id = FieldId(0);
+        let mut facet_map = HashMap::new();
+        let point = serde_json::json!({ "lat": 48.8566, "lng": 2.3522 });
+        add_to_facet_map(&mut facet_map, field_id, point, DocumentId(0)).unwrap();
+        add_to_facet_map(&mut facet_map, field_id, Value::String("u09".to_string()), DocumentId(1)).unwrap();
+        let forged = format!("{}u09", GEOHASH_CELL_PREFIX);
+        add_to_facet_map(&mut facet_map, field_id, Value::String(forged), DocumentId(2)).unwrap();
+
+        let cell = FacetKey::geohash_cell(field_id, "u09");
+        assert_eq!(cell.as_geohash_cell(), Some("u09"));
+        assert_eq!(facet_map[&cell].1, vec![DocumentId(0)]);
+        let value = FacetKey::new(field_id, "u09".to_string());
+        assert_eq!(value.as_geohash_cell(), None);
+        assert_eq!(facet_map[&value].1, vec![DocumentId(1), DocumentId(2)]);
+
+        let mut schema = Schema::new();
+        let id = schema.insert_and_index("_geo").unwrap();
+        let key = FacetKey::from_str("_geo:geohash(u09)", &schema, &[id], &BTreeMap::new()).unwrap();
+        assert_eq!(key, FacetKey::geohash_cell(id, "u09"));
+        let key = FacetKey::from_str("_geo:'geohash(u09)'", &schema, &[id], &BTreeMap::new()).unwrap();
+        assert_eq!(key.as_geohash_cell(), None);
+    }
 }
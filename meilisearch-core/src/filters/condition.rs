@@ -1,7 +1,10 @@
 use std::str::FromStr;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 
 use crate::error::Error;
+use crate::facets::normalize_facet_value;
+use crate::settings::resolve_attribute_alias;
 use crate::{store::Index, DocumentId, MainT};
 use heed::RoTxn;
 use meilisearch_schema::{FieldId, Schema};
@@ -64,15 +67,21 @@ impl<'a> ConditionValue<'a> {
 pub struct Condition<'a> {
     field: FieldId,
     condition: ConditionType,
-    value: ConditionValue<'a>
+    value: ConditionValue<'a>,
+    /// Whether `!=` matches the documents missing the attribute or where it is null.
+    pub(super) not_equal_matches_missing: bool,
 }
 
-fn get_field_value<'a>(schema: &Schema, pair: Pair<'a, Rule>) -> Result<(FieldId, ConditionValue<'a>), Error> {
+fn get_field_value<'a>(
+    schema: &Schema,
+    aliases: &BTreeMap<String, String>,
+    pair: Pair<'a, Rule>,
+) -> Result<(FieldId, ConditionValue<'a>), Error> {
     let mut items = pair.into_inner();
     // lexing ensures that we at least have a key
     let key = items.next().unwrap();
     let field = schema
-        .id(key.as_str())
+        .id(resolve_attribute_alias(aliases, key.as_str()))
         .ok_or::<PestError<Rule>>(PestError::new_from_span(
                 ErrorVariant::CustomError {
                     message: format!(
@@ -86,6 +95,11 @@ fn get_field_value<'a>(schema: &Schema, pair: Pair<'a, Rule>) -> Result<(FieldId
     Ok((field, value))
 }
 
+/// Compares the strings like the facet values, regardless of their case and diacritics.
+fn strings_eq(lhs: &str, rhs: &str) -> bool {
+    unicase::eq(lhs, rhs) || normalize_facet_value(lhs) == normalize_facet_value(rhs)
+}
+
 // undefined behavior with big numbers
 fn compare_numbers(lhs: &Number, rhs: &Number) -> Option<Ordering> {
     match (lhs.as_i64(), lhs.as_u64(), lhs.as_f64(),
@@ -102,55 +116,61 @@ impl<'a> Condition<'a> {
     pub fn less(
         item: Pair<'a, Rule>,
         schema: &'a Schema,
+        aliases: &BTreeMap<String, String>,
     ) -> Result<Self, Error> {
-        let (field, value) = get_field_value(schema, item)?;
+        let (field, value) = get_field_value(schema, aliases, item)?;
         let condition = ConditionType::Less;
-        Ok(Self { field, condition, value })
+        Ok(Self { field, condition, value, not_equal_matches_missing: true })
     }
 
     pub fn greater(
         item: Pair<'a, Rule>,
         schema: &'a Schema,
+        aliases: &BTreeMap<String, String>,
     ) -> Result<Self, Error> {
-        let (field, value) = get_field_value(schema, item)?;
+        let (field, value) = get_field_value(schema, aliases, item)?;
         let condition = ConditionType::Greater;
-        Ok(Self { field, condition, value })
+        Ok(Self { field, condition, value, not_equal_matches_missing: true })
     }
 
     pub fn neq(
         item: Pair<'a, Rule>,
         schema: &'a Schema,
+        aliases: &BTreeMap<String, String>,
     ) -> Result<Self, Error> {
-        let (field, value) = get_field_value(schema, item)?;
+        let (field, value) = get_field_value(schema, aliases, item)?;
         let condition = ConditionType::NotEqual;
-        Ok(Self { field, condition, value })
+        Ok(Self { field, condition, value, not_equal_matches_missing: true })
     }
 
     pub fn geq(
         item: Pair<'a, Rule>,
         schema: &'a Schema,
+        aliases: &BTreeMap<String, String>,
     ) -> Result<Self, Error> {
-        let (field, value) = get_field_value(schema, item)?;
+        let (field, value) = get_field_value(schema, aliases, item)?;
         let condition = ConditionType::GreaterEqual;
-        Ok(Self { field, condition, value })
+        Ok(Self { field, condition, value, not_equal_matches_missing: true })
     }
 
     pub fn leq(
         item: Pair<'a, Rule>,
         schema: &'a Schema,
+        aliases: &BTreeMap<String, String>,
     ) -> Result<Self, Error> {
-        let (field, value) = get_field_value(schema, item)?;
+        let (field, value) = get_field_value(schema, aliases, item)?;
         let condition = ConditionType::LessEqual;
-        Ok(Self { field, condition, value })
+        Ok(Self { field, condition, value, not_equal_matches_missing: true })
     }
 
     pub fn eq(
         item: Pair<'a, Rule>,
         schema: &'a Schema,
+        aliases: &BTreeMap<String, String>,
     ) -> Result<Self, Error> {
-        let (field, value) = get_field_value(schema, item)?;
+        let (field, value) = get_field_value(schema, aliases, item)?;
         let condition = ConditionType::Equal;
-        Ok(Self { field, condition, value })
+        Ok(Self { field, condition, value, not_equal_matches_missing: true })
     }
 
     pub fn test(
@@ -170,8 +190,8 @@ impl<'a> Condition<'a> {
             Some(Value::String(s)) => {
                 let value = self.value.as_str();
                 match self.condition {
-                    ConditionType::Equal => unicase::eq(value, &s),
-                    ConditionType::NotEqual => !unicase::eq(value, &s),
+                    ConditionType::Equal => strings_eq(value, &s),
+                    ConditionType::NotEqual => !strings_eq(value, &s),
                     _ => false
                 }
             },
@@ -202,6 +222,9 @@ impl<'a> Condition<'a> {
                 }
                 false
             },
+            None | Some(Value::Null) => {
+                self.condition == ConditionType::NotEqual && self.not_equal_matches_missing
+            },
             // if field is not supported (or not found), all values are different from it,
             // so != should always return true in this case.
             _ => self.condition == ConditionType::NotEqual,
@@ -3,6 +3,7 @@ mod condition;
 
 pub(crate) use parser::Rule;
 
+use std::collections::BTreeMap;
 use std::ops::Not;
 
 use condition::Condition;
@@ -26,8 +27,31 @@ pub enum Filter<'a> {
 
 impl<'a> Filter<'a> {
     pub fn parse(expr: &'a str, schema: &'a Schema) -> FilterResult<'a> {
+        Self::parse_with_aliases(expr, schema, &BTreeMap::new())
+    }
+
+    /// Parses the expression where the attributes can also be named by their aliases.
+    pub fn parse_with_aliases(
+        expr: &'a str,
+        schema: &'a Schema,
+        aliases: &BTreeMap<String, String>,
+    ) -> FilterResult<'a> {
         let mut lexed = FilterParser::parse(Rule::prgm, expr.as_ref())?;
-        Self::build(lexed.next().unwrap().into_inner(), schema)
+        Self::build(lexed.next().unwrap().into_inner(), schema, aliases)
+    }
+
+    /// Whether the `!=` conditions match the documents missing the attribute,
+    /// or where it is null, they do by default.
+    pub fn set_not_equal_matches_missing(&mut self, value: bool) {
+        use Filter::*;
+        match self {
+            Condition(c) => c.not_equal_matches_missing = value,
+            Or(lhs, rhs) | And(lhs, rhs) => {
+                lhs.set_not_equal_matches_missing(value);
+                rhs.set_not_equal_matches_missing(value);
+            },
+            Not(op) => op.set_not_equal_matches_missing(value),
+        }
     }
 
     pub fn test(
@@ -49,21 +73,26 @@ impl<'a> Filter<'a> {
         }
     }
 
-    fn build(expression: Pairs<'a, Rule>, schema: &'a Schema) -> FilterResult<'a> {
+    fn build(
+        expression: Pairs<'a, Rule>,
+        schema: &'a Schema,
+        aliases: &BTreeMap<String, String>,
+    ) -> FilterResult<'a> {
         PREC_CLIMBER.climb(
             expression,
             |pair: Pair<Rule>| match pair.as_rule() {
-                Rule::eq => Ok(Filter::Condition(Condition::eq(pair, schema)?)),
-                Rule::greater => Ok(Filter::Condition(Condition::greater(pair, schema)?)),
-                Rule::less => Ok(Filter::Condition(Condition::less(pair, schema)?)),
-                Rule::neq => Ok(Filter::Condition(Condition::neq(pair, schema)?)),
-                Rule::geq => Ok(Filter::Condition(Condition::geq(pair, schema)?)),
-                Rule::leq => Ok(Filter::Condition(Condition::leq(pair, schema)?)),
-                Rule::prgm => Self::build(pair.into_inner(), schema),
-                Rule::term => Self::build(pair.into_inner(), schema),
+                Rule::eq => Ok(Filter::Condition(Condition::eq(pair, schema, aliases)?)),
+                Rule::greater => Ok(Filter::Condition(Condition::greater(pair, schema, aliases)?)),
+                Rule::less => Ok(Filter::Condition(Condition::less(pair, schema, aliases)?)),
+                Rule::neq => Ok(Filter::Condition(Condition::neq(pair, schema, aliases)?)),
+                Rule::geq => Ok(Filter::Condition(Condition::geq(pair, schema, aliases)?)),
+                Rule::leq => Ok(Filter::Condition(Condition::leq(pair, schema, aliases)?)),
+                Rule::prgm => Self::build(pair.into_inner(), schema, aliases),
+                Rule::term => Self::build(pair.into_inner(), schema, aliases),
                 Rule::not => Ok(Filter::Not(Box::new(Self::build(
                     pair.into_inner(),
                     schema,
+                    aliases,
                 )?))),
                 _ => unreachable!(),
             },
@@ -0,0 +1,59 @@
+//! Encoding of the geographic points into geohash cells.
+//!
+//! A geohash is a string of base32 characters, each character halving the cell
+//! of the previous ones five times, alternately along the longitude and the latitude.
+//! The cells of a precision are then the prefixes of this length of the geohashes.
+
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// The biggest precision of the geohash cells that are stored, the cells of
+/// this precision are smaller than a few centimeters.
+pub const GEOHASH_MAX_PRECISION: usize = 12;
+
+/// Returns the geohash of the cell of the given precision containing the point.
+pub fn encode(lat: f64, lng: f64, precision: usize) -> String {
+    let mut lat_range = (-90.0, 90.0);
+    let mut lng_range = (-180.0, 180.0);
+    let mut geohash = String::with_capacity(precision);
+
+    let mut is_lng = true;
+    let mut bits = 0;
+    let mut index = 0;
+
+    while geohash.len() < precision {
+        let (range, value) = if is_lng { (&mut lng_range, lng) } else { (&mut lat_range, lat) };
+        let mid = (range.0 + range.1) / 2.0;
+
+        index <<= 1;
+        if value >= mid {
+            index |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+
+        is_lng = !is_lng;
+        bits += 1;
+
+        if bits == 5 {
+            geohash.push(BASE32[index] as char);
+            bits = 0;
+            index = 0;
+        }
+    }
+
+    geohash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_points() {
+        assert_eq!(encode(57.64911, 10.40744, 11), "u4pruydqqvj");
+        assert_eq!(encode(57.64911, 10.40744, 3), "u4p");
+        assert_eq!(encode(48.8566, 2.3522, 5), "u09tv");
+        assert_eq!(encode(-33.8688, 151.2093, 4), "r3gx");
+    }
+}
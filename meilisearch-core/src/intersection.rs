@@ -0,0 +1,178 @@
+//! Intersection of sorted sets of documents ids.
+//!
+//! The sets are intersected two by two starting with the smallest ones. When the sizes
+//! of the two sets are very different, the elements of the smallest one are searched in
+//! the largest using a galloping search, otherwise both sets are merged, using AVX2
+//! instructions to compare four documents ids at a time when available.
+
+use std::{mem, slice};
+
+use sdset::{exponential_search, Set, SetBuf};
+
+use crate::DocumentId;
+
+/// Above this ratio between the sizes of the sets, galloping is faster than merging.
+const GALLOPING_RATIO: usize = 32;
+
+pub fn intersection(sets: Vec<&Set<DocumentId>>) -> SetBuf<DocumentId> {
+    let mut sets = sets;
+    sets.sort_unstable_by_key(|s| s.len());
+
+    let mut sets = sets.into_iter();
+    let mut result = match sets.next() {
+        Some(first) => first.as_slice().to_vec(),
+        None => return SetBuf::new_unchecked(Vec::new()),
+    };
+
+    let mut buffer = Vec::with_capacity(result.len());
+    for set in sets {
+        if result.is_empty() {
+            break;
+        }
+
+        buffer.clear();
+        intersect(as_u64s(&result), as_u64s(set.as_slice()), &mut buffer);
+        result.clear();
+        result.extend(buffer.iter().map(|&id| DocumentId(id)));
+    }
+
+    SetBuf::new_unchecked(result)
+}
+
+fn as_u64s(ids: &[DocumentId]) -> &[u64] {
+    // DocumentId is a `repr(C)` wrapper of a single u64
+    // and therefore has the same layout as an u64.
+    assert_eq!(mem::size_of::<DocumentId>(), mem::size_of::<u64>());
+    unsafe { slice::from_raw_parts(ids.as_ptr() as *const u64, ids.len()) }
+}
+
+/// Intersects `small` and `large`, two sorted and deduplicated slices.
+fn intersect(small: &[u64], large: &[u64], output: &mut Vec<u64>) {
+    if small.len().saturating_mul(GALLOPING_RATIO) < large.len() {
+        return intersect_galloping(small, large, output);
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { intersect_avx2(small, large, output) };
+        }
+    }
+
+    intersect_merge(small, large, output)
+}
+
+fn intersect_galloping(small: &[u64], large: &[u64], output: &mut Vec<u64>) {
+    let mut offset = 0;
+    for x in small {
+        match exponential_search(&large[offset..], x) {
+            Ok(pos) => {
+                output.push(*x);
+                offset += pos + 1;
+            }
+            Err(pos) => offset += pos,
+        }
+
+        if offset >= large.len() {
+            break;
+        }
+    }
+}
+
+fn intersect_merge(a: &[u64], b: &[u64], output: &mut Vec<u64>) {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] < b[j] {
+            i += 1;
+        } else if a[i] > b[j] {
+            j += 1;
+        } else {
+            output.push(a[i]);
+            i += 1;
+            j += 1;
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn intersect_avx2(a: &[u64], b: &[u64], output: &mut Vec<u64>) {
+    use std::arch::x86_64::*;
+
+    let mut j = 0;
+    for &x in a {
+        // skip the blocks of four ids that are all smaller than x
+        while j + 4 <= b.len() && b[j + 3] < x {
+            j += 4;
+        }
+
+        if j + 4 <= b.len() {
+            let needle = _mm256_set1_epi64x(x as i64);
+            let block = _mm256_loadu_si256(b.as_ptr().add(j) as *const __m256i);
+            if _mm256_movemask_epi8(_mm256_cmpeq_epi64(needle, block)) != 0 {
+                output.push(x);
+            }
+        } else {
+            while j < b.len() && b[j] < x {
+                j += 1;
+            }
+            if j == b.len() {
+                break;
+            }
+            if b[j] == x {
+                output.push(x);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive(a: &[u64], b: &[u64]) -> Vec<u64> {
+        a.iter().filter(|x| b.contains(x)).cloned().collect()
+    }
+
+    #[test]
+    fn all_kernels_agree() {
+        let a: Vec<u64> = (0..1000).filter(|x| x % 3 == 0).collect();
+        let b: Vec<u64> = (0..1000).filter(|x| x % 5 == 0).collect();
+        let rare = vec![15, 16, 500, 995, 999];
+        let expected = naive(&a, &b);
+
+        let mut output = Vec::new();
+        intersect_merge(&a, &b, &mut output);
+        assert_eq!(output, expected);
+
+        output.clear();
+        intersect_galloping(&a, &b, &mut output);
+        assert_eq!(output, expected);
+
+        output.clear();
+        intersect(&rare, &b, &mut output);
+        assert_eq!(output, naive(&rare, &b));
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                output.clear();
+                unsafe { intersect_avx2(&a, &b, &mut output) };
+                assert_eq!(output, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn many_sets() {
+        let a = SetBuf::from_dirty(vec![DocumentId(1), DocumentId(2), DocumentId(3), DocumentId(8)]);
+        let b = SetBuf::from_dirty(vec![DocumentId(2), DocumentId(3), DocumentId(8)]);
+        let c = SetBuf::from_dirty(vec![DocumentId(3), DocumentId(8), DocumentId(9)]);
+
+        let result = intersection(vec![a.as_set(), b.as_set(), c.as_set()]);
+        assert_eq!(result.as_slice(), &[DocumentId(3), DocumentId(8)]);
+
+        let result = intersection(vec![]);
+        assert!(result.is_empty());
+    }
+}
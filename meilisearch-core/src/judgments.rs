@@ -0,0 +1,85 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// The documents expected at the top of the results of a query, by document id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Judgment {
+    pub query: String,
+    pub filters: Option<String>,
+    pub expected: Vec<String>,
+    /// The number of hits compared to the expected documents, their number by default.
+    pub limit: Option<usize>,
+}
+
+impl Judgment {
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or_else(|| self.expected.len())
+    }
+}
+
+/// How well the hits of a query match the documents expected by its judgment.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Relevancy {
+    /// The part of the hits that are expected documents.
+    pub precision: f64,
+    /// The part of the expected documents that are in the hits.
+    pub recall: f64,
+}
+
+impl Relevancy {
+    pub fn measure(expected: &[String], hits: &[String]) -> Relevancy {
+        let expected: HashSet<_> = expected.iter().collect();
+        let hits: HashSet<_> = hits.iter().collect();
+        let found = hits.intersection(&expected).count() as f64;
+
+        let ratio = |count: usize| if count == 0 { 0.0 } else { found / count as f64 };
+        Relevancy { precision: ratio(hits.len()), recall: ratio(expected.len()) }
+    }
+
+    /// The mean relevancy of the judgments, a perfect one if there are none.
+    pub fn mean<'a>(relevancies: impl IntoIterator<Item = &'a Relevancy>) -> Relevancy {
+        let (mut precision, mut recall, mut count) = (0.0, 0.0, 0);
+        for relevancy in relevancies {
+            precision += relevancy.precision;
+            recall += relevancy.recall;
+            count += 1;
+        }
+
+        if count == 0 {
+            return Relevancy { precision: 1.0, recall: 1.0 };
+        }
+        Relevancy { precision: precision / count as f64, recall: recall / count as f64 }
+    }
+}
+
+/// The relevancies measured by the last run of the judgments of an index, by query.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JudgmentsRun {
+    pub ran_at: DateTime<Utc>,
+    pub relevancies: BTreeMap<String, Relevancy>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn measure_relevancy() {
+        let relevancy = Relevancy::measure(&ids(&["1", "2", "3", "4"]), &ids(&["1", "5", "3"]));
+        assert_eq!(relevancy.precision, 2.0 / 3.0);
+        assert_eq!(relevancy.recall, 0.5);
+
+        let relevancy = Relevancy::measure(&ids(&["1"]), &[]);
+        assert_eq!(relevancy, Relevancy { precision: 0.0, recall: 0.0 });
+
+        let mean = Relevancy::mean(&[relevancy, Relevancy { precision: 1.0, recall: 0.5 }]);
+        assert_eq!(mean, Relevancy { precision: 0.5, recall: 0.25 });
+    }
+}
@@ -6,37 +6,52 @@ extern crate pest_derive;
 
 mod automaton;
 mod bucket_sort;
+mod cancellation;
 mod database;
 mod distinct_map;
+mod encryption;
 mod error;
 mod filters;
+mod geohash;
+mod intersection;
 mod levenshtein;
 mod number;
 mod query_builder;
 mod query_tree;
-mod query_words_mapper;
 mod ranked_map;
 mod raw_document;
 mod reordered_attrs;
+mod write_queue;
 pub mod criterion;
 pub mod facets;
+pub mod judgments;
 pub mod raw_indexer;
 pub mod serde;
 pub mod settings;
 pub mod store;
 pub mod update;
 
-pub use self::database::{BoxUpdateFn, Database, DatabaseOptions, MainT, UpdateT};
+pub use self::bucket_sort::SearchTiming;
+pub use self::cancellation::CancellationToken;
+pub use self::database::{BoxUpdateFn, Database, DatabaseOptions, MainT, UpdateGroupOutcome, UpdateT};
+pub use self::encryption::ENCRYPTION_KEY_LEN;
 pub use self::error::{Error, HeedError, FstError, MResult, pest_error, FacetError};
 pub use self::filters::Filter;
+pub use self::geohash::GEOHASH_MAX_PRECISION;
 pub use self::number::{Number, ParseNumberError};
+pub use self::query_builder::DEFAULT_MAX_TOTAL_HITS;
 pub use self::ranked_map::RankedMap;
 pub use self::raw_document::RawDocument;
 pub use self::store::Index;
+pub use self::query_tree::{create_query_tree, traverse_query_tree, Context as QueryTreeContext};
+pub use self::query_tree::{analyze_query, QueryAnalysis, QueryNgram, QuerySplit, QuerySynonyms};
+pub use self::query_tree::{LmdbQueryContext, PostingsKey, QueryResult};
 pub use self::update::{EnqueuedUpdateResult, ProcessedUpdateResult, UpdateStatus, UpdateType};
+pub use self::write_queue::{QueuedTxn, WriteQueueStats, WriteQueuesStats};
 pub use meilisearch_types::{DocIndex, DocumentId, Highlight};
 pub use meilisearch_schema::Schema;
-pub use query_words_mapper::QueryWordsMapper;
+pub use meilisearch_query::{MemoryQueryContext, QueryContext};
+pub use meilisearch_query::{Operation, Query, QueryId, QueryKind, QueryTreeBuilder, QueryWordsMapper};
 
 use std::convert::TryFrom;
 use std::collections::HashMap;
@@ -45,7 +60,6 @@ use log::{error, trace};
 
 use crate::bucket_sort::PostingsListView;
 use crate::levenshtein::prefix_damerau_levenshtein;
-use crate::query_tree::{QueryId, QueryKind};
 use crate::reordered_attrs::ReorderedAttrs;
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -74,7 +88,9 @@ fn highlights_from_raw_document<'a, 'tag, 'txn>(
 
         for di in postings_list.iter() {
             let covered_area = match kind {
-                Some(QueryKind::NonTolerant(query)) | Some(QueryKind::Tolerant(query)) => {
+                Some(QueryKind::NonTolerant(query))
+                | Some(QueryKind::Tolerant(query))
+                | Some(QueryKind::Correction(query)) => {
                     let len = if query.len() > input.len() {
                         input.len()
                     } else {
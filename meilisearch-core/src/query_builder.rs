@@ -1,18 +1,22 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::ops::{Range, Deref};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use either::Either;
-use sdset::SetOperation;
+use sdset::{SetBuf, SetOperation};
 
 use meilisearch_schema::FieldId;
 
 use crate::database::MainT;
 use crate::bucket_sort::{bucket_sort, bucket_sort_with_distinct, SortResult};
-use crate::{criterion::Criteria, DocumentId};
+use crate::criterion::{Criteria, SortKey};
+use crate::{CancellationToken, DocumentId};
 use crate::{reordered_attrs::ReorderedAttrs, store, MResult};
-use crate::facets::FacetFilter;
+use crate::facets::{FacetFilter, FacetKey};
+
+/// The number of hits after which counting stops by default.
+pub const DEFAULT_MAX_TOTAL_HITS: usize = 1000;
 
 pub struct QueryBuilder<'c, 'f, 'd, 'i> {
     criteria: Criteria<'c>,
@@ -20,9 +24,21 @@ pub struct QueryBuilder<'c, 'f, 'd, 'i> {
     filter: Option<Box<dyn Fn(DocumentId) -> bool + 'f>>,
     distinct: Option<(Box<dyn Fn(DocumentId) -> Option<u64> + 'd>, usize)>,
     timeout: Option<Duration>,
+    max_total_hits: Option<usize>,
     index: &'i store::Index,
     facet_filter: Option<FacetFilter>,
     facets: Option<Vec<(FieldId, String)>>,
+    geo_distribution: Option<(FieldId, usize)>,
+    disjunctive_facets: bool,
+    exhaustive_facets_count: bool,
+    synonyms: bool,
+    concatenate_ngrams: bool,
+    split_words: bool,
+    keyboard_typos: bool,
+    numeric_tolerance: bool,
+    cancellation: Option<CancellationToken>,
+    sample: Option<(usize, u64)>,
+    search_after: Option<Vec<SortKey>>,
 }
 
 impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
@@ -43,6 +59,24 @@ impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
         self.facets = facets;
     }
 
+    /// counts the values of each facet with the facet filters on other facets only, the
+    /// selected values of a facet don't hide its other values, e.g. for a multi-select list
+    pub fn set_disjunctive_facets(&mut self, disjunctive: bool) {
+        self.disjunctive_facets = disjunctive;
+    }
+
+    /// counts the facet values on a sample of the documents when there are many of them,
+    /// the counts are then approximated, they are exhaustive by default
+    pub fn set_exhaustive_facets_count(&mut self, exhaustive: bool) {
+        self.exhaustive_facets_count = exhaustive;
+    }
+
+    /// sets the geo point attribute and the geohash precision for which to return the count of
+    /// the documents in each cell
+    pub fn set_geo_distribution(&mut self, distribution: Option<(FieldId, usize)>) {
+        self.geo_distribution = distribution;
+    }
+
     pub fn with_criteria(
         index: &'i store::Index,
         criteria: Criteria<'c>,
@@ -53,9 +87,21 @@ impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
             filter: None,
             distinct: None,
             timeout: None,
+            max_total_hits: Some(DEFAULT_MAX_TOTAL_HITS),
             index,
             facet_filter: None,
             facets: None,
+            geo_distribution: None,
+            disjunctive_facets: false,
+            exhaustive_facets_count: true,
+            synonyms: true,
+            concatenate_ngrams: true,
+            split_words: true,
+            keyboard_typos: true,
+            numeric_tolerance: true,
+            cancellation: None,
+            sample: None,
+            search_after: None,
         }
     }
 
@@ -70,6 +116,68 @@ impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
         self.timeout = Some(timeout)
     }
 
+    /// Stops counting the hits once `max` is reached, the count is then marked as not exhaustive.
+    /// Counting is only costly when a filter or a distinct rule is used, `None` counts all the hits.
+    pub fn with_max_total_hits(&mut self, max: Option<usize>) {
+        self.max_total_hits = max
+    }
+
+    /// Disables the expansion of the query words with their synonyms.
+    pub fn without_synonyms(&mut self) {
+        self.synonyms = false
+    }
+
+    /// Disables the alternatives made of consecutive query words concatenated,
+    /// they are a large part of the query tree when the query is long.
+    pub fn without_ngrams_concatenation(&mut self) {
+        self.concatenate_ngrams = false
+    }
+
+    /// Disables the alternatives made of a query word split in two frequent words.
+    pub fn without_words_split(&mut self) {
+        self.split_words = false
+    }
+
+    /// Disables the alternatives made of a short query word with a letter replaced by an
+    /// adjacent key, even when the keyboard typos are enabled in the settings of the index.
+    pub fn without_keyboard_typos(&mut self) {
+        self.keyboard_typos = false
+    }
+
+    /// Disables the alternatives made of the indexed numbers near the query numbers,
+    /// even when a numeric tolerance is set in the settings of the index.
+    pub fn without_numeric_tolerance(&mut self) {
+        self.numeric_tolerance = false
+    }
+
+    /// Only searches the query words as they are, with typos and the last one as a prefix,
+    /// the query trees are much smaller and faster to traverse, e.g. for an autocompletion.
+    pub fn quick_search(&mut self) {
+        self.without_synonyms();
+        self.without_ngrams_concatenation();
+        self.without_words_split();
+        self.without_keyboard_typos();
+        self.without_numeric_tolerance();
+    }
+
+    /// Stops the search with `Error::Cancelled` once the token is cancelled.
+    pub fn with_cancellation(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token)
+    }
+
+    /// Ranks a uniformly random sample of `size` documents drawn from the candidates passing
+    /// the filter, instead of all of them, the same seed draws the same sample from the same
+    /// candidates. The range is then applied to the sample.
+    pub fn with_sample(&mut self, size: usize, seed: u64) {
+        self.sample = Some((size, seed))
+    }
+
+    /// Only returns the documents sorted after the one of the key, the `last_sort_key` of a
+    /// previous search. The range then starts from the first document after it.
+    pub fn with_search_after(&mut self, key: Vec<SortKey>) {
+        self.search_after = Some(key)
+    }
+
     pub fn with_distinct<F>(&mut self, size: usize, function: F)
     where
         F: Fn(DocumentId) -> Option<u64> + 'd,
@@ -88,36 +196,22 @@ impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
         query: &str,
         range: Range<usize>,
     ) -> MResult<SortResult> {
-        let facets_docids = match self.facet_filter {
-            Some(facets) => {
-                let mut ands = Vec::with_capacity(facets.len());
-                let mut ors = Vec::new();
-                for f in facets.deref() {
-                    match f {
-                        Either::Left(keys) => {
-                            ors.reserve(keys.len());
-                            for key in keys {
-                                let docids = self.index.facets.facet_document_ids(reader, &key)?.unwrap_or_default();
-                                ors.push(docids);
-                            }
-                            let sets: Vec<_> = ors.iter().map(Cow::deref).collect();
-                            let or_result = sdset::multi::OpBuilder::from_vec(sets).union().into_set_buf();
-                            ands.push(Cow::Owned(or_result));
-                            ors.clear();
-                        }
-                        Either::Right(key) =>{
-                            match self.index.facets.facet_document_ids(reader, &key)? {
-                                Some(docids) => ands.push(docids),
-                                // no candidates for search, early return.
-                                None => return Ok(SortResult::default()),
-                            }
-                        }
-                    };
-                }
-                let ands: Vec<_> = ands.iter().map(Cow::deref).collect();
-                Some(sdset::multi::OpBuilder::from_vec(ands).intersection().into_set_buf())
-            }
-            None => None
+        let synonyms = if self.synonyms { Some(self.index.synonyms) } else { None };
+
+        // the timeout of the builder takes precedence over the cutoff of the index
+        let timeout = match self.timeout {
+            Some(timeout) => Some(timeout),
+            None => self.index.main.search_cutoff_ms(reader)?.map(Duration::from_millis),
+        };
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        let facets_docids = match &self.facet_filter {
+            Some(facets) => match facet_filter_docids(reader, self.index, facets.iter())? {
+                Some(docids) => Some(docids),
+                // no candidates for search, early return.
+                None => return Ok(SortResult::default()),
+            },
+            None => None,
         };
 
         // for each field to retrieve the count for, create an HashMap associating the attribute
@@ -127,11 +221,43 @@ impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
             Some(field_ids) => {
                 let mut facet_count_map = HashMap::new();
                 for (field_id, field_name) in field_ids {
+                    // the counts are restricted to the documents matching the facet filters,
+                    // but the ones on the facet itself when the faceting is disjunctive
+                    let candidates = match (&self.facet_filter, &facets_docids) {
+                        (Some(facets), Some(docids)) => {
+                            if self.disjunctive_facets && facets.iter().any(|f| filters_field(f, field_id)) {
+                                let others: Vec<_> = facets.iter().filter(|f| !filters_field(f, field_id)).collect();
+                                if others.is_empty() {
+                                    None
+                                } else {
+                                    let docids = facet_filter_docids(reader, self.index, others)?;
+                                    Some(Cow::Owned(docids.unwrap_or_default()))
+                                }
+                            } else {
+                                Some(Cow::Borrowed(docids.as_set()))
+                            }
+                        }
+                        _ => None,
+                    };
+
                     let mut key_map = HashMap::new();
                     for pair in self.index.facets.field_document_ids(reader, field_id)? {
                         let (facet_key, document_ids) = pair?;
-                        let value = facet_key.value();
-                        key_map.insert(value.to_string(), document_ids);
+                        if facet_key.as_geohash_cell().is_some() {
+                            continue;
+                        }
+                        let value = match self.index.facets.display_value(reader, &facet_key)? {
+                            Some(value) => value.to_string(),
+                            None => facet_key.value().to_string(),
+                        };
+                        let document_ids = match &candidates {
+                            Some(candidates) => {
+                                let op = sdset::duo::OpBuilder::new(document_ids.as_ref(), candidates.as_ref());
+                                Cow::Owned(op.intersection().into_set_buf())
+                            }
+                            None => document_ids,
+                        };
+                        key_map.insert(value, document_ids);
                     }
                     facet_count_map.insert(field_name, key_map);
                 }
@@ -140,22 +266,51 @@ impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
             None => None,
         };
 
+        // the geohash cells of every precision are stored with the facet values of the attribute
+        let geo_count_docids = match self.geo_distribution {
+            Some((field_id, precision)) => {
+                let mut cells = HashMap::new();
+                for pair in self.index.facets.field_document_ids(reader, field_id)? {
+                    let (facet_key, document_ids) = pair?;
+                    match facet_key.as_geohash_cell() {
+                        Some(cell) if cell.len() == precision => {
+                            cells.insert(cell.to_string(), document_ids);
+                        }
+                        _ => (),
+                    }
+                }
+                Some(cells)
+            }
+            None => None,
+        };
+
         match self.distinct {
             Some((distinct, distinct_size)) => bucket_sort_with_distinct(
                 reader,
                 query,
                 range,
+                self.search_after,
                 facets_docids,
                 facet_count_docids,
+                self.exhaustive_facets_count,
+                geo_count_docids,
                 self.filter,
                 distinct,
                 distinct_size,
                 self.criteria,
                 self.searchable_attrs,
+                deadline,
+                self.cancellation,
+                self.max_total_hits,
+                self.sample,
                 self.index.main,
                 self.index.postings_lists,
                 self.index.documents_fields_counts,
-                self.index.synonyms,
+                synonyms,
+                self.concatenate_ngrams,
+                self.split_words,
+                self.keyboard_typos,
+                self.numeric_tolerance,
                 self.index.prefix_documents_cache,
                 self.index.prefix_postings_lists_cache,
             ),
@@ -163,15 +318,26 @@ impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
                 reader,
                 query,
                 range,
+                self.search_after,
                 facets_docids,
                 facet_count_docids,
+                self.exhaustive_facets_count,
+                geo_count_docids,
                 self.filter,
                 self.criteria,
                 self.searchable_attrs,
+                deadline,
+                self.cancellation,
+                self.max_total_hits,
+                self.sample,
                 self.index.main,
                 self.index.postings_lists,
                 self.index.documents_fields_counts,
-                self.index.synonyms,
+                synonyms,
+                self.concatenate_ngrams,
+                self.split_words,
+                self.keyboard_typos,
+                self.numeric_tolerance,
                 self.index.prefix_documents_cache,
                 self.index.prefix_postings_lists_cache,
             ),
@@ -179,6 +345,49 @@ impl<'c, 'f, 'd, 'i> QueryBuilder<'c, 'f, 'd, 'i> {
     }
 }
 
+/// Returns the documents matching all of the facet filters,
+/// `None` if a facet value that must match isn't in the index.
+pub(crate) fn facet_filter_docids<'a>(
+    reader: &heed::RoTxn<MainT>,
+    index: &store::Index,
+    facets: impl IntoIterator<Item = &'a Either<Vec<FacetKey>, FacetKey>>,
+) -> MResult<Option<SetBuf<DocumentId>>>
+{
+    let mut ands = Vec::new();
+    let mut ors = Vec::new();
+    for f in facets {
+        match f {
+            Either::Left(keys) => {
+                ors.reserve(keys.len());
+                for key in keys {
+                    let docids = index.facets.facet_document_ids(reader, &key)?.unwrap_or_default();
+                    ors.push(docids);
+                }
+                let sets: Vec<_> = ors.iter().map(Cow::deref).collect();
+                let or_result = sdset::multi::OpBuilder::from_vec(sets).union().into_set_buf();
+                ands.push(Cow::Owned(or_result));
+                ors.clear();
+            }
+            Either::Right(key) => {
+                match index.facets.facet_document_ids(reader, &key)? {
+                    Some(docids) => ands.push(docids),
+                    None => return Ok(None),
+                }
+            }
+        };
+    }
+    let ands: Vec<_> = ands.iter().map(Cow::deref).collect();
+    Ok(Some(sdset::multi::OpBuilder::from_vec(ands).intersection().into_set_buf()))
+}
+
+/// Whether the facet filter only filters on the values of the field.
+fn filters_field(filter: &Either<Vec<FacetKey>, FacetKey>, field_id: FieldId) -> bool {
+    match filter {
+        Either::Left(keys) => keys.iter().all(|key| key.key() == field_id),
+        Either::Right(key) => key.key() == field_id,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,6 +404,7 @@ mod tests {
     use crate::Document;
     use crate::automaton::normalize_str;
     use crate::bucket_sort::SimpleMatch;
+    use crate::criterion::{CriteriaBuilder, Typo};
     use crate::database::{Database,DatabaseOptions};
     use crate::store::Index;
     use meilisearch_schema::Schema;
@@ -396,6 +606,51 @@ mod tests {
         assert_matches!(iter.next(), None);
     }
 
+    #[test]
+    fn search_cutoff() {
+        let store = TempDatabase::from_iter(vec![
+            ("iphone", &[doc_char_index(0, 0, 0), doc_char_index(1, 2, 2)][..]),
+            ("from", &[doc_char_index(0, 1, 1)][..]),
+            ("apple", &[doc_char_index(0, 2, 2), doc_char_index(1, 0, 0)][..]),
+        ]);
+
+        let db = &store.database;
+        let reader = db.main_read_txn().unwrap();
+
+        let builder = store.query_builder();
+        let SortResult { documents, degraded, .. } = builder.query(&reader, "iphone apple", 0..20).unwrap();
+        assert!(!degraded);
+        assert_eq!(documents.len(), 2);
+
+        // the documents are still returned when the cutoff is reached before ranking them
+        let mut builder = store.query_builder();
+        builder.with_fetch_timeout(Duration::from_secs(0));
+        let SortResult { documents, degraded, .. } = builder.query(&reader, "iphone apple", 0..20).unwrap();
+        assert!(degraded);
+        assert_eq!(documents.len(), 2);
+    }
+
+    #[test]
+    fn cancelled_search() {
+        let store = TempDatabase::from_iter(vec![
+            ("iphone", &[doc_char_index(0, 0, 0), doc_char_index(1, 2, 2)][..]),
+            ("apple", &[doc_char_index(0, 2, 2), doc_char_index(1, 0, 0)][..]),
+        ]);
+
+        let db = &store.database;
+        let reader = db.main_read_txn().unwrap();
+
+        let token = CancellationToken::new();
+        let mut builder = store.query_builder();
+        builder.with_cancellation(token.clone());
+        assert_eq!(builder.query(&reader, "iphone apple", 0..20).unwrap().documents.len(), 2);
+
+        token.cancel();
+        let mut builder = store.query_builder();
+        builder.with_cancellation(token);
+        assert_matches!(builder.query(&reader, "iphone apple", 0..20), Err(crate::Error::Cancelled));
+    }
+
     #[test]
     fn simple_synonyms() {
         let mut store = TempDatabase::from_iter(vec![("hello", &[doc_index(0, 0)][..])]);
@@ -428,6 +683,162 @@ mod tests {
         assert_matches!(iter.next(), None);
     }
 
+    #[test]
+    fn tied_documents_are_paginated_by_id() {
+        let indexes: Vec<_> = (0..30).rev().map(|id| doc_index(id, 0)).collect();
+        let store = TempDatabase::from_iter(vec![("hello", &indexes[..])]);
+
+        let db = &store.database;
+        let reader = db.main_read_txn().unwrap();
+
+        // the criteria don't end with the document id one
+        let mut ids = Vec::new();
+        for page in 0..3 {
+            let criteria = CriteriaBuilder::new().add(Typo).build();
+            let builder = store.index.query_builder_with_criteria(criteria);
+            let range = page * 10..(page + 1) * 10;
+            let SortResult { documents, .. } = builder.query(&reader, "hello", range).unwrap();
+            ids.extend(documents.into_iter().map(|document| document.id.0));
+        }
+
+        assert_eq!(ids, (0..30).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn search_continued_after_the_sort_key() {
+        let store = TempDatabase::from_iter(vec![
+            ("hello", &[doc_index(0, 0), doc_index(1, 2), doc_index(2, 1), doc_index(3, 0), doc_index(4, 3)][..]),
+            ("helo", &[doc_index(5, 0)][..]),
+        ]);
+
+        let db = &store.database;
+        let reader = db.main_read_txn().unwrap();
+
+        let builder = store.query_builder();
+        let SortResult { documents, .. } = builder.query(&reader, "hello", 0..6).unwrap();
+        let expected: Vec<_> = documents.into_iter().map(|document| document.id.0).collect();
+
+        let mut ids = Vec::new();
+        let mut search_after = None;
+        loop {
+            let mut builder = store.query_builder();
+            if let Some(key) = search_after.take() {
+                builder.with_search_after(key);
+            }
+            let SortResult { documents, last_sort_key, .. } = builder.query(&reader, "hello", 0..2).unwrap();
+            if documents.is_empty() {
+                break;
+            }
+            ids.extend(documents.into_iter().map(|document| document.id.0));
+            search_after = last_sort_key;
+        }
+
+        assert_eq!(ids, expected);
+        assert!(ids.len() >= 5);
+    }
+
+    #[test]
+    fn hits_counted_up_to_the_max_total_hits() {
+        let store = TempDatabase::from_iter(vec![(
+            "hello",
+            &[doc_index(0, 0), doc_index(1, 0), doc_index(2, 0), doc_index(3, 0), doc_index(4, 0)][..],
+        )]);
+
+        let db = &store.database;
+        let reader = db.main_read_txn().unwrap();
+
+        // the hits below the default maximum are all counted
+        let mut builder = store.query_builder();
+        builder.with_filter(|id| id.0 != 4);
+        let SortResult { nb_hits, exhaustive_nb_hit, .. } = builder.query(&reader, "hello", 0..1).unwrap();
+        assert_eq!(nb_hits, 4);
+        assert!(exhaustive_nb_hit);
+
+        let mut builder = store.query_builder();
+        builder.with_filter(|id| id.0 != 4);
+        builder.with_max_total_hits(None);
+        let SortResult { nb_hits, exhaustive_nb_hit, .. } = builder.query(&reader, "hello", 0..1).unwrap();
+        assert_eq!(nb_hits, 4);
+        assert!(exhaustive_nb_hit);
+
+        let mut builder = store.query_builder();
+        builder.with_filter(|id| id.0 != 4);
+        builder.with_max_total_hits(Some(2));
+        let SortResult { nb_hits, exhaustive_nb_hit, .. } = builder.query(&reader, "hello", 0..1).unwrap();
+        assert_eq!(nb_hits, 2);
+        assert!(!exhaustive_nb_hit);
+    }
+
+    #[test]
+    fn disabled_synonyms() {
+        let mut store = TempDatabase::from_iter(vec![("hello", &[doc_index(0, 0)][..])]);
+
+        store.add_synonym("bonjour", SetBuf::from_dirty(vec!["hello"]));
+
+        let db = &store.database;
+        let reader = db.main_read_txn().unwrap();
+
+        let mut builder = store.query_builder();
+        builder.without_synonyms();
+        let SortResult { documents, .. } = builder.query(&reader, "bonjour", 0..20).unwrap();
+        assert!(documents.is_empty());
+
+        let mut builder = store.query_builder();
+        builder.without_synonyms();
+        let SortResult { documents, .. } = builder.query(&reader, "hello", 0..20).unwrap();
+        assert_eq!(documents.len(), 1);
+    }
+
+    #[test]
+    fn disabled_ngrams_concatenation() {
+        let store = TempDatabase::from_iter(vec![("newyork", &[doc_index(0, 0)][..])]);
+
+        let db = &store.database;
+        let reader = db.main_read_txn().unwrap();
+
+        let builder = store.query_builder();
+        let SortResult { documents, .. } = builder.query(&reader, "new york", 0..20).unwrap();
+        assert_eq!(documents.len(), 1);
+
+        let mut builder = store.query_builder();
+        builder.without_ngrams_concatenation();
+        let SortResult { documents, .. } = builder.query(&reader, "new york", 0..20).unwrap();
+        assert!(documents.is_empty());
+    }
+
+    #[test]
+    fn ignored_last_words() {
+        let store = TempDatabase::from_iter(vec![
+            ("iphone", &[doc_index(0, 0), doc_index(1, 0)][..]),
+            ("case",   &[doc_index(0, 1)][..]),
+        ]);
+
+        let db = &store.database;
+        let reader = db.main_read_txn().unwrap();
+
+        // the words are only ignored when the index allows it
+        let builder = store.query_builder();
+        let SortResult { documents, ignored_words, .. } = builder.query(&reader, "iphone blue cover", 0..20).unwrap();
+        assert!(documents.is_empty());
+        assert!(ignored_words.is_empty());
+        reader.abort().unwrap();
+
+        let mut writer = db.main_write_txn().unwrap();
+        store.index.main.put_optional_words(&mut writer, true).unwrap();
+        writer.commit().unwrap();
+        let reader = db.main_read_txn().unwrap();
+
+        let builder = store.query_builder();
+        let SortResult { documents, ignored_words, .. } = builder.query(&reader, "iphone case", 0..20).unwrap();
+        assert_eq!(documents.len(), 1);
+        assert!(ignored_words.is_empty());
+
+        let builder = store.query_builder();
+        let SortResult { documents, ignored_words, .. } = builder.query(&reader, "iphone blue cover", 0..20).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(ignored_words, vec!["blue".to_string(), "cover".to_string()]);
+    }
+
     // #[test]
     // fn prefix_synonyms() {
     //     let mut store = TempDatabase::from_iter(vec![("hello", &[doc_index(0, 0)][..])]);
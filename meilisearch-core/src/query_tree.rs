@@ -1,178 +1,264 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
-use std::hash::{Hash, Hasher};
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Range;
 use std::time::Instant;
-use std::{cmp, fmt, iter::once};
+use std::iter::once;
 
 use fst::{IntoStreamer, Streamer};
 use itertools::{EitherOrBoth, merge_join_by};
 use meilisearch_tokenizer::split_query_string;
 use sdset::{Set, SetBuf, SetOperation};
+use slice_group_by::GroupBy;
 use log::debug;
+use meilisearch_query::keyboard;
+use meilisearch_query::numbers::{closest_numbers, leading_number, tolerance_range};
+use meilisearch_query::{segment_word, split_best_frequency, QueryContext, DEFAULT_MIN_PREFIX_LENGTH, MAX_NGRAM};
+use meilisearch_schema::IndexedPos;
+use serde::Serialize;
 
 use crate::database::MainT;
-use crate::{store, DocumentId, DocIndex, MResult};
-use crate::automaton::{normalize_str, build_dfa, build_prefix_dfa, build_exact_dfa};
-use crate::QueryWordsMapper;
-
-#[derive(Clone, PartialEq, Eq, Hash)]
-pub enum Operation {
-    And(Vec<Operation>),
-    Or(Vec<Operation>),
-    Query(Query),
-}
+use crate::settings::QueryNormalization;
+use crate::{store, CancellationToken, DocumentId, DocIndex, Error, MResult};
+use crate::{Operation, Query, QueryId, QueryKind};
+use crate::intersection::intersection;
+use crate::automaton::{normalize_str, build_dfa, build_prefix_dfa, build_exact_dfa, build_exact_prefix_dfa};
+use crate::automaton::{is_transposition, tolerates_only_transpositions, transpositions};
 
-impl fmt::Debug for Operation {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fn pprint_tree(f: &mut fmt::Formatter<'_>, op: &Operation, depth: usize) -> fmt::Result {
-            match op {
-                Operation::And(children) => {
-                    writeln!(f, "{:1$}AND", "", depth * 2)?;
-                    children.iter().try_for_each(|c| pprint_tree(f, c, depth + 1))
-                },
-                Operation::Or(children) => {
-                    writeln!(f, "{:1$}OR", "", depth * 2)?;
-                    children.iter().try_for_each(|c| pprint_tree(f, c, depth + 1))
-                },
-                Operation::Query(query) => writeln!(f, "{:2$}{:?}", "", query, depth * 2),
-            }
-        }
+/// The number of indexed words read to find the numbers around a query number.
+const MAX_SCANNED_NUMBERS: usize = 1000;
 
-        pprint_tree(f, self, 0)
-    }
+#[derive(Debug, Default)]
+pub struct PostingsList {
+    docids: SetBuf<DocumentId>,
+    matches: SetBuf<DocIndex>,
 }
 
-impl Operation {
-    fn tolerant(id: QueryId, prefix: bool, s: &str) -> Operation {
-        Operation::Query(Query { id, prefix, exact: true, kind: QueryKind::Tolerant(s.to_string()) })
-    }
+pub struct Context {
+    pub words_set: fst::Set,
+    pub stop_words: fst::Set,
+    /// The synonyms are not used when not set.
+    pub synonyms: Option<store::Synonyms>,
+    pub postings_lists: store::PostingsLists,
+    pub prefix_postings_lists: store::PrefixPostingsListsCache,
+    /// Whether the consecutive query words are also searched concatenated.
+    pub concatenate_ngrams: bool,
+    /// Whether the query words are also searched split in two frequent words.
+    pub split_words: bool,
+    /// Whether the short query words are also searched with a letter replaced by an adjacent key.
+    pub keyboard_typos: bool,
+    /// The layout of the keyboard the adjacent keys are found on.
+    pub keyboard_layout: keyboard::Layout,
+    /// Whether the last query words are ignored when no document contains all of them.
+    pub optional_words: bool,
+    /// The relative distance to the query numbers of the indexed numbers they also match.
+    pub numeric_tolerance: Option<f64>,
+    /// The pairs of attributes, by indexed position, that phrases can span,
+    /// from the last word of the first attribute to the first word of the second.
+    pub adjacent_attributes: Vec<(u16, u16)>,
+    pub documents_fields_counts: store::DocumentsFieldsCounts,
+    /// Whether the quantities of the query are rewritten into their canonical words.
+    pub normalize_units: bool,
+    /// The number of characters the last query word must have to be searched as a prefix.
+    pub min_prefix_length: usize,
+    /// The rewrites applied to the query before its words are searched.
+    pub query_normalization: Option<QueryNormalization>,
+    /// The traversal stops with an error once this token is cancelled.
+    pub cancellation: Option<CancellationToken>,
+}
 
-    fn non_tolerant(id: QueryId, prefix: bool, s: &str) -> Operation {
-        Operation::Query(Query { id, prefix, exact: true, kind: QueryKind::NonTolerant(s.to_string()) })
+impl Context {
+    /// Retrieves the words and the stores of an index needed to traverse query trees.
+    pub fn new(reader: &heed::RoTxn<MainT>, index: &store::Index) -> MResult<Context> {
+        let words_set = index.main.words_fst(reader)?.unwrap_or_default();
+        let stop_words = index.main.stop_words_fst(reader)?.unwrap_or_default();
+
+        Ok(Context {
+            words_set,
+            stop_words,
+            synonyms: Some(index.synonyms),
+            postings_lists: index.postings_lists,
+            prefix_postings_lists: index.prefix_postings_lists_cache,
+            concatenate_ngrams: true,
+            split_words: true,
+            keyboard_typos: index.main.keyboard_typos(reader)?.unwrap_or(false),
+            keyboard_layout: index.main.keyboard_layout(reader)?.map(Into::into).unwrap_or_default(),
+            optional_words: index.main.optional_words(reader)?.unwrap_or(false),
+            numeric_tolerance: index.main.numeric_tolerance(reader)?,
+            adjacent_attributes: adjacent_attributes(reader, index.main)?,
+            documents_fields_counts: index.documents_fields_counts,
+            normalize_units: index.main.normalize_units(reader)?.unwrap_or(false),
+            min_prefix_length: index.main.min_prefix_length(reader)?.unwrap_or(DEFAULT_MIN_PREFIX_LENGTH),
+            query_normalization: index.main.query_normalization(reader)?,
+            cancellation: None,
+        })
     }
 
-    fn phrase2(id: QueryId, prefix: bool, (left, right): (&str, &str)) -> Operation {
-        let kind = QueryKind::Phrase(vec![left.to_owned(), right.to_owned()]);
-        Operation::Query(Query { id, prefix, exact: true, kind })
+    /// The query rewritten by the query normalization of the index, it must
+    /// only be normalized once, the rewrites of the words can chain otherwise.
+    pub fn normalize_query<'q>(&self, query: &'q str) -> Cow<'q, str> {
+        match &self.query_normalization {
+            Some(normalization) => Cow::Owned(normalization.normalize(query)),
+            None => Cow::Borrowed(query),
+        }
     }
 }
 
-pub type QueryId = usize;
+/// Retrieves the adjacent attributes setting of an index as pairs of indexed positions,
+/// the attributes that are not searchable are ignored.
+pub fn adjacent_attributes(reader: &heed::RoTxn<MainT>, main: store::Main) -> MResult<Vec<(u16, u16)>> {
+    let pairs = match main.adjacent_attributes(reader)? {
+        Some(pairs) => pairs,
+        None => return Ok(Vec::new()),
+    };
+
+    let schema = match main.schema(reader)? {
+        Some(schema) => schema,
+        None => return Ok(Vec::new()),
+    };
+
+    let indexed_pos = |name: &str| schema.id(name).and_then(|id| schema.is_indexed(id)).map(|pos| pos.0);
+    let pairs = pairs
+        .iter()
+        .filter_map(|(first, second)| Some((indexed_pos(first)?, indexed_pos(second)?)))
+        .collect();
 
-#[derive(Clone, Eq)]
-pub struct Query {
-    pub id: QueryId,
-    pub prefix: bool,
-    pub exact: bool,
-    pub kind: QueryKind,
+    Ok(pairs)
 }
 
-impl PartialEq for Query {
-    fn eq(&self, other: &Self) -> bool {
-        self.prefix == other.prefix && self.kind == other.kind
-    }
+/// The words and the synonyms of an index, as read by the construction of the query trees.
+pub struct LmdbQueryContext<'a> {
+    reader: &'a heed::RoTxn<MainT>,
+    ctx: &'a Context,
 }
 
-impl Hash for Query {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.prefix.hash(state);
-        self.kind.hash(state);
+impl<'a> LmdbQueryContext<'a> {
+    pub fn new(reader: &'a heed::RoTxn<MainT>, ctx: &'a Context) -> LmdbQueryContext<'a> {
+        LmdbQueryContext { reader, ctx }
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Hash)]
-pub enum QueryKind {
-    Tolerant(String),
-    NonTolerant(String),
-    Phrase(Vec<String>),
-}
+impl QueryContext for LmdbQueryContext<'_> {
+    type Error = Error;
 
-impl fmt::Debug for Query {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let Query { id, prefix, kind, .. } = self;
-        let prefix = if *prefix { String::from("Prefix") } else { String::default() };
-        match kind {
-            QueryKind::NonTolerant(word) => {
-                f.debug_struct(&(prefix + "NonTolerant")).field("id", &id).field("word", &word).finish()
-            },
-            QueryKind::Tolerant(word) => {
-                f.debug_struct(&(prefix + "Tolerant")).field("id", &id).field("word", &word).finish()
-            },
-            QueryKind::Phrase(words) => {
-                f.debug_struct(&(prefix + "Phrase")).field("id", &id).field("words", &words).finish()
-            },
+    fn is_stop_word(&self, word: &str) -> bool {
+        self.ctx.stop_words.contains(word)
+    }
+
+    fn word_frequency(&self, word: &str) -> MResult<usize> {
+        let frequency = self.ctx.postings_lists
+            .word_stats(self.reader, word.as_bytes())?
+            .map_or(0, |stats| stats.documents as usize);
+        Ok(frequency)
+    }
+
+    fn words_frequencies(&self, words: &[&str]) -> MResult<Vec<usize>> {
+        // the words are looked up once each and in the order of the keys,
+        // the consecutive lookups reading the same pages of the store
+        let mut frequencies: BTreeMap<&str, usize> = words.iter().map(|word| (*word, 0)).collect();
+        for (word, frequency) in &mut frequencies {
+            *frequency = self.word_frequency(word)?;
         }
+
+        Ok(words.iter().map(|word| frequencies[word]).collect())
     }
-}
 
-#[derive(Debug, Default)]
-pub struct PostingsList {
-    docids: SetBuf<DocumentId>,
-    matches: SetBuf<DocIndex>,
-}
+    fn synonyms(&self, words: &[&str]) -> MResult<Vec<Vec<String>>> {
+        let synonyms = match self.ctx.synonyms {
+            Some(synonyms) => synonyms,
+            None => return Ok(Vec::new()),
+        };
 
-pub struct Context {
-    pub words_set: fst::Set,
-    pub stop_words: fst::Set,
-    pub synonyms: store::Synonyms,
-    pub postings_lists: store::PostingsLists,
-    pub prefix_postings_lists: store::PrefixPostingsListsCache,
-}
+        let words = normalize_str(&words.join(" "));
+        let set = synonyms.synonyms(self.reader, words.as_bytes())?.unwrap_or_default();
 
-fn split_best_frequency<'a>(reader: &heed::RoTxn<MainT>, ctx: &Context, word: &'a str) -> MResult<Option<(&'a str, &'a str)>> {
-    let chars = word.char_indices().skip(1);
-    let mut best = None;
-
-    for (i, _) in chars {
-        let (left, right) = word.split_at(i);
-
-        let left_freq = ctx.postings_lists
-            .postings_list(reader, left.as_bytes())?
-            .map(|p| p.docids.len())
-            .unwrap_or(0);
-        let right_freq = ctx.postings_lists
-            .postings_list(reader, right.as_bytes())?
-            .map(|p| p.docids.len())
-            .unwrap_or(0);
-
-        let min_freq = cmp::min(left_freq, right_freq);
-        if min_freq != 0 && best.map_or(true, |(old, _, _)| min_freq > old) {
-            best = Some((min_freq, left, right));
+        let mut strings = Vec::new();
+        let mut stream = set.stream();
+        while let Some(input) = stream.next() {
+            if let Ok(input) = std::str::from_utf8(input) {
+                let alts = input.split_ascii_whitespace().map(ToOwned::to_owned).collect();
+                strings.push(alts);
+            }
         }
+
+        Ok(strings)
     }
 
-    Ok(best.map(|(_, l, r)| (l, r)))
-}
+    fn concatenate_ngrams(&self) -> bool {
+        self.ctx.concatenate_ngrams
+    }
 
-fn fetch_synonyms(reader: &heed::RoTxn<MainT>, ctx: &Context, words: &[&str]) -> MResult<Vec<Vec<String>>> {
-    let words = normalize_str(&words.join(" "));
-    let set = ctx.synonyms.synonyms(reader, words.as_bytes())?.unwrap_or_default();
+    fn split_words(&self) -> bool {
+        self.ctx.split_words
+    }
 
-    let mut strings = Vec::new();
-    let mut stream = set.stream();
-    while let Some(input) = stream.next() {
-        if let Ok(input) = std::str::from_utf8(input) {
-            let alts = input.split_ascii_whitespace().map(ToOwned::to_owned).collect();
-            strings.push(alts);
+    fn keyboard_typos(&self) -> bool {
+        self.ctx.keyboard_typos
+    }
+
+    fn keyboard_layout(&self) -> keyboard::Layout {
+        self.ctx.keyboard_layout
+    }
+
+    fn nearby_numbers(&self, number: u64) -> MResult<Vec<String>> {
+        let range = match self.ctx.numeric_tolerance.and_then(|t| tolerance_range(number, t)) {
+            Some(range) => range,
+            None => return Ok(Vec::new()),
+        };
+
+        // the words starting with numbers of the same number of digits are
+        // sorted like their numbers, the range is scanned per number of digits
+        let mut numbers = Vec::new();
+        let mut scanned = 0;
+        let mut start = *range.start();
+        while start <= *range.end() && scanned < MAX_SCANNED_NUMBERS {
+            let digits = start.to_string().len() as u32;
+            let end = 10u64.checked_pow(digits).map_or(u64::max_value(), |p| p - 1).min(*range.end());
+            let end_string = end.to_string();
+
+            let mut stream = self.ctx.words_set.range().ge(start.to_string()).into_stream();
+            while let Some(word) = stream.next() {
+                if &word[..word.len().min(end_string.len())] > end_string.as_bytes() || scanned >= MAX_SCANNED_NUMBERS {
+                    break;
+                }
+                scanned += 1;
+
+                let word = match std::str::from_utf8(word) {
+                    Ok(word) => word,
+                    Err(_) => continue,
+                };
+                if let Some(n) = leading_number(word).filter(|n| (start..=end).contains(n)) {
+                    numbers.push((n, word.to_string()));
+                }
+            }
+
+            match end.checked_add(1) {
+                Some(next) => start = next,
+                None => break,
+            }
         }
+
+        Ok(closest_numbers(number, numbers))
     }
 
-    Ok(strings)
-}
+    fn normalize_units(&self) -> bool {
+        self.ctx.normalize_units
+    }
 
-fn create_operation<I, F>(iter: I, f: F) -> Operation
-where I: IntoIterator<Item=Operation>,
-      F: Fn(Vec<Operation>) -> Operation,
-{
-    let mut iter = iter.into_iter();
-    match (iter.next(), iter.next()) {
-        (Some(first), None) => first,
-        (first, second) => f(first.into_iter().chain(second).chain(iter).collect()),
+    fn min_prefix_length(&self) -> usize {
+        self.ctx.min_prefix_length
     }
 }
 
-const MAX_NGRAM: usize = 3;
+/// Returns the words of the query that are used to build its query tree,
+/// lowercased and without the stop words.
+pub fn query_words(reader: &heed::RoTxn<MainT>, ctx: &Context, query: &str) -> Vec<String> {
+    meilisearch_query::query_words(&LmdbQueryContext::new(reader, ctx), query)
+}
+
+/// Returns the beginning of the query holding its first `words` query words.
+pub fn query_prefix<'q>(reader: &heed::RoTxn<MainT>, ctx: &Context, query: &'q str, words: usize) -> &'q str {
+    meilisearch_query::query_prefix(&LmdbQueryContext::new(reader, ctx), query, words)
+}
 
 pub fn create_query_tree(
     reader: &heed::RoTxn<MainT>,
@@ -180,111 +266,107 @@ pub fn create_query_tree(
     query: &str,
 ) -> MResult<(Operation, HashMap<QueryId, Range<usize>>)>
 {
-    let words = split_query_string(query).map(str::to_lowercase);
-    let words = words.filter(|w| !ctx.stop_words.contains(w));
-    let words: Vec<_> = words.enumerate().collect();
+    meilisearch_query::create_query_tree(&LmdbQueryContext::new(reader, ctx), query)
+}
 
-    let mut mapper = QueryWordsMapper::new(words.iter().map(|(_, w)| w));
+/// The query words that are not indexed and are searched as the two words they are made of.
+pub fn query_segmentations(reader: &heed::RoTxn<MainT>, ctx: &Context, words: &[String]) -> MResult<Vec<QuerySplit>> {
+    if !ctx.split_words {
+        return Ok(Vec::new());
+    }
 
-    fn create_inner(
-        reader: &heed::RoTxn<MainT>,
-        ctx: &Context,
-        mapper: &mut QueryWordsMapper,
-        words: &[(usize, String)],
-    ) -> MResult<Vec<Operation>>
-    {
-        let mut alts = Vec::new();
+    let lmdb_ctx = LmdbQueryContext::new(reader, ctx);
+    let mut segmentations = Vec::new();
+    for word in words {
+        if let Some((left, right)) = segment_word(&lmdb_ctx, word)? {
+            let split = (left.to_string(), right.to_string());
+            segmentations.push(QuerySplit { word: word.clone(), split });
+        }
+    }
 
-        for ngram in 1..=MAX_NGRAM {
-            if let Some(group) = words.get(..ngram) {
-                let mut group_ops = Vec::new();
-
-                let tail = &words[ngram..];
-                let is_last = tail.is_empty();
-
-                let mut group_alts = Vec::new();
-                match group {
-                    [(id, word)] => {
-                        let mut idgen = ((id + 1) * 100)..;
-                        let range = (*id)..id+1;
-
-                        let phrase = split_best_frequency(reader, ctx, word)?
-                            .map(|ws| {
-                                let id = idgen.next().unwrap();
-                                idgen.next().unwrap();
-                                mapper.declare(range.clone(), id, &[ws.0, ws.1]);
-                                Operation::phrase2(id, is_last, ws)
-                            });
-
-                        let synonyms = fetch_synonyms(reader, ctx, &[word])?
-                            .into_iter()
-                            .map(|alts| {
-                                let exact = alts.len() == 1;
-                                let id = idgen.next().unwrap();
-                                mapper.declare(range.clone(), id, &alts);
-
-                                let mut idgen = once(id).chain(&mut idgen);
-                                let iter = alts.into_iter().map(|w| {
-                                    let id = idgen.next().unwrap();
-                                    let kind = QueryKind::NonTolerant(w);
-                                    Operation::Query(Query { id, prefix: false, exact, kind })
-                                });
-
-                                create_operation(iter, Operation::And)
-                            });
-
-                        let original = Operation::tolerant(*id, is_last, word);
-
-                        group_alts.push(original);
-                        group_alts.extend(synonyms.chain(phrase));
-                    },
-                    words => {
-                        let id = words[0].0;
-                        let mut idgen = ((id + 1) * 100_usize.pow(ngram as u32))..;
-                        let range = id..id+ngram;
-
-                        let words: Vec<_> = words.iter().map(|(_, s)| s.as_str()).collect();
-
-                        for synonym in fetch_synonyms(reader, ctx, &words)? {
-                            let exact = synonym.len() == 1;
-                            let id = idgen.next().unwrap();
-                            mapper.declare(range.clone(), id, &synonym);
-
-                            let mut idgen = once(id).chain(&mut idgen);
-                            let synonym = synonym.into_iter().map(|s| {
-                                let id = idgen.next().unwrap();
-                                let kind = QueryKind::NonTolerant(s);
-                                Operation::Query(Query { id, prefix: false, exact, kind })
-                            });
-                            group_alts.push(create_operation(synonym, Operation::And));
-                        }
+    Ok(segmentations)
+}
 
-                        let id = idgen.next().unwrap();
-                        let concat = words.concat();
-                        mapper.declare(range.clone(), id, &[&concat]);
-                        group_alts.push(Operation::non_tolerant(id, is_last, &concat));
-                    }
-                }
+/// Consecutive words of a query that are also searched concatenated.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryNgram {
+    pub words: Vec<String>,
+    pub concatenation: String,
+    /// Whether the concatenation is a word of the index.
+    pub indexed: bool,
+}
 
-                group_ops.push(create_operation(group_alts, Operation::Or));
+/// A word of the query also searched as the two words that, split this way, are the most frequent.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySplit {
+    pub word: String,
+    pub split: (String, String),
+}
 
-                if !tail.is_empty() {
-                    let tail_ops = create_inner(reader, ctx, mapper, tail)?;
-                    group_ops.push(create_operation(tail_ops, Operation::Or));
-                }
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySynonyms {
+    pub words: Vec<String>,
+    pub synonyms: Vec<Vec<String>>,
+}
 
-                alts.push(create_operation(group_ops, Operation::And));
+/// What a query string is turned into before being searched, the query tree is pretty-printed.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryAnalysis {
+    pub tokens: Vec<String>,
+    pub words: Vec<String>,
+    pub ngrams: Vec<QueryNgram>,
+    pub splits: Vec<QuerySplit>,
+    pub synonyms: Vec<QuerySynonyms>,
+    pub query_tree: String,
+}
+
+/// Analyzes the query as `create_query_tree` does, without traversing the tree.
+pub fn analyze_query(reader: &heed::RoTxn<MainT>, ctx: &Context, query: &str) -> MResult<QueryAnalysis> {
+    let query = &ctx.normalize_query(query);
+    let lmdb_ctx = LmdbQueryContext::new(reader, ctx);
+    let tokens = split_query_string(query).map(ToOwned::to_owned).collect();
+    let words = meilisearch_query::query_words(&lmdb_ctx, query);
+
+    let mut ngrams = Vec::new();
+    let mut splits = Vec::new();
+    let mut synonyms = Vec::new();
+
+    for start in 0..words.len() {
+        for ngram in 1..=MAX_NGRAM {
+            let group = match words.get(start..start + ngram) {
+                Some(group) => group,
+                None => break,
+            };
+            let group: Vec<_> = group.iter().map(String::as_str).collect();
+
+            let group_synonyms = lmdb_ctx.synonyms(&group)?;
+            if !group_synonyms.is_empty() {
+                let words = group.iter().map(|w| w.to_string()).collect();
+                synonyms.push(QuerySynonyms { words, synonyms: group_synonyms });
             }
-        }
 
-        Ok(alts)
+            if ngram == 1 {
+                if let Some((left, right)) = split_best_frequency(&lmdb_ctx, group[0])? {
+                    let split = (left.to_string(), right.to_string());
+                    splits.push(QuerySplit { word: group[0].to_string(), split });
+                }
+            } else if ctx.concatenate_ngrams {
+                let concatenation = group.concat();
+                let indexed = ctx.words_set.contains(&concatenation);
+                let words = group.iter().map(|w| w.to_string()).collect();
+                ngrams.push(QueryNgram { words, concatenation, indexed });
+            }
+        }
     }
 
-    let alternatives = create_inner(reader, ctx, &mut mapper, &words)?;
-    let operation = Operation::Or(alternatives);
-    let mapping = mapper.mapping();
+    let (operation, _mapping) = meilisearch_query::create_query_tree(&lmdb_ctx, query)?;
+    let query_tree = format!("{:?}", operation);
 
-    Ok((operation, mapping))
+    Ok(QueryAnalysis { tokens, words, ngrams, splits, synonyms, query_tree })
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -293,9 +375,14 @@ pub struct PostingsKey<'o> {
     pub input: Vec<u8>,
     pub distance: u8,
     pub is_exact: bool,
+    /// Whether the input is the word of the query with two adjacent characters swapped.
+    pub is_transposition: bool,
 }
 
 pub type Postings<'o, 'txn> = HashMap<PostingsKey<'o>, Cow<'txn, Set<DocIndex>>>;
+/// The documents ids of the operations that have already been executed, the queries
+/// are equal when their words are, whatever their ids, a word present in many of the
+/// n-grams alternatives is only searched once.
 pub type Cache<'o, 'txn> = HashMap<&'o Operation, Cow<'txn, Set<DocumentId>>>;
 
 pub struct QueryResult<'o, 'txn> {
@@ -303,12 +390,61 @@ pub struct QueryResult<'o, 'txn> {
     pub queries: Postings<'o, 'txn>,
 }
 
+/// Estimates the number of documents an operation matches from the counts of the words
+/// it searches exactly, the other operations are estimated to match all the documents.
+fn estimated_documents(reader: &heed::RoTxn<MainT>, ctx: &Context, op: &Operation) -> MResult<u64> {
+    let documents = |word: &str| -> MResult<u64> {
+        let stats = ctx.postings_lists.word_stats(reader, word.as_bytes())?;
+        Ok(stats.map_or(0, |stats| stats.documents))
+    };
+
+    match op {
+        Operation::Query(Query { prefix: false, kind: QueryKind::NonTolerant(word), .. }) => documents(word),
+        Operation::Query(Query { kind: QueryKind::Phrase(words), .. }) => {
+            let mut estimation = u64::max_value();
+            for word in words {
+                estimation = estimation.min(documents(word)?);
+            }
+            Ok(estimation)
+        },
+        _ => Ok(u64::max_value()),
+    }
+}
+
 pub fn traverse_query_tree<'o, 'txn>(
     reader: &'txn heed::RoTxn<MainT>,
     ctx: &Context,
     tree: &'o Operation,
 ) -> MResult<QueryResult<'o, 'txn>>
 {
+    /// Computes the documents ids of an operation if they are not already known.
+    fn execute_operation<'o, 'txn>(
+        reader: &'txn heed::RoTxn<MainT>,
+        ctx: &Context,
+        cache: &mut Cache<'o, 'txn>,
+        postings: &mut Postings<'o, 'txn>,
+        depth: usize,
+        op: &'o Operation,
+    ) -> MResult<()>
+    {
+        CancellationToken::check(ctx.cancellation.as_ref())?;
+
+        if cache.contains_key(op) {
+            return Ok(());
+        }
+
+        let docids = match op {
+            Operation::Query(query) => execute_query(reader, ctx, postings, depth, &query)?,
+            Operation::And(ops) => execute_and(reader, ctx, cache, postings, depth, &ops)?,
+            Operation::Or(ops) => execute_or(reader, ctx, cache, postings, depth, &ops)?,
+            // the negation is applied by the parent operation
+            Operation::Not(op) => return execute_operation(reader, ctx, cache, postings, depth, op),
+        };
+        cache.insert(op, docids);
+
+        Ok(())
+    }
+
     fn execute_and<'o, 'txn>(
         reader: &'txn heed::RoTxn<MainT>,
         ctx: &Context,
@@ -323,25 +459,38 @@ pub fn traverse_query_tree<'o, 'txn>(
         let before = Instant::now();
         let mut results = Vec::new();
 
+        // the rarest operations are executed first, once one of them doesn't match
+        // any document the others are not executed, the intersection is empty anyway
+        let mut ordered = Vec::with_capacity(operations.len());
         for op in operations {
-            if cache.get(op).is_none() {
-                let docids = match op {
-                    Operation::And(ops) => execute_and(reader, ctx, cache, postings, depth + 1, &ops)?,
-                    Operation::Or(ops) => execute_or(reader, ctx, cache, postings, depth + 1, &ops)?,
-                    Operation::Query(query) => execute_query(reader, ctx, postings, depth + 1, &query)?,
-                };
-                cache.insert(op, docids);
+            ordered.push((estimated_documents(reader, ctx, op)?, op));
+        }
+        ordered.sort_by_key(|(estimation, _)| *estimation);
+
+        for (_, op) in ordered {
+            execute_operation(reader, ctx, cache, postings, depth + 1, op)?;
+            if let Operation::Not(_) = op {
+                continue;
+            }
+            if cache.get(op).map_or(false, |docids| docids.is_empty()) {
+                break;
             }
         }
 
+        let mut excluded = Vec::new();
         for op in operations {
-            if let Some(docids) = cache.get(op) {
-                results.push(docids.as_ref());
+            match op {
+                Operation::Not(op) => excluded.extend(cache.get(op).map(AsRef::as_ref)),
+                op => results.extend(cache.get(op).map(AsRef::as_ref)),
             }
         }
 
-        let op = sdset::multi::Intersection::new(results);
-        let docids = op.into_set_buf();
+        let mut docids = intersection(results);
+
+        if !excluded.is_empty() {
+            let excluded = sdset::multi::Union::new(excluded).into_set_buf();
+            docids = sdset::duo::Difference::new(&docids, &excluded).into_set_buf();
+        }
 
         debug!("{:3$}--- AND fetched {} documents in {:.02?}", "", docids.len(), before.elapsed(), depth * 2);
 
@@ -363,22 +512,22 @@ pub fn traverse_query_tree<'o, 'txn>(
         let mut results = Vec::new();
 
         for op in operations {
-            if cache.get(op).is_none() {
-                let docids = match op {
-                    Operation::And(ops) => execute_and(reader, ctx, cache, postings, depth + 1, &ops)?,
-                    Operation::Or(ops) => execute_or(reader, ctx, cache, postings, depth + 1, &ops)?,
-                    Operation::Query(query) => execute_query(reader, ctx, postings, depth + 1, &query)?,
-                };
-                cache.insert(op, docids);
-            }
+            execute_operation(reader, ctx, cache, postings, depth + 1, op)?;
         }
 
+        // the negations match the documents that don't match their operation
+        let mut complements = Vec::new();
         for op in operations {
-            if let Some(docids) = cache.get(op) {
-                results.push(docids.as_ref());
+            match op {
+                Operation::Not(op) => {
+                    let excluded = cache.get(&**op).map_or(Set::new_unchecked(&[]), AsRef::as_ref);
+                    complements.push(complement(reader, ctx, excluded)?);
+                }
+                op => results.extend(cache.get(op).map(AsRef::as_ref)),
             }
         }
 
+        results.extend(complements.iter().map(AsRef::as_ref));
         let op = sdset::multi::Union::new(results);
         let docids = op.into_set_buf();
 
@@ -411,14 +560,16 @@ pub fn traverse_query_tree<'o, 'txn>(
                     // We retrieve the cached postings lists for all
                     // the words that starts with this short prefix.
                     let result = ctx.prefix_postings_lists.prefix_postings_list(reader, prefix)?.unwrap_or_default();
-                    let key = PostingsKey { query, input: word.clone().into_bytes(), distance: 0, is_exact: false };
+                    let input = word.clone().into_bytes();
+                    let key = PostingsKey { query, input, distance: 0, is_exact: false, is_transposition: false };
                     postings.insert(key, result.matches);
                     let prefix_docids = &result.docids;
 
                     // We retrieve the exact postings list for the prefix,
                     // because we must consider these matches as exact.
                     let result = ctx.postings_lists.postings_list(reader, word.as_bytes())?.unwrap_or_default();
-                    let key = PostingsKey { query, input: word.clone().into_bytes(), distance: 0, is_exact: true };
+                    let input = word.clone().into_bytes();
+                    let key = PostingsKey { query, input, distance: 0, is_exact: true, is_transposition: false };
                     postings.insert(key, result.matches);
                     let exact_docids = &result.docids;
 
@@ -429,25 +580,83 @@ pub fn traverse_query_tree<'o, 'txn>(
 
                     Cow::Owned(docids)
 
+                } else if tolerates_only_transpositions(word) {
+                    // the short words only match as they are or with two adjacent characters
+                    // swapped, these few candidates are looked up rather than searched with a DFA
+                    let candidates = once((word.clone(), 0)).chain(transpositions(word).into_iter().map(|w| (w, 1)));
+
+                    let before = Instant::now();
+                    let mut results = Vec::new();
+                    for (candidate, distance) in candidates {
+                        let is_transposition = distance == 1;
+                        if !*prefix {
+                            if let Some(result) = ctx.postings_lists.postings_list(reader, candidate.as_bytes())? {
+                                let is_exact = *exact && distance == 0;
+                                results.push(result.docids);
+                                let input = candidate.into_bytes();
+                                let key = PostingsKey { query, input, distance, is_exact, is_transposition };
+                                postings.insert(key, result.matches);
+                            }
+                            continue;
+                        }
+
+                        let dfa = build_exact_prefix_dfa(&candidate);
+                        let byte = candidate.as_bytes()[0];
+                        let mut stream = if byte == u8::max_value() {
+                            ctx.words_set.search(&dfa).ge(&[byte]).into_stream()
+                        } else {
+                            ctx.words_set.search(&dfa).ge(&[byte]).lt(&[byte + 1]).into_stream()
+                        };
+
+                        while let Some(input) = stream.next() {
+                            if let Some(result) = ctx.postings_lists.postings_list(reader, input)? {
+                                let is_exact = *exact && distance == 0 && input.len() == word.len();
+                                results.push(result.docids);
+                                let input = input.to_owned();
+                                let key = PostingsKey { query, input, distance, is_exact, is_transposition };
+                                postings.insert(key, result.matches);
+                            }
+                        }
+                    }
+                    debug!("{:3$}docids retrieval ({:?}) took {:.02?}", "", results.len(), before.elapsed(), depth * 2);
+
+                    let sets = results.iter().map(AsRef::as_ref).collect();
+                    Cow::Owned(sdset::multi::Union::new(sets).into_set_buf())
                 } else {
                     let dfa = if *prefix { build_prefix_dfa(word) } else { build_dfa(word) };
 
-                    let byte = word.as_bytes()[0];
-                    let mut stream = if byte == u8::max_value() {
-                        ctx.words_set.search(&dfa).ge(&[byte]).into_stream()
-                    } else {
-                        ctx.words_set.search(&dfa).ge(&[byte]).lt(&[byte + 1]).into_stream()
-                    };
+                    // the words are searched among the ones starting like the query, and among the
+                    // ones starting with its second character, where the first two are transposed
+                    let mut bytes = vec![word.as_bytes()[0]];
+                    if let Some(second) = word.char_indices().nth(1).map(|(i, _)| word.as_bytes()[i]) {
+                        if second != bytes[0] {
+                            bytes.push(second);
+                        }
+                    }
 
                     let before = Instant::now();
                     let mut results = Vec::new();
-                    while let Some(input) = stream.next() {
-                        if let Some(result) = ctx.postings_lists.postings_list(reader, input)? {
+                    for (i, byte) in bytes.into_iter().enumerate() {
+                        let mut stream = if byte == u8::max_value() {
+                            ctx.words_set.search(&dfa).ge(&[byte]).into_stream()
+                        } else {
+                            ctx.words_set.search(&dfa).ge(&[byte]).lt(&[byte + 1]).into_stream()
+                        };
+
+                        while let Some(input) = stream.next() {
                             let distance = dfa.eval(input).to_u8();
-                            let is_exact = *exact && distance == 0 && input.len() == word.len();
-                            results.push(result.docids);
-                            let key = PostingsKey { query, input: input.to_owned(), distance, is_exact };
-                            postings.insert(key, result.matches);
+                            let is_transposition = distance == 1 && is_transposition(word, input, *prefix);
+                            if i != 0 && distance != 0 && !is_transposition {
+                                continue;
+                            }
+
+                            if let Some(result) = ctx.postings_lists.postings_list(reader, input)? {
+                                let is_exact = *exact && distance == 0 && input.len() == word.len();
+                                results.push(result.docids);
+                                let input = input.to_owned();
+                                let key = PostingsKey { query, input, distance, is_exact, is_transposition };
+                                postings.insert(key, result.matches);
+                            }
                         }
                     }
                     debug!("{:3$}docids retrieval ({:?}) took {:.02?}", "", results.len(), before.elapsed(), depth * 2);
@@ -469,9 +678,11 @@ pub fn traverse_query_tree<'o, 'txn>(
                     Cow::Owned(docids)
                 }
             },
-            QueryKind::NonTolerant(word) => {
+            QueryKind::NonTolerant(word) | QueryKind::Correction(word) => {
                 // TODO support prefix and non-prefix exact DFA
                 let dfa = build_exact_dfa(word);
+                // a keyboard correction is searched exactly but counts as one typo
+                let typos = if let QueryKind::Correction(_) = kind { 1 } else { 0 };
 
                 let byte = word.as_bytes()[0];
                 let mut stream = if byte == u8::max_value() {
@@ -484,9 +695,10 @@ pub fn traverse_query_tree<'o, 'txn>(
                 let mut results = Vec::new();
                 while let Some(input) = stream.next() {
                     if let Some(result) = ctx.postings_lists.postings_list(reader, input)? {
-                        let distance = dfa.eval(input).to_u8();
+                        let distance = dfa.eval(input).to_u8() + typos;
                         results.push(result.docids);
-                        let key = PostingsKey { query, input: input.to_owned(), distance, is_exact: *exact };
+                        let input = input.to_owned();
+                        let key = PostingsKey { query, input, distance, is_exact: *exact, is_transposition: false };
                         postings.insert(key, result.matches);
                     }
                 }
@@ -520,11 +732,53 @@ pub fn traverse_query_tree<'o, 'txn>(
                         x.cmp(&y)
                     });
 
-                    let matches: Vec<_> = iter
+                    let mut matches: Vec<_> = iter
                         .filter_map(EitherOrBoth::both)
                         .flat_map(|(a, b)| once(*a).chain(Some(*b)))
                         .collect();
 
+                    if !ctx.adjacent_attributes.is_empty() {
+                        let len = matches.len();
+                        for &(first_attr, second_attr) in &ctx.adjacent_attributes {
+                            let seconds: Vec<_> = second.matches.iter()
+                                .filter(|m| m.attribute == second_attr && m.word_index == 0)
+                                .collect();
+
+                            // the field count is read once per document having both attributes
+                            for group in first.matches.linear_group_by_key(|m| m.document_id) {
+                                let document_id = group[0].document_id;
+                                let b = match seconds.binary_search_by_key(&document_id, |b| b.document_id) {
+                                    Ok(i) => seconds[i],
+                                    Err(_) => continue,
+                                };
+
+                                let mut candidates = group.iter().filter(|m| m.attribute == first_attr).peekable();
+                                if candidates.peek().is_none() {
+                                    continue;
+                                }
+
+                                // the word index of the last word is its position when the
+                                // attribute doesn't contain hard separators, like names
+                                let count = ctx.documents_fields_counts
+                                    .document_field_count(reader, document_id, IndexedPos(first_attr))?;
+                                let count = match count {
+                                    Some(count) => u32::from(count),
+                                    None => continue,
+                                };
+
+                                for a in candidates.filter(|a| a.word_index as u32 + 1 == count) {
+                                    matches.push(*a);
+                                    matches.push(*b);
+                                }
+                            }
+                        }
+
+                        if matches.len() != len {
+                            matches.sort_unstable();
+                            matches.dedup();
+                        }
+                    }
+
                     let before = Instant::now();
                     let mut docids: Vec<_> = matches.iter().map(|m| m.document_id).collect();
                     docids.dedup();
@@ -532,7 +786,7 @@ pub fn traverse_query_tree<'o, 'txn>(
                     debug!("{:2$}docids construction took {:.02?}", "", before.elapsed(), depth * 2);
 
                     let matches = Cow::Owned(SetBuf::from_dirty(matches));
-                    let key = PostingsKey { query, input: vec![], distance: 0, is_exact: true };
+                    let key = PostingsKey { query, input: vec![], distance: 0, is_exact: true, is_transposition: false };
                     postings.insert(key, matches);
 
                     Cow::Owned(docids)
@@ -547,6 +801,22 @@ pub fn traverse_query_tree<'o, 'txn>(
         Ok(docids)
     }
 
+    /// The documents with indexed attributes that are not in `excluded`.
+    fn complement(
+        reader: &heed::RoTxn<MainT>,
+        ctx: &Context,
+        excluded: &Set<DocumentId>,
+    ) -> MResult<SetBuf<DocumentId>> {
+        let mut docids = Vec::new();
+        for result in ctx.documents_fields_counts.documents_ids(reader)? {
+            let docid = result?;
+            if excluded.binary_search(&docid).is_err() {
+                docids.push(docid);
+            }
+        }
+        Ok(SetBuf::new_unchecked(docids))
+    }
+
     let mut cache = Cache::new();
     let mut postings = Postings::new();
 
@@ -554,7 +824,113 @@ pub fn traverse_query_tree<'o, 'txn>(
         Operation::And(ops) => execute_and(reader, ctx, &mut cache, &mut postings, 0, &ops)?,
         Operation::Or(ops) => execute_or(reader, ctx, &mut cache, &mut postings, 0, &ops)?,
         Operation::Query(query) => execute_query(reader, ctx, &mut postings, 0, &query)?,
+        Operation::Not(op) => {
+            execute_operation(reader, ctx, &mut cache, &mut postings, 0, op)?;
+            let excluded = cache.get(&**op).map_or(Set::new_unchecked(&[]), AsRef::as_ref);
+            Cow::Owned(complement(reader, ctx, excluded)?)
+        }
     };
 
     Ok(QueryResult { docids, queries: postings })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::mpsc;
+
+    use crate::settings::Settings;
+    use crate::store::WordStats;
+    use crate::update::ProcessedUpdateResult;
+    use crate::{Database, DatabaseOptions, QueryTreeBuilder, Schema};
+
+    #[test]
+    fn built_query_tree() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let database = Database::open_or_create(dir.path(), DatabaseOptions::default()).unwrap();
+        let db = &database;
+
+        let (sender, receiver) = mpsc::sync_channel(100);
+        let update_fn = move |_name: &str, update: ProcessedUpdateResult| {
+            sender.send(update.update_id).unwrap()
+        };
+        let index = database.create_index("test").unwrap();
+
+        database.set_update_callback(Box::new(update_fn));
+
+        let mut writer = db.main_write_txn().unwrap();
+        index.main.put_schema(&mut writer, &Schema::with_primary_key("id")).unwrap();
+        writer.commit().unwrap();
+
+        let settings: Settings = serde_json::from_str(r#"{ "searchableAttributes": ["name"] }"#).unwrap();
+        let mut update_writer = db.update_write_txn().unwrap();
+        index.settings_update(&mut update_writer, settings.into_update().unwrap()).unwrap();
+        update_writer.commit().unwrap();
+
+        let mut additions = index.documents_addition();
+        additions.update_document(serde_json::json!({ "id": 1, "name": "red apple" }));
+        additions.update_document(serde_json::json!({ "id": 2, "name": "green apple" }));
+        additions.update_document(serde_json::json!({ "id": 3, "name": "red cherry" }));
+
+        let mut update_writer = db.update_write_txn().unwrap();
+        let update_id = additions.finalize(&mut update_writer).unwrap();
+        update_writer.commit().unwrap();
+
+        // block until the transaction is processed
+        let _ = receiver.into_iter().find(|id| *id == update_id);
+
+        let reader = db.main_read_txn().unwrap();
+        let ctx = Context::new(&reader, &index).unwrap();
+
+        let document_ids = |tree: &Operation| {
+            let QueryResult { docids, .. } = traverse_query_tree(&reader, &ctx, tree).unwrap();
+            docids
+                .iter()
+                .map(|id| {
+                    let document: serde_json::Value = index.document(&reader, None, *id).unwrap().unwrap();
+                    document["id"].clone()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut builder = QueryTreeBuilder::new();
+        let apple_not_green = QueryTreeBuilder::and(vec![
+            builder.non_tolerant("apple", false),
+            QueryTreeBuilder::not(builder.non_tolerant("green", false)),
+        ]);
+        assert_eq!(document_ids(&apple_not_green), vec![serde_json::json!(1)]);
+
+        let red_phrase = QueryTreeBuilder::or(vec![
+            builder.phrase("red", "cherry"),
+            builder.phrase("green", "apple"),
+        ]);
+        let mut ids = document_ids(&red_phrase);
+        ids.sort_by_key(|id| id.as_u64());
+        assert_eq!(ids, vec![serde_json::json!(2), serde_json::json!(3)]);
+
+        let stats = index.postings_lists.word_stats(&reader, b"apple").unwrap();
+        assert_eq!(stats, Some(WordStats { documents: 2, occurrences: 2 }));
+
+        // the word missing from the index stops the intersection
+        let apple_banana = QueryTreeBuilder::and(vec![
+            builder.tolerant("apple", false),
+            builder.non_tolerant("banana", false),
+        ]);
+        assert!(document_ids(&apple_banana).is_empty());
+
+        // a negation nested under an OR matches the documents of the index without the word
+        let cherry_or_not_red = QueryTreeBuilder::or(vec![
+            builder.non_tolerant("cherry", false),
+            QueryTreeBuilder::not(builder.non_tolerant("red", false)),
+        ]);
+        let mut ids = document_ids(&cherry_or_not_red);
+        ids.sort_by_key(|id| id.as_u64());
+        assert_eq!(ids, vec![serde_json::json!(2), serde_json::json!(3)]);
+
+        // as well as a negation alone
+        let not_apple = QueryTreeBuilder::not(builder.non_tolerant("apple", false));
+        assert_eq!(document_ids(&not_apple), vec![serde_json::json!(3)]);
+    }
+}
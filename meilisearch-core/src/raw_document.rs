@@ -8,7 +8,7 @@ pub struct RawDocument<'a, 'tag> {
     pub id: crate::DocumentId,
     pub bare_matches: &'a mut [BareMatch<'tag>],
     pub processed_matches: Vec<SimpleMatch>,
-    /// The list of minimum `distance` found
+    /// The minimum typo cost found for each query word, in half typos
     pub processed_distances: Vec<Option<u8>>,
     /// Does this document contains a field
     /// with one word that is exactly matching
@@ -3,17 +3,23 @@ use std::convert::TryFrom;
 
 use crate::{DocIndex, DocumentId};
 use deunicode::deunicode_with_tofu;
+use meilisearch_query::units::{normalize_quantity, normalize_word};
 use meilisearch_schema::IndexedPos;
 use meilisearch_tokenizer::{is_cjk, SeqTokenizer, Token, Tokenizer};
 use sdset::SetBuf;
 
 const WORD_LENGTH_LIMIT: usize = 80;
 
+/// The number of words of an attribute indexed when no limit is set.
+pub const DEFAULT_WORD_LIMIT: usize = 1000;
+
 type Word = Vec<u8>; // TODO make it be a SmallVec
 
 pub struct RawIndexer {
     word_limit: usize, // the maximum number of indexed words
+    attribute_word_limits: HashMap<IndexedPos, usize>,
     stop_words: fst::Set,
+    normalize_units: bool,
     words_doc_indexes: BTreeMap<Word, Vec<DocIndex>>,
     docs_words: HashMap<DocumentId, Vec<Word>>,
 }
@@ -25,32 +31,57 @@ pub struct Indexed {
 
 impl RawIndexer {
     pub fn new(stop_words: fst::Set) -> RawIndexer {
-        RawIndexer::with_word_limit(stop_words, 1000)
+        RawIndexer::with_word_limit(stop_words, DEFAULT_WORD_LIMIT)
     }
 
     pub fn with_word_limit(stop_words: fst::Set, limit: usize) -> RawIndexer {
         RawIndexer {
             word_limit: limit,
+            attribute_word_limits: HashMap::new(),
             stop_words,
+            normalize_units: false,
             words_doc_indexes: BTreeMap::new(),
             docs_words: HashMap::new(),
         }
     }
 
+    /// Also indexes the quantities written with a unit under
+    /// their canonical word, at the position of their number.
+    pub fn set_normalize_units(&mut self, normalize_units: bool) {
+        self.normalize_units = normalize_units;
+    }
+
+    /// Limits the number of words indexed in an attribute, the words that follow are
+    /// stored but not indexed. The other attributes keep the limit of the indexer.
+    pub fn set_attribute_word_limit(&mut self, indexed_pos: IndexedPos, limit: usize) {
+        self.attribute_word_limits.insert(indexed_pos, limit);
+    }
+
+    fn word_limit(&self, indexed_pos: IndexedPos) -> usize {
+        self.attribute_word_limits.get(&indexed_pos).copied().unwrap_or(self.word_limit)
+    }
+
     pub fn index_text(&mut self, id: DocumentId, indexed_pos: IndexedPos, text: &str) -> usize {
         let mut number_of_words = 0;
+        let mut previous = None;
+        let word_limit = self.word_limit(indexed_pos);
 
         for token in Tokenizer::new(text) {
             let must_continue = index_token(
                 token,
                 id,
                 indexed_pos,
-                self.word_limit,
+                word_limit,
                 &self.stop_words,
                 &mut self.words_doc_indexes,
                 &mut self.docs_words,
             );
 
+            if self.normalize_units && must_continue {
+                self.index_quantity(id, indexed_pos, previous, token);
+            }
+
+            previous = Some(token);
             number_of_words += 1;
 
             if !must_continue {
@@ -66,23 +97,66 @@ impl RawIndexer {
         I: IntoIterator<Item = &'a str>,
     {
         let iter = iter.into_iter();
+        let mut previous = None;
+        let word_limit = self.word_limit(indexed_pos);
+
         for token in SeqTokenizer::new(iter) {
             let must_continue = index_token(
                 token,
                 id,
                 indexed_pos,
-                self.word_limit,
+                word_limit,
                 &self.stop_words,
                 &mut self.words_doc_indexes,
                 &mut self.docs_words,
             );
 
+            if self.normalize_units && must_continue {
+                self.index_quantity(id, indexed_pos, previous, token);
+            }
+
+            previous = Some(token);
+
             if !must_continue {
                 break;
             }
         }
     }
 
+    fn index_quantity(&mut self, id: DocumentId, indexed_pos: IndexedPos, previous: Option<Token>, token: Token) {
+        // a number directly followed by its unit, like "5 kg"
+        let spaced = previous
+            .filter(|p| p.word_index + 1 == token.word_index)
+            .and_then(|p| normalize_quantity(p.word, token.word).map(|word| (p, word)));
+
+        let (start, word) = match spaced {
+            Some(quantity) => quantity,
+            None => match normalize_word(token.word) {
+                Some(word) => (token, word),
+                None => return,
+            },
+        };
+
+        let char_end = token.char_index + token.word.chars().count();
+        let docindex = match (u16::try_from(start.word_index), u16::try_from(start.char_index)) {
+            (Ok(word_index), Ok(char_index)) => DocIndex {
+                document_id: id,
+                attribute: indexed_pos.0,
+                word_index,
+                char_index,
+                char_length: u16::try_from(char_end - start.char_index).unwrap_or(u16::max_value()),
+            },
+            _ => return,
+        };
+
+        let word = Vec::from(word);
+        self.words_doc_indexes
+            .entry(word.clone())
+            .or_insert_with(Vec::new)
+            .push(docindex);
+        self.docs_words.entry(id).or_insert_with(Vec::new).push(word);
+    }
+
     pub fn build(self) -> Indexed {
         let words_doc_indexes = self
             .words_doc_indexes
@@ -225,6 +299,29 @@ mod tests {
             .is_some());
     }
 
+    #[test]
+    fn attribute_word_limit() {
+        let mut indexer = RawIndexer::new(fst::Set::default());
+        indexer.set_attribute_word_limit(IndexedPos(1), 2);
+
+        let docid = DocumentId(0);
+        let text = "the first words of the body";
+        indexer.index_text(docid, IndexedPos(0), "title words");
+        indexer.index_text(docid, IndexedPos(1), text);
+
+        let Indexed {
+            words_doc_indexes, ..
+        } = indexer.build();
+
+        let attributes = |word: &str| -> Vec<u16> {
+            let indexes = words_doc_indexes.get(word.as_bytes());
+            indexes.map(|indexes| indexes.iter().map(|i| i.attribute).collect()).unwrap_or_default()
+        };
+        assert_eq!(attributes("first"), vec![1]);
+        assert_eq!(attributes("words"), vec![0]);
+        assert!(attributes("body").is_empty());
+    }
+
     #[test]
     fn basic_stop_words() {
         let stop_words = sdset::SetBuf::from_dirty(vec!["l", "j", "ai", "de"]);
@@ -269,4 +366,25 @@ mod tests {
             .get(&"🇯🇵".to_owned().into_bytes())
             .is_some());
     }
+
+    #[test]
+    fn normalized_units() {
+        let mut indexer = RawIndexer::new(fst::Set::default());
+        indexer.set_normalize_units(true);
+
+        let docid = DocumentId(0);
+        let indexed_pos = IndexedPos(0);
+        indexer.index_text(docid, indexed_pos, "bag of rice 5 kg");
+        indexer.index_text(DocumentId(1), indexed_pos, "bag of rice 5000g");
+
+        let Indexed {
+            words_doc_indexes, ..
+        } = indexer.build();
+
+        let indexes = words_doc_indexes.get(&b"5000000mg"[..]).unwrap();
+        assert_eq!(indexes.len(), 2);
+        assert_eq!(indexes[0].word_index, 3);
+        assert_eq!(indexes[0].char_length, 4);
+        assert!(words_doc_indexes.get(&b"kg"[..]).is_some());
+    }
 }
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::io::Cursor;
 use std::{error::Error, fmt};
@@ -80,9 +81,24 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut Deserializer<'a> {
     {
         let mut error = None;
 
-        let iter = self
-            .documents_fields
-            .document_fields(self.reader, self.document_id)?
+        // the requested fields are read by key, the other fields of the document are not read
+        let fields: Box<dyn Iterator<Item = heed::Result<(FieldId, Cow<'a, [u8]>)>> + 'a> = match self.fields {
+            Some(fields) => {
+                let (reader, document_id, documents_fields) = (self.reader, self.document_id, self.documents_fields);
+                let mut fields: Vec<_> = fields.iter().copied().collect();
+                fields.sort_unstable();
+                Box::new(fields.into_iter().filter_map(move |field| {
+                    match documents_fields.document_attribute(reader, document_id, field) {
+                        Ok(Some(value)) => Some(Ok((field, value))),
+                        Ok(None) => None,
+                        Err(e) => Some(Err(e)),
+                    }
+                }))
+            }
+            None => Box::new(self.documents_fields.document_fields(self.reader, self.document_id)?),
+        };
+
+        let iter = fields
             .filter_map(|result| {
                 let (attr, value) = match result {
                     Ok(value) => value,
@@ -93,9 +109,9 @@ impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut Deserializer<'a> {
                 };
 
                 let is_displayed = self.schema.is_displayed(attr);
-                if is_displayed && self.fields.map_or(true, |f| f.contains(&attr)) {
+                if is_displayed {
                     if let Some(attribute_name) = self.schema.name(attr) {
-                        let cursor = Cursor::new(value.to_owned());
+                        let cursor = Cursor::new(value.into_owned());
                         let ioread = SerdeJsonIoRead::new(cursor);
                         let value = Value(SerdeJsonDeserializer::new(ioread));
 
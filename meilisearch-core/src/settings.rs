@@ -1,19 +1,20 @@
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::str::FromStr;
 use std::iter::IntoIterator;
+use std::ops::Range;
 
+use chrono::{DateTime, NaiveDate};
+use meilisearch_query::keyboard;
 use serde::{Deserialize, Deserializer, Serialize};
-use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use crate::criterion::RankingExpr;
+use crate::facets::geo_point;
 
 use self::RankingRule::*;
 
 pub const DEFAULT_RANKING_RULES: [RankingRule; 6] = [Typo, Words, Proximity, Attribute, WordsPosition, Exactness];
 
-static RANKING_RULE_REGEX: Lazy<regex::Regex> = Lazy::new(|| {
-    let regex = regex::Regex::new(r"(asc|desc)\(([a-zA-Z0-9-_]*)\)").unwrap();
-    regex
-});
-
 #[derive(Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Settings {
@@ -25,7 +26,7 @@ pub struct Settings {
     pub searchable_attributes: Option<Option<Vec<String>>>,
     #[serde(default, deserialize_with = "deserialize_some")]
     pub displayed_attributes: Option<Option<HashSet<String>>>,
-    #[serde(default, deserialize_with = "deserialize_some")]
+    #[serde(default, deserialize_with = "deserialize_stop_words")]
     pub stop_words: Option<Option<BTreeSet<String>>>,
     #[serde(default, deserialize_with = "deserialize_some")]
     pub synonyms: Option<Option<BTreeMap<String, Vec<String>>>>,
@@ -33,6 +34,34 @@ pub struct Settings {
     pub accept_new_fields: Option<Option<bool>>,
     #[serde(default, deserialize_with = "deserialize_some")]
     pub attributes_for_faceting: Option<Option<Vec<String>>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub search_cutoff_ms: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub attribute_weights: Option<Option<BTreeMap<String, u32>>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub adjacent_attributes: Option<Option<Vec<(String, String)>>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub normalize_units: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub keyboard_typos: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub keyboard_layout: Option<Option<KeyboardLayout>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub optional_words: Option<Option<bool>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub numeric_tolerance: Option<Option<f64>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub field_types: Option<Option<BTreeMap<String, FieldType>>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub excluded_attributes: Option<Option<BTreeSet<String>>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub max_indexed_words: Option<Option<BTreeMap<String, usize>>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub min_prefix_length: Option<Option<usize>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub query_normalization: Option<Option<QueryNormalization>>,
+    #[serde(default, deserialize_with = "deserialize_some", skip_serializing_if = "Option::is_none")]
+    pub attribute_aliases: Option<Option<BTreeMap<String, String>>>,
 }
 
 // Any value that is present is considered Some value, including null.
@@ -43,6 +72,71 @@ fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
     Deserialize::deserialize(deserializer).map(Some)
 }
 
+/// The stop words lists shipped for the most common languages.
+const STOP_WORDS_PRESETS: &[(&str, &str)] = &[
+    ("de", include_str!("../../misc/de.stopwords.txt")),
+    ("en", include_str!("../../misc/en.stopwords.txt")),
+    ("es", include_str!("../../misc/es.stopwords.txt")),
+    ("fr", include_str!("../../misc/fr.stopwords.txt")),
+];
+
+/// Returns the stop words shipped for a language, identified by its ISO 639-1 code.
+pub fn stop_words_preset(language: &str) -> Option<BTreeSet<String>> {
+    STOP_WORDS_PRESETS
+        .iter()
+        .find(|(name, _)| *name == language)
+        .map(|(_, words)| words.lines().map(str::trim).filter(|w| !w.is_empty()).map(String::from).collect())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct StopWordsPreset {
+    preset: String,
+    #[serde(default)]
+    add: BTreeSet<String>,
+    #[serde(default)]
+    remove: BTreeSet<String>,
+}
+
+/// The stop words as sent, either a list of words or a preset with additions and removals.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum StopWordsSetting {
+    Words(BTreeSet<String>),
+    Preset(StopWordsPreset),
+}
+
+impl StopWordsSetting {
+    /// The stop words of the setting, an error is returned if the preset is unknown.
+    pub fn into_words(self) -> Result<BTreeSet<String>, String> {
+        match self {
+            StopWordsSetting::Words(words) => Ok(words),
+            StopWordsSetting::Preset(StopWordsPreset { preset, add, remove }) => {
+                let mut words = stop_words_preset(&preset).ok_or_else(|| {
+                    let names: Vec<_> = STOP_WORDS_PRESETS.iter().map(|(name, _)| *name).collect();
+                    format!("unknown stop words preset {:?}, expected one of {}", preset, names.join(", "))
+                })?;
+                words.extend(add);
+                words.retain(|w| !remove.contains(w));
+                Ok(words)
+            }
+        }
+    }
+}
+
+fn deserialize_stop_words<'de, D>(deserializer: D) -> Result<Option<Option<BTreeSet<String>>>, D::Error>
+    where D: Deserializer<'de>
+{
+    use serde::de::Error;
+
+    let stop_words = match Option::<StopWordsSetting>::deserialize(deserializer)? {
+        Some(setting) => Some(setting.into_words().map_err(D::Error::custom)?),
+        None => None,
+    };
+
+    Ok(Some(stop_words))
+}
+
 impl Settings {
     pub fn into_update(&self) -> Result<SettingsUpdate, RankingRuleConversionError> {
         let settings = self.clone();
@@ -63,6 +157,52 @@ impl Settings {
             synonyms: settings.synonyms.into(),
             accept_new_fields: settings.accept_new_fields.into(),
             attributes_for_faceting: settings.attributes_for_faceting.into(),
+            search_cutoff_ms: settings.search_cutoff_ms.into(),
+            attribute_weights: settings.attribute_weights.into(),
+            adjacent_attributes: settings.adjacent_attributes.into(),
+            normalize_units: settings.normalize_units.into(),
+            keyboard_typos: settings.keyboard_typos.into(),
+            keyboard_layout: settings.keyboard_layout.into(),
+            optional_words: settings.optional_words.into(),
+            numeric_tolerance: settings.numeric_tolerance.into(),
+            field_types: settings.field_types.into(),
+            excluded_attributes: settings.excluded_attributes.into(),
+            max_indexed_words: settings.max_indexed_words.into(),
+            min_prefix_length: settings.min_prefix_length.into(),
+            query_normalization: settings.query_normalization.into(),
+            attribute_aliases: settings.attribute_aliases.into(),
+        })
+    }
+
+    /// Converts the settings into an update replacing all of them,
+    /// the settings that are not given are reset to their default value.
+    pub fn into_replacement_update(&self) -> Result<SettingsUpdate, RankingRuleConversionError> {
+        let update = self.into_update()?;
+
+        Ok(SettingsUpdate {
+            ranking_rules: update.ranking_rules.or_clear(),
+            distinct_attribute: update.distinct_attribute.or_clear(),
+            primary_key: update.primary_key,
+            searchable_attributes: update.searchable_attributes.or_clear(),
+            displayed_attributes: update.displayed_attributes.or_clear(),
+            stop_words: update.stop_words.or_clear(),
+            synonyms: update.synonyms.or_clear(),
+            accept_new_fields: update.accept_new_fields.or_clear(),
+            attributes_for_faceting: update.attributes_for_faceting.or_clear(),
+            search_cutoff_ms: update.search_cutoff_ms.or_clear(),
+            attribute_weights: update.attribute_weights.or_clear(),
+            adjacent_attributes: update.adjacent_attributes.or_clear(),
+            normalize_units: update.normalize_units.or_clear(),
+            keyboard_typos: update.keyboard_typos.or_clear(),
+            keyboard_layout: update.keyboard_layout.or_clear(),
+            optional_words: update.optional_words.or_clear(),
+            numeric_tolerance: update.numeric_tolerance.or_clear(),
+            field_types: update.field_types.or_clear(),
+            excluded_attributes: update.excluded_attributes.or_clear(),
+            max_indexed_words: update.max_indexed_words.or_clear(),
+            min_prefix_length: update.min_prefix_length.or_clear(),
+            query_normalization: update.query_normalization.or_clear(),
+            attribute_aliases: update.attribute_aliases.or_clear(),
         })
     }
 }
@@ -74,6 +214,24 @@ pub enum UpdateState<T> {
     Nothing,
 }
 
+impl<T> UpdateState<T> {
+    fn or_clear(self) -> UpdateState<T> {
+        match self {
+            UpdateState::Nothing => UpdateState::Clear,
+            state => state,
+        }
+    }
+
+    fn merge(&self, next: &UpdateState<T>) -> Option<UpdateState<T>>
+    where T: Clone,
+    {
+        match (self, next) {
+            (UpdateState::Nothing, state) | (state, UpdateState::Nothing) => Some(state.clone()),
+            _ => None,
+        }
+    }
+}
+
 impl <T> From<Option<Option<T>>> for UpdateState<T> {
     fn from(opt: Option<Option<T>>) -> UpdateState<T> {
         match opt {
@@ -84,15 +242,84 @@ impl <T> From<Option<Option<T>>> for UpdateState<T> {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct RankingRuleConversionError;
+/// An invalid ranking rule, `start` and `end` delimit the faulty characters of the rule.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankingRuleConversionError {
+    /// The position of the rule in the ranking rules list.
+    pub index: usize,
+    pub rule: String,
+    pub start: usize,
+    pub end: usize,
+    pub reason: String,
+    pub suggestion: Option<String>,
+}
+
+impl RankingRuleConversionError {
+    fn new(rule: &str, bytes: Range<usize>, reason: impl Into<String>) -> RankingRuleConversionError {
+        RankingRuleConversionError {
+            index: 0,
+            rule: rule.to_string(),
+            start: rule[..bytes.start].chars().count(),
+            end: rule[..bytes.end].chars().count(),
+            reason: reason.into(),
+            suggestion: None,
+        }
+    }
+
+    fn with_suggestion(mut self, suggestion: Option<String>) -> RankingRuleConversionError {
+        self.suggestion = suggestion;
+        self
+    }
+}
 
 impl std::fmt::Display for RankingRuleConversionError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "impossible to convert into RankingRule")
+        write!(
+            f,
+            "invalid ranking rule {:?} at index {} (characters {} to {}): {}",
+            self.rule, self.index, self.start, self.end, self.reason,
+        )?;
+        match &self.suggestion {
+            Some(suggestion) => write!(f, "; did you mean {:?}?", suggestion),
+            None => Ok(()),
+        }
     }
 }
 
+const RULES_NAMES: [&str; 6] = ["typo", "words", "proximity", "attribute", "wordsPosition", "exactness"];
+
+/// The classic Levenshtein distance, computed on the lowercased strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<_> = a.to_lowercase().chars().collect();
+    let b: Vec<_> = b.to_lowercase().chars().collect();
+
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == cb { previous } else { 1 + previous.min(row[j]).min(current) };
+            previous = current;
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Returns the candidate closest to `s` if it is close enough to be a typo,
+/// candidates starting with the same letter as `s` are preferred.
+fn closest<'a>(s: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let first = s.chars().next();
+    candidates
+        .iter()
+        .map(|c| (edit_distance(s, c), *c))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, c)| (*distance, c.chars().next() != first))
+        .map(|(_, c)| c)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RankingRule {
     Typo,
@@ -103,6 +330,16 @@ pub enum RankingRule {
     Exactness,
     Asc(String),
     Desc(String),
+    /// Like `Asc`, the documents missing the attribute come first instead of last.
+    AscNullsFirst(String),
+    /// Like `Desc`, the documents missing the attribute come first instead of last.
+    DescNullsFirst(String),
+    /// The documents with the highest score computed by the expression come first.
+    Expr(String),
+    /// Like `Asc`, the place of the documents missing the attribute is written explicitly.
+    AscNullsLast(String),
+    /// Like `Desc`, the place of the documents missing the attribute is written explicitly.
+    DescNullsLast(String),
 }
 
 impl std::fmt::Display for RankingRule {
@@ -116,6 +353,11 @@ impl std::fmt::Display for RankingRule {
             RankingRule::Exactness => f.write_str("exactness"),
             RankingRule::Asc(field) => write!(f, "asc({})", field),
             RankingRule::Desc(field) => write!(f, "desc({})", field),
+            RankingRule::AscNullsFirst(field) => write!(f, "asc({}) nulls first", field),
+            RankingRule::DescNullsFirst(field) => write!(f, "desc({}) nulls first", field),
+            RankingRule::Expr(expr) => write!(f, "expr({})", expr),
+            RankingRule::AscNullsLast(field) => write!(f, "asc({}) nulls last", field),
+            RankingRule::DescNullsLast(field) => write!(f, "desc({}) nulls last", field),
         }
     }
 }
@@ -124,6 +366,19 @@ impl FromStr for RankingRule {
     type Err = RankingRuleConversionError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        type Error = RankingRuleConversionError;
+
+        // the place of the documents missing the attribute, e.g. "asc(price) nulls first"
+        let original = s;
+        let lowercase = s.to_ascii_lowercase();
+        let (s, nulls_first) = if lowercase.ends_with(" nulls first") {
+            (s[..s.len() - " nulls first".len()].trim_end(), Some(true))
+        } else if lowercase.ends_with(" nulls last") {
+            (s[..s.len() - " nulls last".len()].trim_end(), Some(false))
+        } else {
+            (s, None)
+        };
+
         let rule = match s {
             "typo" => RankingRule::Typo,
             "words" => RankingRule::Words,
@@ -131,34 +386,257 @@ impl FromStr for RankingRule {
             "attribute" => RankingRule::Attribute,
             "wordsPosition" => RankingRule::WordsPosition,
             "exactness" => RankingRule::Exactness,
+            _ if s.starts_with("expr(") => {
+                let open = "expr(".len();
+                if !s.ends_with(')') {
+                    return Err(Error::new(s, open - 1..s.len(), "missing closing parenthesis"));
+                }
+                let expr = &s[open..s.len() - 1];
+                if let Err(e) = RankingExpr::parse(expr) {
+                    let range = open + e.range.start..open + e.range.end;
+                    return Err(Error::new(s, range, e.reason));
+                }
+                RankingRule::Expr(expr.trim().to_string())
+            }
             _ => {
-                let captures = RANKING_RULE_REGEX.captures(s).ok_or(RankingRuleConversionError)?;
-                match (captures.get(1).map(|m| m.as_str()), captures.get(2)) {
-                    (Some("asc"), Some(field)) => RankingRule::Asc(field.as_str().to_string()),
-                    (Some("desc"), Some(field)) => RankingRule::Desc(field.as_str().to_string()),
-                    _ => return Err(RankingRuleConversionError)
+                let open = match s.find('(') {
+                    Some(open) => open,
+                    None => {
+                        let suggestion = closest(s, &RULES_NAMES).map(ToString::to_string);
+                        let error = Error::new(s, 0..s.len(), "unknown ranking rule");
+                        return Err(error.with_suggestion(suggestion));
+                    }
+                };
+
+                let (order, rest) = (&s[..open], &s[open + 1..]);
+                let field = match rest.len().checked_sub(1).filter(|&end| rest.ends_with(')')) {
+                    Some(end) => &rest[..end],
+                    None => {
+                        let error = Error::new(s, open..s.len(), "missing closing parenthesis");
+                        let suggestion = if rest.contains(')') { None } else { Some(format!("{})", s)) };
+                        return Err(error.with_suggestion(suggestion));
+                    }
+                };
+
+                let field_range = open + 1..s.len() - 1;
+                if field.is_empty() {
+                    return Err(Error::new(s, field_range, "missing attribute name"));
+                }
+                if let Some(pos) = field.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_')) {
+                    let start = field_range.start + pos;
+                    let end = start + field[pos..].chars().next().map_or(1, char::len_utf8);
+                    return Err(Error::new(s, start..end, "invalid character in attribute name"));
+                }
+
+                match order {
+                    "asc" => RankingRule::Asc(field.to_string()),
+                    "desc" => RankingRule::Desc(field.to_string()),
+                    _ => {
+                        let suggestion = closest(order, &["asc", "desc"]).map(|o| format!("{}({})", o, field));
+                        let error = Error::new(s, 0..open, "the order must be asc or desc");
+                        return Err(error.with_suggestion(suggestion));
+                    }
                 }
             }
         };
-        Ok(rule)
+
+        match (rule, nulls_first) {
+            (RankingRule::Asc(field), Some(true)) => Ok(RankingRule::AscNullsFirst(field)),
+            (RankingRule::Desc(field), Some(true)) => Ok(RankingRule::DescNullsFirst(field)),
+            (RankingRule::Asc(field), Some(false)) => Ok(RankingRule::AscNullsLast(field)),
+            (RankingRule::Desc(field), Some(false)) => Ok(RankingRule::DescNullsLast(field)),
+            (rule, None) => Ok(rule),
+            (_, Some(_)) => {
+                let range = s.len()..original.len();
+                Err(Error::new(original, range, "only the asc and desc rules accept a nulls modifier"))
+            }
+        }
     }
 }
 
 impl RankingRule {
     pub fn field(&self) -> Option<&str> {
         match self {
-            RankingRule::Asc(field) | RankingRule::Desc(field) => Some(field),
+            RankingRule::Asc(field)
+            | RankingRule::Desc(field)
+            | RankingRule::AscNullsFirst(field)
+            | RankingRule::DescNullsFirst(field)
+            | RankingRule::AscNullsLast(field)
+            | RankingRule::DescNullsLast(field) => Some(field),
             _ => None,
         }
     }
 
+    /// Whether the rule sorts the documents by the values of their attributes.
+    pub fn is_sort_rule(&self) -> bool {
+        match self {
+            RankingRule::Asc(_)
+            | RankingRule::Desc(_)
+            | RankingRule::AscNullsFirst(_)
+            | RankingRule::DescNullsFirst(_)
+            | RankingRule::AscNullsLast(_)
+            | RankingRule::DescNullsLast(_)
+            | RankingRule::Expr(_) => true,
+            _ => false,
+        }
+    }
+
+    /// The attributes the rule sorts the documents by.
+    pub fn fields(&self) -> Vec<String> {
+        match self {
+            RankingRule::Expr(expr) => match RankingExpr::parse(expr) {
+                Ok(expr) => expr.attributes().into_iter().map(ToString::to_string).collect(),
+                Err(_) => Vec::new(),
+            },
+            rule => rule.field().map(ToString::to_string).into_iter().collect(),
+        }
+    }
+
     pub fn from_iter(rules: impl IntoIterator<Item = impl AsRef<str>>) -> Result<Vec<RankingRule>, RankingRuleConversionError> {
         rules.into_iter()
-            .map(|s| RankingRule::from_str(s.as_ref()))
+            .enumerate()
+            .map(|(index, s)| RankingRule::from_str(s.as_ref()).map_err(|e| RankingRuleConversionError { index, ..e }))
             .collect()
     }
 }
 
+/// The type declared for an attribute, the documents whose value
+/// of the attribute is of another type are rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FieldType {
+    String,
+    Number,
+    /// A string holding an RFC 3339 date, with or without its time.
+    Date,
+    /// An object holding `lat` and `lng` coordinates.
+    Geo,
+    Bool,
+    Array,
+}
+
+impl FieldType {
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldType::String => "string",
+            FieldType::Number => "number",
+            FieldType::Date => "date",
+            FieldType::Geo => "geo",
+            FieldType::Bool => "bool",
+            FieldType::Array => "array",
+        }
+    }
+
+    /// Whether the value is of this type, null values are accepted whatever the type.
+    pub fn accepts(self, value: &Value) -> bool {
+        match (self, value) {
+            (_, Value::Null) => true,
+            (FieldType::String, Value::String(_)) => true,
+            (FieldType::Number, Value::Number(_)) => true,
+            (FieldType::Date, Value::String(s)) => {
+                DateTime::parse_from_rfc3339(s).is_ok() || NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok()
+            },
+            (FieldType::Geo, Value::Object(object)) => geo_point(object).is_some(),
+            (FieldType::Bool, Value::Bool(_)) => true,
+            (FieldType::Array, Value::Array(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The layout of the keyboard the typos of the short query words are corrected for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyboardLayout {
+    Qwerty,
+    Azerty,
+    Qwertz,
+}
+
+impl From<KeyboardLayout> for keyboard::Layout {
+    fn from(layout: KeyboardLayout) -> keyboard::Layout {
+        match layout {
+            KeyboardLayout::Qwerty => keyboard::Layout::Qwerty,
+            KeyboardLayout::Azerty => keyboard::Layout::Azerty,
+            KeyboardLayout::Qwertz => keyboard::Layout::Qwertz,
+        }
+    }
+}
+
+/// The rewrites applied to the query strings before their query trees are built,
+/// the words of the documents are indexed as they are.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct QueryNormalization {
+    /// Removes the punctuation and the symbols of the words, `U.S.A.` is searched as `USA`,
+    /// the double quotes of the phrases are kept.
+    #[serde(default)]
+    pub strip_punctuation: bool,
+    /// Collapses the letters repeated three times or more, `sooooo` is searched as `so`,
+    /// the digits are kept as they are.
+    #[serde(default)]
+    pub collapse_repeated_characters: bool,
+    /// The words replaced by other ones, e.g. the abbreviations by what they stand for,
+    /// the words are compared case-insensitively and stored lowercased.
+    #[serde(default)]
+    pub rewrites: BTreeMap<String, String>,
+}
+
+impl QueryNormalization {
+    /// Lowercases the words to rewrite, once when the setting is applied
+    /// rather than for every word of every query.
+    pub fn lowercase_rewrites(mut self) -> QueryNormalization {
+        self.rewrites = self.rewrites.into_iter().map(|(from, to)| (from.to_lowercase(), to)).collect();
+        self
+    }
+
+    pub fn normalize(&self, query: &str) -> String {
+        let mut normalized = Vec::new();
+
+        for word in query.split_whitespace() {
+            let mut word = word.to_string();
+            if self.strip_punctuation {
+                word.retain(|c| c.is_alphanumeric() || c == '"');
+            }
+            if self.collapse_repeated_characters {
+                word = collapse_repeated_characters(&word);
+            }
+            if word.is_empty() {
+                continue;
+            }
+
+            match self.rewrites.get(&word.to_lowercase()) {
+                Some(to) => normalized.push(to.clone()),
+                None => normalized.push(word),
+            }
+        }
+
+        normalized.join(" ")
+    }
+}
+
+fn collapse_repeated_characters(word: &str) -> String {
+    let chars: Vec<char> = word.chars().collect();
+    let mut collapsed = String::with_capacity(word.len());
+    let mut start = 0;
+
+    while start < chars.len() {
+        let c = chars[start];
+        let len = chars[start..].iter().take_while(|x| **x == c).count();
+        let kept = if len >= 3 && c.is_alphabetic() { 1 } else { len };
+        collapsed.extend(std::iter::repeat(c).take(kept));
+        start += len;
+    }
+
+    collapsed
+}
+
+/// Returns the attribute named by the alias, or the name itself when it is not an alias,
+/// the aliases let the queries name the attributes without exposing their internal names.
+pub fn resolve_attribute_alias<'a>(aliases: &'a BTreeMap<String, String>, name: &'a str) -> &'a str {
+    aliases.get(name).map_or(name, String::as_str)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SettingsUpdate {
     pub ranking_rules: UpdateState<Vec<RankingRule>>,
@@ -170,6 +648,27 @@ pub struct SettingsUpdate {
     pub synonyms: UpdateState<BTreeMap<String, Vec<String>>>,
     pub accept_new_fields: UpdateState<bool>,
     pub attributes_for_faceting: UpdateState<Vec<String>>,
+    pub search_cutoff_ms: UpdateState<u64>,
+    pub attribute_weights: UpdateState<BTreeMap<String, u32>>,
+    pub adjacent_attributes: UpdateState<Vec<(String, String)>>,
+    pub normalize_units: UpdateState<bool>,
+    /// Whether the short query words are also searched with a letter replaced by an adjacent key.
+    pub keyboard_typos: UpdateState<bool>,
+    /// The layout of the keyboard the adjacent keys are found on, QWERTY by default.
+    pub keyboard_layout: UpdateState<KeyboardLayout>,
+    /// Whether the last query words are ignored when no document contains all of them.
+    pub optional_words: UpdateState<bool>,
+    /// The relative distance to the query numbers of the indexed numbers they also match.
+    pub numeric_tolerance: UpdateState<f64>,
+    pub field_types: UpdateState<BTreeMap<String, FieldType>>,
+    pub excluded_attributes: UpdateState<BTreeSet<String>>,
+    /// The number of words indexed per attribute, `*` sets the limit of the attributes not listed.
+    pub max_indexed_words: UpdateState<BTreeMap<String, usize>>,
+    /// The number of characters the last query word must have to be searched as a prefix.
+    pub min_prefix_length: UpdateState<usize>,
+    pub query_normalization: UpdateState<QueryNormalization>,
+    /// The public names of the attributes, given to the filters in place of their names.
+    pub attribute_aliases: UpdateState<BTreeMap<String, String>>,
 }
 
 impl Default for SettingsUpdate {
@@ -184,6 +683,72 @@ impl Default for SettingsUpdate {
             synonyms: UpdateState::Nothing,
             accept_new_fields: UpdateState::Nothing,
             attributes_for_faceting: UpdateState::Nothing,
+            search_cutoff_ms: UpdateState::Nothing,
+            attribute_weights: UpdateState::Nothing,
+            adjacent_attributes: UpdateState::Nothing,
+            normalize_units: UpdateState::Nothing,
+            keyboard_typos: UpdateState::Nothing,
+            keyboard_layout: UpdateState::Nothing,
+            optional_words: UpdateState::Nothing,
+            numeric_tolerance: UpdateState::Nothing,
+            field_types: UpdateState::Nothing,
+            excluded_attributes: UpdateState::Nothing,
+            max_indexed_words: UpdateState::Nothing,
+            min_prefix_length: UpdateState::Nothing,
+            query_normalization: UpdateState::Nothing,
+            attribute_aliases: UpdateState::Nothing,
         }
     }
 }
+
+impl SettingsUpdate {
+    /// Merges `self` with `next`, the update enqueued right after it, into a single update.
+    /// Returns `None` when both updates change the same setting.
+    pub fn merge(&self, next: &SettingsUpdate) -> Option<SettingsUpdate> {
+        Some(SettingsUpdate {
+            ranking_rules: self.ranking_rules.merge(&next.ranking_rules)?,
+            distinct_attribute: self.distinct_attribute.merge(&next.distinct_attribute)?,
+            primary_key: self.primary_key.merge(&next.primary_key)?,
+            searchable_attributes: self.searchable_attributes.merge(&next.searchable_attributes)?,
+            displayed_attributes: self.displayed_attributes.merge(&next.displayed_attributes)?,
+            stop_words: self.stop_words.merge(&next.stop_words)?,
+            synonyms: self.synonyms.merge(&next.synonyms)?,
+            accept_new_fields: self.accept_new_fields.merge(&next.accept_new_fields)?,
+            attributes_for_faceting: self.attributes_for_faceting.merge(&next.attributes_for_faceting)?,
+            search_cutoff_ms: self.search_cutoff_ms.merge(&next.search_cutoff_ms)?,
+            attribute_weights: self.attribute_weights.merge(&next.attribute_weights)?,
+            adjacent_attributes: self.adjacent_attributes.merge(&next.adjacent_attributes)?,
+            normalize_units: self.normalize_units.merge(&next.normalize_units)?,
+            keyboard_typos: self.keyboard_typos.merge(&next.keyboard_typos)?,
+            keyboard_layout: self.keyboard_layout.merge(&next.keyboard_layout)?,
+            optional_words: self.optional_words.merge(&next.optional_words)?,
+            numeric_tolerance: self.numeric_tolerance.merge(&next.numeric_tolerance)?,
+            field_types: self.field_types.merge(&next.field_types)?,
+            excluded_attributes: self.excluded_attributes.merge(&next.excluded_attributes)?,
+            max_indexed_words: self.max_indexed_words.merge(&next.max_indexed_words)?,
+            min_prefix_length: self.min_prefix_length.merge(&next.min_prefix_length)?,
+            query_normalization: self.query_normalization.merge(&next.query_normalization)?,
+            attribute_aliases: self.attribute_aliases.merge(&next.attribute_aliases)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_queries() {
+        let normalization = QueryNormalization {
+            strip_punctuation: true,
+            collapse_repeated_characters: true,
+            rewrites: vec![("STL".to_string(), "saint louis".to_string())].into_iter().collect(),
+        };
+        let normalization = normalization.lowercase_rewrites();
+
+        assert_eq!(normalization.normalize("sooooo U.S.A.!"), "so USA");
+        // the quotes of the phrases and the repeated digits are kept
+        assert_eq!(normalization.normalize("\"new york\" 1000"), "\"new york\" 1000");
+        assert_eq!(normalization.normalize("Stl"), "saint louis");
+    }
+}
@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use heed::types::{ByteSlice, OwnedType};
+use heed::Result as ZResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::BEU64;
+use crate::database::MainT;
+use crate::encryption::{open_json, seal_json, Cipher};
+
+/// A documents mutation that has been applied to an index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub update_id: u64,
+    pub applied_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+}
+
+/// The changes only hold the primary key values of the documents, the documents
+/// added or updated are read from the index when the changes are read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ChangeKind {
+    DocumentsAddition { documents: Vec<Value> },
+    DocumentsPartial { documents: Vec<Value> },
+    DocumentsDeletion { documents: Vec<Value> },
+    ClearAll,
+}
+
+/// The change data capture log of an index.
+///
+/// Changes are written in the same transaction as the update that produced them,
+/// every change is identified by a sequence number that is strictly increasing.
+/// The oldest changes are removed once the log holds more than its retention.
+#[derive(Copy, Clone)]
+pub struct Changes {
+    pub(crate) changes: heed::Database<OwnedType<BEU64>, ByteSlice>,
+    /// The changes hold the documents, they are encrypted like them.
+    pub(crate) cipher: Option<&'static Cipher>,
+}
+
+impl Changes {
+    pub fn last_sequence(self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<u64>> {
+        match self.changes.last(reader)? {
+            Some((key, _)) => Ok(Some(key.get())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn first_sequence(self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<u64>> {
+        match self.changes.first(reader)? {
+            Some((key, _)) => Ok(Some(key.get())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn push_change(self, writer: &mut heed::RwTxn<MainT>, change: &Change) -> ZResult<u64> {
+        let sequence = self.last_sequence(writer)?.map_or(0, |n| n + 1);
+        let value = seal_json(self.cipher, &sequence.to_be_bytes(), change)?;
+        self.changes.put(writer, &BEU64::new(sequence), &value)?;
+        Ok(sequence)
+    }
+
+    /// Returns at most `limit` changes with a sequence number greater or equal to `from`.
+    pub fn changes(
+        self,
+        reader: &heed::RoTxn<MainT>,
+        from: u64,
+        limit: usize,
+    ) -> ZResult<Vec<(u64, Change)>> {
+        let start = BEU64::new(from);
+        let end = BEU64::new(u64::max_value());
+
+        let mut changes = Vec::new();
+        for result in self.changes.range(reader, &(start..=end))?.take(limit) {
+            let (key, bytes) = result?;
+            let sequence = key.get();
+            changes.push((sequence, open_json(self.cipher, &sequence.to_be_bytes(), bytes)?));
+        }
+
+        Ok(changes)
+    }
+
+    /// Removes the oldest changes to only keep the `retention` last ones, returns the number of changes removed.
+    ///
+    /// The last change is always kept, the next sequence number is computed from it
+    /// and the consumers resuming from it must never be given a reused one.
+    pub fn trim(self, writer: &mut heed::RwTxn<MainT>, retention: u64) -> ZResult<usize> {
+        let retention = retention.max(1);
+        let (first, last) = match (self.first_sequence(writer)?, self.last_sequence(writer)?) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(0),
+        };
+
+        if last - first < retention {
+            return Ok(0);
+        }
+
+        let start = BEU64::new(first);
+        let end = BEU64::new(last.saturating_sub(retention));
+        self.changes.delete_range(writer, &(start..=end))
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<()> {
+        self.changes.clear(writer)
+    }
+}
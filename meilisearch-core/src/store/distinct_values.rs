@@ -0,0 +1,38 @@
+use heed::types::OwnedType;
+use heed::Result as ZResult;
+
+use super::BEU64;
+use crate::database::MainT;
+use crate::DocumentId;
+
+/// The hash of the distinct attribute value of every document that has one.
+#[derive(Copy, Clone)]
+pub struct DistinctValues {
+    pub(crate) distinct_values: heed::Database<OwnedType<BEU64>, OwnedType<BEU64>>,
+}
+
+impl DistinctValues {
+    pub fn put_distinct_value(
+        self,
+        writer: &mut heed::RwTxn<MainT>,
+        document_id: DocumentId,
+        hash: u64,
+    ) -> ZResult<()> {
+        let document_id = BEU64::new(document_id.0);
+        self.distinct_values.put(writer, &document_id, &BEU64::new(hash))
+    }
+
+    pub fn del_distinct_value(self, writer: &mut heed::RwTxn<MainT>, document_id: DocumentId) -> ZResult<bool> {
+        let document_id = BEU64::new(document_id.0);
+        self.distinct_values.delete(writer, &document_id)
+    }
+
+    pub fn distinct_value(self, reader: &heed::RoTxn<MainT>, document_id: DocumentId) -> ZResult<Option<u64>> {
+        let document_id = BEU64::new(document_id.0);
+        Ok(self.distinct_values.get(reader, &document_id)?.map(|hash| hash.get()))
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<()> {
+        self.distinct_values.clear(writer)
+    }
+}
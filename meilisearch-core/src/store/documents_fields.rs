@@ -1,14 +1,34 @@
+use std::borrow::Cow;
+
 use heed::types::{ByteSlice, OwnedType};
 use crate::database::MainT;
 use heed::Result as ZResult;
 use meilisearch_schema::FieldId;
+use zerocopy::AsBytes;
 
 use super::DocumentFieldStoredKey;
+use crate::encryption::Cipher;
 use crate::DocumentId;
 
 #[derive(Copy, Clone)]
 pub struct DocumentsFields {
     pub(crate) documents_fields: heed::Database<OwnedType<DocumentFieldStoredKey>, ByteSlice>,
+    /// The cipher of the values if the database is encrypted, bound to their keys.
+    pub(crate) cipher: Option<&'static Cipher>,
+}
+
+fn decrypt<'txn>(
+    cipher: Option<&Cipher>,
+    key: &DocumentFieldStoredKey,
+    bytes: &'txn [u8],
+) -> ZResult<Cow<'txn, [u8]>> {
+    match cipher {
+        Some(cipher) => match cipher.decrypt(key.as_bytes(), bytes) {
+            Some(bytes) => Ok(Cow::Owned(bytes)),
+            None => Err(heed::Error::Decoding),
+        },
+        None => Ok(Cow::Borrowed(bytes)),
+    }
 }
 
 impl DocumentsFields {
@@ -20,7 +40,13 @@ impl DocumentsFields {
         value: &[u8],
     ) -> ZResult<()> {
         let key = DocumentFieldStoredKey::new(document_id, field);
-        self.documents_fields.put(writer, &key, value)
+        match self.cipher {
+            Some(cipher) => {
+                let value = cipher.encrypt(key.as_bytes(), value).ok_or(heed::Error::Encoding)?;
+                self.documents_fields.put(writer, &key, &value)
+            }
+            None => self.documents_fields.put(writer, &key, value),
+        }
     }
 
     pub fn del_all_document_fields(
@@ -42,9 +68,12 @@ impl DocumentsFields {
         reader: &'txn heed::RoTxn<MainT>,
         document_id: DocumentId,
         field: FieldId,
-    ) -> ZResult<Option<&'txn [u8]>> {
+    ) -> ZResult<Option<Cow<'txn, [u8]>>> {
         let key = DocumentFieldStoredKey::new(document_id, field);
-        self.documents_fields.get(reader, &key)
+        match self.documents_fields.get(reader, &key)? {
+            Some(bytes) => decrypt(self.cipher, &key, bytes).map(Some),
+            None => Ok(None),
+        }
     }
 
     pub fn document_fields<'txn>(
@@ -55,22 +84,23 @@ impl DocumentsFields {
         let start = DocumentFieldStoredKey::new(document_id, FieldId::min());
         let end = DocumentFieldStoredKey::new(document_id, FieldId::max());
         let iter = self.documents_fields.range(reader, &(start..=end))?;
-        Ok(DocumentFieldsIter { iter })
+        Ok(DocumentFieldsIter { iter, cipher: self.cipher })
     }
 }
 
 pub struct DocumentFieldsIter<'txn> {
     iter: heed::RoRange<'txn, OwnedType<DocumentFieldStoredKey>, ByteSlice>,
+    cipher: Option<&'static Cipher>,
 }
 
 impl<'txn> Iterator for DocumentFieldsIter<'txn> {
-    type Item = ZResult<(FieldId, &'txn [u8])>;
+    type Item = ZResult<(FieldId, Cow<'txn, [u8]>)>;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
             Some(Ok((key, bytes))) => {
                 let field_id = FieldId(key.field_id.get());
-                Some(Ok((field_id, bytes)))
+                Some(decrypt(self.cipher, &key, bytes).map(|bytes| (field_id, bytes)))
             }
             Some(Err(e)) => Some(Err(e)),
             None => None,
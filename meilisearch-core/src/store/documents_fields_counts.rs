@@ -61,7 +61,17 @@ impl DocumentsFieldsCounts {
     }
 
     pub fn documents_ids<'txn>(self, reader: &'txn heed::RoTxn<MainT>) -> ZResult<DocumentsIdsIter<'txn>> {
-        let iter = self.documents_fields_counts.iter(reader)?;
+        self.documents_ids_from(reader, DocumentId(0))
+    }
+
+    /// Returns the ids of the documents in ascending order, starting at `start`.
+    pub fn documents_ids_from<'txn>(
+        self,
+        reader: &'txn heed::RoTxn<MainT>,
+        start: DocumentId,
+    ) -> ZResult<DocumentsIdsIter<'txn>> {
+        let start = DocumentFieldIndexedKey::new(start, IndexedPos::min());
+        let iter = self.documents_fields_counts.range(reader, &(start..))?;
         Ok(DocumentsIdsIter {
             last_seen_id: None,
             iter,
@@ -98,7 +108,7 @@ impl Iterator for DocumentFieldsCountsIter<'_> {
 
 pub struct DocumentsIdsIter<'txn> {
     last_seen_id: Option<DocumentId>,
-    iter: heed::RoIter<'txn, OwnedType<DocumentFieldIndexedKey>, OwnedType<u16>>,
+    iter: heed::RoRange<'txn, OwnedType<DocumentFieldIndexedKey>, OwnedType<u16>>,
 }
 
 impl Iterator for DocumentsIdsIter<'_> {
@@ -1,6 +1,6 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
 
+use heed::types::Str;
 use heed::{RwTxn, RoTxn, Result as ZResult, RoRange};
 use sdset::{SetBuf, Set, SetOperation};
 
@@ -8,13 +8,15 @@ use meilisearch_types::DocumentId;
 use meilisearch_schema::FieldId;
 
 use crate::database::MainT;
-use crate::facets::FacetKey;
+use crate::facets::{FacetKey, FacetMap};
 use super::cow_set::CowSet;
 
 /// contains facet info
 #[derive(Clone, Copy)]
 pub struct Facets {
     pub(crate) facets: heed::Database<FacetKey, CowSet<DocumentId>>,
+    /// The facet values are stored normalized, they are displayed like in the documents.
+    pub(crate) display_values: heed::Database<FacetKey, Str>,
 }
 
 impl Facets {
@@ -31,21 +33,34 @@ impl Facets {
         self.facets.get(reader, &facet_key)
     }
 
+    /// The facet value like it is written in the documents, the normalized value
+    /// must be displayed when it is `None`.
+    pub fn display_value<'txn>(&self, reader: &'txn RoTxn<MainT>, facet_key: &FacetKey) -> ZResult<Option<&'txn str>> {
+        self.display_values.get(reader, facet_key)
+    }
+
     /// updates the facets  store, revmoving the documents from the facets provided in the
     /// `facet_map` argument
-    pub fn remove(&self, writer: &mut RwTxn<MainT>, facet_map: HashMap<FacetKey, Vec<DocumentId>>) -> ZResult<()> {
-        for (key, document_ids) in facet_map {
+    pub fn remove(&self, writer: &mut RwTxn<MainT>, facet_map: FacetMap) -> ZResult<()> {
+        for (key, (_, document_ids)) in facet_map {
             if let Some(old) = self.facets.get(writer, &key)? {
                 let to_remove = SetBuf::from_dirty(document_ids);
                 let new = sdset::duo::OpBuilder::new(old.as_ref(), to_remove.as_set()).difference().into_set_buf();
+                // the value may be written differently by the next document having it
+                if new.is_empty() {
+                    self.display_values.delete(writer, &key)?;
+                }
                 self.facets.put(writer, &key, new.as_set())?;
             }
         }
         Ok(())
     }
 
-    pub fn add(&self, writer: &mut RwTxn<MainT>, facet_map: HashMap<FacetKey, Vec<DocumentId>>) -> ZResult<()> {
-        for (key, document_ids) in facet_map {
+    pub fn add(&self, writer: &mut RwTxn<MainT>, facet_map: FacetMap) -> ZResult<()> {
+        for (key, (display_value, document_ids)) in facet_map {
+            if self.display_values.get(writer, &key)?.is_none() {
+                self.display_values.put(writer, &key, &display_value)?;
+            }
             let set = SetBuf::from_dirty(document_ids);
             self.put_facet_document_ids(writer, key, set.as_set())?;
         }
@@ -53,6 +68,7 @@ impl Facets {
     }
 
     pub fn clear(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<()> {
-        self.facets.clear(writer)
+        self.facets.clear(writer)?;
+        self.display_values.clear(writer)
     }
 }
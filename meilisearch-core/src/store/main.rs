@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 use std::sync::Arc;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use chrono::{DateTime, Utc};
 use heed::types::{ByteSlice, OwnedType, SerdeBincode, Str};
@@ -9,22 +9,43 @@ use meilisearch_schema::{FieldId, Schema};
 use sdset::Set;
 
 use crate::database::MainT;
+use crate::judgments::{Judgment, JudgmentsRun};
 use crate::RankedMap;
-use crate::settings::RankingRule;
+use crate::settings::{FieldType, KeyboardLayout, QueryNormalization, RankingRule};
 use super::cow_set::CowSet;
 
 const CREATED_AT_KEY: &str = "created-at";
 const ATTRIBUTES_FOR_FACETING: &str = "attributes-for-faceting";
+const ATTRIBUTE_ALIASES_KEY: &str = "attribute-aliases";
+const ADJACENT_ATTRIBUTES_KEY: &str = "adjacent-attributes";
+const ATTRIBUTE_WEIGHTS_KEY: &str = "attribute-weights";
 const RANKING_RULES_KEY: &str = "ranking-rules";
 const DISTINCT_ATTRIBUTE_KEY: &str = "distinct-attribute";
+const DISTINCT_VALUES_KEY: &str = "distinct-values";
 const STOP_WORDS_KEY: &str = "stop-words";
 const SYNONYMS_KEY: &str = "synonyms";
 const CUSTOMS_KEY: &str = "customs";
+const DATA_SIZE_KEY: &str = "data-size";
 const FIELDS_FREQUENCY_KEY: &str = "fields-frequency";
 const NAME_KEY: &str = "name";
+const NORMALIZE_UNITS_KEY: &str = "normalize-units";
+const FIELD_TYPES_KEY: &str = "field-types";
+const KEYBOARD_LAYOUT_KEY: &str = "keyboard-layout";
+const KEYBOARD_TYPOS_KEY: &str = "keyboard-typos";
+const JUDGMENTS_KEY: &str = "judgments";
+const JUDGMENTS_RUN_KEY: &str = "judgments-run";
+const EXCLUDED_ATTRIBUTES_KEY: &str = "excluded-attributes";
+const MAX_DATA_SIZE_KEY: &str = "max-data-size";
+const MAX_INDEXED_WORDS_KEY: &str = "max-indexed-words";
+const MIN_PREFIX_LENGTH_KEY: &str = "min-prefix-length";
+const MOUNTED_ARTIFACT_KEY: &str = "mounted-artifact";
 const NUMBER_OF_DOCUMENTS_KEY: &str = "number-of-documents";
+const NUMERIC_TOLERANCE_KEY: &str = "numeric-tolerance";
+const OPTIONAL_WORDS_KEY: &str = "optional-words";
+const QUERY_NORMALIZATION_KEY: &str = "query-normalization";
 const RANKED_MAP_KEY: &str = "ranked-map";
 const SCHEMA_KEY: &str = "schema";
+const SEARCH_CUTOFF_MS_KEY: &str = "search-cutoff-ms";
 const UPDATED_AT_KEY: &str = "updated-at";
 const WORDS_KEY: &str = "words";
 
@@ -53,6 +74,18 @@ impl Main {
             .map(|name| name.to_owned()))
     }
 
+    /// Marks the index as a copy of a mounted artifact, the identity tells which version of it.
+    pub fn put_mounted_artifact(self, writer: &mut heed::RwTxn<MainT>, artifact: &str) -> ZResult<()> {
+        self.main.put::<_, Str, Str>(writer, MOUNTED_ARTIFACT_KEY, artifact)
+    }
+
+    pub fn mounted_artifact(self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<String>> {
+        Ok(self
+            .main
+            .get::<_, Str, Str>(reader, MOUNTED_ARTIFACT_KEY)?
+            .map(|artifact| artifact.to_owned()))
+    }
+
     pub fn put_created_at(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<()> {
         self.main
             .put::<_, Str, SerdeDatetime>(writer, CREATED_AT_KEY, &Utc::now())
@@ -119,6 +152,11 @@ impl Main {
         self.main.get::<_, Str, SerdeBincode<RankedMap>>(reader, RANKED_MAP_KEY)
     }
 
+    /// The distinct values were stored in this single entry before they had their own store.
+    pub fn delete_distinct_values(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, DISTINCT_VALUES_KEY)
+    }
+
     pub fn put_synonyms_fst(self, writer: &mut heed::RwTxn<MainT>, fst: &fst::Set) -> ZResult<()> {
         let bytes = fst.as_fst().as_bytes();
         self.main.put::<_, Str, ByteSlice>(writer, SYNONYMS_KEY, bytes)
@@ -231,6 +269,218 @@ impl Main {
         self.main.delete::<_, Str>(writer, DISTINCT_ATTRIBUTE_KEY)
     }
 
+    pub fn search_cutoff_ms(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<u64>> {
+        self.main.get::<_, Str, OwnedType<u64>>(reader, SEARCH_CUTOFF_MS_KEY)
+    }
+
+    pub fn put_search_cutoff_ms(self, writer: &mut heed::RwTxn<MainT>, value: u64) -> ZResult<()> {
+        self.main.put::<_, Str, OwnedType<u64>>(writer, SEARCH_CUTOFF_MS_KEY, &value)
+    }
+
+    pub fn delete_search_cutoff_ms(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, SEARCH_CUTOFF_MS_KEY)
+    }
+
+    pub fn attribute_weights(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<BTreeMap<String, u32>>> {
+        self.main.get::<_, Str, SerdeBincode<BTreeMap<String, u32>>>(reader, ATTRIBUTE_WEIGHTS_KEY)
+    }
+
+    pub fn put_attribute_weights(self, writer: &mut heed::RwTxn<MainT>, value: &BTreeMap<String, u32>) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<BTreeMap<String, u32>>>(writer, ATTRIBUTE_WEIGHTS_KEY, value)
+    }
+
+    pub fn delete_attribute_weights(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, ATTRIBUTE_WEIGHTS_KEY)
+    }
+
+    pub fn adjacent_attributes(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<Vec<(String, String)>>> {
+        self.main.get::<_, Str, SerdeBincode<Vec<(String, String)>>>(reader, ADJACENT_ATTRIBUTES_KEY)
+    }
+
+    pub fn put_adjacent_attributes(self, writer: &mut heed::RwTxn<MainT>, value: &[(String, String)]) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<Vec<(String, String)>>>(writer, ADJACENT_ATTRIBUTES_KEY, &value.to_vec())
+    }
+
+    pub fn delete_adjacent_attributes(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, ADJACENT_ATTRIBUTES_KEY)
+    }
+
+    pub fn normalize_units(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<bool>> {
+        self.main.get::<_, Str, SerdeBincode<bool>>(reader, NORMALIZE_UNITS_KEY)
+    }
+
+    pub fn put_normalize_units(self, writer: &mut heed::RwTxn<MainT>, value: bool) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<bool>>(writer, NORMALIZE_UNITS_KEY, &value)
+    }
+
+    pub fn delete_normalize_units(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, NORMALIZE_UNITS_KEY)
+    }
+
+    pub fn keyboard_typos(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<bool>> {
+        self.main.get::<_, Str, SerdeBincode<bool>>(reader, KEYBOARD_TYPOS_KEY)
+    }
+
+    pub fn put_keyboard_typos(self, writer: &mut heed::RwTxn<MainT>, value: bool) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<bool>>(writer, KEYBOARD_TYPOS_KEY, &value)
+    }
+
+    pub fn delete_keyboard_typos(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, KEYBOARD_TYPOS_KEY)
+    }
+
+    pub fn keyboard_layout(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<KeyboardLayout>> {
+        self.main.get::<_, Str, SerdeBincode<KeyboardLayout>>(reader, KEYBOARD_LAYOUT_KEY)
+    }
+
+    pub fn put_keyboard_layout(self, writer: &mut heed::RwTxn<MainT>, value: KeyboardLayout) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<KeyboardLayout>>(writer, KEYBOARD_LAYOUT_KEY, &value)
+    }
+
+    pub fn delete_keyboard_layout(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, KEYBOARD_LAYOUT_KEY)
+    }
+
+    pub fn optional_words(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<bool>> {
+        self.main.get::<_, Str, SerdeBincode<bool>>(reader, OPTIONAL_WORDS_KEY)
+    }
+
+    pub fn put_optional_words(self, writer: &mut heed::RwTxn<MainT>, value: bool) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<bool>>(writer, OPTIONAL_WORDS_KEY, &value)
+    }
+
+    pub fn delete_optional_words(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, OPTIONAL_WORDS_KEY)
+    }
+
+    pub fn numeric_tolerance(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<f64>> {
+        self.main.get::<_, Str, SerdeBincode<f64>>(reader, NUMERIC_TOLERANCE_KEY)
+    }
+
+    pub fn put_numeric_tolerance(self, writer: &mut heed::RwTxn<MainT>, value: f64) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<f64>>(writer, NUMERIC_TOLERANCE_KEY, &value)
+    }
+
+    pub fn delete_numeric_tolerance(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, NUMERIC_TOLERANCE_KEY)
+    }
+
+    pub fn attribute_aliases(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<BTreeMap<String, String>>> {
+        self.main.get::<_, Str, SerdeBincode<BTreeMap<String, String>>>(reader, ATTRIBUTE_ALIASES_KEY)
+    }
+
+    pub fn put_attribute_aliases(self, writer: &mut heed::RwTxn<MainT>, value: &BTreeMap<String, String>) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<BTreeMap<String, String>>>(writer, ATTRIBUTE_ALIASES_KEY, value)
+    }
+
+    pub fn delete_attribute_aliases(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, ATTRIBUTE_ALIASES_KEY)
+    }
+
+    pub fn field_types(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<BTreeMap<String, FieldType>>> {
+        self.main.get::<_, Str, SerdeBincode<BTreeMap<String, FieldType>>>(reader, FIELD_TYPES_KEY)
+    }
+
+    pub fn put_field_types(self, writer: &mut heed::RwTxn<MainT>, value: &BTreeMap<String, FieldType>) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<BTreeMap<String, FieldType>>>(writer, FIELD_TYPES_KEY, value)
+    }
+
+    pub fn delete_field_types(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, FIELD_TYPES_KEY)
+    }
+
+    pub fn judgments(self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<Vec<Judgment>>> {
+        self.main.get::<_, Str, SerdeBincode<Vec<Judgment>>>(reader, JUDGMENTS_KEY)
+    }
+
+    pub fn put_judgments(self, writer: &mut heed::RwTxn<MainT>, value: &[Judgment]) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<Vec<Judgment>>>(writer, JUDGMENTS_KEY, &value.to_vec())
+    }
+
+    /// Deletes the judgments with the relevancies of their last run.
+    pub fn delete_judgments(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, JUDGMENTS_RUN_KEY)?;
+        self.main.delete::<_, Str>(writer, JUDGMENTS_KEY)
+    }
+
+    pub fn judgments_run(self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<JudgmentsRun>> {
+        self.main.get::<_, Str, SerdeBincode<JudgmentsRun>>(reader, JUDGMENTS_RUN_KEY)
+    }
+
+    pub fn put_judgments_run(self, writer: &mut heed::RwTxn<MainT>, value: &JudgmentsRun) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<JudgmentsRun>>(writer, JUDGMENTS_RUN_KEY, value)
+    }
+
+    /// The number of bytes of the stores of the index when its last update was processed.
+    pub fn data_size(self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<u64>> {
+        self.main.get::<_, Str, SerdeBincode<u64>>(reader, DATA_SIZE_KEY)
+    }
+
+    pub fn put_data_size(self, writer: &mut heed::RwTxn<MainT>, value: u64) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<u64>>(writer, DATA_SIZE_KEY, &value)
+    }
+
+    /// The data size over which the documents additions are refused.
+    pub fn max_data_size(self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<u64>> {
+        self.main.get::<_, Str, SerdeBincode<u64>>(reader, MAX_DATA_SIZE_KEY)
+    }
+
+    pub fn put_max_data_size(self, writer: &mut heed::RwTxn<MainT>, value: u64) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<u64>>(writer, MAX_DATA_SIZE_KEY, &value)
+    }
+
+    pub fn delete_max_data_size(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, MAX_DATA_SIZE_KEY)
+    }
+
+    pub fn excluded_attributes(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<BTreeSet<String>>> {
+        self.main.get::<_, Str, SerdeBincode<BTreeSet<String>>>(reader, EXCLUDED_ATTRIBUTES_KEY)
+    }
+
+    pub fn put_excluded_attributes(self, writer: &mut heed::RwTxn<MainT>, value: &BTreeSet<String>) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<BTreeSet<String>>>(writer, EXCLUDED_ATTRIBUTES_KEY, value)
+    }
+
+    pub fn delete_excluded_attributes(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, EXCLUDED_ATTRIBUTES_KEY)
+    }
+
+    pub fn max_indexed_words(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<BTreeMap<String, usize>>> {
+        self.main.get::<_, Str, SerdeBincode<BTreeMap<String, usize>>>(reader, MAX_INDEXED_WORDS_KEY)
+    }
+
+    pub fn put_max_indexed_words(self, writer: &mut heed::RwTxn<MainT>, value: &BTreeMap<String, usize>) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<BTreeMap<String, usize>>>(writer, MAX_INDEXED_WORDS_KEY, value)
+    }
+
+    pub fn delete_max_indexed_words(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, MAX_INDEXED_WORDS_KEY)
+    }
+
+    pub fn min_prefix_length(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<usize>> {
+        self.main.get::<_, Str, SerdeBincode<usize>>(reader, MIN_PREFIX_LENGTH_KEY)
+    }
+
+    pub fn put_min_prefix_length(self, writer: &mut heed::RwTxn<MainT>, value: usize) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<usize>>(writer, MIN_PREFIX_LENGTH_KEY, &value)
+    }
+
+    pub fn delete_min_prefix_length(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, MIN_PREFIX_LENGTH_KEY)
+    }
+
+    pub fn query_normalization(&self, reader: &heed::RoTxn<MainT>) -> ZResult<Option<QueryNormalization>> {
+        self.main.get::<_, Str, SerdeBincode<QueryNormalization>>(reader, QUERY_NORMALIZATION_KEY)
+    }
+
+    pub fn put_query_normalization(self, writer: &mut heed::RwTxn<MainT>, value: &QueryNormalization) -> ZResult<()> {
+        self.main.put::<_, Str, SerdeBincode<QueryNormalization>>(writer, QUERY_NORMALIZATION_KEY, value)
+    }
+
+    pub fn delete_query_normalization(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<bool> {
+        self.main.delete::<_, Str>(writer, QUERY_NORMALIZATION_KEY)
+    }
+
     pub fn put_customs(self, writer: &mut heed::RwTxn<MainT>, customs: &[u8]) -> ZResult<()> {
         self.main
             .put::<_, Str, ByteSlice>(writer, CUSTOMS_KEY, customs)
@@ -1,4 +1,6 @@
+mod changes;
 mod cow_set;
+mod distinct_values;
 mod docs_words;
 mod prefix_documents_cache;
 mod prefix_postings_lists_cache;
@@ -8,9 +10,13 @@ mod main;
 mod postings_lists;
 mod synonyms;
 mod updates;
+mod updates_idempotency_keys;
+mod updates_priorities;
 mod updates_results;
 mod facets;
 
+pub use self::changes::{Change, ChangeKind, Changes};
+pub use self::distinct_values::DistinctValues;
 pub use self::docs_words::DocsWords;
 pub use self::facets::Facets;
 pub use self::prefix_documents_cache::PrefixDocumentsCache;
@@ -20,9 +26,11 @@ pub use self::documents_fields_counts::{
     DocumentFieldsCountsIter, DocumentsFieldsCounts, DocumentsIdsIter,
 };
 pub use self::main::Main;
-pub use self::postings_lists::PostingsLists;
+pub use self::postings_lists::{PostingsLists, WordStats};
 pub use self::synonyms::Synonyms;
 pub use self::updates::Updates;
+pub use self::updates_idempotency_keys::{IdempotentUpdate, UpdatesIdempotencyKeys};
+pub use self::updates_priorities::UpdatesPriorities;
 pub use self::updates_results::UpdatesResults;
 
 use std::borrow::Cow;
@@ -30,19 +38,22 @@ use std::collections::HashSet;
 use std::convert::TryInto;
 use std::{mem, ptr};
 
+use fst::{IntoStreamer, Streamer};
 use heed::Result as ZResult;
 use heed::{BytesEncode, BytesDecode};
 use meilisearch_schema::{IndexedPos, FieldId};
 use sdset::{Set, SetBuf};
 use serde::de::{self, Deserialize};
+use serde::Serialize;
 use zerocopy::{AsBytes, FromBytes};
 
 use crate::criterion::Criteria;
 use crate::database::{MainT, UpdateT};
 use crate::database::{UpdateEvent, UpdateEventsEmitter};
+use crate::encryption::Cipher;
 use crate::serde::Deserializer;
 use crate::settings::SettingsUpdate;
-use crate::{query_builder::QueryBuilder, update, DocIndex, DocumentId, Error, MResult};
+use crate::{query_builder::QueryBuilder, update, DocIndex, DocumentId, Error, Highlight, MResult};
 
 type BEU64 = zerocopy::U64<byteorder::BigEndian>;
 pub type BEU16 = zerocopy::U16<byteorder::BigEndian>;
@@ -160,6 +171,70 @@ impl<'a> BytesDecode<'a> for PostingsCodec {
     }
 }
 
+/// The number of entries of a store and the size, in bytes, of their keys and values.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct StoreSize {
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+impl StoreSize {
+    fn push(&mut self, key_size: usize, data_size: usize) {
+        self.entries += 1;
+        self.bytes += (key_size + data_size) as u64;
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStoresSizes {
+    pub main: StoreSize,
+    pub postings_lists: StoreSize,
+    pub words_stats: StoreSize,
+    pub documents_fields: StoreSize,
+    pub documents_fields_counts: StoreSize,
+    pub facets: StoreSize,
+    pub distinct_values: StoreSize,
+    pub synonyms: StoreSize,
+    pub docs_words: StoreSize,
+    pub prefix_caches: StoreSize,
+    pub changes: StoreSize,
+    pub updates: StoreSize,
+    pub updates_results: StoreSize,
+}
+
+impl IndexStoresSizes {
+    /// The number of bytes of the keys and the values of all the stores.
+    pub fn total_bytes(&self) -> u64 {
+        let stores = [
+            &self.main,
+            &self.postings_lists,
+            &self.words_stats,
+            &self.documents_fields,
+            &self.documents_fields_counts,
+            &self.facets,
+            &self.distinct_values,
+            &self.synonyms,
+            &self.docs_words,
+            &self.prefix_caches,
+            &self.changes,
+            &self.updates,
+            &self.updates_results,
+        ];
+        stores.iter().map(|store| store.bytes).sum()
+    }
+}
+
+fn postings_size(postings: &Postings) -> usize {
+    mem::size_of::<u64>()
+        + postings.docids.len() * mem::size_of::<DocumentId>()
+        + postings.matches.len() * mem::size_of::<DocIndex>()
+}
+
+fn json_size<T: Serialize>(value: &T) -> usize {
+    serde_json::to_vec(value).map_or(0, |bytes| bytes.len())
+}
+
 fn main_name(name: &str) -> String {
     format!("store-{}", name)
 }
@@ -168,6 +243,10 @@ fn postings_lists_name(name: &str) -> String {
     format!("store-{}-postings-lists", name)
 }
 
+fn words_stats_name(name: &str) -> String {
+    format!("store-{}-words-stats", name)
+}
+
 fn documents_fields_name(name: &str) -> String {
     format!("store-{}-documents-fields", name)
 }
@@ -200,10 +279,30 @@ fn updates_results_name(name: &str) -> String {
     format!("store-{}-updates-results", name)
 }
 
+fn updates_priorities_name(name: &str) -> String {
+    format!("store-{}-updates-priorities", name)
+}
+
+fn updates_idempotency_keys_name(name: &str) -> String {
+    format!("store-{}-updates-idempotency-keys", name)
+}
+
 fn facets_name(name: &str) -> String {
     format!("store-{}-facets", name)
 }
 
+fn changes_name(name: &str) -> String {
+    format!("store-{}-changes", name)
+}
+
+fn facets_display_values_name(name: &str) -> String {
+    format!("store-{}-facets-display-values", name)
+}
+
+fn distinct_values_name(name: &str) -> String {
+    format!("store-{}-distinct-values", name)
+}
+
 #[derive(Clone)]
 pub struct Index {
     pub main: Main,
@@ -211,17 +310,177 @@ pub struct Index {
     pub documents_fields: DocumentsFields,
     pub documents_fields_counts: DocumentsFieldsCounts,
     pub facets: Facets,
+    pub distinct_values: DistinctValues,
     pub synonyms: Synonyms,
     pub docs_words: DocsWords,
     pub prefix_documents_cache: PrefixDocumentsCache,
     pub prefix_postings_lists_cache: PrefixPostingsListsCache,
+    pub changes: Changes,
 
     pub updates: Updates,
     pub updates_results: UpdatesResults,
+    pub updates_priorities: UpdatesPriorities,
+    pub updates_idempotency_keys: UpdatesIdempotencyKeys,
     pub(crate) updates_notifier: UpdateEventsEmitter,
+    /// The quota of the index when it has none of its own.
+    pub(crate) default_max_data_size: Option<u64>,
 }
 
 impl Index {
+    /// The data size, in bytes, from which the documents additions to the index fail,
+    /// its own quota or the one of the database.
+    pub fn max_data_size(&self, reader: &heed::RoTxn<MainT>) -> MResult<Option<u64>> {
+        let max_data_size = self.main.max_data_size(reader)?;
+        Ok(max_data_size.or(self.default_max_data_size))
+    }
+
+    /// Returns the words indexed for a document along with their positions,
+    /// the positions are sorted by attribute then by word position.
+    pub fn document_words(
+        &self,
+        reader: &heed::RoTxn<MainT>,
+        document_id: DocumentId,
+    ) -> MResult<Option<Vec<(String, DocIndex)>>> {
+        let words = match self.docs_words.doc_words(reader, document_id)? {
+            Some(words) => words,
+            None => return Ok(None),
+        };
+
+        let mut positions = Vec::new();
+        let mut stream = words.stream();
+        while let Some(word) = stream.next() {
+            if let Some(postings) = self.postings_lists.postings_list(reader, word)? {
+                let string = String::from_utf8_lossy(word).into_owned();
+                let matches = postings.matches.iter().filter(|m| m.document_id == document_id);
+                positions.extend(matches.map(|m| (string.clone(), *m)));
+            }
+        }
+
+        positions.sort_unstable_by_key(|(_, m)| (m.attribute, m.word_index, m.char_index));
+
+        Ok(Some(positions))
+    }
+
+    /// Calls `f` with every indexed word starting with `prefix`, in lexicographic order,
+    /// and the number of documents containing it, stops as soon as `f` returns `false`.
+    pub fn for_each_word<F>(&self, reader: &heed::RoTxn<MainT>, prefix: &[u8], mut f: F) -> MResult<()>
+    where
+        F: FnMut(&[u8], usize) -> bool,
+    {
+        let words = match self.main.words_fst(reader)? {
+            Some(words) => words,
+            None => return Ok(()),
+        };
+
+        let mut stream = words.range().ge(prefix).into_stream();
+        while let Some(word) = stream.next() {
+            if !word.starts_with(prefix) {
+                break;
+            }
+
+            let number_of_documents = match self.postings_lists.word_stats(reader, word)? {
+                Some(stats) => stats.documents as usize,
+                None => 0,
+            };
+
+            if !f(word, number_of_documents) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Walks all the stores of the index to compute their sizes, this reads the whole index.
+    ///
+    /// The sizes are the ones of the stored keys and values, the lmdb pages overhead is not counted.
+    pub fn stores_sizes(
+        &self,
+        reader: &heed::RoTxn<MainT>,
+        update_reader: &heed::RoTxn<UpdateT>,
+    ) -> MResult<IndexStoresSizes> {
+        let mut sizes = IndexStoresSizes::default();
+
+        for result in self.main.main.iter::<_, heed::types::Str, heed::types::ByteSlice>(reader)? {
+            let (key, data) = result?;
+            sizes.main.push(key.len(), data.len());
+        }
+
+        for result in self.postings_lists.postings_lists.iter(reader)? {
+            let (word, postings) = result?;
+            sizes.postings_lists.push(word.len(), postings_size(&postings));
+        }
+
+        for result in self.postings_lists.words_stats.iter(reader)? {
+            let (word, _) = result?;
+            sizes.words_stats.push(word.len(), mem::size_of::<WordStats>());
+        }
+
+        for result in self.documents_fields.documents_fields.iter(reader)? {
+            let (_, data) = result?;
+            sizes.documents_fields.push(mem::size_of::<DocumentFieldStoredKey>(), data.len());
+        }
+
+        for result in self.documents_fields_counts.documents_fields_counts.iter(reader)? {
+            result?;
+            sizes.documents_fields_counts.push(mem::size_of::<DocumentFieldIndexedKey>(), mem::size_of::<u16>());
+        }
+
+        for result in self.facets.facets.iter(reader)? {
+            let (key, docids) = result?;
+            let docids_size = docids.len() * mem::size_of::<DocumentId>();
+            sizes.facets.push(mem::size_of::<u16>() + key.value().len(), docids_size);
+        }
+
+        for result in self.facets.display_values.iter(reader)? {
+            let (key, value) = result?;
+            sizes.facets.push(mem::size_of::<u16>() + key.value().len(), value.len());
+        }
+
+        for result in self.distinct_values.distinct_values.iter(reader)? {
+            result?;
+            sizes.distinct_values.push(mem::size_of::<u64>(), mem::size_of::<u64>());
+        }
+
+        for result in self.synonyms.synonyms.iter(reader)? {
+            let (word, data) = result?;
+            sizes.synonyms.push(word.len(), data.len());
+        }
+
+        for result in self.docs_words.docs_words.iter(reader)? {
+            let (_, data) = result?;
+            sizes.docs_words.push(mem::size_of::<u64>(), data.len());
+        }
+
+        for result in self.prefix_documents_cache.prefix_documents_cache.iter(reader)? {
+            let (_, highlights) = result?;
+            let highlights_size = highlights.len() * mem::size_of::<Highlight>();
+            sizes.prefix_caches.push(mem::size_of::<prefix_documents_cache::PrefixKey>(), highlights_size);
+        }
+
+        for result in self.prefix_postings_lists_cache.prefix_postings_lists_cache.iter(reader)? {
+            let (prefix, postings) = result?;
+            sizes.prefix_caches.push(prefix.len(), postings_size(&postings));
+        }
+
+        for result in self.changes.changes.iter(reader)? {
+            let (_, change) = result?;
+            sizes.changes.push(mem::size_of::<u64>(), change.len());
+        }
+
+        for result in self.updates.updates.iter(update_reader)? {
+            let (_, update) = result?;
+            sizes.updates.push(mem::size_of::<u64>(), update.len());
+        }
+
+        for result in self.updates_results.updates_results.iter(update_reader)? {
+            let (_, result) = result?;
+            sizes.updates_results.push(mem::size_of::<u64>(), json_size(&result));
+        }
+
+        Ok(sizes)
+    }
+
     pub fn document<T: de::DeserializeOwned>(
         &self,
         reader: &heed::RoTxn<MainT>,
@@ -257,7 +516,7 @@ impl Index {
             .documents_fields
             .document_attribute(reader, document_id, attribute)?;
         match bytes {
-            Some(bytes) => Ok(Some(serde_json::from_slice(bytes)?)),
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
             None => Ok(None),
         }
     }
@@ -267,14 +526,11 @@ impl Index {
         reader: &'txn heed::RoTxn<MainT>,
         document_id: DocumentId,
         attribute: FieldId,
-    ) -> MResult<Option<&'txn [u8]>> {
+    ) -> MResult<Option<Cow<'txn, [u8]>>> {
         let bytes = self
             .documents_fields
             .document_attribute(reader, document_id, attribute)?;
-        match bytes {
-            Some(bytes) => Ok(Some(bytes)),
-            None => Ok(None),
-        }
+        Ok(bytes)
     }
 
     pub fn customs_update(&self, writer: &mut heed::RwTxn<UpdateT>, customs: Vec<u8>) -> ZResult<u64> {
@@ -311,6 +567,12 @@ impl Index {
         )
     }
 
+    /// Marks an enqueued update to be processed before the older ones, like a single
+    /// document update that must not wait for a bulk import to be indexed.
+    pub fn prioritize_update(&self, writer: &mut heed::RwTxn<UpdateT>, update_id: u64) -> ZResult<()> {
+        self.updates_priorities.put_priority(writer, update_id)
+    }
+
     pub fn clear_all(&self, writer: &mut heed::RwTxn<UpdateT>) -> MResult<u64> {
         let _ = self.updates_notifier.send(UpdateEvent::NewUpdate);
         update::push_clear_all(writer, self.updates, self.updates_results)
@@ -376,10 +638,13 @@ pub fn create(
     update_env: &heed::Env,
     name: &str,
     updates_notifier: UpdateEventsEmitter,
+    cipher: Option<&'static Cipher>,
+    default_max_data_size: Option<u64>,
 ) -> MResult<Index> {
     // create all the store names
     let main_name = main_name(name);
     let postings_lists_name = postings_lists_name(name);
+    let words_stats_name = words_stats_name(name);
     let documents_fields_name = documents_fields_name(name);
     let documents_fields_counts_name = documents_fields_counts_name(name);
     let synonyms_name = synonyms_name(name);
@@ -388,35 +653,51 @@ pub fn create(
     let prefix_postings_lists_cache_name = prefix_postings_lists_cache_name(name);
     let updates_name = updates_name(name);
     let updates_results_name = updates_results_name(name);
+    let updates_priorities_name = updates_priorities_name(name);
+    let updates_idempotency_keys_name = updates_idempotency_keys_name(name);
     let facets_name = facets_name(name);
+    let changes_name = changes_name(name);
+    let distinct_values_name = distinct_values_name(name);
+    let facets_display_values_name = facets_display_values_name(name);
 
     // open all the stores
     let main = env.create_poly_database(Some(&main_name))?;
     let postings_lists = env.create_database(Some(&postings_lists_name))?;
+    let words_stats = env.create_database(Some(&words_stats_name))?;
     let documents_fields = env.create_database(Some(&documents_fields_name))?;
     let documents_fields_counts = env.create_database(Some(&documents_fields_counts_name))?;
     let facets = env.create_database(Some(&facets_name))?;
+    let display_values = env.create_database(Some(&facets_display_values_name))?;
+    let distinct_values = env.create_database(Some(&distinct_values_name))?;
     let synonyms = env.create_database(Some(&synonyms_name))?;
     let docs_words = env.create_database(Some(&docs_words_name))?;
     let prefix_documents_cache = env.create_database(Some(&prefix_documents_cache_name))?;
     let prefix_postings_lists_cache = env.create_database(Some(&prefix_postings_lists_cache_name))?;
+    let changes = env.create_database(Some(&changes_name))?;
     let updates = update_env.create_database(Some(&updates_name))?;
     let updates_results = update_env.create_database(Some(&updates_results_name))?;
+    let updates_priorities = update_env.create_database(Some(&updates_priorities_name))?;
+    let updates_idempotency_keys = update_env.create_database(Some(&updates_idempotency_keys_name))?;
 
     Ok(Index {
         main: Main { main },
-        postings_lists: PostingsLists { postings_lists },
-        documents_fields: DocumentsFields { documents_fields },
+        postings_lists: PostingsLists { postings_lists, words_stats },
+        documents_fields: DocumentsFields { documents_fields, cipher },
         documents_fields_counts: DocumentsFieldsCounts { documents_fields_counts },
         synonyms: Synonyms { synonyms },
         docs_words: DocsWords { docs_words },
         prefix_postings_lists_cache: PrefixPostingsListsCache { prefix_postings_lists_cache },
         prefix_documents_cache: PrefixDocumentsCache { prefix_documents_cache },
-        facets: Facets { facets },
+        facets: Facets { facets, display_values },
+        distinct_values: DistinctValues { distinct_values },
+        changes: Changes { changes, cipher },
 
-        updates: Updates { updates },
+        updates: Updates { updates, cipher },
         updates_results: UpdatesResults { updates_results },
+        updates_priorities: UpdatesPriorities { priorities: updates_priorities },
+        updates_idempotency_keys: UpdatesIdempotencyKeys { keys: updates_idempotency_keys },
         updates_notifier,
+        default_max_data_size,
     })
 }
 
@@ -425,10 +706,13 @@ pub fn open(
     update_env: &heed::Env,
     name: &str,
     updates_notifier: UpdateEventsEmitter,
+    cipher: Option<&'static Cipher>,
+    default_max_data_size: Option<u64>,
 ) -> MResult<Option<Index>> {
     // create all the store names
     let main_name = main_name(name);
     let postings_lists_name = postings_lists_name(name);
+    let words_stats_name = words_stats_name(name);
     let documents_fields_name = documents_fields_name(name);
     let documents_fields_counts_name = documents_fields_counts_name(name);
     let synonyms_name = synonyms_name(name);
@@ -438,6 +722,11 @@ pub fn open(
     let prefix_postings_lists_cache_name = prefix_postings_lists_cache_name(name);
     let updates_name = updates_name(name);
     let updates_results_name = updates_results_name(name);
+    let updates_priorities_name = updates_priorities_name(name);
+    let updates_idempotency_keys_name = updates_idempotency_keys_name(name);
+    let changes_name = changes_name(name);
+    let distinct_values_name = distinct_values_name(name);
+    let facets_display_values_name = facets_display_values_name(name);
 
     // open all the stores
     let main = match env.open_poly_database(Some(&main_name))? {
@@ -476,6 +765,20 @@ pub fn open(
         Some(prefix_postings_lists_cache) => prefix_postings_lists_cache,
         None => return Ok(None),
     };
+    // the changes and words stats stores have been introduced after the others,
+    // we create them to be able to open indexes of older databases
+    let changes = env.create_database(Some(&changes_name))?;
+    let words_stats = env.create_database(Some(&words_stats_name))?;
+    // the distinct values of the indexes of older databases must be computed
+    let (distinct_values, migrate_distinct_values) = match env.open_database(Some(&distinct_values_name))? {
+        Some(distinct_values) => (distinct_values, false),
+        None => (env.create_database(Some(&distinct_values_name))?, true),
+    };
+    // the facet values of the indexes of older databases are not normalized
+    let (display_values, migrate_facets) = match env.open_database(Some(&facets_display_values_name))? {
+        Some(display_values) => (display_values, false),
+        None => (env.create_database(Some(&facets_display_values_name))?, true),
+    };
     let updates = match update_env.open_database(Some(&updates_name))? {
         Some(updates) => updates,
         None => return Ok(None),
@@ -484,21 +787,43 @@ pub fn open(
         Some(updates_results) => updates_results,
         None => return Ok(None),
     };
+    // the priorities and idempotency keys stores have been introduced after the others,
+    // we create them to be able to open indexes of older databases
+    let updates_priorities = update_env.create_database(Some(&updates_priorities_name))?;
+    let updates_idempotency_keys = update_env.create_database(Some(&updates_idempotency_keys_name))?;
 
-    Ok(Some(Index {
+    let index = Index {
         main: Main { main },
-        postings_lists: PostingsLists { postings_lists },
-        documents_fields: DocumentsFields { documents_fields },
+        postings_lists: PostingsLists { postings_lists, words_stats },
+        documents_fields: DocumentsFields { documents_fields, cipher },
         documents_fields_counts: DocumentsFieldsCounts { documents_fields_counts },
         synonyms: Synonyms { synonyms },
         docs_words: DocsWords { docs_words },
         prefix_documents_cache: PrefixDocumentsCache { prefix_documents_cache },
-        facets: Facets { facets },
+        facets: Facets { facets, display_values },
+        distinct_values: DistinctValues { distinct_values },
         prefix_postings_lists_cache: PrefixPostingsListsCache { prefix_postings_lists_cache },
-        updates: Updates { updates },
+        changes: Changes { changes, cipher },
+        updates: Updates { updates, cipher },
         updates_results: UpdatesResults { updates_results },
+        updates_priorities: UpdatesPriorities { priorities: updates_priorities },
+        updates_idempotency_keys: UpdatesIdempotencyKeys { keys: updates_idempotency_keys },
         updates_notifier,
-    }))
+        default_max_data_size,
+    };
+
+    if migrate_distinct_values || migrate_facets {
+        let mut writer = env.typed_write_txn::<MainT>()?;
+        if migrate_distinct_values {
+            update::compute_distinct_values(&mut writer, &index)?;
+        }
+        if migrate_facets {
+            update::compute_facets(&mut writer, &index)?;
+        }
+        writer.commit()?;
+    }
+
+    Ok(Some(index))
 }
 
 pub fn clear(
@@ -511,11 +836,63 @@ pub fn clear(
     index.postings_lists.clear(writer)?;
     index.documents_fields.clear(writer)?;
     index.documents_fields_counts.clear(writer)?;
+    index.facets.clear(writer)?;
+    index.distinct_values.clear(writer)?;
     index.synonyms.clear(writer)?;
     index.docs_words.clear(writer)?;
     index.prefix_documents_cache.clear(writer)?;
     index.prefix_postings_lists_cache.clear(writer)?;
+    index.changes.clear(writer)?;
     index.updates.clear(update_writer)?;
     index.updates_results.clear(update_writer)?;
+    index.updates_priorities.clear(update_writer)?;
+    index.updates_idempotency_keys.clear(update_writer)?;
+    Ok(())
+}
+
+/// Copies the entries of the stores of an index into the stores, already created, of another one,
+/// possibly of another environment, the documents are not reindexed. The entries are written
+/// with the given transaction on the target environment, they are visible once it is committed.
+/// The updates stores and the changes feed are not copied.
+pub fn copy(
+    source_env: &heed::Env,
+    name: &str,
+    target_env: &heed::Env,
+    new_name: &str,
+    writer: &mut heed::RwTxn<MainT>,
+) -> MResult<()> {
+    let stores_names: [fn(&str) -> String; 12] = [
+        main_name,
+        postings_lists_name,
+        words_stats_name,
+        documents_fields_name,
+        documents_fields_counts_name,
+        synonyms_name,
+        docs_words_name,
+        prefix_documents_cache_name,
+        prefix_postings_lists_cache_name,
+        facets_name,
+        facets_display_values_name,
+        distinct_values_name,
+    ];
+
+    // the stores are opened as raw bytes stores, whatever their types
+    let mut stores = Vec::with_capacity(stores_names.len());
+    for store_name in stores_names.iter() {
+        let source = source_env.open_poly_database(Some(&store_name(name)))?;
+        let target = target_env.open_poly_database(Some(&store_name(new_name)))?;
+        if let (Some(source), Some(target)) = (source, target) {
+            stores.push((source, target));
+        }
+    }
+
+    let reader = source_env.typed_read_txn::<MainT>()?;
+    for (source, target) in stores {
+        for result in source.iter::<_, heed::types::ByteSlice, heed::types::ByteSlice>(&reader)? {
+            let (key, data) = result?;
+            target.put::<_, heed::types::ByteSlice, heed::types::ByteSlice>(writer, key, data)?;
+        }
+    }
+
     Ok(())
 }
@@ -1,17 +1,38 @@
 use std::borrow::Cow;
 
 use heed::Result as ZResult;
-use heed::types::ByteSlice;
+use heed::types::{ByteSlice, OwnedType};
 use sdset::{Set, SetBuf};
 use slice_group_by::GroupBy;
+use zerocopy::{AsBytes, FromBytes};
 
 use crate::database::MainT;
 use crate::DocIndex;
 use crate::store::{Postings, PostingsCodec};
 
+/// The counts of a postings list, kept aside to be read without decoding the whole list.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, AsBytes, FromBytes)]
+#[repr(C)]
+pub struct WordStats {
+    /// The number of documents containing the word.
+    pub documents: u64,
+    /// The number of times the word appears in all the documents.
+    pub occurrences: u64,
+}
+
+impl WordStats {
+    fn from_postings(postings: &Postings) -> WordStats {
+        WordStats {
+            documents: postings.docids.len() as u64,
+            occurrences: postings.matches.len() as u64,
+        }
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct PostingsLists {
     pub(crate) postings_lists: heed::Database<ByteSlice, PostingsCodec>,
+    pub(crate) words_stats: heed::Database<ByteSlice, OwnedType<WordStats>>,
 }
 
 impl PostingsLists {
@@ -26,14 +47,17 @@ impl PostingsLists {
         let matches = Cow::Borrowed(matches);
         let postings = Postings { docids, matches };
 
+        self.words_stats.put(writer, word, &WordStats::from_postings(&postings))?;
         self.postings_lists.put(writer, word, &postings)
     }
 
     pub fn del_postings_list(self, writer: &mut heed::RwTxn<MainT>, word: &[u8]) -> ZResult<bool> {
+        self.words_stats.delete(writer, word)?;
         self.postings_lists.delete(writer, word)
     }
 
     pub fn clear(self, writer: &mut heed::RwTxn<MainT>) -> ZResult<()> {
+        self.words_stats.clear(writer)?;
         self.postings_lists.clear(writer)
     }
 
@@ -44,4 +68,13 @@ impl PostingsLists {
     ) -> ZResult<Option<Postings<'txn>>> {
         self.postings_lists.get(reader, word)
     }
+
+    /// Returns the counts of the postings list of the word, the lists written
+    /// before the counts were kept are decoded to count them.
+    pub fn word_stats(self, reader: &heed::RoTxn<MainT>, word: &[u8]) -> ZResult<Option<WordStats>> {
+        match self.words_stats.get(reader, word)? {
+            Some(stats) => Ok(Some(stats)),
+            None => Ok(self.postings_list(reader, word)?.as_ref().map(WordStats::from_postings)),
+        }
+    }
 }
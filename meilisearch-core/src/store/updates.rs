@@ -1,19 +1,27 @@
 use super::BEU64;
 use crate::database::UpdateT;
+use crate::encryption::{open_json, seal_json, Cipher};
 use crate::update::Update;
-use heed::types::{OwnedType, SerdeJson};
+use heed::types::{ByteSlice, OwnedType};
 use heed::Result as ZResult;
 
 #[derive(Copy, Clone)]
 pub struct Updates {
-    pub(crate) updates: heed::Database<OwnedType<BEU64>, SerdeJson<Update>>,
+    pub(crate) updates: heed::Database<OwnedType<BEU64>, ByteSlice>,
+    /// The enqueued updates hold the documents, they are encrypted like them.
+    pub(crate) cipher: Option<&'static Cipher>,
 }
 
 impl Updates {
+    fn decode(self, key: &BEU64, bytes: &[u8]) -> ZResult<(u64, Update)> {
+        let update_id = key.get();
+        Ok((update_id, open_json(self.cipher, &update_id.to_be_bytes(), bytes)?))
+    }
+
     // TODO do not trigger deserialize if possible
     pub fn last_update(self, reader: &heed::RoTxn<UpdateT>) -> ZResult<Option<(u64, Update)>> {
         match self.updates.last(reader)? {
-            Some((key, data)) => Ok(Some((key.get(), data))),
+            Some((key, data)) => self.decode(&key, data).map(Some),
             None => Ok(None),
         }
     }
@@ -21,15 +29,46 @@ impl Updates {
     // TODO do not trigger deserialize if possible
     pub fn first_update(self, reader: &heed::RoTxn<UpdateT>) -> ZResult<Option<(u64, Update)>> {
         match self.updates.first(reader)? {
-            Some((key, data)) => Ok(Some((key.get(), data))),
+            Some((key, data)) => self.decode(&key, data).map(Some),
             None => Ok(None),
         }
     }
 
+    /// Iterates over the updates from the oldest one, they are deserialized as they are read.
+    pub fn iter<'txn>(
+        self,
+        reader: &'txn heed::RoTxn<UpdateT>,
+    ) -> ZResult<impl Iterator<Item = ZResult<(u64, Update)>> + 'txn> {
+        let iter = self.updates.iter(reader)?;
+        Ok(iter.map(move |result| result.and_then(|(key, data)| self.decode(&key, data))))
+    }
+
+    /// Returns the ids of the `limit` oldest updates, they are not deserialized.
+    pub fn first_update_ids(self, reader: &heed::RoTxn<UpdateT>, limit: usize) -> ZResult<Vec<u64>> {
+        self.updates
+            .iter(reader)?
+            .take(limit)
+            .map(|result| result.map(|(key, _)| key.get()))
+            .collect()
+    }
+
+    /// The number of bytes of the enqueued updates, they are not deserialized.
+    pub fn payloads_size(self, reader: &heed::RoTxn<UpdateT>) -> ZResult<u64> {
+        let mut size = 0;
+        for result in self.updates.iter(reader)? {
+            let (_, data) = result?;
+            size += data.len() as u64;
+        }
+        Ok(size)
+    }
+
     // TODO do not trigger deserialize if possible
     pub fn get(self, reader: &heed::RoTxn<UpdateT>, update_id: u64) -> ZResult<Option<Update>> {
-        let update_id = BEU64::new(update_id);
-        self.updates.get(reader, &update_id)
+        let key = BEU64::new(update_id);
+        match self.updates.get(reader, &key)? {
+            Some(data) => self.decode(&key, data).map(|(_, update)| Some(update)),
+            None => Ok(None),
+        }
     }
 
     pub fn put_update(
@@ -38,9 +77,9 @@ impl Updates {
         update_id: u64,
         update: &Update,
     ) -> ZResult<()> {
-        // TODO prefer using serde_json?
+        let value = seal_json(self.cipher, &update_id.to_be_bytes(), update)?;
         let update_id = BEU64::new(update_id);
-        self.updates.put(writer, &update_id, update)
+        self.updates.put(writer, &update_id, &value)
     }
 
     pub fn del_update(self, writer: &mut heed::RwTxn<UpdateT>, update_id: u64) -> ZResult<bool> {
@@ -0,0 +1,57 @@
+use crate::database::UpdateT;
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeBincode, Str};
+use heed::Result as ZResult;
+use serde::{Deserialize, Serialize};
+
+/// The update enqueued by a request sent with an idempotency key, the digest
+/// identifies the request, a key can't be reused for another one until it expires.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdempotentUpdate {
+    pub update_id: u64,
+    pub request_digest: [u8; 32],
+    pub enqueued_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone)]
+pub struct UpdatesIdempotencyKeys {
+    pub(crate) keys: heed::Database<Str, SerdeBincode<IdempotentUpdate>>,
+}
+
+impl UpdatesIdempotencyKeys {
+    pub fn put_update(self, writer: &mut heed::RwTxn<UpdateT>, key: &str, update: &IdempotentUpdate) -> ZResult<()> {
+        self.keys.put(writer, key, update)
+    }
+
+    /// Returns the update of the key, unless it has been enqueued before `expired_before`.
+    pub fn update(
+        self,
+        reader: &heed::RoTxn<UpdateT>,
+        key: &str,
+        expired_before: DateTime<Utc>,
+    ) -> ZResult<Option<IdempotentUpdate>> {
+        let update = self.keys.get(reader, key)?;
+        Ok(update.filter(|update| update.enqueued_at >= expired_before))
+    }
+
+    /// Removes the keys of the updates enqueued before `expired_before`, returns their number.
+    pub fn remove_expired(self, writer: &mut heed::RwTxn<UpdateT>, expired_before: DateTime<Utc>) -> ZResult<usize> {
+        let mut expired = Vec::new();
+        for result in self.keys.iter(writer)? {
+            let (key, update) = result?;
+            if update.enqueued_at < expired_before {
+                expired.push(key.to_string());
+            }
+        }
+
+        for key in &expired {
+            self.keys.delete(writer, key)?;
+        }
+
+        Ok(expired.len())
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn<UpdateT>) -> ZResult<()> {
+        self.keys.clear(writer)
+    }
+}
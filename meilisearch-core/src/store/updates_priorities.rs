@@ -0,0 +1,34 @@
+use super::BEU64;
+use crate::database::UpdateT;
+use heed::types::{OwnedType, Unit};
+use heed::Result as ZResult;
+
+/// The ids of the enqueued updates that are processed before the older ones.
+#[derive(Copy, Clone)]
+pub struct UpdatesPriorities {
+    pub(crate) priorities: heed::Database<OwnedType<BEU64>, Unit>,
+}
+
+impl UpdatesPriorities {
+    pub fn put_priority(self, writer: &mut heed::RwTxn<UpdateT>, update_id: u64) -> ZResult<()> {
+        let update_id = BEU64::new(update_id);
+        self.priorities.put(writer, &update_id, &())
+    }
+
+    /// Returns the oldest of the priority updates.
+    pub fn first_priority(self, reader: &heed::RoTxn<UpdateT>) -> ZResult<Option<u64>> {
+        match self.priorities.first(reader)? {
+            Some((key, ())) => Ok(Some(key.get())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn del_priority(self, writer: &mut heed::RwTxn<UpdateT>, update_id: u64) -> ZResult<bool> {
+        let update_id = BEU64::new(update_id);
+        self.priorities.delete(writer, &update_id)
+    }
+
+    pub fn clear(self, writer: &mut heed::RwTxn<UpdateT>) -> ZResult<()> {
+        self.priorities.clear(writer)
+    }
+}
@@ -8,6 +8,7 @@ pub fn apply_clear_all(
 ) -> MResult<()> {
     index.main.put_words_fst(writer, &fst::Set::default())?;
     index.main.put_ranked_map(writer, &RankedMap::default())?;
+    index.distinct_values.clear(writer)?;
     index.main.put_number_of_documents(writer, |_| 0)?;
     index.documents_fields.clear(writer)?;
     index.documents_fields_counts.clear(writer)?;
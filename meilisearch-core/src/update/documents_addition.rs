@@ -1,22 +1,86 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 
 use fst::{set::OpBuilder, SetBuilder};
 use indexmap::IndexMap;
 use meilisearch_schema::{Schema, FieldId};
 use meilisearch_types::DocumentId;
 use sdset::{duo::Union, SetOperation};
-use serde::Deserialize;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::database::{MainT, UpdateT};
 use crate::database::{UpdateEvent, UpdateEventsEmitter};
 use crate::facets;
-use crate::raw_indexer::RawIndexer;
+use crate::raw_indexer::{RawIndexer, DEFAULT_WORD_LIMIT};
 use crate::serde::Deserializer;
+use crate::settings::FieldType;
 use crate::store::{self, DocumentsFields, DocumentsFieldsCounts};
-use crate::update::helpers::{index_value, value_to_number, extract_document_id};
+use crate::update::helpers::{index_value, value_to_number, value_to_string, extract_document_id};
+use crate::update::helpers::{distinct_field, distinct_value};
+use crate::update::field_operations::{apply_operation, parse_operations};
 use crate::update::{apply_documents_deletion, compute_short_prefixes, next_update_id, Update};
-use crate::{Error, MResult, RankedMap};
+use crate::{Error, Filter, MResult, RankedMap};
+
+/// A filter the stored documents must match to be replaced or updated by an addition,
+/// checked for each document when the addition is processed.
+///
+/// The `{attribute}` placeholders of the filter are replaced by the values of the document
+/// of the addition, `version < {version}` only writes the documents newer than the stored ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Precondition {
+    pub filter: String,
+    /// Whether the documents that are not stored yet are added, they are skipped otherwise.
+    #[serde(default)]
+    pub insert_missing: bool,
+}
+
+impl Precondition {
+    /// Checks that the filter is valid, whatever the values of the documents.
+    pub fn validate(&self, schema: &Schema) -> MResult<()> {
+        let filter = replace_placeholders(&self.filter, |_| Some("0".to_string()));
+        Filter::parse(&filter, schema)?;
+        Ok(())
+    }
+
+    /// Returns the filter of the document, none when the document has no scalar
+    /// value for one of the placeholders, its precondition can't be met.
+    fn document_filter(&self, document: &IndexMap<String, Value>) -> Option<String> {
+        let mut missing = false;
+        let filter = replace_placeholders(&self.filter, |attribute| {
+            let value = match document.get(attribute) {
+                Some(Value::String(s)) => Some(format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))),
+                Some(Value::Number(n)) => Some(n.to_string()),
+                Some(Value::Bool(b)) => Some(b.to_string()),
+                _ => None,
+            };
+            missing |= value.is_none();
+            value
+        });
+
+        if missing { None } else { Some(filter) }
+    }
+}
+
+fn replace_placeholders(filter: &str, mut value: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(filter.len());
+    let mut rest = filter;
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        output.push_str(&rest[..start]);
+        output.push_str(&value(rest[start + 1..end].trim()).unwrap_or_default());
+        rest = &rest[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
 
 pub struct DocumentsAddition<D> {
     updates_store: store::Updates,
@@ -24,6 +88,9 @@ pub struct DocumentsAddition<D> {
     updates_notifier: UpdateEventsEmitter,
     documents: Vec<D>,
     is_partial: bool,
+    precondition: Option<Precondition>,
+    operations_attribute: Option<String>,
+    excluded_attributes: BTreeSet<String>,
 }
 
 impl<D> DocumentsAddition<D> {
@@ -38,6 +105,9 @@ impl<D> DocumentsAddition<D> {
             updates_notifier,
             documents: Vec::new(),
             is_partial: false,
+            precondition: None,
+            operations_attribute: None,
+            excluded_attributes: BTreeSet::new(),
         }
     }
 
@@ -52,6 +122,9 @@ impl<D> DocumentsAddition<D> {
             updates_notifier,
             documents: Vec::new(),
             is_partial: true,
+            precondition: None,
+            operations_attribute: None,
+            excluded_attributes: BTreeSet::new(),
         }
     }
 
@@ -59,6 +132,24 @@ impl<D> DocumentsAddition<D> {
         self.documents.push(document);
     }
 
+    /// Only replaces or updates the stored documents matching the precondition, when the
+    /// addition is processed, the other documents of the addition are skipped and reported.
+    pub fn set_precondition(&mut self, precondition: Precondition) {
+        self.precondition = Some(precondition);
+    }
+
+    /// Applies the operations held by this attribute to the fields of the updated documents,
+    /// the attribute itself is not stored. The full additions replace the documents, they ignore it.
+    pub fn set_operations_attribute(&mut self, attribute: String) {
+        self.operations_attribute = Some(attribute);
+    }
+
+    /// Drops these attributes when the documents are enqueued, they never reach the updates
+    /// store. The attributes excluded later are dropped when the addition is processed.
+    pub fn set_excluded_attributes(&mut self, excluded_attributes: BTreeSet<String>) {
+        self.excluded_attributes = excluded_attributes;
+    }
+
     pub fn finalize(self, writer: &mut heed::RwTxn<UpdateT>) -> MResult<u64>
     where
         D: serde::Serialize,
@@ -70,6 +161,9 @@ impl<D> DocumentsAddition<D> {
             self.updates_results_store,
             self.documents,
             self.is_partial,
+            self.precondition,
+            self.operations_attribute,
+            &self.excluded_attributes,
         )?;
         Ok(update_id)
     }
@@ -87,20 +181,24 @@ pub fn push_documents_addition<D: serde::Serialize>(
     updates_results_store: store::UpdatesResults,
     addition: Vec<D>,
     is_partial: bool,
+    precondition: Option<Precondition>,
+    operations_attribute: Option<String>,
+    excluded_attributes: &BTreeSet<String>,
 ) -> MResult<u64> {
     let mut values = Vec::with_capacity(addition.len());
     for add in addition {
         let vec = serde_json::to_vec(&add)?;
-        let add = serde_json::from_slice(&vec)?;
+        let mut deserializer = serde_json::Deserializer::from_slice(&vec);
+        let add = DocumentSeed { excluded_attributes }.deserialize(&mut deserializer)?;
         values.push(add);
     }
 
     let last_update_id = next_update_id(writer, updates_store, updates_results_store)?;
 
     let update = if is_partial {
-        Update::documents_partial(values)
+        Update::documents_partial(values, precondition, operations_attribute)
     } else {
-        Update::documents_addition(values)
+        Update::documents_addition(values, precondition)
     };
 
     updates_store.put_update(writer, last_update_id, &update)?;
@@ -108,6 +206,40 @@ pub fn push_documents_addition<D: serde::Serialize>(
     Ok(last_update_id)
 }
 
+/// Deserializes a document without its excluded attributes, their values are skipped
+/// without being built.
+struct DocumentSeed<'a> {
+    excluded_attributes: &'a BTreeSet<String>,
+}
+
+impl<'de, 'a> DeserializeSeed<'de> for DocumentSeed<'a> {
+    type Value = IndexMap<String, Value>;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for DocumentSeed<'a> {
+    type Value = IndexMap<String, Value>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a document")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut document = IndexMap::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(attribute) = map.next_key::<String>()? {
+            if self.excluded_attributes.contains(&attribute) {
+                map.next_value::<IgnoredAny>()?;
+            } else {
+                document.insert(attribute, map.next_value()?);
+            }
+        }
+        Ok(document)
+    }
+}
+
 fn index_document(
     writer: &mut heed::RwTxn<MainT>,
     documents_fields: DocumentsFields,
@@ -143,13 +275,111 @@ fn index_document(
     Ok(())
 }
 
+/// Ensures the values of a document are of the types declared for their attributes.
+fn check_field_types(
+    field_types: &BTreeMap<String, FieldType>,
+    primary_key: &str,
+    document: &IndexMap<String, Value>,
+) -> MResult<()>
+{
+    for (attribute, value) in document {
+        if let Some(expected) = field_types.get(attribute) {
+            if !expected.accepts(value) {
+                let document_id = document.get(primary_key).map(value_to_string).unwrap_or_default();
+                return Err(Error::InvalidFieldType { document_id, attribute: attribute.clone(), expected: *expected });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reports an operation that couldn't be applied on the field of a document.
+fn field_operation_error(
+    document: &IndexMap<String, Value>,
+    primary_key: &str,
+    attribute: &str,
+    reason: String,
+) -> Error
+{
+    let document_id = document.get(primary_key).map(value_to_string).unwrap_or_default();
+    Error::InvalidFieldOperation { document_id, attribute: attribute.to_string(), reason }
+}
+
+/// Creates an indexer limited to the number of words given for `*`, the attributes
+/// with their own limit must be registered on the indexer.
+fn word_limited_indexer(stop_words: fst::Set, max_indexed_words: &BTreeMap<String, usize>) -> RawIndexer {
+    let limit = max_indexed_words.get("*").copied().unwrap_or(DEFAULT_WORD_LIMIT);
+    RawIndexer::with_word_limit(stop_words, limit)
+}
+
+/// Documents sharing the same id in an addition, only the last one of them is kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateDocuments {
+    /// The primary key value of the kept document.
+    pub document_id: Value,
+    /// The number of documents overwritten by the kept one.
+    pub duplicates: usize,
+    /// The position of the kept document in the addition.
+    pub kept: usize,
+}
+
+/// Reports the documents of the addition overwritten by a later document with the same id,
+/// the documents are given with their position in the addition. The documents without
+/// a valid id are ignored, the addition fails on them anyway.
+pub fn duplicate_documents<'a, I>(primary_key: &str, documents: I) -> Vec<DuplicateDocuments>
+where
+    I: IntoIterator<Item = (usize, &'a IndexMap<String, Value>)>,
+{
+    let mut positions: HashMap<DocumentId, (usize, usize, &Value)> = HashMap::new();
+    for (position, document) in documents {
+        if let Ok(document_id) = extract_document_id(primary_key, document) {
+            let value = &document[primary_key];
+            positions
+                .entry(document_id)
+                .and_modify(|(count, kept, kept_value)| { *count += 1; *kept = position; *kept_value = value; })
+                .or_insert((0, position, value));
+        }
+    }
+
+    let mut duplicates: Vec<_> = positions
+        .into_iter()
+        .filter(|(_, (count, _, _))| *count > 0)
+        .map(|(_, (count, kept, value))| DuplicateDocuments { document_id: value.clone(), duplicates: count, kept })
+        .collect();
+
+    duplicates.sort_unstable_by_key(|d| d.kept);
+    duplicates
+}
+
+/// The documents of a processed addition reported in its update result.
+#[derive(Debug, Default)]
+pub struct AdditionReport {
+    /// The ids of the documents not meeting the precondition of the addition.
+    pub skipped: Vec<Value>,
+    /// The documents overwritten by a later one, the skipped documents overwrite none.
+    pub duplicates: Vec<DuplicateDocuments>,
+}
+
 pub fn apply_addition<'a, 'b>(
     writer: &'a mut heed::RwTxn<'b, MainT>,
     index: &store::Index,
     new_documents: Vec<IndexMap<String, Value>>,
-    partial: bool
-) -> MResult<()> {
+    partial: bool,
+    precondition: Option<&Precondition>,
+    operations_attribute: Option<&str>,
+) -> MResult<AdditionReport> {
+    // the additions accepted while the index was below its quota are refused once it is over,
+    // the data size is the one measured when the previous update was processed
+    if let Some(max_data_size) = index.max_data_size(writer)? {
+        if index.main.data_size(writer)?.unwrap_or_default() >= max_data_size {
+            return Err(Error::QuotaExceeded { max_data_size });
+        }
+    }
+
     let mut documents_additions = HashMap::new();
+    let mut skipped = Vec::new();
 
     let mut schema = match index.main.schema(writer)? {
         Some(schema) => schema,
@@ -157,27 +387,97 @@ pub fn apply_addition<'a, 'b>(
     };
 
     let primary_key = schema.primary_key().ok_or(Error::MissingPrimaryKey)?;
+    let field_types = index.main.field_types(writer)?.unwrap_or_default();
+    let excluded_attributes = index.main.excluded_attributes(writer)?.unwrap_or_default();
+    let primary_key_id = schema.id(primary_key);
 
-    // 1. store documents ids for future deletion
-    for mut document in new_documents {
+    // the documents not meeting the precondition are skipped before their duplicates are counted
+    let mut kept_documents = Vec::with_capacity(new_documents.len());
+    for (position, document) in new_documents.into_iter().enumerate() {
         let document_id = extract_document_id(&primary_key, &document)?;
 
+        if let Some(precondition) = precondition {
+            let stored = match primary_key_id {
+                Some(field_id) => index.document_attribute_bytes(writer, document_id, field_id)?.is_some(),
+                None => false,
+            };
+            let met = if stored {
+                match precondition.document_filter(&document) {
+                    Some(filter) => Filter::parse(&filter, &schema)?.test(writer, index, document_id)?,
+                    None => false,
+                }
+            } else {
+                precondition.insert_missing
+            };
+            if !met {
+                skipped.push(document[primary_key].clone());
+                continue;
+            }
+        }
+
+        kept_documents.push((position, document_id, document));
+    }
+
+    let kept = kept_documents.iter().map(|(position, _, document)| (*position, document));
+    let duplicates = duplicate_documents(primary_key, kept);
+
+    // 1. store documents ids for future deletion
+    for (_, document_id, mut document) in kept_documents {
+        // a replaced document has no stored fields to operate on
+        let operations = match operations_attribute.filter(|_| partial) {
+            Some(attribute) => match document.remove(attribute) {
+                Some(operations) => parse_operations(operations)
+                    .map_err(|reason| field_operation_error(&document, primary_key, attribute, reason))?,
+                None => IndexMap::new(),
+            },
+            None => IndexMap::new(),
+        };
+
+        // the documents enqueued before their attributes were excluded still have them
+        document.retain(|attribute, _| *attribute == primary_key || !excluded_attributes.contains(attribute));
+        check_field_types(&field_types, &primary_key, &document)?;
+
         if partial {
-            let mut deserializer = Deserializer {
-                document_id,
-                reader: writer,
-                documents_fields: index.documents_fields,
-                schema: &schema,
-                fields: None,
+            // a document updated several times by the addition is updated in order
+            let old_document = match documents_additions.remove(&document_id) {
+                Some(old_document) => Some(old_document),
+                None => {
+                    let mut deserializer = Deserializer {
+                        document_id,
+                        reader: writer,
+                        documents_fields: index.documents_fields,
+                        schema: &schema,
+                        fields: None,
+                    };
+                    Option::<IndexMap<String, Value>>::deserialize(&mut deserializer)?
+                }
             };
 
-            let old_document = Option::<HashMap<String, Value>>::deserialize(&mut deserializer)?;
             if let Some(old_document) = old_document {
                 for (key, value) in old_document {
-                    document.entry(key).or_insert(value);
+                    if !excluded_attributes.contains(&key) {
+                        document.entry(key).or_insert(value);
+                    }
                 }
             }
         }
+
+        for (attribute, operation) in operations {
+            if attribute == primary_key || excluded_attributes.contains(&attribute) {
+                let reason = "the primary key and the excluded attributes can't be modified".to_string();
+                return Err(field_operation_error(&document, primary_key, &attribute, reason));
+            }
+            apply_operation(&mut document, &attribute, operation)
+                .map_err(|reason| field_operation_error(&document, primary_key, &attribute, reason))?;
+
+            if let Some(expected) = field_types.get(&attribute) {
+                if !expected.accepts(&document[&attribute]) {
+                    let document_id = document.get(primary_key).map(value_to_string).unwrap_or_default();
+                    return Err(Error::InvalidFieldType { document_id, attribute, expected: *expected });
+                }
+            }
+        }
+
         documents_additions.insert(document_id, document);
     }
 
@@ -191,6 +491,8 @@ pub fn apply_addition<'a, 'b>(
         None => RankedMap::default(),
     };
 
+    let distinct_attribute = index.main.distinct_attribute(writer)?;
+
     let stop_words = match index.main.stop_words_fst(writer)? {
         Some(stop_words) => stop_words,
         None => fst::Set::default(),
@@ -202,13 +504,19 @@ pub fn apply_addition<'a, 'b>(
         index.facets.add(writer, facet_map)?;
     }
 
-    let mut indexer = RawIndexer::new(stop_words);
+    let max_indexed_words = index.main.max_indexed_words(writer)?.unwrap_or_default();
+    let mut indexer = word_limited_indexer(stop_words, &max_indexed_words);
+    indexer.set_normalize_units(index.main.normalize_units(writer)?.unwrap_or(false));
 
     // For each document in this update
     for (document_id, document) in documents_additions {
         // For each key-value pair in the document.
-        for (attribute, value) in document {
-            let field_id = schema.insert_and_index(&attribute)?;
+        for (attribute, value) in &document {
+            let field_id = schema.insert_and_index(attribute)?;
+            // the attribute may have just been added to the schema
+            if let (Some(limit), Some(indexed_pos)) = (max_indexed_words.get(attribute), schema.is_indexed(field_id)) {
+                indexer.set_attribute_word_limit(*indexed_pos, *limit);
+            }
             index_document(
                 writer,
                 index.documents_fields,
@@ -218,9 +526,20 @@ pub fn apply_addition<'a, 'b>(
                 &schema,
                 field_id,
                 document_id,
-                &value,
+                value,
             )?;
         }
+
+        // The distinct attribute may be a field of this document that was just added to the schema.
+        if let Some(attribute) = &distinct_attribute {
+            let hash = distinct_field(&schema, attribute).and_then(|(field_id, path)| {
+                let value = document.get(schema.name(field_id)?)?;
+                distinct_value(value, &path)
+            });
+            if let Some(hash) = hash {
+                index.distinct_values.put_distinct_value(writer, document_id, hash)?;
+            }
+        }
     }
 
     write_documents_addition_index(
@@ -233,7 +552,7 @@ pub fn apply_addition<'a, 'b>(
 
     index.main.put_schema(writer, &schema)?;
 
-    Ok(())
+    Ok(AdditionReport { skipped, duplicates })
 }
 
 pub fn apply_documents_partial_addition<'a, 'b>(
@@ -241,7 +560,7 @@ pub fn apply_documents_partial_addition<'a, 'b>(
     index: &store::Index,
     new_documents: Vec<IndexMap<String, Value>>,
 ) -> MResult<()> {
-    apply_addition(writer, index, new_documents, true)
+    apply_addition(writer, index, new_documents, true, None, None).map(drop)
 }
 
 pub fn apply_documents_addition<'a, 'b>(
@@ -249,7 +568,51 @@ pub fn apply_documents_addition<'a, 'b>(
     index: &store::Index,
     new_documents: Vec<IndexMap<String, Value>>,
 ) -> MResult<()> {
-    apply_addition(writer, index, new_documents, false)
+    apply_addition(writer, index, new_documents, false, None, None).map(drop)
+}
+
+/// Computes the distinct values of all the documents, for the indexes created before they
+/// were stored per document: they were stored in a single entry, or not at all.
+pub(crate) fn compute_distinct_values(writer: &mut heed::RwTxn<MainT>, index: &store::Index) -> MResult<()> {
+    index.distinct_values.clear(writer)?;
+    index.main.delete_distinct_values(writer)?;
+
+    let schema = match index.main.schema(writer)? {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+    let distinct = index.main.distinct_attribute(writer)?.and_then(|attribute| distinct_field(&schema, &attribute));
+    let (field_id, path) = match distinct {
+        Some(distinct) => distinct,
+        None => return Ok(()),
+    };
+
+    let documents_ids: Result<Vec<_>, _> = index.documents_fields_counts.documents_ids(writer)?.collect();
+    for document_id in documents_ids? {
+        let value = match index.documents_fields.document_attribute(writer, document_id, field_id)? {
+            Some(bytes) => serde_json::from_slice::<Value>(&bytes)?,
+            None => continue,
+        };
+        if let Some(hash) = distinct_value(&value, &path) {
+            index.distinct_values.put_distinct_value(writer, document_id, hash)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Indexes the facet values of all the documents again, for the indexes created before
+/// the values were normalized and their display values stored.
+pub(crate) fn compute_facets(writer: &mut heed::RwTxn<MainT>, index: &store::Index) -> MResult<()> {
+    index.facets.clear(writer)?;
+
+    if let Some(attributes_for_facetting) = index.main.attributes_for_faceting(writer)? {
+        let documents_ids: Result<Vec<_>, _> = index.documents_fields_counts.documents_ids(writer)?.collect();
+        let facet_map = facets::facet_map_from_docids(writer, &index, &documents_ids?, &attributes_for_facetting)?;
+        index.facets.add(writer, facet_map)?;
+    }
+
+    Ok(())
 }
 
 pub fn reindex_all_documents(writer: &mut heed::RwTxn<MainT>, index: &store::Index) -> MResult<()> {
@@ -281,9 +644,20 @@ pub fn reindex_all_documents(writer: &mut heed::RwTxn<MainT>, index: &store::Ind
     };
 
     let number_of_inserted_documents = documents_ids_to_reindex.len();
-    let mut indexer = RawIndexer::new(stop_words);
+    let max_indexed_words = index.main.max_indexed_words(writer)?.unwrap_or_default();
+    let mut indexer = word_limited_indexer(stop_words, &max_indexed_words);
+    indexer.set_normalize_units(index.main.normalize_units(writer)?.unwrap_or(false));
+    for (attribute, limit) in &max_indexed_words {
+        if let Some(indexed_pos) = schema.id(attribute).and_then(|id| schema.is_indexed(id)) {
+            indexer.set_attribute_word_limit(*indexed_pos, *limit);
+        }
+    }
     let mut ram_store = HashMap::new();
 
+    index.distinct_values.clear(writer)?;
+    let distinct_attribute = index.main.distinct_attribute(writer)?;
+    let distinct = distinct_attribute.as_ref().and_then(|a| distinct_field(&schema, a));
+
     if let Some(ref attributes_for_facetting) = index.main.attributes_for_faceting(writer)? {
         let facet_map = facets::facet_map_from_docids(writer, &index, &documents_ids_to_reindex, &attributes_for_facetting)?;
         index.facets.add(writer, facet_map)?;
@@ -292,10 +666,17 @@ pub fn reindex_all_documents(writer: &mut heed::RwTxn<MainT>, index: &store::Ind
     for document_id in documents_ids_to_reindex {
         for result in index.documents_fields.document_fields(writer, document_id)? {
             let (field_id, bytes) = result?;
-            let value: Value = serde_json::from_slice(bytes)?;
+            let value: Value = serde_json::from_slice(&bytes)?;
             ram_store.insert((document_id, field_id), value);
         }
 
+        if let Some((field_id, path)) = &distinct {
+            let value = ram_store.get(&(document_id, *field_id));
+            if let Some(hash) = value.and_then(|value| distinct_value(value, path)) {
+                index.distinct_values.put_distinct_value(writer, document_id, hash)?;
+            }
+        }
+
         // For each key-value pair in the document.
         for ((document_id, field_id), value) in ram_store.drain() {
             index_document(
@@ -381,3 +762,55 @@ pub fn write_documents_addition_index(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn report_duplicate_documents() {
+        let documents: Vec<IndexMap<String, Value>> = serde_json::from_value(json!([
+            { "id": 1, "title": "first" },
+            { "id": 2, "title": "second" },
+            { "id": "1", "title": "first again" },
+            { "id": 3, "title": "third" },
+            { "id": 1, "title": "first once more" },
+        ])).unwrap();
+
+        let duplicates = duplicate_documents("id", documents.iter().enumerate());
+        let expected = vec![DuplicateDocuments { document_id: json!(1), duplicates: 2, kept: 4 }];
+        assert_eq!(duplicates, expected);
+    }
+
+    #[test]
+    fn preconditions_take_the_values_of_the_documents() {
+        let precondition = Precondition {
+            filter: "version < {version} AND author = { author }".to_string(),
+            insert_missing: false,
+        };
+
+        let document: IndexMap<String, Value> = serde_json::from_value(json!({
+            "id": 1,
+            "version": 8,
+            "author": "Ridley \"Sir\" Scott",
+        })).unwrap();
+        let filter = precondition.document_filter(&document).unwrap();
+        assert_eq!(filter, r#"version < 8 AND author = "Ridley \"Sir\" Scott""#);
+
+        let document: IndexMap<String, Value> = serde_json::from_value(json!({ "id": 1, "version": 8 })).unwrap();
+        assert_eq!(precondition.document_filter(&document), None);
+    }
+
+    #[test]
+    fn excluded_attributes_are_skipped_when_deserialized() {
+        let excluded_attributes: BTreeSet<_> = vec!["thumbnail".to_string()].into_iter().collect();
+        let bytes = br#"{ "id": 1, "thumbnail": { "data": ["iVBORw0KGgo"] }, "title": "shoe" }"#;
+
+        let seed = DocumentSeed { excluded_attributes: &excluded_attributes };
+        let document = seed.deserialize(&mut serde_json::Deserializer::from_slice(bytes)).unwrap();
+        let expected: IndexMap<String, Value> = serde_json::from_value(json!({ "id": 1, "title": "shoe" })).unwrap();
+        assert_eq!(document, expected);
+        assert_eq!(document.keys().collect::<Vec<_>>(), vec!["id", "title"]);
+    }
+}
@@ -99,6 +99,8 @@ pub fn apply_documents_deletion(
             ranked_map.remove(id, *ranked_attr);
         }
 
+        index.distinct_values.del_distinct_value(writer, id)?;
+
         if let Some(words) = index.docs_words.doc_words(writer, id)? {
             let mut stream = words.stream();
             while let Some(word) = stream.next() {
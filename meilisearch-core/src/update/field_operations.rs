@@ -0,0 +1,138 @@
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{Number, Value};
+
+/// An operation applied to the value of a field when the addition is processed,
+/// the value is read from the document resulting from the addition.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub enum FieldOperation {
+    /// Adds the number to the field, a missing field counts as zero.
+    Increment(Number),
+    /// Pushes the value, or the values of an array, at the end of the array field.
+    Append(Value),
+    /// Removes the value, or the values of an array, from the array field.
+    Remove(Value),
+}
+
+/// Parses the operations of a document, indexed by the name of their field. They are held by
+/// the attribute named by the partial addition, e.g. `{ "id": 1, "$ops": { "views": { "increment": 1 } } }`.
+pub fn parse_operations(operations: Value) -> Result<IndexMap<String, FieldOperation>, String> {
+    serde_json::from_value(operations).map_err(|e| e.to_string())
+}
+
+/// Applies the operation to the field of the document, an error
+/// is returned if the field value doesn't support the operation.
+pub fn apply_operation(
+    document: &mut IndexMap<String, Value>,
+    attribute: &str,
+    operation: FieldOperation,
+) -> Result<(), String> {
+    // there is nothing to remove from a missing field, it must not be created
+    if let FieldOperation::Remove(_) = operation {
+        if !document.contains_key(attribute) {
+            return Ok(());
+        }
+    }
+
+    let value = document.entry(attribute.to_string()).or_insert(Value::Null);
+
+    match operation {
+        FieldOperation::Increment(number) => {
+            let current = match value {
+                Value::Null => Number::from(0),
+                Value::Number(current) => current.clone(),
+                _ => return Err("only the numbers can be incremented".to_string()),
+            };
+            *value = Value::Number(add_numbers(&current, &number)?);
+        }
+        FieldOperation::Append(appended) => {
+            if value.is_null() {
+                *value = Value::Array(Vec::new());
+            }
+            let array = match value {
+                Value::Array(array) => array,
+                _ => return Err("only the arrays can be appended to".to_string()),
+            };
+            match appended {
+                Value::Array(values) => array.extend(values),
+                value => array.push(value),
+            }
+        }
+        FieldOperation::Remove(removed) => {
+            let array = match value {
+                Value::Null => return Ok(()),
+                Value::Array(array) => array,
+                _ => return Err("only the values of the arrays can be removed".to_string()),
+            };
+            let removed = match removed {
+                Value::Array(values) => values,
+                value => vec![value],
+            };
+            array.retain(|value| !removed.contains(value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the numbers without losing precision when they are both integers.
+fn add_numbers(a: &Number, b: &Number) -> Result<Number, String> {
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        if let Some(sum) = a.checked_add(b) {
+            return Ok(Number::from(sum));
+        }
+    }
+    if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+        if let Some(sum) = a.checked_add(b) {
+            return Ok(Number::from(sum));
+        }
+    }
+
+    let sum = a.as_f64().unwrap_or_default() + b.as_f64().unwrap_or_default();
+    Number::from_f64(sum).ok_or_else(|| "the incremented number is not finite".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn document(value: Value) -> IndexMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn operations() {
+        let mut doc = document(json!({ "id": 1, "views": 41, "tags": ["red", "blue"] }));
+        let operations = parse_operations(json!({
+            "views": { "increment": 1 },
+            "likes": { "increment": 0.5 },
+            "tags": { "remove": "red" },
+            "colors": { "append": ["green", "black"] },
+        })).unwrap();
+
+        for (attribute, operation) in operations {
+            apply_operation(&mut doc, &attribute, operation).unwrap();
+        }
+
+        let expected = document(json!({
+            "id": 1,
+            "views": 42,
+            "tags": ["blue"],
+            "likes": 0.5,
+            "colors": ["green", "black"],
+        }));
+        assert_eq!(doc, expected);
+    }
+
+    #[test]
+    fn invalid_operations() {
+        let mut doc = document(json!({ "id": 1, "title": "Alien" }));
+        let operation = FieldOperation::Increment(Number::from(1));
+        assert!(apply_operation(&mut doc, "title", operation).is_err());
+
+        assert!(parse_operations(json!({ "views": { "multiply": 2 } })).is_err());
+        assert!(parse_operations(json!(["views"])).is_err());
+    }
+}
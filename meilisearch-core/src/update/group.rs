@@ -0,0 +1,57 @@
+use chrono::Utc;
+
+use crate::database::{MainT, UpdateT};
+use crate::{store, MResult};
+use super::{next_update_id, update_task, ProcessedUpdateResult, Update, UpdateData};
+
+/// Applies the updates of a group, possibly on several indexes, in the same main transaction.
+///
+/// The updates are applied in order, those following the first failed update are not applied
+/// and the ones preceding it are marked as rolled back. The results are written in the update
+/// stores in any case, the caller must commit the main transaction only if no update failed.
+pub fn apply_update_group(
+    writer: &mut heed::RwTxn<MainT>,
+    update_writer: &mut heed::RwTxn<UpdateT>,
+    group: Vec<(&store::Index, UpdateData)>,
+) -> MResult<Vec<ProcessedUpdateResult>> {
+    let mut statuses: Vec<(&store::Index, ProcessedUpdateResult)> = Vec::with_capacity(group.len());
+    let mut failure: Option<String> = None;
+
+    for (index, data) in group {
+        let update_id = next_update_id(update_writer, index.updates, index.updates_results)?;
+        let update = Update { data, enqueued_at: Utc::now(), precondition: None, operations_attribute: None };
+
+        let status = match &failure {
+            None => update_task(writer, index, update_id, update)?,
+            Some(error) => ProcessedUpdateResult {
+                update_id,
+                update_type: update.data.update_type(),
+                error: Some(format!("not applied, an update of the group failed: {}", error)),
+                duplicates: Vec::new(),
+                skipped: Vec::new(),
+                duration: 0.0,
+                enqueued_at: update.enqueued_at,
+                processed_at: Utc::now(),
+            },
+        };
+
+        if failure.is_none() {
+            failure = status.error.clone();
+        }
+
+        // the result is written right away, the next update of the same index needs a new id
+        index.updates_results.put_update_result(update_writer, update_id, &status)?;
+        statuses.push((index, status));
+    }
+
+    if let Some(error) = failure {
+        for (index, status) in &mut statuses {
+            if status.error.is_none() {
+                status.error = Some(format!("rolled back, an update of the group failed: {}", error));
+                index.updates_results.put_update_result(update_writer, status.update_id, status)?;
+            }
+        }
+    }
+
+    Ok(statuses.into_iter().map(|(_, status)| status).collect())
+}
@@ -2,7 +2,7 @@ use std::fmt::Write as _;
 use std::hash::{Hash, Hasher};
 
 use indexmap::IndexMap;
-use meilisearch_schema::IndexedPos;
+use meilisearch_schema::{FieldId, IndexedPos, Schema};
 use meilisearch_types::DocumentId;
 use ordered_float::OrderedFloat;
 use serde_json::Value;
@@ -122,3 +122,42 @@ pub fn extract_document_id(primary_key: &str, document: &IndexMap<String, Value>
         None => Err(SerializerError::DocumentIdNotFound),
     }
 }
+
+/// Infers the primary key from the fields of a document, the first one with `id` in its name.
+pub fn find_primary_key<'a, I>(fields: I) -> Option<&'a str>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    fields.into_iter().map(String::as_str).find(|field| field.to_lowercase().contains("id"))
+}
+
+/// Finds the document field holding the distinct attribute and the path of keys to follow
+/// inside of its value, the `product.sku` attribute is the `sku` key of the `product` field.
+pub fn distinct_field<'a>(schema: &Schema, attribute: &'a str) -> Option<(FieldId, Vec<&'a str>)> {
+    if let Some(field_id) = schema.id(attribute) {
+        return Some((field_id, Vec::new()));
+    }
+
+    attribute
+        .match_indices('.')
+        .find_map(|(i, _)| schema.id(&attribute[..i]).map(|field_id| (field_id, i)))
+        .map(|(field_id, i)| (field_id, attribute[i + 1..].split('.').collect()))
+}
+
+/// Returns the hash of the value found by following the path inside of the
+/// field value, `None` if the value is missing or null.
+pub fn distinct_value(value: &Value, path: &[&str]) -> Option<u64> {
+    let mut value = value;
+    for key in path {
+        value = value.as_object()?.get(*key)?;
+    }
+
+    if value.is_null() {
+        return None;
+    }
+
+    let bytes = serde_json::to_vec(value).ok()?;
+    let mut s = SipHasher::new();
+    bytes.hash(&mut s);
+    Some(s.finish())
+}
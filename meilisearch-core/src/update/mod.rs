@@ -2,17 +2,25 @@ mod clear_all;
 mod customs_update;
 mod documents_addition;
 mod documents_deletion;
+mod field_operations;
+mod group;
 mod settings_update;
 mod helpers;
 
 pub use self::clear_all::{apply_clear_all, push_clear_all};
 pub use self::customs_update::{apply_customs_update, push_customs_update};
 pub use self::documents_addition::{apply_documents_addition, apply_documents_partial_addition, DocumentsAddition};
+pub use self::documents_addition::{duplicate_documents, AdditionReport, DuplicateDocuments, Precondition};
+pub(crate) use self::documents_addition::{compute_distinct_values, compute_facets};
 pub use self::documents_deletion::{apply_documents_deletion, DocumentsDeletion};
+pub use self::field_operations::FieldOperation;
+pub use self::group::apply_update_group;
 pub use self::helpers::{index_value, value_to_string, value_to_number, compute_document_id, extract_document_id};
+pub use self::helpers::{distinct_field, distinct_value, find_primary_key};
 pub use self::settings_update::{apply_settings_update, push_settings_update};
 
 use std::cmp;
+use std::collections::HashSet;
 use std::time::Instant;
 
 use chrono::{DateTime, Utc};
@@ -24,14 +32,21 @@ use sdset::Set;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::{store, DocumentId, MResult};
+use crate::{store, DocumentId, Error, MResult};
 use crate::database::{MainT, UpdateT};
+use crate::store::{Change, ChangeKind};
 use crate::settings::SettingsUpdate;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Update {
     data: UpdateData,
     enqueued_at: DateTime<Utc>,
+    /// A filter the stored documents must match to be replaced or updated by an addition.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    precondition: Option<Precondition>,
+    /// The attribute of the documents holding the operations applied to their fields, partial additions only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    operations_attribute: Option<String>,
 }
 
 impl Update {
@@ -39,6 +54,8 @@ impl Update {
         Update {
             data: UpdateData::ClearAll,
             enqueued_at: Utc::now(),
+            precondition: None,
+            operations_attribute: None,
         }
     }
 
@@ -46,20 +63,30 @@ impl Update {
         Update {
             data: UpdateData::Customs(data),
             enqueued_at: Utc::now(),
+            precondition: None,
+            operations_attribute: None,
         }
     }
 
-    fn documents_addition(documents: Vec<IndexMap<String, Value>>) -> Update {
+    fn documents_addition(documents: Vec<IndexMap<String, Value>>, precondition: Option<Precondition>) -> Update {
         Update {
             data: UpdateData::DocumentsAddition(documents),
             enqueued_at: Utc::now(),
+            precondition,
+            operations_attribute: None,
         }
     }
 
-    fn documents_partial(documents: Vec<IndexMap<String, Value>>) -> Update {
+    fn documents_partial(
+        documents: Vec<IndexMap<String, Value>>,
+        precondition: Option<Precondition>,
+        operations_attribute: Option<String>,
+    ) -> Update {
         Update {
             data: UpdateData::DocumentsPartial(documents),
             enqueued_at: Utc::now(),
+            precondition,
+            operations_attribute,
         }
     }
 
@@ -67,6 +94,8 @@ impl Update {
         Update {
             data: UpdateData::DocumentsDeletion(data),
             enqueued_at: Utc::now(),
+            precondition: None,
+            operations_attribute: None,
         }
     }
 
@@ -74,6 +103,8 @@ impl Update {
         Update {
             data: UpdateData::Settings(data),
             enqueued_at: Utc::now(),
+            precondition: None,
+            operations_attribute: None,
         }
     }
 }
@@ -128,6 +159,12 @@ pub struct ProcessedUpdateResult {
     pub update_type: UpdateType,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// The documents of an addition overwritten by a later one with the same id.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<DuplicateDocuments>,
+    /// The primary keys of the documents not applied because their precondition failed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<Value>,
     pub duration: f64, // in seconds
     pub enqueued_at: DateTime<Utc>,
     pub processed_at: DateTime<Utc>,
@@ -211,7 +248,12 @@ pub fn update_task<'a, 'b>(
 ) -> MResult<ProcessedUpdateResult> {
     debug!("Processing update number {}", update_id);
 
-    let Update { enqueued_at, data } = update;
+    let Update { enqueued_at, data, precondition, operations_attribute } = update;
+
+    // the change must be computed before applying the update,
+    // the deleted documents would no longer be readable otherwise
+    let change = documents_change(writer, index, &data);
+    let mut report = AdditionReport::default();
 
     let (update_type, result, duration) = match data {
         UpdateData::ClearAll => {
@@ -237,7 +279,15 @@ pub fn update_task<'a, 'b>(
                 number: documents.len(),
             };
 
-            let result = apply_documents_addition(writer, index, documents);
+            let result = documents_addition::apply_addition(
+                writer,
+                index,
+                documents,
+                false,
+                precondition.as_ref(),
+                None,
+            )
+            .map(|addition_report| report = addition_report);
 
             (update_type, result, start.elapsed())
         }
@@ -248,7 +298,15 @@ pub fn update_task<'a, 'b>(
                 number: documents.len(),
             };
 
-            let result = apply_documents_partial_addition(writer, index, documents);
+            let result = documents_addition::apply_addition(
+                writer,
+                index,
+                documents,
+                true,
+                precondition.as_ref(),
+                operations_attribute.as_deref(),
+            )
+            .map(|addition_report| report = addition_report);
 
             (update_type, result, start.elapsed())
         }
@@ -280,15 +338,35 @@ pub fn update_task<'a, 'b>(
         }
     };
 
+    let result = result.and_then(|()| match change? {
+        Some(mut kind) => {
+            // the documents skipped by the precondition have not changed
+            match &mut kind {
+                ChangeKind::DocumentsAddition { documents } | ChangeKind::DocumentsPartial { documents } => {
+                    let skipped: HashSet<_> = report.skipped.iter().map(Value::to_string).collect();
+                    documents.retain(|id| !skipped.contains(&id.to_string()));
+                }
+                ChangeKind::DocumentsDeletion { .. } | ChangeKind::ClearAll => (),
+            }
+            let change = Change { update_id, applied_at: Utc::now(), kind };
+            index.changes.push_change(writer, &change)?;
+            Ok(())
+        }
+        None => Ok(()),
+    });
+
     debug!(
         "Processed update number {} {:?} {:?}",
         update_id, update_type, result
     );
 
+    let error = result.map_err(|e| e.to_string()).err();
     let status = ProcessedUpdateResult {
         update_id,
         update_type,
-        error: result.map_err(|e| e.to_string()).err(),
+        duplicates: if error.is_none() { report.duplicates } else { Vec::new() },
+        skipped: if error.is_none() { report.skipped } else { Vec::new() },
+        error,
         duration: duration.as_secs_f64(),
         enqueued_at,
         processed_at: Utc::now(),
@@ -297,6 +375,223 @@ pub fn update_task<'a, 'b>(
     Ok(status)
 }
 
+/// The maximum number of consecutive settings updates applied together.
+pub const MAX_SETTINGS_BATCH: usize = 32;
+
+/// The number of priority updates that can be processed in a row while older updates
+/// are waiting, the oldest update is then processed so that it is never starved.
+pub const MAX_PRIORITY_STREAK: usize = 16;
+
+/// The number of updates enqueued before a priority update that can be checked for it to be
+/// processed ahead of them, a priority update enqueued after more updates waits for its turn.
+pub const MAX_PRIORITY_LOOKBEHIND: usize = 256;
+
+/// The documents an update adds, updates or deletes, `None` if it may change any document.
+fn affected_documents(data: &UpdateData, primary_key: Option<&str>) -> Option<HashSet<DocumentId>> {
+    match data {
+        UpdateData::DocumentsAddition(documents) | UpdateData::DocumentsPartial(documents) => {
+            let primary_key = primary_key?;
+            documents
+                .iter()
+                .map(|document| helpers::extract_document_id(primary_key, document).ok())
+                .collect()
+        }
+        UpdateData::DocumentsDeletion(documents_ids) => Some(documents_ids.iter().copied().collect()),
+        UpdateData::ClearAll | UpdateData::Customs(_) | UpdateData::Settings(_) => None,
+    }
+}
+
+/// Whether applying the `later` update before the `earlier` one gives the same index as
+/// applying them in order: the settings updates changing different settings, the deletions,
+/// and the documents updates of different documents once the primary key is known.
+fn commute(earlier: &UpdateData, later: &UpdateData, primary_key: Option<&str>) -> bool {
+    match (earlier, later) {
+        (UpdateData::Settings(earlier), UpdateData::Settings(later)) => earlier.merge(later).is_some(),
+        (UpdateData::DocumentsDeletion(_), UpdateData::DocumentsDeletion(_)) => true,
+        _ => match (affected_documents(earlier, primary_key), affected_documents(later, primary_key)) {
+            (Some(earlier), Some(later)) => earlier.is_disjoint(&later),
+            _ => false,
+        },
+    }
+}
+
+/// Returns the updates to process next, either the oldest priority update alone or the
+/// oldest update followed by the settings updates enqueued right after it when it is a
+/// settings update, `priority_streak` counts the priority updates that have been processed
+/// ahead of the oldest update.
+///
+/// A priority update is only processed ahead of the updates enqueued before it when it
+/// commutes with all of them, it otherwise waits for them to be processed. The updates
+/// are only deserialized when needed, the lookbehind first reads the ids of the queue.
+pub fn next_updates(
+    main_reader: &heed::RoTxn<MainT>,
+    reader: &heed::RoTxn<UpdateT>,
+    index: &store::Index,
+    priority_streak: &mut usize,
+) -> MResult<Vec<(u64, Update)>> {
+    let mut updates = index.updates.iter(reader)?;
+    let (oldest_id, oldest) = match updates.next() {
+        Some(result) => result?,
+        None => return Ok(Vec::new()),
+    };
+
+    if *priority_streak < MAX_PRIORITY_STREAK {
+        if let Some(update_id) = index.updates_priorities.first_priority(reader)? {
+            if update_id != oldest_id {
+                let ids = index.updates.first_update_ids(reader, MAX_PRIORITY_LOOKBEHIND + 1)?;
+                let position = ids.iter().position(|id| *id == update_id);
+                if let (Some(position), Some(update)) = (position, index.updates.get(reader, update_id)?) {
+                    let schema = index.main.schema(main_reader)?;
+                    let primary_key = schema.as_ref().and_then(|schema| schema.primary_key());
+
+                    // the earlier updates are read one by one, up to the first one it doesn't commute with
+                    let mut commutes = commute(&oldest.data, &update.data, primary_key);
+                    for earlier_id in &ids[1..position] {
+                        if !commutes {
+                            break;
+                        }
+                        commutes = match index.updates.get(reader, *earlier_id)? {
+                            Some(earlier) => commute(&earlier.data, &update.data, primary_key),
+                            None => false,
+                        };
+                    }
+
+                    if commutes {
+                        *priority_streak += 1;
+                        return Ok(vec![(update_id, update)]);
+                    }
+                }
+            }
+        }
+    }
+
+    *priority_streak = 0;
+
+    let is_settings = |update: &Update| matches!(update.data, UpdateData::Settings(_));
+
+    let mut batch = vec![(oldest_id, oldest)];
+    if is_settings(&batch[0].1) {
+        for result in updates.take(MAX_SETTINGS_BATCH - 1) {
+            let (update_id, update) = result?;
+            if !is_settings(&update) {
+                break;
+            }
+            batch.push((update_id, update));
+        }
+    }
+
+    Ok(batch)
+}
+
+/// Applies the settings updates at the front of `updates` as a single update,
+/// the documents are therefore reindexed once for all of them.
+///
+/// Only consecutive updates changing different settings are merged, `None` is returned
+/// when there is less than two of them or if the merged update failed, the transaction
+/// must then be aborted and the updates applied one by one.
+pub fn settings_batch_task(
+    writer: &mut heed::RwTxn<MainT>,
+    index: &store::Index,
+    updates: &[(u64, Update)],
+) -> Option<Vec<ProcessedUpdateResult>> {
+    let mut merged: Option<SettingsUpdate> = None;
+    let mut batch = Vec::new();
+
+    for (update_id, update) in updates {
+        let settings = match &update.data {
+            UpdateData::Settings(settings) => settings,
+            _ => break,
+        };
+
+        let next = match &merged {
+            Some(merged) => match merged.merge(settings) {
+                Some(next) => next,
+                None => break,
+            },
+            None => settings.clone(),
+        };
+
+        merged = Some(next);
+        batch.push((*update_id, update));
+    }
+
+    let merged = match merged {
+        Some(merged) if batch.len() > 1 => merged,
+        _ => return None,
+    };
+
+    debug!("Processing {} settings updates at once", batch.len());
+
+    let start = Instant::now();
+    if let Err(e) = apply_settings_update(writer, index, merged) {
+        debug!("Settings updates batch failed, they will be processed one by one: {}", e);
+        return None;
+    }
+
+    let duration = start.elapsed().as_secs_f64();
+    let processed_at = Utc::now();
+
+    let results = batch
+        .into_iter()
+        .map(|(update_id, update)| ProcessedUpdateResult {
+            update_id,
+            update_type: update.data.update_type(),
+            error: None,
+            duplicates: Vec::new(),
+            skipped: Vec::new(),
+            duration,
+            enqueued_at: update.enqueued_at,
+            processed_at,
+        })
+        .collect();
+
+    Some(results)
+}
+
+fn documents_change(
+    reader: &heed::RoTxn<MainT>,
+    index: &store::Index,
+    data: &UpdateData,
+) -> MResult<Option<ChangeKind>> {
+    fn primary_key_values(
+        reader: &heed::RoTxn<MainT>,
+        index: &store::Index,
+        documents: &[IndexMap<String, Value>],
+    ) -> MResult<Vec<Value>> {
+        let schema = index.main.schema(reader)?.ok_or(Error::SchemaMissing)?;
+        let primary_key = schema.primary_key().ok_or(Error::MissingPrimaryKey)?;
+        Ok(documents.iter().filter_map(|document| document.get(primary_key).cloned()).collect())
+    }
+
+    let kind = match data {
+        UpdateData::DocumentsAddition(documents) => {
+            ChangeKind::DocumentsAddition { documents: primary_key_values(reader, index, documents)? }
+        }
+        UpdateData::DocumentsPartial(documents) => {
+            ChangeKind::DocumentsPartial { documents: primary_key_values(reader, index, documents)? }
+        }
+        UpdateData::DocumentsDeletion(document_ids) => {
+            let schema = index.main.schema(reader)?.ok_or(Error::SchemaMissing)?;
+            let primary_key = schema.primary_key().and_then(|name| schema.id(name));
+
+            let mut documents = Vec::with_capacity(document_ids.len());
+            if let Some(primary_key) = primary_key {
+                for id in document_ids {
+                    if let Some(value) = index.document_attribute::<Value>(reader, *id, primary_key)? {
+                        documents.push(value);
+                    }
+                }
+            }
+
+            ChangeKind::DocumentsDeletion { documents }
+        }
+        UpdateData::ClearAll => ChangeKind::ClearAll,
+        UpdateData::Customs(_) | UpdateData::Settings(_) => return Ok(None),
+    };
+
+    Ok(Some(kind))
+}
+
 fn compute_short_prefixes(writer: &mut heed::RwTxn<MainT>, index: &store::Index) -> MResult<()> {
     // retrieve the words fst to compute all those prefixes
     let words_fst = match index.main.words_fst(writer)? {
@@ -44,12 +44,12 @@ pub fn apply_settings_update(
 
     match settings.ranking_rules {
         UpdateState::Update(v) => {
-            let ranked_field: Vec<&str> = v.iter().filter_map(RankingRule::field).collect();
+            let ranked_field: Vec<String> = v.iter().flat_map(RankingRule::fields).collect();
             schema.update_ranked(&ranked_field)?;
             for name in ranked_field {
                 if schema.accept_new_fields() {
-                    schema.set_indexed(name.as_ref())?;
-                    schema.set_displayed(name.as_ref())?;
+                    schema.set_indexed(&name)?;
+                    schema.set_displayed(&name)?;
                 }
             }
             index.main.put_ranking_rules(writer, &v)?;
@@ -66,9 +66,147 @@ pub fn apply_settings_update(
     match settings.distinct_attribute {
         UpdateState::Update(v) => {
             index.main.put_distinct_attribute(writer, &v)?;
+            must_reindex = true;
         },
         UpdateState::Clear => {
             index.main.delete_distinct_attribute(writer)?;
+            must_reindex = true;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.search_cutoff_ms {
+        UpdateState::Update(v) => {
+            index.main.put_search_cutoff_ms(writer, v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_search_cutoff_ms(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.attribute_weights {
+        UpdateState::Update(v) => {
+            index.main.put_attribute_weights(writer, &v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_attribute_weights(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.adjacent_attributes {
+        UpdateState::Update(v) => {
+            index.main.put_adjacent_attributes(writer, &v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_adjacent_attributes(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.keyboard_typos {
+        UpdateState::Update(v) => {
+            index.main.put_keyboard_typos(writer, v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_keyboard_typos(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.keyboard_layout {
+        UpdateState::Update(v) => {
+            index.main.put_keyboard_layout(writer, v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_keyboard_layout(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.optional_words {
+        UpdateState::Update(v) => {
+            index.main.put_optional_words(writer, v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_optional_words(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.numeric_tolerance {
+        UpdateState::Update(v) => {
+            index.main.put_numeric_tolerance(writer, v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_numeric_tolerance(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.normalize_units {
+        UpdateState::Update(v) => {
+            index.main.put_normalize_units(writer, v)?;
+            must_reindex = true;
+        },
+        UpdateState::Clear => {
+            index.main.delete_normalize_units(writer)?;
+            must_reindex = true;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    // the types are checked by the next document additions, the indexed documents are kept
+    match settings.field_types {
+        UpdateState::Update(v) => {
+            index.main.put_field_types(writer, &v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_field_types(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    // excluding an attribute doesn't remove it from the stored documents, they must be added again
+    match settings.excluded_attributes {
+        UpdateState::Update(v) => {
+            index.main.put_excluded_attributes(writer, &v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_excluded_attributes(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.max_indexed_words {
+        UpdateState::Update(v) => {
+            index.main.put_max_indexed_words(writer, &v)?;
+            must_reindex = true;
+        },
+        UpdateState::Clear => {
+            index.main.delete_max_indexed_words(writer)?;
+            must_reindex = true;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.min_prefix_length {
+        UpdateState::Update(v) => {
+            index.main.put_min_prefix_length(writer, v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_min_prefix_length(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
+    match settings.query_normalization {
+        UpdateState::Update(v) => {
+            index.main.put_query_normalization(writer, &v.lowercase_rewrites())?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_query_normalization(writer)?;
         },
         UpdateState::Nothing => (),
     }
@@ -114,6 +252,18 @@ pub fn apply_settings_update(
         UpdateState::Nothing => (),
     }
 
+    // the aliases are checked against the schema updated with the attributes of the settings
+    match settings.attribute_aliases {
+        UpdateState::Update(v) => {
+            check_attribute_aliases(&schema, &v)?;
+            index.main.put_attribute_aliases(writer, &v)?;
+        },
+        UpdateState::Clear => {
+            index.main.delete_attribute_aliases(writer)?;
+        },
+        UpdateState::Nothing => (),
+    }
+
     index.main.put_schema(writer, &schema)?;
 
     match settings.stop_words {
@@ -143,6 +293,22 @@ pub fn apply_settings_update(
     Ok(())
 }
 
+/// An alias can't be named like an attribute, it would hide it from the queries,
+/// and must name an attribute of the schema.
+fn check_attribute_aliases(schema: &Schema, aliases: &BTreeMap<String, String>) -> MResult<()> {
+    for (alias, attribute) in aliases {
+        if schema.id(alias).is_some() {
+            let reason = "an attribute already has this name".to_string();
+            return Err(Error::InvalidAttributeAlias { alias: alias.clone(), reason });
+        }
+        if schema.id(attribute).is_none() {
+            let reason = format!("the attribute {:?} doesn't exist", attribute);
+            return Err(Error::InvalidAttributeAlias { alias: alias.clone(), reason });
+        }
+    }
+    Ok(())
+}
+
 fn apply_attributes_for_faceting_update(
     writer: &mut heed::RwTxn<MainT>,
     index: &store::Index,
@@ -0,0 +1,309 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::database::{MainT, UpdateT};
+
+#[derive(Default)]
+struct Tickets {
+    next: u64,
+    serving: u64,
+    /// The tasks waiting for their turn, woken when it comes.
+    wakers: HashMap<u64, Waker>,
+    /// The tickets of the waits that have been given up, skipped when their turn comes.
+    abandoned: HashSet<u64>,
+}
+
+impl Tickets {
+    fn take(&mut self) -> u64 {
+        let ticket = self.next;
+        self.next += 1;
+        ticket
+    }
+
+    fn advance(&mut self) {
+        self.serving += 1;
+        while self.abandoned.remove(&self.serving) {
+            self.serving += 1;
+        }
+        if let Some(waker) = self.wakers.remove(&self.serving) {
+            waker.wake();
+        }
+    }
+}
+
+/// A FIFO of the threads and the tasks waiting to open a write transaction on an environment.
+///
+/// lmdb allows a single write transaction per environment, whatever the index it touches,
+/// and its writer lock gives no guarantee on the order in which the waiting threads get it.
+/// The writers take a ticket and open their transaction in the order of their tickets, once
+/// its turn has come the transaction is opened right away, all the writers using the queue.
+#[derive(Default)]
+struct Queue {
+    tickets: Mutex<Tickets>,
+    turn: Condvar,
+    waiting: AtomicUsize,
+    acquired: AtomicU64,
+    total_wait_us: AtomicU64,
+    max_wait_us: AtomicU64,
+}
+
+impl Queue {
+    /// Blocks the thread until its turn comes.
+    fn acquire(self: &Arc<Self>) -> Turn {
+        let start = Instant::now();
+
+        let mut tickets = self.tickets.lock().unwrap();
+        let ticket = tickets.take();
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        while tickets.serving != ticket {
+            tickets = self.turn.wait(tickets).unwrap();
+        }
+        drop(tickets);
+
+        self.acquired(start)
+    }
+
+    /// Takes a ticket now and resolves when its turn comes, without blocking the thread.
+    fn acquire_async(self: &Arc<Self>) -> WaitTurn {
+        let ticket = self.tickets.lock().unwrap().take();
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        WaitTurn { queue: self.clone(), ticket, start: Instant::now(), done: false }
+    }
+
+    fn acquired(self: &Arc<Self>, start: Instant) -> Turn {
+        let waited = start.elapsed().as_micros() as u64;
+        self.waiting.fetch_sub(1, Ordering::Relaxed);
+        self.acquired.fetch_add(1, Ordering::Relaxed);
+        self.total_wait_us.fetch_add(waited, Ordering::Relaxed);
+
+        let mut max = self.max_wait_us.load(Ordering::Relaxed);
+        while max < waited {
+            match self.max_wait_us.compare_exchange_weak(max, waited, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => max = current,
+            }
+        }
+
+        Turn(self.clone())
+    }
+
+    fn stats(&self) -> WriteQueueStats {
+        WriteQueueStats {
+            waiting: self.waiting.load(Ordering::Relaxed),
+            acquired: self.acquired.load(Ordering::Relaxed),
+            total_wait_us: self.total_wait_us.load(Ordering::Relaxed),
+            max_wait_us: self.max_wait_us.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct Turn(Arc<Queue>);
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        let mut tickets = self.0.tickets.lock().unwrap();
+        tickets.advance();
+        self.0.turn.notify_all();
+    }
+}
+
+/// The wait of a task for its turn, the turn is passed on if the wait is dropped.
+struct WaitTurn {
+    queue: Arc<Queue>,
+    ticket: u64,
+    start: Instant,
+    done: bool,
+}
+
+impl Future for WaitTurn {
+    type Output = Turn;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Turn> {
+        let mut tickets = self.queue.tickets.lock().unwrap();
+        if tickets.serving == self.ticket {
+            drop(tickets);
+            self.done = true;
+            Poll::Ready(self.queue.acquired(self.start))
+        } else {
+            tickets.wakers.insert(self.ticket, cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for WaitTurn {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        let mut tickets = self.queue.tickets.lock().unwrap();
+        tickets.wakers.remove(&self.ticket);
+        if tickets.serving == self.ticket {
+            tickets.advance();
+            self.queue.turn.notify_all();
+        } else {
+            tickets.abandoned.insert(self.ticket);
+        }
+        self.queue.waiting.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A write transaction opened in turn, the next writer in the queue can open
+/// its own once this one is committed or aborted.
+pub struct QueuedTxn<'a, T> {
+    txn: heed::RwTxn<'a, T>,
+    _turn: Turn,
+}
+
+impl<T> QueuedTxn<'_, T> {
+    pub fn commit(self) -> heed::Result<()> {
+        self.txn.commit()
+    }
+
+    pub fn abort(self) {
+        self.txn.abort()
+    }
+}
+
+impl<'a, T> Deref for QueuedTxn<'a, T> {
+    type Target = heed::RwTxn<'a, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.txn
+    }
+}
+
+impl<T> DerefMut for QueuedTxn<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.txn
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteQueueStats {
+    pub waiting: usize,
+    pub acquired: u64,
+    pub total_wait_us: u64,
+    pub max_wait_us: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteQueuesStats {
+    pub main: WriteQueueStats,
+    pub update: WriteQueueStats,
+}
+
+/// Orders the write transactions opened on each environment of a database,
+/// by the update loops as well as by the users of the database.
+#[derive(Clone, Default)]
+pub(crate) struct WriteQueues {
+    main: Arc<Queue>,
+    update: Arc<Queue>,
+}
+
+impl WriteQueues {
+    pub(crate) fn main_write_txn<'a>(&self, env: &'a heed::Env) -> heed::Result<QueuedTxn<'a, MainT>> {
+        let turn = self.main.acquire();
+        let txn = env.typed_write_txn::<MainT>()?;
+        Ok(QueuedTxn { txn, _turn: turn })
+    }
+
+    pub(crate) fn update_write_txn<'a>(&self, env: &'a heed::Env) -> heed::Result<QueuedTxn<'a, UpdateT>> {
+        let turn = self.update.acquire();
+        let txn = env.typed_write_txn::<UpdateT>()?;
+        Ok(QueuedTxn { txn, _turn: turn })
+    }
+
+    pub(crate) async fn main_write_txn_async<'a>(&self, env: &'a heed::Env) -> heed::Result<QueuedTxn<'a, MainT>> {
+        let turn = self.main.acquire_async().await;
+        let txn = env.typed_write_txn::<MainT>()?;
+        Ok(QueuedTxn { txn, _turn: turn })
+    }
+
+    pub(crate) async fn update_write_txn_async<'a>(&self, env: &'a heed::Env) -> heed::Result<QueuedTxn<'a, UpdateT>> {
+        let turn = self.update.acquire_async().await;
+        let txn = env.typed_write_txn::<UpdateT>()?;
+        Ok(QueuedTxn { txn, _turn: turn })
+    }
+
+    pub(crate) fn stats(&self) -> WriteQueuesStats {
+        WriteQueuesStats {
+            main: self.main.stats(),
+            update: self.update.stats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn wait_for(queue: &Queue, waiting: usize) {
+        while queue.waiting.load(Ordering::Relaxed) != waiting {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn turns_are_given_in_order() {
+        let queue = Arc::new(Queue::default());
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let first = queue.acquire();
+        let mut handles = Vec::new();
+        for i in 0..4 {
+            let (thread_queue, thread_order) = (queue.clone(), order.clone());
+            handles.push(thread::spawn(move || {
+                let _turn = thread_queue.acquire();
+                thread_order.lock().unwrap().push(i);
+            }));
+            // wait for the thread to take its ticket before spawning the next one
+            wait_for(&queue, i + 1);
+        }
+
+        drop(first);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2, 3]);
+        let stats = queue.stats();
+        assert_eq!(stats.waiting, 0);
+        assert_eq!(stats.acquired, 5);
+    }
+
+    #[test]
+    fn abandoned_waits_pass_their_turn() {
+        let queue = Arc::new(Queue::default());
+
+        let first = queue.acquire();
+        let abandoned = queue.acquire_async();
+
+        let thread_queue = queue.clone();
+        let handle = thread::spawn(move || drop(thread_queue.acquire()));
+        wait_for(&queue, 2);
+
+        // the task gives up before its turn, the thread after it gets the turn
+        drop(abandoned);
+        drop(first);
+        handle.join().unwrap();
+
+        assert_eq!(queue.stats().waiting, 0);
+        assert_eq!(queue.stats().acquired, 2);
+        drop(queue.acquire());
+    }
+}
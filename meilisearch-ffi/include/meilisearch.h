@@ -0,0 +1,21 @@
+#ifndef MEILISEARCH_H
+#define MEILISEARCH_H
+
+#include <stdint.h>
+
+/* The strings returned by the functions below are JSON objects, errors are
+ * reported as {"message": "..."}, they must be freed with meili_string_free. */
+
+typedef struct MeiliDatabase MeiliDatabase;
+
+MeiliDatabase *meili_database_open(const char *path);
+void meili_database_close(MeiliDatabase *database);
+
+char *meili_index_open(MeiliDatabase *database, const char *index_uid, const char *primary_key);
+char *meili_documents_add(MeiliDatabase *database, const char *index_uid, const char *documents);
+char *meili_update_status(MeiliDatabase *database, const char *index_uid, uint64_t update_id);
+char *meili_search(MeiliDatabase *database, const char *index_uid, const char *query);
+
+void meili_string_free(char *string);
+
+#endif
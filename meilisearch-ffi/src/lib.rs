@@ -0,0 +1,368 @@
+//! A C ABI over meilisearch-core, to embed the search engine in other languages.
+//!
+//! Every function exchanges JSON strings, the returned strings are owned by the
+//! caller and must be released with `meili_string_free`. The errors are returned
+//! as `{"message": "..."}` objects, only `meili_database_open` returns a null pointer.
+
+use std::collections::HashSet;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::{fmt, ptr};
+
+use meilisearch_core::criterion::*;
+use meilisearch_core::settings::DEFAULT_RANKING_RULES;
+use meilisearch_core::update::find_primary_key;
+use meilisearch_core::{Database, DatabaseOptions, Index, MainT, RankedMap, Schema};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+/// An opened database, must be closed with `meili_database_close`.
+pub struct MeiliDatabase {
+    database: Database,
+}
+
+struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<meilisearch_core::Error> for Error {
+    fn from(error: meilisearch_core::Error) -> Error {
+        Error(error.to_string())
+    }
+}
+
+impl From<heed::Error> for Error {
+    fn from(error: heed::Error) -> Error {
+        Error(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error(format!("invalid JSON: {}", error))
+    }
+}
+
+unsafe fn str_arg<'a>(name: &str, ptr: *const c_char) -> Result<&'a str, Error> {
+    if ptr.is_null() {
+        return Err(Error(format!("{} must not be null", name)));
+    }
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map_err(|_| Error(format!("{} is not a valid UTF-8 string", name)))
+}
+
+unsafe fn database_arg<'a>(database: *mut MeiliDatabase) -> Result<&'a Database, Error> {
+    database
+        .as_ref()
+        .map(|db| &db.database)
+        .ok_or_else(|| Error(String::from("database must not be null")))
+}
+
+fn open_index(database: &Database, index_uid: &str) -> Result<Index, Error> {
+    database
+        .open_index(index_uid)
+        .ok_or_else(|| Error(format!("Index {} not found", index_uid)))
+}
+
+/// Converts the result into a JSON string, the panics must not unwind through the C frames.
+fn respond<F>(f: F) -> *mut c_char
+where
+    F: FnOnce() -> Result<Value, Error>,
+{
+    let value = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(error)) => json!({ "message": error.to_string() }),
+        Err(_) => json!({ "message": "internal error" }),
+    };
+
+    // a serialized JSON never contains a nul byte, the strings are escaped
+    let string = serde_json::to_string(&value).unwrap_or_default();
+    CString::new(string).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Opens the database at `path`, creating it if needed, returns null on failure.
+///
+/// # Safety
+///
+/// `path` must be a valid nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn meili_database_open(path: *const c_char) -> *mut MeiliDatabase {
+    let path = match str_arg("path", path) {
+        Ok(path) => path,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    match catch_unwind(|| Database::open_or_create(path, DatabaseOptions::default())) {
+        Ok(Ok(database)) => Box::into_raw(Box::new(MeiliDatabase { database })),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Waits for the updates being processed and closes the database.
+///
+/// # Safety
+///
+/// `database` must have been returned by `meili_database_open` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn meili_database_close(database: *mut MeiliDatabase) {
+    if !database.is_null() {
+        let database = Box::from_raw(database);
+        let _ = catch_unwind(AssertUnwindSafe(|| database.database.close()));
+    }
+}
+
+/// Opens the index, creating it with the optional primary key if it does not exist.
+///
+/// # Safety
+///
+/// `database` must be an opened database, `index_uid` a valid nul terminated string
+/// and `primary_key` either null or a valid nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn meili_index_open(
+    database: *mut MeiliDatabase,
+    index_uid: *const c_char,
+    primary_key: *const c_char,
+) -> *mut c_char {
+    respond(|| {
+        let database = database_arg(database)?;
+        let index_uid = str_arg("index_uid", index_uid)?;
+        let primary_key = if primary_key.is_null() { None } else { Some(str_arg("primary_key", primary_key)?) };
+
+        let index = match database.open_index(index_uid) {
+            Some(index) => index,
+            None => {
+                let index = database.create_index(index_uid)?;
+                let mut writer = database.main_write_txn()?;
+                index.main.put_name(&mut writer, index_uid)?;
+                if let Some(primary_key) = primary_key {
+                    if let Some(mut schema) = index.main.schema(&writer)? {
+                        schema.set_primary_key(primary_key).map_err(|e| Error(e.to_string()))?;
+                        index.main.put_schema(&mut writer, &schema)?;
+                    }
+                }
+                writer.commit()?;
+                index
+            }
+        };
+
+        let reader = database.main_read_txn()?;
+        let primary_key = index.main.schema(&reader)?.and_then(|s| s.primary_key().map(ToOwned::to_owned));
+
+        Ok(json!({ "uid": index_uid, "primaryKey": primary_key }))
+    })
+}
+
+/// Enqueues the addition of a JSON array of documents, returns the update id.
+///
+/// # Safety
+///
+/// `database` must be an opened database, `index_uid` and `documents` valid nul terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn meili_documents_add(
+    database: *mut MeiliDatabase,
+    index_uid: *const c_char,
+    documents: *const c_char,
+) -> *mut c_char {
+    respond(|| {
+        let database = database_arg(database)?;
+        let index = open_index(database, str_arg("index_uid", index_uid)?)?;
+        let documents: Vec<Map<String, Value>> = serde_json::from_str(str_arg("documents", documents)?)?;
+
+        let reader = database.main_read_txn()?;
+        let mut schema = index.main.schema(&reader)?.ok_or_else(|| Error(String::from("schema not found")))?;
+        drop(reader);
+
+        if schema.primary_key().is_none() {
+            let id = documents
+                .first()
+                .and_then(|document| find_primary_key(document.keys()))
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| Error(String::from("Could not infer a primary key")))?;
+
+            let mut writer = database.main_write_txn()?;
+            schema.set_primary_key(&id).map_err(|e| Error(e.to_string()))?;
+            index.main.put_schema(&mut writer, &schema)?;
+            writer.commit()?;
+        }
+
+        let mut addition = index.documents_addition();
+        for document in documents {
+            addition.update_document(document);
+        }
+
+        let mut update_writer = database.update_write_txn()?;
+        let update_id = addition.finalize(&mut update_writer)?;
+        update_writer.commit()?;
+
+        Ok(json!({ "updateId": update_id }))
+    })
+}
+
+/// Returns the status of an update, enqueued or processed.
+///
+/// # Safety
+///
+/// `database` must be an opened database and `index_uid` a valid nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn meili_update_status(
+    database: *mut MeiliDatabase,
+    index_uid: *const c_char,
+    update_id: u64,
+) -> *mut c_char {
+    respond(|| {
+        let database = database_arg(database)?;
+        let index = open_index(database, str_arg("index_uid", index_uid)?)?;
+
+        let reader = database.update_read_txn()?;
+        match index.update_status(&reader, update_id)? {
+            Some(status) => Ok(serde_json::to_value(status)?),
+            None => Err(Error(format!("Update {} not found", update_id))),
+        }
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SearchQuery {
+    q: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+/// The criteria defined by the ranking rules of the index, like the HTTP server does.
+fn criteria<'a>(
+    reader: &heed::RoTxn<MainT>,
+    index: &Index,
+    ranked_map: &'a RankedMap,
+    schema: &Schema,
+) -> Result<Option<Criteria<'a>>, Error> {
+    let attribute_weights = index.main.attribute_weights(reader)?;
+    let ranking_rules = match (index.main.ranking_rules(reader)?, &attribute_weights) {
+        (Some(ranking_rules), _) => ranking_rules,
+        (None, Some(_)) => DEFAULT_RANKING_RULES.to_vec(),
+        (None, None) => return Ok(None),
+    };
+
+    let mut builder = CriteriaBuilder::with_capacity(7 + ranking_rules.len());
+    for rule in &ranking_rules {
+        builder.push_ranking_rule(rule, ranked_map, schema, attribute_weights.as_ref());
+    }
+    builder.push(DocumentId);
+
+    Ok(Some(builder.build()))
+}
+
+/// Searches the index with a JSON query of the form `{"q": "...", "offset": 0, "limit": 20}`.
+///
+/// # Safety
+///
+/// `database` must be an opened database, `index_uid` and `query` valid nul terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn meili_search(
+    database: *mut MeiliDatabase,
+    index_uid: *const c_char,
+    query: *const c_char,
+) -> *mut c_char {
+    respond(|| {
+        let database = database_arg(database)?;
+        let index = open_index(database, str_arg("index_uid", index_uid)?)?;
+        let query: SearchQuery = serde_json::from_str(str_arg("query", query)?)?;
+        let offset = query.offset.unwrap_or(0);
+        let limit = query.limit.unwrap_or(20);
+
+        let reader = database.main_read_txn()?;
+        let schema = index.main.schema(&reader)?.ok_or_else(|| Error(String::from("schema not found")))?;
+        let ranked_map = index.main.ranked_map(&reader)?.unwrap_or_default();
+
+        let builder = match criteria(&reader, &index, &ranked_map, &schema)? {
+            Some(criteria) => index.query_builder_with_criteria(criteria),
+            None => index.query_builder(),
+        };
+        let result = builder.query(&reader, &query.q, offset..offset + limit)?;
+
+        let displayed: HashSet<_> = schema.displayed_name();
+        let mut hits = Vec::with_capacity(result.documents.len());
+        for document in result.documents {
+            let document: Option<Map<String, Value>> = index.document(&reader, Some(&displayed), document.id)?;
+            hits.extend(document);
+        }
+
+        Ok(json!({
+            "hits": hits,
+            "offset": offset,
+            "limit": limit,
+            "nbHits": result.nb_hits,
+            "exhaustiveNbHits": result.exhaustive_nb_hit,
+        }))
+    })
+}
+
+/// Releases a string returned by this library.
+///
+/// # Safety
+///
+/// `string` must have been returned by this library and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn meili_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::thread;
+    use std::time::Duration;
+
+    unsafe fn call(string: *mut c_char) -> Value {
+        let value = serde_json::from_slice(CStr::from_ptr(string).to_bytes()).unwrap();
+        meili_string_free(string);
+        value
+    }
+
+    #[test]
+    fn add_documents_and_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = CString::new(dir.path().to_str().unwrap()).unwrap();
+        let uid = CString::new("movies").unwrap();
+
+        unsafe {
+            let database = meili_database_open(path.as_ptr());
+            assert!(!database.is_null());
+
+            let index = call(meili_index_open(database, uid.as_ptr(), ptr::null()));
+            assert_eq!(index["uid"], "movies");
+
+            let documents = CString::new(r#"[{ "id": 1, "title": "Carol" }, { "id": 2, "title": "Wonder Woman" }]"#).unwrap();
+            let update = call(meili_documents_add(database, uid.as_ptr(), documents.as_ptr()));
+            let update_id = update["updateId"].as_u64().unwrap();
+
+            // wait for the documents to be indexed
+            loop {
+                let status = call(meili_update_status(database, uid.as_ptr(), update_id));
+                if status["status"] == "processed" {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            let query = CString::new(r#"{ "q": "carol" }"#).unwrap();
+            let result = call(meili_search(database, uid.as_ptr(), query.as_ptr()));
+            assert_eq!(result["hits"], serde_json::json!([{ "id": 1, "title": "Carol" }]));
+
+            let unknown = CString::new("unknown").unwrap();
+            let result = call(meili_search(database, unknown.as_ptr(), query.as_ptr()));
+            assert_eq!(result["message"], "Index unknown not found");
+
+            meili_database_close(database);
+        }
+    }
+}
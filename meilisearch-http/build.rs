@@ -1,5 +1,16 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::{env, fs};
+
+use serde_json::{json, Map, Value};
 use vergen::{generate_cargo_keys, ConstantsFlags};
 
+use self::table::{Body, Route, ROUTES};
+
+mod table {
+    include!("src/routes/table.rs");
+}
+
 fn main() {
     // Setup the flags, toggling off the 'SEMVER_FROM_CARGO_PKG' flag
     let mut flags = ConstantsFlags::all();
@@ -7,4 +18,368 @@ fn main() {
 
     // Generate the 'cargo:' key output
     generate_cargo_keys(ConstantsFlags::all()).expect("Unable to generate the cargo keys!");
+
+    // Generate the OpenAPI document served by the `/openapi.json` route
+    let out_dir = env::var("OUT_DIR").unwrap();
+    println!("cargo:rerun-if-changed=src/routes/table.rs");
+    let document = openapi_document(ROUTES, &["src/routes", "src/helpers", "../meilisearch-core/src/settings.rs"]);
+    fs::write(Path::new(&out_dir).join("openapi.json"), document.to_string())
+        .expect("Unable to write the OpenAPI document!");
+
+    // Generate the server side of the gRPC service
+    #[cfg(feature = "grpc")]
+    tonic_build::configure()
+        .build_client(false)
+        .compile(&["proto/meilisearch.proto"], &["proto"])
+        .expect("Unable to compile the gRPC service!");
+}
+
+/// A struct or a unit-only enum declared in the source files,
+/// serialized or deserialized by the routes.
+struct TypeDef {
+    rename_all: Option<String>,
+    fields: Vec<FieldDef>,
+    variants: Vec<String>,
+}
+
+struct FieldDef {
+    name: String,
+    rename: Option<String>,
+    ty: String,
+    optional: bool,
+}
+
+/// Returns the contents of the rust source files at the given path.
+fn source_files(path: &str) -> Vec<String> {
+    println!("cargo:rerun-if-changed={}", path);
+
+    let path = Path::new(path);
+    let mut paths = if path.is_dir() {
+        fs::read_dir(path).unwrap().filter_map(|e| e.ok()).map(|e| e.path()).collect()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    paths.sort();
+
+    let mut files = Vec::new();
+    for path in paths {
+        if path.extension().map_or(false, |ext| ext == "rs") {
+            println!("cargo:rerun-if-changed={}", path.display());
+            files.push(fs::read_to_string(&path).unwrap());
+        }
+    }
+    files
+}
+
+/// Describes the declared routes, the types they use can be declared
+/// in any of the `type_sources` files and directories.
+fn openapi_document(routes: &[Route], type_sources: &[&str]) -> Value {
+    let mut types = BTreeMap::new();
+    for source in type_sources.iter().flat_map(|path| source_files(path)) {
+        parse_types(&source, &mut types);
+    }
+
+    let mut used = Vec::new();
+    let mut paths = Map::new();
+    for route in routes {
+        let operation = operation(route, &types, &mut used);
+        let item = paths.entry(route.path.to_string()).or_insert_with(|| json!({}));
+        item[route.method.to_lowercase().as_str()] = operation;
+    }
+
+    // only the types reachable from the routes are described
+    let mut schemas = Map::new();
+    while let Some(name) = used.pop() {
+        if schemas.contains_key(&name) {
+            continue;
+        }
+        let schema = type_def_schema(&types[&name], &types, &mut used);
+        schemas.insert(name, schema);
+    }
+    schemas.insert(
+        "Error".to_string(),
+        json!({
+            "type": "object",
+            "properties": { "message": { "type": "string" } },
+            "required": ["message"],
+        }),
+    );
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "MeiliSearch",
+            "version": env::var("CARGO_PKG_VERSION").unwrap(),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "apiKey": { "type": "apiKey", "in": "header", "name": "X-Meili-API-Key" },
+            },
+        },
+    })
+}
+
+/// Returns the value of the `key = "value"` pair of an attribute.
+fn attribute_value<'a>(attribute: &'a str, key: &str) -> Option<&'a str> {
+    let start = attribute.find(&format!("{} = \"", key))? + key.len() + 4;
+    attribute[start..].split('"').next()
+}
+
+fn parse_types(source: &str, types: &mut BTreeMap<String, TypeDef>) {
+    let lines: Vec<&str> = source.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim_start_matches("pub ").trim_start_matches("pub(crate) ");
+        let (is_enum, name) = if line.starts_with("struct ") {
+            (false, &line["struct ".len()..])
+        } else if line.starts_with("enum ") {
+            (true, &line["enum ".len()..])
+        } else {
+            continue;
+        };
+        if !name.ends_with('{') || name.contains('<') {
+            continue;
+        }
+        let name = name.trim_end_matches('{').trim();
+
+        let attributes = lines[..i].iter().rev().take_while(|l| l.starts_with("#[") || l.starts_with("///"));
+        if !attributes.clone().any(|l| l.contains("Serialize") || l.contains("Deserialize")) {
+            continue;
+        }
+        let rename_all = attributes.clone().find_map(|l| attribute_value(l, "rename_all")).map(String::from);
+
+        let mut def = TypeDef { rename_all, fields: Vec::new(), variants: Vec::new() };
+        let mut optional = false;
+        let mut rename = None;
+        let mut skip = false;
+        for member in lines[i + 1..].iter().map(|l| l.trim()).take_while(|l| *l != "}") {
+            if member.starts_with("#[") {
+                optional |= member.contains("skip_serializing_if") || member.contains("default");
+                skip |= member.contains("flatten") || member.contains("skip)") || member.contains("skip_serializing)");
+                rename = rename.or(attribute_value(member, "rename").map(String::from));
+                continue;
+            }
+            if member.is_empty() || member.starts_with("//") {
+                continue;
+            }
+
+            if is_enum {
+                if member.contains(|c| c == '(' || c == '{') {
+                    // only the enums serialized as strings are described
+                    def.variants.clear();
+                    break;
+                }
+                def.variants.push(rename.take().unwrap_or_else(|| member.trim_end_matches(',').to_string()));
+            } else if let Some(colon) = member.find(':') {
+                let member = member.trim_start_matches("pub ").trim_start_matches("pub(crate) ");
+                let colon = member.find(':').unwrap_or(colon);
+                let ty = member[colon + 1..].trim().trim_end_matches(',').to_string();
+                if !skip {
+                    def.fields.push(FieldDef {
+                        name: member[..colon].trim().to_string(),
+                        rename: rename.take(),
+                        optional: optional || ty.starts_with("Option<"),
+                        ty,
+                    });
+                }
+            }
+            optional = false;
+            rename = None;
+            skip = false;
+        }
+
+        if !is_enum || !def.variants.is_empty() {
+            types.insert(name.to_string(), def);
+        }
+    }
+}
+
+fn operation(route: &Route, types: &BTreeMap<String, TypeDef>, used: &mut Vec<String>) -> Value {
+    // the handlers of different modules can have the same name
+    let mut operation = json!({ "operationId": format!("{}::{}", route.tag, route.handler) });
+    // the routes of the `routes` module itself are not grouped
+    if route.tag != "mod" {
+        operation["tags"] = json!([route.tag]);
+    }
+
+    if !route.description.is_empty() {
+        operation["summary"] = json!(route.description[0]);
+        operation["description"] = json!(route.description.join(" "));
+    }
+
+    let mut parameters = Vec::new();
+    let path_struct = route.path_params.and_then(|name| types.get(name));
+    for segment in route.path.split('/').filter(|s| s.starts_with('{')) {
+        let name = segment.trim_start_matches('{').trim_end_matches('}');
+        let schema = path_struct
+            .and_then(|s| s.fields.iter().find(|f| f.name == name))
+            .map_or(json!({ "type": "string" }), |f| type_schema(&f.ty, types, used));
+        parameters.push(json!({ "name": name, "in": "path", "required": true, "schema": schema }));
+    }
+    if let Some(query) = route.query.and_then(|name| types.get(name)) {
+        for field in &query.fields {
+            parameters.push(json!({
+                "name": field_name(query, field),
+                "in": "query",
+                "required": !field.optional,
+                "schema": type_schema(&field.ty, types, used),
+            }));
+        }
+    }
+    if !parameters.is_empty() {
+        operation["parameters"] = json!(parameters);
+    }
+
+    match route.body {
+        Body::Json(body) => {
+            let schema = type_schema(body, types, used);
+            operation["requestBody"] = json!({ "required": true, "content": { "application/json": { "schema": schema } } });
+        }
+        Body::Raw => {
+            operation["requestBody"] = json!({ "required": true, "content": { "*/*": { "schema": { "type": "string" } } } });
+        }
+        Body::None => (),
+    }
+
+    let mut responses = Map::new();
+    for response in route.responses {
+        let mut value = json!({ "description": status_description(response.status) });
+        if response.json {
+            let schema = response.ty.map_or(json!({}), |ty| type_schema(ty, types, used));
+            value["content"] = json!({ "application/json": { "schema": schema } });
+        }
+        responses.insert(response.status.to_string(), value);
+    }
+    if route.fallible {
+        let error = json!({ "$ref": "#/components/schemas/Error" });
+        responses.insert(
+            "default".to_string(),
+            json!({ "description": "Error", "content": { "application/json": { "schema": error } } }),
+        );
+    }
+    operation["responses"] = Value::Object(responses);
+
+    match route.key {
+        Some(key) => {
+            operation["security"] = json!([{ "apiKey": [] }]);
+            let accepted = if key == "Settings" { "private".to_string() } else { key.to_lowercase() };
+            operation["x-meili-key"] = json!(accepted);
+            operation["x-meili-admin-allowlist"] = json!(key == "Settings" || key == "Admin");
+        }
+        None => operation["security"] = json!([]),
+    }
+    operation["x-meili-write"] = json!(route.write);
+
+    operation
+}
+
+fn status_description(status: u16) -> &'static str {
+    match status {
+        200 => "Ok",
+        201 => "Created",
+        202 => "Accepted",
+        _ => "No Content",
+    }
+}
+
+fn type_def_schema(def: &TypeDef, types: &BTreeMap<String, TypeDef>, used: &mut Vec<String>) -> Value {
+    if !def.variants.is_empty() {
+        let variants: Vec<_> = def.variants.iter().map(|v| rename(v, def.rename_all.as_deref())).collect();
+        return json!({ "type": "string", "enum": variants });
+    }
+
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for field in &def.fields {
+        let name = field_name(def, field);
+        if !field.optional {
+            required.push(name.clone());
+        }
+        properties.insert(name, type_schema(&field.ty, types, used));
+    }
+
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+    schema
+}
+
+fn field_name(def: &TypeDef, field: &FieldDef) -> String {
+    match &field.rename {
+        Some(name) => name.clone(),
+        None => rename(&field.name, def.rename_all.as_deref()),
+    }
+}
+
+/// Applies the serde `rename_all` rule to a snake case field or a pascal case variant.
+fn rename(name: &str, rule: Option<&str>) -> String {
+    match rule {
+        Some("lowercase") => name.to_lowercase(),
+        Some("camelCase") => {
+            let mut camel = String::new();
+            let mut upper = false;
+            for (i, c) in name.chars().enumerate() {
+                if c == '_' {
+                    upper = true;
+                } else if upper {
+                    camel.extend(c.to_uppercase());
+                    upper = false;
+                } else if i == 0 {
+                    camel.extend(c.to_lowercase());
+                } else {
+                    camel.push(c);
+                }
+            }
+            camel
+        }
+        _ => name.to_string(),
+    }
+}
+
+fn type_schema(ty: &str, types: &BTreeMap<String, TypeDef>, used: &mut Vec<String>) -> Value {
+    let ty = ty.trim();
+    let (outer, inner) = match ty.find('<') {
+        Some(start) => (&ty[..start], &ty[start + 1..ty.len() - 1]),
+        None => (ty, ""),
+    };
+    let outer = outer.rsplit("::").next().unwrap();
+
+    match outer {
+        "Option" | "Box" | "Arc" => type_schema(inner, types, used),
+        "Vec" | "HashSet" | "BTreeSet" | "IndexSet" => json!({ "type": "array", "items": type_schema(inner, types, used) }),
+        "HashMap" | "BTreeMap" | "IndexMap" => {
+            let value = split_generics(inner).pop().unwrap_or_default();
+            json!({ "type": "object", "additionalProperties": type_schema(&value, types, used) })
+        }
+        "String" | "&str" | "char" | "IpNetwork" => json!({ "type": "string" }),
+        "DateTime" => json!({ "type": "string", "format": "date-time" }),
+        "bool" => json!({ "type": "boolean" }),
+        "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize" => json!({ "type": "integer" }),
+        "f32" | "f64" => json!({ "type": "number" }),
+        name if types.contains_key(name) => {
+            used.push(name.to_string());
+            json!({ "$ref": format!("#/components/schemas/{}", name) })
+        }
+        _ => json!({}),
+    }
+}
+
+fn split_generics(generics: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut depth = 0;
+    for c in generics.chars() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => (),
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+    parts.into_iter().map(|p| p.trim().to_string()).collect()
 }
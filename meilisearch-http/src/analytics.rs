@@ -1,8 +1,9 @@
 use std::hash::{Hash, Hasher};
-use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crossbeam_channel::Receiver;
 use log::error;
+use meilisearch_http::helpers::background::wait_or_stop;
 use serde::Serialize;
 use serde_qs as qs;
 use siphasher::sip::SipHasher;
@@ -23,7 +24,7 @@ struct AmplitudeRequest<'a> {
     event: &'a str,
 }
 
-pub fn analytics_sender() {
+pub fn analytics_sender(stop: Receiver<()>) {
     let username = whoami::username();
     let hostname = whoami::hostname();
     let platform = whoami::platform();
@@ -64,6 +65,9 @@ pub fn analytics_sender() {
             error!("Unsuccessful call to Amplitude: {}", body);
         }
 
-        thread::sleep(Duration::from_secs(86_400)) // one day
+        // one day
+        if wait_or_stop(&stop, Duration::from_secs(86_400)) {
+            break;
+        }
     }
 }
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use meilisearch_core::{Database, DatabaseOptions};
+
+use crate::helpers::meilisearch::IndexSearchExt;
+use crate::option::{BenchOpt, Opt};
+
+/// The latencies of the searches, the whole search or one of its phases.
+#[derive(Default)]
+struct Latencies {
+    durations: Vec<Duration>,
+}
+
+impl Latencies {
+    fn push(&mut self, duration: Duration) {
+        self.durations.push(duration);
+    }
+
+    /// Returns the duration under which the given fraction of the searches ran.
+    fn percentile(&self, fraction: f64) -> Duration {
+        let rank = (fraction * self.durations.len() as f64).ceil() as usize;
+        self.durations[rank.saturating_sub(1).min(self.durations.len() - 1)]
+    }
+
+    fn mean(&self) -> Duration {
+        let total: Duration = self.durations.iter().sum();
+        total / self.durations.len() as u32
+    }
+
+    fn print(&mut self, name: &str) {
+        self.durations.sort_unstable();
+        println!(
+            "{:<12} mean {:>10.02?}  p50 {:>10.02?}  p90 {:>10.02?}  p99 {:>10.02?}  max {:>10.02?}",
+            name,
+            self.mean(),
+            self.percentile(0.5),
+            self.percentile(0.9),
+            self.percentile(0.99),
+            self.percentile(1.0),
+        );
+    }
+}
+
+/// Replays the queries of the file against the index and prints their latencies.
+///
+/// The searches are the ones of the search route, with the settings of the index, but
+/// are run one after the other and without the HTTP layer. The updates still enqueued
+/// in the database are processed in the meanwhile and can slow the searches down.
+pub fn run(opt: &Opt, bench: &BenchOpt) -> Result<(), Box<dyn Error>> {
+    let queries = fs::read_to_string(&bench.queries)?;
+    let queries: Vec<_> = queries.lines().map(str::trim).filter(|q| !q.is_empty()).collect();
+    if queries.is_empty() {
+        return Err(format!("no query to replay in {}", bench.queries.display()).into());
+    }
+
+    let db_opt = DatabaseOptions {
+        main_map_size: opt.main_map_size,
+        update_map_size: opt.update_map_size,
+        max_readers: opt.max_readers,
+        encryption_key: opt.encryption_key.map(|key| key.0),
+        index_max_data_size: opt.index_max_data_size,
+    };
+    let db = Database::open_or_create(&opt.db_path, db_opt)?;
+    let index = db
+        .open_index(&bench.index)
+        .ok_or_else(|| format!("index {} not found", bench.index))?;
+
+    let reader = db.main_read_txn()?;
+    let mut total = Latencies::default();
+    let mut phases = BTreeMap::<String, Latencies>::new();
+
+    let start = Instant::now();
+    for _ in 0..bench.runs {
+        for query in &queries {
+            let mut search_builder = index.new_search(query.to_string());
+            search_builder.limit(bench.limit);
+
+            let before = Instant::now();
+            let result = search_builder.search(&reader).map_err(|e| e.to_string())?;
+            let elapsed = before.elapsed();
+
+            // a phase can run several times, once per relaxation level of the query
+            let mut search_phases = BTreeMap::<String, Duration>::new();
+            for timing in result.timings {
+                *search_phases.entry(timing.phase).or_default() += timing.duration;
+            }
+            let engine: Duration = search_phases.values().sum();
+            for (phase, duration) in search_phases {
+                phases.entry(phase).or_default().push(duration);
+            }
+            // the documents are read and formatted once the engine is done
+            let formatting = elapsed.checked_sub(engine).unwrap_or_default();
+            phases.entry("formatting".to_string()).or_default().push(formatting);
+            total.push(elapsed);
+        }
+    }
+
+    println!(
+        "{} searches of {} queries on the index {} in {:.02?}",
+        total.durations.len(),
+        queries.len(),
+        bench.index,
+        start.elapsed(),
+    );
+    total.print("total");
+    for (phase, latencies) in &mut phases {
+        latencies.print(phase);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latencies_percentiles() {
+        let mut latencies = Latencies::default();
+        for ms in (1..=100).rev() {
+            latencies.push(Duration::from_millis(ms));
+        }
+        latencies.durations.sort_unstable();
+
+        assert_eq!(latencies.percentile(0.5), Duration::from_millis(50));
+        assert_eq!(latencies.percentile(0.99), Duration::from_millis(99));
+        assert_eq!(latencies.percentile(1.0), Duration::from_millis(100));
+        assert_eq!(latencies.percentile(0.0), Duration::from_millis(1));
+        assert_eq!(latencies.mean(), Duration::from_micros(50_500));
+    }
+}
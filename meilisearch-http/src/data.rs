@@ -1,20 +1,39 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, Utc};
 use heed::types::{SerdeBincode, Str};
-use log::error;
-use meilisearch_core::{Database, DatabaseOptions, Error as MError, MResult, MainT, UpdateT};
+use log::{error, info, warn};
+use meilisearch_core::{Database, DatabaseOptions, Error as MError, Index, MResult, MainT, QueuedTxn, UpdateT};
 use sha2::Digest;
 use sysinfo::Pid;
 
+use crate::error::ResponseError;
+use crate::helpers::audit_log::AuditLog;
+use crate::helpers::usage::UsageCounters;
+use crate::helpers::reader_limit::LimitedTxn;
+use crate::helpers::{IpAllowlist, PayloadLimits, ReadSnapshots, ReaderLimit, RouteMetrics, SearchLog, SearchPool};
+use crate::helpers::{SpanExporter, Usage};
 use crate::index_update_callback;
 use crate::option::Opt;
+use crate::replication::IndexCopyStatus;
+use crate::routes::OPENAPI_DOCUMENT;
+use crate::snapshot::SnapshotOptions;
 
 const LAST_UPDATE_KEY: &str = "last-update";
+const USAGE_KEYS_KEY: &str = "usage-keys";
+const USAGE_INDEXES_KEY: &str = "usage-indexes";
 
 type SerdeDatetime = SerdeBincode<DateTime<Utc>>;
+type SerdeUsage = SerdeBincode<BTreeMap<String, UsageCounters>>;
 
 #[derive(Clone)]
 pub struct Data {
@@ -34,6 +53,29 @@ pub struct DataInner {
     pub db: Arc<Database>,
     pub db_path: String,
     pub api_keys: ApiKeys,
+    pub admin_allowlist: Arc<RwLock<IpAllowlist>>,
+    pub index_copies: Arc<Mutex<HashMap<String, IndexCopyStatus>>>,
+    pub rollover_lock: Arc<Mutex<()>>,
+    pub search_pool: SearchPool,
+    pub reader_limit: ReaderLimit,
+    pub read_snapshots: Arc<ReadSnapshots>,
+    pub payload_limits: PayloadLimits,
+    pub snapshot_options: Option<SnapshotOptions>,
+    pub health_min_disk_space: u64,
+    /// The number of changes kept in the changes log of every index.
+    pub changes_retention: u64,
+    /// The time without a chunk received after which an upload session expires.
+    pub upload_sessions_retention: chrono::Duration,
+    /// The uids of the indexes mounted from an artifact, their writes are rejected.
+    pub read_only_indexes: Arc<HashSet<String>>,
+    /// Whether the instance replicates the indexes of a leader, they are then all read-only.
+    pub follower: bool,
+    pub started_at: DateTime<Utc>,
+    pub audit_log: Option<Arc<AuditLog>>,
+    pub metrics: Arc<RouteMetrics>,
+    pub usage: Arc<Usage>,
+    pub search_log: Option<SearchLog>,
+    pub span_exporter: Option<SpanExporter>,
     pub server_pid: Pid,
 }
 
@@ -62,6 +104,59 @@ impl ApiKeys {
 }
 
 impl DataInner {
+    /// Opens an index to write into it, the indexes mounted read-only are refused.
+    pub fn writable_index(&self, index_uid: &str) -> Result<Index, ResponseError> {
+        self.check_writable(index_uid)?;
+        self.db
+            .open_index(index_uid)
+            .ok_or(ResponseError::index_not_found(index_uid))
+    }
+
+    /// Refuses the writes to the indexes mounted read-only, existing or not,
+    /// and to all of the indexes of a follower.
+    pub fn check_writable(&self, index_uid: &str) -> Result<(), ResponseError> {
+        if self.follower {
+            Err(ResponseError::ReadOnlyFollower)
+        } else if self.read_only_indexes.contains(index_uid) {
+            Err(ResponseError::ReadOnlyIndex(index_uid.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Opens a read transaction on the main environment if a reader slot is free.
+    pub fn main_read_txn(&self) -> Result<LimitedTxn<MainT>, ResponseError> {
+        self.reader_limit.main_read_txn(&self.db)
+    }
+
+    /// Opens a read transaction on the update environment if a reader slot is free.
+    pub fn update_read_txn(&self) -> Result<LimitedTxn<UpdateT>, ResponseError> {
+        self.reader_limit.update_read_txn(&self.db)
+    }
+
+    /// Opens a write transaction on the main environment once the ones requested before are done,
+    /// it can wait for a whole import and must not be called on the threads answering the requests.
+    pub fn main_write_txn(&self) -> Result<QueuedTxn<'_, MainT>, ResponseError> {
+        Ok(self.db.main_write_txn()?)
+    }
+
+    /// Opens a write transaction on the update environment once the ones requested before are done,
+    /// it must not be called on the threads answering the requests either.
+    pub fn update_write_txn(&self) -> Result<QueuedTxn<'_, UpdateT>, ResponseError> {
+        Ok(self.db.update_write_txn()?)
+    }
+
+    /// Waits for the turn of a write transaction on the main environment without blocking the thread,
+    /// the routes answered on the threads answering the requests open their transactions with it.
+    pub async fn main_write_txn_async(&self) -> Result<QueuedTxn<'_, MainT>, ResponseError> {
+        Ok(self.db.main_write_txn_async().await?)
+    }
+
+    /// Waits for the turn of a write transaction on the update environment without blocking the thread.
+    pub async fn update_write_txn_async(&self) -> Result<QueuedTxn<'_, UpdateT>, ResponseError> {
+        Ok(self.db.update_write_txn_async().await?)
+    }
+
     pub fn is_indexing(&self, reader: &heed::RoTxn<UpdateT>, index: &str) -> MResult<Option<bool>> {
         match self.db.open_index(&index) {
             Some(index) => index.current_update_id(&reader).map(|u| Some(u.is_some())),
@@ -87,6 +182,14 @@ impl DataInner {
             .map_err(Into::into)
     }
 
+    /// Writes the usage counters, they are continued from there when the server starts again.
+    pub fn persist_usage(&self, writer: &mut heed::RwTxn<MainT>) -> MResult<()> {
+        let store = self.db.common_store();
+        store.put::<_, Str, SerdeUsage>(writer, USAGE_KEYS_KEY, &self.usage.keys())?;
+        store.put::<_, Str, SerdeUsage>(writer, USAGE_INDEXES_KEY, &self.usage.indexes())?;
+        Ok(())
+    }
+
     pub fn compute_stats(&self, writer: &mut heed::RwTxn<MainT>, index_uid: &str) -> MResult<()> {
         let index = match self.db.open_index(&index_uid) {
             Some(index) => index,
@@ -125,19 +228,119 @@ impl DataInner {
             .put_fields_frequency(writer, &frequency)
             .map_err(MError::Zlmdb)
     }
+
+    /// The data size over which the documents additions to the index are refused, if any.
+    pub fn max_data_size(&self, reader: &heed::RoTxn<MainT>, index: &Index) -> MResult<Option<u64>> {
+        index.max_data_size(reader)
+    }
+
+    /// Stores the size of the processed data of the index, the quota is checked against it and
+    /// the size of the enqueued updates rather than walking the stores on every documents addition.
+    pub fn compute_data_size(&self, writer: &mut heed::RwTxn<MainT>, index_uid: &str) -> MResult<()> {
+        let index = match self.db.open_index(&index_uid) {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+
+        if self.max_data_size(writer, &index)?.is_none() {
+            return Ok(());
+        }
+
+        let update_reader = self.db.update_read_txn()?;
+        let sizes = index.stores_sizes(writer, &update_reader)?;
+        // the enqueued updates are added when the quota is checked, they are processed since
+        let data_size = sizes.total_bytes() - sizes.updates.bytes;
+        update_reader.abort()?;
+
+        index.main.put_data_size(writer, data_size).map_err(MError::Zlmdb)
+    }
+
+    /// Loads the database files and the words FSTs in the OS page cache, returns the number
+    /// of bytes that have been read. The reading is interrupted once `stop` is set.
+    pub fn warm_up(&self, stop: &AtomicBool) -> MResult<u64> {
+        let mut buffer = vec![0; 1024 * 1024];
+        let mut read = 0;
+
+        // a sequential scan of the files is much faster than
+        // the random accesses done by the first queries
+        for env in &["main", "update"] {
+            let path = Path::new(&self.db_path).join(env).join("data.mdb");
+            let mut file = File::open(path)?;
+            loop {
+                if stop.load(Ordering::Relaxed) {
+                    return Ok(read);
+                }
+                match file.read(&mut buffer)? {
+                    0 => break,
+                    n => read += n as u64,
+                }
+            }
+        }
+
+        let reader = self.db.main_read_txn()?;
+        for index_uid in self.db.indexes_uids() {
+            if let Some(index) = self.db.open_index(&index_uid) {
+                if let Some(words) = index.main.words_fst(&reader)? {
+                    read += words.as_fst().as_bytes().len() as u64;
+                }
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+/// The warm up of the database, the files are read in the background
+/// while the server already answers the requests.
+pub struct WarmUp {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl WarmUp {
+    pub fn start(data: Data) -> std::io::Result<WarmUp> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_cloned = stop.clone();
+        let handle = thread::Builder::new().name("warm-up".to_string()).spawn(move || {
+            let start = Instant::now();
+            match data.warm_up(&stop_cloned) {
+                Ok(read) => info!("Database warmed up, {} bytes read in {:.02?}", read, start.elapsed()),
+                Err(e) => warn!("Impossible to warm up the database: {}", e),
+            }
+        })?;
+
+        Ok(WarmUp { stop, handle })
+    }
+
+    /// Interrupts the reading of the files and waits for the warm up thread.
+    pub fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
 }
 
 impl Data {
-    pub fn new(opt: Opt) -> Data {
+    pub fn new(opt: Opt) -> Result<Data, Box<dyn Error>> {
         let db_path = opt.db_path.clone();
         let server_pid = sysinfo::get_current_pid().unwrap();
+        let payload_limits = PayloadLimits::from_opt(&opt);
+        let snapshot_options = SnapshotOptions::from_opt(&opt);
+        let search_log = SearchLog::from_opt(&opt);
+        let span_exporter = opt.otlp_endpoint.clone().map(SpanExporter::start).transpose()?;
+        let audit_log = match &opt.audit_log_path {
+            Some(path) => Some(Arc::new(AuditLog::open(path, opt.audit_log_max_size, opt.audit_log_retention)?)),
+            None => None,
+        };
 
         let db_opt = DatabaseOptions {
             main_map_size: opt.main_map_size,
             update_map_size: opt.update_map_size,
+            max_readers: opt.max_readers,
+            encryption_key: opt.encryption_key.map(|key| key.0),
+            index_max_data_size: opt.index_max_data_size,
         };
 
-        let db = Arc::new(Database::open_or_create(opt.db_path, db_opt).unwrap());
+        let db = Arc::new(Database::open_or_create(opt.db_path, db_opt)?);
 
         let mut api_keys = ApiKeys {
             master: opt.master_key,
@@ -147,10 +350,38 @@ impl Data {
 
         api_keys.generate_missing_api_keys();
 
+        let read_only_indexes = opt.mount_indexes.iter().map(|mount| mount.uid.clone()).collect();
+        let admin_allowlist = IpAllowlist::new(opt.admin_allowed_ips);
+        let admin_allowlist = Arc::new(RwLock::new(admin_allowlist));
+
+        let search_pool = SearchPool::new(opt.search_threads, opt.search_queue_size);
+        let reader_limit = ReaderLimit::new(opt.max_readers);
+        let snapshot_ttl = Duration::from_secs(opt.search_snapshot_ttl_sec);
+        let read_snapshots = Arc::new(ReadSnapshots::new(snapshot_ttl, opt.search_snapshots_max, opt.search_threads));
+
         let inner_data = DataInner {
             db: db.clone(),
             db_path,
             api_keys,
+            admin_allowlist,
+            index_copies: Arc::default(),
+            rollover_lock: Arc::default(),
+            search_pool,
+            reader_limit,
+            read_snapshots,
+            payload_limits,
+            snapshot_options,
+            health_min_disk_space: opt.health_min_disk_space,
+            changes_retention: opt.changes_retention,
+            upload_sessions_retention: chrono::Duration::days(opt.upload_sessions_retention_days.into()),
+            read_only_indexes: Arc::new(read_only_indexes),
+            follower: opt.replicate_from.is_some(),
+            started_at: Utc::now(),
+            audit_log,
+            metrics: Arc::new(RouteMetrics::from_openapi(OPENAPI_DOCUMENT)),
+            usage: Arc::new(load_usage(&db)?),
+            search_log,
+            span_exporter,
             server_pid,
         };
 
@@ -163,6 +394,14 @@ impl Data {
             index_update_callback(&index_uid, &callback_context, status);
         }));
 
-        data
+        Ok(data)
     }
 }
+
+fn load_usage(db: &Database) -> MResult<Usage> {
+    let reader = db.main_read_txn()?;
+    let store = db.common_store();
+    let keys = store.get::<_, Str, SerdeUsage>(&reader, USAGE_KEYS_KEY)?.unwrap_or_default();
+    let indexes = store.get::<_, Str, SerdeUsage>(&reader, USAGE_INDEXES_KEY)?.unwrap_or_default();
+    Ok(Usage::new(keys, indexes))
+}
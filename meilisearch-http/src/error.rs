@@ -4,14 +4,17 @@ use actix_http::ResponseBuilder;
 use actix_web as aweb;
 use actix_web::http::StatusCode;
 use serde_json::json;
-use actix_web::error::JsonPayloadError;
+use actix_web::error::{JsonPayloadError, PayloadError};
+use meilisearch_core::settings::RankingRuleConversionError;
 
 #[derive(Debug)]
 pub enum ResponseError {
     BadParameter(String, String),
     BadRequest(String),
+    Conflict(String),
     CreateIndex(String),
     DocumentNotFound(String),
+    ForbiddenAddress(String),
     IndexNotFound(String),
     Internal(String),
     InvalidIndexUid,
@@ -21,10 +24,15 @@ pub enum ResponseError {
     MissingHeader(String),
     NotFound(String),
     OpenIndex(String),
+    Overloaded(u64),
+    QuotaExceeded(String, u64),
+    RankingRule(RankingRuleConversionError),
     FilterParsing(String),
     RetrieveDocument(u64, String),
     SearchDocuments(String),
     PayloadTooLarge,
+    ReadOnlyIndex(String),
+    ReadOnlyFollower,
     UnsupportedMediaType,
     FacetExpression(String),
     FacetCount(String),
@@ -80,10 +88,18 @@ impl ResponseError {
         ResponseError::InvalidToken(err.to_string())
     }
 
+    pub fn conflict(err: impl fmt::Display) -> ResponseError {
+        ResponseError::Conflict(err.to_string())
+    }
+
     pub fn not_found(err: impl fmt::Display) -> ResponseError {
         ResponseError::NotFound(err.to_string())
     }
 
+    pub fn forbidden_address(addr: impl fmt::Display) -> ResponseError {
+        ResponseError::ForbiddenAddress(addr.to_string())
+    }
+
     pub fn index_not_found(err: impl fmt::Display) -> ResponseError {
         ResponseError::IndexNotFound(err.to_string())
     }
@@ -130,8 +146,10 @@ impl fmt::Display for ResponseError {
         match self {
             Self::BadParameter(param, err) => write!(f, "Url parameter {} error: {}", param, err),
             Self::BadRequest(err) => f.write_str(err),
+            Self::Conflict(err) => f.write_str(err),
             Self::CreateIndex(err) => write!(f, "Impossible to create index; {}", err),
             Self::DocumentNotFound(document_id) => write!(f, "Document with id {} not found", document_id),
+            Self::ForbiddenAddress(addr) => write!(f, "Access from {} is not allowed on this route", addr),
             Self::IndexNotFound(index_uid) => write!(f, "Index {} not found", index_uid),
             Self::Internal(err) => f.write_str(err),
             Self::InvalidIndexUid => f.write_str("Index must have a valid uid; Index uid can be of type integer or string only composed of alphanumeric characters, hyphens (-) and underscores (_)."),
@@ -142,10 +160,15 @@ impl fmt::Display for ResponseError {
             Self::MissingHeader(header) => write!(f, "Header {} is missing", header),
             Self::NotFound(err) => write!(f, "{} not found", err),
             Self::OpenIndex(err) => write!(f, "Impossible to open index; {}", err),
+            Self::Overloaded(_) => f.write_str("Too many requests are being processed, please try again later"),
+            Self::QuotaExceeded(index_uid, max_data_size) => write!(f, "Index {} exceeded its data size quota of {} bytes", index_uid, max_data_size),
+            Self::RankingRule(e) => write!(f, "{}", e),
             Self::RetrieveDocument(id, err) => write!(f, "impossible to retrieve the document with id: {}; {}", id, err),
             Self::SearchDocuments(err) => write!(f, "impossible to search documents; {}", err),
             Self::FacetExpression(e) => write!(f, "error parsing facet filter expression: {}", e),
             Self::PayloadTooLarge => f.write_str("Payload to large"),
+            Self::ReadOnlyIndex(index_uid) => write!(f, "Index {} is mounted read-only", index_uid),
+            Self::ReadOnlyFollower => f.write_str("The indexes of a follower are read-only, write to its leader"),
             Self::UnsupportedMediaType => f.write_str("Unsupported media type"),
             Self::FacetCount(e) => write!(f, "error with facet count: {}", e),
         }
@@ -154,9 +177,19 @@ impl fmt::Display for ResponseError {
 
 impl aweb::error::ResponseError for ResponseError {
     fn error_response(&self) -> aweb::HttpResponse {
-        ResponseBuilder::new(self.status_code()).json(json!({
-            "message": self.to_string(),
-        }))
+        let mut response = ResponseBuilder::new(self.status_code());
+        if let Self::Overloaded(retry_after) = self {
+            response.header("Retry-After", retry_after.to_string());
+        }
+        match self {
+            Self::RankingRule(e) => response.json(json!({
+                "message": self.to_string(),
+                "rankingRule": e,
+            })),
+            _ => response.json(json!({
+                "message": self.to_string(),
+            })),
+        }
     }
 
     fn status_code(&self) -> StatusCode {
@@ -170,16 +203,23 @@ impl aweb::error::ResponseError for ResponseError {
             | Self::FacetExpression(_)
             | Self::SearchDocuments(_)
             | Self::FacetCount(_)
+            | Self::RankingRule(_)
             | Self::FilterParsing(_) => StatusCode::BAD_REQUEST,
             Self::DocumentNotFound(_)
             | Self::IndexNotFound(_)
             | Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
             Self::InvalidToken(_)
             | Self::MissingHeader(_) => StatusCode::UNAUTHORIZED,
-            Self::MissingAuthorizationHeader => StatusCode::FORBIDDEN,
+            Self::MissingAuthorizationHeader
+            | Self::ForbiddenAddress(_)
+            | Self::ReadOnlyIndex(_)
+            | Self::ReadOnlyFollower => StatusCode::FORBIDDEN,
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            Self::Maintenance => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Maintenance
+            | Self::Overloaded(_) => StatusCode::SERVICE_UNAVAILABLE,
             Self::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::QuotaExceeded(_, _) => StatusCode::INSUFFICIENT_STORAGE,
             Self::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
         }
     }
@@ -222,6 +262,12 @@ impl From<meilisearch_core::Error> for ResponseError {
     }
 }
 
+impl From<RankingRuleConversionError> for ResponseError {
+    fn from(err: RankingRuleConversionError) -> ResponseError {
+        ResponseError::RankingRule(err)
+    }
+}
+
 impl From<meilisearch_schema::Error> for ResponseError {
     fn from(err: meilisearch_schema::Error) -> ResponseError {
         ResponseError::Internal(err.to_string())
@@ -245,6 +291,7 @@ impl From<JsonPayloadError> for ResponseError {
         match err {
             JsonPayloadError::Deserialize(err) => ResponseError::BadRequest(format!("Invalid JSON: {}", err)),
             JsonPayloadError::Overflow => ResponseError::PayloadTooLarge,
+            JsonPayloadError::Payload(PayloadError::Overflow) => ResponseError::PayloadTooLarge,
             JsonPayloadError::ContentType => ResponseError::UnsupportedMediaType,
             JsonPayloadError::Payload(err) => ResponseError::BadRequest(format!("Problem while decoding the request: {}", err)),
         }
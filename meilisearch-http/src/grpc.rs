@@ -0,0 +1,344 @@
+//! The gRPC server of the `grpc` feature, it serves the search, the documents addition
+//! and the settings of the HTTP API with the protobuf messages of `proto/meilisearch.proto`.
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::thread::{self, JoinHandle};
+
+use actix_web::ResponseError as _;
+use futures::channel::oneshot;
+use log::error;
+use meilisearch_core::settings::Settings;
+use meilisearch_core::CancellationToken;
+use tonic::metadata::MetadataMap;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::error::ResponseError;
+use crate::helpers::Authentication;
+use crate::routes::document::{push_documents, AdditionOptions};
+use crate::routes::enqueue_once;
+use crate::routes::search::{search_index, SearchQuery};
+use crate::routes::setting::index_settings;
+use crate::Data;
+
+pub mod proto {
+    tonic::include_proto!("meilisearch");
+}
+
+use proto::meili_search_server::{MeiliSearch, MeiliSearchServer};
+
+/// The gRPC server running on its own thread, next to the HTTP server.
+pub struct GrpcServer {
+    shutdown: oneshot::Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl GrpcServer {
+    pub fn start(data: Data, addr: SocketAddr) -> std::io::Result<GrpcServer> {
+        let (shutdown, signal) = oneshot::channel::<()>();
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()?;
+
+        let handle = thread::Builder::new().name("grpc".to_string()).spawn(move || {
+            let service = MeiliSearchServer::new(Service { data });
+            let server = Server::builder()
+                .add_service(service)
+                .serve_with_shutdown(addr, async {
+                    let _ = signal.await;
+                });
+            if let Err(e) = runtime.block_on(server) {
+                error!("gRPC server error: {}", e);
+            }
+        })?;
+
+        Ok(GrpcServer { shutdown, handle })
+    }
+
+    /// Stops accepting requests, answers the in-flight ones and waits for the server thread.
+    pub fn stop(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.handle.join();
+    }
+}
+
+struct Service {
+    data: Data,
+}
+
+impl Service {
+    /// Checks the `x-meili-api-key` metadata like the authentication middleware checks the header.
+    fn authenticate(&self, metadata: &MetadataMap, acl: Authentication) -> Result<(), Status> {
+        if self.data.api_keys.master.is_none() {
+            return Ok(());
+        }
+
+        let key = metadata
+            .get("x-meili-api-key")
+            .and_then(|key| key.to_str().ok())
+            .ok_or_else(|| status(ResponseError::MissingAuthorizationHeader))?;
+
+        if acl.accepts(&self.data.api_keys, key) {
+            Ok(())
+        } else {
+            Err(status(ResponseError::InvalidToken(key.to_string())))
+        }
+    }
+
+    /// The settings are admin routes, only reachable by the clients of the admin allowlist.
+    fn check_admin_address(&self, addr: Option<SocketAddr>) -> Result<(), Status> {
+        let peer_ip = addr.map(|addr| addr.ip());
+        if self.data.admin_allowlist.read().unwrap().is_allowed(peer_ip) {
+            Ok(())
+        } else {
+            let peer = peer_ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+            Err(status(ResponseError::ForbiddenAddress(peer)))
+        }
+    }
+
+    async fn blocking<F, T>(&self, f: F) -> Result<T, Status>
+    where
+        F: FnOnce(&Data) -> Result<T, ResponseError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let data = self.data.clone();
+        tokio::task::spawn_blocking(move || f(&data))
+            .await
+            .map_err(|_| Status::internal("the request has been canceled"))?
+            .map_err(status)
+    }
+}
+
+#[tonic::async_trait]
+impl MeiliSearch for Service {
+    async fn search(&self, request: Request<proto::SearchRequest>) -> Result<Response<proto::SearchResponse>, Status> {
+        self.authenticate(request.metadata(), Authentication::Public)?;
+        let request = request.into_inner();
+        let index_uid = request.index_uid.clone();
+        let params = search_query(request)?;
+
+        let data = self.data.clone();
+        let result = self
+            .data
+            .search_pool
+            .run(move || {
+                let reader = data.main_read_txn()?;
+                search_index(&data, &reader, &index_uid, &params, None, &CancellationToken::new())
+            })
+            .await
+            .map_err(status)?;
+
+        let hits = result
+            .hits
+            .iter()
+            .map(serde_json::to_vec)
+            .collect::<Result<_, _>>()
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        let facets_distribution = result
+            .facets
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, values)| {
+                let values = values.into_iter().map(|(value, count)| (value, count as u64)).collect();
+                (name, proto::FacetDistribution { values })
+            })
+            .collect();
+
+        Ok(Response::new(proto::SearchResponse {
+            hits,
+            offset: result.offset as u32,
+            limit: result.limit as u32,
+            nb_hits: result.nb_hits as u64,
+            exhaustive_nb_hits: result.exhaustive_nb_hits,
+            processing_time_ms: result.processing_time_ms as u64,
+            query: result.query,
+            facets_distribution,
+        }))
+    }
+
+    async fn add_documents(
+        &self,
+        request: Request<proto::AddDocumentsRequest>,
+    ) -> Result<Response<proto::UpdateResponse>, Status> {
+        self.authenticate(request.metadata(), Authentication::Private)?;
+        let request = request.into_inner();
+
+        let documents = request
+            .documents
+            .iter()
+            .map(|document| serde_json::from_slice(document))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| status(ResponseError::bad_parameter("documents", e)))?;
+
+        let update_id = self
+            .blocking(move |data| {
+                let primary_key = Some(request.primary_key.as_str()).filter(|key| !key.is_empty());
+                let options = AdditionOptions { partial: !request.replace, ..AdditionOptions::default() };
+                push_documents(data, &request.index_uid, primary_key, documents, options, None)
+            })
+            .await?;
+
+        Ok(Response::new(proto::UpdateResponse { update_id }))
+    }
+
+    async fn get_settings(&self, request: Request<proto::GetSettingsRequest>) -> Result<Response<proto::Settings>, Status> {
+        self.authenticate(request.metadata(), Authentication::Settings)?;
+        self.check_admin_address(request.remote_addr())?;
+        let index_uid = request.into_inner().index_uid;
+
+        let settings = self
+            .blocking(move |data| {
+                let index = data
+                    .db
+                    .open_index(&index_uid)
+                    .ok_or(ResponseError::index_not_found(&index_uid))?;
+                let reader = data.main_read_txn()?;
+                index_settings(&index, &reader)
+            })
+            .await?;
+
+        Ok(Response::new(proto_settings(settings)))
+    }
+
+    async fn update_settings(
+        &self,
+        request: Request<proto::UpdateSettingsRequest>,
+    ) -> Result<Response<proto::UpdateResponse>, Status> {
+        self.authenticate(request.metadata(), Authentication::Settings)?;
+        self.check_admin_address(request.remote_addr())?;
+        let request = request.into_inner();
+        let settings = core_settings(request.settings.unwrap_or_default());
+
+        let update_id = self
+            .blocking(move |data| {
+                let index = data.writable_index(&request.index_uid)?;
+                let settings = settings.into_update()?;
+                let mut writer = data.update_write_txn()?;
+                let update_id = enqueue_once(&index, &mut writer, None, |writer| {
+                    Ok(index.settings_update(writer, settings)?)
+                })?;
+                writer.commit()?;
+                Ok(update_id)
+            })
+            .await?;
+
+        Ok(Response::new(proto::UpdateResponse { update_id }))
+    }
+}
+
+/// The errors are answered with the gRPC code of their HTTP status code.
+fn status(error: ResponseError) -> Status {
+    let message = error.to_string();
+    match error.status_code().as_u16() {
+        400 | 413 | 415 => Status::invalid_argument(message),
+        401 => Status::unauthenticated(message),
+        403 => Status::permission_denied(message),
+        404 => Status::not_found(message),
+        409 => Status::already_exists(message),
+        429 => Status::resource_exhausted(message),
+        503 => Status::unavailable(message),
+        _ => Status::internal(message),
+    }
+}
+
+fn search_query(request: proto::SearchRequest) -> Result<SearchQuery, Status> {
+    fn non_empty(value: String) -> Option<String> {
+        Some(value).filter(|value| !value.is_empty())
+    }
+
+    fn list(values: Vec<String>) -> Option<String> {
+        non_empty(values.join(","))
+    }
+
+    let facets = if request.facets_distribution.is_empty() {
+        None
+    } else {
+        let facets = serde_json::to_string(&request.facets_distribution).map_err(|e| Status::internal(e.to_string()))?;
+        Some(facets)
+    };
+
+    Ok(SearchQuery {
+        q: request.q,
+        offset: Some(request.offset as usize),
+        limit: Some(request.limit as usize).filter(|limit| *limit != 0),
+        attributes_to_retrieve: list(request.attributes_to_retrieve),
+        attributes_to_crop: list(request.attributes_to_crop),
+        crop_length: Some(request.crop_length as usize).filter(|length| *length != 0),
+        attributes_to_highlight: list(request.attributes_to_highlight),
+        filters: non_empty(request.filters),
+        matches: Some(request.matches),
+        facet_filters: non_empty(request.facet_filters),
+        facets,
+        ..SearchQuery::default()
+    })
+}
+
+fn string_list<I: IntoIterator<Item = String>>(values: I) -> proto::StringList {
+    proto::StringList { values: values.into_iter().collect() }
+}
+
+/// An empty list resets the setting, like a null in the HTTP API.
+fn setting_list(list: Option<proto::StringList>) -> Option<Option<Vec<String>>> {
+    list.map(|list| Some(list.values).filter(|values| !values.is_empty()))
+}
+
+fn core_settings(settings: proto::Settings) -> Settings {
+    Settings {
+        ranking_rules: setting_list(settings.ranking_rules),
+        distinct_attribute: settings.distinct_attribute.map(|name| Some(name).filter(|name| !name.is_empty())),
+        searchable_attributes: setting_list(settings.searchable_attributes),
+        displayed_attributes: setting_list(settings.displayed_attributes)
+            .map(|names| names.map(|names| names.into_iter().collect())),
+        stop_words: setting_list(settings.stop_words).map(|words| words.map(|words| words.into_iter().collect())),
+        synonyms: settings.synonyms.map(|synonyms| {
+            let synonyms: BTreeMap<_, _> = synonyms
+                .values
+                .into_iter()
+                .map(|(word, synonyms)| (word, synonyms.values))
+                .collect();
+            Some(synonyms).filter(|synonyms| !synonyms.is_empty())
+        }),
+        accept_new_fields: settings.accept_new_fields.map(Some),
+        attributes_for_faceting: setting_list(settings.attributes_for_faceting),
+        ..Settings::default()
+    }
+}
+
+fn proto_settings(settings: Settings) -> proto::Settings {
+    proto::Settings {
+        ranking_rules: settings.ranking_rules.flatten().map(string_list),
+        distinct_attribute: settings.distinct_attribute.flatten(),
+        searchable_attributes: settings.searchable_attributes.flatten().map(string_list),
+        displayed_attributes: settings.displayed_attributes.flatten().map(string_list),
+        stop_words: settings.stop_words.flatten().map(string_list),
+        synonyms: settings.synonyms.flatten().map(|synonyms| proto::Synonyms {
+            values: synonyms.into_iter().map(|(word, synonyms)| (word, string_list(synonyms))).collect(),
+        }),
+        accept_new_fields: settings.accept_new_fields.flatten(),
+        attributes_for_faceting: settings.attributes_for_faceting.flatten().map(string_list),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_settings_are_reset() {
+        let settings = core_settings(proto::Settings {
+            ranking_rules: Some(string_list(vec![])),
+            distinct_attribute: Some(String::new()),
+            stop_words: Some(string_list(vec!["the".to_string()])),
+            ..proto::Settings::default()
+        });
+
+        assert_eq!(settings.ranking_rules, Some(None));
+        assert_eq!(settings.distinct_attribute, Some(None));
+        assert_eq!(settings.stop_words, Some(Some(vec!["the".to_string()].into_iter().collect())));
+        assert_eq!(settings.searchable_attributes, None);
+        assert_eq!(settings.accept_new_fields, None);
+    }
+}
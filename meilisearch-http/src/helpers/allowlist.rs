@@ -0,0 +1,171 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A network described in the CIDR notation, e.g. `10.0.0.0/8` or `::1/128`.
+/// A bare address is accepted and considered as a network of a single host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNetwork {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, normalize_ip(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = prefix_mask_u32(self.prefix);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = prefix_mask_u128(self.prefix);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// IPv4 clients connecting through a dual-stack socket are seen as IPv4-mapped
+/// IPv6 addresses, we convert them back to match them against IPv4 networks.
+fn normalize_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.segments() {
+            [0, 0, 0, 0, 0, 0xffff, _, _] => IpAddr::V4(v6.to_ipv4().unwrap()),
+            _ => ip,
+        },
+        ip => ip,
+    }
+}
+
+fn prefix_mask_u32(prefix: u8) -> u32 {
+    u32::max_value().checked_shl(32 - prefix as u32).unwrap_or(0)
+}
+
+fn prefix_mask_u128(prefix: u8) -> u128 {
+    u128::max_value().checked_shl(128 - prefix as u32).unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub struct IpNetworkParseError(String);
+
+impl fmt::Display for IpNetworkParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid network {:?}, expected an IP address or a CIDR range", self.0)
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = IpNetworkParseError;
+
+    fn from_str(s: &str) -> Result<IpNetwork, Self::Err> {
+        let error = || IpNetworkParseError(s.to_string());
+        let mut split = s.trim().splitn(2, '/');
+
+        let addr = split.next().unwrap_or_default();
+        let addr = IpAddr::from_str(addr).map_err(|_| error())?;
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+
+        let prefix = match split.next() {
+            Some(prefix) => u8::from_str(prefix).map_err(|_| error())?,
+            None => max_prefix,
+        };
+
+        if prefix > max_prefix {
+            return Err(error());
+        }
+
+        Ok(IpNetwork { addr, prefix })
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl Serialize for IpNetwork {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNetwork {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<IpNetwork, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        IpNetwork::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The list of networks allowed to reach the admin routes.
+/// An empty list means that every client is allowed.
+#[derive(Debug, Clone, Default)]
+pub struct IpAllowlist {
+    networks: Vec<IpNetwork>,
+}
+
+impl IpAllowlist {
+    pub fn new(networks: Vec<IpNetwork>) -> IpAllowlist {
+        IpAllowlist { networks }
+    }
+
+    pub fn parse<S: AsRef<str>>(networks: &[S]) -> Result<IpAllowlist, IpNetworkParseError> {
+        let networks = networks
+            .iter()
+            .map(|s| IpNetwork::from_str(s.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(IpAllowlist { networks })
+    }
+
+    pub fn networks(&self) -> &[IpNetwork] {
+        &self.networks
+    }
+
+    pub fn is_allowed(&self, ip: Option<IpAddr>) -> bool {
+        if self.networks.is_empty() {
+            return true;
+        }
+
+        match ip {
+            Some(ip) => self.networks.iter().any(|net| net.contains(ip)),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_networks() {
+        assert!(IpNetwork::from_str("10.0.0.0/8").is_ok());
+        assert!(IpNetwork::from_str("127.0.0.1").is_ok());
+        assert!(IpNetwork::from_str("::1/128").is_ok());
+        assert!(IpNetwork::from_str("10.0.0.0/33").is_err());
+        assert!(IpNetwork::from_str("10.0.0/8").is_err());
+        assert!(IpNetwork::from_str("hello").is_err());
+    }
+
+    #[test]
+    fn allowlist_contains() {
+        let allowlist = IpAllowlist::parse(&["10.0.0.0/8", "192.168.1.12", "fd00::/8"]).unwrap();
+
+        assert!(allowlist.is_allowed(Some("10.42.0.1".parse().unwrap())));
+        assert!(allowlist.is_allowed(Some("192.168.1.12".parse().unwrap())));
+        assert!(allowlist.is_allowed(Some("::ffff:10.1.2.3".parse().unwrap())));
+        assert!(allowlist.is_allowed(Some("fd12::1".parse().unwrap())));
+        assert!(!allowlist.is_allowed(Some("192.168.1.13".parse().unwrap())));
+        assert!(!allowlist.is_allowed(Some("::1".parse().unwrap())));
+        assert!(!allowlist.is_allowed(None));
+
+        let everyone = IpAllowlist::default();
+        assert!(everyone.is_allowed(None));
+
+        let all = IpAllowlist::parse(&["0.0.0.0/0"]).unwrap();
+        assert!(all.is_allowed(Some("8.8.8.8".parse().unwrap())));
+    }
+}
@@ -0,0 +1,318 @@
+//! Audit log of the write operations.
+//!
+//! Every call to a write route is appended as a JSON line to the audit log, with the key
+//! that authorized it, the index targeted, a digest of the payload and the response status.
+//! The log is rotated once bigger than its maximum size, only the most recent files are kept.
+
+use std::cell::RefCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use actix_web::http::Method;
+use actix_web::web::BytesMut;
+use actix_web::{dev::ServiceRequest, dev::ServiceResponse, Error, HttpMessage};
+use chrono::{DateTime, Utc};
+use futures::future::{ok, Future, Ready};
+use futures::StreamExt;
+use log::error;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::data::ApiKeys;
+use crate::error::ResponseError;
+use crate::helpers::authentication::is_key;
+use crate::routes::table::ROUTES;
+use crate::Data;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// The name of the key that authorized the call, `anonymous` if no key was needed.
+    pub key: &'static str,
+    pub method: String,
+    pub route: String,
+    pub index: Option<String>,
+    pub payload_digest: String,
+    pub payload_size: usize,
+    pub status: u16,
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    max_size: u64,
+    retention: usize,
+    file: Mutex<LogFile>,
+}
+
+impl AuditLog {
+    /// Opens the audit log at the given path, the entries are appended to the existing ones.
+    pub fn open(path: impl AsRef<Path>, max_size: u64, retention: usize) -> io::Result<AuditLog> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(AuditLog { path, max_size, retention, file: Mutex::new(LogFile { file, size }) })
+    }
+
+    fn rotated_path(&self, number: usize) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", number));
+        self.path.with_file_name(name)
+    }
+
+    /// Renames the current file with the number 1, shifting the numbers of the older files.
+    fn rotate(&self, log: &mut LogFile) -> io::Result<()> {
+        let _ = fs::remove_file(self.rotated_path(self.retention.max(1)));
+        for number in (1..self.retention).rev() {
+            let from = self.rotated_path(number);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(number + 1))?;
+            }
+        }
+
+        if self.retention == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        log.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        log.size = 0;
+        Ok(())
+    }
+
+    pub fn append(&self, entry: &AuditEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut log = self.file.lock().unwrap();
+        if log.size > 0 && log.size + line.len() as u64 > self.max_size {
+            self.rotate(&mut log)?;
+        }
+
+        log.file.write_all(&line)?;
+        log.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Whether the request is served by a route of the table declared as a write one, the
+/// literal segments are preferred to the parameters, like the router does.
+pub(crate) fn is_write(method: &Method, path: &str) -> bool {
+    let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+    ROUTES
+        .iter()
+        .filter(|route| route.method == method.as_str())
+        .filter_map(|route| {
+            let route_segments: Vec<_> = route.path.trim_matches('/').split('/').collect();
+            if route_segments.len() != segments.len() {
+                return None;
+            }
+            let mut literals = 0;
+            for (route_segment, segment) in route_segments.iter().zip(&segments) {
+                if route_segment.starts_with('{') {
+                    if segment.is_empty() {
+                        return None;
+                    }
+                } else if route_segment == segment {
+                    literals += 1;
+                } else {
+                    return None;
+                }
+            }
+            Some((literals, route))
+        })
+        .max_by_key(|(literals, _)| *literals)
+        .map_or(false, |(_, route)| route.write)
+}
+
+pub(crate) fn key_name(api_keys: &ApiKeys, key: Option<&str>) -> &'static str {
+    if api_keys.master.is_none() {
+        return "anonymous";
+    }
+    match key {
+        Some(key) if is_key(api_keys.master.as_deref(), key) => "master",
+        Some(key) if is_key(api_keys.private.as_deref(), key) => "private",
+        Some(key) if is_key(api_keys.public.as_deref(), key) => "public",
+        Some(_) => "invalid",
+        None => "missing",
+    }
+}
+
+pub(crate) fn index_uid(path: &str) -> Option<String> {
+    let mut segments = path.trim_start_matches('/').split('/');
+    match (segments.next(), segments.next()) {
+        (Some("indexes"), Some(uid)) if !uid.is_empty() && uid != "search" => Some(uid.to_string()),
+        _ => None,
+    }
+}
+
+/// Appends the calls to the write routes to the audit log, if it is enabled.
+pub struct AuditLogging;
+
+impl<S: 'static, B> Transform<S> for AuditLogging
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuditLoggingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AuditLoggingMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct AuditLoggingMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for AuditLoggingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let mut svc = self.service.clone();
+        let data = req.app_data::<Data>().unwrap();
+
+        if data.audit_log.is_none() || !is_write(req.method(), req.path()) {
+            return Box::pin(svc.call(req));
+        }
+
+        let key = req.headers().get("X-Meili-API-Key").and_then(|key| key.to_str().ok());
+        let key = key_name(&data.api_keys, key);
+        let method = req.method().to_string();
+        let route = req.path().to_string();
+        let limit = data.payload_limits.limit(&route);
+        let mut payload = req.take_payload();
+
+        Box::pin(async move {
+            // the payload is read to be digested and given back to the route
+            let mut body = BytesMut::new();
+            while let Some(chunk) = payload.next().await {
+                let chunk = chunk?;
+                if body.len() + chunk.len() > limit {
+                    return Err(ResponseError::PayloadTooLarge.into());
+                }
+                body.extend_from_slice(&chunk);
+            }
+            let body = body.freeze();
+            let payload_digest = format!("{:x}", Sha256::digest(&body));
+            let payload_size = body.len();
+
+            let (_, mut replayed) = actix_http::h1::Payload::create(true);
+            replayed.unread_data(body);
+            req.set_payload(replayed.into());
+
+            let result = svc.call(req).await;
+            let status = match &result {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+
+            let entry = AuditEntry {
+                timestamp: Utc::now(),
+                key,
+                method,
+                index: index_uid(&route),
+                route,
+                payload_digest,
+                payload_size,
+                status: status.as_u16(),
+            };
+
+            if let Some(audit_log) = &data.audit_log {
+                if let Err(e) = audit_log.append(&entry) {
+                    error!("Impossible to write in the audit log: {}", e);
+                }
+            }
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(route: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc::now(),
+            key: "master",
+            method: "DELETE".to_string(),
+            index: index_uid(route),
+            route: route.to_string(),
+            payload_digest: format!("{:x}", Sha256::digest(b"")),
+            payload_size: 0,
+            status: 202,
+        }
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = tempdir::TempDir::new("audit-log").unwrap();
+        let path = dir.path().join("audit.log");
+
+        let line_size = serde_json::to_vec(&entry("/indexes/movies")).unwrap().len() as u64 + 1;
+        let audit_log = AuditLog::open(&path, line_size * 2, 2).unwrap();
+        for _ in 0..7 {
+            audit_log.append(&entry("/indexes/movies")).unwrap();
+        }
+
+        // the last line is in a new file, the oldest lines have been removed
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        assert_eq!(fs::read_to_string(dir.path().join("audit.log.1")).unwrap().lines().count(), 2);
+        assert_eq!(fs::read_to_string(dir.path().join("audit.log.2")).unwrap().lines().count(), 2);
+        assert!(!dir.path().join("audit.log.3").exists());
+    }
+
+    #[test]
+    fn write_routes() {
+        assert!(is_write(&Method::POST, "/indexes/movies/documents"));
+        assert!(is_write(&Method::DELETE, "/indexes/movies"));
+        assert!(!is_write(&Method::POST, "/indexes/search"));
+        assert!(!is_write(&Method::GET, "/indexes/movies/documents"));
+        assert!(!is_write(&Method::POST, "/indexes/movies/debug/analyze"));
+        assert!(is_write(&Method::POST, "/indexes/movies/documents/delete-batch"));
+        assert!(!is_write(&Method::POST, "/unknown"));
+
+        assert_eq!(index_uid("/indexes/movies/settings"), Some("movies".to_string()));
+        assert_eq!(index_uid("/indexes"), None);
+        assert_eq!(index_uid("/keys"), None);
+    }
+}
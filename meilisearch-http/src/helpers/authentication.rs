@@ -8,12 +8,17 @@ use actix_web::{dev::ServiceRequest, dev::ServiceResponse, Error};
 use futures::future::{err, ok, Future, Ready};
 
 use crate::error::ResponseError;
+use crate::data::ApiKeys;
 use crate::Data;
 
+/// The access policy of a route, the key it requires and whether
+/// it is only reachable by the clients of the admin allowlist.
 #[derive(Clone)]
 pub enum Authentication {
     Public,
     Private,
+    /// The routes modifying the settings of an index, reachable with the private key.
+    Settings,
     Admin,
 }
 
@@ -38,6 +43,44 @@ where
     }
 }
 
+impl Authentication {
+    /// Whether the key is one of the keys allowed to reach the routes of this level.
+    pub fn accepts(&self, api_keys: &ApiKeys, key: &str) -> bool {
+        match self {
+            Authentication::Admin => is_key(api_keys.master.as_deref(), key),
+            Authentication::Private | Authentication::Settings => {
+                is_key(api_keys.master.as_deref(), key)
+                    || is_key(api_keys.private.as_deref(), key)
+            }
+            Authentication::Public => {
+                is_key(api_keys.master.as_deref(), key)
+                    || is_key(api_keys.private.as_deref(), key)
+                    || is_key(api_keys.public.as_deref(), key)
+            }
+        }
+    }
+
+    /// Admin routes are the ones modifying the configuration of the engine,
+    /// they are only reachable by the clients of the admin allowlist.
+    fn is_admin_route(&self) -> bool {
+        match self {
+            Authentication::Admin | Authentication::Settings => true,
+            Authentication::Private | Authentication::Public => false,
+        }
+    }
+}
+
+/// Compares the key in a time that doesn't depend on the position of the first difference,
+/// only the length of the keys is leaked.
+pub(crate) fn is_key(expected: Option<&str>, key: &str) -> bool {
+    match expected {
+        Some(expected) if expected.len() == key.len() => {
+            expected.bytes().zip(key.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+        }
+        _ => false,
+    }
+}
+
 pub struct LoggingMiddleware<S> {
     acl: Authentication,
     service: Rc<RefCell<S>>,
@@ -64,6 +107,15 @@ where
         // it means that actix-web has an issue or someone changes the type `Data`.
         let data = req.app_data::<Data>().unwrap();
 
+        if self.acl.is_admin_route() {
+            let peer_ip = req.peer_addr().map(|addr| addr.ip());
+            let allowed = data.admin_allowlist.read().unwrap().is_allowed(peer_ip);
+            if !allowed {
+                let peer = peer_ip.map_or_else(|| "unknown".to_string(), |ip| ip.to_string());
+                return Box::pin(err(ResponseError::ForbiddenAddress(peer).into()));
+            }
+        }
+
         if data.api_keys.master.is_none() {
             return Box::pin(svc.call(req));
         }
@@ -78,20 +130,7 @@ where
             }
         };
 
-        let authenticated = match self.acl {
-            Authentication::Admin => data.api_keys.master.as_deref() == Some(auth_header),
-            Authentication::Private => {
-                data.api_keys.master.as_deref() == Some(auth_header)
-                    || data.api_keys.private.as_deref() == Some(auth_header)
-            }
-            Authentication::Public => {
-                data.api_keys.master.as_deref() == Some(auth_header)
-                    || data.api_keys.private.as_deref() == Some(auth_header)
-                    || data.api_keys.public.as_deref() == Some(auth_header)
-            }
-        };
-
-        if authenticated {
+        if self.acl.accepts(&data.api_keys, auth_header) {
             Box::pin(svc.call(req))
         } else {
             Box::pin(err(
@@ -0,0 +1,63 @@
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+
+/// A thread running next to the server until it is stopped, it is given a receiver
+/// which is disconnected once the server stops and must wait on it instead of sleeping.
+pub struct BackgroundThread {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl BackgroundThread {
+    pub fn spawn<F>(name: &str, f: F) -> io::Result<BackgroundThread>
+    where
+        F: FnOnce(Receiver<()>) + Send + 'static,
+    {
+        let (stop, stop_receiver) = bounded(1);
+        let handle = thread::Builder::new().name(name.to_string()).spawn(move || f(stop_receiver))?;
+        Ok(BackgroundThread { stop, handle })
+    }
+
+    /// Asks the thread to stop and waits for the work it is doing, if any.
+    pub fn stop(self) {
+        drop(self.stop);
+        let _ = self.handle.join();
+    }
+}
+
+/// Waits for `duration` unless the thread is stopped before, returns whether it has been.
+pub fn wait_or_stop(stop: &Receiver<()>, duration: Duration) -> bool {
+    match stop.recv_timeout(duration) {
+        Err(RecvTimeoutError::Timeout) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn stopped_while_waiting() {
+        let rounds = Arc::new(AtomicUsize::new(0));
+        let rounds_cloned = rounds.clone();
+        let thread = BackgroundThread::spawn("test", move |stop| loop {
+            rounds_cloned.fetch_add(1, Ordering::SeqCst);
+            if wait_or_stop(&stop, Duration::from_secs(3600)) {
+                break;
+            }
+        })
+        .unwrap();
+
+        while rounds.load(Ordering::SeqCst) == 0 {
+            thread::yield_now();
+        }
+        thread.stop();
+        assert_eq!(rounds.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,17 @@
+use actix_web::error::BlockingError;
+use actix_web::web;
+
+use crate::error::ResponseError;
+
+/// Runs `f` on the blocking threads, off the threads answering the requests,
+/// `operation` names what has been canceled if the blocking threads are shut down.
+pub async fn run_blocking<F, T>(operation: &'static str, f: F) -> Result<T, ResponseError>
+where
+    F: FnOnce() -> Result<T, ResponseError> + Send + 'static,
+    T: Send + 'static,
+{
+    web::block(f).await.map_err(|e| match e {
+        BlockingError::Error(e) => e,
+        BlockingError::Canceled => ResponseError::internal(format!("{} has been canceled", operation)),
+    })
+}
@@ -1,23 +1,30 @@
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
 use std::hash::{Hash, Hasher};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use indexmap::IndexMap;
 use log::error;
 use meilisearch_core::Filter;
 use meilisearch_core::facets::FacetFilter;
 use meilisearch_core::criterion::*;
-use meilisearch_core::settings::RankingRule;
-use meilisearch_core::{Highlight, Index, MainT, RankedMap};
+use meilisearch_core::raw_indexer::DEFAULT_WORD_LIMIT;
+use meilisearch_core::settings::{RankingRule, DEFAULT_RANKING_RULES};
+use meilisearch_core::{CancellationToken, Highlight, Index, MainT, QuerySplit, RankedMap, SearchTiming};
 use meilisearch_schema::{FieldId, Schema};
-use meilisearch_tokenizer::is_cjk;
+use meilisearch_tokenizer::{is_cjk, Tokenizer};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use siphasher::sip::SipHasher;
 use slice_group_by::GroupBy;
 
 use crate::error::ResponseError;
+use crate::helpers::TraceContext;
+
+/// The number of best hits that are diversified, the same window is diversified whatever
+/// the page so that a hit moved down to another page is never returned twice or skipped.
+const DIVERSIFICATION_WINDOW: usize = 1000;
 
 pub trait IndexSearchExt {
     fn new_search(&self, query: String) -> SearchBuilder;
@@ -35,8 +42,25 @@ impl IndexSearchExt for Index {
             attributes_to_highlight: None,
             filters: None,
             matches: false,
+            matches_position_unit: MatchesPositionUnit::Chars,
             facet_filters: None,
             facets: None,
+            disjunctive_facets: false,
+            exhaustive_facets_count: true,
+            geo_distribution: None,
+            exhaustive_nb_hits: false,
+            search_after: None,
+            synonyms: true,
+            concatenate_ngrams: true,
+            quick_search: false,
+            not_equal_matches_missing: true,
+            ranking_rules: None,
+            sort: None,
+            diversify: None,
+            sample: None,
+            search_cutoff: None,
+            trace: None,
+            cancellation: None,
         }
     }
 }
@@ -51,8 +75,25 @@ pub struct SearchBuilder<'a> {
     attributes_to_highlight: Option<HashSet<String>>,
     filters: Option<String>,
     matches: bool,
+    matches_position_unit: MatchesPositionUnit,
     facet_filters: Option<FacetFilter>,
-    facets: Option<Vec<(FieldId, String)>>
+    facets: Option<Vec<(FieldId, String)>>,
+    disjunctive_facets: bool,
+    exhaustive_facets_count: bool,
+    geo_distribution: Option<(FieldId, usize)>,
+    exhaustive_nb_hits: bool,
+    search_after: Option<SearchCursor>,
+    synonyms: bool,
+    concatenate_ngrams: bool,
+    quick_search: bool,
+    not_equal_matches_missing: bool,
+    ranking_rules: Option<Vec<RankingRule>>,
+    sort: Option<Vec<(String, bool)>>,
+    diversify: Option<(FieldId, usize)>,
+    sample: Option<(usize, u64)>,
+    search_cutoff: Option<Duration>,
+    trace: Option<TraceContext>,
+    cancellation: Option<CancellationToken>,
 }
 
 impl<'a> SearchBuilder<'a> {
@@ -102,12 +143,144 @@ impl<'a> SearchBuilder<'a> {
         self
     }
 
+    /// The unit in which the positions of the matches are counted, characters by default.
+    pub fn matches_position_unit(&mut self, unit: MatchesPositionUnit) -> &SearchBuilder {
+        self.matches_position_unit = unit;
+        self
+    }
+
     pub fn add_facets(&mut self, facets: Vec<(FieldId, String)>) -> &SearchBuilder {
         self.facets = Some(facets);
         self
     }
 
-    pub fn search(self, reader: &heed::RoTxn<MainT>) -> Result<SearchResult, ResponseError> {
+    /// Whether the values of a facet are counted without the facet filters on this facet,
+    /// they are counted with all the facet filters by default.
+    pub fn disjunctive_facets(&mut self, value: bool) -> &SearchBuilder {
+        self.disjunctive_facets = value;
+        self
+    }
+
+    /// Whether the facet values are counted on all of the hits, they are by default,
+    /// otherwise they are approximated from a sample of the hits when there are many.
+    pub fn exhaustive_facets_count(&mut self, value: bool) -> &SearchBuilder {
+        self.exhaustive_facets_count = value;
+        self
+    }
+
+    /// Counts the documents in each geohash cell of the given precision of the geo point attribute.
+    pub fn geo_distribution(&mut self, field_id: FieldId, precision: usize) -> &SearchBuilder {
+        self.geo_distribution = Some((field_id, precision));
+        self
+    }
+
+    /// Whether all the hits are counted. By default the counting stops after
+    /// `DEFAULT_MAX_TOTAL_HITS` hits and the count is then marked as not exhaustive.
+    pub fn exhaustive_nb_hits(&mut self, value: bool) -> &SearchBuilder {
+        self.exhaustive_nb_hits = value;
+        self
+    }
+
+    /// Whether the query words are expanded with their synonyms, they are by default.
+    pub fn enable_synonyms(&mut self, value: bool) -> &SearchBuilder {
+        self.synonyms = value;
+        self
+    }
+
+    /// Whether the consecutive query words are also searched concatenated, they are by default.
+    pub fn concatenate_ngrams(&mut self, value: bool) -> &SearchBuilder {
+        self.concatenate_ngrams = value;
+        self
+    }
+
+    /// Whether only the query words themselves are searched, without synonyms, concatenations
+    /// nor splits, the lighter search suits the autocompletion, it is disabled by default.
+    pub fn quick_search(&mut self, value: bool) -> &SearchBuilder {
+        self.quick_search = value;
+        self
+    }
+
+    /// Truncates the ranking once the duration has elapsed,
+    /// the `searchCutoffMs` setting of the index is used otherwise.
+    pub fn search_cutoff(&mut self, value: Duration) -> &SearchBuilder {
+        self.search_cutoff = Some(value);
+        self
+    }
+
+    /// Whether the `!=` filters match the documents missing the attribute, they do by default.
+    pub fn not_equal_matches_missing(&mut self, value: bool) -> &SearchBuilder {
+        self.not_equal_matches_missing = value;
+        self
+    }
+
+    /// Replaces the sort rules at the end of the ranking rules of the index for this search only,
+    /// the rules are appended when the index doesn't end with sort rules.
+    pub fn ranking_rules(&mut self, rules: Vec<RankingRule>) -> &SearchBuilder {
+        self.ranking_rules = Some(rules);
+        self
+    }
+
+    /// Sorts the hits by the values of the attributes, after the ranking rules and in order,
+    /// each one in descending order if the boolean is set, the document ids break the ties.
+    pub fn sort(&mut self, sort: Vec<(String, bool)>) -> &SearchBuilder {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// Reorders the hits so that at most `max_per_value` consecutive hits share the same
+    /// value of the attribute, the hits are moved down the ranking, never removed.
+    pub fn diversify(&mut self, field_id: FieldId, max_per_value: usize) -> &SearchBuilder {
+        self.diversify = Some((field_id, max_per_value));
+        self
+    }
+
+    /// Returns a uniformly random sample of the hits in place of a page, in the order in
+    /// which they are ranked, the same seed returns the same sample from the same hits.
+    pub fn sample(&mut self, size: usize, seed: Option<u64>) -> &SearchBuilder {
+        self.sample = Some((size, seed.unwrap_or_else(rand::random)));
+        self
+    }
+
+    /// Records the phases of the search as spans of the given trace.
+    pub fn trace(&mut self, context: TraceContext) -> &SearchBuilder {
+        self.trace = Some(context);
+        self
+    }
+
+    /// Stops the search as soon as the token is cancelled.
+    pub fn cancellation(&mut self, token: CancellationToken) -> &SearchBuilder {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Continues the search after the last hit of a previous page, the hits are those
+    /// sorted after it even if documents have been added or deleted, the offset is ignored.
+    pub fn search_after(&mut self, cursor: &str) -> Result<&SearchBuilder, ResponseError> {
+        let cursor = SearchCursor::decode(cursor)
+            .filter(|c| c.query_hash == self.query_hash())
+            .ok_or(ResponseError::bad_parameter("searchAfter", "invalid cursor for this search"))?;
+        self.search_after = Some(cursor);
+        Ok(self)
+    }
+
+    /// Identifies the searches returning the same hits, a cursor
+    /// given by one search can't be used to continue another one.
+    fn query_hash(&self) -> u64 {
+        let mut hasher = SipHasher::new();
+        self.query.hash(&mut hasher);
+        self.filters.hash(&mut hasher);
+        self.facet_filters.as_ref().map(|f| format!("{:?}", f)).hash(&mut hasher);
+        self.synonyms.hash(&mut hasher);
+        self.concatenate_ngrams.hash(&mut hasher);
+        self.quick_search.hash(&mut hasher);
+        self.not_equal_matches_missing.hash(&mut hasher);
+        self.ranking_rules.as_ref().map(|rules| rules.iter().map(ToString::to_string).collect::<Vec<_>>()).hash(&mut hasher);
+        self.sort.hash(&mut hasher);
+        self.diversify.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn search(mut self, reader: &heed::RoTxn<MainT>) -> Result<SearchResult, ResponseError> {
         let schema = self
             .index
             .main
@@ -122,8 +295,11 @@ impl<'a> SearchBuilder<'a> {
             None => self.index.query_builder(),
         };
 
+        let aliases = self.index.main.attribute_aliases(reader)?.unwrap_or_default();
+
         if let Some(filter_expression) = &self.filters {
-            let filter = Filter::parse(filter_expression, &schema)?;
+            let mut filter = Filter::parse_with_aliases(filter_expression, &schema, &aliases)?;
+            filter.set_not_equal_matches_missing(self.not_equal_matches_missing);
             let index = &self.index;
             query_builder.with_filter(move |id| {
                 let reader = &reader;
@@ -138,30 +314,97 @@ impl<'a> SearchBuilder<'a> {
             });
         }
 
-        if let Some(field) = self.index.main.distinct_attribute(reader)? {
-            if let Some(field_id) = schema.id(&field) {
-                let index = &self.index;
-                query_builder.with_distinct(1, move |id| {
-                    match index.document_attribute_bytes(reader, id, field_id) {
-                        Ok(Some(bytes)) => {
-                            let mut s = SipHasher::new();
-                            bytes.hash(&mut s);
-                            Some(s.finish())
-                        }
-                        _ => None,
-                    }
-                });
-            }
+        if self.index.main.distinct_attribute(reader)?.is_some() {
+            let distinct_values = self.index.distinct_values;
+            query_builder.with_distinct(1, move |id| match distinct_values.distinct_value(reader, id) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    log::warn!("unexpected error during distinct: {}", e);
+                    None
+                }
+            });
+        }
+
+        if self.exhaustive_nb_hits {
+            query_builder.with_max_total_hits(None);
+        }
+
+        if !self.synonyms {
+            query_builder.without_synonyms();
+        }
+
+        if !self.concatenate_ngrams {
+            query_builder.without_ngrams_concatenation();
+        }
+
+        if self.quick_search {
+            query_builder.quick_search();
+        }
+
+        if let Some(cutoff) = self.search_cutoff {
+            query_builder.with_fetch_timeout(cutoff);
         }
 
         query_builder.set_facet_filter(self.facet_filters);
         query_builder.set_facets(self.facets);
+        query_builder.set_disjunctive_facets(self.disjunctive_facets);
+        query_builder.set_exhaustive_facets_count(self.exhaustive_facets_count);
+        query_builder.set_geo_distribution(self.geo_distribution);
+        if let Some(token) = self.cancellation.take() {
+            query_builder.with_cancellation(token);
+        }
+
+        // the candidates sorted before the last hit of the cursor are removed
+        if let Some(cursor) = self.search_after.take() {
+            query_builder.with_search_after(cursor.sort_key);
+            self.offset = 0;
+        }
 
+        let span = self.trace.as_ref().map(|trace| trace.span("search"));
         let start = Instant::now();
-        let result = query_builder.query(reader, &self.query, self.offset..(self.offset + self.limit));
-        let search_result = result.map_err(ResponseError::search_documents)?;
+        // the sample is drawn from the candidates, only the sampled documents are ranked
+        if let Some((size, seed)) = self.sample {
+            query_builder.with_sample(size, seed);
+            self.offset = 0;
+            self.limit = size;
+        }
+
+        // the diversification reorders the hits of the window from the first one,
+        // the hits ranked after the window are returned in their order
+        let end = self.offset.saturating_add(self.limit);
+        let range = match self.diversify {
+            Some(_) => 0..end.max(DIVERSIFICATION_WINDOW),
+            None => self.offset..end,
+        };
+        let result = query_builder.query(reader, &self.query, range);
+        let mut search_result = result.map_err(ResponseError::search_documents)?;
+
+        if let Some((field_id, max_per_value)) = self.diversify {
+            let index = &self.index;
+            let mut window = search_result.documents;
+            let after_window = window.split_off(window.len().min(DIVERSIFICATION_WINDOW));
+            let mut documents = diversify(window, max_per_value, |document| {
+                match index.document_attribute_bytes(reader, document.id, field_id) {
+                    Ok(Some(bytes)) => {
+                        let mut s = SipHasher::new();
+                        bytes.hash(&mut s);
+                        Some(s.finish())
+                    }
+                    _ => None,
+                }
+            });
+            documents.extend(after_window);
+            search_result.documents = documents.into_iter().skip(self.offset).take(self.limit).collect();
+        }
+
         let time_ms = start.elapsed().as_millis() as usize;
 
+        if let Some(span) = &span {
+            for timing in &search_result.timings {
+                span.context().record(&timing.phase, timing.start, timing.duration);
+            }
+        }
+
         let mut all_attributes: HashSet<&str> = HashSet::new();
         let mut all_formatted: HashSet<&str> = HashSet::new();
 
@@ -189,7 +432,30 @@ impl<'a> SearchBuilder<'a> {
             },
         }
 
-        let mut hits = Vec::with_capacity(self.limit);
+        // the diversified hits are not sorted by their keys
+        let full_page = search_result.documents.len() == self.limit;
+        let next_cursor = match search_result.last_sort_key.take() {
+            Some(sort_key) if full_page && self.sample.is_none() && self.diversify.is_none() => {
+                Some(SearchCursor { sort_key, query_hash: self.query_hash() }.encode())
+            }
+            _ => None,
+        };
+
+        // the highlighted attributes are only tokenized again when their indexed words are limited
+        let unindexed_words = match self.index.main.max_indexed_words(reader)? {
+            Some(max_indexed_words) if self.attributes_to_highlight.is_some() => {
+                let query = match self.index.main.query_normalization(reader)? {
+                    Some(normalization) => normalization.normalize(&self.query),
+                    None => self.query.clone(),
+                };
+                let query_words: Vec<_> = Tokenizer::new(&query).map(|token| token.word.to_lowercase()).collect();
+                Some((max_indexed_words, query_words)).filter(|(_, query_words)| !query_words.is_empty())
+            }
+            _ => None,
+        };
+
+        let fetch_span = span.as_ref().map(|span| span.context().span("documents fetch"));
+        let mut hits = Vec::with_capacity(search_result.documents.len());
         for doc in search_result.documents {
             let mut document: IndexMap<String, Value> = self
                 .index
@@ -206,11 +472,42 @@ impl<'a> SearchBuilder<'a> {
 
             let mut matches = doc.highlights.clone();
 
+            if let (Some(attributes_to_highlight), Some((max_indexed_words, query_words))) =
+                (&self.attributes_to_highlight, &unindexed_words)
+            {
+                highlight_unindexed_words(
+                    &formatted,
+                    &mut matches,
+                    &schema,
+                    attributes_to_highlight,
+                    max_indexed_words,
+                    query_words,
+                );
+            }
+
             // Crops fields if needed
             if let Some(fields) = &self.attributes_to_crop {
                 crop_document(&mut formatted, &mut matches, &schema, fields);
             }
 
+            // the positions are counted in the text returned, cropped but not highlighted
+            let matches_info = if self.matches {
+                let mut matches_info = calculate_matches(&matches, self.attributes_to_retrieve.clone(), &schema);
+                if self.matches_position_unit != MatchesPositionUnit::Chars {
+                    for (attribute, positions) in matches_info.iter_mut() {
+                        let text = formatted.get(attribute).or_else(|| document.get(attribute));
+                        if let Some(Value::String(text)) = text {
+                            for position in positions.iter_mut() {
+                                *position = self.matches_position_unit.convert(text, position);
+                            }
+                        }
+                    }
+                }
+                Some(matches_info)
+            } else {
+                None
+            };
+
             // Transform to readable matches
             if let Some(attributes_to_highlight) = &self.attributes_to_highlight {
                 let matches = calculate_matches(
@@ -221,12 +518,6 @@ impl<'a> SearchBuilder<'a> {
                 formatted = calculate_highlights(&formatted, &matches, attributes_to_highlight);
             }
 
-            let matches_info = if self.matches {
-                Some(calculate_matches(&matches, self.attributes_to_retrieve.clone(), &schema))
-            } else {
-                None
-            };
-
             if let Some(attributes_to_retrieve) = &self.attributes_to_retrieve {
                 document.retain(|key, _| attributes_to_retrieve.contains(&key.to_string()))
             }
@@ -239,6 +530,7 @@ impl<'a> SearchBuilder<'a> {
 
             hits.push(hit);
         }
+        drop(fetch_span);
 
         let results = SearchResult {
             hits,
@@ -249,6 +541,15 @@ impl<'a> SearchBuilder<'a> {
             processing_time_ms: time_ms,
             query: self.query.to_string(),
             facets: search_result.facets,
+            exhaustive_facets_count: search_result.exhaustive_facet_count,
+            geo_distribution: search_result.geo_distribution,
+            degraded: search_result.degraded,
+            ignored_words: search_result.ignored_words,
+            segmentations: search_result.segmentations,
+            next_cursor,
+            snapshot_token: None,
+            sample_seed: self.sample.map(|(_, seed)| seed),
+            timings: search_result.timings,
         };
 
         Ok(results)
@@ -261,28 +562,35 @@ impl<'a> SearchBuilder<'a> {
         schema: &Schema,
     ) -> Result<Option<Criteria<'a>>, ResponseError> {
         let ranking_rules = self.index.main.ranking_rules(reader)?;
+        let attribute_weights = self.index.main.attribute_weights(reader)?;
+
+        // the default criteria must also be built when only the weights or the sort are customized
+        let mut ranking_rules = match ranking_rules {
+            None if attribute_weights.is_some() || self.sort.is_some() => Some(DEFAULT_RANKING_RULES.to_vec()),
+            ranking_rules => ranking_rules,
+        };
+
+        if let Some(overrides) = &self.ranking_rules {
+            let mut rules = ranking_rules.unwrap_or_else(|| DEFAULT_RANKING_RULES.to_vec());
+            while rules.last().map_or(false, RankingRule::is_sort_rule) {
+                rules.pop();
+            }
+            rules.extend(overrides.iter().cloned());
+            ranking_rules = Some(rules);
+        }
 
         if let Some(ranking_rules) = ranking_rules {
             let mut builder = CriteriaBuilder::with_capacity(7 + ranking_rules.len());
-            for rule in ranking_rules {
-                match rule {
-                    RankingRule::Typo => builder.push(Typo),
-                    RankingRule::Words => builder.push(Words),
-                    RankingRule::Proximity => builder.push(Proximity),
-                    RankingRule::Attribute => builder.push(Attribute),
-                    RankingRule::WordsPosition => builder.push(WordsPosition),
-                    RankingRule::Exactness => builder.push(Exactness),
-                    RankingRule::Asc(field) => {
-                        match SortByAttr::lower_is_better(&ranked_map, &schema, &field) {
-                            Ok(rule) => builder.push(rule),
-                            Err(err) => error!("Error during criteria builder; {:?}", err),
-                        }
-                    }
-                    RankingRule::Desc(field) => {
-                        match SortByAttr::higher_is_better(&ranked_map, &schema, &field) {
-                            Ok(rule) => builder.push(rule),
-                            Err(err) => error!("Error during criteria builder; {:?}", err),
-                        }
+            for rule in &ranking_rules {
+                builder.push_ranking_rule(rule, ranked_map, schema, attribute_weights.as_ref());
+            }
+            if let Some(sort) = &self.sort {
+                let field_types = self.index.main.field_types(reader)?;
+                for (field, descending) in sort {
+                    let documents_fields = self.index.documents_fields;
+                    match SortByValue::new(documents_fields, &schema, field_types.as_ref(), field, *descending) {
+                        Ok(rule) => builder.push(rule),
+                        Err(err) => error!("Error during criteria builder; {:?}", err),
                     }
                 }
             }
@@ -309,6 +617,33 @@ impl Ord for MatchPosition {
     }
 }
 
+/// The unit in which the positions of the matches are counted, the JavaScript
+/// strings are for example sliced by UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchesPositionUnit {
+    Bytes,
+    Chars,
+    Utf16,
+}
+
+impl MatchesPositionUnit {
+    fn char_len(self, c: char) -> usize {
+        match self {
+            MatchesPositionUnit::Bytes => c.len_utf8(),
+            MatchesPositionUnit::Chars => 1,
+            MatchesPositionUnit::Utf16 => c.len_utf16(),
+        }
+    }
+
+    /// Converts a position counted in characters of the text into this unit.
+    fn convert(self, text: &str, position: &MatchPosition) -> MatchPosition {
+        let start = text.chars().take(position.start).map(|c| self.char_len(c)).sum();
+        let length = text.chars().skip(position.start).take(position.length).map(|c| self.char_len(c)).sum();
+        MatchPosition { start, length }
+    }
+}
+
 pub type HighlightInfos = HashMap<String, Value>;
 pub type MatchesInfos = HashMap<String, Vec<MatchPosition>>;
 // pub type RankingInfos = HashMap<String, u64>;
@@ -317,6 +652,9 @@ pub type MatchesInfos = HashMap<String, Vec<MatchPosition>>;
 pub struct SearchHit {
     #[serde(flatten)]
     pub document: IndexMap<String, Value>,
+    /// The highlighted and cropped versions of the attributes to highlight or crop,
+    /// or of all the displayed ones if the attributes to retrieve aren't given, the fields
+    /// of the document are left raw.
     #[serde(rename = "_formatted", skip_serializing_if = "IndexMap::is_empty")]
     pub formatted: IndexMap<String, Value>,
     #[serde(rename = "_matchesInfo", skip_serializing_if = "Option::is_none")]
@@ -334,6 +672,62 @@ pub struct SearchResult {
     pub processing_time_ms: usize,
     pub query: String,
     pub facets: Option<HashMap<String, HashMap<String, usize>>>,
+    /// Whether the facet values have been counted on all of the hits, set with the facets.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exhaustive_facets_count: Option<bool>,
+    /// The number of hits in each geohash cell of the requested precision.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geo_distribution: Option<HashMap<String, usize>>,
+    /// Set when the search cutoff of the index has been reached,
+    /// the hits are then not entirely sorted by the ranking rules.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub degraded: bool,
+    /// The last query words that the hits don't contain, they have been ignored because
+    /// no document contained all of the words of the query, when the index allows it.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored_words: Vec<String>,
+    /// The query words that are not indexed and have been searched
+    /// as the two words they are made of, e.g. `harrypotter`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub segmentations: Vec<QuerySplit>,
+    /// The cursor to give as `searchAfter` to retrieve the next page of hits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// The token to give as `snapshotToken` to read the next pages from the same snapshot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_token: Option<String>,
+    /// The seed the sample has been drawn with, to give as `sampleSeed` to draw it again.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_seed: Option<u64>,
+    /// The time spent in each phase of the engine search, the hits formatting excluded.
+    #[serde(skip)]
+    pub timings: Vec<SearchTiming>,
+}
+
+/// The sort key of the last hit returned by a search, the values of the
+/// document compared by the ranking rules, encoded in hexadecimal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchCursor {
+    pub sort_key: Vec<SortKey>,
+    pub query_hash: u64,
+}
+
+impl SearchCursor {
+    pub fn encode(&self) -> String {
+        let bytes = serde_json::to_vec(self).unwrap_or_default();
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    pub fn decode(s: &str) -> Option<SearchCursor> {
+        if s.len() % 2 != 0 || !s.is_ascii() {
+            return None;
+        }
+        let bytes = (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+            .collect::<Option<Vec<_>>>()?;
+        serde_json::from_slice(&bytes).ok()
+    }
 }
 
 /// returns the start index and the length on the crop.
@@ -426,6 +820,53 @@ fn crop_document(
     }
 }
 
+/// The words after the word limit of an attribute are not indexed and can't be found by the
+/// engine, the query words are searched in this remainder to highlight them all the same.
+///
+/// There is no typo tolerance here, the words must match exactly, the last one being a prefix.
+fn highlight_unindexed_words(
+    document: &IndexMap<String, Value>,
+    matches: &mut Vec<Highlight>,
+    schema: &Schema,
+    attributes_to_highlight: &HashSet<String>,
+    max_indexed_words: &BTreeMap<String, usize>,
+    query_words: &[String],
+) {
+    let default_limit = max_indexed_words.get("*").copied().unwrap_or(DEFAULT_WORD_LIMIT);
+
+    for attribute in attributes_to_highlight {
+        let text = match document.get(attribute) {
+            Some(Value::String(text)) => text,
+            _ => continue,
+        };
+        let field_id = match schema.id(attribute) {
+            Some(field_id) if schema.is_indexed(field_id).is_some() => field_id,
+            _ => continue,
+        };
+        let limit = max_indexed_words.get(attribute).copied().unwrap_or(default_limit);
+
+        for token in Tokenizer::new(text).skip_while(|token| token.word_index < limit) {
+            let word = token.word.to_lowercase();
+            let matching = query_words.iter().enumerate().find_map(|(i, query_word)| {
+                if word == *query_word {
+                    Some(token.word.chars().count())
+                } else if i + 1 == query_words.len() && word.starts_with(query_word.as_str()) {
+                    Some(query_word.chars().count())
+                } else {
+                    None
+                }
+            });
+
+            if let Some(length) = matching {
+                // highlights positions are stored on 16 bits
+                if let (Ok(char_index), Ok(char_length)) = (u16::try_from(token.char_index), u16::try_from(length)) {
+                    matches.push(Highlight { attribute: field_id.0, char_index, char_length });
+                }
+            }
+        }
+    }
+}
+
 fn calculate_matches(
     matches: &[Highlight],
     attributes_to_retrieve: Option<HashSet<String>>,
@@ -504,10 +945,91 @@ fn calculate_highlights(
     highlight_result
 }
 
+/// Reorders the items so that at most `max_per_value` consecutive items share the same value,
+/// an item is moved down to the first place where it doesn't make the run too long, and placed
+/// anyway when the remaining items all would. The items without a value are never moved down.
+///
+/// The items are queued by value, the next item is the first of the heads of the queues
+/// that doesn't extend a full run, the heads are kept ordered by their rank.
+fn diversify<T>(items: Vec<T>, max_per_value: usize, mut value: impl FnMut(&T) -> Option<u64>) -> Vec<T> {
+    let values: Vec<_> = items.iter().map(|item| value(item)).collect();
+    let mut items: Vec<_> = items.into_iter().map(Some).collect();
+
+    let mut queues: HashMap<u64, VecDeque<usize>> = HashMap::new();
+    let mut heads = BTreeSet::new();
+    for (rank, value) in values.iter().enumerate() {
+        match value {
+            Some(value) => {
+                let queue = queues.entry(*value).or_default();
+                if queue.is_empty() {
+                    heads.insert(rank);
+                }
+                queue.push_back(rank);
+            }
+            None => {
+                heads.insert(rank);
+            }
+        }
+    }
+
+    let mut diversified = Vec::with_capacity(items.len());
+    let mut run: Option<(u64, usize)> = None;
+
+    while let Some(&first) = heads.iter().next() {
+        let rank = match (values[first], run) {
+            (Some(value), Some((last, len))) if value == last && len >= max_per_value => {
+                heads.iter().nth(1).copied().unwrap_or(first)
+            }
+            _ => first,
+        };
+        heads.remove(&rank);
+
+        let value = values[rank];
+        if let Some(value) = value {
+            let queue = queues.get_mut(&value).unwrap();
+            queue.pop_front();
+            if let Some(&next) = queue.front() {
+                heads.insert(next);
+            }
+        }
+
+        run = match (value, run) {
+            (Some(value), Some((last, len))) if value == last => Some((value, len + 1)),
+            (Some(value), _) => Some((value, 1)),
+            (None, _) => None,
+        };
+        diversified.extend(items[rank].take());
+    }
+
+    diversified
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn diversified_items() {
+        let items = vec![(1, Some(0)), (2, Some(0)), (3, Some(0)), (4, Some(1)), (5, Some(0)), (6, None)];
+        let ids = |items: Vec<(u32, Option<u64>)>| items.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+        assert_eq!(ids(diversify(items.clone(), 2, |(_, value)| *value)), vec![1, 2, 4, 3, 5, 6]);
+        assert_eq!(ids(diversify(items.clone(), 1, |(_, value)| *value)), vec![1, 4, 2, 6, 3, 5]);
+        assert_eq!(ids(diversify(items, 3, |(_, value)| *value)), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn long_runs_are_interleaved() {
+        let items: Vec<_> = (0..100_000).map(|id| (id, Some(if id < 99_000 { 0 } else { 1 }))).collect();
+        let diversified = diversify(items, 1, |(_, value)| *value);
+
+        assert_eq!(diversified.len(), 100_000);
+        assert_eq!(&diversified[..4], &[(0, Some(0)), (99_000, Some(1)), (1, Some(0)), (99_001, Some(1))]);
+        assert_eq!(diversified[1999], (99_999, Some(1)));
+        assert_eq!(diversified[2000], (1000, Some(0)));
+        assert_eq!(diversified[99_999], (98_999, Some(0)));
+    }
+
     #[test]
     fn aligned_crops() {
         let text = r#"En ce début de trentième millénaire, l'Empire n'a jamais été aussi puissant, aussi étendu à travers toute la galaxie. C'est dans sa capitale, Trantor, que l'éminent savant Hari Seldon invente la psychohistoire, une science toute nouvelle, à base de psychologie et de mathématiques, qui lui permet de prédire l'avenir... C'est-à-dire l'effondrement de l'Empire d'ici cinq siècles et au-delà, trente mille années de chaos et de ténèbres. Pour empêcher cette catastrophe et sauver la civilisation, Seldon crée la Fondation."#;
@@ -641,4 +1163,51 @@ mod tests {
 
         assert_eq!(result, result_expected);
     }
+
+    #[test]
+    fn highlight_unindexed_words() {
+        let mut schema = Schema::with_primary_key("id");
+        let field_id = schema.insert_and_index("description").unwrap();
+
+        let mut document = IndexMap::new();
+        document.insert("description".to_string(), Value::String("Ice cream with ice cubes".to_string()));
+
+        let mut attributes_to_highlight = HashSet::new();
+        attributes_to_highlight.insert("description".to_string());
+
+        let mut max_indexed_words = BTreeMap::new();
+        max_indexed_words.insert("description".to_string(), 2);
+
+        let query_words = vec!["ice".to_string(), "cub".to_string()];
+
+        let mut matches = vec![Highlight { attribute: field_id.0, char_index: 0, char_length: 3 }];
+        super::highlight_unindexed_words(
+            &document,
+            &mut matches,
+            &schema,
+            &attributes_to_highlight,
+            &max_indexed_words,
+            &query_words,
+        );
+
+        let expected = vec![
+            Highlight { attribute: field_id.0, char_index: 0, char_length: 3 },
+            Highlight { attribute: field_id.0, char_index: 15, char_length: 3 },
+            Highlight { attribute: field_id.0, char_index: 19, char_length: 3 },
+        ];
+        assert_eq!(matches, expected);
+    }
+
+    #[test]
+    fn convert_matches_positions() {
+        let text = "🦀 crabe ランタン";
+        let crabe = MatchPosition { start: 2, length: 5 };
+        let lantern = MatchPosition { start: 8, length: 4 };
+
+        assert_eq!(MatchesPositionUnit::Chars.convert(text, &crabe), crabe);
+        assert_eq!(MatchesPositionUnit::Bytes.convert(text, &crabe), MatchPosition { start: 5, length: 5 });
+        assert_eq!(MatchesPositionUnit::Utf16.convert(text, &crabe), MatchPosition { start: 3, length: 5 });
+        assert_eq!(MatchesPositionUnit::Bytes.convert(text, &lantern), MatchPosition { start: 11, length: 12 });
+        assert_eq!(MatchesPositionUnit::Utf16.convert(text, &lantern), MatchPosition { start: 9, length: 4 });
+    }
 }
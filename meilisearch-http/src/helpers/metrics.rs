@@ -0,0 +1,248 @@
+//! Request counts and latencies of the routes.
+//!
+//! The requests are labeled with the template of the route they reached, not with their
+//! path, the number of series stays bounded whatever the number of indexes and documents.
+//! The metrics are rendered in the Prometheus text format by the metrics route.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_service::{Service, Transform};
+use actix_web::{dev::ServiceRequest, dev::ServiceResponse, Error};
+use futures::future::{ok, Future, Ready};
+
+use crate::Data;
+
+/// The upper bounds, in seconds, of the buckets of the latency histograms.
+const BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// The label of the requests that did not reach any known route.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Default)]
+struct RouteStats {
+    statuses: BTreeMap<u16, u64>,
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+pub struct RouteMetrics {
+    templates: Vec<(String, Vec<String>)>,
+    routes: Mutex<BTreeMap<(String, String), RouteStats>>,
+}
+
+fn segments(path: &str) -> Vec<String> {
+    path.trim_matches('/').split('/').map(String::from).collect()
+}
+
+fn is_parameter(segment: &str) -> bool {
+    segment.starts_with('{') && segment.ends_with('}')
+}
+
+impl RouteMetrics {
+    /// Uses the paths of the given OpenAPI document as the route templates.
+    pub fn from_openapi(document: &str) -> RouteMetrics {
+        let document: serde_json::Value = serde_json::from_str(document).unwrap_or_default();
+        let paths = document["paths"].as_object().map(|paths| paths.keys().cloned().collect());
+        RouteMetrics::new(paths.unwrap_or_default())
+    }
+
+    pub fn new(templates: Vec<String>) -> RouteMetrics {
+        let templates = templates.into_iter().map(|t| {
+            let segments = segments(&t);
+            (t, segments)
+        });
+
+        RouteMetrics {
+            templates: templates.collect(),
+            routes: Mutex::default(),
+        }
+    }
+
+    /// Returns the template matching the path, the ones with the most literal segments win,
+    /// `/indexes/search` is then preferred to `/indexes/{index_uid}`.
+    pub fn route(&self, path: &str) -> &str {
+        let path = segments(path);
+        let mut best: Option<(&str, usize)> = None;
+
+        for (template, template_segments) in &self.templates {
+            if template_segments.len() != path.len() {
+                continue;
+            }
+
+            let matches = template_segments
+                .iter()
+                .zip(&path)
+                .all(|(t, p)| is_parameter(t) || t == p);
+
+            if matches {
+                let literals = template_segments.iter().filter(|t| !is_parameter(t)).count();
+                if best.map_or(true, |(_, l)| literals > l) {
+                    best = Some((template, literals));
+                }
+            }
+        }
+
+        best.map_or(UNMATCHED_ROUTE, |(template, _)| template)
+    }
+
+    pub fn record(&self, method: &str, path: &str, status: u16, duration: Duration) {
+        let route = self.route(path).to_string();
+        let seconds = duration.as_secs_f64();
+
+        let mut routes = self.routes.lock().unwrap();
+        let stats = routes.entry((route, method.to_string())).or_default();
+        *stats.statuses.entry(status).or_default() += 1;
+        for (bucket, bound) in stats.buckets.iter_mut().zip(BUCKETS.iter()) {
+            if seconds <= *bound {
+                *bucket += 1;
+            }
+        }
+        stats.sum += seconds;
+        stats.count += 1;
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let routes = self.routes.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP meilisearch_http_requests_total The number of requests answered.\n");
+        out.push_str("# TYPE meilisearch_http_requests_total counter\n");
+        for ((route, method), stats) in routes.iter() {
+            for (status, count) in &stats.statuses {
+                let _ = writeln!(
+                    out,
+                    "meilisearch_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    method, route, status, count,
+                );
+            }
+        }
+
+        out.push_str("# HELP meilisearch_http_request_duration_seconds The time taken to answer the requests.\n");
+        out.push_str("# TYPE meilisearch_http_request_duration_seconds histogram\n");
+        for ((route, method), stats) in routes.iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, route);
+            for (count, bound) in stats.buckets.iter().zip(BUCKETS.iter()) {
+                let _ = writeln!(
+                    out,
+                    "meilisearch_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count,
+                );
+            }
+            let _ = writeln!(
+                out,
+                "meilisearch_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, stats.count,
+            );
+            let _ = writeln!(out, "meilisearch_http_request_duration_seconds_sum{{{}}} {}", labels, stats.sum);
+            let _ = writeln!(out, "meilisearch_http_request_duration_seconds_count{{{}}} {}", labels, stats.count);
+        }
+
+        out
+    }
+}
+
+/// Records the status and the latency of every request in the route metrics.
+pub struct RequestMetrics;
+
+impl<S: 'static, B> Transform<S> for RequestMetrics
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestMetricsMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for RequestMetricsMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let mut svc = self.service.clone();
+        let data = req.app_data::<Data>().unwrap();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = svc.call(req).await;
+            let status = match &result {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            data.metrics.record(&method, &path, status.as_u16(), start.elapsed());
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics() -> RouteMetrics {
+        let templates = vec!["/indexes", "/indexes/search", "/indexes/{index_uid}", "/indexes/{index_uid}/search"];
+        RouteMetrics::new(templates.into_iter().map(String::from).collect())
+    }
+
+    #[test]
+    fn route_templates() {
+        let metrics = metrics();
+        assert_eq!(metrics.route("/indexes/movies"), "/indexes/{index_uid}");
+        assert_eq!(metrics.route("/indexes/search"), "/indexes/search");
+        assert_eq!(metrics.route("/indexes/movies/search/"), "/indexes/{index_uid}/search");
+        assert_eq!(metrics.route("/indexes/movies/documents"), UNMATCHED_ROUTE);
+    }
+
+    #[test]
+    fn render_histograms() {
+        let metrics = metrics();
+        metrics.record("GET", "/indexes/movies/search", 200, Duration::from_millis(20));
+        metrics.record("GET", "/indexes/books/search", 200, Duration::from_millis(200));
+        metrics.record("GET", "/indexes/books/search", 404, Duration::from_millis(2));
+
+        let rendered = metrics.render();
+        let labels = "method=\"GET\",route=\"/indexes/{index_uid}/search\"";
+        assert!(rendered.contains(&format!("meilisearch_http_requests_total{{{},status=\"200\"}} 2", labels)));
+        assert!(rendered.contains(&format!("meilisearch_http_requests_total{{{},status=\"404\"}} 1", labels)));
+        assert!(rendered.contains(&format!("meilisearch_http_request_duration_seconds_bucket{{{},le=\"0.005\"}} 1", labels)));
+        assert!(rendered.contains(&format!("meilisearch_http_request_duration_seconds_bucket{{{},le=\"0.025\"}} 2", labels)));
+        assert!(rendered.contains(&format!("meilisearch_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 3", labels)));
+        assert!(rendered.contains(&format!("meilisearch_http_request_duration_seconds_count{{{}}} 3", labels)));
+    }
+}
@@ -1,6 +1,36 @@
+pub mod allowlist;
+pub mod audit_log;
 pub mod authentication;
+pub mod background;
+pub mod blocking;
 pub mod meilisearch;
+pub mod metrics;
 pub mod normalize_slashes;
+pub mod payload_limit;
+pub mod read_snapshots;
+pub mod reader_limit;
+pub mod remote_documents;
+pub mod s3;
+pub mod search_log;
+pub mod search_pool;
+pub mod span_exporter;
+pub mod trace_context;
+pub mod upload_sessions;
+pub mod usage;
+pub mod write_queue;
 
+pub use allowlist::IpAllowlist;
+pub use audit_log::AuditLogging;
 pub use authentication::Authentication;
+pub use background::BackgroundThread;
+pub use blocking::run_blocking;
+pub use metrics::{RequestMetrics, RouteMetrics};
 pub use normalize_slashes::NormalizeSlashes;
+pub use payload_limit::{PayloadLimit, PayloadLimits};
+pub use read_snapshots::ReadSnapshots;
+pub use reader_limit::ReaderLimit;
+pub use search_log::SearchLog;
+pub use search_pool::SearchPool;
+pub use span_exporter::SpanExporter;
+pub use trace_context::{TraceContext, TracePropagation};
+pub use usage::{Usage, UsageAccounting};
@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::PayloadError;
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::web::Bytes;
+use actix_web::Error;
+use futures::future::{err, ok, Future, Ready};
+use futures::Stream;
+
+use crate::error::ResponseError;
+use crate::option::Opt;
+use crate::Data;
+
+/// The maximum sizes of the payloads, by class of routes.
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadLimits {
+    pub default: usize,
+    pub documents: usize,
+    pub search: usize,
+    pub settings: usize,
+    pub chunks: usize,
+}
+
+impl PayloadLimits {
+    pub fn from_opt(opt: &Opt) -> PayloadLimits {
+        let default = opt.http_payload_size_limit;
+        let documents = opt.documents_payload_size_limit.unwrap_or(default);
+        PayloadLimits {
+            default,
+            documents,
+            search: opt.search_payload_size_limit.unwrap_or(default),
+            settings: opt.settings_payload_size_limit.unwrap_or(default),
+            chunks: opt.upload_chunk_size_limit.unwrap_or(documents),
+        }
+    }
+
+    /// The biggest of the limits, the payload extractors are configured with it
+    /// and the payloads are checked against the limit of their route beforehand.
+    pub fn max(&self) -> usize {
+        self.default.max(self.documents).max(self.search).max(self.settings).max(self.chunks)
+    }
+
+    /// Returns the limit of the route with the given path.
+    pub fn limit(&self, path: &str) -> usize {
+        let segments: Vec<_> = path.trim_start_matches('/').split('/').collect();
+        match segments.as_slice() {
+            ["indexes", "search"] => self.search,
+            ["indexes", _, "documents", "sessions", _, "chunks", _] => self.chunks,
+            ["indexes", _, "documents", ..] => self.documents,
+            ["indexes", _, "search", ..] => self.search,
+            ["indexes", _, "settings", ..] => self.settings,
+            _ => self.default,
+        }
+    }
+}
+
+/// Rejects the requests announcing a payload bigger than the limit of their route,
+/// the payloads that don't announce their length are cut once they go over it.
+pub struct PayloadLimit;
+
+impl<S: 'static, B> Transform<S> for PayloadLimit
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = PayloadLimitMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(PayloadLimitMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct PayloadLimitMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for PayloadLimitMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: ServiceRequest) -> Self::Future {
+        let mut svc = self.service.clone();
+        let data = req.app_data::<Data>().unwrap();
+        let limit = data.payload_limits.limit(req.path());
+
+        let length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse::<usize>().ok());
+
+        match length {
+            Some(length) if length > limit => Box::pin(err(ResponseError::PayloadTooLarge.into())),
+            _ => {
+                // the chunked payloads have no length, the bytes are counted while being read
+                let payload = LimitedPayload { payload: req.take_payload(), limit, read: 0 };
+                req.set_payload(Payload::Stream(Box::pin(payload)));
+                Box::pin(svc.call(req))
+            }
+        }
+    }
+}
+
+/// A payload failing with an overflow once more bytes than the limit have been read.
+struct LimitedPayload {
+    payload: Payload,
+    limit: usize,
+    read: usize,
+}
+
+impl Stream for LimitedPayload {
+    type Item = Result<Bytes, PayloadError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.payload).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.read += chunk.len();
+                if self.read > self.limit {
+                    Poll::Ready(Some(Err(PayloadError::Overflow)))
+                } else {
+                    Poll::Ready(Some(Ok(chunk)))
+                }
+            }
+            poll => poll,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_limits() {
+        let limits = PayloadLimits { default: 1, documents: 2, search: 3, settings: 4, chunks: 5 };
+        assert_eq!(limits.max(), 5);
+        assert_eq!(limits.limit("/indexes/movies/documents"), 2);
+        assert_eq!(limits.limit("/indexes/movies/documents/sessions/abc"), 2);
+        assert_eq!(limits.limit("/indexes/movies/documents/sessions/abc/chunks/0"), 5);
+        assert_eq!(limits.limit("/indexes/movies/search"), 3);
+        assert_eq!(limits.limit("/indexes/search"), 3);
+        assert_eq!(limits.limit("/indexes/movies/settings/ranking-rules"), 4);
+        assert_eq!(limits.limit("/indexes/movies"), 1);
+        assert_eq!(limits.limit("/keys"), 1);
+    }
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam_channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender};
+use meilisearch_core::{Database, MainT};
+
+use crate::error::ResponseError;
+use crate::helpers::reader_limit::LimitedTxn;
+use crate::helpers::{ReaderLimit, SearchPool};
+
+type Job = Box<dyn FnOnce(Option<&heed::RoTxn<MainT>>) + Send>;
+
+/// The number of seconds clients are asked to wait before retrying when too many snapshots are pinned.
+const RETRY_AFTER_SECS: u64 = 1;
+
+enum Command {
+    Open {
+        token: String,
+        db: Arc<Database>,
+        reader_limit: ReaderLimit,
+        deadline: Instant,
+        opened: Sender<Result<(), ResponseError>>,
+    },
+    Run {
+        token: String,
+        job: Job,
+    },
+}
+
+struct Pinned {
+    worker: usize,
+    index_uid: String,
+    expires_at: Instant,
+}
+
+/// Read transactions kept open between requests, the pages of a search
+/// are then all read from the same state of the database.
+///
+/// lmdb read transactions cannot be moved between threads, the pinned snapshots are kept by a
+/// fixed number of threads which run the searches given to them until the time to live has
+/// elapsed. The searches are run from the search pool, they are queued and rejected like the others.
+pub struct ReadSnapshots {
+    ttl: Duration,
+    max: usize,
+    workers: Vec<Sender<Command>>,
+    next_worker: AtomicUsize,
+    pinned: Mutex<HashMap<String, Pinned>>,
+}
+
+impl ReadSnapshots {
+    pub fn new(ttl: Duration, max: usize, workers: usize) -> ReadSnapshots {
+        let workers = (0..workers.max(1))
+            .map(|i| {
+                let (sender, receiver) = unbounded();
+                thread::Builder::new()
+                    .name(format!("read-snapshots-{}", i))
+                    .spawn(move || keep_snapshots(receiver))
+                    .expect("impossible to spawn a read snapshots thread");
+                sender
+            })
+            .collect();
+
+        ReadSnapshots { ttl, max, workers, next_worker: AtomicUsize::new(0), pinned: Mutex::default() }
+    }
+
+    /// Opens a read transaction that is kept for the time to live, returns the token identifying it,
+    /// the token can only be used to search the index it has been pinned for.
+    pub fn pin(&self, db: Arc<Database>, reader_limit: ReaderLimit, index_uid: &str) -> Result<String, ResponseError> {
+        let token = format!("{:032x}", rand::random::<u128>());
+        let worker = self.next_worker.fetch_add(1, Ordering::Relaxed) % self.workers.len();
+        let deadline = Instant::now() + self.ttl;
+
+        // the snapshot is reserved, the transaction is opened without holding the lock
+        {
+            let mut pinned = self.pinned.lock().unwrap();
+            let now = Instant::now();
+            pinned.retain(|_, snapshot| snapshot.expires_at > now);
+            if pinned.len() >= self.max {
+                return Err(ResponseError::Overloaded(RETRY_AFTER_SECS));
+            }
+            let snapshot = Pinned { worker, index_uid: index_uid.to_string(), expires_at: deadline };
+            pinned.insert(token.clone(), snapshot);
+        }
+
+        let (opened, wait_opened) = bounded(1);
+        let command = Command::Open { token: token.clone(), db, reader_limit, deadline, opened };
+        let result = self.workers[worker]
+            .send(command)
+            .map_err(|_| ResponseError::internal("the read snapshots thread has stopped"))
+            .and_then(|()| {
+                wait_opened
+                    .recv()
+                    .map_err(|_| ResponseError::internal("the read snapshots thread has stopped"))?
+            });
+
+        match result {
+            Ok(()) => Ok(token),
+            Err(e) => {
+                self.pinned.lock().unwrap().remove(&token);
+                Err(e)
+            }
+        }
+    }
+
+    /// Executes `f` with the read transaction of the snapshot, an error is returned if the
+    /// token is unknown, if the snapshot has expired or if it has been pinned for another index.
+    pub fn run<F, T>(
+        &self,
+        search_pool: &SearchPool,
+        token: &str,
+        index_uid: &str,
+        f: F,
+    ) -> impl Future<Output = Result<T, ResponseError>>
+    where
+        F: FnOnce(&heed::RoTxn<MainT>) -> Result<T, ResponseError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let worker = match self.pinned.lock().unwrap().get(token) {
+            Some(snapshot) if snapshot.expires_at > Instant::now() && snapshot.index_uid == index_uid => {
+                Ok(self.workers[snapshot.worker].clone())
+            }
+            _ => Err(expired()),
+        };
+
+        let token = token.to_string();
+        let search = worker.map(|worker| {
+            search_pool.run(move || {
+                let (tx, rx) = bounded(1);
+                let job: Job = Box::new(move |reader| {
+                    let _ = tx.send(reader.map_or_else(|| Err(expired()), f));
+                });
+                worker.send(Command::Run { token, job }).map_err(|_| expired())?;
+                rx.recv().map_err(|_| expired())?
+            })
+        });
+
+        async move { search?.await }
+    }
+
+    /// Moves the snapshots pinned for a renamed index to its new uid.
+    pub fn rename_index(&self, from: &str, to: &str) {
+        for snapshot in self.pinned.lock().unwrap().values_mut() {
+            if snapshot.index_uid == from {
+                snapshot.index_uid = to.to_string();
+            }
+        }
+    }
+
+    pub fn pinned(&self) -> usize {
+        let now = Instant::now();
+        self.pinned.lock().unwrap().values().filter(|snapshot| snapshot.expires_at > now).count()
+    }
+}
+
+fn expired() -> ResponseError {
+    ResponseError::bad_parameter("snapshotToken", "the snapshot does not exist or has expired")
+}
+
+/// Keeps the read transactions of the snapshots given to a thread, they are aborted once expired.
+fn keep_snapshots(commands: Receiver<Command>) {
+    let mut readers: HashMap<String, (LimitedTxn<MainT>, Instant)> = HashMap::new();
+
+    loop {
+        let now = Instant::now();
+        readers.retain(|_, (_, deadline)| *deadline > now);
+
+        let next_deadline = readers.values().map(|(_, deadline)| *deadline).min();
+        let command = match next_deadline {
+            Some(deadline) => match commands.recv_timeout(deadline.saturating_duration_since(now)) {
+                Ok(command) => command,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            },
+            None => match commands.recv() {
+                Ok(command) => command,
+                Err(_) => break,
+            },
+        };
+
+        match command {
+            Command::Open { token, db, reader_limit, deadline, opened } => match reader_limit.main_read_txn(&db) {
+                Ok(reader) => {
+                    readers.insert(token, (reader, deadline));
+                    let _ = opened.send(Ok(()));
+                }
+                Err(e) => {
+                    let _ = opened.send(Err(e));
+                }
+            },
+            Command::Run { token, job } => {
+                let reader = readers
+                    .get(&token)
+                    .filter(|(_, deadline)| *deadline > Instant::now())
+                    .map(|(reader, _)| &**reader);
+                job(reader);
+            }
+        }
+    }
+}
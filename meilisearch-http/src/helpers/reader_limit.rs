@@ -0,0 +1,167 @@
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use meilisearch_core::{Database, MainT, UpdateT};
+use serde::Serialize;
+
+use crate::error::ResponseError;
+
+/// The number of seconds clients are asked to wait before retrying when no reader slot is free.
+const RETRY_AFTER_SECS: u64 = 1;
+
+/// The reader slots kept for the update loops, the snapshots and the replication,
+/// these read transactions are not counted by the limit.
+const RESERVED_READERS: usize = 8;
+
+#[derive(Default)]
+struct Slots {
+    max: usize,
+    in_use: AtomicUsize,
+    peak: AtomicUsize,
+    acquired: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl Slots {
+    fn acquire(self: &Arc<Self>) -> Result<Slot, ResponseError> {
+        let mut in_use = self.in_use.load(Ordering::Relaxed);
+        loop {
+            if in_use >= self.max {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return Err(ResponseError::Overloaded(RETRY_AFTER_SECS));
+            }
+
+            match self.in_use.compare_exchange_weak(in_use, in_use + 1, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => in_use = current,
+            }
+        }
+
+        let mut peak = self.peak.load(Ordering::Relaxed);
+        while peak < in_use + 1 {
+            match self.peak.compare_exchange_weak(peak, in_use + 1, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => peak = current,
+            }
+        }
+
+        self.acquired.fetch_add(1, Ordering::Relaxed);
+        Ok(Slot(self.clone()))
+    }
+
+    fn stats(&self) -> ReaderSlotsStats {
+        ReaderSlotsStats {
+            in_use: self.in_use.load(Ordering::Relaxed),
+            peak: self.peak.load(Ordering::Relaxed),
+            acquired: self.acquired.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+struct Slot(Arc<Slots>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.in_use.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// A read transaction holding one of the reader slots of the limit,
+/// the slot is given back when the transaction is dropped.
+pub struct LimitedTxn<T> {
+    txn: heed::RoTxn<T>,
+    _slot: Slot,
+}
+
+impl<T> Deref for LimitedTxn<T> {
+    type Target = heed::RoTxn<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.txn
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReaderSlotsStats {
+    pub in_use: usize,
+    pub peak: usize,
+    pub acquired: u64,
+    pub rejected: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReaderLimitStats {
+    pub max_readers: usize,
+    pub main: ReaderSlotsStats,
+    pub update: ReaderSlotsStats,
+}
+
+/// Bounds the number of read transactions opened by the requests, it counts the reader slots in use
+/// like a semaphore, every request still opens its own transaction which is aborted once dropped.
+///
+/// lmdb fails with `MDB_READERS_FULL` once all the reader slots of an environment
+/// are taken, requests are rejected before reaching this limit instead.
+///
+/// The transactions are neither pooled nor renewed: heed doesn't expose the reset and the renewal
+/// of the read transactions, and the environments are not opened with `MDB_NOTLS`, a transaction
+/// is thus bound to the thread that opened it while a request can be resumed on another thread.
+#[derive(Clone)]
+pub struct ReaderLimit {
+    main: Arc<Slots>,
+    update: Arc<Slots>,
+}
+
+impl ReaderLimit {
+    pub fn new(max_readers: u32) -> ReaderLimit {
+        let max = (max_readers as usize).saturating_sub(RESERVED_READERS).max(1);
+        let slots = || Arc::new(Slots { max, ..Slots::default() });
+        ReaderLimit { main: slots(), update: slots() }
+    }
+
+    pub fn main_read_txn(&self, db: &Database) -> Result<LimitedTxn<MainT>, ResponseError> {
+        let slot = self.main.acquire()?;
+        let txn = db.main_read_txn()?;
+        Ok(LimitedTxn { txn, _slot: slot })
+    }
+
+    pub fn update_read_txn(&self, db: &Database) -> Result<LimitedTxn<UpdateT>, ResponseError> {
+        let slot = self.update.acquire()?;
+        let txn = db.update_read_txn()?;
+        Ok(LimitedTxn { txn, _slot: slot })
+    }
+
+    pub fn stats(&self) -> ReaderLimitStats {
+        ReaderLimitStats {
+            max_readers: self.main.max,
+            main: self.main.stats(),
+            update: self.update.stats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_when_full() {
+        let slots = Arc::new(Slots { max: 2, ..Slots::default() });
+
+        let first = slots.acquire().unwrap();
+        let _second = slots.acquire().unwrap();
+        assert!(matches!(slots.acquire(), Err(ResponseError::Overloaded(_))));
+
+        drop(first);
+        let _third = slots.acquire().unwrap();
+
+        let stats = slots.stats();
+        assert_eq!(stats.in_use, 2);
+        assert_eq!(stats.peak, 2);
+        assert_eq!(stats.acquired, 3);
+        assert_eq!(stats.rejected, 1);
+    }
+}
@@ -0,0 +1,327 @@
+//! Fetching of documents files hosted on another server.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read};
+
+use indexmap::IndexMap;
+use serde::de::{self, Deserializer as _, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ResponseError;
+
+type Document = IndexMap<String, Value>;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentsFormat {
+    /// A JSON array of objects.
+    Json,
+    /// One JSON object per line.
+    Ndjson,
+    /// A header line with the fields names then one document per line,
+    /// every value is kept as a string.
+    Csv,
+}
+
+/// Downloads the file at `url`, its content is read as it is received.
+pub fn fetch_file(url: &str, headers: &HashMap<String, String>) -> Result<impl BufRead, ResponseError> {
+    let mut request = ureq::get(url);
+    for (name, value) in headers {
+        request.set(name, value);
+    }
+
+    let response = request.call();
+    if let Some(error) = response.synthetic_error() {
+        return Err(ResponseError::bad_request(format!("Impossible to fetch {}: {}", url, error)));
+    }
+    if !response.ok() {
+        return Err(ResponseError::bad_request(format!(
+            "Impossible to fetch {}: the server returned the status {}",
+            url,
+            response.status()
+        )));
+    }
+
+    Ok(BufReader::new(response.into_reader()))
+}
+
+/// Reads the documents of a file in the given format.
+pub fn read_documents(reader: impl BufRead, format: DocumentsFormat) -> Result<Vec<Document>, ResponseError> {
+    let mut documents = Vec::new();
+    read_documents_batches(reader, format, usize::MAX, |batch| {
+        documents.extend(batch);
+        Ok(())
+    })?;
+    Ok(documents)
+}
+
+/// Reads the documents of a file in the given format as they are received, they are given to `f`
+/// by batches of `batch_size`. Only the batch being read is kept in memory, the file is never
+/// read entirely. Stops at the first invalid document or at the first error returned by `f`.
+pub fn read_documents_batches<F>(
+    reader: impl BufRead,
+    format: DocumentsFormat,
+    batch_size: usize,
+    mut f: F,
+) -> Result<(), ResponseError>
+where
+    F: FnMut(Vec<Document>) -> Result<(), ResponseError>,
+{
+    match format {
+        DocumentsFormat::Json => json_batches(reader, batch_size, &mut f),
+        DocumentsFormat::Ndjson => batches(ndjson_documents(reader), batch_size, &mut f),
+        DocumentsFormat::Csv => batches(csv_documents(reader), batch_size, &mut f),
+    }
+}
+
+fn batches<F>(
+    documents: impl Iterator<Item = Result<Document, ResponseError>>,
+    batch_size: usize,
+    f: &mut F,
+) -> Result<(), ResponseError>
+where
+    F: FnMut(Vec<Document>) -> Result<(), ResponseError>,
+{
+    let mut batch = Vec::new();
+    for document in documents {
+        batch.push(document?);
+        if batch.len() >= batch_size {
+            f(std::mem::take(&mut batch))?;
+        }
+    }
+
+    if !batch.is_empty() {
+        f(batch)?;
+    }
+
+    Ok(())
+}
+
+/// The documents of a JSON array are deserialized one at a time, the array is never
+/// deserialized entirely.
+fn json_batches<F>(reader: impl Read, batch_size: usize, f: &mut F) -> Result<(), ResponseError>
+where
+    F: FnMut(Vec<Document>) -> Result<(), ResponseError>,
+{
+    let mut refused = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let visitor = JsonBatches { batch_size, f, refused: &mut refused };
+    let result = deserializer.deserialize_seq(visitor).and_then(|()| deserializer.end());
+
+    match refused {
+        Some(error) => Err(error),
+        None => result.map_err(|e| ResponseError::bad_request(format!("invalid documents: {}", e))),
+    }
+}
+
+struct JsonBatches<'a, F> {
+    batch_size: usize,
+    f: &'a mut F,
+    /// The error returned by `f`, the deserializer can only return its own errors.
+    refused: &'a mut Option<ResponseError>,
+}
+
+impl<'a, F> JsonBatches<'a, F>
+where
+    F: FnMut(Vec<Document>) -> Result<(), ResponseError>,
+{
+    fn send<E: de::Error>(&mut self, batch: Vec<Document>) -> Result<(), E> {
+        (self.f)(batch).map_err(|e| {
+            *self.refused = Some(e);
+            E::custom("the documents have been refused")
+        })
+    }
+}
+
+impl<'de, 'a, F> Visitor<'de> for JsonBatches<'a, F>
+where
+    F: FnMut(Vec<Document>) -> Result<(), ResponseError>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of documents")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+        let mut batch = Vec::new();
+        while let Some(document) = seq.next_element()? {
+            batch.push(document);
+            if batch.len() >= self.batch_size {
+                self.send(std::mem::take(&mut batch))?;
+            }
+        }
+
+        if !batch.is_empty() {
+            self.send(batch)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads the documents of a NDJSON file one line at a time, as they are consumed.
+pub fn ndjson_documents(reader: impl BufRead) -> impl Iterator<Item = Result<Document, ResponseError>> {
+    reader.lines().enumerate().filter_map(|(i, line)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(ResponseError::bad_request(e))),
+        };
+        if line.trim().is_empty() {
+            return None;
+        }
+
+        let document = serde_json::from_str(&line)
+            .map_err(|e| ResponseError::bad_request(format!("invalid document at line {}: {}", i + 1, e)));
+        Some(document)
+    })
+}
+
+/// Reads the documents of a CSV file one record at a time, as they are consumed.
+fn csv_documents(reader: impl BufRead) -> impl Iterator<Item = Result<Document, ResponseError>> {
+    let mut records = CsvRecords { reader, line: String::new() };
+    let headers = records.next().transpose();
+
+    let (headers, error) = match headers {
+        Ok(headers) => (headers.unwrap_or_default(), None),
+        Err(e) => (Vec::new(), Some(Err(e))),
+    };
+
+    let documents = records.enumerate().map(move |(i, record)| {
+        let record = record?;
+        if record.len() != headers.len() {
+            return Err(ResponseError::bad_request(format!(
+                "invalid document at line {}: expected {} fields, found {}",
+                i + 2,
+                headers.len(),
+                record.len()
+            )));
+        }
+
+        let document = headers
+            .iter()
+            .cloned()
+            .zip(record.into_iter().map(Value::String))
+            .collect();
+        Ok(document)
+    });
+
+    error.into_iter().chain(documents)
+}
+
+/// Reads the records of a RFC 4180 CSV file one at a time, quoted fields can contain
+/// commas, line breaks and doubled quotes.
+struct CsvRecords<R> {
+    reader: R,
+    line: String,
+}
+
+impl<R: BufRead> Iterator for CsvRecords<R> {
+    type Item = Result<Vec<String>, ResponseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) if in_quotes => {
+                    return Some(Err(ResponseError::bad_request("invalid csv: unterminated quoted field")));
+                }
+                Ok(0) if field.is_empty() && record.is_empty() => return None,
+                Ok(0) => {
+                    record.push(field);
+                    return Some(Ok(record));
+                }
+                Ok(_) => (),
+                Err(e) => return Some(Err(ResponseError::bad_request(e))),
+            }
+
+            // a line only ends the record if the line break isn't quoted
+            let mut chars = self.line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match (c, in_quotes) {
+                    ('"', true) if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    ('"', true) => in_quotes = false,
+                    ('"', false) if field.is_empty() => in_quotes = true,
+                    (',', false) => record.push(std::mem::take(&mut field)),
+                    ('\r', false) if chars.peek() == Some(&'\n') => (),
+                    ('\n', false) => {
+                        record.push(std::mem::take(&mut field));
+                        if record.iter().any(|f| !f.is_empty()) {
+                            return Some(Ok(record));
+                        }
+                        record.clear();
+                    }
+                    (c, _) => field.push(c),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ndjson_documents() {
+        let content = "{\"id\": 1, \"title\": \"hello\"}\n\n{\"id\": 2}\n";
+        let documents = read_documents(content.as_bytes(), DocumentsFormat::Ndjson).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0]["title"], "hello");
+    }
+
+    #[test]
+    fn csv_documents() {
+        let content = "id,title\r\n1,\"hello, \"\"world\"\"\"\n2,\"multi\nline\"\n";
+        let documents = read_documents(content.as_bytes(), DocumentsFormat::Csv).unwrap();
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0]["id"], "1");
+        assert_eq!(documents[0]["title"], "hello, \"world\"");
+        assert_eq!(documents[1]["title"], "multi\nline");
+    }
+
+    #[test]
+    fn csv_mismatching_fields() {
+        let content = "id,title\n1\n";
+        assert!(read_documents(content.as_bytes(), DocumentsFormat::Csv).is_err());
+    }
+
+    #[test]
+    fn json_documents_by_batches() {
+        let content = "[{\"id\": 1}, {\"id\": 2}, {\"id\": 3}]";
+        let mut batches = Vec::new();
+        read_documents_batches(content.as_bytes(), DocumentsFormat::Json, 2, |batch| {
+            batches.push(batch.len());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(batches, vec![2, 1]);
+    }
+
+    #[test]
+    fn refused_batch_stops_the_reading() {
+        let content = "id\n1\n2\n3\n";
+        let mut batches = 0;
+        let result = read_documents_batches(content.as_bytes(), DocumentsFormat::Csv, 1, |_| {
+            batches += 1;
+            Err(ResponseError::bad_request("refused"))
+        });
+        assert!(result.is_err());
+        assert_eq!(batches, 1);
+
+        let content = "[{\"id\": 1}, {\"id\": 2}]";
+        let result = read_documents_batches(content.as_bytes(), DocumentsFormat::Json, 1, |_| {
+            Err(ResponseError::bad_request("refused"))
+        });
+        assert_eq!(result.unwrap_err().to_string(), ResponseError::bad_request("refused").to_string());
+    }
+}
@@ -0,0 +1,131 @@
+//! Sampled log of the search requests.
+//!
+//! A fraction of the searches is logged as JSON lines under the `search` target, with their
+//! query, filters, number of hits and latency. The parts of the queries and filters matching
+//! the redaction rules are replaced before being logged, to keep the personal data out of the logs.
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use log::{error, info};
+use regex::Regex;
+use serde::Serialize;
+
+use crate::option::Opt;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// A regular expression, the matching parts of the logged queries and filters are redacted.
+#[derive(Debug, Clone)]
+pub struct Redaction(Regex);
+
+impl FromStr for Redaction {
+    type Err = regex::Error;
+
+    fn from_str(s: &str) -> Result<Redaction, regex::Error> {
+        Regex::new(s).map(Redaction)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchLogEntry<'a> {
+    pub indexes: Vec<&'a str>,
+    pub query: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub filters: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facet_filters: Option<String>,
+    pub nb_hits: usize,
+    pub processing_time_ms: usize,
+}
+
+#[derive(Clone)]
+pub struct SearchLog {
+    sample_rate: f64,
+    redactions: Vec<Redaction>,
+}
+
+impl SearchLog {
+    /// Returns the search log of the options, `None` if the searches must not be logged.
+    pub fn from_opt(opt: &Opt) -> Option<SearchLog> {
+        if opt.search_log_sample_rate <= 0.0 {
+            return None;
+        }
+
+        Some(SearchLog {
+            sample_rate: opt.search_log_sample_rate.min(1.0),
+            redactions: opt.search_log_redactions.clone(),
+        })
+    }
+
+    /// Draws whether the current search must be logged.
+    pub fn sampled(&self) -> bool {
+        rand::random::<f64>() < self.sample_rate
+    }
+
+    pub fn redact(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for Redaction(regex) in &self.redactions {
+            text = regex.replace_all(&text, REDACTED).into_owned();
+        }
+        text
+    }
+
+    /// Builds the entry of a search, with its query and filters redacted.
+    pub fn entry<'a>(
+        &self,
+        indexes: Vec<&'a str>,
+        query: &str,
+        filters: Vec<&str>,
+        facet_filters: Option<&str>,
+        nb_hits: usize,
+        latency: Duration,
+    ) -> SearchLogEntry<'a> {
+        SearchLogEntry {
+            indexes,
+            query: self.redact(query),
+            filters: filters.into_iter().map(|f| self.redact(f)).collect(),
+            facet_filters: facet_filters.map(|f| self.redact(f)),
+            nb_hits,
+            processing_time_ms: latency.as_millis() as usize,
+        }
+    }
+
+    pub fn log(&self, entry: &SearchLogEntry) {
+        match serde_json::to_string(entry) {
+            Ok(line) => info!(target: "search", "{}", line),
+            Err(e) => error!("Impossible to serialize the search log entry: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_queries_and_filters() {
+        let log = SearchLog {
+            sample_rate: 1.0,
+            redactions: vec![r"[\w.+-]+@[\w-]+\.[\w.]+".parse().unwrap(), r"\d{6,}".parse().unwrap()],
+        };
+
+        let entry = log.entry(
+            vec!["users"],
+            "john.doe@example.com 0612345678",
+            vec!["age > 18 AND phone = 0612345678"],
+            None,
+            1,
+            Duration::from_millis(3),
+        );
+        assert_eq!(entry.query, "[REDACTED] [REDACTED]");
+        assert_eq!(entry.filters, vec!["age > 18 AND phone = [REDACTED]".to_string()]);
+
+        let line = serde_json::to_value(&entry).unwrap();
+        assert_eq!(line["nbHits"], 1);
+        assert_eq!(line["processingTimeMs"], 3);
+        assert!(line.get("facetFilters").is_none());
+        assert!(log.sampled());
+    }
+}
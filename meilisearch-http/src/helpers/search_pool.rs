@@ -0,0 +1,175 @@
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crossbeam_channel::{bounded, Sender, TrySendError};
+use futures::channel::oneshot;
+use log::error;
+use serde::Serialize;
+
+use crate::error::ResponseError;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The number of seconds clients are asked to wait before retrying when the pool is full.
+const RETRY_AFTER_SECS: u64 = 1;
+
+#[derive(Default)]
+struct Counters {
+    active: AtomicUsize,
+    queued: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// Counts a job as active until it is dropped, even if the job panicked.
+struct ActiveJob<'a>(&'a AtomicUsize);
+
+impl<'a> ActiveJob<'a> {
+    fn start(active: &'a AtomicUsize) -> ActiveJob<'a> {
+        active.fetch_add(1, Ordering::Relaxed);
+        ActiveJob(active)
+    }
+}
+
+impl Drop for ActiveJob<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPoolStats {
+    pub workers: usize,
+    pub queue_size: usize,
+    pub active: usize,
+    pub queued: usize,
+    pub rejected: u64,
+}
+
+/// A fixed number of threads executing the searches.
+///
+/// Searches wait for a free thread in a bounded queue, once this queue is full
+/// new searches are rejected instead of contending with the running ones.
+#[derive(Clone)]
+pub struct SearchPool {
+    sender: Sender<Job>,
+    workers: usize,
+    queue_size: usize,
+    counters: Arc<Counters>,
+}
+
+impl SearchPool {
+    pub fn new(workers: usize, queue_size: usize) -> SearchPool {
+        let workers = workers.max(1);
+        let (sender, receiver) = bounded::<Job>(queue_size);
+        let counters = Arc::new(Counters::default());
+
+        for i in 0..workers {
+            let receiver = receiver.clone();
+            let counters = counters.clone();
+            thread::Builder::new()
+                .name(format!("search-{}", i))
+                .spawn(move || {
+                    for job in receiver {
+                        counters.queued.fetch_sub(1, Ordering::Relaxed);
+                        let _active = ActiveJob::start(&counters.active);
+                        // a panicking search must not take its thread down with it, the sender of
+                        // its result is dropped and the search is answered with an internal error
+                        if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                            error!("a search panicked");
+                        }
+                    }
+                })
+                .expect("impossible to spawn a search thread");
+        }
+
+        SearchPool { sender, workers, queue_size, counters }
+    }
+
+    /// Enqueues `f` to be executed on one of the pool threads, the returned future
+    /// resolves to an overloaded error if the queue was full.
+    pub fn run<F, T>(&self, f: F) -> impl Future<Output = Result<T, ResponseError>>
+    where
+        F: FnOnce() -> Result<T, ResponseError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = tx.send(f());
+        });
+
+        self.counters.queued.fetch_add(1, Ordering::Relaxed);
+        let enqueued = match self.sender.try_send(job) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                Err(ResponseError::Overloaded(RETRY_AFTER_SECS))
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+                Err(ResponseError::internal("the search threads are stopped"))
+            }
+        };
+
+        async move {
+            enqueued?;
+            rx.await
+                .map_err(|_| ResponseError::internal("the search has been interrupted"))?
+        }
+    }
+
+    pub fn stats(&self) -> SearchPoolStats {
+        SearchPoolStats {
+            workers: self.workers,
+            queue_size: self.queue_size,
+            active: self.counters.active.load(Ordering::Relaxed),
+            queued: self.counters.queued.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[actix_rt::test]
+    async fn rejects_when_saturated() {
+        let pool = SearchPool::new(1, 1);
+        let (unblock, blocked) = mpsc::channel::<()>();
+        let (started, wait_started) = mpsc::channel::<()>();
+
+        // occupy the only worker then fill the queue
+        let running = pool.run(move || {
+            started.send(()).unwrap();
+            blocked.recv().unwrap();
+            Ok(1)
+        });
+        wait_started.recv().unwrap();
+        let queued = pool.run(|| Ok(2));
+
+        let rejected = pool.run(|| Ok(3)).await;
+        assert!(matches!(rejected, Err(ResponseError::Overloaded(_))));
+        assert_eq!(pool.stats().rejected, 1);
+
+        unblock.send(()).unwrap();
+        assert_eq!(running.await.unwrap(), 1);
+        assert_eq!(queued.await.unwrap(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn survives_panicking_searches() {
+        let pool = SearchPool::new(1, 1);
+
+        let panicked = pool.run(|| -> Result<(), ResponseError> { panic!("search bug") }).await;
+        assert!(panicked.is_err());
+
+        // the only thread of the pool is still there to run the next search
+        assert_eq!(pool.run(|| Ok(2)).await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,277 @@
+//! The export of the finished spans to an OpenTelemetry collector, with the OTLP/HTTP
+//! protocol and its JSON encoding, e.g. to `http://localhost:4318/v1/traces`.
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossbeam_channel::{at, bounded, select, Receiver, Sender};
+use log::warn;
+use serde::Serialize;
+
+/// The number of spans waiting to be exported over which the new spans are dropped,
+/// a collector that is down must not make the server keep all of its spans.
+const QUEUE_SIZE: usize = 4096;
+/// The maximum number of spans sent to the collector in a request.
+const BATCH_SIZE: usize = 512;
+/// The time after which a batch is sent even if it isn't full.
+const BATCH_DELAY: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT_MS: u64 = 10_000;
+const SERVICE_NAME: &str = "meilisearch";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    Internal = 1,
+    Server = 2,
+}
+
+#[derive(Debug, Clone)]
+pub struct FinishedSpan {
+    pub name: String,
+    pub kind: SpanKind,
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_id: Option<String>,
+    pub start_unix_nano: u64,
+    pub end_unix_nano: u64,
+}
+
+/// Converts the start of a span to the time of the system clock, the instants are only monotonic,
+/// returns the start and the end of the span in nanoseconds since the unix epoch.
+pub fn unix_nanos(start: Instant, duration: Duration) -> (u64, u64) {
+    let now = SystemTime::now();
+    let start = now.checked_sub(start.elapsed()).unwrap_or(now);
+    let start = start.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let end = start + duration;
+    (start.as_nanos() as u64, end.as_nanos() as u64)
+}
+
+/// Sends the finished spans to the collector by batches, from a background thread.
+///
+/// The spans are dropped if the queue is full, the server never waits for the collector.
+#[derive(Clone)]
+pub struct SpanExporter {
+    spans: Sender<FinishedSpan>,
+    stop: Sender<()>,
+    handle: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl SpanExporter {
+    pub fn start(endpoint: String) -> std::io::Result<SpanExporter> {
+        let (spans, receiver) = bounded(QUEUE_SIZE);
+        let (stop, stop_receiver) = bounded(1);
+        let handle = thread::Builder::new()
+            .name("span-exporter".to_string())
+            .spawn(move || export_batches(&endpoint, receiver, stop_receiver))?;
+
+        Ok(SpanExporter { spans, stop, handle: Arc::new(Mutex::new(Some(handle))) })
+    }
+
+    pub fn export(&self, span: FinishedSpan) {
+        let _ = self.spans.try_send(span);
+    }
+
+    /// Sends the spans that are waiting and stops the background thread.
+    pub fn stop(&self) {
+        if let Some(handle) = self.handle.lock().unwrap().take() {
+            let _ = self.stop.send(());
+            let _ = handle.join();
+        }
+    }
+}
+
+impl std::fmt::Debug for SpanExporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SpanExporter").field("waiting", &self.spans.len()).finish()
+    }
+}
+
+fn export_batches(endpoint: &str, spans: Receiver<FinishedSpan>, stop: Receiver<()>) {
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut send_at = Instant::now() + BATCH_DELAY;
+
+    loop {
+        select! {
+            recv(spans) -> span => match span {
+                Ok(span) => batch.push(span),
+                Err(_) => break,
+            },
+            recv(at(send_at)) -> _ => (),
+            recv(stop) -> _ => break,
+        }
+
+        if batch.len() >= BATCH_SIZE || Instant::now() >= send_at {
+            send_batch(endpoint, &mut batch);
+            send_at = Instant::now() + BATCH_DELAY;
+        }
+    }
+
+    batch.extend(spans.try_iter());
+    while !batch.is_empty() {
+        let mut rest = batch.split_off(batch.len().min(BATCH_SIZE));
+        send_batch(endpoint, &mut batch);
+        batch.append(&mut rest);
+    }
+}
+
+fn send_batch(endpoint: &str, batch: &mut Vec<FinishedSpan>) {
+    if batch.is_empty() {
+        return;
+    }
+
+    let body = match serde_json::to_string(&export_request(batch)) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("impossible to encode the spans: {}", e);
+            batch.clear();
+            return;
+        }
+    };
+    batch.clear();
+
+    let response = ureq::post(endpoint)
+        .timeout_connect(REQUEST_TIMEOUT_MS)
+        .timeout_read(REQUEST_TIMEOUT_MS)
+        .set("Content-Type", "application/json")
+        .send_string(&body);
+    if !response.ok() {
+        warn!("the collector {} refused the spans with the status {}", endpoint, response.status());
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportTraceServiceRequest<'a> {
+    resource_spans: Vec<ResourceSpans<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResourceSpans<'a> {
+    resource: Resource,
+    scope_spans: Vec<ScopeSpans<'a>>,
+}
+
+#[derive(Serialize)]
+struct Resource {
+    attributes: Vec<KeyValue>,
+}
+
+#[derive(Serialize)]
+struct KeyValue {
+    key: &'static str,
+    value: AnyValue,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnyValue {
+    string_value: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScopeSpans<'a> {
+    scope: Scope,
+    spans: Vec<Span<'a>>,
+}
+
+#[derive(Serialize)]
+struct Scope {
+    name: &'static str,
+    version: &'static str,
+}
+
+/// The ids are encoded in hexadecimal and the 64 bits integers as strings, as required by
+/// the JSON encoding of OTLP.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Span<'a> {
+    trace_id: &'a str,
+    span_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_span_id: Option<&'a str>,
+    name: &'a str,
+    kind: u8,
+    start_time_unix_nano: String,
+    end_time_unix_nano: String,
+}
+
+fn export_request(spans: &[FinishedSpan]) -> ExportTraceServiceRequest {
+    let spans = spans
+        .iter()
+        .map(|span| Span {
+            trace_id: &span.trace_id,
+            span_id: &span.span_id,
+            parent_span_id: span.parent_id.as_deref(),
+            name: &span.name,
+            kind: span.kind as u8,
+            start_time_unix_nano: span.start_unix_nano.to_string(),
+            end_time_unix_nano: span.end_unix_nano.to_string(),
+        })
+        .collect();
+
+    let service_name = KeyValue { key: "service.name", value: AnyValue { string_value: SERVICE_NAME } };
+    ExportTraceServiceRequest {
+        resource_spans: vec![ResourceSpans {
+            resource: Resource { attributes: vec![service_name] },
+            scope_spans: vec![ScopeSpans {
+                scope: Scope { name: SERVICE_NAME, version: env!("CARGO_PKG_VERSION") },
+                spans,
+            }],
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_are_encoded_as_otlp_json() {
+        let spans = vec![
+            FinishedSpan {
+                name: "http request".to_string(),
+                kind: SpanKind::Server,
+                trace_id: "0af7651916cd43dd8448eb211c80319c".to_string(),
+                span_id: "b7ad6b7169203331".to_string(),
+                parent_id: None,
+                start_unix_nano: 1_600_000_000_000_000_000,
+                end_unix_nano: 1_600_000_000_020_000_000,
+            },
+            FinishedSpan {
+                name: "ranking typo".to_string(),
+                kind: SpanKind::Internal,
+                trace_id: "0af7651916cd43dd8448eb211c80319c".to_string(),
+                span_id: "00f067aa0ba902b7".to_string(),
+                parent_id: Some("b7ad6b7169203331".to_string()),
+                start_unix_nano: 1_600_000_000_005_000_000,
+                end_unix_nano: 1_600_000_000_006_000_000,
+            },
+        ];
+
+        let value = serde_json::to_value(export_request(&spans)).unwrap();
+        let resource_spans = &value["resourceSpans"][0];
+        assert_eq!(resource_spans["resource"]["attributes"][0]["key"], "service.name");
+        assert_eq!(resource_spans["resource"]["attributes"][0]["value"]["stringValue"], "meilisearch");
+
+        let encoded = &resource_spans["scopeSpans"][0]["spans"];
+        assert_eq!(encoded[0]["traceId"], "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(encoded[0]["kind"], 2);
+        assert_eq!(encoded[0]["startTimeUnixNano"], "1600000000000000000");
+        assert_eq!(encoded[0]["endTimeUnixNano"], "1600000000020000000");
+        assert!(encoded[0].get("parentSpanId").is_none());
+        assert_eq!(encoded[1]["name"], "ranking typo");
+        assert_eq!(encoded[1]["kind"], 1);
+        assert_eq!(encoded[1]["parentSpanId"], "b7ad6b7169203331");
+    }
+
+    #[test]
+    fn instants_are_converted_to_the_system_time() {
+        let start = Instant::now();
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        let (start_nano, end_nano) = unix_nanos(start, Duration::from_millis(3));
+        assert_eq!(end_nano - start_nano, 3_000_000);
+        assert!(start_nano <= before + 1_000_000);
+    }
+}
@@ -0,0 +1,278 @@
+//! W3C trace context propagation.
+//!
+//! The trace of a request is continued from its `traceparent` and `tracestate` headers, or
+//! started if they are missing or invalid, and the `traceparent` of the server span is sent
+//! back. The spans are logged under the `trace` target once finished, with the ids of their
+//! trace and parent, and sent to the OpenTelemetry collector if an OTLP endpoint is given.
+
+use std::cell::RefCell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_service::{Service, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{dev::ServiceRequest, dev::ServiceResponse, Error, HttpMessage};
+use futures::future::{ok, Future, Ready};
+use log::debug;
+
+use crate::helpers::span_exporter::{unix_nanos, FinishedSpan, SpanExporter, SpanKind};
+use crate::Data;
+
+const TRACEPARENT: &str = "traceparent";
+const TRACESTATE: &str = "tracestate";
+const SAMPLED_FLAG: u8 = 0x01;
+
+#[derive(Debug, Clone)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_id: Option<String>,
+    pub flags: u8,
+    pub state: Option<String>,
+    /// Where the finished spans of the trace are sent, if anywhere.
+    pub exporter: Option<SpanExporter>,
+}
+
+fn random_id(bytes: usize) -> String {
+    loop {
+        let id: String = (0..bytes).map(|_| format!("{:02x}", rand::random::<u8>())).collect();
+        // the ids made only of zeros are invalid
+        if id.bytes().any(|b| b != b'0') {
+            return id;
+        }
+    }
+}
+
+fn is_id(id: &str, len: usize) -> bool {
+    id.len() == len
+        && id.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+        && id.bytes().any(|b| b != b'0')
+}
+
+impl TraceContext {
+    /// Starts a new trace, the server span is its root.
+    pub fn new_root() -> TraceContext {
+        TraceContext {
+            trace_id: random_id(16),
+            span_id: random_id(8),
+            parent_id: None,
+            flags: SAMPLED_FLAG,
+            state: None,
+            exporter: None,
+        }
+    }
+
+    /// Continues the trace of the given headers, the returned context is a new span of the trace.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<TraceContext> {
+        let parts: Vec<_> = traceparent.trim().split('-').collect();
+        let (version, trace_id, parent_id, flags) = match parts.as_slice() {
+            [version, trace_id, parent_id, flags] => (*version, *trace_id, *parent_id, *flags),
+            // the future versions can add fields after the flags
+            [version, trace_id, parent_id, flags, ..] if *version != "00" => (*version, *trace_id, *parent_id, *flags),
+            _ => return None,
+        };
+
+        if version.len() != 2 || version == "ff" || u8::from_str_radix(version, 16).is_err() {
+            return None;
+        }
+        if !is_id(trace_id, 32) || !is_id(parent_id, 16) || flags.len() != 2 {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+
+        Some(TraceContext {
+            trace_id: trace_id.to_string(),
+            span_id: random_id(8),
+            parent_id: Some(parent_id.to_string()),
+            flags,
+            state: tracestate.map(str::trim).filter(|s| !s.is_empty()).map(String::from),
+            exporter: None,
+        })
+    }
+
+    /// Returns a context for a new span, child of this one.
+    pub fn child(&self) -> TraceContext {
+        TraceContext {
+            trace_id: self.trace_id.clone(),
+            span_id: random_id(8),
+            parent_id: Some(self.span_id.clone()),
+            flags: self.flags,
+            state: self.state.clone(),
+            exporter: self.exporter.clone(),
+        }
+    }
+
+    pub fn sampled(&self) -> bool {
+        self.flags & SAMPLED_FLAG != 0
+    }
+
+    /// The `traceparent` header value identifying this span.
+    pub fn traceparent(&self) -> String {
+        format!("00-{}-{}-{:02x}", self.trace_id, self.span_id, self.flags)
+    }
+
+    /// Starts a child span, finished when the returned guard is dropped.
+    pub fn span(&self, name: &'static str) -> Span {
+        Span { name, context: self.child(), start: Instant::now() }
+    }
+
+    /// Records a child span that has been timed elsewhere.
+    pub fn record(&self, name: &str, start: Instant, duration: Duration) {
+        log_span(name, SpanKind::Internal, &self.child(), start, duration);
+    }
+}
+
+fn log_span(name: &str, kind: SpanKind, context: &TraceContext, start: Instant, duration: Duration) {
+    if !context.sampled() {
+        return;
+    }
+
+    debug!(
+        target: "trace",
+        "span={:?} trace_id={} span_id={} parent_id={} duration={:.02?}",
+        name,
+        context.trace_id,
+        context.span_id,
+        context.parent_id.as_deref().unwrap_or(""),
+        duration,
+    );
+
+    if let Some(exporter) = &context.exporter {
+        let (start_unix_nano, end_unix_nano) = unix_nanos(start, duration);
+        exporter.export(FinishedSpan {
+            name: name.to_string(),
+            kind,
+            trace_id: context.trace_id.clone(),
+            span_id: context.span_id.clone(),
+            parent_id: context.parent_id.clone(),
+            start_unix_nano,
+            end_unix_nano,
+        });
+    }
+}
+
+/// A span of a trace, logged once dropped.
+pub struct Span {
+    name: &'static str,
+    context: TraceContext,
+    start: Instant,
+}
+
+impl Span {
+    pub fn context(&self) -> &TraceContext {
+        &self.context
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        log_span(self.name, SpanKind::Internal, &self.context, self.start, self.start.elapsed());
+    }
+}
+
+/// Continues or starts the trace of every request, the handlers can find the
+/// context of the server span in the extensions of the request.
+pub struct TracePropagation;
+
+impl<S: 'static, B> Transform<S> for TracePropagation
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TracePropagationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(TracePropagationMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct TracePropagationMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for TracePropagationMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let mut svc = self.service.clone();
+        let data = req.app_data::<Data>().unwrap();
+
+        let headers = req.headers();
+        let traceparent = headers.get(TRACEPARENT).and_then(|value| value.to_str().ok());
+        let tracestate = headers.get(TRACESTATE).and_then(|value| value.to_str().ok());
+        let mut context = traceparent
+            .and_then(|traceparent| TraceContext::parse(traceparent, tracestate))
+            .unwrap_or_else(TraceContext::new_root);
+        context.exporter = data.span_exporter.clone();
+        req.extensions_mut().insert(context.clone());
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let result = svc.call(req).await;
+            log_span("http request", SpanKind::Server, &context, start, start.elapsed());
+
+            let mut res = result?;
+            let headers = res.headers_mut();
+            if let Ok(value) = HeaderValue::from_str(&context.traceparent()) {
+                headers.insert(HeaderName::from_static(TRACEPARENT), value);
+            }
+            if let Some(Ok(value)) = context.state.as_deref().map(HeaderValue::from_str) {
+                headers.insert(HeaderName::from_static(TRACESTATE), value);
+            }
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_traceparent() {
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let context = TraceContext::parse(traceparent, Some("congo=t61rcWkgMzE")).unwrap();
+        assert_eq!(context.trace_id, "0af7651916cd43dd8448eb211c80319c");
+        assert_eq!(context.parent_id.as_deref(), Some("b7ad6b7169203331"));
+        assert_ne!(context.span_id, "b7ad6b7169203331");
+        assert!(context.sampled());
+        assert_eq!(context.state.as_deref(), Some("congo=t61rcWkgMzE"));
+        assert_eq!(context.traceparent(), format!("00-0af7651916cd43dd8448eb211c80319c-{}-01", context.span_id));
+
+        let child = context.child();
+        assert_eq!(child.trace_id, context.trace_id);
+        assert_eq!(child.parent_id, Some(context.span_id));
+
+        // a future version with more fields
+        assert!(TraceContext::parse("cc-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-00-ab", None).is_some());
+
+        assert!(TraceContext::parse("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-ab", None).is_none());
+        assert!(TraceContext::parse("ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01", None).is_none());
+        assert!(TraceContext::parse("00-00000000000000000000000000000000-b7ad6b7169203331-01", None).is_none());
+        assert!(TraceContext::parse("00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01", None).is_none());
+        assert!(TraceContext::parse("00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01", None).is_none());
+        assert!(TraceContext::parse("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331", None).is_none());
+    }
+}
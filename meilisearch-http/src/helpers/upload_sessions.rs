@@ -0,0 +1,412 @@
+//! Resumable uploads of documents.
+//!
+//! The chunks of an upload session are written in the `upload-sessions` directory of the
+//! database, a chunk that failed to be uploaded can be sent again until the session is
+//! committed. The chunks are concatenated in the order of their numbers at commit time.
+//! They hold the documents, they are encrypted like them when the database is.
+//!
+//! A session is deleted once successfully committed, the sessions that failed or were never
+//! committed expire when no chunk has been received for a retention period, after which
+//! they are purged. The committed sessions that could not be deleted are purged by the cleanup.
+//!
+//! A session created with an idempotency key has an id derived from the index and the key,
+//! the creation retried with the same key returns the session instead of creating another one.
+
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+use std::vec;
+
+use chrono::{DateTime, Utc};
+use crossbeam_channel::{bounded, RecvTimeoutError, Sender};
+use log::{info, warn};
+use meilisearch_core::Database;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::error::ResponseError;
+use crate::helpers::remote_documents::DocumentsFormat;
+
+const SESSIONS_DIRECTORY: &str = "upload-sessions";
+const SESSION_FILE: &str = "session.json";
+/// The digest of the request that created the session with an idempotency key.
+const REQUEST_DIGEST_FILE: &str = "request.digest";
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSession {
+    pub index_uid: String,
+    pub format: DocumentsFormat,
+    pub primary_key: Option<String>,
+    pub partial: bool,
+    pub created_at: DateTime<Utc>,
+    /// The date at which the documents of the session have started to be enqueued,
+    /// the chunks can't be changed anymore.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committed_at: Option<DateTime<Utc>>,
+}
+
+/// The number of upload sessions and the space, in bytes, used by their files.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionsUsage {
+    pub sessions: usize,
+    pub size: u64,
+}
+
+fn session_path(db_path: &str, session_id: &str) -> Option<PathBuf> {
+    // the session ids are used as directory names
+    if session_id.is_empty() || !session_id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    Some(Path::new(db_path).join(SESSIONS_DIRECTORY).join(session_id))
+}
+
+fn chunk_path(session_path: &Path, number: u32) -> PathBuf {
+    session_path.join(format!("{:010}.chunk", number))
+}
+
+/// The chunks are encrypted bound to their session and number, they can't be moved around.
+fn chunk_associated_data(session_id: &str, number: u32) -> String {
+    format!("{}/{}", session_id, number)
+}
+
+fn idempotent_session_id(index_uid: &str, key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.input(index_uid.as_bytes());
+    hasher.input(b"\0");
+    hasher.input(key.as_bytes());
+    hasher.result()[..16].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Creates a new upload session and returns its id, with `false` when the session
+/// had already been created by the same request sent with the same idempotency key.
+pub fn create_session(
+    db_path: &str,
+    session: &UploadSession,
+    idempotency_key: Option<(&str, &[u8; 32])>,
+) -> Result<(String, bool), ResponseError> {
+    let session_id = match idempotency_key {
+        Some((key, _)) => idempotent_session_id(&session.index_uid, key),
+        None => rand::thread_rng().sample_iter(&Alphanumeric).take(16).collect(),
+    };
+    let path = session_path(db_path, &session_id).unwrap();
+
+    fs::create_dir_all(path.parent().unwrap()).map_err(ResponseError::internal)?;
+    match (fs::create_dir(&path), idempotency_key) {
+        (Ok(()), _) => (),
+        (Err(e), Some((key, request_digest))) if e.kind() == io::ErrorKind::AlreadyExists => {
+            let digest = fs::read(path.join(REQUEST_DIGEST_FILE)).unwrap_or_default();
+            if digest[..] == request_digest[..] {
+                return Ok((session_id, false));
+            }
+            return Err(ResponseError::conflict(format!(
+                "The idempotency key {:?} has already been used for another request",
+                key,
+            )));
+        }
+        (Err(e), _) => return Err(ResponseError::internal(e)),
+    }
+
+    let bytes = serde_json::to_vec(session).map_err(ResponseError::internal)?;
+    fs::write(path.join(SESSION_FILE), bytes).map_err(ResponseError::internal)?;
+    // the digest is written last, a retry never gets a session that isn't entirely created
+    if let Some((_, request_digest)) = idempotency_key {
+        fs::write(path.join(REQUEST_DIGEST_FILE), request_digest).map_err(ResponseError::internal)?;
+    }
+
+    Ok((session_id, true))
+}
+
+/// Returns the session if it exists, targets the given index and has not expired,
+/// the uncommitted sessions expire once idle for longer than the retention period.
+pub fn open_session(
+    db_path: &str,
+    index_uid: &str,
+    session_id: &str,
+    retention: chrono::Duration,
+) -> Result<UploadSession, ResponseError> {
+    let not_found = || ResponseError::not_found(format!("upload session {}", session_id));
+    let path = session_path(db_path, session_id).ok_or_else(not_found)?;
+
+    let bytes = match fs::read(path.join(SESSION_FILE)) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(not_found()),
+        Err(e) => return Err(ResponseError::internal(e)),
+    };
+    let session: UploadSession = serde_json::from_slice(&bytes).map_err(ResponseError::internal)?;
+
+    if session.index_uid != index_uid {
+        return Err(not_found());
+    }
+
+    match expires_at(db_path, session_id, &session, retention) {
+        Some(expires_at) if expires_at <= Utc::now() => Err(not_found()),
+        _ => Ok(session),
+    }
+}
+
+/// Returns the session if it can still be changed, see `open_session`.
+pub fn open_uncommitted_session(
+    db_path: &str,
+    index_uid: &str,
+    session_id: &str,
+    retention: chrono::Duration,
+) -> Result<UploadSession, ResponseError> {
+    let session = open_session(db_path, index_uid, session_id, retention)?;
+    match session.committed_at {
+        Some(_) => Err(ResponseError::conflict(format!("the upload session {} has been committed", session_id))),
+        None => Ok(session),
+    }
+}
+
+/// Writes the commit date of the session, `None` gives the session back to the uploads.
+pub fn set_committed(db_path: &str, session_id: &str, session: &mut UploadSession, committed_at: Option<DateTime<Utc>>) -> Result<(), ResponseError> {
+    let path = session_path(db_path, session_id).unwrap();
+    session.committed_at = committed_at;
+
+    let tmp_path = path.join(format!("{}.tmp", SESSION_FILE));
+    let bytes = serde_json::to_vec(session).map_err(ResponseError::internal)?;
+    fs::write(&tmp_path, bytes).map_err(ResponseError::internal)?;
+    fs::rename(&tmp_path, path.join(SESSION_FILE)).map_err(ResponseError::internal)
+}
+
+/// Writes a chunk of the session, replacing the chunk with the same number if any.
+pub fn write_chunk(
+    db: &Database,
+    db_path: &str,
+    session_id: &str,
+    number: u32,
+    bytes: &[u8],
+) -> Result<(), ResponseError> {
+    let path = session_path(db_path, session_id).unwrap();
+    let associated_data = chunk_associated_data(session_id, number);
+    let bytes = db.seal(associated_data.as_bytes(), bytes.to_vec()).map_err(ResponseError::internal)?;
+
+    // the chunk is renamed once entirely written, an interrupted write leaves no partial chunk
+    let tmp_path = path.join(format!("{:010}.tmp", number));
+    fs::write(&tmp_path, bytes).map_err(ResponseError::internal)?;
+    fs::rename(&tmp_path, chunk_path(&path, number)).map_err(ResponseError::internal)?;
+
+    Ok(())
+}
+
+/// Returns the sorted numbers of the chunks that have been received.
+pub fn chunks(db_path: &str, session_id: &str) -> Result<Vec<u32>, ResponseError> {
+    let path = session_path(db_path, session_id).unwrap();
+
+    let mut numbers = Vec::new();
+    for entry in fs::read_dir(&path).map_err(ResponseError::internal)? {
+        let entry = entry.map_err(ResponseError::internal)?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.ends_with(".chunk") {
+            if let Ok(number) = name.trim_end_matches(".chunk").parse() {
+                numbers.push(number);
+            }
+        }
+    }
+
+    numbers.sort_unstable();
+    Ok(numbers)
+}
+
+/// Returns a reader of the concatenated chunks, the chunks must be numbered from zero without gaps.
+pub fn chunks_reader<'a>(db: &'a Database, db_path: &str, session_id: &str) -> Result<impl Read + 'a, ResponseError> {
+    let path = session_path(db_path, session_id).unwrap();
+    let numbers = chunks(db_path, session_id)?;
+
+    if let Some(missing) = (0..).zip(&numbers).find(|(expected, number)| expected != *number) {
+        return Err(ResponseError::bad_request(format!("the chunk {} is missing", missing.0)));
+    }
+
+    Ok(ChunksReader {
+        db,
+        path,
+        session_id: session_id.to_string(),
+        numbers: numbers.into_iter(),
+        chunk: Cursor::new(Vec::new()),
+    })
+}
+
+/// Reads the chunks one after the other, a chunk is only read, and decrypted, once the
+/// previous one has been entirely read.
+struct ChunksReader<'a> {
+    db: &'a Database,
+    path: PathBuf,
+    session_id: String,
+    numbers: vec::IntoIter<u32>,
+    chunk: Cursor<Vec<u8>>,
+}
+
+impl Read for ChunksReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.chunk.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+
+            let number = match self.numbers.next() {
+                Some(number) => number,
+                None => return Ok(0),
+            };
+
+            let bytes = fs::read(chunk_path(&self.path, number))?;
+            let associated_data = chunk_associated_data(&self.session_id, number);
+            let bytes = self
+                .db
+                .open_sealed(associated_data.as_bytes(), bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            self.chunk = Cursor::new(bytes);
+        }
+    }
+}
+
+/// Deletes the session and all of its chunks.
+pub fn delete_session(db_path: &str, session_id: &str) -> Result<(), ResponseError> {
+    let path = session_path(db_path, session_id).unwrap();
+    fs::remove_dir_all(path).map_err(ResponseError::internal)
+}
+
+fn directory_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .fold(0, |acc, m| acc + m.len())
+}
+
+/// The last time a file of the session has been written, a chunk or the session itself.
+fn last_activity(path: &Path) -> Option<DateTime<Utc>> {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter_map(|metadata| metadata.modified().ok())
+        .max()
+        .map(|modified: SystemTime| modified.into())
+}
+
+/// The date at which the session expires if no chunk is received until then,
+/// `None` for the committed sessions.
+pub fn expires_at(
+    db_path: &str,
+    session_id: &str,
+    session: &UploadSession,
+    retention: chrono::Duration,
+) -> Option<DateTime<Utc>> {
+    if session.committed_at.is_some() {
+        return None;
+    }
+    let path = session_path(db_path, session_id)?;
+    let last_activity = last_activity(&path).unwrap_or(session.created_at);
+    Some(last_activity + retention)
+}
+
+/// The state of a session directory as seen by the purge.
+struct SessionEntry {
+    path: PathBuf,
+    /// `None` when the session cannot be read.
+    session: Option<UploadSession>,
+    last_activity: Option<DateTime<Utc>>,
+}
+
+/// Returns the directories of all the sessions with their last activity.
+fn list_sessions(db_path: &str) -> Result<Vec<SessionEntry>, ResponseError> {
+    let entries = match fs::read_dir(Path::new(db_path).join(SESSIONS_DIRECTORY)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(ResponseError::internal(e)),
+    };
+
+    let mut sessions = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(ResponseError::internal)?.path();
+        if path.is_dir() {
+            let session = fs::read(path.join(SESSION_FILE))
+                .ok()
+                .and_then(|bytes| serde_json::from_slice::<UploadSession>(&bytes).ok());
+            let last_activity = last_activity(&path);
+            sessions.push(SessionEntry { path, session, last_activity });
+        }
+    }
+
+    Ok(sessions)
+}
+
+/// Returns the space used by all the upload sessions.
+pub fn usage(db_path: &str) -> Result<SessionsUsage, ResponseError> {
+    let sessions = list_sessions(db_path)?;
+    let size = sessions.iter().map(|entry| directory_size(&entry.path)).sum();
+    Ok(SessionsUsage { sessions: sessions.len(), size })
+}
+
+/// Deletes the sessions without activity since the given date, or all of them, the sessions
+/// that cannot be read are always deleted. The committed sessions that are left, their
+/// documents have been enqueued, are deleted once the commit is older than the cleanup interval.
+/// Returns the space that has been freed.
+pub fn purge_sessions(db_path: &str, idle_since: Option<DateTime<Utc>>) -> Result<SessionsUsage, ResponseError> {
+    let mut purged = SessionsUsage::default();
+    let committed_before = Utc::now() - chrono::Duration::from_std(CLEANUP_INTERVAL).unwrap();
+
+    for entry in list_sessions(db_path)? {
+        let expired = match (&entry.session, entry.last_activity, idle_since) {
+            (_, _, None) => true,
+            (Some(UploadSession { committed_at: Some(committed_at), .. }), _, _) => *committed_at < committed_before,
+            (Some(_), Some(last_activity), Some(idle_since)) => last_activity < idle_since,
+            _ => true,
+        };
+
+        if expired {
+            let size = directory_size(&entry.path);
+            fs::remove_dir_all(&entry.path).map_err(ResponseError::internal)?;
+            purged.sessions += 1;
+            purged.size += size;
+        }
+    }
+
+    Ok(purged)
+}
+
+/// Periodically purges the sessions idle for longer than the retention period, from a background thread.
+pub struct SessionsCleanup {
+    stop: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+impl SessionsCleanup {
+    pub fn start(db_path: String, retention: chrono::Duration) -> io::Result<SessionsCleanup> {
+        let (stop, stop_receiver) = bounded(1);
+        let handle = thread::Builder::new().name("upload-sessions-cleanup".to_string()).spawn(move || loop {
+            let idle_since = Utc::now() - retention;
+            match purge_sessions(&db_path, Some(idle_since)) {
+                Ok(purged) if purged.sessions > 0 => {
+                    info!("{} expired upload sessions purged, {} bytes freed", purged.sessions, purged.size)
+                }
+                Ok(_) => (),
+                Err(e) => warn!("Impossible to purge the expired upload sessions: {}", e),
+            }
+
+            match stop_receiver.recv_timeout(CLEANUP_INTERVAL) {
+                Err(RecvTimeoutError::Timeout) => (),
+                _ => break,
+            }
+        })?;
+
+        Ok(SessionsCleanup { stop, handle })
+    }
+
+    /// Stops the cleanup and waits for the purge in progress, if any.
+    pub fn stop(self) {
+        let _ = self.stop.send(());
+        let _ = self.handle.join();
+    }
+}
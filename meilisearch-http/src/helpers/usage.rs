@@ -0,0 +1,184 @@
+//! Searches and indexing operations counted per API key and per index.
+//!
+//! The counters are kept in memory and written in the main environment when an update is
+//! processed and when the server stops, the hosting providers read them on the usage route
+//! to bill or cap their tenants.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use actix_service::{Service, Transform};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{dev::ServiceRequest, dev::ServiceResponse, Error};
+use futures::future::{ok, Future, Ready};
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::audit_log::{index_uid, is_write, key_name};
+use crate::Data;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageCounters {
+    pub searches: u64,
+    /// The updates enqueued, whatever their number of documents.
+    pub indexing_operations: u64,
+}
+
+#[derive(Default)]
+pub struct Usage {
+    keys: Mutex<BTreeMap<String, UsageCounters>>,
+    indexes: Mutex<BTreeMap<String, UsageCounters>>,
+}
+
+/// What a request answered successfully is counted as.
+#[derive(Debug, PartialEq, Eq)]
+enum Operation {
+    Search,
+    Indexing,
+}
+
+fn operation(method: &Method, path: &str, status: StatusCode) -> Option<Operation> {
+    let path = path.trim_end_matches('/');
+    if status == StatusCode::OK && path.ends_with("/search") && (method == Method::GET || method == Method::POST) {
+        return Some(Operation::Search);
+    }
+    // the routes enqueuing an update are the only ones answering `202 Accepted`
+    if status == StatusCode::ACCEPTED && is_write(method, path) {
+        return Some(Operation::Indexing);
+    }
+    None
+}
+
+impl Usage {
+    /// Continues the counters read back from the database.
+    pub fn new(keys: BTreeMap<String, UsageCounters>, indexes: BTreeMap<String, UsageCounters>) -> Usage {
+        Usage { keys: Mutex::new(keys), indexes: Mutex::new(indexes) }
+    }
+
+    fn record(&self, key: &str, index_uid: Option<String>, operation: Operation) {
+        let count = |counters: &mut UsageCounters| match operation {
+            Operation::Search => counters.searches += 1,
+            Operation::Indexing => counters.indexing_operations += 1,
+        };
+
+        count(self.keys.lock().unwrap().entry(key.to_string()).or_default());
+        if let Some(index_uid) = index_uid {
+            count(self.indexes.lock().unwrap().entry(index_uid).or_default());
+        }
+    }
+
+    /// The counters of the API keys, named like in the audit log.
+    pub fn keys(&self) -> BTreeMap<String, UsageCounters> {
+        self.keys.lock().unwrap().clone()
+    }
+
+    pub fn index(&self, index_uid: &str) -> UsageCounters {
+        self.indexes.lock().unwrap().get(index_uid).copied().unwrap_or_default()
+    }
+
+    pub fn indexes(&self) -> BTreeMap<String, UsageCounters> {
+        self.indexes.lock().unwrap().clone()
+    }
+
+    /// Moves the counters of a renamed index to its new uid.
+    pub fn rename_index(&self, from: &str, to: &str) {
+        let mut indexes = self.indexes.lock().unwrap();
+        if let Some(counters) = indexes.remove(from) {
+            indexes.insert(to.to_string(), counters);
+        }
+    }
+}
+
+/// Counts the searches and the indexing operations answered successfully.
+pub struct UsageAccounting;
+
+impl<S: 'static, B> Transform<S> for UsageAccounting
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = UsageAccountingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(UsageAccountingMiddleware {
+            service: Rc::new(RefCell::new(service)),
+        })
+    }
+}
+
+pub struct UsageAccountingMiddleware<S> {
+    service: Rc<RefCell<S>>,
+}
+
+impl<S, B> Service for UsageAccountingMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let mut svc = self.service.clone();
+        let data = req.app_data::<Data>().unwrap();
+        let key = req.headers().get("X-Meili-API-Key").and_then(|key| key.to_str().ok());
+        let key = key_name(&data.api_keys, key);
+        let method = req.method().clone();
+        let path = req.path().to_string();
+
+        Box::pin(async move {
+            let res = svc.call(req).await?;
+            if let Some(operation) = operation(&method, &path, res.status()) {
+                data.usage.record(key, index_uid(&path), operation);
+            }
+            Ok(res)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_operations() {
+        let search = operation(&Method::POST, "/indexes/movies/search", StatusCode::OK);
+        assert_eq!(search, Some(Operation::Search));
+        let addition = operation(&Method::POST, "/indexes/movies/documents", StatusCode::ACCEPTED);
+        assert_eq!(addition, Some(Operation::Indexing));
+        assert_eq!(operation(&Method::GET, "/indexes/movies/search", StatusCode::BAD_REQUEST), None);
+        assert_eq!(operation(&Method::POST, "/indexes", StatusCode::CREATED), None);
+
+        let usage = Usage::default();
+        usage.record("private", Some("movies".to_string()), Operation::Search);
+        usage.record("private", None, Operation::Search);
+        usage.record("master", Some("movies".to_string()), Operation::Indexing);
+
+        assert_eq!(usage.keys()["private"], UsageCounters { searches: 2, indexing_operations: 0 });
+        assert_eq!(usage.index("movies"), UsageCounters { searches: 1, indexing_operations: 1 });
+        assert_eq!(usage.index("books"), UsageCounters::default());
+
+        // the counters read back from the database are continued
+        let usage = Usage::new(usage.keys(), usage.indexes());
+        usage.record("private", Some("movies".to_string()), Operation::Search);
+        assert_eq!(usage.keys()["private"], UsageCounters { searches: 3, indexing_operations: 0 });
+        assert_eq!(usage.index("movies"), UsageCounters { searches: 2, indexing_operations: 1 });
+    }
+}
@@ -0,0 +1,23 @@
+use meilisearch_core::WriteQueuesStats;
+
+/// Renders the waits for the write transactions in the Prometheus text exposition format.
+pub fn render(stats: &WriteQueuesStats) -> String {
+    let queues = [("main", &stats.main), ("update", &stats.update)];
+    let mut out = String::new();
+
+    out.push_str("# HELP meilisearch_write_txn_waiting The number of writers waiting for a write transaction.\n");
+    out.push_str("# TYPE meilisearch_write_txn_waiting gauge\n");
+    for (env, queue) in &queues {
+        out.push_str(&format!("meilisearch_write_txn_waiting{{env=\"{}\"}} {}\n", env, queue.waiting));
+    }
+
+    out.push_str("# HELP meilisearch_write_txn_wait_seconds The time spent waiting for the write transactions.\n");
+    out.push_str("# TYPE meilisearch_write_txn_wait_seconds summary\n");
+    for (env, queue) in &queues {
+        let total = queue.total_wait_us as f64 / 1_000_000.0;
+        out.push_str(&format!("meilisearch_write_txn_wait_seconds_sum{{env=\"{}\"}} {}\n", env, total));
+        out.push_str(&format!("meilisearch_write_txn_wait_seconds_count{{env=\"{}\"}} {}\n", env, queue.acquired));
+    }
+
+    out
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::time::Instant;
+
+use indexmap::IndexMap;
+use meilisearch_core::settings::Settings;
+use meilisearch_core::update::find_primary_key;
+use meilisearch_core::{Index, ProcessedUpdateResult};
+use meilisearch_schema::Schema;
+use serde_json::Value;
+
+use crate::data::Data;
+use crate::error::ResponseError;
+use crate::helpers::remote_documents::ndjson_documents;
+use crate::index_update_callback;
+use crate::option::{BuildIndexOpt, Opt};
+
+/// The number of documents enqueued per update, the file is read as the batches are enqueued.
+const DOCUMENTS_BATCH_SIZE: usize = 10_000;
+
+/// Builds an index in the database from a file of documents and an optional settings file,
+/// waiting for the updates to be processed, so that the database directory can be shipped
+/// as it is to the servers that serve it.
+///
+/// The settings are applied before the documents are indexed, the index must not already exist.
+/// An invalid document stops the build, the batches read before it are left in the index.
+pub fn run(opt: &Opt, build: &BuildIndexOpt) -> Result<(), Box<dyn Error>> {
+    let uid = &build.index;
+    if uid.is_empty() || !uid.chars().all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_') {
+        return Err(ResponseError::InvalidIndexUid.to_string().into());
+    }
+
+    let settings = match &build.settings {
+        Some(path) => {
+            let settings: Settings = serde_json::from_str(&fs::read_to_string(path)?)
+                .map_err(|e| format!("invalid settings in {}: {}", path.display(), e))?;
+            Some(settings.into_update().map_err(|e| e.to_string())?)
+        }
+        None => None,
+    };
+
+    let file = File::open(&build.documents)?;
+    let mut documents = ndjson_documents(BufReader::new(file));
+    let first_document = documents.next().transpose().map_err(|e| e.to_string())?;
+
+    let start = Instant::now();
+    let data = Data::new(opt.clone())?;
+    if data.db.open_index(uid).is_some() {
+        return Err(format!("index {} already exists in {}", uid, opt.db_path).into());
+    }
+
+    // the callback is called for the updates of every index of the database
+    let (sender, receiver) = crossbeam_channel::unbounded::<ProcessedUpdateResult>();
+    let data_cloned = data.clone();
+    let index_uid = uid.clone();
+    data.db.set_update_callback(Box::new(move |name, status| {
+        index_update_callback(name, &data_cloned, status.clone());
+        if name == index_uid {
+            let _ = sender.send(status);
+        }
+    }));
+
+    let primary_key = build
+        .primary_key
+        .clone()
+        .or_else(|| {
+            let document = first_document.as_ref()?;
+            find_primary_key(document.keys()).map(ToOwned::to_owned)
+        })
+        .ok_or("could not infer a primary key")?;
+
+    // the index is new, its schema is empty until the primary key is set
+    let index = data.db.create_index(uid)?;
+    let mut writer = data.db.main_write_txn()?;
+    let mut schema = Schema::new();
+    schema.set_primary_key(&primary_key)?;
+    index.main.put_schema(&mut writer, &schema)?;
+    writer.commit()?;
+
+    let mut last_update_id = None;
+    if let Some(settings) = settings {
+        let mut update_writer = data.db.update_write_txn()?;
+        last_update_id = Some(index.settings_update(&mut update_writer, settings)?);
+        update_writer.commit()?;
+    }
+
+    let mut documents_count = 0;
+    let mut batch = Vec::with_capacity(DOCUMENTS_BATCH_SIZE);
+    for document in first_document.map(Ok).into_iter().chain(documents) {
+        batch.push(document.map_err(|e| e.to_string())?);
+        documents_count += 1;
+        if batch.len() == DOCUMENTS_BATCH_SIZE {
+            last_update_id = Some(enqueue_documents(&data, &index, &mut batch)?);
+        }
+    }
+    if !batch.is_empty() {
+        last_update_id = Some(enqueue_documents(&data, &index, &mut batch)?);
+    }
+
+    // the updates of an index are processed in order, the last batch is the last one
+    if let Some(last_update_id) = last_update_id {
+        for status in receiver.iter() {
+            if let Some(error) = &status.error {
+                return Err(format!("update {} failed: {}", status.update_id, error).into());
+            }
+            if status.update_id == last_update_id {
+                break;
+            }
+        }
+    }
+
+    data.db.unset_update_callback();
+    data.db.close()?;
+
+    println!(
+        "index {} built in {} with {} documents in {:.02?}",
+        build.index,
+        opt.db_path,
+        documents_count,
+        start.elapsed(),
+    );
+
+    Ok(())
+}
+
+fn enqueue_documents(
+    data: &Data,
+    index: &Index,
+    batch: &mut Vec<IndexMap<String, Value>>,
+) -> Result<u64, Box<dyn Error>> {
+    let mut update_writer = data.db.update_write_txn()?;
+    let mut documents_addition = index.documents_addition();
+    for document in batch.drain(..) {
+        documents_addition.update_document(document);
+    }
+    let update_id = documents_addition.finalize(&mut update_writer)?;
+    update_writer.commit()?;
+    Ok(update_id)
+}
@@ -1,14 +1,22 @@
 #![allow(clippy::or_fun_call)]
 
+pub mod bench;
 pub mod data;
 pub mod error;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod helpers;
+pub mod index_builder;
 pub mod models;
+pub mod mount;
 pub mod option;
+pub mod replication;
 pub mod routes;
+pub mod snapshot;
 
 pub use self::data::Data;
 use self::error::json_error_handler;
+use self::helpers::{AuditLogging, PayloadLimit, RequestMetrics, TracePropagation, UsageAccounting};
 use actix_http::Error;
 use actix_service::ServiceFactory;
 use actix_web::{dev, web, App};
@@ -31,21 +39,40 @@ pub fn create_app(
         .app_data(web::Data::new(data.clone()))
         .app_data(
             web::JsonConfig::default()
-                .limit(1024 * 1024 * 10) // Json Limit of 10Mb
+                .limit(data.payload_limits.max())
                 .content_type(|_mime| true) // Accept all mime types
                 .error_handler(|err, _req| json_error_handler(err).into()),
         )
+        .app_data(web::PayloadConfig::new(data.payload_limits.max()))
         .service(routes::load_html)
         .service(routes::load_css)
+        .service(routes::load_openapi)
         .configure(routes::document::services)
         .configure(routes::index::services)
+        .configure(routes::index_template::services)
+        .configure(routes::rollover::services)
+        .configure(routes::changes::services)
         .configure(routes::search::services)
+        .configure(routes::aggregation::services)
+        .configure(routes::debug::services)
+        .configure(routes::judgments::services)
+        .configure(routes::quota::services)
         .configure(routes::setting::services)
         .configure(routes::stop_words::services)
         .configure(routes::synonym::services)
+        .configure(routes::update_group::services)
+        .configure(routes::upload_session::services)
+        .configure(routes::words::services)
         .configure(routes::health::services)
         .configure(routes::stats::services)
         .configure(routes::key::services)
+        .configure(routes::allowlist::services)
+        .configure(routes::usage::services)
+        .wrap(AuditLogging)
+        .wrap(PayloadLimit)
+        .wrap(TracePropagation)
+        .wrap(UsageAccounting)
+        .wrap(RequestMetrics)
 }
 
 pub fn index_update_callback(index_uid: &str, data: &Data, status: ProcessedUpdateResult) {
@@ -54,8 +81,7 @@ pub fn index_update_callback(index_uid: &str, data: &Data, status: ProcessedUpda
     }
 
     if let Some(index) = data.db.open_index(&index_uid) {
-        let db = &data.db;
-        let mut writer = match db.main_write_txn() {
+        let mut writer = match data.main_write_txn() {
             Ok(writer) => writer,
             Err(e) => {
                 error!("Impossible to get write_txn; {}", e);
@@ -67,6 +93,14 @@ pub fn index_update_callback(index_uid: &str, data: &Data, status: ProcessedUpda
             error!("Impossible to compute stats; {}", e)
         }
 
+        if let Err(e) = data.compute_data_size(&mut writer, &index_uid) {
+            error!("Impossible to compute data size; {}", e)
+        }
+
+        if let Err(e) = data.persist_usage(&mut writer) {
+            error!("Impossible to persist the usage; {}", e)
+        }
+
         if let Err(e) = data.set_last_update(&mut writer) {
             error!("Impossible to update last_update; {}", e)
         }
@@ -75,6 +109,10 @@ pub fn index_update_callback(index_uid: &str, data: &Data, status: ProcessedUpda
             error!("Impossible to update updated_at; {}", e)
         }
 
+        if let Err(e) = index.changes.trim(&mut writer, data.changes_retention) {
+            error!("Impossible to trim the changes log; {}", e)
+        }
+
         if let Err(e) = writer.commit() {
             error!("Impossible to get write_txn; {}", e);
         }
@@ -1,11 +1,17 @@
-use std::{env, thread};
+use std::path::Path;
+use std::env;
 
 use actix_cors::Cors;
 use actix_web::{middleware, HttpServer};
 use main_error::MainError;
-use meilisearch_http::data::Data;
-use meilisearch_http::helpers::NormalizeSlashes;
-use meilisearch_http::option::Opt;
+use meilisearch_http::data::{Data, WarmUp};
+use meilisearch_http::helpers::{upload_sessions, BackgroundThread, NormalizeSlashes};
+use meilisearch_http::{bench, index_builder};
+use meilisearch_http::option::{Command, Opt};
+use meilisearch_http::mount;
+use meilisearch_http::replication::{self, Remote};
+use meilisearch_http::routes::remove_expired_idempotency_keys;
+use meilisearch_http::snapshot::{self, SnapshotOptions};
 use meilisearch_http::{create_app, index_update_callback};
 use structopt::StructOpt;
 
@@ -49,35 +55,124 @@ async fn main() -> Result<(), MainError> {
         _ => unreachable!(),
     }
 
+    match &opt.command {
+        Some(Command::Bench(bench_opt)) => {
+            bench::run(&opt, bench_opt)?;
+            return Ok(());
+        }
+        Some(Command::BuildIndex(build_opt)) => {
+            index_builder::run(&opt, build_opt)?;
+            return Ok(());
+        }
+        None => (),
+    }
+
+    let mut background_threads = Vec::new();
+
     if !opt.no_analytics {
-        thread::spawn(analytics::analytics_sender);
+        background_threads.push(BackgroundThread::spawn("analytics", analytics::analytics_sender)?);
+    }
+
+    if let Some(url) = &opt.restore_from_url {
+        snapshot::restore_from_url(url, Path::new(&opt.db_path))?;
     }
 
-    let data = Data::new(opt.clone());
+    let data = Data::new(opt.clone())?;
+    mount::mount_indexes(&data, &opt.mount_indexes)?;
 
     let data_cloned = data.clone();
     data.db.set_update_callback(Box::new(move |name, status| {
         index_update_callback(name, &data_cloned, status);
     }));
 
+    let warm_up = if opt.warm_up { Some(WarmUp::start(data.clone())?) } else { None };
+
+    if let Some(url) = opt.replicate_from.clone() {
+        let leader = Remote::new(url, opt.replication_key.clone());
+        let data_cloned = data.clone();
+        let thread = BackgroundThread::spawn("replication", move |stop| {
+            replication::follow(data_cloned, leader, stop)
+        })?;
+        background_threads.push(thread);
+    }
+
+    if let Some(options) = SnapshotOptions::from_opt(&opt) {
+        let data_cloned = data.clone();
+        let thread = BackgroundThread::spawn("snapshots", move |stop| {
+            snapshot::schedule_snapshots(data_cloned, options, stop)
+        })?;
+        background_threads.push(thread);
+    }
+
+    let data_cloned = data.clone();
+    let thread = BackgroundThread::spawn("idempotency-keys-cleanup", move |stop| {
+        remove_expired_idempotency_keys(data_cloned, stop)
+    })?;
+    background_threads.push(thread);
+
+    let retention = data.upload_sessions_retention;
+    let sessions_cleanup = upload_sessions::SessionsCleanup::start(opt.db_path.clone(), retention)?;
+
+    #[cfg(feature = "grpc")]
+    let grpc_server = match opt.grpc_addr {
+        Some(addr) => Some(meilisearch_http::grpc::GrpcServer::start(data.clone(), addr)?),
+        None => None,
+    };
+
+    #[cfg(not(feature = "grpc"))]
+    if opt.grpc_addr.is_some() {
+        return Err("MeiliSearch must be built with the grpc feature to serve the gRPC API".into());
+    }
+
     print_launch_resume(&opt, &data);
 
+    let data_cloned = data.clone();
     HttpServer::new(move || {
         create_app(&data)
             .wrap(
                 Cors::new()
                     .send_wildcard()
                     .allowed_header("x-meili-api-key")
+                    .allowed_header("idempotency-key")
                     .finish(),
             )
             .wrap(middleware::Logger::default())
             .wrap(middleware::Compress::default())
             .wrap(NormalizeSlashes)
     })
+    .shutdown_timeout(opt.shutdown_timeout_sec)
     .bind(opt.http_addr)?
     .run()
     .await?;
 
+    #[cfg(feature = "grpc")]
+    if let Some(server) = grpc_server {
+        server.stop();
+    }
+
+    sessions_cleanup.stop();
+
+    // the replication and the snapshots write to the database, they must be done before it is closed
+    for thread in background_threads {
+        thread.stop();
+    }
+
+    if let Some(warm_up) = warm_up {
+        warm_up.stop();
+    }
+
+    let mut writer = data_cloned.db.main_write_txn()?;
+    data_cloned.persist_usage(&mut writer)?;
+    writer.commit()?;
+
+    if let Some(exporter) = &data_cloned.span_exporter {
+        exporter.stop();
+    }
+
+    // the server stopped accepting requests and the in-flight ones are answered,
+    // wait for the updates being processed to be committed before exiting
+    data_cloned.db.close()?;
+
     Ok(())
 }
 
@@ -97,7 +192,19 @@ pub fn print_launch_resume(opt: &Opt, data: &Data) {
 
     eprintln!("Database path:\t\t{:?}", opt.db_path);
     eprintln!("Server listening on:\t{:?}", opt.http_addr);
+    if let Some(addr) = &opt.grpc_addr {
+        eprintln!("gRPC listening on:\t{:?}", addr.to_string());
+    }
     eprintln!("Environment:\t\t{:?}", opt.env);
+    if let Some(url) = &opt.replicate_from {
+        eprintln!("Replicating from:\t{:?}", url);
+    }
+    for mount in &opt.mount_indexes {
+        eprintln!("Mounted read-only:\t{} from {:?}", mount.uid, mount.source);
+    }
+    if let Some(dir) = &opt.snapshot_dir {
+        eprintln!("Snapshots directory:\t{:?}", dir);
+    }
     eprintln!("Commit SHA:\t\t{:?}", env!("VERGEN_SHA").to_string());
     eprintln!(
         "Build date:\t\t{:?}",
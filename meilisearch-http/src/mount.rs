@@ -0,0 +1,98 @@
+//! Indexes mounted from artifacts built by another instance, e.g. with the `build-index` subcommand.
+//!
+//! The artifact of a mounted index is a database directory, local or hosted with the layout of
+//! a snapshot, its stores are copied into the database at startup and it is only read, so
+//! several instances can share it. The writes to a mounted index are rejected.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use log::info;
+
+use crate::snapshot::download_database;
+use crate::Data;
+
+/// An index to mount, written `uid=path` or `uid=url` on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexMount {
+    pub uid: String,
+    pub source: String,
+}
+
+impl IndexMount {
+    fn is_remote(&self) -> bool {
+        self.source.starts_with("http://") || self.source.starts_with("https://")
+    }
+}
+
+#[derive(Debug)]
+pub struct IndexMountParseError(String);
+
+impl fmt::Display for IndexMountParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid index mount {:?}, expected an index uid and a path or an url: uid=source", self.0)
+    }
+}
+
+impl FromStr for IndexMount {
+    type Err = IndexMountParseError;
+
+    fn from_str(s: &str) -> Result<IndexMount, Self::Err> {
+        let mut split = s.trim().splitn(2, '=');
+        match (split.next(), split.next()) {
+            (Some(uid), Some(source)) if !uid.is_empty() && !source.is_empty() => {
+                if !uid.chars().all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_') {
+                    return Err(IndexMountParseError(s.to_string()));
+                }
+                Ok(IndexMount { uid: uid.to_string(), source: source.to_string() })
+            }
+            _ => Err(IndexMountParseError(s.to_string())),
+        }
+    }
+}
+
+/// Mounts the indexes, the remote artifacts are downloaded again on every startup
+/// next to the database, in the `mounts` directory.
+pub fn mount_indexes(data: &Data, mounts: &[IndexMount]) -> Result<(), Box<dyn Error>> {
+    for mount in mounts {
+        let path = if mount.is_remote() {
+            let path = Path::new(&data.db_path).join("mounts").join(&mount.uid);
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(path.parent().unwrap())?;
+            download_database(&mount.source, &path)?;
+            path
+        } else {
+            PathBuf::from(&mount.source)
+        };
+
+        match data.db.mount_index(&mount.uid, &path)? {
+            Some(_) => info!("Index {} mounted read-only from {}", mount.uid, mount.source),
+            None => return Err(format!("there is no index {} in {}", mount.uid, mount.source).into()),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_index_mounts() {
+        let mount = IndexMount::from_str("movies=/artifacts/movies").unwrap();
+        assert_eq!(mount, IndexMount { uid: "movies".to_string(), source: "/artifacts/movies".to_string() });
+        assert!(!mount.is_remote());
+
+        let mount = IndexMount::from_str("movies=https://cdn.example.com/movies?v=2").unwrap();
+        assert_eq!(mount.source, "https://cdn.example.com/movies?v=2");
+        assert!(mount.is_remote());
+
+        assert!(IndexMount::from_str("movies").is_err());
+        assert!(IndexMount::from_str("=/artifacts/movies").is_err());
+        assert!(IndexMount::from_str("bad uid=/artifacts/movies").is_err());
+    }
+}
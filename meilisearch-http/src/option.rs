@@ -1,5 +1,15 @@
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use meilisearch_core::ENCRYPTION_KEY_LEN;
 use structopt::StructOpt;
 
+use crate::helpers::allowlist::IpNetwork;
+use crate::helpers::search_log::Redaction;
+use crate::mount::IndexMount;
+
 const POSSIBLE_ENV: [&str; 2] = ["development", "production"];
 
 #[derive(Debug, Clone, StructOpt)]
@@ -12,6 +22,11 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_HTTP_ADDR", default_value = "127.0.0.1:7700")]
     pub http_addr: String,
 
+    /// The address on which the gRPC server will listen, it is only started when this is set
+    /// and requires meilisearch to be built with the `grpc` feature.
+    #[structopt(long, env = "MEILI_GRPC_ADDR")]
+    pub grpc_addr: Option<SocketAddr>,
+
     /// The master key allowing you to do everything on the server.
     #[structopt(long, env = "MEILI_MASTER_KEY")]
     pub master_key: Option<String>,
@@ -34,4 +49,285 @@ pub struct Opt {
     /// The maximum size, in bytes, of the update lmdb database directory
     #[structopt(long, env = "MEILI_UPDATE_MAP_SIZE", default_value = "107374182400")] // 100GB
     pub update_map_size: usize,
+
+    /// The key encrypting the documents stored on disk, 32 bytes written in hexadecimal.
+    /// A database must always be opened with the key it has been created with.
+    ///
+    /// The documents, the enqueued updates, the changes log and the chunks of the upload sessions
+    /// are encrypted, the words and the facet values indexed to be searched and filtered are not.
+    #[structopt(long, env = "MEILI_ENCRYPTION_KEY")]
+    pub encryption_key: Option<EncryptionKey>,
+
+    /// A comma separated list of IP addresses or CIDR ranges allowed to reach the admin routes
+    /// (settings and keys). All clients are allowed when this list is empty.
+    #[structopt(long, env = "MEILI_ADMIN_ALLOWED_IPS", use_delimiter = true)]
+    pub admin_allowed_ips: Vec<IpNetwork>,
+
+    /// The url of a MeiliSearch instance to follow, all its indexes and their documents
+    /// will be continuously replicated on this instance.
+    #[structopt(long, env = "MEILI_REPLICATE_FROM")]
+    pub replicate_from: Option<String>,
+
+    /// The private key of the instance to follow.
+    #[structopt(long, env = "MEILI_REPLICATION_KEY")]
+    pub replication_key: Option<String>,
+
+    /// The directory in which the periodic snapshots of the database are written,
+    /// no snapshot is made if it is not set.
+    #[structopt(long, env = "MEILI_SNAPSHOT_DIR")]
+    pub snapshot_dir: Option<String>,
+
+    /// The number of seconds between two snapshots.
+    #[structopt(long, env = "MEILI_SNAPSHOT_INTERVAL_SEC", default_value = "86400")] // 1 day
+    pub snapshot_interval_sec: u64,
+
+    /// The number of snapshots to keep, older ones are removed locally and from the bucket.
+    #[structopt(long, env = "MEILI_SNAPSHOT_RETENTION", default_value = "5")]
+    pub snapshot_retention: usize,
+
+    /// The endpoint of the S3-compatible storage the snapshots are uploaded to.
+    #[structopt(long, env = "MEILI_S3_ENDPOINT", default_value = "https://s3.amazonaws.com")]
+    pub s3_endpoint: String,
+
+    /// The region of the bucket.
+    #[structopt(long, env = "MEILI_S3_REGION", default_value = "us-east-1")]
+    pub s3_region: String,
+
+    /// The bucket the snapshots are uploaded to, nothing is uploaded if it is not set.
+    #[structopt(long, env = "MEILI_S3_BUCKET")]
+    pub s3_bucket: Option<String>,
+
+    /// The path in the bucket under which the snapshots are uploaded.
+    #[structopt(long, env = "MEILI_S3_PREFIX", default_value = "")]
+    pub s3_prefix: String,
+
+    /// The access key id used to sign the requests to the bucket.
+    #[structopt(long, env = "MEILI_S3_ACCESS_KEY")]
+    pub s3_access_key: Option<String>,
+
+    /// The secret access key used to sign the requests to the bucket.
+    #[structopt(long, env = "MEILI_S3_SECRET_KEY")]
+    pub s3_secret_key: Option<String>,
+
+    /// The url of a snapshot directory to restore the database from,
+    /// ignored if a database already exists at the database path.
+    #[structopt(long, env = "MEILI_RESTORE_FROM_URL")]
+    pub restore_from_url: Option<String>,
+
+    /// A comma separated list of indexes to mount read-only at startup, each written `uid=source`.
+    /// The source is a database directory containing the index, or the url of one laid out as
+    /// a snapshot, e.g. built by the `build-index` subcommand.
+    #[structopt(long, env = "MEILI_MOUNT_INDEXES", use_delimiter = true)]
+    pub mount_indexes: Vec<IndexMount>,
+
+    /// The number of threads executing the searches.
+    #[structopt(long, env = "MEILI_SEARCH_THREADS", default_value = "4")]
+    pub search_threads: usize,
+
+    /// The number of searches waiting for a free thread above which
+    /// new searches are rejected with a 503 status.
+    #[structopt(long, env = "MEILI_SEARCH_QUEUE_SIZE", default_value = "256")]
+    pub search_queue_size: usize,
+
+    /// The number of seconds given to the in-flight requests to be answered after
+    /// a termination signal, before the server is forcibly stopped.
+    #[structopt(long, env = "MEILI_SHUTDOWN_TIMEOUT_SEC", default_value = "30")]
+    pub shutdown_timeout_sec: u64,
+
+    /// Read the whole database in the background on startup to load it in the OS page cache,
+    /// the first searches after a restart are then as fast as the following ones.
+    #[structopt(long, env = "MEILI_WARM_UP")]
+    pub warm_up: bool,
+
+    /// The maximum number of concurrent read transactions on each lmdb environment,
+    /// requests needing one more are rejected with a 503 status. Every request opens
+    /// its own read transactions, they are not reused from one request to another.
+    #[structopt(long, env = "MEILI_MAX_READERS", default_value = "126")]
+    pub max_readers: u32,
+
+    /// The maximum size, in bytes, of the payloads of the routes without a more specific limit.
+    #[structopt(long, env = "MEILI_HTTP_PAYLOAD_SIZE_LIMIT", default_value = "10485760")] // 10MB
+    pub http_payload_size_limit: usize,
+
+    /// The maximum size, in bytes, of the payloads of the documents routes.
+    #[structopt(long, env = "MEILI_DOCUMENTS_PAYLOAD_SIZE_LIMIT")]
+    pub documents_payload_size_limit: Option<usize>,
+
+    /// The maximum size, in bytes, of the payloads of the search routes.
+    #[structopt(long, env = "MEILI_SEARCH_PAYLOAD_SIZE_LIMIT")]
+    pub search_payload_size_limit: Option<usize>,
+
+    /// The maximum size, in bytes, of the payloads of the settings routes.
+    #[structopt(long, env = "MEILI_SETTINGS_PAYLOAD_SIZE_LIMIT")]
+    pub settings_payload_size_limit: Option<usize>,
+
+    /// The maximum size, in bytes, of the chunks of the upload sessions, the limit
+    /// of the documents routes is used when it is not set.
+    #[structopt(long, env = "MEILI_UPLOAD_CHUNK_SIZE_LIMIT")]
+    pub upload_chunk_size_limit: Option<usize>,
+
+    /// The number of days without a chunk received after which the upload sessions that failed
+    /// or were never committed are deleted, the committed sessions are deleted right away.
+    #[structopt(long, env = "MEILI_UPLOAD_SESSIONS_RETENTION_DAYS", default_value = "7")]
+    pub upload_sessions_retention_days: u32,
+
+    /// The number of changes kept in the changes log of every index, at least one, the oldest ones are removed.
+    #[structopt(
+        long,
+        env = "MEILI_CHANGES_RETENTION",
+        default_value = "100000",
+        parse(try_from_str = parse_changes_retention)
+    )]
+    pub changes_retention: u64,
+
+    /// The free space, in bytes, of the database disk below which the health route
+    /// reports the server as unavailable, zero disables the check, which is the default.
+    #[structopt(long, env = "MEILI_HEALTH_MIN_DISK_SPACE", default_value = "0")]
+    pub health_min_disk_space: u64,
+
+    /// The size, in bytes, over which the documents additions to an index are refused,
+    /// it can be set per index on the quota route. The indexes have no quota by default.
+    #[structopt(long, env = "MEILI_INDEX_MAX_DATA_SIZE")]
+    pub index_max_data_size: Option<u64>,
+
+    /// The file in which the calls to the write routes are logged, nothing is logged if it is not set.
+    #[structopt(long, env = "MEILI_AUDIT_LOG_PATH")]
+    pub audit_log_path: Option<String>,
+
+    /// The size, in bytes, above which the audit log is rotated.
+    #[structopt(long, env = "MEILI_AUDIT_LOG_MAX_SIZE", default_value = "104857600")] // 100MB
+    pub audit_log_max_size: u64,
+
+    /// The number of rotated audit log files to keep, older ones are removed.
+    #[structopt(long, env = "MEILI_AUDIT_LOG_RETENTION", default_value = "5")]
+    pub audit_log_retention: usize,
+
+    /// The fraction of the searches to log, between 0 (no search is logged) and 1 (every search is).
+    #[structopt(long, env = "MEILI_SEARCH_LOG_SAMPLE_RATE", default_value = "0")]
+    pub search_log_sample_rate: f64,
+
+    /// A semicolon separated list of regular expressions, the matching parts of the queries
+    /// and filters are redacted from the search log. The emails and numbers of six digits or
+    /// more are redacted by default.
+    #[structopt(
+        long,
+        env = "MEILI_SEARCH_LOG_REDACTIONS",
+        use_delimiter = true,
+        value_delimiter = ";",
+        default_value = r"[\w.+-]+@[\w-]+\.[\w.]+;\d{6,}"
+    )]
+    pub search_log_redactions: Vec<Redaction>,
+
+    /// The OTLP/HTTP endpoint of an OpenTelemetry collector the spans of the requests are
+    /// sent to, e.g. `http://localhost:4318/v1/traces`.
+    #[structopt(long, env = "MEILI_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
+    /// The number of seconds a search snapshot stays pinned, the pages requested
+    /// with its token after this delay are rejected.
+    #[structopt(long, env = "MEILI_SEARCH_SNAPSHOT_TTL_SEC", default_value = "60")]
+    pub search_snapshot_ttl_sec: u64,
+
+    /// The maximum number of search snapshots pinned at the same time, each one keeps
+    /// a read transaction open and prevents lmdb from reusing the pages freed since.
+    #[structopt(long, env = "MEILI_SEARCH_SNAPSHOTS_MAX", default_value = "32")]
+    pub search_snapshots_max: usize,
+
+    /// Runs a command on the database instead of serving it.
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub enum Command {
+    /// Replays the queries of a file against an index of the database and prints
+    /// the percentiles of their latencies, with the time spent in each search phase.
+    Bench(BenchOpt),
+    /// Builds an index in the database from a file of documents, one JSON object per line,
+    /// and a settings file, without starting the server.
+    BuildIndex(BuildIndexOpt),
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct BenchOpt {
+    /// The uid of the index that is searched.
+    #[structopt(long)]
+    pub index: String,
+
+    /// The file of the queries to replay, one per line.
+    #[structopt(long)]
+    pub queries: PathBuf,
+
+    /// The number of times the whole file of queries is replayed, at least once.
+    #[structopt(long, default_value = "1", parse(try_from_str = parse_runs))]
+    pub runs: usize,
+
+    /// The number of hits requested by each query.
+    #[structopt(long, default_value = "20")]
+    pub limit: usize,
+}
+
+fn parse_changes_retention(s: &str) -> Result<u64, String> {
+    match s.parse() {
+        Ok(0) => Err("the changes log must keep at least one change".to_string()),
+        Ok(retention) => Ok(retention),
+        Err(e) => Err(format!("invalid changes retention: {}", e)),
+    }
+}
+
+fn parse_runs(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("the queries must be replayed at least once".to_string()),
+        Ok(runs) => Ok(runs),
+        Err(e) => Err(format!("invalid number of runs: {}", e)),
+    }
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub struct BuildIndexOpt {
+    /// The uid of the index that is built, it must not exist in the database.
+    #[structopt(long)]
+    pub index: String,
+
+    /// The file of the documents to index, one JSON object per line.
+    #[structopt(long)]
+    pub documents: PathBuf,
+
+    /// The JSON file of the settings of the index, the ones of the settings route.
+    #[structopt(long)]
+    pub settings: Option<PathBuf>,
+
+    /// The primary key of the documents, inferred from the first document by default.
+    #[structopt(long)]
+    pub primary_key: Option<String>,
+}
+
+/// A key encrypting the stored documents, it is never displayed.
+#[derive(Clone, Copy)]
+pub struct EncryptionKey(pub [u8; ENCRYPTION_KEY_LEN]);
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+impl FromStr for EncryptionKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<EncryptionKey, Self::Err> {
+        let error = || format!("the encryption key must be {} bytes written in hexadecimal", ENCRYPTION_KEY_LEN);
+        let s = s.trim();
+        if s.len() != ENCRYPTION_KEY_LEN * 2 || !s.is_ascii() {
+            return Err(error());
+        }
+
+        let mut key = [0; ENCRYPTION_KEY_LEN];
+        for (byte, hex) in key.iter_mut().zip(s.as_bytes().chunks(2)) {
+            let hex = std::str::from_utf8(hex).map_err(|_| error())?;
+            *byte = u8::from_str_radix(hex, 16).map_err(|_| error())?;
+        }
+
+        Ok(EncryptionKey(key))
+    }
 }
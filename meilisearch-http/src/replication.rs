@@ -0,0 +1,466 @@
+//! Replication of indexes between MeiliSearch instances.
+//!
+//! A follower periodically pulls the indexes, the settings and the changes log
+//! of a leader instance and enqueues the same updates on its own indexes.
+//! The position of the follower in the changes log of every index is persisted with the
+//! updates it enqueued, allowing it to resume where it stopped after a restart. The indexes
+//! deleted from the leader are deleted too, the indexes of a follower are read-only.
+//! A follower that fell behind the retention of the changes log of the leader can't resume.
+//!
+//! An index can also be pushed once to another instance, its settings
+//! then its documents by chunks, see [`copy_index`](fn.copy_index.html).
+
+use std::collections::HashSet;
+use std::time::Duration;
+use std::{fmt, io};
+
+use crossbeam_channel::Receiver;
+use heed::types::{SerdeBincode, Str};
+use indexmap::IndexMap;
+use log::{error, info};
+use meilisearch_core::settings::Settings;
+use meilisearch_core::{update, Error as MError, Index, UpdateT};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::helpers::background::wait_or_stop;
+use crate::routes::changes::ChangeDocuments;
+use crate::routes::setting::index_settings;
+use crate::Data;
+
+const REPLICATION_INTERVAL: Duration = Duration::from_secs(1);
+const CHANGES_BATCH_SIZE: usize = 1000;
+
+type Document = IndexMap<String, Value>;
+
+#[derive(Debug)]
+pub enum ReplicationError {
+    Remote(String),
+    Local(String),
+}
+
+impl fmt::Display for ReplicationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplicationError::Remote(e) => write!(f, "remote instance error: {}", e),
+            ReplicationError::Local(e) => write!(f, "local instance error: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ReplicationError {
+    fn from(error: io::Error) -> ReplicationError {
+        ReplicationError::Remote(error.to_string())
+    }
+}
+
+impl From<serde_json::Error> for ReplicationError {
+    fn from(error: serde_json::Error) -> ReplicationError {
+        ReplicationError::Remote(error.to_string())
+    }
+}
+
+impl From<MError> for ReplicationError {
+    fn from(error: MError) -> ReplicationError {
+        ReplicationError::Local(error.to_string())
+    }
+}
+
+impl From<heed::Error> for ReplicationError {
+    fn from(error: heed::Error) -> ReplicationError {
+        ReplicationError::Local(error.to_string())
+    }
+}
+
+/// Another MeiliSearch instance, reached over its HTTP API.
+#[derive(Clone)]
+pub struct Remote {
+    url: String,
+    api_key: Option<String>,
+}
+
+impl Remote {
+    pub fn new(url: String, api_key: Option<String>) -> Remote {
+        let url = url.trim_end_matches('/').to_string();
+        Remote { url, api_key }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let url = format!("{}{}", self.url, path);
+        let mut request = ureq::request(method, &url);
+        if let Some(api_key) = &self.api_key {
+            request.set("X-Meili-API-Key", api_key);
+        }
+        request
+    }
+
+    fn read_response<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        response: ureq::Response,
+    ) -> Result<T, ReplicationError> {
+        if !response.ok() {
+            let message = format!("{} {}{} returned the status {}", method, self.url, path, response.status());
+            return Err(ReplicationError::Remote(message));
+        }
+
+        let body = response.into_string()?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ReplicationError> {
+        let response = self.request("GET", path).call();
+        self.read_response("GET", path, response)
+    }
+
+    fn post<T: DeserializeOwned>(&self, path: &str, body: &impl Serialize) -> Result<T, ReplicationError> {
+        let body = serde_json::to_string(body)?;
+        let response = self
+            .request("POST", path)
+            .set("Content-Type", "application/json")
+            .send_string(&body);
+        self.read_response("POST", path, response)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LeaderIndex {
+    uid: String,
+    name: String,
+    primary_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LeaderChange {
+    #[serde(flatten)]
+    documents: ChangeDocuments,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LeaderChanges {
+    changes: Vec<LeaderChange>,
+    next_from: u64,
+}
+
+fn progress_key(index_uid: &str) -> String {
+    format!("replication-progress-{}", index_uid)
+}
+
+fn settings_key(index_uid: &str) -> String {
+    format!("replication-settings-{}", index_uid)
+}
+
+/// The position of the follower in the changes log of the index and the last settings of the
+/// leader it has enqueued, they are stored with the updates, in the same transactions.
+fn replication_state(data: &Data, index_uid: &str) -> Result<(u64, Option<Value>), ReplicationError> {
+    let reader = data.db.update_read_txn()?;
+    let store = data.db.update_common_store();
+    let progress = store.get::<_, Str, SerdeBincode<u64>>(&reader, &progress_key(index_uid))?;
+    let settings = store.get::<_, Str, Str>(&reader, &settings_key(index_uid))?;
+    let settings = settings.map(serde_json::from_str).transpose().map_err(|e| ReplicationError::Local(e.to_string()))?;
+    Ok((progress.unwrap_or(0), settings))
+}
+
+/// The uids of the indexes that have been created by the replication.
+fn replicated_indexes(data: &Data) -> Result<Vec<String>, ReplicationError> {
+    let reader = data.db.update_read_txn()?;
+    let store = data.db.update_common_store();
+    let prefix = progress_key("");
+    let mut uids = Vec::new();
+    for result in store.prefix_iter::<_, Str, SerdeBincode<u64>>(&reader, &prefix)? {
+        let (key, _) = result?;
+        uids.push(key[prefix.len()..].to_string());
+    }
+    Ok(uids)
+}
+
+fn create_follower_index(data: &Data, leader_index: &LeaderIndex) -> Result<Index, ReplicationError> {
+    let index = data.db.create_index(&leader_index.uid)?;
+
+    let mut writer = data.db.main_write_txn()?;
+    index.main.put_name(&mut writer, &leader_index.name)?;
+
+    if let Some(primary_key) = &leader_index.primary_key {
+        if let Some(mut schema) = index.main.schema(&writer)? {
+            schema
+                .set_primary_key(primary_key)
+                .map_err(|e| ReplicationError::Local(e.to_string()))?;
+            index.main.put_schema(&mut writer, &schema)?;
+        }
+    }
+
+    writer.commit()?;
+
+    // the index is known as a replicated one as soon as it is created
+    let mut update_writer = data.db.update_write_txn()?;
+    let store = data.db.update_common_store();
+    store.put::<_, Str, SerdeBincode<u64>>(&mut update_writer, &progress_key(&leader_index.uid), &0)?;
+    update_writer.commit()?;
+
+    info!("replicating the {} index", leader_index.uid);
+
+    Ok(index)
+}
+
+/// Deletes the replicated index that has been deleted from the leader, and its replication state.
+fn delete_follower_index(data: &Data, index_uid: &str) -> Result<(), ReplicationError> {
+    if data.db.open_index(index_uid).is_some() {
+        data.db.delete_index(index_uid)?;
+    }
+
+    let mut update_writer = data.db.update_write_txn()?;
+    let store = data.db.update_common_store();
+    store.delete::<_, Str>(&mut update_writer, &progress_key(index_uid))?;
+    store.delete::<_, Str>(&mut update_writer, &settings_key(index_uid))?;
+    update_writer.commit()?;
+
+    info!("the {} index has been deleted from the leader", index_uid);
+
+    Ok(())
+}
+
+fn apply_change(
+    update_writer: &mut heed::RwTxn<UpdateT>,
+    index: &Index,
+    change: ChangeDocuments,
+) -> Result<(), ReplicationError> {
+    match change {
+        ChangeDocuments::DocumentsAddition { documents } => {
+            let mut addition = index.documents_addition::<Document>();
+            addition.extend(documents);
+            addition.finalize(update_writer)?;
+        }
+        ChangeDocuments::DocumentsPartial { documents } => {
+            let mut addition = index.documents_partial_addition::<Document>();
+            addition.extend(documents);
+            addition.finalize(update_writer)?;
+        }
+        ChangeDocuments::DocumentsDeletion { documents } => {
+            let mut deletion = index.documents_deletion();
+            for document_id in documents {
+                let document_id = update::value_to_string(&document_id);
+                let document_id = update::compute_document_id(&document_id).map_err(MError::Serializer)?;
+                deletion.delete_document_by_id(document_id);
+            }
+            deletion.finalize(update_writer)?;
+        }
+        ChangeDocuments::ClearAll => {
+            index.clear_all(update_writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn replicate_index(data: &Data, leader: &Remote, leader_index: LeaderIndex) -> Result<(), ReplicationError> {
+    let uid = &leader_index.uid;
+    let index = match data.db.open_index(uid) {
+        Some(index) => index,
+        None => create_follower_index(data, &leader_index)?,
+    };
+    let store = data.db.update_common_store();
+
+    // settings are not part of the changes log, we only
+    // enqueue a settings update when they differ from the last seen ones
+    let (_, last_settings) = replication_state(data, uid)?;
+    let settings: Value = leader.get(&format!("/indexes/{}/settings", uid))?;
+    if last_settings.as_ref() != Some(&settings) {
+        let update = serde_json::from_value::<Settings>(settings.clone())?
+            .into_update()
+            .map_err(|e| ReplicationError::Remote(e.to_string()))?;
+
+        let mut update_writer = data.db.update_write_txn()?;
+        index.settings_update(&mut update_writer, update)?;
+        store.put::<_, Str, Str>(&mut update_writer, &settings_key(uid), &settings.to_string())?;
+        update_writer.commit()?;
+    }
+
+    loop {
+        let (from, _) = replication_state(data, uid)?;
+        let path = format!("/indexes/{}/changes?from={}&limit={}", uid, from, CHANGES_BATCH_SIZE);
+        let LeaderChanges { changes, next_from } = leader.get(&path)?;
+
+        if changes.is_empty() {
+            break;
+        }
+
+        // the changes are enqueued with the position of the next ones, a follower
+        // stopped in the middle of a batch never enqueues a change twice
+        let mut update_writer = data.db.update_write_txn()?;
+        for LeaderChange { documents } in changes {
+            apply_change(&mut update_writer, &index, documents)?;
+        }
+        store.put::<_, Str, SerdeBincode<u64>>(&mut update_writer, &progress_key(uid), &next_from)?;
+        update_writer.commit()?;
+    }
+
+    Ok(())
+}
+
+fn replicate(data: &Data, leader: &Remote) -> Result<(), ReplicationError> {
+    let leader_indexes: Vec<LeaderIndex> = leader.get("/indexes")?;
+    let leader_uids: HashSet<String> = leader_indexes.iter().map(|index| index.uid.clone()).collect();
+
+    for uid in replicated_indexes(data)? {
+        if !leader_uids.contains(&uid) {
+            delete_follower_index(data, &uid)?;
+        }
+    }
+
+    for leader_index in leader_indexes {
+        replicate_index(data, leader, leader_index)?;
+    }
+    Ok(())
+}
+
+/// Follows the leader until `stop` is disconnected, must be run in a dedicated thread.
+pub fn follow(data: Data, leader: Remote, stop: Receiver<()>) {
+    info!("following the leader at {}", leader.url());
+
+    loop {
+        if let Err(e) = replicate(&data, &leader) {
+            error!("replication failed; {}", e);
+        }
+
+        if wait_or_stop(&stop, REPLICATION_INTERVAL) {
+            break;
+        }
+    }
+}
+
+/// The progression of the copy of an index to another instance.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexCopyStatus {
+    pub target: String,
+    pub state: IndexCopyState,
+    /// The number of documents already sent, a failed copy
+    /// can be resumed from there with the `offset` parameter.
+    pub documents_sent: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IndexCopyState {
+    Copying,
+    Copied,
+    Failed,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexCreation<'a> {
+    uid: &'a str,
+    name: &'a str,
+    primary_key: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoteUpdate {
+    update_id: u64,
+}
+
+fn update_copy_status(data: &Data, index_uid: &str, f: impl FnOnce(&mut IndexCopyStatus)) {
+    if let Ok(mut copies) = data.index_copies.lock() {
+        if let Some(status) = copies.get_mut(index_uid) {
+            f(status);
+        }
+    }
+}
+
+fn send_index(
+    data: &Data,
+    index_uid: &str,
+    target: &Remote,
+    offset: usize,
+    chunk_size: usize,
+) -> Result<(), ReplicationError> {
+    let index = data
+        .db
+        .open_index(index_uid)
+        .ok_or_else(|| ReplicationError::Local(format!("index {} not found", index_uid)))?;
+
+    // every chunk is read in its own read transaction, a long copy doesn't prevent
+    // lmdb from reusing the pages freed by the updates processed meanwhile
+    let reader = data.db.main_read_txn()?;
+    let name = index.main.name(&reader)?.unwrap_or_else(|| index_uid.to_string());
+    let schema = index.main.schema(&reader)?;
+    let primary_key = schema.as_ref().and_then(|s| s.primary_key()).map(str::to_string);
+    let settings = index_settings(&index, &reader).map_err(|e| ReplicationError::Local(e.to_string()))?;
+    let mut next_id = index.documents_fields_counts.documents_ids(&reader)?.nth(offset).transpose()?;
+    reader.abort()?;
+
+    // the documents are only sent once the index has its settings,
+    // this way the target instance indexes them a single time
+    if offset == 0 {
+        if let Err(e) = target.get::<Value>(&format!("/indexes/{}", index_uid)) {
+            log::debug!("creating the index on the target ({})", e);
+            let creation = IndexCreation { uid: index_uid, name: &name, primary_key: primary_key.as_deref() };
+            target.post::<Value>("/indexes", &creation)?;
+        }
+
+        target.post::<RemoteUpdate>(&format!("/indexes/{}/settings", index_uid), &settings)?;
+    }
+
+    let documents_path = match &primary_key {
+        Some(primary_key) => format!("/indexes/{}/documents?primaryKey={}", index_uid, primary_key),
+        None => format!("/indexes/{}/documents", index_uid),
+    };
+
+    let mut documents_sent = offset;
+    while let Some(start) = next_id {
+        let reader = data.db.main_read_txn()?;
+        let mut documents_ids = index.documents_fields_counts.documents_ids_from(&reader, start)?;
+        let mut chunk = Vec::with_capacity(chunk_size);
+        for document_id in documents_ids.by_ref().take(chunk_size) {
+            if let Some(document) = index.document::<Document>(&reader, None, document_id?)? {
+                chunk.push(document);
+            }
+        }
+        next_id = documents_ids.next().transpose()?;
+        drop(documents_ids);
+        reader.abort()?;
+
+        if chunk.is_empty() {
+            continue;
+        }
+
+        let update = target.post::<RemoteUpdate>(&documents_path, &chunk)?;
+        log::debug!("documents sent to the target in the update {}", update.update_id);
+
+        documents_sent += chunk.len();
+        update_copy_status(data, index_uid, |status| status.documents_sent = documents_sent);
+    }
+
+    Ok(())
+}
+
+/// Sends the settings and the documents of an index to another instance, chunk by chunk.
+/// The progression is reported in the `index_copies` of the data. The documents changed
+/// during the copy are sent in the state they have when their chunk is read.
+pub fn copy_index(data: Data, index_uid: String, target: Remote, offset: usize, chunk_size: usize) {
+    info!("copying the {} index to {}", index_uid, target.url());
+
+    let result = send_index(&data, &index_uid, &target, offset, chunk_size);
+
+    update_copy_status(&data, &index_uid, |status| match result {
+        Ok(()) => status.state = IndexCopyState::Copied,
+        Err(e) => {
+            error!("copy of the {} index failed; {}", index_uid, e);
+            status.state = IndexCopyState::Failed;
+            status.error = Some(e.to_string());
+        }
+    });
+}
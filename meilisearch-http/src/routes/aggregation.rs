@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use actix_web::{web, HttpResponse};
+use actix_web_macros::get;
+use meilisearch_core::facets::FacetFilter;
+use meilisearch_core::settings::resolve_attribute_alias;
+use meilisearch_core::{Filter, Index, MainT};
+use meilisearch_schema::{FieldId, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ResponseError;
+use crate::helpers::Authentication;
+use crate::routes::IndexParam;
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(aggregate);
+}
+
+/// The attributes to aggregate are given as comma separated lists.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AggregateQuery {
+    filters: Option<String>,
+    facet_filters: Option<String>,
+    sum: Option<String>,
+    avg: Option<String>,
+    min: Option<String>,
+    max: Option<String>,
+    cardinality: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AggregateResponse {
+    /// The number of documents matching the filter.
+    count: usize,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    sum: BTreeMap<String, f64>,
+    /// The averages are null when no document has a number in the attribute, like the bounds.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    avg: BTreeMap<String, Option<f64>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    min: BTreeMap<String, Option<f64>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    max: BTreeMap<String, Option<f64>>,
+    /// The number of distinct values of the facets, counted like the facets distribution.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    cardinality: BTreeMap<String, usize>,
+}
+
+/// The numbers found in an attribute of the documents matching the filter.
+#[derive(Default)]
+struct NumericValues {
+    count: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl NumericValues {
+    fn push(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    fn avg(&self) -> Option<f64> {
+        if self.count == 0 {
+            None
+        } else {
+            Some(self.sum / self.count as f64)
+        }
+    }
+}
+
+/// The strings holding a number are aggregated too, the other values are ignored,
+/// like the strings parsed as an infinity or as NaN.
+fn numeric_value(value: &Value) -> Option<f64> {
+    let number = match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    };
+    number.filter(|number: &f64| number.is_finite())
+}
+
+fn parse_attributes(
+    param: &str,
+    attributes: Option<&str>,
+    schema: &Schema,
+    aliases: &BTreeMap<String, String>,
+) -> Result<Vec<(String, FieldId)>, ResponseError> {
+    let attributes = match attributes {
+        Some(attributes) => attributes,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut fields = Vec::new();
+    for name in attributes.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        match schema.id(resolve_attribute_alias(aliases, name)) {
+            Some(field_id) => fields.push((name.to_string(), field_id)),
+            None => return Err(ResponseError::bad_parameter(param, format!("the attribute {} doesn't exist", name))),
+        }
+    }
+
+    Ok(fields)
+}
+
+fn aggregate_documents(
+    index: &Index,
+    reader: &heed::RoTxn<MainT>,
+    params: &AggregateQuery,
+) -> Result<AggregateResponse, ResponseError> {
+    let schema = index
+        .main
+        .schema(reader)?
+        .ok_or(ResponseError::internal("missing schema"))?;
+    let aliases = index.main.attribute_aliases(reader)?.unwrap_or_default();
+
+    let sum = parse_attributes("sum", params.sum.as_deref(), &schema, &aliases)?;
+    let avg = parse_attributes("avg", params.avg.as_deref(), &schema, &aliases)?;
+    let min = parse_attributes("min", params.min.as_deref(), &schema, &aliases)?;
+    let max = parse_attributes("max", params.max.as_deref(), &schema, &aliases)?;
+    let cardinality = parse_attributes("cardinality", params.cardinality.as_deref(), &schema, &aliases)?;
+
+    let attributes_for_faceting = match index.main.attributes_for_faceting(reader)? {
+        Some(attributes) => attributes,
+        None if params.facet_filters.is_some() => {
+            return Err(ResponseError::FacetExpression("can't filter on facets, as no facet is set".to_string()))
+        }
+        None => Vec::new(),
+    };
+    if let Some((name, _)) = cardinality.iter().find(|(_, field_id)| !attributes_for_faceting.contains(field_id)) {
+        return Err(ResponseError::bad_parameter("cardinality", format!("the attribute {} is not set as facet", name)));
+    }
+
+    let filter = match &params.filters {
+        Some(filters) => Some(Filter::parse_with_aliases(filters, &schema, &aliases)?),
+        None => None,
+    };
+
+    // The facet filters give the candidates from their postings, only the candidates
+    // are tested with the filters, all of the documents are without facet filters.
+    let candidates = match &params.facet_filters {
+        Some(facet_filters) => {
+            let facet_filter = FacetFilter::from_str_with_aliases(facet_filters, &schema, &attributes_for_faceting, &aliases)?;
+            facet_filter.documents_ids(reader, index)?.map(|ids| ids.into_vec()).unwrap_or_default()
+        }
+        None => index
+            .documents_fields_counts
+            .documents_ids(reader)?
+            .collect::<Result<Vec<_>, _>>()?,
+    };
+
+    let numeric_fields: HashSet<FieldId> = sum.iter().chain(&avg).chain(&min).chain(&max).map(|(_, id)| *id).collect();
+    let mut numeric_values: HashMap<FieldId, NumericValues> = HashMap::new();
+    let mut documents_ids = Vec::new();
+
+    for document_id in candidates {
+        if let Some(filter) = &filter {
+            if !filter.test(reader, index, document_id)? {
+                continue;
+            }
+        }
+
+        for &field_id in &numeric_fields {
+            let value = index.document_attribute::<Value>(reader, document_id, field_id)?;
+            if let Some(number) = value.as_ref().and_then(numeric_value) {
+                numeric_values.entry(field_id).or_default().push(number);
+            }
+        }
+
+        documents_ids.push(document_id);
+    }
+
+    documents_ids.sort_unstable();
+
+    let mut cardinalities = BTreeMap::new();
+    for (name, field_id) in cardinality {
+        let mut count = 0;
+        for pair in index.facets.field_document_ids(reader, field_id)? {
+            let (facet_key, facet_documents_ids) = pair?;
+            // the geohash cells are not values of the attribute
+            if facet_key.as_geohash_cell().is_some() {
+                continue;
+            }
+            if facet_documents_ids.iter().any(|id| documents_ids.binary_search(id).is_ok()) {
+                count += 1;
+            }
+        }
+        cardinalities.insert(name, count);
+    }
+
+    let values = |field_id: FieldId| numeric_values.get(&field_id);
+
+    Ok(AggregateResponse {
+        count: documents_ids.len(),
+        sum: sum.into_iter().map(|(name, id)| (name, values(id).map_or(0.0, |v| v.sum))).collect(),
+        avg: avg.into_iter().map(|(name, id)| (name, values(id).and_then(NumericValues::avg))).collect(),
+        min: min.into_iter().map(|(name, id)| (name, values(id).and_then(|v| v.min))).collect(),
+        max: max.into_iter().map(|(name, id)| (name, values(id).and_then(|v| v.max))).collect(),
+        cardinality: cardinalities,
+    })
+}
+
+/// Aggregates the documents matching the filters, all of the documents without one, so that
+/// the totals don't have to be computed by paginating through the documents. The `facetFilters`
+/// narrow the documents to test with the `filters` and should be preferred on large indexes.
+///
+/// `sum`, `avg`, `min` and `max` take numeric attributes and `cardinality` takes facets.
+#[get("/indexes/{index_uid}/aggregate", wrap = "Authentication::Public")]
+async fn aggregate(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<AggregateQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data
+        .db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    let data_cloned = data.clone();
+    let response = data
+        .search_pool
+        .run(move || {
+            let reader = data_cloned.main_read_txn()?;
+            aggregate_documents(&index, &reader, &params)
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
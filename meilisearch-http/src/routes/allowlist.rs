@@ -0,0 +1,47 @@
+use actix_web::web;
+use actix_web::HttpResponse;
+use actix_web_macros::{get, put};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ResponseError;
+use crate::helpers::allowlist::IpNetwork;
+use crate::helpers::{Authentication, IpAllowlist};
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_allowed_ips).service(update_allowed_ips);
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AllowedIps {
+    admin: Vec<IpNetwork>,
+}
+
+#[get("/allowed-ips", wrap = "Authentication::Admin")]
+async fn get_allowed_ips(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
+    let allowlist = data
+        .admin_allowlist
+        .read()
+        .map_err(ResponseError::internal)?;
+
+    Ok(HttpResponse::Ok().json(AllowedIps {
+        admin: allowlist.networks().to_vec(),
+    }))
+}
+
+#[put("/allowed-ips", wrap = "Authentication::Admin")]
+async fn update_allowed_ips(
+    data: web::Data<Data>,
+    body: web::Json<AllowedIps>,
+) -> Result<HttpResponse, ResponseError> {
+    let AllowedIps { admin } = body.into_inner();
+
+    let mut allowlist = data
+        .admin_allowlist
+        .write()
+        .map_err(ResponseError::internal)?;
+    *allowlist = IpAllowlist::new(admin.clone());
+
+    Ok(HttpResponse::Ok().json(AllowedIps { admin }))
+}
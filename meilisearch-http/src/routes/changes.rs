@@ -0,0 +1,119 @@
+use actix_web::{web, HttpResponse};
+use actix_web_macros::get;
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use meilisearch_core::store::{Change, ChangeKind};
+use meilisearch_core::{update, Error, Index, MainT};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ResponseError;
+use crate::helpers::Authentication;
+use crate::routes::IndexParam;
+use crate::Data;
+
+type Document = IndexMap<String, Value>;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_changes);
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ChangesQuery {
+    from: Option<u64>,
+    limit: Option<usize>,
+}
+
+/// A change with the current version of the documents it added or updated,
+/// the documents deleted since then are no longer part of it.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ChangeDocuments {
+    DocumentsAddition { documents: Vec<Document> },
+    DocumentsPartial { documents: Vec<Document> },
+    /// The primary key values of the documents that have been deleted.
+    DocumentsDeletion { documents: Vec<Value> },
+    ClearAll,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangeResponse {
+    sequence: u64,
+    update_id: u64,
+    applied_at: DateTime<Utc>,
+    #[serde(flatten)]
+    documents: ChangeDocuments,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangesResponse {
+    changes: Vec<ChangeResponse>,
+    /// The sequence number to ask for to continue tailing the changes.
+    next_from: u64,
+}
+
+fn current_documents(
+    reader: &heed::RoTxn<MainT>,
+    index: &Index,
+    primary_keys: Vec<Value>,
+) -> Result<Vec<Document>, ResponseError> {
+    let mut documents = Vec::with_capacity(primary_keys.len());
+    for primary_key in primary_keys {
+        let document_id = update::value_to_string(&primary_key);
+        let document_id = update::compute_document_id(&document_id).map_err(Error::Serializer)?;
+        if let Some(document) = index.document(reader, None, document_id)? {
+            documents.push(document);
+        }
+    }
+    Ok(documents)
+}
+
+#[get("/indexes/{index_uid}/changes", wrap = "Authentication::Private")]
+async fn get_changes(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<ChangesQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data
+        .db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    let limit = params.limit.unwrap_or(100).min(1000);
+
+    let reader = data.main_read_txn()?;
+
+    // the oldest changes are removed past the retention, a client asking for them has missed some
+    let first_sequence = index.changes.first_sequence(&reader)?;
+    let from = match (params.from, first_sequence) {
+        (Some(from), Some(first)) if from < first => {
+            let message = format!("the changes before the sequence {} have been removed", first);
+            return Err(ResponseError::bad_parameter("from", message));
+        }
+        (Some(from), _) => from,
+        (None, first) => first.unwrap_or(0),
+    };
+
+    let mut changes = Vec::new();
+    for (sequence, change) in index.changes.changes(&reader, from, limit)? {
+        let Change { update_id, applied_at, kind } = change;
+        let documents = match kind {
+            ChangeKind::DocumentsAddition { documents } => ChangeDocuments::DocumentsAddition {
+                documents: current_documents(&reader, &index, documents)?,
+            },
+            ChangeKind::DocumentsPartial { documents } => ChangeDocuments::DocumentsPartial {
+                documents: current_documents(&reader, &index, documents)?,
+            },
+            ChangeKind::DocumentsDeletion { documents } => ChangeDocuments::DocumentsDeletion { documents },
+            ChangeKind::ClearAll => ChangeDocuments::ClearAll,
+        };
+        changes.push(ChangeResponse { sequence, update_id, applied_at, documents });
+    }
+
+    let next_from = changes.last().map_or(from, |c| c.sequence + 1);
+
+    Ok(HttpResponse::Ok().json(ChangesResponse { changes, next_from }))
+}
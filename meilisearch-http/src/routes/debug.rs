@@ -0,0 +1,49 @@
+use actix_web::{web, HttpResponse};
+use actix_web_macros::post;
+use meilisearch_core::{analyze_query, QueryTreeContext};
+use serde::Deserialize;
+
+use crate::error::ResponseError;
+use crate::helpers::Authentication;
+use crate::routes::IndexParam;
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(analyze);
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AnalyzeQuery {
+    q: String,
+    concatenate_ngrams: Option<bool>,
+}
+
+/// Returns the tokens, the n-grams, the splits and the synonyms of the query
+/// with the query tree built from them, the search isn't executed.
+#[post("/indexes/{index_uid}/debug/analyze", wrap = "Authentication::Private")]
+async fn analyze(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<AnalyzeQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data
+        .db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    let data_cloned = data.clone();
+    let analysis = data
+        .search_pool
+        .run(move || {
+            let reader = data_cloned.main_read_txn()?;
+            let mut context = QueryTreeContext::new(&reader, &index)?;
+            if let Some(concatenate_ngrams) = body.concatenate_ngrams {
+                context.concatenate_ngrams = concatenate_ngrams;
+            }
+            Ok(analyze_query(&reader, &context, &body.q)?)
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(analysis))
+}
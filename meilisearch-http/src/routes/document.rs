@@ -1,15 +1,23 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::BufReader;
 
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_macros::{delete, get, post, put};
 use indexmap::IndexMap;
+use log::warn;
+use meilisearch_core::update::Precondition;
 use meilisearch_core::{update, Error};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::error::ResponseError;
-use crate::helpers::Authentication;
-use crate::routes::{IndexParam, IndexUpdateResponse};
+use crate::helpers::remote_documents::{fetch_file, read_documents, read_documents_batches, DocumentsFormat};
+use crate::helpers::upload_sessions::{self, UploadSession};
+use crate::helpers::{run_blocking, Authentication};
+use crate::routes::index_template::create_index_from_template;
+use crate::routes::quota::check_data_quota;
+use crate::routes::rollover::resolve_write_alias;
+use crate::routes::{enqueue_once, IdempotencyKey, IndexParam, IndexUpdateResponse, UpdatePriority};
 use crate::Data;
 
 type Document = IndexMap<String, Value>;
@@ -22,10 +30,17 @@ struct DocumentParam {
 
 pub fn services(cfg: &mut web::ServiceConfig) {
     cfg.service(get_document)
+        .service(get_document_tokens)
         .service(delete_document)
         .service(get_all_documents)
         .service(add_documents)
         .service(update_documents)
+        .service(add_documents_from_url)
+        .service(create_upload_session)
+        .service(get_upload_session)
+        .service(upload_chunk)
+        .service(commit_upload_session)
+        .service(delete_upload_session)
         .service(delete_documents)
         .service(clear_all_documents);
 }
@@ -44,7 +59,7 @@ async fn get_document(
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
     let document_id = update::compute_document_id(&path.document_id).map_err(Error::Serializer)?;
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let response: Document = index
         .document(&reader, None, document_id)?
@@ -53,11 +68,20 @@ async fn get_document(
     Ok(HttpResponse::Ok().json(response))
 }
 
-#[delete(
-    "/indexes/{index_uid}/documents/{document_id}",
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexedToken {
+    word: String,
+    position: u16,
+    char_index: u16,
+    char_length: u16,
+}
+
+#[get(
+    "/indexes/{index_uid}/documents/{document_id}/tokens",
     wrap = "Authentication::Private"
 )]
-async fn delete_document(
+async fn get_document_tokens(
     data: web::Data<Data>,
     path: web::Path<DocumentParam>,
 ) -> Result<HttpResponse, ResponseError> {
@@ -66,14 +90,62 @@ async fn delete_document(
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
+    let document_id = update::compute_document_id(&path.document_id).map_err(Error::Serializer)?;
+    let reader = data.main_read_txn()?;
+
+    let schema = index
+        .main
+        .schema(&reader)?
+        .ok_or(ResponseError::internal("schema not found"))?;
+
+    let words = index
+        .document_words(&reader, document_id)?
+        .ok_or(ResponseError::document_not_found(&path.document_id))?;
+
+    // the words are sorted by attribute, attributes are therefore listed in the indexing order
+    let mut response = IndexMap::<String, Vec<IndexedToken>>::new();
+    for (word, position) in words {
+        let name = match schema
+            .indexed_pos_to_field_id(position.attribute)
+            .and_then(|field_id| schema.name(field_id))
+        {
+            Some(name) => name,
+            None => continue,
+        };
+
+        response.entry(name.to_string()).or_default().push(IndexedToken {
+            word,
+            position: position.word_index,
+            char_index: position.char_index,
+            char_length: position.char_length,
+        });
+    }
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[delete(
+    "/indexes/{index_uid}/documents/{document_id}",
+    wrap = "Authentication::Private"
+)]
+async fn delete_document(
+    req: HttpRequest,
+    data: web::Data<Data>,
+    path: web::Path<DocumentParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
+
     let document_id = update::compute_document_id(&path.document_id).map_err(Error::Serializer)?;
 
-    let mut update_writer = data.db.update_write_txn()?;
+    let mut update_writer = data.update_write_txn_async().await?;
 
     let mut documents_deletion = index.documents_deletion();
     documents_deletion.delete_document_by_id(document_id);
 
-    let update_id = documents_deletion.finalize(&mut update_writer)?;
+    let update_id = enqueue_once(&index, &mut update_writer, idempotency_key.as_ref(), |writer| {
+        Ok(documents_deletion.finalize(writer)?)
+    })?;
 
     update_writer.commit()?;
 
@@ -102,7 +174,7 @@ async fn get_all_documents(
     let offset = params.offset.unwrap_or(0);
     let limit = params.limit.unwrap_or(20);
 
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let documents_ids: Result<BTreeSet<_>, _> = index
         .documents_fields_counts
@@ -130,34 +202,96 @@ async fn get_all_documents(
     Ok(HttpResponse::Ok().json(response))
 }
 
-fn find_primary_key(document: &IndexMap<String, Value>) -> Option<String> {
-    for key in document.keys() {
-        if key.to_lowercase().contains("id") {
-            return Some(key.to_string());
-        }
-    }
-    None
-}
-
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct UpdateDocumentsQuery {
     primary_key: Option<String>,
+    #[serde(default)]
+    priority: UpdatePriority,
+    precondition: Option<String>,
+    /// Whether the documents that are not stored yet are added despite the precondition.
+    #[serde(default)]
+    insert_missing: bool,
+    /// The attribute of the documents holding the operations applied to their fields, partial updates only.
+    operations_attribute: Option<String>,
+}
+
+/// How the documents are pushed, the documents not matching the `precondition`
+/// filter, when the addition is processed, are skipped.
+#[derive(Default)]
+pub(crate) struct AdditionOptions {
+    pub(crate) partial: bool,
+    pub(crate) priority: UpdatePriority,
+    pub(crate) precondition: Option<Precondition>,
+    pub(crate) operations_attribute: Option<String>,
 }
 
 async fn update_multiple_documents(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     params: web::Query<UpdateDocumentsQuery>,
     body: web::Json<Vec<Document>>,
     is_partial: bool,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+
+    // setting the primary key needs a write transaction on the main environment
+    let UpdateDocumentsQuery { primary_key, priority, precondition, insert_missing, operations_attribute } =
+        params.into_inner();
+    let precondition = precondition.map(|filter| Precondition { filter, insert_missing });
+
+    // the replaced documents have no stored fields to operate on
+    if operations_attribute.is_some() && !is_partial {
+        let message = "the operations can only be applied by the partial updates";
+        return Err(ResponseError::bad_parameter("operationsAttribute", message));
+    }
 
-    let reader = data.db.main_read_txn()?;
+    let index_uid = path.into_inner().index_uid;
+    let update_id = run_blocking("documents addition", move || {
+        push_documents(
+            &data,
+            &index_uid,
+            primary_key.as_deref(),
+            body.into_inner(),
+            AdditionOptions { partial: is_partial, priority, precondition, operations_attribute },
+            idempotency_key.as_ref(),
+        )
+    })
+    .await?;
+
+    Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
+}
+
+pub(crate) fn push_documents(
+    data: &Data,
+    index_uid: &str,
+    primary_key: Option<&str>,
+    documents: Vec<Document>,
+    options: AdditionOptions,
+    idempotency_key: Option<&IdempotencyKey>,
+) -> Result<u64, ResponseError> {
+    // the documents added to a rollover alias go to its current partition
+    let index_uid = &resolve_write_alias(data, index_uid)?;
+    data.check_writable(index_uid)?;
+
+    // the missing indexes matching a template are created
+    let index = match data.db.open_index(index_uid) {
+        Some(index) => index,
+        None => create_index_from_template(data, index_uid)?
+            .ok_or(ResponseError::index_not_found(index_uid))?,
+    };
+
+    // a retried request gets its update back before anything is written, the primary key included
+    if let Some(idempotency_key) = idempotency_key {
+        let update_reader = data.update_read_txn()?;
+        if let Some(update_id) = idempotency_key.enqueued_update(&index, &update_reader)? {
+            return Ok(update_id);
+        }
+    }
+
+    let reader = data.main_read_txn()?;
+    check_data_quota(data, &index, &reader, index_uid)?;
 
     let mut schema = index
         .main
@@ -165,15 +299,16 @@ async fn update_multiple_documents(
         .ok_or(ResponseError::internal("Impossible to retrieve the schema"))?;
 
     if schema.primary_key().is_none() {
-        let id = match &params.primary_key {
+        let id = match primary_key {
             Some(id) => id.to_string(),
-            None => body
+            None => documents
                 .first()
-                .and_then(find_primary_key)
+                .and_then(|document| update::find_primary_key(document.keys()))
+                .map(ToOwned::to_owned)
                 .ok_or(ResponseError::bad_request("Could not infer a primary key"))?,
         };
 
-        let mut writer = data.db.main_write_txn()?;
+        let mut writer = data.main_write_txn()?;
 
         schema
             .set_primary_key(&id)
@@ -182,41 +317,327 @@ async fn update_multiple_documents(
         writer.commit()?;
     }
 
-    let mut document_addition = if is_partial {
+    let AdditionOptions { partial, priority, precondition, operations_attribute } = options;
+    let mut document_addition = if partial {
         index.documents_partial_addition()
     } else {
         index.documents_addition()
     };
 
-    for document in body.into_inner() {
+    // the documents are enqueued without their excluded attributes, the primary key is always kept
+    let mut excluded_attributes = index.main.excluded_attributes(&reader)?.unwrap_or_default();
+    if let Some(primary_key) = schema.primary_key() {
+        excluded_attributes.remove(primary_key);
+    }
+
+    if let Some(attribute) = operations_attribute {
+        if schema.primary_key() == Some(attribute.as_str()) {
+            let message = "the primary key can't hold the operations";
+            return Err(ResponseError::bad_parameter("operationsAttribute", message));
+        }
+        excluded_attributes.remove(&attribute);
+        document_addition.set_operations_attribute(attribute);
+    }
+    document_addition.set_excluded_attributes(excluded_attributes);
+
+    // the filter is checked again for each document when the addition is processed
+    if let Some(precondition) = precondition {
+        precondition.validate(&schema)?;
+        document_addition.set_precondition(precondition);
+    }
+
+    for document in documents {
         document_addition.update_document(document);
     }
 
-    let mut update_writer = data.db.update_write_txn()?;
-    let update_id = document_addition.finalize(&mut update_writer)?;
+    let mut update_writer = data.update_write_txn()?;
+    let update_id = enqueue_once(&index, &mut update_writer, idempotency_key, |writer| {
+        let update_id = document_addition.finalize(writer)?;
+        if priority == UpdatePriority::High {
+            index.prioritize_update(writer, update_id)?;
+        }
+        Ok(update_id)
+    })?;
     update_writer.commit()?;
 
-    Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
+    Ok(update_id)
 }
 
 #[post("/indexes/{index_uid}/documents", wrap = "Authentication::Private")]
 async fn add_documents(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     params: web::Query<UpdateDocumentsQuery>,
     body: web::Json<Vec<Document>>,
 ) -> Result<HttpResponse, ResponseError> {
-    update_multiple_documents(data, path, params, body, false).await
+    update_multiple_documents(req, data, path, params, body, false).await
 }
 
 #[put("/indexes/{index_uid}/documents", wrap = "Authentication::Private")]
 async fn update_documents(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     params: web::Query<UpdateDocumentsQuery>,
     body: web::Json<Vec<Document>>,
 ) -> Result<HttpResponse, ResponseError> {
-    update_multiple_documents(data, path, params, body, true).await
+    update_multiple_documents(req, data, path, params, body, true).await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct DocumentsFromUrl {
+    url: String,
+    format: DocumentsFormat,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    primary_key: Option<String>,
+    #[serde(default)]
+    partial: bool,
+}
+
+/// The number of documents of a remote file enqueued in the same update.
+const REMOTE_BATCH_SIZE: usize = 10_000;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentsFromUrlResponse {
+    update_ids: Vec<u64>,
+}
+
+/// Enqueues the documents of a file hosted on another server, the file is downloaded by MeiliSearch
+/// and never goes through the client connection. It is read as it is received and its documents are
+/// enqueued by batches, one update each, the batches enqueued before an invalid document are kept.
+#[post(
+    "/indexes/{index_uid}/documents/from-url",
+    wrap = "Authentication::Private"
+)]
+async fn add_documents_from_url(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<DocumentsFromUrl>,
+) -> Result<HttpResponse, ResponseError> {
+    data.writable_index(&path.index_uid)?;
+
+    let body = body.into_inner();
+    let data_cloned = data.clone();
+    let index_uid = path.index_uid.clone();
+
+    let update_ids = run_blocking("documents fetching", move || {
+        let file = fetch_file(&body.url, &body.headers)?;
+        let mut update_ids = Vec::new();
+        read_documents_batches(file, body.format, REMOTE_BATCH_SIZE, |documents| {
+            let update_id = push_documents(
+                &data_cloned,
+                &index_uid,
+                body.primary_key.as_deref(),
+                documents,
+                AdditionOptions { partial: body.partial, ..AdditionOptions::default() },
+                None,
+            )?;
+            update_ids.push(update_id);
+            Ok(())
+        })?;
+        Ok(update_ids)
+    })
+    .await?;
+
+    Ok(HttpResponse::Accepted().json(DocumentsFromUrlResponse { update_ids }))
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct UploadSessionBody {
+    #[serde(default = "default_upload_format")]
+    format: DocumentsFormat,
+    primary_key: Option<String>,
+    #[serde(default)]
+    partial: bool,
+}
+
+fn default_upload_format() -> DocumentsFormat {
+    DocumentsFormat::Json
+}
+
+#[derive(Deserialize)]
+struct UploadSessionParam {
+    index_uid: String,
+    session_id: String,
+}
+
+#[derive(Deserialize)]
+struct UploadChunkParam {
+    index_uid: String,
+    session_id: String,
+    chunk: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadSessionResponse {
+    session_id: String,
+    #[serde(flatten)]
+    session: UploadSession,
+    /// The numbers of the chunks that have been received.
+    chunks: Vec<u32>,
+    /// Not set once the session is committed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl UploadSessionResponse {
+    fn new(data: &Data, session_id: String, session: UploadSession) -> Result<UploadSessionResponse, ResponseError> {
+        let chunks = upload_sessions::chunks(&data.db_path, &session_id)?;
+        let retention = data.upload_sessions_retention;
+        let expires_at = upload_sessions::expires_at(&data.db_path, &session_id, &session, retention);
+        Ok(UploadSessionResponse { session_id, session, chunks, expires_at })
+    }
+}
+
+/// Starts the upload of documents in chunks, the documents are only enqueued
+/// for indexing once all the chunks have been received and the session is committed.
+/// The creation retried with the same `Idempotency-Key` returns the session created the first time.
+#[post("/indexes/{index_uid}/documents/sessions", wrap = "Authentication::Private")]
+async fn create_upload_session(
+    req: HttpRequest,
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<UploadSessionBody>,
+) -> Result<HttpResponse, ResponseError> {
+    data.writable_index(&path.index_uid)?;
+
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let body = body.into_inner();
+    let session = UploadSession {
+        index_uid: path.index_uid.clone(),
+        format: body.format,
+        primary_key: body.primary_key,
+        partial: body.partial,
+        created_at: chrono::Utc::now(),
+        committed_at: None,
+    };
+    let idempotency_key = idempotency_key.as_ref().map(|key| (key.key(), key.request_digest()));
+    let (session_id, created) = upload_sessions::create_session(&data.db_path, &session, idempotency_key)?;
+
+    // the retried creation gets the session back with the chunks received since
+    if created {
+        Ok(HttpResponse::Created().json(UploadSessionResponse::new(&data, session_id, session)?))
+    } else {
+        let retention = data.upload_sessions_retention;
+        let session = upload_sessions::open_session(&data.db_path, &path.index_uid, &session_id, retention)?;
+        Ok(HttpResponse::Ok().json(UploadSessionResponse::new(&data, session_id, session)?))
+    }
+}
+
+/// Returns the chunks of the session that have been received, the missing ones can be sent again.
+#[get(
+    "/indexes/{index_uid}/documents/sessions/{session_id}",
+    wrap = "Authentication::Private"
+)]
+async fn get_upload_session(
+    data: web::Data<Data>,
+    path: web::Path<UploadSessionParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let retention = data.upload_sessions_retention;
+    let session = upload_sessions::open_session(&data.db_path, &path.index_uid, &path.session_id, retention)?;
+    let session_id = path.into_inner().session_id;
+
+    Ok(HttpResponse::Ok().json(UploadSessionResponse::new(&data, session_id, session)?))
+}
+
+#[put(
+    "/indexes/{index_uid}/documents/sessions/{session_id}/chunks/{chunk}",
+    wrap = "Authentication::Private"
+)]
+async fn upload_chunk(
+    data: web::Data<Data>,
+    path: web::Path<UploadChunkParam>,
+    body: web::Bytes,
+) -> Result<HttpResponse, ResponseError> {
+    let retention = data.upload_sessions_retention;
+    upload_sessions::open_uncommitted_session(&data.db_path, &path.index_uid, &path.session_id, retention)?;
+    upload_sessions::write_chunk(&data.db, &data.db_path, &path.session_id, path.chunk, &body)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Reads the documents of the concatenated chunks and enqueues them in a single update.
+#[post(
+    "/indexes/{index_uid}/documents/sessions/{session_id}/commit",
+    wrap = "Authentication::Private"
+)]
+async fn commit_upload_session(
+    req: HttpRequest,
+    data: web::Data<Data>,
+    path: web::Path<UploadSessionParam>,
+) -> Result<HttpResponse, ResponseError> {
+    // the session is deleted once committed, a retried commit gets its update back
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    if let Some(idempotency_key) = &idempotency_key {
+        let index_uid = resolve_write_alias(&data, &path.index_uid)?;
+        if let Some(index) = data.db.open_index(&index_uid) {
+            let update_reader = data.update_read_txn()?;
+            if let Some(update_id) = idempotency_key.enqueued_update(&index, &update_reader)? {
+                return Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)));
+            }
+        }
+    }
+
+    let retention = data.upload_sessions_retention;
+    let mut session =
+        upload_sessions::open_uncommitted_session(&data.db_path, &path.index_uid, &path.session_id, retention)?;
+    let data_cloned = data.clone();
+    let session_id = path.into_inner().session_id;
+
+    // the committed session can't be committed twice, it is given back if its documents are refused
+    upload_sessions::set_committed(&data.db_path, &session_id, &mut session, Some(chrono::Utc::now()))?;
+
+    let update_id = run_blocking("documents reading", move || {
+        let reader = upload_sessions::chunks_reader(&data_cloned.db, &data_cloned.db_path, &session_id);
+        let result = reader.and_then(|reader| {
+            let documents = read_documents(BufReader::new(reader), session.format)?;
+            push_documents(
+                &data_cloned,
+                &session.index_uid,
+                session.primary_key.as_deref(),
+                documents,
+                AdditionOptions { partial: session.partial, ..AdditionOptions::default() },
+                idempotency_key.as_ref(),
+            )
+        });
+
+        match result {
+            Ok(update_id) => {
+                // the session left is purged by the cleanup, the documents have been enqueued
+                if let Err(e) = upload_sessions::delete_session(&data_cloned.db_path, &session_id) {
+                    warn!("Impossible to delete the committed upload session {}: {}", session_id, e);
+                }
+                Ok(update_id)
+            }
+            Err(e) => {
+                upload_sessions::set_committed(&data_cloned.db_path, &session_id, &mut session, None)?;
+                Err(e)
+            }
+        }
+    })
+    .await?;
+
+    Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
+}
+
+#[delete(
+    "/indexes/{index_uid}/documents/sessions/{session_id}",
+    wrap = "Authentication::Private"
+)]
+async fn delete_upload_session(
+    data: web::Data<Data>,
+    path: web::Path<UploadSessionParam>,
+) -> Result<HttpResponse, ResponseError> {
+    upload_sessions::open_session(&data.db_path, &path.index_uid, &path.session_id, data.upload_sessions_retention)?;
+    upload_sessions::delete_session(&data.db_path, &path.session_id)?;
+
+    Ok(HttpResponse::NoContent().finish())
 }
 
 #[post(
@@ -224,16 +645,15 @@ async fn update_documents(
     wrap = "Authentication::Private"
 )]
 async fn delete_documents(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     body: web::Json<Vec<Value>>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
 
-    let mut writer = data.db.update_write_txn()?;
+    let mut writer = data.update_write_txn_async().await?;
 
     let mut documents_deletion = index.documents_deletion();
 
@@ -243,7 +663,9 @@ async fn delete_documents(
         documents_deletion.delete_document_by_id(document_id);
     }
 
-    let update_id = documents_deletion.finalize(&mut writer)?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(documents_deletion.finalize(writer)?)
+    })?;
 
     writer.commit()?;
 
@@ -252,17 +674,18 @@ async fn delete_documents(
 
 #[delete("/indexes/{index_uid}/documents", wrap = "Authentication::Private")]
 async fn clear_all_documents(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
 
-    let mut writer = data.db.update_write_txn()?;
+    let mut writer = data.update_write_txn_async().await?;
 
-    let update_id = index.clear_all(&mut writer)?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.clear_all(writer)?)
+    })?;
 
     writer.commit()?;
 
@@ -1,10 +1,16 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
 use actix_web::{web, HttpResponse};
 use actix_web_macros::{get, put};
+use chrono::Utc;
 use heed::types::{Str, Unit};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use sysinfo::{DiskExt, System, SystemExt};
 
 use crate::error::ResponseError;
-use crate::helpers::Authentication;
+use crate::helpers::{run_blocking, Authentication};
+use crate::snapshot;
 use crate::Data;
 
 const UNHEALTHY_KEY: &str = "_is_unhealthy";
@@ -13,33 +19,164 @@ pub fn services(cfg: &mut web::ServiceConfig) {
     cfg.service(get_health).service(change_healthyness);
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum CheckStatus {
+    Ok,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize)]
+struct Check {
+    status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl Check {
+    fn ok() -> Check {
+        Check { status: CheckStatus::Ok, message: None }
+    }
+
+    fn failed(message: impl ToString) -> Check {
+        Check { status: CheckStatus::Failed, message: Some(message.to_string()) }
+    }
+
+    fn skipped(message: impl ToString) -> Check {
+        Check { status: CheckStatus::Skipped, message: Some(message.to_string()) }
+    }
+
+    fn is_failed(&self) -> bool {
+        match self.status {
+            CheckStatus::Failed => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: &'static str,
+    checks: BTreeMap<&'static str, Check>,
+}
+
+/// Reports the status of the dependencies of the server: the lmdb environments, the update loops,
+/// the free disk space and the snapshots. Answers with a 503 status if any of them failed.
 #[get("/health", wrap = "Authentication::Private")]
 async fn get_health(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
-    let reader = data.db.main_read_txn()?;
+    {
+        let reader = data.main_read_txn()?;
+        let common_store = data.db.common_store();
+
+        if let Ok(Some(_)) = common_store.get::<_, Str, Unit>(&reader, UNHEALTHY_KEY) {
+            return Err(ResponseError::Maintenance);
+        }
+    }
 
-    let common_store = data.db.common_store();
+    let mut checks = BTreeMap::new();
+    checks.insert("environments", check_environments(&data));
+    checks.insert("updates", check_update_loops(&data));
+    checks.insert("diskSpace", check_disk_space(&data));
+    checks.insert("snapshots", check_snapshots(&data));
 
-    if let Ok(Some(_)) = common_store.get::<_, Str, Unit>(&reader, UNHEALTHY_KEY) {
-        return Err(ResponseError::Maintenance);
+    if checks.values().any(Check::is_failed) {
+        let response = HealthResponse { status: "unavailable", checks };
+        return Ok(HttpResponse::ServiceUnavailable().json(response));
     }
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(HttpResponse::Ok().json(HealthResponse { status: "available", checks }))
 }
 
-async fn set_healthy(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
-    let mut writer = data.db.main_write_txn()?;
-    let common_store = data.db.common_store();
-    common_store.delete::<_, Str>(&mut writer, UNHEALTHY_KEY)?;
-    writer.commit()?;
+fn check_environments(data: &Data) -> Check {
+    if let Err(e) = data.main_read_txn() {
+        return Check::failed(format!("the main environment cannot be read: {}", e));
+    }
+    if let Err(e) = data.update_read_txn() {
+        return Check::failed(format!("the update environment cannot be read: {}", e));
+    }
+    Check::ok()
+}
 
-    Ok(HttpResponse::Ok().finish())
+fn check_update_loops(data: &Data) -> Check {
+    let stopped = data.db.stopped_update_loops();
+    if stopped.is_empty() {
+        Check::ok()
+    } else {
+        Check::failed(format!("the updates of the indexes {} are not processed anymore", stopped.join(", ")))
+    }
+}
+
+fn check_disk_space(data: &Data) -> Check {
+    if data.health_min_disk_space == 0 {
+        return Check::skipped("no minimum disk space is set");
+    }
+
+    let db_path = match Path::new(&data.db_path).canonicalize() {
+        Ok(path) => path,
+        Err(e) => return Check::failed(format!("the database path cannot be resolved: {}", e)),
+    };
+
+    let mut sys = System::new();
+    sys.refresh_disks_list();
+
+    // the disk of the database is the one mounted on the closest parent directory
+    let disk = sys
+        .get_disks()
+        .iter()
+        .filter(|disk| db_path.starts_with(disk.get_mount_point()))
+        .max_by_key(|disk| disk.get_mount_point().as_os_str().len());
+
+    match disk {
+        Some(disk) if disk.get_available_space() < data.health_min_disk_space => Check::failed(format!(
+            "{} bytes are available on the database disk, the minimum is {} bytes",
+            disk.get_available_space(),
+            data.health_min_disk_space,
+        )),
+        Some(_) => Check::ok(),
+        None => Check::skipped("the disk of the database cannot be found"),
+    }
+}
+
+fn check_snapshots(data: &Data) -> Check {
+    let options = match &data.snapshot_options {
+        Some(options) => options,
+        None => return Check::skipped("the snapshots are disabled"),
+    };
+
+    let last_snapshot = match snapshot::last_snapshot_date(&options.dir) {
+        Ok(date) => date,
+        Err(e) => return Check::failed(format!("the snapshots directory cannot be read: {}", e)),
+    };
+
+    // a snapshot may take some time to be made, one missed interval is tolerated
+    let reference = last_snapshot.unwrap_or(data.started_at).max(data.started_at);
+    let max_age = chrono::Duration::from_std(options.interval * 2).unwrap_or_else(|_| chrono::Duration::max_value());
+    if Utc::now() - reference > max_age {
+        let message = match last_snapshot {
+            Some(date) => format!("the last snapshot was made at {}", date),
+            None => "no snapshot has been made".to_string(),
+        };
+        return Check::failed(message);
+    }
+
+    Check::ok()
 }
 
-async fn set_unhealthy(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
-    let mut writer = data.db.main_write_txn()?;
-    let common_store = data.db.common_store();
-    common_store.put::<_, Str, Unit>(&mut writer, UNHEALTHY_KEY, &())?;
-    writer.commit()?;
+/// Stores the maintenance flag, on a blocking thread as an import can hold the main writer.
+async fn set_maintenance(data: web::Data<Data>, maintenance: bool) -> Result<HttpResponse, ResponseError> {
+    run_blocking("health update", move || -> Result<_, ResponseError> {
+        let mut writer = data.main_write_txn()?;
+        let common_store = data.db.common_store();
+        if maintenance {
+            common_store.put::<_, Str, Unit>(&mut writer, UNHEALTHY_KEY, &())?;
+        } else {
+            common_store.delete::<_, Str>(&mut writer, UNHEALTHY_KEY)?;
+        }
+        writer.commit()?;
+        Ok(())
+    })
+    .await?;
 
     Ok(HttpResponse::Ok().finish())
 }
@@ -55,8 +192,8 @@ async fn change_healthyness(
     body: web::Json<HealthBody>,
 ) -> Result<HttpResponse, ResponseError> {
     if body.health {
-        set_healthy(data).await
+        set_maintenance(data, false).await
     } else {
-        set_unhealthy(data).await
+        set_maintenance(data, true).await
     }
 }
@@ -1,12 +1,17 @@
+use std::thread;
+
 use actix_web::{web, HttpResponse};
-use actix_web_macros::{delete, get, post, put};
+use actix_web_macros::{delete, get, patch, post, put};
 use chrono::{DateTime, Utc};
 use log::error;
 use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 
 use crate::error::ResponseError;
-use crate::helpers::Authentication;
+use crate::helpers::{run_blocking, Authentication};
+use crate::replication::{self, IndexCopyState, IndexCopyStatus, Remote};
+use crate::routes::index_template::{apply_template, matching_template};
+use crate::routes::rollover::rename_partition;
 use crate::routes::IndexParam;
 use crate::Data;
 
@@ -15,9 +20,13 @@ pub fn services(cfg: &mut web::ServiceConfig) {
         .service(get_index)
         .service(create_index)
         .service(update_index)
+        .service(rename_index)
+        .service(clone_index)
         .service(delete_index)
         .service(get_update_status)
-        .service(get_all_updates_status);
+        .service(get_all_updates_status)
+        .service(replicate_to)
+        .service(get_replication_status);
 }
 
 fn generate_uid() -> String {
@@ -41,7 +50,7 @@ struct IndexResponse {
 
 #[get("/indexes", wrap = "Authentication::Private")]
 async fn list_indexes(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let mut response = Vec::new();
 
@@ -103,7 +112,7 @@ async fn get_index(
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let name = index.main.name(&reader)?.ok_or(ResponseError::internal(
         "Impossible to get the name of an index",
@@ -175,45 +184,61 @@ async fn create_index(
             }
         },
     };
+    data.check_writable(&uid)?;
+
+    // the index is created with a write transaction on the main environment,
+    // waiting for it must not block the thread answering the requests
+    let body = body.into_inner();
+    let response = run_blocking("index creation", move || -> Result<_, ResponseError> {
+        let created_index = data
+            .db
+            .create_index(&uid)
+            .map_err(ResponseError::create_index)?;
+
+        let mut writer = data.main_write_txn()?;
+
+        let name = body.name.unwrap_or_else(|| uid.clone());
+        created_index.main.put_name(&mut writer, &name)?;
+
+        let created_at = created_index
+            .main
+            .created_at(&writer)?
+            .ok_or(ResponseError::internal("Impossible to read created at"))?;
+
+        let updated_at = created_index
+            .main
+            .updated_at(&writer)?
+            .ok_or(ResponseError::internal("Impossible to read updated at"))?;
+
+        if let Some(id) = &body.primary_key {
+            if let Some(mut schema) = created_index.main.schema(&writer)? {
+                schema
+                    .set_primary_key(id)
+                    .map_err(ResponseError::bad_request)?;
+                created_index.main.put_schema(&mut writer, &schema)?;
+            }
+        }
 
-    let created_index = data
-        .db
-        .create_index(&uid)
-        .map_err(ResponseError::create_index)?;
-
-    let mut writer = data.db.main_write_txn()?;
-
-    let name = body.name.as_ref().unwrap_or(&uid);
-    created_index.main.put_name(&mut writer, name)?;
-
-    let created_at = created_index
-        .main
-        .created_at(&writer)?
-        .ok_or(ResponseError::internal("Impossible to read created at"))?;
+        writer.commit()?;
 
-    let updated_at = created_index
-        .main
-        .updated_at(&writer)?
-        .ok_or(ResponseError::internal("Impossible to read updated at"))?;
-
-    if let Some(id) = body.primary_key.clone() {
-        if let Some(mut schema) = created_index.main.schema(&writer)? {
-            schema
-                .set_primary_key(&id)
-                .map_err(ResponseError::bad_request)?;
-            created_index.main.put_schema(&mut writer, &schema)?;
+        // the primary key given with the request takes precedence over the one of the template
+        let mut primary_key = body.primary_key;
+        if let Some(template) = matching_template(&data, &uid)? {
+            apply_template(&data, &created_index, &template)?;
+            primary_key = primary_key.or(template.primary_key);
         }
-    }
-
-    writer.commit()?;
 
-    Ok(HttpResponse::Created().json(IndexResponse {
-        name: name.to_string(),
-        uid,
-        created_at,
-        updated_at,
-        primary_key: body.primary_key.clone(),
-    }))
+        Ok(IndexResponse {
+            name,
+            uid,
+            created_at,
+            updated_at,
+            primary_key,
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(response))
 }
 
 #[derive(Debug, Deserialize)]
@@ -239,37 +264,42 @@ async fn update_index(
     path: web::Path<IndexParam>,
     body: web::Json<IndexCreateRequest>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let index = data.writable_index(&path.index_uid)?;
 
-    let mut writer = data.db.main_write_txn()?;
+    let data_cloned = data.clone();
+    let index_cloned = index.clone();
+    let body = body.into_inner();
+    run_blocking("index update", move || -> Result<_, ResponseError> {
+        let index = index_cloned;
+        let mut writer = data_cloned.main_write_txn()?;
 
-    if let Some(name) = &body.name {
-        index.main.put_name(&mut writer, name)?;
-    }
+        if let Some(name) = &body.name {
+            index.main.put_name(&mut writer, name)?;
+        }
 
-    if let Some(id) = body.primary_key.clone() {
-        if let Some(mut schema) = index.main.schema(&writer)? {
-            match schema.primary_key() {
-                Some(_) => {
-                    return Err(ResponseError::bad_request(
-                        "The primary key cannot be updated",
-                    ));
-                }
-                None => {
-                    schema.set_primary_key(&id)?;
-                    index.main.put_schema(&mut writer, &schema)?;
+        if let Some(id) = body.primary_key {
+            if let Some(mut schema) = index.main.schema(&writer)? {
+                match schema.primary_key() {
+                    Some(_) => {
+                        return Err(ResponseError::bad_request(
+                            "The primary key cannot be updated",
+                        ));
+                    }
+                    None => {
+                        schema.set_primary_key(&id)?;
+                        index.main.put_schema(&mut writer, &schema)?;
+                    }
                 }
             }
         }
-    }
 
-    index.main.put_updated_at(&mut writer)?;
-    writer.commit()?;
+        index.main.put_updated_at(&mut writer)?;
+        writer.commit()?;
+        Ok(())
+    })
+    .await?;
 
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let name = index.main.name(&reader)?.ok_or(ResponseError::internal(
         "Impossible to get the name of an index",
@@ -304,12 +334,157 @@ async fn update_index(
     }))
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RenameIndexRequest {
+    uid: String,
+}
+
+#[patch("/indexes/{index_uid}", wrap = "Authentication::Private")]
+async fn rename_index(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<RenameIndexRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    let uid = &body.uid;
+    if !uid.chars().all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_') {
+        return Err(ResponseError::InvalidIndexUid);
+    }
+    data.check_writable(&path.index_uid)?;
+    data.check_writable(uid)?;
+
+    // the state kept under the uid by the server is moved with the index
+    let data_cloned = data.clone();
+    let (from, to) = (path.index_uid.clone(), uid.clone());
+    let renamed = run_blocking("index renaming", move || {
+        let data = &data_cloned;
+        let renamed = data
+            .db
+            .rename_index_with(&from, &to, |writer| rename_partition(data, writer, &from, &to))
+            .map_err(ResponseError::bad_request)?;
+        if renamed {
+            data.usage.rename_index(&from, &to);
+            data.read_snapshots.rename_index(&from, &to);
+        }
+        Ok(renamed)
+    })
+    .await?;
+
+    if !renamed {
+        return Err(ResponseError::index_not_found(&path.index_uid));
+    }
+
+    let index = data
+        .db
+        .open_index(uid)
+        .ok_or(ResponseError::index_not_found(uid))?;
+
+    let reader = data.main_read_txn()?;
+
+    let name = index.main.name(&reader)?.ok_or(ResponseError::internal(
+        "Impossible to get the name of an index",
+    ))?;
+    let created_at = index
+        .main
+        .created_at(&reader)?
+        .ok_or(ResponseError::internal(
+            "Impossible to get the create date of an index",
+        ))?;
+    let updated_at = index
+        .main
+        .updated_at(&reader)?
+        .ok_or(ResponseError::internal(
+            "Impossible to get the last update date of an index",
+        ))?;
+
+    let primary_key = index
+        .main
+        .schema(&reader)?
+        .and_then(|schema| schema.primary_key().map(ToOwned::to_owned));
+
+    Ok(HttpResponse::Ok().json(IndexResponse {
+        name,
+        uid: uid.clone(),
+        created_at,
+        updated_at,
+        primary_key,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct CloneIndexRequest {
+    uid: String,
+}
+
+/// Creates an index with a copy of the documents and the settings of another one,
+/// without reindexing them, the updates still enqueued in the cloned index are not copied.
+#[post("/indexes/{index_uid}/clone", wrap = "Authentication::Private")]
+async fn clone_index(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<CloneIndexRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    let uid = body.into_inner().uid;
+    if !uid.chars().all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_') {
+        return Err(ResponseError::InvalidIndexUid);
+    }
+    // the mounted indexes can be cloned but not overwritten by a clone
+    data.check_writable(&uid)?;
+
+    let data_cloned = data.clone();
+    let (from, to) = (path.index_uid.clone(), uid.clone());
+    let index = run_blocking("index cloning", move || {
+        data_cloned.db.copy_index(&from, &to).map_err(ResponseError::create_index)
+    })
+    .await?
+    .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    let reader = data.main_read_txn()?;
+
+    let name = index.main.name(&reader)?.ok_or(ResponseError::internal(
+        "Impossible to get the name of an index",
+    ))?;
+    let created_at = index
+        .main
+        .created_at(&reader)?
+        .ok_or(ResponseError::internal(
+            "Impossible to get the create date of an index",
+        ))?;
+    let updated_at = index
+        .main
+        .updated_at(&reader)?
+        .ok_or(ResponseError::internal(
+            "Impossible to get the last update date of an index",
+        ))?;
+
+    let primary_key = index
+        .main
+        .schema(&reader)?
+        .and_then(|schema| schema.primary_key().map(ToOwned::to_owned));
+
+    Ok(HttpResponse::Created().json(IndexResponse {
+        name,
+        uid,
+        created_at,
+        updated_at,
+        primary_key,
+    }))
+}
+
 #[delete("/indexes/{index_uid}", wrap = "Authentication::Private")]
 async fn delete_index(
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    data.db.delete_index(&path.index_uid)?;
+    data.check_writable(&path.index_uid)?;
+
+    let data_cloned = data.clone();
+    let index_uid = path.index_uid.clone();
+    run_blocking("index deletion", move || -> Result<_, ResponseError> {
+        Ok(data_cloned.db.delete_index(&index_uid)?)
+    })
+    .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -333,7 +508,7 @@ async fn get_update_status(
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
-    let reader = data.db.update_read_txn()?;
+    let reader = data.update_read_txn()?;
 
     let status = index.update_status(&reader, path.update_id)?;
 
@@ -356,9 +531,76 @@ async fn get_all_updates_status(
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
-    let reader = data.db.update_read_txn()?;
+    let reader = data.update_read_txn()?;
 
     let response = index.all_updates_status(&reader)?;
 
     Ok(HttpResponse::Ok().json(response))
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ReplicateToRequest {
+    url: String,
+    api_key: Option<String>,
+    offset: Option<usize>,
+    chunk_size: Option<usize>,
+}
+
+#[post("/indexes/{index_uid}/replicate-to", wrap = "Authentication::Private")]
+async fn replicate_to(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<ReplicateToRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    data.db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    let body = body.into_inner();
+    let offset = body.offset.unwrap_or(0);
+    let chunk_size = body.chunk_size.unwrap_or(1000).max(1);
+
+    let status = {
+        let mut copies = data.index_copies.lock().map_err(ResponseError::internal)?;
+        if let Some(status) = copies.get(&path.index_uid) {
+            if status.state == IndexCopyState::Copying {
+                return Err(ResponseError::bad_request(format!(
+                    "Index {} is already being copied to {}",
+                    path.index_uid, status.target
+                )));
+            }
+        }
+
+        let status = IndexCopyStatus {
+            target: body.url.clone(),
+            state: IndexCopyState::Copying,
+            documents_sent: offset,
+            error: None,
+        };
+        copies.insert(path.index_uid.clone(), status.clone());
+        status
+    };
+
+    let target = Remote::new(body.url, body.api_key);
+    let data = data.get_ref().clone();
+    let index_uid = path.index_uid.clone();
+    thread::spawn(move || replication::copy_index(data, index_uid, target, offset, chunk_size));
+
+    Ok(HttpResponse::Accepted().json(status))
+}
+
+#[get("/indexes/{index_uid}/replicate-to", wrap = "Authentication::Private")]
+async fn get_replication_status(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let copies = data.index_copies.lock().map_err(ResponseError::internal)?;
+    match copies.get(&path.index_uid) {
+        Some(status) => Ok(HttpResponse::Ok().json(status)),
+        None => Err(ResponseError::not_found(format!(
+            "Copy of the index {}",
+            path.index_uid
+        ))),
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use actix_web_macros::{delete, get, put};
+use heed::types::{SerdeJson, Str};
+use meilisearch_core::settings::{Settings, SettingsUpdate};
+use meilisearch_core::Index;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ResponseError;
+use crate::helpers::{run_blocking, Authentication};
+use crate::Data;
+
+const INDEX_TEMPLATES_KEY: &str = "index-templates";
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_templates)
+        .service(get_template)
+        .service(update_template)
+        .service(delete_template);
+}
+
+/// The configuration given to the indexes created with an uid matching the pattern,
+/// a `*` in the pattern matches any sequence of characters, e.g. `logs-*`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IndexTemplate {
+    pub uid_pattern: String,
+    #[serde(default)]
+    pub primary_key: Option<String>,
+    /// Kept as sent, the settings missing from it must not be reset when they are applied.
+    #[serde(default)]
+    pub settings: Option<Value>,
+}
+
+impl IndexTemplate {
+    fn settings_update(&self) -> Result<Option<SettingsUpdate>, ResponseError> {
+        match &self.settings {
+            Some(settings) => {
+                let settings: Settings = serde_json::from_value(settings.clone())
+                    .map_err(|e| ResponseError::bad_parameter("settings", e))?;
+                Ok(Some(settings.into_update()?))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TemplateParam {
+    template_name: String,
+}
+
+/// Returns whether the uid matches the pattern, the `*` of which match any sequence of characters.
+fn uid_matches(pattern: &str, uid: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    if !uid.starts_with(first) {
+        return false;
+    }
+
+    let mut rest = &uid[first.len()..];
+
+    let mut parts: Vec<_> = parts.collect();
+    let last = match parts.pop() {
+        Some(last) => last,
+        None => return rest.is_empty(),
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+fn index_templates(data: &Data) -> Result<BTreeMap<String, IndexTemplate>, ResponseError> {
+    let reader = data.main_read_txn()?;
+    let templates = data
+        .db
+        .common_store()
+        .get::<_, Str, SerdeJson<BTreeMap<String, IndexTemplate>>>(&reader, INDEX_TEMPLATES_KEY)?;
+    Ok(templates.unwrap_or_default())
+}
+
+fn put_index_templates(data: &Data, templates: &BTreeMap<String, IndexTemplate>) -> Result<(), ResponseError> {
+    let mut writer = data.main_write_txn()?;
+    data.db
+        .common_store()
+        .put::<_, Str, SerdeJson<BTreeMap<String, IndexTemplate>>>(&mut writer, INDEX_TEMPLATES_KEY, templates)?;
+    writer.commit()?;
+    Ok(())
+}
+
+/// Returns the template with the longest pattern matching the uid, the most specific one,
+/// the templates with patterns of the same length are ordered by name.
+pub fn matching_template(data: &Data, uid: &str) -> Result<Option<IndexTemplate>, ResponseError> {
+    let template = index_templates(data)?
+        .into_iter()
+        .map(|(_, template)| template)
+        .filter(|template| uid_matches(&template.uid_pattern, uid))
+        .fold(None, |best: Option<IndexTemplate>, template| match best {
+            Some(best) if best.uid_pattern.len() >= template.uid_pattern.len() => Some(best),
+            _ => Some(template),
+        });
+    Ok(template)
+}
+
+/// Gives the primary key of the template to the index if it doesn't have one yet
+/// and enqueues the settings of the template as an update of the index.
+pub fn apply_template(data: &Data, index: &Index, template: &IndexTemplate) -> Result<(), ResponseError> {
+    if let Some(primary_key) = &template.primary_key {
+        let mut writer = data.main_write_txn()?;
+        if let Some(mut schema) = index.main.schema(&writer)? {
+            if schema.primary_key().is_none() {
+                schema
+                    .set_primary_key(primary_key)
+                    .map_err(ResponseError::bad_request)?;
+                index.main.put_schema(&mut writer, &schema)?;
+            }
+        }
+        writer.commit()?;
+    }
+
+    if let Some(settings) = template.settings_update()? {
+        let mut writer = data.update_write_txn()?;
+        index.settings_update(&mut writer, settings)?;
+        writer.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Creates the index configured by the template matching the uid, if there is one.
+/// Must not be called on the threads answering the requests.
+pub fn create_index_from_template(data: &Data, uid: &str) -> Result<Option<Index>, ResponseError> {
+    let template = match matching_template(data, uid)? {
+        Some(template) => template,
+        None => return Ok(None),
+    };
+
+    if !uid.chars().all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_') {
+        return Err(ResponseError::InvalidIndexUid);
+    }
+
+    let index = data.db.create_index(uid).map_err(ResponseError::create_index)?;
+
+    let mut writer = data.main_write_txn()?;
+    index.main.put_name(&mut writer, uid)?;
+    writer.commit()?;
+
+    apply_template(data, &index, &template)?;
+
+    Ok(Some(index))
+}
+
+#[get("/index-templates", wrap = "Authentication::Private")]
+async fn list_templates(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
+    Ok(HttpResponse::Ok().json(index_templates(&data)?))
+}
+
+#[get("/index-templates/{template_name}", wrap = "Authentication::Private")]
+async fn get_template(
+    data: web::Data<Data>,
+    path: web::Path<TemplateParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let template = index_templates(&data)?
+        .remove(&path.template_name)
+        .ok_or(ResponseError::NotFound(format!("Index template {}", path.template_name)))?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+/// Creates or replaces the template, it only applies to the indexes created afterward.
+#[put("/index-templates/{template_name}", wrap = "Authentication::Private")]
+async fn update_template(
+    data: web::Data<Data>,
+    path: web::Path<TemplateParam>,
+    body: web::Json<IndexTemplate>,
+) -> Result<HttpResponse, ResponseError> {
+    let template = body.into_inner();
+    if template.uid_pattern.is_empty() {
+        return Err(ResponseError::bad_parameter("uidPattern", "the pattern can't be empty"));
+    }
+    // the invalid settings are refused now rather than when an index is created
+    template.settings_update()?;
+
+    let template_name = path.into_inner().template_name;
+    let response = template.clone();
+    run_blocking("index template update", move || -> Result<_, ResponseError> {
+        let mut templates = index_templates(&data)?;
+        templates.insert(template_name, template);
+        put_index_templates(&data, &templates)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[delete("/index-templates/{template_name}", wrap = "Authentication::Private")]
+async fn delete_template(
+    data: web::Data<Data>,
+    path: web::Path<TemplateParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let template_name = path.into_inner().template_name;
+    run_blocking("index template deletion", move || -> Result<_, ResponseError> {
+        let mut templates = index_templates(&data)?;
+        if templates.remove(&template_name).is_none() {
+            return Err(ResponseError::NotFound(format!("Index template {}", template_name)));
+        }
+        put_index_templates(&data, &templates)
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
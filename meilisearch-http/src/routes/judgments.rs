@@ -0,0 +1,195 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use actix_web_macros::{delete, get, post, put};
+use chrono::{DateTime, Utc};
+use meilisearch_core::judgments::{Judgment, JudgmentsRun, Relevancy};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::ResponseError;
+use crate::helpers::meilisearch::IndexSearchExt;
+use crate::helpers::{run_blocking, Authentication};
+use crate::routes::IndexParam;
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_judgments)
+        .service(update_judgments)
+        .service(delete_judgments)
+        .service(run_judgments);
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JudgmentResult {
+    query: String,
+    hits: Vec<String>,
+    #[serde(flatten)]
+    relevancy: Relevancy,
+    /// The relevancy gained since the last run, missing for the new queries.
+    delta: Option<Relevancy>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JudgmentsRunResponse {
+    judgments: Vec<JudgmentResult>,
+    mean: Relevancy,
+    mean_delta: Option<Relevancy>,
+    previous_run_at: Option<DateTime<Utc>>,
+}
+
+fn delta(current: Relevancy, previous: Relevancy) -> Relevancy {
+    Relevancy {
+        precision: current.precision - previous.precision,
+        recall: current.recall - previous.recall,
+    }
+}
+
+fn document_id(value: &Value) -> String {
+    match value {
+        Value::String(id) => id.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[get("/indexes/{index_uid}/judgments", wrap = "Authentication::Private")]
+async fn get_judgments(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data
+        .db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    let reader = data.main_read_txn()?;
+    let judgments = index.main.judgments(&reader)?.unwrap_or_default();
+
+    Ok(HttpResponse::Ok().json(judgments))
+}
+
+/// Replaces the judgments of the index, the relevancies of the queries
+/// that are kept are still compared to their last run.
+#[put("/indexes/{index_uid}/judgments", wrap = "Authentication::Private")]
+async fn update_judgments(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<Vec<Judgment>>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data.writable_index(&path.index_uid)?;
+
+    let judgments = body.into_inner();
+    if judgments.iter().any(|judgment| judgment.expected.is_empty()) {
+        return Err(ResponseError::bad_parameter("expected", "the judgments must expect at least one document"));
+    }
+
+    let response = judgments.clone();
+    run_blocking("judgments update", move || -> Result<_, ResponseError> {
+        let mut writer = data.main_write_txn()?;
+        index.main.put_judgments(&mut writer, &judgments)?;
+        writer.commit()?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[delete("/indexes/{index_uid}/judgments", wrap = "Authentication::Private")]
+async fn delete_judgments(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data.writable_index(&path.index_uid)?;
+
+    run_blocking("judgments deletion", move || -> Result<_, ResponseError> {
+        let mut writer = data.main_write_txn()?;
+        index.main.delete_judgments(&mut writer)?;
+        writer.commit()?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Searches the queries of the judgments and measures the precision and the recall of their hits,
+/// the relevancies are compared to the ones of the last run, then replace them.
+#[post("/indexes/{index_uid}/judgments/run", wrap = "Authentication::Private")]
+async fn run_judgments(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data.writable_index(&path.index_uid)?;
+
+    let data_cloned = data.clone();
+    let index_cloned = index.clone();
+    let (response, run) = data
+        .search_pool
+        .run(move || {
+            let reader = data_cloned.main_read_txn()?;
+            let judgments = index_cloned.main.judgments(&reader)?.unwrap_or_default();
+            let previous_run = index_cloned.main.judgments_run(&reader)?;
+            let primary_key = index_cloned
+                .main
+                .schema(&reader)?
+                .and_then(|schema| schema.primary_key().map(str::to_string));
+
+            let mut results = Vec::with_capacity(judgments.len());
+            let mut relevancies = BTreeMap::new();
+            for judgment in judgments {
+                let mut search_builder = index_cloned.new_search(judgment.query.clone());
+                search_builder.limit(judgment.limit());
+                if let Some(filters) = &judgment.filters {
+                    search_builder.filters(filters.clone());
+                }
+                if let Some(primary_key) = &primary_key {
+                    search_builder.add_retrievable_field(primary_key.clone());
+                }
+
+                let result = search_builder.search(&reader)?;
+                let hits: Vec<_> = result
+                    .hits
+                    .iter()
+                    .filter_map(|hit| primary_key.as_ref().and_then(|key| hit.document.get(key)))
+                    .map(document_id)
+                    .collect();
+
+                let relevancy = Relevancy::measure(&judgment.expected, &hits);
+                let previous = previous_run.as_ref().and_then(|run| run.relevancies.get(&judgment.query));
+                relevancies.insert(judgment.query.clone(), relevancy);
+                results.push(JudgmentResult {
+                    query: judgment.query,
+                    hits,
+                    relevancy,
+                    delta: previous.map(|previous| delta(relevancy, *previous)),
+                });
+            }
+
+            let mean = Relevancy::mean(relevancies.values());
+            let mean_delta = previous_run
+                .as_ref()
+                .map(|run| delta(mean, Relevancy::mean(run.relevancies.values())));
+
+            let response = JudgmentsRunResponse {
+                judgments: results,
+                mean,
+                mean_delta,
+                previous_run_at: previous_run.map(|run| run.ran_at),
+            };
+            Ok((response, JudgmentsRun { ran_at: Utc::now(), relevancies }))
+        })
+        .await?;
+
+    run_blocking("judgments run", move || -> Result<_, ResponseError> {
+        let mut writer = data.main_write_txn()?;
+        index.main.put_judgments_run(&mut writer, &run)?;
+        writer.commit()?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -1,15 +1,40 @@
-use actix_web::{get, HttpResponse};
+use std::time::Duration;
+
+use actix_web::{get, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use crossbeam_channel::Receiver;
+use log::{info, warn};
+use meilisearch_core::store::IdempotentUpdate;
+use meilisearch_core::{Index, UpdateT};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::ResponseError;
+use crate::helpers::background::wait_or_stop;
+use crate::Data;
 
+pub mod aggregation;
+pub mod allowlist;
+pub mod changes;
+pub mod debug;
 pub mod document;
 pub mod health;
 pub mod index;
+pub mod index_template;
+pub mod judgments;
 pub mod key;
+pub mod quota;
+pub mod rollover;
 pub mod search;
 pub mod setting;
 pub mod stats;
 pub mod stop_words;
 pub mod synonym;
+pub mod table;
+pub mod update_group;
+pub mod upload_session;
+pub mod usage;
+pub mod words;
 
 #[derive(Deserialize)]
 pub struct IndexParam {
@@ -28,6 +53,160 @@ impl IndexUpdateResponse {
     }
 }
 
+/// The priority of an update, the `high` priority updates are processed before the `normal`
+/// ones enqueued earlier when the order doesn't change the result: when they touch other
+/// documents, or other settings. They otherwise wait for the updates they depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdatePriority {
+    Normal,
+    High,
+}
+
+impl Default for UpdatePriority {
+    fn default() -> UpdatePriority {
+        UpdatePriority::Normal
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UpdatePriorityQuery {
+    #[serde(default)]
+    priority: UpdatePriority,
+}
+
+/// The `Idempotency-Key` header of a write request, a request retried with the same key
+/// returns the update enqueued the first time instead of enqueuing it again.
+pub struct IdempotencyKey {
+    key: String,
+    request_digest: [u8; 32],
+}
+
+impl IdempotencyKey {
+    /// The longest key accepted, in bytes.
+    pub const MAX_LENGTH: usize = 255;
+    /// The time after which a key is forgotten and can be used for another request.
+    pub const EXPIRY_HOURS: i64 = 24;
+    /// The time between two removals of the expired keys, they are ignored until they are removed.
+    pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+    pub fn from_request(req: &HttpRequest, payload: &impl Serialize) -> Result<Option<IdempotencyKey>, ResponseError> {
+        let key = match req.headers().get("Idempotency-Key") {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+
+        let key = key
+            .to_str()
+            .ok()
+            .filter(|key| !key.is_empty() && key.len() <= IdempotencyKey::MAX_LENGTH)
+            .ok_or_else(|| ResponseError::bad_request(format!(
+                "The Idempotency-Key header must be a visible ASCII string of 1 to {} characters",
+                IdempotencyKey::MAX_LENGTH,
+            )))?;
+
+        // the same key sent to another route or with another body identifies another request,
+        // the parts are separated by a null byte that can't appear in a path nor a query string
+        let payload = serde_json::to_vec(payload).map_err(ResponseError::internal)?;
+        let mut hasher = Sha256::new();
+        for part in &[req.method().as_str().as_bytes(), req.path().as_bytes(), req.query_string().as_bytes()] {
+            hasher.input(part);
+            hasher.input(b"\0");
+        }
+        hasher.input(&payload);
+
+        let mut request_digest = [0; 32];
+        request_digest.copy_from_slice(&hasher.result());
+        Ok(Some(IdempotencyKey { key: key.to_string(), request_digest }))
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn request_digest(&self) -> &[u8; 32] {
+        &self.request_digest
+    }
+
+    fn expired_before() -> DateTime<Utc> {
+        Utc::now() - chrono::Duration::hours(IdempotencyKey::EXPIRY_HOURS)
+    }
+
+    /// Returns the id of the update already enqueued with this key, fails if the key
+    /// has been used for another request.
+    pub fn enqueued_update(&self, index: &Index, reader: &heed::RoTxn<UpdateT>) -> Result<Option<u64>, ResponseError> {
+        match index.updates_idempotency_keys.update(reader, &self.key, IdempotencyKey::expired_before())? {
+            Some(update) if update.request_digest == self.request_digest => Ok(Some(update.update_id)),
+            Some(_) => Err(ResponseError::conflict(format!(
+                "The idempotency key {:?} has already been used for another request",
+                self.key,
+            ))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Enqueues an update with `enqueue`, unless an update has already been enqueued with the
+/// same idempotency key, the id of this update is then returned. The key is stored in the
+/// transaction of the update, a key is never stored without its update.
+pub fn enqueue_once(
+    index: &Index,
+    writer: &mut heed::RwTxn<UpdateT>,
+    idempotency_key: Option<&IdempotencyKey>,
+    enqueue: impl FnOnce(&mut heed::RwTxn<UpdateT>) -> Result<u64, ResponseError>,
+) -> Result<u64, ResponseError> {
+    let idempotency_key = match idempotency_key {
+        Some(idempotency_key) => idempotency_key,
+        None => return enqueue(writer),
+    };
+
+    if let Some(update_id) = idempotency_key.enqueued_update(index, writer)? {
+        return Ok(update_id);
+    }
+
+    let update_id = enqueue(writer)?;
+    let update = IdempotentUpdate {
+        update_id,
+        request_digest: idempotency_key.request_digest,
+        enqueued_at: Utc::now(),
+    };
+    index.updates_idempotency_keys.put_update(writer, &idempotency_key.key, &update)?;
+    Ok(update_id)
+}
+
+/// Periodically removes the expired idempotency keys of the indexes, until the server stops.
+/// The keys are scanned to find the expired ones, an index at a time in its own transaction.
+pub fn remove_expired_idempotency_keys(data: Data, stop: Receiver<()>) {
+    loop {
+        for uid in data.db.indexes_uids() {
+            let index = match data.db.open_index(&uid) {
+                Some(index) => index,
+                None => continue,
+            };
+
+            let expired_before = IdempotencyKey::expired_before();
+            let result = data.db.update_write_txn().and_then(|mut writer| {
+                let removed = index.updates_idempotency_keys.remove_expired(&mut writer, expired_before)?;
+                writer.commit()?;
+                Ok(removed)
+            });
+
+            match result {
+                Ok(removed) if removed > 0 => {
+                    info!("{} expired idempotency keys of the {} index removed", removed, uid)
+                }
+                Ok(_) => (),
+                Err(e) => warn!("Impossible to remove the expired idempotency keys of the {} index: {}", uid, e),
+            }
+        }
+
+        if wait_or_stop(&stop, IdempotencyKey::CLEANUP_INTERVAL) {
+            break;
+        }
+    }
+}
+
 #[get("/")]
 pub async fn load_html() -> HttpResponse {
     HttpResponse::Ok()
@@ -35,6 +214,16 @@ pub async fn load_html() -> HttpResponse {
         .body(include_str!("../../public/interface.html").to_string())
 }
 
+/// The OpenAPI specification of the routes, generated from their declarations at build time.
+pub const OPENAPI_DOCUMENT: &str = include_str!(concat!(env!("OUT_DIR"), "/openapi.json"));
+
+#[get("/openapi.json")]
+pub async fn load_openapi() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(OPENAPI_DOCUMENT)
+}
+
 #[get("/bulma.min.css")]
 pub async fn load_css() -> HttpResponse {
     HttpResponse::Ok()
@@ -0,0 +1,118 @@
+use actix_web::{web, HttpResponse};
+use actix_web_macros::{delete, get, put};
+use meilisearch_core::{Index, MainT};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ResponseError;
+use crate::helpers::{run_blocking, Authentication};
+use crate::routes::IndexParam;
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_quota)
+        .service(update_quota)
+        .service(delete_quota);
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct QuotaBody {
+    max_data_size: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QuotaResponse {
+    /// The quota of the index, or the one of the server if the index has none.
+    max_data_size: Option<u64>,
+    /// The size of the processed data of the index when its last update was processed,
+    /// it is only measured while the index has a quota.
+    data_size: Option<u64>,
+}
+
+/// Refuses the documents additions to an index which data size reached its quota, the searches
+/// and the deletions are never refused. The documents enqueued since the data size was measured
+/// are counted, the additions sent before the next update is processed would be accepted otherwise.
+///
+/// The additions checked at the same time can all be accepted, the ones processed once the index
+/// is over its quota then fail when they are applied.
+pub fn check_data_quota(
+    data: &Data,
+    index: &Index,
+    reader: &heed::RoTxn<MainT>,
+    index_uid: &str,
+) -> Result<(), ResponseError> {
+    if let Some(max_data_size) = data.max_data_size(reader, index)? {
+        let update_reader = data.update_read_txn()?;
+        let pending_size = index.updates.payloads_size(&update_reader)?;
+        drop(update_reader);
+
+        let data_size = index.main.data_size(reader)?.unwrap_or_default();
+        if data_size.saturating_add(pending_size) >= max_data_size {
+            return Err(ResponseError::QuotaExceeded(index_uid.to_string(), max_data_size));
+        }
+    }
+
+    Ok(())
+}
+
+fn quota_response(data: &Data, index: &Index) -> Result<QuotaResponse, ResponseError> {
+    let reader = data.main_read_txn()?;
+    let max_data_size = data.max_data_size(&reader, index)?;
+    let data_size = index.main.data_size(&reader)?;
+    Ok(QuotaResponse { max_data_size, data_size })
+}
+
+#[get("/indexes/{index_uid}/quota", wrap = "Authentication::Private")]
+async fn get_quota(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data
+        .db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    Ok(HttpResponse::Ok().json(quota_response(&data, &index)?))
+}
+
+/// Sets the quota of the index, its size is measured right away
+/// so that the next documents addition is checked against it.
+#[put("/indexes/{index_uid}/quota", wrap = "Authentication::Private")]
+async fn update_quota(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<QuotaBody>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data.writable_index(&path.index_uid)?;
+
+    let response = run_blocking("quota update", move || -> Result<_, ResponseError> {
+        let mut writer = data.main_write_txn()?;
+        index.main.put_max_data_size(&mut writer, body.max_data_size)?;
+        data.compute_data_size(&mut writer, &path.index_uid)?;
+        writer.commit()?;
+        quota_response(&data, &index)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Removes the quota of the index, the quota of the server applies again if there is one.
+#[delete("/indexes/{index_uid}/quota", wrap = "Authentication::Private")]
+async fn delete_quota(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data.writable_index(&path.index_uid)?;
+
+    run_blocking("quota deletion", move || -> Result<_, ResponseError> {
+        let mut writer = data.main_write_txn()?;
+        index.main.delete_max_data_size(&mut writer)?;
+        writer.commit()?;
+        Ok(())
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use actix_web_macros::{delete, get, post, put};
+use chrono::{DateTime, Duration, Utc};
+use heed::types::{SerdeJson, Str};
+use meilisearch_core::{MResult, MainT};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ResponseError;
+use crate::helpers::{run_blocking, Authentication};
+use crate::routes::index_template::{apply_template, matching_template};
+use crate::Data;
+
+const ROLLOVER_ALIASES_KEY: &str = "rollover-aliases";
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(list_aliases)
+        .service(get_alias)
+        .service(update_alias)
+        .service(delete_alias)
+        .service(rollover);
+}
+
+/// The conditions on which the write index of an alias is rolled over,
+/// with the retention window of the partitions it isn't written to anymore.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RolloverConditions {
+    #[serde(default)]
+    max_documents: Option<u64>,
+    #[serde(default)]
+    max_size: Option<u64>,
+    #[serde(default)]
+    retention_days: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Partition {
+    uid: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RolloverAlias {
+    conditions: RolloverConditions,
+    write_index: String,
+    partitions: Vec<Partition>,
+}
+
+#[derive(Deserialize)]
+struct AliasParam {
+    alias: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RolloverQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RolloverResponse {
+    rolled_over: bool,
+    write_index: String,
+    deleted_indexes: Vec<String>,
+}
+
+fn rollover_aliases(data: &Data) -> Result<BTreeMap<String, RolloverAlias>, ResponseError> {
+    let reader = data.main_read_txn()?;
+    let aliases = data
+        .db
+        .common_store()
+        .get::<_, Str, SerdeJson<BTreeMap<String, RolloverAlias>>>(&reader, ROLLOVER_ALIASES_KEY)?;
+    Ok(aliases.unwrap_or_default())
+}
+
+fn put_rollover_aliases(data: &Data, aliases: &BTreeMap<String, RolloverAlias>) -> Result<(), ResponseError> {
+    let mut writer = data.main_write_txn()?;
+    data.db
+        .common_store()
+        .put::<_, Str, SerdeJson<BTreeMap<String, RolloverAlias>>>(&mut writer, ROLLOVER_ALIASES_KEY, aliases)?;
+    writer.commit()?;
+    Ok(())
+}
+
+/// Returns the uid of the index the documents added to the alias must go to,
+/// the uid itself if it isn't a rollover alias.
+pub fn resolve_write_alias(data: &Data, uid: &str) -> Result<String, ResponseError> {
+    match rollover_aliases(data)?.remove(uid) {
+        Some(alias) => Ok(alias.write_index),
+        None => Ok(uid.to_string()),
+    }
+}
+
+/// Replaces the uid of a renamed index in the partitions of the aliases, with the
+/// transaction renaming it.
+pub fn rename_partition(data: &Data, writer: &mut heed::RwTxn<MainT>, from: &str, to: &str) -> MResult<()> {
+    let store = data.db.common_store();
+    let aliases = store.get::<_, Str, SerdeJson<BTreeMap<String, RolloverAlias>>>(writer, ROLLOVER_ALIASES_KEY)?;
+    let mut aliases = match aliases {
+        Some(aliases) => aliases,
+        None => return Ok(()),
+    };
+
+    let mut renamed = false;
+    for alias in aliases.values_mut() {
+        if alias.write_index == from {
+            alias.write_index = to.to_string();
+            renamed = true;
+        }
+        for partition in alias.partitions.iter_mut().filter(|partition| partition.uid == from) {
+            partition.uid = to.to_string();
+            renamed = true;
+        }
+    }
+
+    if renamed {
+        store.put::<_, Str, SerdeJson<BTreeMap<String, RolloverAlias>>>(writer, ROLLOVER_ALIASES_KEY, &aliases)?;
+    }
+
+    Ok(())
+}
+
+/// Creates a new partition of the alias, suffixed by its creation time,
+/// configured by the index template matching its uid if there is one.
+fn create_partition(data: &Data, alias: &str) -> Result<Partition, ResponseError> {
+    let created_at = Utc::now();
+    let base_uid = format!("{}-{}", alias, created_at.format("%Y%m%d%H%M%S"));
+
+    let mut uid = base_uid.clone();
+    let mut suffix = 1;
+    while data.db.open_index(&uid).is_some() {
+        uid = format!("{}-{}", base_uid, suffix);
+        suffix += 1;
+    }
+    data.check_writable(&uid)?;
+
+    let index = data.db.create_index(&uid).map_err(ResponseError::create_index)?;
+    let mut writer = data.main_write_txn()?;
+    index.main.put_name(&mut writer, &uid)?;
+    writer.commit()?;
+
+    if let Some(template) = matching_template(data, &uid)? {
+        apply_template(data, &index, &template)?;
+    }
+
+    Ok(Partition { uid, created_at })
+}
+
+fn conditions_reached(data: &Data, alias: &RolloverAlias) -> Result<bool, ResponseError> {
+    let index = match data.db.open_index(&alias.write_index) {
+        Some(index) => index,
+        // a write index deleted by hand is replaced
+        None => return Ok(true),
+    };
+
+    if let Some(max_documents) = alias.conditions.max_documents {
+        let reader = data.main_read_txn()?;
+        if index.main.number_of_documents(&reader)? >= max_documents {
+            return Ok(true);
+        }
+    }
+
+    if let Some(max_size) = alias.conditions.max_size {
+        let reader = data.main_read_txn()?;
+        let update_reader = data.update_read_txn()?;
+        if index.stores_sizes(&reader, &update_reader)?.total_bytes() >= max_size {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Deletes the partitions, but the write one, created before the retention window.
+fn delete_expired_partitions(data: &Data, alias: &mut RolloverAlias) -> Result<Vec<String>, ResponseError> {
+    let retention_days = match alias.conditions.retention_days {
+        Some(retention_days) => retention_days,
+        None => return Ok(Vec::new()),
+    };
+
+    let expiration = Utc::now() - Duration::days(i64::from(retention_days));
+    let mut deleted = Vec::new();
+    let mut partitions = Vec::new();
+
+    for partition in alias.partitions.drain(..) {
+        if partition.uid != alias.write_index && partition.created_at <= expiration {
+            data.db.delete_index(&partition.uid)?;
+            deleted.push(partition.uid);
+        } else {
+            partitions.push(partition);
+        }
+    }
+
+    alias.partitions = partitions;
+    Ok(deleted)
+}
+
+#[get("/rollover-aliases", wrap = "Authentication::Private")]
+async fn list_aliases(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
+    Ok(HttpResponse::Ok().json(rollover_aliases(&data)?))
+}
+
+#[get("/rollover-aliases/{alias}", wrap = "Authentication::Private")]
+async fn get_alias(
+    data: web::Data<Data>,
+    path: web::Path<AliasParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let alias = rollover_aliases(&data)?
+        .remove(&path.alias)
+        .ok_or(ResponseError::NotFound(format!("Rollover alias {}", path.alias)))?;
+
+    Ok(HttpResponse::Ok().json(alias))
+}
+
+/// Creates the alias, with its first partition, or replaces its rollover conditions.
+#[put("/rollover-aliases/{alias}", wrap = "Authentication::Private")]
+async fn update_alias(
+    data: web::Data<Data>,
+    path: web::Path<AliasParam>,
+    body: web::Json<RolloverConditions>,
+) -> Result<HttpResponse, ResponseError> {
+    let name = path.into_inner().alias;
+    if !name.chars().all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_') {
+        return Err(ResponseError::InvalidIndexUid);
+    }
+    if data.db.open_index(&name).is_some() {
+        return Err(ResponseError::bad_request(format!("An index is already named {}", name)));
+    }
+
+    let conditions = body.into_inner();
+    let response = run_blocking("rollover alias update", move || -> Result<_, ResponseError> {
+        let _lock = data.rollover_lock.lock().map_err(ResponseError::internal)?;
+        let mut aliases = rollover_aliases(&data)?;
+
+        let alias = match aliases.remove(&name) {
+            Some(alias) => RolloverAlias { conditions, ..alias },
+            None => {
+                let partition = create_partition(&data, &name)?;
+                RolloverAlias {
+                    conditions,
+                    write_index: partition.uid.clone(),
+                    partitions: vec![partition],
+                }
+            }
+        };
+
+        aliases.insert(name, alias.clone());
+        put_rollover_aliases(&data, &aliases)?;
+        Ok(alias)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Deletes the alias, its partitions are kept.
+#[delete("/rollover-aliases/{alias}", wrap = "Authentication::Private")]
+async fn delete_alias(
+    data: web::Data<Data>,
+    path: web::Path<AliasParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let name = path.into_inner().alias;
+    run_blocking("rollover alias deletion", move || -> Result<_, ResponseError> {
+        let _lock = data.rollover_lock.lock().map_err(ResponseError::internal)?;
+        let mut aliases = rollover_aliases(&data)?;
+        if aliases.remove(&name).is_none() {
+            return Err(ResponseError::NotFound(format!("Rollover alias {}", name)));
+        }
+        put_rollover_aliases(&data, &aliases)
+    })
+    .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Rolls the alias over to a new partition if one of its conditions is reached, or if
+/// it is forced, then deletes the partitions older than the retention window.
+#[post("/rollover-aliases/{alias}/rollover", wrap = "Authentication::Private")]
+async fn rollover(
+    data: web::Data<Data>,
+    path: web::Path<AliasParam>,
+    params: web::Query<RolloverQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let name = path.into_inner().alias;
+    let force = params.force;
+    let response = run_blocking("rollover", move || -> Result<_, ResponseError> {
+        let _lock = data.rollover_lock.lock().map_err(ResponseError::internal)?;
+        let mut aliases = rollover_aliases(&data)?;
+        let alias = aliases
+            .get_mut(&name)
+            .ok_or(ResponseError::NotFound(format!("Rollover alias {}", name)))?;
+
+        let rolled_over = force || conditions_reached(&data, alias)?;
+        if rolled_over {
+            let partition = create_partition(&data, &name)?;
+            alias.write_index = partition.uid.clone();
+            alias.partitions.push(partition);
+        }
+
+        let deleted_indexes = delete_expired_partitions(&data, alias)?;
+        let response = RolloverResponse {
+            rolled_over,
+            write_index: alias.write_index.clone(),
+            deleted_indexes,
+        };
+
+        put_rollover_aliases(&data, &aliases)?;
+        Ok(response)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -1,57 +1,325 @@
-use std::collections::{HashSet, HashMap};
+use std::collections::{BTreeMap, HashSet, HashMap};
+use std::time::{Duration, Instant};
 
 use log::warn;
 use actix_web::web;
-use actix_web::HttpResponse;
-use actix_web_macros::get;
-use serde::Deserialize;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use actix_web_macros::{get, post};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::error::{ResponseError, FacetCountError};
-use crate::helpers::meilisearch::IndexSearchExt;
-use crate::helpers::Authentication;
+use crate::helpers::meilisearch::{IndexSearchExt, MatchesPositionUnit, SearchHit, SearchResult};
+use crate::helpers::{Authentication, TraceContext};
 use crate::routes::IndexParam;
 use crate::Data;
 
 use meilisearch_core::facets::FacetFilter;
+use meilisearch_core::settings::{resolve_attribute_alias, RankingRule};
+use meilisearch_core::{CancellationToken, MainT, GEOHASH_MAX_PRECISION};
 use meilisearch_schema::{Schema, FieldId};
 
 pub fn services(cfg: &mut web::ServiceConfig) {
-    cfg.service(search_with_url_query);
+    cfg.service(search_multiple_indexes).service(search_with_url_query);
 }
 
+/// Cancels the search when dropped, actix drops the future of a request
+/// once its client has disconnected, before the response is sent.
+struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub(crate) struct SearchQuery {
+    pub(crate) q: String,
+    pub(crate) offset: Option<usize>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) attributes_to_retrieve: Option<String>,
+    pub(crate) attributes_to_crop: Option<String>,
+    pub(crate) crop_length: Option<usize>,
+    pub(crate) attributes_to_highlight: Option<String>,
+    pub(crate) filters: Option<String>,
+    pub(crate) matches: Option<bool>,
+    pub(crate) matches_position_unit: Option<MatchesPositionUnit>,
+    pub(crate) facet_filters: Option<String>,
+    pub(crate) facets: Option<String>,
+    pub(crate) disjunctive_facets: Option<bool>,
+    pub(crate) exhaustive_facets_count: Option<bool>,
+    pub(crate) geo_distribution: Option<usize>,
+    pub(crate) exhaustive_nb_hits: Option<bool>,
+    pub(crate) search_after: Option<String>,
+    pub(crate) enable_synonyms: Option<bool>,
+    pub(crate) concatenate_ngrams: Option<bool>,
+    pub(crate) quick_search: Option<bool>,
+    pub(crate) not_equal_matches_missing: Option<bool>,
+    pub(crate) pin_snapshot: Option<bool>,
+    pub(crate) snapshot_token: Option<String>,
+    pub(crate) ranking_rules: Option<String>,
+    pub(crate) sort: Option<String>,
+    pub(crate) diversify: Option<String>,
+    pub(crate) sample: Option<usize>,
+    pub(crate) sample_seed: Option<u64>,
+    pub(crate) search_cutoff_ms: Option<u64>,
+}
+
+/// At most `max_per_value` consecutive hits share the same value of the attribute.
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
-struct SearchQuery {
-    q: String,
-    offset: Option<usize>,
-    limit: Option<usize>,
-    attributes_to_retrieve: Option<String>,
-    attributes_to_crop: Option<String>,
-    crop_length: Option<usize>,
-    attributes_to_highlight: Option<String>,
-    filters: Option<String>,
-    matches: Option<bool>,
-    facet_filters: Option<String>,
-    facets: Option<String>,
+struct Diversify {
+    attribute: String,
+    max_per_value: usize,
 }
 
+/// Searches an index.
+///
+/// With `pinSnapshot` the read transaction of the search is kept open and the token returned
+/// with the hits can be given as `snapshotToken` to read the next pages from the same state of
+/// the index, whatever the updates processed meanwhile.
 #[get("/indexes/{index_uid}/search", wrap = "Authentication::Public")]
 async fn search_with_url_query(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     params: web::Query<SearchQuery>,
 ) -> Result<HttpResponse, ResponseError> {
+    let start = Instant::now();
+    let data_cloned = data.clone();
+    let index_uid = path.into_inner().index_uid;
+    let path_index_uid = index_uid.clone();
+    let params = params.into_inner();
+    let trace = req.extensions().get::<TraceContext>().cloned();
+    let cancellation = CancellationToken::new();
+    let _cancel_on_drop = CancelOnDrop(cancellation.clone());
+
+    let snapshot_token = match (params.snapshot_token.clone(), params.pin_snapshot) {
+        (Some(token), _) => Some(token),
+        (None, Some(true)) => Some(data.read_snapshots.pin(data.db.clone(), data.reader_limit.clone(), &index_uid)?),
+        (None, _) => None,
+    };
+
+    let search = move |reader: &heed::RoTxn<MainT>| -> Result<SearchResult, ResponseError> {
+        let result = search_index(&data_cloned, reader, &index_uid, &params, trace.as_ref(), &cancellation)?;
+        if let Some(search_log) = data_cloned.search_log.as_ref().filter(|log| log.sampled()) {
+            let entry = search_log.entry(
+                vec![index_uid.as_str()],
+                &params.q,
+                params.filters.as_deref().into_iter().collect(),
+                params.facet_filters.as_deref(),
+                result.nb_hits,
+                start.elapsed(),
+            );
+            search_log.log(&entry);
+        }
+        Ok(result)
+    };
+
+    let mut result = match &snapshot_token {
+        Some(token) => data.read_snapshots.run(&data.search_pool, token, &path_index_uid, search).await?,
+        None => {
+            let data_cloned = data.clone();
+            data.search_pool.run(move || search(&data_cloned.main_read_txn()?)).await?
+        }
+    };
+    result.snapshot_token = snapshot_token;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct IndexQuery {
+    index_uid: String,
+    filters: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct MultiSearchQuery {
+    q: String,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    indexes: Vec<IndexQuery>,
+    /// Keeps the first copy of the documents found in several indexes, the copies
+    /// are recognized by their primary key or by the `dedupAttribute` if given.
+    #[serde(default)]
+    dedup: bool,
+    dedup_attribute: Option<String>,
+    /// The cutoff of the search of every index, their `searchCutoffMs` setting is used otherwise.
+    search_cutoff_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MultiSearchResult {
+    hits: Vec<SearchHit>,
+    offset: usize,
+    limit: usize,
+    nb_hits: usize,
+    processing_time_ms: usize,
+    query: String,
+    /// The last query words ignored by the search of each index, keyed by index uid.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    ignored_words: BTreeMap<String, Vec<String>>,
+    /// Set when the search cutoff of one of the indexes has been reached.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    degraded: bool,
+}
+
+/// Searches the given indexes, each one with its own filters.
+///
+/// The hits of the indexes are interleaved, the first hit of every index comes before
+/// the second ones, as the relevancy of hits from different indexes cannot be compared.
+#[post("/indexes/search", wrap = "Authentication::Public")]
+async fn search_multiple_indexes(
+    req: HttpRequest,
+    data: web::Data<Data>,
+    body: web::Json<MultiSearchQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let start = Instant::now();
+    let data_cloned = data.clone();
+    let body = body.into_inner();
+    let trace = req.extensions().get::<TraceContext>().cloned();
+    let cancellation = CancellationToken::new();
+    let _cancel_on_drop = CancelOnDrop(cancellation.clone());
+
+    let result = data
+        .search_pool
+        .run(move || {
+            let result = search_indexes(&data_cloned, &body, trace.as_ref(), &cancellation)?;
+            if let Some(search_log) = data_cloned.search_log.as_ref().filter(|log| log.sampled()) {
+                let entry = search_log.entry(
+                    body.indexes.iter().map(|index| index.index_uid.as_str()).collect(),
+                    &body.q,
+                    body.indexes.iter().filter_map(|index| index.filters.as_deref()).collect(),
+                    None,
+                    result.nb_hits,
+                    start.elapsed(),
+                );
+                search_log.log(&entry);
+            }
+            Ok(result)
+        })
+        .await?;
+
+    Ok(HttpResponse::Ok().json(result))
+}
+
+fn search_indexes(
+    data: &Data,
+    query: &MultiSearchQuery,
+    trace: Option<&TraceContext>,
+    cancellation: &CancellationToken,
+) -> Result<MultiSearchResult, ResponseError> {
+    let start = Instant::now();
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(20);
+    let end = offset.saturating_add(limit);
+
+    let reader = data.main_read_txn()?;
+    let mut nb_hits = 0;
+    let mut degraded = false;
+    let mut ignored_words = BTreeMap::new();
+    let mut results = Vec::with_capacity(query.indexes.len());
+    for index_query in &query.indexes {
+        // every index returns enough hits to fill the requested page
+        let params = SearchQuery {
+            q: query.q.clone(),
+            offset: Some(0),
+            limit: Some(end),
+            filters: index_query.filters.clone(),
+            search_cutoff_ms: query.search_cutoff_ms,
+            ..SearchQuery::default()
+        };
+
+        let result = search_index(data, &reader, &index_query.index_uid, &params, trace, cancellation)?;
+        nb_hits += result.nb_hits;
+        degraded |= result.degraded;
+        if !result.ignored_words.is_empty() {
+            ignored_words.insert(index_query.index_uid.clone(), result.ignored_words);
+        }
+
+        let dedup_attribute = match &query.dedup_attribute {
+            Some(attribute) => Some(attribute.clone()),
+            None if query.dedup => primary_key(data, &reader, &index_query.index_uid)?,
+            None => None,
+        };
+
+        let index_uid = Value::String(index_query.index_uid.clone());
+        let hits = result.hits.into_iter().map(move |mut hit| {
+            hit.document.insert("_indexUid".to_string(), index_uid.clone());
+            hit
+        });
+        results.push((hits, dedup_attribute));
+    }
+
+    // the copies of a document coming later in the interleaved hits are ranked lower,
+    // the number of hits isn't reduced by the copies that are dropped
+    let mut seen = HashSet::new();
+    let mut hits = Vec::new();
+    while hits.len() < end {
+        let mut exhausted = true;
+        for (index_hits, dedup_attribute) in &mut results {
+            let hit = match index_hits.next() {
+                Some(hit) => hit,
+                None => continue,
+            };
+            exhausted = false;
+
+            let value = dedup_attribute.as_ref().and_then(|attribute| hit.document.get(attribute));
+            if value.map_or(true, |value| seen.insert(value.to_string())) {
+                hits.push(hit);
+            }
+        }
+        if exhausted {
+            break;
+        }
+    }
+
+    let hits = hits.into_iter().skip(offset).take(limit).collect();
+
+    Ok(MultiSearchResult {
+        hits,
+        offset,
+        limit,
+        nb_hits,
+        processing_time_ms: start.elapsed().as_millis() as usize,
+        query: query.q.clone(),
+        ignored_words,
+        degraded,
+    })
+}
+
+fn primary_key(data: &Data, reader: &heed::RoTxn<MainT>, index_uid: &str) -> Result<Option<String>, ResponseError> {
     let index = data
         .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+        .open_index(index_uid)
+        .ok_or(ResponseError::index_not_found(index_uid))?;
 
-    let reader = data.db.main_read_txn()?;
+    let schema = index.main.schema(reader)?;
+    Ok(schema.and_then(|schema| schema.primary_key().map(str::to_string)))
+}
+
+pub(crate) fn search_index(
+    data: &Data,
+    reader: &heed::RoTxn<MainT>,
+    index_uid: &str,
+    params: &SearchQuery,
+    trace: Option<&TraceContext>,
+    cancellation: &CancellationToken,
+) -> Result<SearchResult, ResponseError> {
+    let index = data
+        .db
+        .open_index(index_uid)
+        .ok_or(ResponseError::index_not_found(index_uid))?;
 
     let schema = index
         .main
-        .schema(&reader)?
+        .schema(reader)?
         .ok_or(ResponseError::internal("Impossible to retrieve the schema"))?;
 
     let mut search_builder = index.new_search(params.q.clone());
@@ -87,23 +355,53 @@ async fn search_with_url_query(
         }
     }
 
+    let aliases = index.main.attribute_aliases(reader)?.unwrap_or_default();
+
     if let Some(ref facet_filters) = params.facet_filters {
-        match index.main.attributes_for_faceting(&reader)? {
-            Some(ref attrs) => { search_builder.add_facet_filters(FacetFilter::from_str(facet_filters, &schema, attrs)?); },
+        match index.main.attributes_for_faceting(reader)? {
+            Some(ref attrs) => {
+                let filter = FacetFilter::from_str_with_aliases(facet_filters, &schema, attrs, &aliases)?;
+                search_builder.add_facet_filters(filter);
+            },
             None => return Err(ResponseError::FacetExpression("can't filter on facets, as no facet is set".to_string()))
         }
     }
 
     if let Some(facets) = &params.facets {
-        match index.main.attributes_for_faceting(&reader)? {
+        match index.main.attributes_for_faceting(reader)? {
             Some(ref attrs) => {
-                let field_ids = prepare_facet_list(&facets, &schema, attrs)?;
+                let field_ids = prepare_facet_list(&facets, &schema, attrs, &aliases)?;
                 search_builder.add_facets(field_ids);
             },
             None => return Err(FacetCountError::NoFacetSet.into())
         }
     }
 
+    if let Some(disjunctive_facets) = params.disjunctive_facets {
+        search_builder.disjunctive_facets(disjunctive_facets);
+    }
+
+    if let Some(exhaustive_facets_count) = params.exhaustive_facets_count {
+        search_builder.exhaustive_facets_count(exhaustive_facets_count);
+    }
+
+    if let Some(precision) = params.geo_distribution {
+        if precision == 0 || precision > GEOHASH_MAX_PRECISION {
+            return Err(ResponseError::bad_request(format!(
+                "geoDistribution must be a precision between 1 and {}",
+                GEOHASH_MAX_PRECISION,
+            )));
+        }
+
+        let attrs = index.main.attributes_for_faceting(reader)?.ok_or(FacetCountError::NoFacetSet)?;
+        match schema.id("_geo") {
+            Some(field_id) if attrs.contains(&field_id) => {
+                search_builder.geo_distribution(field_id, precision);
+            },
+            _ => return Err(FacetCountError::AttributeNotSet("_geo".to_string()).into()),
+        }
+    }
+
     if let Some(attributes_to_crop) = &params.attributes_to_crop {
         let default_length = params.crop_length.unwrap_or(200);
         let mut final_attributes: HashMap<String, usize> = HashMap::new();
@@ -155,21 +453,128 @@ async fn search_with_url_query(
         search_builder.filters(filters.to_string());
     }
 
+    if let Some(ranking_rules) = &params.ranking_rules {
+        let ranking_rules = RankingRule::from_iter(ranking_rules.split(','))?;
+        for rule in &ranking_rules {
+            if !rule.is_sort_rule() {
+                return Err(ResponseError::bad_parameter("rankingRules", "only the asc, desc and expr rules can be given"));
+            }
+            for field in rule.fields() {
+                match schema.id(&field) {
+                    Some(field_id) if schema.is_ranked(field_id) => (),
+                    _ => return Err(ResponseError::bad_parameter(
+                        "rankingRules",
+                        format!("the attribute {} is not sortable, it must be used by the ranking rules of the index", field),
+                    )),
+                }
+            }
+        }
+        search_builder.ranking_rules(ranking_rules);
+    }
+
+    if let Some(sort) = &params.sort {
+        let sort: Vec<String> = serde_json::from_str(sort).map_err(|e| ResponseError::bad_parameter("sort", e))?;
+        let mut attributes = Vec::with_capacity(sort.len());
+        for rule in sort {
+            let (field, descending) = match rule.rsplitn(2, ':').collect::<Vec<_>>().as_slice() {
+                ["asc", field] => (field.to_string(), false),
+                ["desc", field] => (field.to_string(), true),
+                _ => return Err(ResponseError::bad_parameter(
+                    "sort",
+                    format!("{} must be of the form attribute:asc or attribute:desc", rule),
+                )),
+            };
+            if schema.id(&field).is_none() {
+                return Err(ResponseError::bad_parameter("sort", format!("the attribute {} doesn't exist", field)));
+            }
+            attributes.push((field, descending));
+        }
+        search_builder.sort(attributes);
+    }
+
+    if let Some(sample) = params.sample {
+        // the sample is unordered, there is nothing to diversify
+        if params.diversify.is_some() {
+            return Err(ResponseError::bad_parameter("sample", "a sample can't be diversified"));
+        }
+        search_builder.sample(sample, params.sample_seed);
+    }
+
+    if let Some(diversify) = &params.diversify {
+        let diversify: Diversify = serde_json::from_str(diversify)
+            .map_err(|e| ResponseError::bad_parameter("diversify", e))?;
+        let field_id = schema
+            .id(resolve_attribute_alias(&aliases, &diversify.attribute))
+            .ok_or_else(|| ResponseError::bad_parameter(
+                "diversify",
+                format!("the attribute {} doesn't exist", diversify.attribute),
+            ))?;
+        if diversify.max_per_value == 0 {
+            return Err(ResponseError::bad_parameter("diversify", "maxPerValue must be greater than 0"));
+        }
+        search_builder.diversify(field_id, diversify.max_per_value);
+    }
+
+    if let Some(not_equal_matches_missing) = params.not_equal_matches_missing {
+        search_builder.not_equal_matches_missing(not_equal_matches_missing);
+    }
+
+    // set before the cursor, which depends on the alternatives being searched
+    if let Some(enable_synonyms) = params.enable_synonyms {
+        search_builder.enable_synonyms(enable_synonyms);
+    }
+
+    if let Some(concatenate_ngrams) = params.concatenate_ngrams {
+        search_builder.concatenate_ngrams(concatenate_ngrams);
+    }
+
+    if let Some(quick_search) = params.quick_search {
+        search_builder.quick_search(quick_search);
+    }
+
+    if let Some(cursor) = &params.search_after {
+        search_builder.search_after(cursor)?;
+    }
+
+    if let Some(exhaustive_nb_hits) = params.exhaustive_nb_hits {
+        search_builder.exhaustive_nb_hits(exhaustive_nb_hits);
+    }
+
+    if let Some(cutoff) = params.search_cutoff_ms {
+        search_builder.search_cutoff(Duration::from_millis(cutoff));
+    }
+
     if let Some(matches) = params.matches {
         if matches {
             search_builder.get_matches();
         }
     }
 
-    Ok(HttpResponse::Ok().json(search_builder.search(&reader)?))
+    if let Some(unit) = params.matches_position_unit {
+        search_builder.matches_position_unit(unit);
+    }
+
+    if let Some(trace) = trace {
+        search_builder.trace(trace.clone());
+    }
+
+    search_builder.cancellation(cancellation.clone());
+
+    search_builder.search(reader)
 }
 
 /// Parses the incoming string into an array of attributes for which to return a count. It returns
 /// a Vec of attribute names ascociated with their id.
 ///
 /// An error is returned if the array is malformed, or if it contains attributes that are
-/// unexisting, or not set as facets.
-fn prepare_facet_list(facets: &str, schema: &Schema, facet_attrs: &[FieldId]) -> Result<Vec<(FieldId, String)>, FacetCountError> {
+/// unexisting, or not set as facets. The attributes can be named by their aliases, and the
+/// attributes which have one are counted under it.
+fn prepare_facet_list(
+    facets: &str,
+    schema: &Schema,
+    facet_attrs: &[FieldId],
+    aliases: &BTreeMap<String, String>,
+) -> Result<Vec<(FieldId, String)>, FacetCountError> {
     let json_array = serde_json::from_str(facets)?;
     match json_array {
         Value::Array(vals) => {
@@ -177,7 +582,11 @@ fn prepare_facet_list(facets: &str, schema: &Schema, facet_attrs: &[FieldId]) ->
             if vals.iter().any(|f| f == &wildcard) {
                 let attrs = facet_attrs
                     .iter()
-                    .filter_map(|&id| schema.name(id).map(|n| (id, n.to_string())))
+                    .filter_map(|&id| {
+                        let name = schema.name(id)?;
+                        let alias = aliases.iter().find(|(_, attribute)| *attribute == name);
+                        Some((id, alias.map_or(name, |(alias, _)| alias.as_str()).to_string()))
+                    })
                     .collect();
                 return Ok(attrs);
             }
@@ -185,7 +594,7 @@ fn prepare_facet_list(facets: &str, schema: &Schema, facet_attrs: &[FieldId]) ->
             for facet in vals {
                 match facet {
                     Value::String(facet) => {
-                        if let Some(id) = schema.id(&facet) {
+                        if let Some(id) = schema.id(resolve_attribute_alias(aliases, &facet)) {
                             if !facet_attrs.contains(&id) {
                                 return Err(FacetCountError::AttributeNotSet(facet));
                             }
@@ -1,15 +1,17 @@
-use actix_web::{web, HttpResponse};
-use actix_web_macros::{delete, get, post};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_macros::{delete, get, post, put};
 use meilisearch_core::settings::{Settings, SettingsUpdate, UpdateState, DEFAULT_RANKING_RULES};
+use meilisearch_core::{Index, MainT};
 use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use crate::error::ResponseError;
 use crate::helpers::Authentication;
-use crate::routes::{IndexParam, IndexUpdateResponse};
+use crate::routes::{enqueue_once, IdempotencyKey, IndexParam, IndexUpdateResponse, UpdatePriority, UpdatePriorityQuery};
 use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
     cfg.service(update_all)
+        .service(replace_all)
         .service(get_all)
         .service(delete_all)
         .service(get_rules)
@@ -28,29 +30,58 @@ pub fn services(cfg: &mut web::ServiceConfig) {
         .service(update_accept_new_fields);
 }
 
-#[post("/indexes/{index_uid}/settings", wrap = "Authentication::Private")]
+#[post("/indexes/{index_uid}/settings", wrap = "Authentication::Settings")]
 async fn update_all(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
+    params: web::Query<UpdatePriorityQuery>,
     body: web::Json<Settings>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
+
+    let mut writer = data.update_write_txn_async().await?;
+    let settings = body
+        .into_inner()
+        .into_update()?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        let update_id = index.settings_update(writer, settings)?;
+        if params.priority == UpdatePriority::High {
+            index.prioritize_update(writer, update_id)?;
+        }
+        Ok(update_id)
+    })?;
+    writer.commit()?;
+
+    Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
+}
+
+/// Replaces all the settings in a single update, the settings missing
+/// from the body are reset to their default values.
+#[put("/indexes/{index_uid}/settings", wrap = "Authentication::Settings")]
+async fn replace_all(
+    req: HttpRequest,
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<Settings>,
+) -> Result<HttpResponse, ResponseError> {
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
 
-    let mut writer = data.db.update_write_txn()?;
+    let mut writer = data.update_write_txn_async().await?;
     let settings = body
         .into_inner()
-        .into_update()
-        .map_err(ResponseError::bad_request)?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+        .into_replacement_update()?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
 }
 
-#[get("/indexes/{index_uid}/settings", wrap = "Authentication::Private")]
+#[get("/indexes/{index_uid}/settings", wrap = "Authentication::Settings")]
 async fn get_all(
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
@@ -60,19 +91,28 @@ async fn get_all(
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
+    let settings = index_settings(&index, &reader)?;
 
-    let stop_words_fst = index.main.stop_words_fst(&reader)?;
+    Ok(HttpResponse::Ok().json(settings))
+}
+
+/// Retrieves all the settings of an index, as returned by the settings route.
+pub(crate) fn index_settings(
+    index: &Index,
+    reader: &heed::RoTxn<MainT>,
+) -> Result<Settings, ResponseError> {
+    let stop_words_fst = index.main.stop_words_fst(reader)?;
     let stop_words = stop_words_fst.unwrap_or_default().stream().into_strs()?;
     let stop_words: BTreeSet<String> = stop_words.into_iter().collect();
 
-    let synonyms_fst = index.main.synonyms_fst(&reader)?.unwrap_or_default();
+    let synonyms_fst = index.main.synonyms_fst(reader)?.unwrap_or_default();
     let synonyms_list = synonyms_fst.stream().into_strs()?;
 
     let mut synonyms = BTreeMap::new();
     let index_synonyms = &index.synonyms;
     for synonym in synonyms_list {
-        let alternative_list = index_synonyms.synonyms(&reader, synonym.as_bytes())?;
+        let alternative_list = index_synonyms.synonyms(reader, synonym.as_bytes())?;
         if let Some(list) = alternative_list {
             let list = list.stream().into_strs()?;
             synonyms.insert(synonym, list);
@@ -81,17 +121,17 @@ async fn get_all(
 
     let ranking_rules = index
         .main
-        .ranking_rules(&reader)?
+        .ranking_rules(reader)?
         .unwrap_or(DEFAULT_RANKING_RULES.to_vec())
         .into_iter()
         .map(|r| r.to_string())
         .collect();
 
-    let distinct_attribute = index.main.distinct_attribute(&reader)?;
+    let distinct_attribute = index.main.distinct_attribute(reader)?;
 
-    let schema = index.main.schema(&reader)?;
+    let schema = index.main.schema(reader)?;
 
-    let attributes_for_faceting = match (&schema, &index.main.attributes_for_faceting(&reader)?) {
+    let attributes_for_faceting = match (&schema, &index.main.attributes_for_faceting(reader)?) {
         (Some(schema), Some(attrs)) => {
             Some(attrs
                 .iter()
@@ -118,7 +158,7 @@ async fn get_all(
 
     let accept_new_fields = schema.map(|s| s.accept_new_fields());
 
-    let settings = Settings {
+    Ok(Settings {
         ranking_rules: Some(Some(ranking_rules)),
         distinct_attribute: Some(distinct_attribute),
         searchable_attributes: Some(searchable_attributes),
@@ -127,21 +167,32 @@ async fn get_all(
         synonyms: Some(Some(synonyms)),
         accept_new_fields: Some(accept_new_fields),
         attributes_for_faceting: Some(attributes_for_faceting),
-    };
-
-    Ok(HttpResponse::Ok().json(settings))
+        search_cutoff_ms: index.main.search_cutoff_ms(reader)?.map(Some),
+        attribute_weights: index.main.attribute_weights(reader)?.map(Some),
+        adjacent_attributes: index.main.adjacent_attributes(reader)?.map(Some),
+        normalize_units: index.main.normalize_units(reader)?.map(Some),
+        keyboard_typos: index.main.keyboard_typos(reader)?.map(Some),
+        keyboard_layout: index.main.keyboard_layout(reader)?.map(Some),
+        optional_words: index.main.optional_words(reader)?.map(Some),
+        numeric_tolerance: index.main.numeric_tolerance(reader)?.map(Some),
+        attribute_aliases: index.main.attribute_aliases(reader)?.map(Some),
+        field_types: index.main.field_types(reader)?.map(Some),
+        excluded_attributes: index.main.excluded_attributes(reader)?.map(Some),
+        max_indexed_words: index.main.max_indexed_words(reader)?.map(Some),
+        min_prefix_length: index.main.min_prefix_length(reader)?.map(Some),
+        query_normalization: index.main.query_normalization(reader)?.map(Some),
+    })
 }
 
-#[delete("/indexes/{index_uid}/settings", wrap = "Authentication::Private")]
+#[delete("/indexes/{index_uid}/settings", wrap = "Authentication::Settings")]
 async fn delete_all(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let mut writer = data.db.update_write_txn()?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
+    let mut writer = data.update_write_txn_async().await?;
 
     let settings = SettingsUpdate {
         ranking_rules: UpdateState::Clear,
@@ -153,9 +204,25 @@ async fn delete_all(
         synonyms: UpdateState::Clear,
         accept_new_fields: UpdateState::Clear,
         attributes_for_faceting: UpdateState::Clear,
+        search_cutoff_ms: UpdateState::Clear,
+        attribute_weights: UpdateState::Clear,
+        adjacent_attributes: UpdateState::Clear,
+        normalize_units: UpdateState::Clear,
+        keyboard_typos: UpdateState::Clear,
+        keyboard_layout: UpdateState::Clear,
+        optional_words: UpdateState::Clear,
+        numeric_tolerance: UpdateState::Clear,
+        attribute_aliases: UpdateState::Clear,
+        field_types: UpdateState::Clear,
+        excluded_attributes: UpdateState::Clear,
+        max_indexed_words: UpdateState::Clear,
+        min_prefix_length: UpdateState::Clear,
+        query_normalization: UpdateState::Clear,
     };
 
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -163,7 +230,7 @@ async fn delete_all(
 
 #[get(
     "/indexes/{index_uid}/settings/ranking-rules",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn get_rules(
     data: web::Data<Data>,
@@ -173,7 +240,7 @@ async fn get_rules(
         .db
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let ranking_rules = index
         .main
@@ -188,26 +255,27 @@ async fn get_rules(
 
 #[post(
     "/indexes/{index_uid}/settings/ranking-rules",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn update_rules(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     body: web::Json<Option<Vec<String>>>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = Settings {
         ranking_rules: Some(body.into_inner()),
         ..Settings::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let settings = settings.into_update().map_err(ResponseError::bad_request)?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let settings = settings.into_update()?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -215,24 +283,25 @@ async fn update_rules(
 
 #[delete(
     "/indexes/{index_uid}/settings/ranking-rules",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn delete_rules(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let mut writer = data.db.update_write_txn()?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
+    let mut writer = data.update_write_txn_async().await?;
 
     let settings = SettingsUpdate {
         ranking_rules: UpdateState::Clear,
         ..SettingsUpdate::default()
     };
 
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
 
     writer.commit()?;
 
@@ -241,7 +310,7 @@ async fn delete_rules(
 
 #[get(
     "/indexes/{index_uid}/settings/distinct-attribute",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn get_distinct(
     data: web::Data<Data>,
@@ -251,7 +320,7 @@ async fn get_distinct(
         .db
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
     let distinct_attribute = index.main.distinct_attribute(&reader)?;
 
     Ok(HttpResponse::Ok().json(distinct_attribute))
@@ -259,26 +328,27 @@ async fn get_distinct(
 
 #[post(
     "/indexes/{index_uid}/settings/distinct-attribute",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn update_distinct(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     body: web::Json<Option<String>>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = Settings {
         distinct_attribute: Some(body.into_inner()),
         ..Settings::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let settings = settings.into_update().map_err(ResponseError::bad_request)?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let settings = settings.into_update()?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -286,24 +356,25 @@ async fn update_distinct(
 
 #[delete(
     "/indexes/{index_uid}/settings/distinct-attribute",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn delete_distinct(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let mut writer = data.db.update_write_txn()?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
+    let mut writer = data.update_write_txn_async().await?;
 
     let settings = SettingsUpdate {
         distinct_attribute: UpdateState::Clear,
         ..SettingsUpdate::default()
     };
 
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
 
     writer.commit()?;
 
@@ -312,7 +383,7 @@ async fn delete_distinct(
 
 #[get(
     "/indexes/{index_uid}/settings/searchable-attributes",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn get_searchable(
     data: web::Data<Data>,
@@ -322,7 +393,7 @@ async fn get_searchable(
         .db
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
     let schema = index.main.schema(&reader)?;
     let searchable_attributes: Option<Vec<String>> =
         schema.map(|s| s.indexed_name().iter().map(|i| i.to_string()).collect());
@@ -332,26 +403,27 @@ async fn get_searchable(
 
 #[post(
     "/indexes/{index_uid}/settings/searchable-attributes",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn update_searchable(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     body: web::Json<Option<Vec<String>>>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = Settings {
         searchable_attributes: Some(body.into_inner()),
         ..Settings::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let settings = settings.into_update().map_err(ResponseError::bad_request)?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let settings = settings.into_update()?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -359,24 +431,25 @@ async fn update_searchable(
 
 #[delete(
     "/indexes/{index_uid}/settings/searchable-attributes",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn delete_searchable(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = SettingsUpdate {
         searchable_attributes: UpdateState::Clear,
         ..SettingsUpdate::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -384,7 +457,7 @@ async fn delete_searchable(
 
 #[get(
     "/indexes/{index_uid}/settings/displayed-attributes",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn get_displayed(
     data: web::Data<Data>,
@@ -394,7 +467,7 @@ async fn get_displayed(
         .db
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let schema = index.main.schema(&reader)?;
 
@@ -406,26 +479,27 @@ async fn get_displayed(
 
 #[post(
     "/indexes/{index_uid}/settings/displayed-attributes",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn update_displayed(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     body: web::Json<Option<HashSet<String>>>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = Settings {
         displayed_attributes: Some(body.into_inner()),
         ..Settings::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let settings = settings.into_update().map_err(ResponseError::bad_request)?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let settings = settings.into_update()?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -433,24 +507,25 @@ async fn update_displayed(
 
 #[delete(
     "/indexes/{index_uid}/settings/displayed-attributes",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn delete_displayed(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = SettingsUpdate {
         displayed_attributes: UpdateState::Clear,
         ..SettingsUpdate::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -458,7 +533,7 @@ async fn delete_displayed(
 
 #[get(
     "/indexes/{index_uid}/settings/accept-new-fields",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn get_accept_new_fields(
     data: web::Data<Data>,
@@ -468,7 +543,7 @@ async fn get_accept_new_fields(
         .db
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let schema = index.main.schema(&reader)?;
 
@@ -479,26 +554,27 @@ async fn get_accept_new_fields(
 
 #[post(
     "/indexes/{index_uid}/settings/accept-new-fields",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn update_accept_new_fields(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     body: web::Json<Option<bool>>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = Settings {
         accept_new_fields: Some(body.into_inner()),
         ..Settings::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let settings = settings.into_update().map_err(ResponseError::bad_request)?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let settings = settings.into_update()?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -5,19 +5,25 @@ use actix_web::HttpResponse;
 use actix_web_macros::get;
 use chrono::{DateTime, Utc};
 use log::error;
+use meilisearch_core::WriteQueuesStats;
 use pretty_bytes::converter::convert;
 use serde::Serialize;
 use sysinfo::{NetworkExt, ProcessExt, ProcessorExt, System, SystemExt};
 use walkdir::WalkDir;
 
 use crate::error::ResponseError;
-use crate::helpers::Authentication;
+use crate::helpers::reader_limit::ReaderLimitStats;
+use crate::helpers::search_pool::SearchPoolStats;
+use crate::helpers::write_queue;
+use crate::helpers::{run_blocking, Authentication};
 use crate::routes::IndexParam;
 use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
     cfg.service(index_stats)
+        .service(index_stores_sizes)
         .service(get_stats)
+        .service(get_metrics)
         .service(get_version)
         .service(get_sys_info)
         .service(get_sys_info_pretty);
@@ -41,13 +47,13 @@ async fn index_stats(
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
 
     let number_of_documents = index.main.number_of_documents(&reader)?;
 
     let fields_frequency = index.main.fields_frequency(&reader)?.unwrap_or_default();
 
-    let update_reader = data.db.update_read_txn()?;
+    let update_reader = data.update_read_txn()?;
 
     let is_indexing =
         data.is_indexing(&update_reader, &path.index_uid)?
@@ -62,20 +68,45 @@ async fn index_stats(
     }))
 }
 
+#[get("/indexes/{index_uid}/stats/stores", wrap = "Authentication::Private")]
+async fn index_stores_sizes(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data
+        .db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    // the whole index is read, it must not block the server threads
+    let data_cloned = data.clone();
+    let sizes = run_blocking("stores sizes computation", move || -> Result<_, ResponseError> {
+        let reader = data_cloned.main_read_txn()?;
+        let update_reader = data_cloned.update_read_txn()?;
+        Ok(index.stores_sizes(&reader, &update_reader)?)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(sizes))
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct StatsResult {
     database_size: u64,
     last_update: Option<DateTime<Utc>>,
     indexes: HashMap<String, IndexStatsResponse>,
+    search_pool: SearchPoolStats,
+    readers: ReaderLimitStats,
+    write_queues: WriteQueuesStats,
 }
 
 #[get("/stats", wrap = "Authentication::Private")]
 async fn get_stats(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
     let mut index_list = HashMap::new();
 
-    let reader = data.db.main_read_txn()?;
-    let update_reader = data.db.update_read_txn()?;
+    let reader = data.main_read_txn()?;
+    let update_reader = data.update_read_txn()?;
 
     let indexes_set = data.db.indexes_uids();
     for index_uid in indexes_set {
@@ -117,9 +148,21 @@ async fn get_stats(data: web::Data<Data>) -> Result<HttpResponse, ResponseError>
         database_size,
         last_update,
         indexes: index_list,
+        search_pool: data.search_pool.stats(),
+        readers: data.reader_limit.stats(),
+        write_queues: data.db.write_queues_stats(),
     }))
 }
 
+/// The request counts and latencies of the routes and the waits for the write transactions,
+/// in the Prometheus text format.
+#[get("/metrics", wrap = "Authentication::Private")]
+async fn get_metrics(data: web::Data<Data>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(data.metrics.render() + &write_queue::render(&data.db.write_queues_stats()))
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct VersionResponse {
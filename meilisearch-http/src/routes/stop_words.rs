@@ -1,11 +1,10 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_macros::{delete, get, post};
-use meilisearch_core::settings::{SettingsUpdate, UpdateState};
-use std::collections::BTreeSet;
+use meilisearch_core::settings::{SettingsUpdate, StopWordsSetting, UpdateState};
 
 use crate::error::ResponseError;
 use crate::helpers::Authentication;
-use crate::routes::{IndexParam, IndexUpdateResponse};
+use crate::routes::{enqueue_once, IdempotencyKey, IndexParam, IndexUpdateResponse};
 use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
@@ -14,7 +13,7 @@ pub fn services(cfg: &mut web::ServiceConfig) {
 
 #[get(
     "/indexes/{index_uid}/settings/stop-words",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn get(
     data: web::Data<Data>,
@@ -24,7 +23,7 @@ async fn get(
         .db
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
-    let reader = data.db.main_read_txn()?;
+    let reader = data.main_read_txn()?;
     let stop_words_fst = index.main.stop_words_fst(&reader)?;
     let stop_words = stop_words_fst.unwrap_or_default().stream().into_strs()?;
 
@@ -33,25 +32,27 @@ async fn get(
 
 #[post(
     "/indexes/{index_uid}/settings/stop-words",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn update(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
-    body: web::Json<BTreeSet<String>>,
+    body: web::Json<StopWordsSetting>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let stop_words = body.into_inner().into_words().map_err(ResponseError::bad_request)?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &stop_words)?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = SettingsUpdate {
-        stop_words: UpdateState::Update(body.into_inner()),
+        stop_words: UpdateState::Update(stop_words),
         ..SettingsUpdate::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -59,24 +60,25 @@ async fn update(
 
 #[delete(
     "/indexes/{index_uid}/settings/stop-words",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn delete(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = SettingsUpdate {
         stop_words: UpdateState::Clear,
         ..SettingsUpdate::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -1,22 +1,47 @@
 use std::collections::BTreeMap;
+use std::mem;
 
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_macros::{delete, get, post};
 use indexmap::IndexMap;
 use meilisearch_core::settings::{SettingsUpdate, UpdateState};
+use meilisearch_core::{Index, MainT};
+use serde::{Deserialize, Serialize};
 
 use crate::error::ResponseError;
 use crate::helpers::Authentication;
-use crate::routes::{IndexParam, IndexUpdateResponse};
+use crate::routes::{enqueue_once, IdempotencyKey, IndexParam, IndexUpdateResponse};
 use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
-    cfg.service(get).service(update).service(delete);
+    cfg.service(get)
+        .service(update)
+        .service(delete)
+        .service(export)
+        .service(import);
+}
+
+fn index_synonyms(reader: &heed::RoTxn<MainT>, index: &Index) -> Result<IndexMap<String, Vec<String>>, ResponseError> {
+    let synonyms_fst = index.main.synonyms_fst(reader)?.unwrap_or_default();
+    let synonyms_list = synonyms_fst.stream().into_strs()?;
+
+    let mut synonyms = IndexMap::new();
+    let index_synonyms = &index.synonyms;
+    for synonym in synonyms_list {
+        let alternative_list = index_synonyms.synonyms(reader, synonym.as_bytes())?;
+
+        if let Some(list) = alternative_list {
+            let list = list.stream().into_strs()?;
+            synonyms.insert(synonym, list);
+        }
+    }
+
+    Ok(synonyms)
 }
 
 #[get(
     "/indexes/{index_uid}/settings/synonyms",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn get(
     data: web::Data<Data>,
@@ -27,46 +52,34 @@ async fn get(
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
-    let reader = data.db.main_read_txn()?;
-
-    let synonyms_fst = index.main.synonyms_fst(&reader)?.unwrap_or_default();
-    let synonyms_list = synonyms_fst.stream().into_strs()?;
-
-    let mut synonyms = IndexMap::new();
-    let index_synonyms = &index.synonyms;
-    for synonym in synonyms_list {
-        let alternative_list = index_synonyms.synonyms(&reader, synonym.as_bytes())?;
-
-        if let Some(list) = alternative_list {
-            let list = list.stream().into_strs()?;
-            synonyms.insert(synonym, list);
-        }
-    }
+    let reader = data.main_read_txn()?;
+    let synonyms = index_synonyms(&reader, &index)?;
 
     Ok(HttpResponse::Ok().json(synonyms))
 }
 
 #[post(
     "/indexes/{index_uid}/settings/synonyms",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn update(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     body: web::Json<BTreeMap<String, Vec<String>>>,
 ) -> Result<HttpResponse, ResponseError> {
-    let index = data
-        .db
-        .open_index(&path.index_uid)
-        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+    let idempotency_key = IdempotencyKey::from_request(&req, &*body)?;
+    let index = data.writable_index(&path.index_uid)?;
 
     let settings = SettingsUpdate {
         synonyms: UpdateState::Update(body.into_inner()),
         ..SettingsUpdate::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let update_id = index.settings_update(&mut writer, settings)?;
+    let mut writer = data.update_write_txn_async().await?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
     writer.commit()?;
 
     Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
@@ -74,26 +87,312 @@ async fn update(
 
 #[delete(
     "/indexes/{index_uid}/settings/synonyms",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn delete(
+    req: HttpRequest,
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let idempotency_key = IdempotencyKey::from_request(&req, &())?;
+    let index = data.writable_index(&path.index_uid)?;
+
+    let settings = SettingsUpdate {
+        synonyms: UpdateState::Clear,
+        ..SettingsUpdate::default()
+    };
+
+    let mut writer = data.update_write_txn_async().await?;
+    let update_id = enqueue_once(&index, &mut writer, idempotency_key.as_ref(), |writer| {
+        Ok(index.settings_update(writer, settings)?)
+    })?;
+
+    writer.commit()?;
+
+    Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
+}
+
+/// The text formats in which the synonyms can be exported and imported.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SynonymsFormat {
+    /// One `word => alternative, alternative` mapping per line, or
+    /// `word, word, word` for words that are all synonyms of each other,
+    /// the `\`, `,`, `=`, `>` and `#` of the words are escaped with a `\`.
+    Solr,
+    /// One `word,alternative,alternative` record per line.
+    Csv,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ExportQuery {
+    format: SynonymsFormat,
+}
+
+#[get(
+    "/indexes/{index_uid}/settings/synonyms/export",
+    wrap = "Authentication::Settings"
+)]
+async fn export(
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
+    params: web::Query<ExportQuery>,
 ) -> Result<HttpResponse, ResponseError> {
     let index = data
         .db
         .open_index(&path.index_uid)
         .ok_or(ResponseError::index_not_found(&path.index_uid))?;
 
+    let reader = data.main_read_txn()?;
+    let synonyms = index_synonyms(&reader, &index)?;
+
+    let mut file = String::new();
+    for (word, alternatives) in synonyms {
+        let line = match params.format {
+            SynonymsFormat::Solr => {
+                let alternatives: Vec<_> = alternatives.iter().map(|a| solr_word(a)).collect();
+                format!("{} => {}", solr_word(&word), alternatives.join(", "))
+            }
+            SynonymsFormat::Csv => {
+                let fields: Vec<_> = std::iter::once(&word).chain(&alternatives).map(|f| csv_field(f)).collect();
+                fields.join(",")
+            }
+        };
+        file.push_str(&line);
+        file.push('\n');
+    }
+
+    let content_type = match params.format {
+        SynonymsFormat::Solr => "text/plain",
+        SynonymsFormat::Csv => "text/csv",
+    };
+
+    Ok(HttpResponse::Ok().content_type(content_type).body(file))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ImportQuery {
+    format: SynonymsFormat,
+    dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportResponse {
+    /// Not set when the import is a dry run.
+    update_id: Option<u64>,
+    dry_run: bool,
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+/// Replaces the synonyms by the ones of the file, answering with the words whose
+/// synonyms are added, removed or changed compared to the current synonyms.
+/// A dry run only answers with the changes, nothing is enqueued.
+#[post(
+    "/indexes/{index_uid}/settings/synonyms/import",
+    wrap = "Authentication::Settings"
+)]
+async fn import(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<ImportQuery>,
+    body: String,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data.writable_index(&path.index_uid)?;
+
+    let imported = parse_synonyms(&body, params.format).map_err(ResponseError::bad_request)?;
+
+    let reader = data.main_read_txn()?;
+    let current = index_synonyms(&reader, &index)?;
+    drop(reader);
+
+    let dry_run = params.dry_run.unwrap_or(false);
+    let mut response = ImportResponse {
+        update_id: None,
+        dry_run,
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+
+    for (word, alternatives) in &imported {
+        match current.get(word) {
+            None => response.added.push(word.clone()),
+            Some(current) => {
+                let mut current = current.clone();
+                current.sort();
+                if &current != alternatives {
+                    response.changed.push(word.clone());
+                }
+            }
+        }
+    }
+    response.removed = current.keys().filter(|w| !imported.contains_key(*w)).cloned().collect();
+
+    if dry_run {
+        return Ok(HttpResponse::Ok().json(response));
+    }
+
     let settings = SettingsUpdate {
-        synonyms: UpdateState::Clear,
+        synonyms: UpdateState::Update(imported),
         ..SettingsUpdate::default()
     };
 
-    let mut writer = data.db.update_write_txn()?;
-    let update_id = index.settings_update(&mut writer, settings)?;
-
+    let mut writer = data.update_write_txn_async().await?;
+    response.update_id = Some(index.settings_update(&mut writer, settings)?);
     writer.commit()?;
 
-    Ok(HttpResponse::Accepted().json(IndexUpdateResponse::with_id(update_id)))
+    Ok(HttpResponse::Accepted().json(response))
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn parse_csv_record(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(mem::replace(&mut field, String::new())),
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(String::from("unterminated quoted field"));
+    }
+
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Escapes the characters that are separators or comments in a Solr synonyms file.
+fn solr_word(word: &str) -> String {
+    let mut escaped = String::with_capacity(word.len());
+    for c in word.chars() {
+        if let '\\' | ',' | '=' | '>' | '#' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Splits the line on the separators that aren't escaped, the escapes are kept.
+fn split_unescaped<'a>(line: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while let Some(c) = line[i..].chars().next() {
+        if c == '\\' {
+            i += c.len_utf8();
+            i += line[i..].chars().next().map_or(0, char::len_utf8);
+        } else if line[i..].starts_with(separator) {
+            parts.push(&line[start..i]);
+            i += separator.len();
+            start = i;
+        } else {
+            i += c.len_utf8();
+        }
+    }
+    parts.push(&line[start..]);
+    parts
+}
+
+fn split_words(words: &str) -> Vec<String> {
+    split_unescaped(words, ",")
+        .into_iter()
+        .map(|word| {
+            let mut unescaped = String::with_capacity(word.len());
+            let mut chars = word.trim().chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => unescaped.push(chars.next().unwrap_or('\\')),
+                    c => unescaped.push(c),
+                }
+            }
+            unescaped.to_lowercase()
+        })
+        .collect()
+}
+
+/// Parses a synonyms file, the alternatives of each word are sorted and deduplicated.
+fn parse_synonyms(file: &str, format: SynonymsFormat) -> Result<BTreeMap<String, Vec<String>>, String> {
+    let mut synonyms: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for (i, line) in file.lines().enumerate() {
+        let line = line.trim();
+        let is_comment = match format {
+            SynonymsFormat::Solr => line.starts_with('#'),
+            SynonymsFormat::Csv => false,
+        };
+        if line.is_empty() || is_comment {
+            continue;
+        }
+
+        let error = |message: &str| format!("line {}: {}", i + 1, message);
+
+        let mappings: Vec<(Vec<String>, Vec<String>)> = match format {
+            SynonymsFormat::Solr => match split_unescaped(line, "=>").as_slice() {
+                [words, alternatives] => vec![(split_words(words), split_words(alternatives))],
+                [_, _, ..] => return Err(error("a line must contain at most one =>")),
+                _ => {
+                    // all the words are synonyms of each other
+                    let words = split_words(line);
+                    if words.len() < 2 {
+                        return Err(error("a line must contain at least two words"));
+                    }
+                    words
+                        .iter()
+                        .map(|word| {
+                            let others = words.iter().filter(|w| *w != word).cloned().collect();
+                            (vec![word.clone()], others)
+                        })
+                        .collect()
+                }
+            },
+            SynonymsFormat::Csv => {
+                let fields = parse_csv_record(line).map_err(|e| error(&e))?;
+                let mut fields = fields.into_iter().map(|f| f.trim().to_lowercase());
+                let word = fields.next().unwrap_or_default();
+                vec![(vec![word], fields.collect())]
+            }
+        };
+
+        for (words, alternatives) in mappings {
+            if words.iter().chain(&alternatives).any(String::is_empty) {
+                return Err(error("empty words are not allowed"));
+            }
+            if alternatives.is_empty() {
+                return Err(error("a word must have at least one synonym"));
+            }
+            for word in words {
+                synonyms.entry(word).or_default().extend(alternatives.iter().cloned());
+            }
+        }
+    }
+
+    for alternatives in synonyms.values_mut() {
+        alternatives.sort();
+        alternatives.dedup();
+    }
+
+    Ok(synonyms)
 }
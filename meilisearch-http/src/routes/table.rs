@@ -0,0 +1,1443 @@
+// The routes of the HTTP API, declared once for the build script that generates the OpenAPI
+// document from them and for the tests checking that each of them is served. This file is
+// included by the build script and must only depend on the standard library.
+
+/// A route served by one of the handlers of the `routes` modules.
+pub struct Route {
+    /// The HTTP method, in uppercase.
+    pub method: &'static str,
+    pub path: &'static str,
+    /// The module declaring the handler, the routes are grouped by it.
+    pub tag: &'static str,
+    pub handler: &'static str,
+    /// The `Authentication` wrapper of the route, `None` for the routes without one. The `Settings`
+    /// routes take the private key, they and the `Admin` ones are restricted to the admin allowlist.
+    pub key: Option<&'static str>,
+    /// Whether the route changes the indexes or the configuration, its calls are audited.
+    pub write: bool,
+    /// The lines of the doc comment of the handler, the first one is the summary.
+    pub description: &'static [&'static str],
+    /// The types extracted from the query string and from the path.
+    pub query: Option<&'static str>,
+    pub path_params: Option<&'static str>,
+    pub body: Body,
+    pub responses: &'static [Response],
+    /// Whether the errors of the handler are answered.
+    pub fallible: bool,
+}
+
+pub enum Body {
+    None,
+    /// The body is deserialized from json into the given type.
+    Json(&'static str),
+    /// The body is read as is, as bytes or as a string.
+    Raw,
+}
+
+/// A successful response, with the type of its json body if it's a named one.
+pub struct Response {
+    pub status: u16,
+    pub json: bool,
+    pub ty: Option<&'static str>,
+}
+
+pub const ROUTES: &[Route] = &[
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/aggregate",
+        tag: "aggregation",
+        handler: "aggregate",
+        key: Some("Public"),
+        write: false,
+        description: &[
+            "Aggregates the documents matching the filters, all of the documents without one, so that",
+            "the totals don't have to be computed by paginating through the documents. The `facetFilters`",
+            "narrow the documents to test with the `filters` and should be preferred on large indexes.",
+            "",
+            "`sum`, `avg`, `min` and `max` take numeric attributes and `cardinality` takes facets.",
+        ],
+        query: Some("AggregateQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/allowed-ips",
+        tag: "allowlist",
+        handler: "get_allowed_ips",
+        key: Some("Admin"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("AllowedIps") }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/allowed-ips",
+        tag: "allowlist",
+        handler: "update_allowed_ips",
+        key: Some("Admin"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::Json("AllowedIps"),
+        responses: &[Response { status: 200, json: true, ty: Some("AllowedIps") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/changes",
+        tag: "changes",
+        handler: "get_changes",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: Some("ChangesQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("ChangesResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/debug/analyze",
+        tag: "debug",
+        handler: "analyze",
+        key: Some("Private"),
+        write: false,
+        description: &[
+            "Returns the tokens, the n-grams, the splits and the synonyms of the query",
+            "with the query tree built from them, the search isn't executed.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("AnalyzeQuery"),
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/documents/{document_id}",
+        tag: "document",
+        handler: "get_document",
+        key: Some("Public"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("DocumentParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/documents/{document_id}/tokens",
+        tag: "document",
+        handler: "get_document_tokens",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("DocumentParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/documents/{document_id}",
+        tag: "document",
+        handler: "delete_document",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("DocumentParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/documents",
+        tag: "document",
+        handler: "get_all_documents",
+        key: Some("Public"),
+        write: false,
+        description: &[],
+        query: Some("BrowseQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/documents",
+        tag: "document",
+        handler: "add_documents",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: Some("UpdateDocumentsQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::Json("Vec<Document>"),
+        responses: &[],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/indexes/{index_uid}/documents",
+        tag: "document",
+        handler: "update_documents",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Adds or updates the documents, the fields missing from a document keep their stored values.",
+            "The attribute named by `operationsAttribute` holds the increment, append and remove operations",
+            "applied to the fields of each document, it isn't stored.",
+        ],
+        query: Some("UpdateDocumentsQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::Json("Vec<Document>"),
+        responses: &[],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/documents/from-url",
+        tag: "document",
+        handler: "add_documents_from_url",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Enqueues the documents of a file hosted on another server, the file is downloaded by MeiliSearch",
+            "and never goes through the client connection. It is read as it is received and its documents are",
+            "enqueued by batches, one update each, the batches enqueued before an invalid document are kept.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("DocumentsFromUrl"),
+        responses: &[Response { status: 202, json: true, ty: Some("DocumentsFromUrlResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/documents/sessions",
+        tag: "document",
+        handler: "create_upload_session",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Starts the upload of documents in chunks, the documents are only enqueued",
+            "for indexing once all the chunks have been received and the session is committed.",
+            "The creation retried with the same `Idempotency-Key` returns the session created the first time.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("UploadSessionBody"),
+        responses: &[
+            Response { status: 200, json: true, ty: Some("UploadSessionResponse") },
+            Response { status: 201, json: true, ty: Some("UploadSessionResponse") },
+        ],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/documents/sessions/{session_id}",
+        tag: "document",
+        handler: "get_upload_session",
+        key: Some("Private"),
+        write: false,
+        description: &[
+            "Returns the chunks of the session that have been received, the missing ones can be sent again.",
+        ],
+        query: None,
+        path_params: Some("UploadSessionParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("UploadSessionResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/indexes/{index_uid}/documents/sessions/{session_id}/chunks/{chunk}",
+        tag: "document",
+        handler: "upload_chunk",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("UploadChunkParam"),
+        body: Body::Raw,
+        responses: &[Response { status: 204, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/documents/sessions/{session_id}/commit",
+        tag: "document",
+        handler: "commit_upload_session",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Reads the documents of the concatenated chunks and enqueues them in a single update.",
+        ],
+        query: None,
+        path_params: Some("UploadSessionParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/documents/sessions/{session_id}",
+        tag: "document",
+        handler: "delete_upload_session",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("UploadSessionParam"),
+        body: Body::None,
+        responses: &[Response { status: 204, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/documents/delete-batch",
+        tag: "document",
+        handler: "delete_documents",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("Vec<Value>"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/documents",
+        tag: "document",
+        handler: "clear_all_documents",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/health",
+        tag: "health",
+        handler: "get_health",
+        key: Some("Private"),
+        write: false,
+        description: &[
+            "Reports the status of the dependencies of the server: the lmdb environments, the update loops,",
+            "the free disk space and the snapshots. Answers with a 503 status if any of them failed.",
+        ],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("HealthResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/health",
+        tag: "health",
+        handler: "change_healthyness",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::Json("HealthBody"),
+        responses: &[],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes",
+        tag: "index",
+        handler: "list_indexes",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}",
+        tag: "index",
+        handler: "get_index",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("IndexResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes",
+        tag: "index",
+        handler: "create_index",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::Json("IndexCreateRequest"),
+        responses: &[Response { status: 201, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/indexes/{index_uid}",
+        tag: "index",
+        handler: "update_index",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("IndexCreateRequest"),
+        responses: &[Response { status: 200, json: true, ty: Some("IndexResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "PATCH",
+        path: "/indexes/{index_uid}",
+        tag: "index",
+        handler: "rename_index",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("RenameIndexRequest"),
+        responses: &[Response { status: 200, json: true, ty: Some("IndexResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/clone",
+        tag: "index",
+        handler: "clone_index",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Creates an index with a copy of the documents and the settings of another one,",
+            "without reindexing them, the updates still enqueued in the cloned index are not copied.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("CloneIndexRequest"),
+        responses: &[Response { status: 201, json: true, ty: Some("IndexResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}",
+        tag: "index",
+        handler: "delete_index",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 204, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/updates/{update_id}",
+        tag: "index",
+        handler: "get_update_status",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("UpdateParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/updates",
+        tag: "index",
+        handler: "get_all_updates_status",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/replicate-to",
+        tag: "index",
+        handler: "replicate_to",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("ReplicateToRequest"),
+        responses: &[Response { status: 202, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/replicate-to",
+        tag: "index",
+        handler: "get_replication_status",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/index-templates",
+        tag: "index_template",
+        handler: "list_templates",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/index-templates/{template_name}",
+        tag: "index_template",
+        handler: "get_template",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("TemplateParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/index-templates/{template_name}",
+        tag: "index_template",
+        handler: "update_template",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Creates or replaces the template, it only applies to the indexes created afterward.",
+        ],
+        query: None,
+        path_params: Some("TemplateParam"),
+        body: Body::Json("IndexTemplate"),
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/index-templates/{template_name}",
+        tag: "index_template",
+        handler: "delete_template",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("TemplateParam"),
+        body: Body::None,
+        responses: &[Response { status: 204, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/judgments",
+        tag: "judgments",
+        handler: "get_judgments",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/indexes/{index_uid}/judgments",
+        tag: "judgments",
+        handler: "update_judgments",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Replaces the judgments of the index, the relevancies of the queries",
+            "that are kept are still compared to their last run.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("Vec<Judgment>"),
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/judgments",
+        tag: "judgments",
+        handler: "delete_judgments",
+        key: Some("Private"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 204, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/judgments/run",
+        tag: "judgments",
+        handler: "run_judgments",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Searches the queries of the judgments and measures the precision and the recall of their hits,",
+            "the relevancies are compared to the ones of the last run, then replace them.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/keys",
+        tag: "key",
+        handler: "list",
+        key: Some("Admin"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("KeysResponse") }],
+        fallible: false,
+    },
+    Route {
+        method: "GET",
+        path: "/",
+        tag: "mod",
+        handler: "load_html",
+        key: None,
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: false, ty: None }],
+        fallible: false,
+    },
+    Route {
+        method: "GET",
+        path: "/openapi.json",
+        tag: "mod",
+        handler: "load_openapi",
+        key: None,
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: false, ty: None }],
+        fallible: false,
+    },
+    Route {
+        method: "GET",
+        path: "/bulma.min.css",
+        tag: "mod",
+        handler: "load_css",
+        key: None,
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: false, ty: None }],
+        fallible: false,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/quota",
+        tag: "quota",
+        handler: "get_quota",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/indexes/{index_uid}/quota",
+        tag: "quota",
+        handler: "update_quota",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Sets the quota of the index, its size is measured right away",
+            "so that the next documents addition is checked against it.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("QuotaBody"),
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/quota",
+        tag: "quota",
+        handler: "delete_quota",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Removes the quota of the index, the quota of the server applies again if there is one.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 204, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/rollover-aliases",
+        tag: "rollover",
+        handler: "list_aliases",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/rollover-aliases/{alias}",
+        tag: "rollover",
+        handler: "get_alias",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("AliasParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/rollover-aliases/{alias}",
+        tag: "rollover",
+        handler: "update_alias",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Creates the alias, with its first partition, or replaces its rollover conditions.",
+        ],
+        query: None,
+        path_params: Some("AliasParam"),
+        body: Body::Json("RolloverConditions"),
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/rollover-aliases/{alias}",
+        tag: "rollover",
+        handler: "delete_alias",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Deletes the alias, its partitions are kept.",
+        ],
+        query: None,
+        path_params: Some("AliasParam"),
+        body: Body::None,
+        responses: &[Response { status: 204, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/rollover-aliases/{alias}/rollover",
+        tag: "rollover",
+        handler: "rollover",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Rolls the alias over to a new partition if one of its conditions is reached, or if",
+            "it is forced, then deletes the partitions older than the retention window.",
+        ],
+        query: Some("RolloverQuery"),
+        path_params: Some("AliasParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/search",
+        tag: "search",
+        handler: "search_with_url_query",
+        key: Some("Public"),
+        write: false,
+        description: &[
+            "Searches an index.",
+            "",
+            "With `pinSnapshot` the read transaction of the search is kept open and the token returned",
+            "with the hits can be given as `snapshotToken` to read the next pages from the same state of",
+            "the index, whatever the updates processed meanwhile.",
+        ],
+        query: Some("SearchQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/search",
+        tag: "search",
+        handler: "search_multiple_indexes",
+        key: Some("Public"),
+        write: false,
+        description: &[
+            "Searches the given indexes, each one with its own filters.",
+            "",
+            "The hits of the indexes are interleaved, the first hit of every index comes before",
+            "the second ones, as the relevancy of hits from different indexes cannot be compared.",
+        ],
+        query: None,
+        path_params: None,
+        body: Body::Json("MultiSearchQuery"),
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings",
+        tag: "setting",
+        handler: "update_all",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: Some("UpdatePriorityQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::Json("Settings"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "PUT",
+        path: "/indexes/{index_uid}/settings",
+        tag: "setting",
+        handler: "replace_all",
+        key: Some("Settings"),
+        write: true,
+        description: &[
+            "Replaces all the settings in a single update, the settings missing",
+            "from the body are reset to their default values.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("Settings"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings",
+        tag: "setting",
+        handler: "get_all",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/settings",
+        tag: "setting",
+        handler: "delete_all",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings/ranking-rules",
+        tag: "setting",
+        handler: "get_rules",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings/ranking-rules",
+        tag: "setting",
+        handler: "update_rules",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("Option<Vec<String>>"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/settings/ranking-rules",
+        tag: "setting",
+        handler: "delete_rules",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings/distinct-attribute",
+        tag: "setting",
+        handler: "get_distinct",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings/distinct-attribute",
+        tag: "setting",
+        handler: "update_distinct",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("Option<String>"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/settings/distinct-attribute",
+        tag: "setting",
+        handler: "delete_distinct",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings/searchable-attributes",
+        tag: "setting",
+        handler: "get_searchable",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings/searchable-attributes",
+        tag: "setting",
+        handler: "update_searchable",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("Option<Vec<String>>"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/settings/searchable-attributes",
+        tag: "setting",
+        handler: "delete_searchable",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings/displayed-attributes",
+        tag: "setting",
+        handler: "get_displayed",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings/displayed-attributes",
+        tag: "setting",
+        handler: "update_displayed",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("Option<HashSet<String>>"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/settings/displayed-attributes",
+        tag: "setting",
+        handler: "delete_displayed",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings/accept-new-fields",
+        tag: "setting",
+        handler: "get_accept_new_fields",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings/accept-new-fields",
+        tag: "setting",
+        handler: "update_accept_new_fields",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("Option<bool>"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/stats",
+        tag: "stats",
+        handler: "index_stats",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("IndexStatsResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/stats/stores",
+        tag: "stats",
+        handler: "index_stores_sizes",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/stats",
+        tag: "stats",
+        handler: "get_stats",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("StatsResult") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/metrics",
+        tag: "stats",
+        handler: "get_metrics",
+        key: Some("Private"),
+        write: false,
+        description: &[
+            "The request counts and latencies of the routes and the waits for the write transactions,",
+            "in the Prometheus text format.",
+        ],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: false, ty: None }],
+        fallible: false,
+    },
+    Route {
+        method: "GET",
+        path: "/version",
+        tag: "stats",
+        handler: "get_version",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("VersionResponse") }],
+        fallible: false,
+    },
+    Route {
+        method: "GET",
+        path: "/sys-info",
+        tag: "stats",
+        handler: "get_sys_info",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: false,
+    },
+    Route {
+        method: "GET",
+        path: "/sys-info/pretty",
+        tag: "stats",
+        handler: "get_sys_info_pretty",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: false,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings/stop-words",
+        tag: "stop_words",
+        handler: "get",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings/stop-words",
+        tag: "stop_words",
+        handler: "update",
+        key: Some("Settings"),
+        write: true,
+        description: &[
+            "Replaces the stop words by a list of words, or by a preset like \"en\" with the words to",
+            "`add` and to `remove`, as in the `stopWords` of the settings.",
+        ],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("BTreeSet<String>"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/settings/stop-words",
+        tag: "stop_words",
+        handler: "delete",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings/synonyms",
+        tag: "synonym",
+        handler: "get",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings/synonyms",
+        tag: "synonym",
+        handler: "update",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::Json("BTreeMap<String, Vec<String>>"),
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/indexes/{index_uid}/settings/synonyms",
+        tag: "synonym",
+        handler: "delete",
+        key: Some("Settings"),
+        write: true,
+        description: &[],
+        query: None,
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 202, json: true, ty: Some("IndexUpdateResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/settings/synonyms/export",
+        tag: "synonym",
+        handler: "export",
+        key: Some("Settings"),
+        write: false,
+        description: &[],
+        query: Some("ExportQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/indexes/{index_uid}/settings/synonyms/import",
+        tag: "synonym",
+        handler: "import",
+        key: Some("Settings"),
+        write: true,
+        description: &[
+            "Replaces the synonyms by the ones of the file, answering with the words whose",
+            "synonyms are added, removed or changed compared to the current synonyms.",
+            "A dry run only answers with the changes, nothing is enqueued.",
+        ],
+        query: Some("ImportQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::Raw,
+        responses: &[
+            Response { status: 200, json: true, ty: Some("ImportResponse") },
+            Response { status: 202, json: true, ty: Some("ImportResponse") },
+        ],
+        fallible: true,
+    },
+    Route {
+        method: "POST",
+        path: "/update-groups",
+        tag: "update_group",
+        handler: "apply_update_group",
+        key: Some("Private"),
+        write: true,
+        description: &[
+            "Applies document updates to several indexes, they are all visible at once or none is applied.",
+            "",
+            "The updates are not enqueued, they are applied before the response is sent. The group is",
+            "rejected if one of its indexes has enqueued updates, to keep the updates in order.",
+        ],
+        query: None,
+        path_params: None,
+        body: Body::Json("UpdateGroupRequest"),
+        responses: &[Response { status: 200, json: true, ty: Some("UpdateGroupResponse") }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/upload-sessions",
+        tag: "upload_session",
+        handler: "get_upload_sessions_usage",
+        key: Some("Admin"),
+        write: false,
+        description: &[
+            "Returns the number of upload sessions of all the indexes and the space used by their chunks.",
+        ],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "DELETE",
+        path: "/upload-sessions",
+        tag: "upload_session",
+        handler: "purge_upload_sessions",
+        key: Some("Admin"),
+        write: true,
+        description: &[
+            "Deletes the upload sessions that haven't received a chunk for the given number of days,",
+            "or all of them, and returns the space that has been freed.",
+        ],
+        query: Some("PurgeQuery"),
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/usage",
+        tag: "usage",
+        handler: "get_usage",
+        key: Some("Admin"),
+        write: false,
+        description: &[
+            "The searches and the indexing operations counted since the server started,",
+            "per API key and per index, and what the indexes currently store.",
+        ],
+        query: None,
+        path_params: None,
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/words",
+        tag: "words",
+        handler: "export_words",
+        key: Some("Private"),
+        write: false,
+        description: &[
+            "Streams the indexed words with their documents frequencies, one JSON object per line.",
+        ],
+        query: Some("ExportQuery"),
+        path_params: Some("IndexParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: false, ty: None }],
+        fallible: true,
+    },
+    Route {
+        method: "GET",
+        path: "/indexes/{index_uid}/words/{word}",
+        tag: "words",
+        handler: "get_word",
+        key: Some("Private"),
+        write: false,
+        description: &[],
+        query: Some("WordQuery"),
+        path_params: Some("WordParam"),
+        body: Body::None,
+        responses: &[Response { status: 200, json: true, ty: Some("WordResponse") }],
+        fallible: true,
+    },
+];
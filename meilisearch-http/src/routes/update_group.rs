@@ -0,0 +1,134 @@
+use actix_web::{web, HttpResponse};
+use actix_web_macros::post;
+use indexmap::IndexMap;
+use meilisearch_core::update::{self, UpdateData};
+use meilisearch_core::{Error, UpdateGroupOutcome, UpdateStatus};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ResponseError;
+use crate::helpers::{run_blocking, Authentication};
+use crate::routes::quota::check_data_quota;
+use crate::Data;
+
+type Document = IndexMap<String, Value>;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(apply_update_group);
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", deny_unknown_fields)]
+enum GroupUpdate {
+    #[serde(rename_all = "camelCase")]
+    DocumentsAddition { index_uid: String, documents: Vec<Document> },
+    #[serde(rename_all = "camelCase")]
+    DocumentsPartial { index_uid: String, documents: Vec<Document> },
+    #[serde(rename_all = "camelCase")]
+    DocumentsDeletion { index_uid: String, document_ids: Vec<Value> },
+}
+
+impl GroupUpdate {
+    fn into_update_data(self) -> Result<(String, UpdateData), ResponseError> {
+        match self {
+            GroupUpdate::DocumentsAddition { index_uid, documents } => {
+                Ok((index_uid, UpdateData::DocumentsAddition(documents)))
+            }
+            GroupUpdate::DocumentsPartial { index_uid, documents } => {
+                Ok((index_uid, UpdateData::DocumentsPartial(documents)))
+            }
+            GroupUpdate::DocumentsDeletion { index_uid, document_ids } => {
+                let mut ids = Vec::with_capacity(document_ids.len());
+                for document_id in document_ids {
+                    let document_id = update::value_to_string(&document_id);
+                    ids.push(update::compute_document_id(&document_id).map_err(Error::Serializer)?);
+                }
+                Ok((index_uid, UpdateData::DocumentsDeletion(ids)))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct UpdateGroupRequest {
+    updates: Vec<GroupUpdate>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GroupUpdateStatus {
+    index_uid: String,
+    #[serde(flatten)]
+    status: UpdateStatus,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateGroupResponse {
+    applied: bool,
+    updates: Vec<GroupUpdateStatus>,
+}
+
+/// Applies document updates to several indexes, they are all visible at once or none is applied.
+///
+/// The updates are not enqueued, they are applied before the response is sent. The group is
+/// rejected if one of its indexes has enqueued updates, to keep the updates in order.
+#[post("/update-groups", wrap = "Authentication::Private")]
+async fn apply_update_group(
+    data: web::Data<Data>,
+    body: web::Json<UpdateGroupRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    let mut group = Vec::new();
+    for update in body.into_inner().updates {
+        let (index_uid, update) = update.into_update_data()?;
+        data.check_writable(&index_uid)?;
+        group.push((index_uid, update));
+    }
+    if group.is_empty() {
+        return Err(ResponseError::bad_request("An update group must contain at least one update"));
+    }
+
+    let outcome = run_blocking("update group", move || -> Result<_, ResponseError> {
+        let reader = data.main_read_txn()?;
+        for (index_uid, update) in &group {
+            let is_addition = match update {
+                UpdateData::DocumentsAddition(_) | UpdateData::DocumentsPartial(_) => true,
+                _ => false,
+            };
+            // the missing indexes are reported by the group itself
+            if let (true, Some(index)) = (is_addition, data.db.open_index(index_uid)) {
+                check_data_quota(&data, &index, &reader, index_uid)?;
+            }
+        }
+        drop(reader);
+
+        Ok(data.db.apply_update_group(group)?)
+    })
+    .await?;
+
+    let results = match outcome {
+        UpdateGroupOutcome::Processed(results) => results,
+        UpdateGroupOutcome::IndexNotFound(index_uid) => return Err(ResponseError::index_not_found(index_uid)),
+        UpdateGroupOutcome::PendingUpdates(index_uid) => {
+            return Err(ResponseError::conflict(format!(
+                "The index {} has enqueued updates, the group can be applied once they are processed",
+                index_uid,
+            )))
+        }
+    };
+
+    let applied = results.iter().all(|(_, result)| result.error.is_none());
+    let updates = results
+        .into_iter()
+        .map(|(index_uid, content)| {
+            let status = match content.error {
+                Some(_) => UpdateStatus::Failed { content },
+                None => UpdateStatus::Processed { content },
+            };
+            GroupUpdateStatus { index_uid, status }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(UpdateGroupResponse { applied, updates }))
+}
@@ -0,0 +1,46 @@
+use actix_web::web;
+use actix_web::HttpResponse;
+use actix_web_macros::{delete, get};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+
+use crate::error::ResponseError;
+use crate::helpers::{run_blocking, upload_sessions};
+use crate::helpers::Authentication;
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_upload_sessions_usage).service(purge_upload_sessions);
+}
+
+/// Returns the number of upload sessions of all the indexes and the space used by their chunks.
+#[get("/upload-sessions", wrap = "Authentication::Admin")]
+async fn get_upload_sessions_usage(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
+    let db_path = data.db_path.clone();
+    let usage = run_blocking("upload sessions listing", move || upload_sessions::usage(&db_path)).await?;
+
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct PurgeQuery {
+    older_than_days: Option<u32>,
+}
+
+/// Deletes the upload sessions that haven't received a chunk for the given number of days,
+/// or all of them, and returns the space that has been freed.
+#[delete("/upload-sessions", wrap = "Authentication::Admin")]
+async fn purge_upload_sessions(
+    data: web::Data<Data>,
+    params: web::Query<PurgeQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let db_path = data.db_path.clone();
+    let idle_since = params.older_than_days.map(|days| Utc::now() - Duration::days(days.into()));
+    let purged = run_blocking("upload sessions purge", move || {
+        upload_sessions::purge_sessions(&db_path, idle_since)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(purged))
+}
@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+use actix_web::{web, HttpResponse};
+use actix_web_macros::get;
+use serde::Serialize;
+
+use crate::error::ResponseError;
+use crate::helpers::usage::UsageCounters;
+use crate::helpers::{run_blocking, Authentication};
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_usage);
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexUsage {
+    #[serde(flatten)]
+    counters: UsageCounters,
+    number_of_documents: u64,
+    /// The bytes stored by the index, its pending updates included.
+    data_size: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageResponse {
+    keys: BTreeMap<String, UsageCounters>,
+    indexes: BTreeMap<String, IndexUsage>,
+}
+
+/// The searches and the indexing operations counted since the server started,
+/// per API key and per index, and what the indexes currently store.
+#[get("/usage", wrap = "Authentication::Admin")]
+async fn get_usage(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
+    let response = run_blocking("usage computation", move || -> Result<_, ResponseError> {
+        let reader = data.main_read_txn()?;
+        let update_reader = data.update_read_txn()?;
+
+        let mut indexes = BTreeMap::new();
+        for index_uid in data.db.indexes_uids() {
+            if let Some(index) = data.db.open_index(&index_uid) {
+                let usage = IndexUsage {
+                    counters: data.usage.index(&index_uid),
+                    number_of_documents: index.main.number_of_documents(&reader)?,
+                    data_size: index.stores_sizes(&reader, &update_reader)?.total_bytes(),
+                };
+                indexes.insert(index_uid, usage);
+            }
+        }
+
+        Ok(UsageResponse { keys: data.usage.keys(), indexes })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(response))
+}
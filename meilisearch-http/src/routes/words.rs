@@ -0,0 +1,192 @@
+use std::collections::HashSet;
+use std::thread;
+
+use actix_web::{web, HttpResponse};
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::SinkExt;
+use actix_web_macros::get;
+use indexmap::IndexMap;
+use meilisearch_core::{DocIndex, DocumentId, Index};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::ResponseError;
+use crate::helpers::Authentication;
+use crate::routes::IndexParam;
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(export_words).service(get_word);
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ExportQuery {
+    prefix: Option<String>,
+    min_documents: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedWord<'a> {
+    word: &'a str,
+    number_of_documents: usize,
+}
+
+type Chunk = Result<Bytes, ResponseError>;
+
+/// Streams the indexed words with their documents frequencies, one JSON object per line.
+#[get("/indexes/{index_uid}/words", wrap = "Authentication::Private")]
+async fn export_words(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<ExportQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data
+        .db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    let params = params.into_inner();
+    let data = data.get_ref().clone();
+    let (mut sender, receiver) = mpsc::channel::<Chunk>(64);
+
+    // the words are read on another thread, the channel makes it
+    // wait for the client to consume the lines already sent
+    thread::spawn(move || {
+        if let Err(e) = send_words(&data, &index, &params, &mut sender) {
+            let _ = block_on(sender.send(Err(e)));
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(receiver))
+}
+
+fn send_words(
+    data: &Data,
+    index: &Index,
+    params: &ExportQuery,
+    sender: &mut mpsc::Sender<Chunk>,
+) -> Result<(), ResponseError> {
+    let reader = data.main_read_txn()?;
+    let prefix = params.prefix.as_deref().unwrap_or("").to_lowercase();
+    let min_documents = params.min_documents.unwrap_or(0);
+
+    index.for_each_word(&reader, prefix.as_bytes(), |word, number_of_documents| {
+        if number_of_documents < min_documents {
+            return true;
+        }
+
+        let word = String::from_utf8_lossy(word);
+        let exported = ExportedWord { word: &word, number_of_documents };
+        let mut line = serde_json::to_vec(&exported).unwrap_or_default();
+        line.push(b'\n');
+
+        // the client disconnected
+        block_on(sender.send(Ok(Bytes::from(line)))).is_ok()
+    })?;
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct WordParam {
+    index_uid: String,
+    word: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct WordQuery {
+    sample: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WordResponse {
+    word: String,
+    is_stop_word: bool,
+    number_of_documents: usize,
+    number_of_occurrences: usize,
+    /// The number of occurrences of the word in each attribute.
+    attributes: IndexMap<String, usize>,
+    /// The primary keys of the first documents containing the word.
+    sample_documents: Vec<Value>,
+}
+
+#[get("/indexes/{index_uid}/words/{word}", wrap = "Authentication::Private")]
+async fn get_word(
+    data: web::Data<Data>,
+    path: web::Path<WordParam>,
+    params: web::Query<WordQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let index = data
+        .db
+        .open_index(&path.index_uid)
+        .ok_or(ResponseError::index_not_found(&path.index_uid))?;
+
+    // the words are lowercased when indexed
+    let word = path.word.to_lowercase();
+    let sample = params.sample.unwrap_or(10).min(100);
+
+    let reader = data.main_read_txn()?;
+
+    let schema = index
+        .main
+        .schema(&reader)?
+        .ok_or(ResponseError::internal("schema not found"))?;
+
+    let is_stop_word = index
+        .main
+        .stop_words_fst(&reader)?
+        .map_or(false, |stop_words| stop_words.contains(&word));
+
+    let postings = index.postings_lists.postings_list(&reader, word.as_bytes())?;
+
+    // stop words are never indexed, they are answered with empty postings
+    let (docids, matches): (&[DocumentId], &[DocIndex]) = match &postings {
+        Some(postings) => (postings.docids.as_slice(), postings.matches.as_slice()),
+        None if is_stop_word => (&[], &[]),
+        None => return Err(ResponseError::not_found(format!("word {} is not indexed", word))),
+    };
+
+    let mut counts = Vec::new();
+    for (pos, field_id) in schema.indexed().iter().enumerate() {
+        let count = matches.iter().filter(|m| m.attribute as usize == pos).count();
+        if count != 0 {
+            if let Some(name) = schema.name(*field_id) {
+                counts.push((name.to_string(), count));
+            }
+        }
+    }
+    let attributes = counts.into_iter().collect();
+
+    let number_of_documents = docids.len();
+
+    let mut sample_documents = Vec::new();
+    if let Some(primary_key) = schema.primary_key() {
+        let attributes: HashSet<_> = std::iter::once(primary_key).collect();
+        for document_id in docids.iter().take(sample) {
+            let document: Option<IndexMap<String, Value>> =
+                index.document(&reader, Some(&attributes), *document_id)?;
+            if let Some(mut document) = document {
+                if let Some(value) = document.remove(primary_key) {
+                    sample_documents.push(value);
+                }
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(WordResponse {
+        word,
+        is_stop_word,
+        number_of_documents,
+        number_of_occurrences: matches.len(),
+        attributes,
+        sample_documents,
+    }))
+}
@@ -0,0 +1,263 @@
+//! Periodic snapshots of the database.
+//!
+//! A snapshot is a compacted copy of the main and update environments, written
+//! in a directory named after its creation date. Finished snapshots can be uploaded
+//! to an S3-compatible bucket and only the most recent ones are kept, locally and remotely.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use crossbeam_channel::Receiver;
+use log::{error, info, warn};
+
+use crate::helpers::background::wait_or_stop;
+use crate::helpers::s3::{Bucket, S3Error};
+use crate::option::Opt;
+use crate::Data;
+
+const ENVIRONMENTS: [&str; 2] = ["main", "update"];
+const NAME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+#[derive(Debug, Clone)]
+pub struct SnapshotOptions {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    pub retention: usize,
+    pub bucket: Option<Bucket>,
+    pub prefix: String,
+}
+
+impl SnapshotOptions {
+    pub fn from_opt(opt: &Opt) -> Option<SnapshotOptions> {
+        let dir = opt.snapshot_dir.as_ref()?;
+
+        let bucket = match (&opt.s3_bucket, &opt.s3_access_key, &opt.s3_secret_key) {
+            (Some(name), Some(access_key), Some(secret_key)) => Some(Bucket::new(
+                &opt.s3_endpoint,
+                name,
+                &opt.s3_region,
+                access_key,
+                secret_key,
+            )),
+            _ => None,
+        };
+
+        Some(SnapshotOptions {
+            dir: PathBuf::from(dir),
+            interval: Duration::from_secs(opt.snapshot_interval_sec),
+            retention: opt.snapshot_retention,
+            bucket,
+            prefix: opt.s3_prefix.trim_matches('/').to_string(),
+        })
+    }
+}
+
+/// Creates a snapshot every `interval`, until `stop` is disconnected.
+pub fn schedule_snapshots(data: Data, options: SnapshotOptions, stop: Receiver<()>) {
+    loop {
+        if wait_or_stop(&stop, options.interval) {
+            break;
+        }
+
+        let name = Utc::now().format(NAME_FORMAT).to_string();
+        let path = options.dir.join(&name);
+
+        if let Err(e) = create_snapshot(&data, &path) {
+            error!("Snapshot {:?} failed: {}", path, e);
+            let _ = fs::remove_dir_all(&path);
+            continue;
+        }
+        info!("Snapshot {:?} created", path);
+
+        if let Err(e) = prune_local_snapshots(&options.dir, options.retention) {
+            warn!("Impossible to remove the old snapshots: {}", e);
+        }
+
+        if let Some(bucket) = &options.bucket {
+            let remote_prefix = remote_path(&options.prefix, &name);
+            match upload_snapshot(bucket, &path, &remote_prefix) {
+                Ok(()) => info!("Snapshot {:?} uploaded to {:?}", path, remote_prefix),
+                Err(e) => error!("Snapshot {:?} upload failed: {}", path, e),
+            }
+
+            if let Err(e) = prune_remote_snapshots(bucket, &options.prefix, options.retention) {
+                warn!("Impossible to remove the old uploaded snapshots: {}", e);
+            }
+        }
+    }
+}
+
+fn create_snapshot(data: &Data, path: &Path) -> io::Result<()> {
+    fs::create_dir_all(path)?;
+    data.db
+        .copy_and_compact_to_path(path)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    Ok(())
+}
+
+fn remote_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        format!("{}/", name)
+    } else {
+        format!("{}/{}/", prefix, name)
+    }
+}
+
+fn upload_snapshot(bucket: &Bucket, path: &Path, remote_prefix: &str) -> Result<(), S3Error> {
+    for env in ENVIRONMENTS.iter() {
+        let file_path = path.join(env).join("data.mdb");
+        let file = File::open(&file_path)?;
+        let length = file.metadata()?.len();
+        let key = format!("{}{}/data.mdb", remote_prefix, env);
+        bucket.put_object(&key, file, length)?;
+    }
+    Ok(())
+}
+
+/// Returns the creation date of a snapshot from its name, none if it isn't the name of a snapshot.
+fn snapshot_date(name: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDateTime::parse_from_str(name, NAME_FORMAT).ok()?;
+    Some(Utc.from_utc_datetime(&date))
+}
+
+/// Returns the snapshots directories, the other entries of the directory are left out.
+fn local_snapshots(dir: &Path) -> io::Result<Vec<(DateTime<Utc>, PathBuf)>> {
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        if let Some(date) = snapshot_date(&entry.file_name().to_string_lossy()) {
+            snapshots.push((date, entry.path()));
+        }
+    }
+    Ok(snapshots)
+}
+
+/// Returns the creation date of the most recent local snapshot, if any.
+pub fn last_snapshot_date(dir: &Path) -> io::Result<Option<DateTime<Utc>>> {
+    Ok(local_snapshots(dir)?.into_iter().map(|(date, _)| date).max())
+}
+
+/// Removes the oldest snapshots, the directories that are not named like a snapshot are kept.
+fn prune_local_snapshots(dir: &Path, retention: usize) -> io::Result<()> {
+    let mut snapshots = local_snapshots(dir)?;
+    snapshots.sort();
+
+    let outdated = snapshots.len().saturating_sub(retention);
+    for (_, path) in snapshots.drain(..outdated) {
+        fs::remove_dir_all(path)?;
+    }
+
+    Ok(())
+}
+
+fn prune_remote_snapshots(bucket: &Bucket, prefix: &str, retention: usize) -> Result<(), S3Error> {
+    let prefix = if prefix.is_empty() { String::new() } else { format!("{}/", prefix) };
+
+    // the prefixes are listed with the prefix they are under and a trailing slash
+    let mut snapshots: Vec<_> = bucket
+        .list_prefixes(&prefix)?
+        .into_iter()
+        .filter(|snapshot| {
+            let name = snapshot[prefix.len().min(snapshot.len())..].trim_end_matches('/');
+            snapshot_date(name).is_some()
+        })
+        .collect();
+    snapshots.sort();
+
+    let outdated = snapshots.len().saturating_sub(retention);
+    for snapshot in snapshots.drain(..outdated) {
+        for env in ENVIRONMENTS.iter() {
+            bucket.delete_object(&format!("{}{}/data.mdb", snapshot, env))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads the snapshot found at `url` into `db_path`.
+///
+/// The url must point to a snapshot directory, the `main/data.mdb` and
+/// `update/data.mdb` files are fetched from it. Nothing is done if a
+/// database already exists at `db_path`.
+pub fn restore_from_url(url: &str, db_path: &Path) -> io::Result<bool> {
+    if db_path.exists() {
+        warn!("A database already exists at {:?}, the snapshot will not be restored", db_path);
+        return Ok(false);
+    }
+
+    download_database(url, db_path)?;
+    Ok(true)
+}
+
+/// Downloads the environments of the database hosted at `url`, laid out as a snapshot,
+/// they are written in a temporary directory then moved to `db_path` once all received.
+pub fn download_database(url: &str, db_path: &Path) -> io::Result<()> {
+    let url = url.trim_end_matches('/');
+    let tmp_path = db_path.with_extension("restoring");
+    let _ = fs::remove_dir_all(&tmp_path);
+
+    for env in ENVIRONMENTS.iter() {
+        let file_url = format!("{}/{}/data.mdb", url, env);
+        let response = ureq::get(&file_url).call();
+        if !response.ok() {
+            let message = format!("GET {} returned the status {}", file_url, response.status());
+            return Err(io::Error::new(io::ErrorKind::Other, message));
+        }
+
+        let env_path = tmp_path.join(env);
+        fs::create_dir_all(&env_path)?;
+        let mut file = File::create(env_path.join("data.mdb"))?;
+        io::copy(&mut response.into_reader(), &mut file)?;
+    }
+
+    fs::rename(tmp_path, db_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_keeps_most_recent() {
+        let dir = tempdir::TempDir::new("snapshots").unwrap();
+        for name in &["20200501-000000", "20200502-000000", "20200503-000000"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+
+        prune_local_snapshots(dir.path(), 2).unwrap();
+
+        let mut remaining: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort();
+
+        assert_eq!(remaining, vec!["20200502-000000", "20200503-000000"]);
+    }
+
+    #[test]
+    fn prune_keeps_the_other_directories() {
+        let dir = tempdir::TempDir::new("snapshots").unwrap();
+        for name in &["20200501-000000", "20200502-000000", "archives", "0-manual"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+
+        prune_local_snapshots(dir.path(), 1).unwrap();
+
+        let mut remaining: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        remaining.sort();
+
+        assert_eq!(remaining, vec!["0-manual", "20200502-000000", "archives"]);
+        assert_eq!(last_snapshot_date(dir.path()).unwrap(), snapshot_date("20200502-000000"));
+    }
+}
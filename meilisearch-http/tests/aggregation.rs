@@ -0,0 +1,50 @@
+use serde_json::json;
+
+mod common;
+
+#[actix_rt::test]
+async fn filtered_documents_are_aggregated() {
+    let mut server = common::Server::with_uid("orders");
+    server.create_index(json!({ "uid": "orders", "primaryKey": "id" })).await;
+    let body = json!([
+        { "id": 1, "brand": "Nike", "price": 100, "status": "paid" },
+        { "id": 2, "brand": "Adidas", "price": 50, "status": "paid" },
+        { "id": 3, "brand": "Nike", "price": "30", "status": "paid" },
+        { "id": 4, "brand": "Puma", "price": 80, "status": "refunded" },
+        { "id": 5, "brand": "Puma", "status": "paid" },
+        { "id": 6, "brand": "Puma", "price": "NaN", "status": "paid" },
+        { "id": 7, "brand": "Nike", "price": "inf", "status": "paid" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+    server.update_all_settings(json!({ "attributesForFaceting": ["brand"] })).await;
+
+    let url = "/indexes/orders/aggregate?filters=status%20%3D%20paid&sum=price&avg=price&min=price&max=price&cardinality=brand";
+    let (response, status_code) = server.get_request(url).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response, json!({
+        "count": 6,
+        "sum": { "price": 180.0 },
+        "avg": { "price": 60.0 },
+        "min": { "price": 30.0 },
+        "max": { "price": 100.0 },
+        "cardinality": { "brand": 3 },
+    }));
+
+    let (response, status_code) = server.get_request("/indexes/orders/aggregate?filters=price%20%3E%201000&avg=price").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response, json!({ "count": 0, "avg": { "price": null } }));
+
+    let (response, _status_code) = server.get_request("/indexes/orders/aggregate").await;
+    assert_eq!(response, json!({ "count": 7 }));
+
+    let url = "/indexes/orders/aggregate?facetFilters=%5B%22brand%3ANike%22%5D&filters=status%20%3D%20paid&sum=price&cardinality=brand";
+    let (response, status_code) = server.get_request(url).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response, json!({ "count": 3, "sum": { "price": 130.0 }, "cardinality": { "brand": 1 } }));
+
+    let (_response, status_code) = server.get_request("/indexes/orders/aggregate?sum=unknown").await;
+    assert_eq!(status_code, 400);
+
+    let (_response, status_code) = server.get_request("/indexes/orders/aggregate?cardinality=status").await;
+    assert_eq!(status_code, 400);
+}
@@ -0,0 +1,30 @@
+use serde_json::json;
+
+mod common;
+
+#[actix_rt::test]
+async fn settings_routes_are_restricted_to_the_allowlist() {
+    let mut server = common::Server::with_uid("movies");
+    server.populate_movies().await;
+
+    let (_response, status_code) = server.put_request("/allowed-ips", json!({ "admin": ["10.0.0.0/8"] })).await;
+    assert_eq!(status_code, 200);
+
+    // the test requests have no peer address, they are refused on the admin routes
+    let (response, status_code) = server.get_request("/indexes/movies/settings").await;
+    assert_eq!(status_code, 403);
+    assert!(response["message"].as_str().unwrap().contains("is not allowed"));
+
+    let (_response, status_code) = server.get_request("/indexes/movies/settings/stop-words").await;
+    assert_eq!(status_code, 403);
+
+    let (_response, status_code) = server.get_request("/allowed-ips").await;
+    assert_eq!(status_code, 403);
+
+    // the policy is the one of the route, not guessed from the url
+    let (_response, status_code) = server.get_request("/indexes/movies/documents/settings").await;
+    assert_eq!(status_code, 404);
+
+    let (_response, status_code) = server.search("q=settings").await;
+    assert_eq!(status_code, 200);
+}
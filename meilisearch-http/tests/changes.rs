@@ -0,0 +1,93 @@
+use serde_json::json;
+
+mod common;
+
+#[actix_rt::test]
+async fn tail_documents_changes() {
+    let mut server = common::Server::with_uid("test");
+    server.create_index(json!({ "uid": "test", "primaryKey": "id" })).await;
+
+    let (response, status_code) = server.get_changes(0).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["changes"], json!([]));
+    assert_eq!(response["nextFrom"], 0);
+
+    let body = json!([
+        { "id": 1, "title": "hello" },
+        { "id": 2, "title": "world" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+    server.delete_document(1).await;
+
+    let (response, status_code) = server.get_changes(0).await;
+    assert_eq!(status_code, 200);
+
+    let changes = response["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[0]["sequence"], 0);
+    assert_eq!(changes[0]["type"], "documentsAddition");
+    // the documents are read when the changes are, the deleted one is no longer part of the addition
+    assert_eq!(changes[0]["documents"], json!([{ "id": 2, "title": "world" }]));
+    assert_eq!(changes[1]["sequence"], 1);
+    assert_eq!(changes[1]["type"], "documentsDeletion");
+    assert_eq!(changes[1]["documents"], json!([1]));
+    assert_eq!(response["nextFrom"], 2);
+
+    // resume from the last seen sequence
+    let (response, _) = server.get_changes(1).await;
+    let changes = response["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["updateId"], 1);
+}
+
+#[actix_rt::test]
+async fn oldest_changes_are_removed_past_the_retention() {
+    let mut server = common::Server::with_options("test", |opt| opt.changes_retention = 1);
+    server.create_index(json!({ "uid": "test", "primaryKey": "id" })).await;
+
+    // the log is trimmed after every update, the first change is removed once the second
+    // one is applied, whether the second one is removed depends on when the third one is read
+    server.add_or_replace_multiple_documents(json!([{ "id": 1, "title": "hello" }])).await;
+    server.add_or_replace_multiple_documents(json!([{ "id": 2, "title": "world" }])).await;
+    server.add_or_replace_multiple_documents(json!([{ "id": 3, "title": "!" }])).await;
+
+    // the changes are read from the first one kept by default
+    let (response, status_code) = server.get_request("/indexes/test/changes").await;
+    assert_eq!(status_code, 200);
+    let changes = response["changes"].as_array().unwrap();
+    assert!(changes.iter().all(|change| change["sequence"] != 0));
+    assert_eq!(changes.last().unwrap()["documents"], json!([{ "id": 3, "title": "!" }]));
+
+    // the sequence numbers continue after the trimmed changes
+    assert_eq!(changes.last().unwrap()["sequence"], 2);
+
+    let (response, status_code) = server.get_changes(0).await;
+    assert_eq!(status_code, 400);
+    assert!(response["message"].as_str().unwrap().contains("have been removed"));
+}
+
+#[actix_rt::test]
+async fn skipped_documents_are_not_part_of_the_change() {
+    let mut server = common::Server::with_uid("test");
+    server.create_index(json!({ "uid": "test", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "version": 1 },
+        { "id": 2, "version": 2 },
+    ])).await;
+
+    let body = json!([
+        { "id": 1, "version": 3 },
+        { "id": 2, "version": 3 },
+    ]);
+    let url = "/indexes/test/documents?precondition=version%20%3D%201";
+    let (response, status_code) = server.put_request(url, body).await;
+    assert_eq!(status_code, 202);
+    let update_id = response["updateId"].as_u64().unwrap();
+    server.wait_update_id(update_id).await;
+
+    let (response, status_code) = server.get_changes(1).await;
+    assert_eq!(status_code, 200);
+    let changes = response["changes"].as_array().unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0]["documents"], json!([{ "id": 1, "version": 3 }]));
+}
@@ -3,7 +3,7 @@
 use serde_json::{json, Value};
 use std::time::Duration;
 
-use actix_web::{http::StatusCode, test};
+use actix_web::{http::HeaderMap, http::Method, http::StatusCode, test};
 use meilisearch_core::DatabaseOptions;
 use meilisearch_http::data::Data;
 use meilisearch_http::option::Opt;
@@ -18,21 +18,66 @@ pub struct Server {
 
 impl Server {
     pub fn with_uid(uid: &str) -> Server {
+        Server::with_options(uid, |_| ())
+    }
+
+    /// Creates a server with the default test options, changed by the given function.
+    pub fn with_options(uid: &str, options: impl FnOnce(&mut Opt)) -> Server {
         let tmp_dir = TempDir::new("meilisearch").unwrap();
 
         let default_db_options = DatabaseOptions::default();
 
-        let opt = Opt {
+        let mut opt = Opt {
             db_path: tmp_dir.path().to_str().unwrap().to_string(),
             http_addr: "127.0.0.1:7700".to_owned(),
+            grpc_addr: None,
             master_key: None,
             env: "development".to_owned(),
             no_analytics: true,
             main_map_size: default_db_options.main_map_size,
             update_map_size: default_db_options.update_map_size,
+            encryption_key: None,
+            admin_allowed_ips: Vec::new(),
+            replicate_from: None,
+            replication_key: None,
+            snapshot_dir: None,
+            snapshot_interval_sec: 86400,
+            snapshot_retention: 5,
+            s3_endpoint: "https://s3.amazonaws.com".to_string(),
+            s3_region: "us-east-1".to_string(),
+            s3_bucket: None,
+            s3_prefix: String::new(),
+            s3_access_key: None,
+            s3_secret_key: None,
+            restore_from_url: None,
+            mount_indexes: Vec::new(),
+            search_threads: 4,
+            search_queue_size: 256,
+            shutdown_timeout_sec: 30,
+            warm_up: false,
+            max_readers: 126,
+            http_payload_size_limit: 10 * 1024 * 1024,
+            documents_payload_size_limit: None,
+            search_payload_size_limit: None,
+            settings_payload_size_limit: Some(64 * 1024),
+            upload_sessions_retention_days: 7,
+            upload_chunk_size_limit: None,
+            changes_retention: 100_000,
+            health_min_disk_space: 0,
+            index_max_data_size: None,
+            audit_log_path: None,
+            audit_log_max_size: 100 * 1024 * 1024,
+            audit_log_retention: 5,
+            search_log_sample_rate: 0.0,
+            search_log_redactions: Vec::new(),
+            otlp_endpoint: None,
+            search_snapshot_ttl_sec: 60,
+            search_snapshots_max: 32,
+            command: None,
         };
+        options(&mut opt);
 
-        let data = Data::new(opt.clone());
+        let data = Data::new(opt.clone()).unwrap();
 
         Server {
             uid: uid.to_string(),
@@ -113,7 +158,7 @@ impl Server {
             let (response, status_code) = self.get_update_status(update_id).await;
             assert_eq!(status_code, 200);
 
-            if response["status"] == "processed" || response["status"] == "error" {
+            if response["status"] == "processed" || response["status"] == "failed" || response["status"] == "error" {
                 eprintln!("{:#?}", response);
                 return;
             }
@@ -138,6 +183,19 @@ impl Server {
         (response, status_code)
     }
 
+    pub async fn get_request_text(&mut self, url: &str) -> (String, StatusCode) {
+        eprintln!("get_request_text: {}", url);
+
+        let mut app = test::init_service(meilisearch_http::create_app(&self.data).wrap(NormalizeSlashes)).await;
+
+        let req = test::TestRequest::get().uri(url).to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status().clone();
+
+        let body = test::read_body(res).await;
+        (String::from_utf8_lossy(&body).into_owned(), status_code)
+    }
+
     pub async fn post_request(&mut self, url: &str, body: Value) -> (Value, StatusCode) {
         eprintln!("post_request: {}", url);
 
@@ -155,6 +213,95 @@ impl Server {
         (response, status_code)
     }
 
+    /// Sends the request with the given headers, the headers of the response are returned too.
+    pub async fn get_request_with_headers(&mut self, url: &str, headers: &[(&str, &str)]) -> (Value, StatusCode, HeaderMap) {
+        eprintln!("get_request_with_headers: {}", url);
+
+        let mut app = test::init_service(meilisearch_http::create_app(&self.data).wrap(NormalizeSlashes)).await;
+
+        let mut req = test::TestRequest::get().uri(url);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let res = test::call_service(&mut app, req.to_request()).await;
+        let status_code = res.status().clone();
+        let response_headers = res.headers().clone();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code, response_headers)
+    }
+
+    pub async fn post_request_with_headers(&mut self, url: &str, body: Value, headers: &[(&str, &str)]) -> (Value, StatusCode) {
+        eprintln!("post_request_with_headers: {}", url);
+
+        let mut app = test::init_service(meilisearch_http::create_app(&self.data).wrap(NormalizeSlashes)).await;
+
+        let mut req = test::TestRequest::post().uri(url).set_json(&body);
+        for (name, value) in headers {
+            req = req.header(*name, *value);
+        }
+        let res = test::call_service(&mut app, req.to_request()).await;
+        let status_code = res.status().clone();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
+    /// Sends the body with its `Content-Length` header, like the HTTP clients do.
+    pub async fn post_request_with_length(&mut self, url: &str, body: Value) -> (Value, StatusCode) {
+        eprintln!("post_request_with_length: {}", url);
+
+        let mut app = test::init_service(meilisearch_http::create_app(&self.data).wrap(NormalizeSlashes)).await;
+
+        let body = body.to_string();
+        let req = test::TestRequest::post()
+            .uri(url)
+            .header("Content-Type", "application/json")
+            .header("Content-Length", body.len().to_string())
+            .set_payload(body)
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status().clone();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
+    /// Sends a request without a body, the raw body of the response is returned.
+    pub async fn raw_request(&mut self, method: &str, url: &str) -> (Vec<u8>, StatusCode) {
+        eprintln!("raw_request: {} {}", method, url);
+
+        let mut app = test::init_service(meilisearch_http::create_app(&self.data).wrap(NormalizeSlashes)).await;
+
+        let method = Method::from_bytes(method.as_bytes()).unwrap();
+        let req = test::TestRequest::with_uri(url).method(method).to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status().clone();
+
+        let body = test::read_body(res).await;
+        (body.to_vec(), status_code)
+    }
+
+    pub async fn post_request_text(&mut self, url: &str, body: &str) -> (Value, StatusCode) {
+        eprintln!("post_request_text: {}", url);
+
+        let mut app = test::init_service(meilisearch_http::create_app(&self.data).wrap(NormalizeSlashes)).await;
+
+        let req = test::TestRequest::post()
+            .uri(url)
+            .set_payload(body.to_string())
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status().clone();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
     pub async fn post_request_async(&mut self, url: &str, body: Value) -> (Value, StatusCode) {
         eprintln!("post_request_async: {}", url);
 
@@ -184,6 +331,23 @@ impl Server {
         (response, status_code)
     }
 
+    pub async fn put_request_text(&mut self, url: &str, body: &str) -> (Value, StatusCode) {
+        eprintln!("put_request_text: {}", url);
+
+        let mut app = test::init_service(meilisearch_http::create_app(&self.data).wrap(NormalizeSlashes)).await;
+
+        let req = test::TestRequest::put()
+            .uri(url)
+            .set_payload(body.to_string())
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status().clone();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
     pub async fn put_request_async(&mut self, url: &str, body: Value) -> (Value, StatusCode) {
         eprintln!("put_request_async: {}", url);
 
@@ -195,6 +359,23 @@ impl Server {
         (response, status_code)
     }
 
+    pub async fn patch_request(&mut self, url: &str, body: Value) -> (Value, StatusCode) {
+        eprintln!("patch_request: {}", url);
+
+        let mut app = test::init_service(meilisearch_http::create_app(&self.data).wrap(NormalizeSlashes)).await;
+
+        let req = test::TestRequest::patch()
+            .uri(url)
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status().clone();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
     pub async fn delete_request(&mut self, url: &str) -> (Value, StatusCode) {
         eprintln!("delete_request: {}", url);
 
@@ -235,6 +416,10 @@ impl Server {
         self.get_request(&url).await
     }
 
+    pub async fn search_multiple_indexes(&mut self, body: Value) -> (Value, StatusCode) {
+        self.post_request("/indexes/search", body).await
+    }
+
     pub async fn get_index(&mut self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}", self.uid);
         self.get_request(&url).await
@@ -245,6 +430,15 @@ impl Server {
         self.put_request(&url, body).await
     }
 
+    pub async fn rename_index(&mut self, uid: &str) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}", self.uid);
+        let (response, status_code) = self.patch_request(&url, json!({ "uid": uid })).await;
+        if status_code == 200 {
+            self.uid = uid.to_string();
+        }
+        (response, status_code)
+    }
+
     pub async fn delete_index(&mut self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}", self.uid);
         self.delete_request(&url).await
@@ -316,6 +510,11 @@ impl Server {
         self.post_request_async(&url, body).await;
     }
 
+    pub async fn get_changes(&mut self, from: u64) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/changes?from={}", self.uid, from);
+        self.get_request(&url).await
+    }
+
     pub async fn get_all_settings(&mut self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}/settings", self.uid);
         self.get_request(&url).await
@@ -326,6 +525,11 @@ impl Server {
         self.post_request_async(&url, body).await;
     }
 
+    pub async fn replace_all_settings(&mut self, body: Value) {
+        let url = format!("/indexes/{}/settings", self.uid);
+        self.put_request_async(&url, body).await;
+    }
+
     pub async fn delete_all_settings(&mut self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}/settings", self.uid);
         self.delete_request_async(&url).await
@@ -446,6 +650,11 @@ impl Server {
         self.get_request(&url).await
     }
 
+    pub async fn get_index_stores_sizes(&mut self) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/stats/stores", self.uid);
+        self.get_request(&url).await
+    }
+
     pub async fn list_keys(&mut self) -> (Value, StatusCode) {
         self.get_request("/keys").await
     }
@@ -175,3 +175,627 @@ async fn check_add_documents_with_nested_sequence() {
     assert_eq!(status_code, 200);
     assert_eq!(response["hits"], body);
 }
+
+#[actix_rt::test]
+async fn get_document_tokens() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([{ "id": 1, "title": "Wonder Woman" }]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, status_code) = server.get_request("/indexes/movies/documents/1/tokens").await;
+    assert_eq!(status_code, 200);
+
+    let expected = json!([
+        { "word": "wonder", "position": 0, "charIndex": 0, "charLength": 6 },
+        { "word": "woman", "position": 1, "charIndex": 7, "charLength": 5 },
+    ]);
+    assert_eq!(response["title"], expected);
+
+    let (_, status_code) = server.get_request("/indexes/movies/documents/2/tokens").await;
+    assert_eq!(status_code, 404);
+}
+
+
+#[actix_rt::test]
+async fn add_documents_with_high_priority() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([{ "id": 1, "title": "Wonder Woman" }]);
+    server.post_request_async("/indexes/movies/documents?priority=high", body).await;
+
+    let (response, _status_code) = server.get_request("/indexes/movies/documents/1").await;
+    assert_eq!(response["title"], "Wonder Woman");
+
+    let body = json!([{ "id": 2, "title": "Batman" }]);
+    let (_response, status_code) = server.post_request("/indexes/movies/documents?priority=urgent", body).await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn add_documents_with_upload_session() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let (response, status_code) = server.post_request("/indexes/movies/documents/sessions", json!({})).await;
+    assert_eq!(status_code, 201);
+    let session_id = response["sessionId"].as_str().unwrap().to_string();
+    let url = format!("/indexes/movies/documents/sessions/{}", session_id);
+
+    // the chunks can be sent in any order and sent again
+    let (_, status_code) = server.put_request_text(&format!("{}/chunks/1", url), r#"{ "id": 2, "title": "Batman" }]"#).await;
+    assert_eq!(status_code, 204);
+    let (_, status_code) = server.put_request_text(&format!("{}/chunks/0", url), r#"[{ "id": 1, "title": "Wonder"#).await;
+    assert_eq!(status_code, 204);
+
+    let (response, status_code) = server.post_request(&format!("{}/commit", url), json!({})).await;
+    assert_eq!(status_code, 400);
+    assert!(response["message"].as_str().unwrap().contains("invalid documents"));
+
+    let (_, status_code) = server.put_request_text(&format!("{}/chunks/0", url), r#"[{ "id": 1, "title": "Wonder Woman" },"#).await;
+    assert_eq!(status_code, 204);
+
+    let (response, _status_code) = server.get_request(&url).await;
+    assert_eq!(response["chunks"], json!([0, 1]));
+    // the documents have been refused, the session can still be changed
+    assert!(response.get("committedAt").is_none());
+
+    let (response, status_code) = server.post_request(&format!("{}/commit", url), json!({})).await;
+    assert_eq!(status_code, 202);
+    server.wait_update_id(response["updateId"].as_u64().unwrap()).await;
+
+    let (response, _status_code) = server.get_request("/indexes/movies/documents/2").await;
+    assert_eq!(response["title"], "Batman");
+
+    // the session is deleted once committed
+    let (_, status_code) = server.get_request(&url).await;
+    assert_eq!(status_code, 404);
+}
+
+#[actix_rt::test]
+async fn retried_upload_sessions_are_created_and_committed_once() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let headers = [("Idempotency-Key", "upload-42")];
+    let (response, status_code) = server
+        .post_request_with_headers("/indexes/movies/documents/sessions", json!({}), &headers)
+        .await;
+    assert_eq!(status_code, 201);
+    assert!(response["expiresAt"].is_string());
+    let session_id = response["sessionId"].as_str().unwrap().to_string();
+    let url = format!("/indexes/movies/documents/sessions/{}", session_id);
+    server.put_request_text(&format!("{}/chunks/0", url), r#"[{ "id": 1, "title": "Alien" }]"#).await;
+
+    // the retry gets the session back with its chunks
+    let (response, status_code) = server
+        .post_request_with_headers("/indexes/movies/documents/sessions", json!({}), &headers)
+        .await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["sessionId"], session_id.as_str());
+    assert_eq!(response["chunks"], json!([0]));
+
+    // the key can't be reused for another session
+    let (_response, status_code) = server
+        .post_request_with_headers("/indexes/movies/documents/sessions", json!({ "partial": true }), &headers)
+        .await;
+    assert_eq!(status_code, 409);
+
+    let commit_url = format!("{}/commit", url);
+    let headers = [("Idempotency-Key", "commit-42")];
+    let (response, status_code) = server.post_request_with_headers(&commit_url, json!({}), &headers).await;
+    assert_eq!(status_code, 202);
+    let update_id = response["updateId"].as_u64().unwrap();
+
+    // the session has been deleted, the retried commit gets its update back
+    let (response, status_code) = server.post_request_with_headers(&commit_url, json!({}), &headers).await;
+    assert_eq!(status_code, 202);
+    assert_eq!(response["updateId"].as_u64().unwrap(), update_id);
+}
+
+#[actix_rt::test]
+async fn purge_upload_sessions() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let (response, _status_code) = server.post_request("/indexes/movies/documents/sessions", json!({})).await;
+    let session_id = response["sessionId"].as_str().unwrap().to_string();
+    let url = format!("/indexes/movies/documents/sessions/{}", session_id);
+    let chunk = r#"[{ "id": 1, "title": "Wonder Woman" }]"#;
+    server.put_request_text(&format!("{}/chunks/0", url), chunk).await;
+
+    let (response, status_code) = server.get_request("/upload-sessions").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["sessions"], 1);
+    assert!(response["size"].as_u64().unwrap() > chunk.len() as u64);
+
+    // the session has just been created
+    let (response, status_code) = server.delete_request("/upload-sessions?olderThanDays=1").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["sessions"], 0);
+
+    let (response, status_code) = server.delete_request("/upload-sessions").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["sessions"], 1);
+
+    let (_, status_code) = server.get_request(&url).await;
+    assert_eq!(status_code, 404);
+
+    let (response, _status_code) = server.get_request("/upload-sessions").await;
+    assert_eq!(response, json!({ "sessions": 0, "size": 0 }));
+}
+
+#[actix_rt::test]
+async fn update_groups_are_applied_atomically() {
+    let mut server = common::Server::with_uid("products");
+    server.create_index(json!({ "uid": "products", "primaryKey": "id" })).await;
+    server.create_index(json!({ "uid": "prices", "primaryKey": "id" })).await;
+
+    let body = json!({
+        "updates": [
+            { "type": "documentsAddition", "indexUid": "products", "documents": [{ "id": 1, "title": "shoe" }] },
+            { "type": "documentsAddition", "indexUid": "prices", "documents": [{ "id": 1, "price": 42 }] },
+        ]
+    });
+    let (response, status_code) = server.post_request("/update-groups", body).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["applied"], true);
+    assert_eq!(response["updates"][0]["indexUid"], "products");
+    assert_eq!(response["updates"][0]["status"], "processed");
+
+    let (_response, status_code) = server.get_request("/indexes/prices/documents/1").await;
+    assert_eq!(status_code, 200);
+
+    // the prices document has no id, the products deletion is rolled back
+    let body = json!({
+        "updates": [
+            { "type": "documentsDeletion", "indexUid": "products", "documentIds": [1] },
+            { "type": "documentsAddition", "indexUid": "prices", "documents": [{ "price": 12 }] },
+        ]
+    });
+    let (response, status_code) = server.post_request("/update-groups", body).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["applied"], false);
+    assert_eq!(response["updates"][0]["status"], "failed");
+    assert_eq!(response["updates"][1]["status"], "failed");
+
+    let (_response, status_code) = server.get_request("/indexes/products/documents/1").await;
+    assert_eq!(status_code, 200);
+
+    let body = json!({ "updates": [{ "type": "documentsDeletion", "indexUid": "unknown", "documentIds": [1] }] });
+    let (_response, status_code) = server.post_request("/update-groups", body).await;
+    assert_eq!(status_code, 404);
+}
+
+#[actix_rt::test]
+async fn documents_are_checked_against_the_field_types() {
+    let mut server = common::Server::with_uid("products");
+    server.create_index(json!({ "uid": "products", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "fieldTypes": { "price": "number", "releasedAt": "date" } })).await;
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["fieldTypes"], json!({ "price": "number", "releasedAt": "date" }));
+
+    let body = json!([{ "id": 1, "price": 12, "releasedAt": "2020-04-01" }]);
+    let (response, _status_code) = server.post_request_async("/indexes/products/documents", body).await;
+    let (response, _status_code) = server.get_update_status(response["updateId"].as_u64().unwrap()).await;
+    assert_eq!(response["status"], "processed");
+
+    // the price is not coerced from the string
+    let body = json!([{ "id": 2, "price": "12", "releasedAt": "2020-04-01" }]);
+    let (response, _status_code) = server.post_request_async("/indexes/products/documents", body).await;
+    let (response, _status_code) = server.get_update_status(response["updateId"].as_u64().unwrap()).await;
+    assert_eq!(response["status"], "failed");
+    assert_eq!(response["error"], "the attribute \"price\" of the document 2 must be of type number");
+
+    let (_response, status_code) = server.get_document(2).await;
+    assert_eq!(status_code, 404);
+}
+
+#[actix_rt::test]
+async fn excluded_attributes_are_dropped() {
+    let mut server = common::Server::with_uid("products");
+    server.create_index(json!({ "uid": "products", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "excludedAttributes": ["thumbnail"] })).await;
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["excludedAttributes"], json!(["thumbnail"]));
+
+    server.add_or_replace_multiple_documents(json!([{ "id": 1, "title": "shoe", "thumbnail": "iVBORw0KGgo" }])).await;
+
+    let (response, status_code) = server.get_document(1).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response, json!({ "id": 1, "title": "shoe" }));
+
+    let (response, _status_code) = server.search("q=iVBORw0KGgo").await;
+    assert_eq!(response["nbHits"], 0);
+}
+
+#[actix_rt::test]
+async fn excluding_the_primary_key_keeps_the_documents() {
+    let mut server = common::Server::with_uid("products");
+    server.create_index(json!({ "uid": "products", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "excludedAttributes": ["id", "thumbnail"] })).await;
+
+    server.add_or_replace_multiple_documents(json!([{ "id": 1, "title": "shoe", "thumbnail": "iVBORw0KGgo" }])).await;
+
+    let (response, status_code) = server.get_document(1).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response, json!({ "id": 1, "title": "shoe" }));
+}
+
+#[actix_rt::test]
+async fn partial_updates_drop_the_attributes_excluded_after_the_addition() {
+    let mut server = common::Server::with_uid("products");
+    server.create_index(json!({ "uid": "products", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([{ "id": 1, "title": "shoe", "thumbnail": "iVBORw0KGgo" }])).await;
+    server.update_all_settings(json!({ "excludedAttributes": ["thumbnail"] })).await;
+
+    // the stored documents are kept as they were added
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response, json!({ "id": 1, "title": "shoe", "thumbnail": "iVBORw0KGgo" }));
+
+    server.add_or_update_multiple_documents(json!([{ "id": 1, "title": "boot" }])).await;
+
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response, json!({ "id": 1, "title": "boot" }));
+}
+
+#[actix_rt::test]
+async fn words_after_the_limit_are_not_indexed() {
+    let mut server = common::Server::with_uid("articles");
+    server.create_index(json!({ "uid": "articles", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "maxIndexedWords": { "body": 2 } })).await;
+
+    let document = json!({ "id": 1, "title": "hiking boots", "body": "waterproof leather boots" });
+    server.add_or_replace_multiple_documents(json!([document.clone()])).await;
+
+    let (response, _status_code) = server.search("q=leather").await;
+    assert_eq!(response["nbHits"], 1);
+    let (response, _status_code) = server.search("q=boots").await;
+    assert_eq!(response["nbHits"], 1);
+    let (response, _status_code) = server.search("q=waterproof%20boots&attributesToRetrieve=body").await;
+    assert_eq!(response["hits"][0]["body"], "waterproof leather boots");
+
+    // the text of the body is still stored in full
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response, document);
+
+    server.update_all_settings(json!({ "maxIndexedWords": { "*": 1 } })).await;
+    let (response, _status_code) = server.search("q=leather").await;
+    assert_eq!(response["nbHits"], 0);
+}
+
+#[actix_rt::test]
+async fn words_after_the_limit_are_highlighted() {
+    let mut server = common::Server::with_uid("articles");
+    server.create_index(json!({ "uid": "articles", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "maxIndexedWords": { "body": 2 } })).await;
+    let document = json!({ "id": 1, "title": "hiking boots", "body": "waterproof leather boots" });
+    server.add_or_replace_multiple_documents(json!([document])).await;
+
+    let (response, _status_code) = server.search("q=boots&attributesToHighlight=body").await;
+    assert_eq!(response["hits"][0]["_formatted"]["body"], "waterproof leather <em>boots</em>");
+
+    // without the limit the body is highlighted from the words found by the engine
+    server.update_all_settings(json!({ "maxIndexedWords": null })).await;
+    let (response, _status_code) = server.search("q=boots&attributesToHighlight=body").await;
+    assert_eq!(response["hits"][0]["_formatted"]["body"], "waterproof leather <em>boots</em>");
+}
+
+#[actix_rt::test]
+async fn duplicate_documents_are_reported() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "Alien" },
+        { "id": 2, "title": "Aliens" },
+        { "id": 1, "title": "Alien 3" },
+    ]);
+    let (response, status_code) = server.add_or_replace_multiple_documents_sync(body).await;
+    assert_eq!(status_code, 202);
+    let update_id = response["updateId"].as_u64().unwrap();
+    server.wait_update_id(update_id).await;
+
+    let (response, _status_code) = server.get_update_status(update_id).await;
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["duplicates"], json!([{ "documentId": 1, "duplicates": 1, "kept": 2 }]));
+
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["title"], "Alien 3");
+}
+
+#[actix_rt::test]
+async fn retried_additions_are_enqueued_once() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let url = "/indexes/movies/documents";
+    let headers = [("Idempotency-Key", "import-42")];
+    let body = json!([{ "id": 1, "title": "Alien" }]);
+
+    let (response, status_code) = server.post_request_with_headers(url, body.clone(), &headers).await;
+    assert_eq!(status_code, 202);
+    let update_id = response["updateId"].as_u64().unwrap();
+
+    // the retry returns the update enqueued by the first request
+    let (response, status_code) = server.post_request_with_headers(url, body, &headers).await;
+    assert_eq!(status_code, 202);
+    assert_eq!(response["updateId"].as_u64().unwrap(), update_id);
+
+    let (_response, status_code) = server.get_update_status(update_id + 1).await;
+    assert_eq!(status_code, 404);
+
+    // the key can't be reused for another request
+    let body = json!([{ "id": 2, "title": "Aliens" }]);
+    let (_response, status_code) = server.post_request_with_headers(url, body, &headers).await;
+    assert_eq!(status_code, 409);
+}
+
+#[actix_rt::test]
+async fn documents_not_matching_the_precondition_are_skipped() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Alien", "version": 7 },
+        { "id": 2, "title": "Aliens", "version": 3 },
+    ])).await;
+
+    let body = json!([
+        { "id": 1, "title": "Alien: director's cut", "version": 8 },
+        { "id": 2, "title": "Aliens: special edition", "version": 8 },
+        { "id": 3, "title": "Alien 3", "version": 1 },
+    ]);
+    let url = "/indexes/movies/documents?precondition=version%20%3D%207";
+    let (response, status_code) = server.put_request(url, body).await;
+    assert_eq!(status_code, 202);
+    let update_id = response["updateId"].as_u64().unwrap();
+    server.wait_update_id(update_id).await;
+
+    let (response, _status_code) = server.get_update_status(update_id).await;
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["skipped"], json!([2, 3]));
+
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["version"], 8);
+    let (response, _status_code) = server.get_document(2).await;
+    assert_eq!(response["version"], 3);
+    let (_response, status_code) = server.get_document(3).await;
+    assert_eq!(status_code, 404);
+
+    let url = "/indexes/movies/documents?precondition=version%20%3D";
+    let (_response, status_code) = server.put_request(url, json!([{ "id": 1 }])).await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn preconditions_are_checked_with_the_values_of_each_document() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Alien", "version": 7 },
+        { "id": 2, "title": "Aliens", "version": 3 },
+    ])).await;
+
+    // only the documents newer than the stored ones are written, the new ones are added
+    let body = json!([
+        { "id": 1, "title": "Alien: director's cut", "version": 8 },
+        { "id": 2, "title": "Aliens: outdated", "version": 2 },
+        { "id": 3, "title": "Alien 3", "version": 1 },
+        { "id": 4, "title": "Alien: Resurrection" },
+    ]);
+    let url = "/indexes/movies/documents?precondition=version%20%3C%20%7Bversion%7D&insertMissing=true";
+    let (response, status_code) = server.put_request(url, body).await;
+    assert_eq!(status_code, 202);
+    let update_id = response["updateId"].as_u64().unwrap();
+    server.wait_update_id(update_id).await;
+
+    let (response, _status_code) = server.get_update_status(update_id).await;
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["skipped"], json!([2]));
+
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["version"], 8);
+    let (response, _status_code) = server.get_document(2).await;
+    assert_eq!(response["version"], 3);
+    let (response, _status_code) = server.get_document(3).await;
+    assert_eq!(response["version"], 1);
+    let (_response, status_code) = server.get_document(4).await;
+    assert_eq!(status_code, 200);
+}
+
+#[actix_rt::test]
+async fn skipped_documents_are_not_reported_as_duplicates() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([{ "id": 1, "title": "Alien", "version": 7 }])).await;
+
+    let body = json!([
+        { "id": 1, "title": "Alien: director's cut", "version": 8 },
+        { "id": 1, "title": "Alien: outdated", "version": 6 },
+        { "id": 1, "title": "Alien: remastered", "version": 9 },
+    ]);
+    let url = "/indexes/movies/documents?precondition=version%20%3C%20%7Bversion%7D";
+    let (response, status_code) = server.put_request(url, body).await;
+    assert_eq!(status_code, 202);
+    let update_id = response["updateId"].as_u64().unwrap();
+    server.wait_update_id(update_id).await;
+
+    // the skipped document overwrites none of the others
+    let (response, _status_code) = server.get_update_status(update_id).await;
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["skipped"], json!([1]));
+    assert_eq!(response["duplicates"], json!([{ "documentId": 1, "duplicates": 1, "kept": 2 }]));
+
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["title"], "Alien: remastered");
+}
+
+#[actix_rt::test]
+async fn field_operations_are_applied_to_the_updated_documents() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Alien", "views": 40, "tags": ["horror", "space"] },
+    ])).await;
+
+    // the operations of a document updated twice are both applied
+    let body = json!([
+        { "id": 1, "$ops": { "views": { "increment": 1 }, "tags": { "remove": "horror" } } },
+        { "id": 1, "$ops": { "views": { "increment": 1 }, "tags": { "append": ["scifi"] } } },
+    ]);
+    let url = "/indexes/movies/documents?operationsAttribute=%24ops";
+    let (response, status_code) = server.put_request(url, body).await;
+    assert_eq!(status_code, 202);
+    server.wait_update_id(response["updateId"].as_u64().unwrap()).await;
+
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["views"], 42);
+    assert_eq!(response["tags"], json!(["space", "scifi"]));
+    assert!(response.get("$ops").is_none());
+
+    let body = json!([{ "id": 1, "$ops": { "title": { "increment": 1 } } }]);
+    let (response, status_code) = server.put_request(url, body).await;
+    assert_eq!(status_code, 202);
+    let update_id = response["updateId"].as_u64().unwrap();
+    server.wait_update_id(update_id).await;
+
+    let (response, _status_code) = server.get_update_status(update_id).await;
+    assert_eq!(response["status"], "failed");
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["title"], "Alien");
+}
+
+#[actix_rt::test]
+async fn field_operations_are_only_read_from_the_named_attribute() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([{ "id": 1, "title": "Alien", "views": 40 }])).await;
+
+    // without the parameter the attribute is a field like the others
+    let operations = json!({ "views": { "increment": 1 } });
+    server.add_or_update_multiple_documents(json!([{ "id": 1, "_operations": operations }])).await;
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["views"], 40);
+    assert_eq!(response["_operations"], operations);
+
+    // the replaced documents have no fields to operate on
+    let url = "/indexes/movies/documents?operationsAttribute=_operations";
+    let body = json!([{ "id": 1, "_operations": operations }]);
+    let (_response, status_code) = server.post_request(url, body).await;
+    assert_eq!(status_code, 400);
+
+    let url = "/indexes/movies/documents?operationsAttribute=id";
+    let (_response, status_code) = server.put_request(url, json!([{ "id": 1 }])).await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn encrypted_documents_are_read_back() {
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    let mut server = common::Server::with_options("movies", |opt| opt.encryption_key = Some(key.parse().unwrap()));
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "attributesForFaceting": ["genre"] })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Carol", "genre": "romance" },
+        { "id": 2, "title": "Wonder Woman", "genre": "action" },
+    ])).await;
+
+    let (response, _status_code) = server.get_document(2).await;
+    assert_eq!(response["title"], "Wonder Woman");
+
+    let (response, _status_code) = server.search("q=carol&facetFilters=[\"genre:romance\"]").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+    assert_eq!(response["hits"][0]["title"], "Carol");
+}
+
+#[actix_rt::test]
+async fn encrypted_documents_are_not_stored_in_clear() {
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    let dir = tempdir::TempDir::new("meilisearch").unwrap();
+    let db_path = dir.path().to_str().unwrap().to_string();
+    let mut server = common::Server::with_options("movies", |opt| {
+        opt.encryption_key = Some(key.parse().unwrap());
+        opt.db_path = db_path;
+    });
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "searchableAttributes": ["title"] })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Carol", "secret": "zanzibar-lagoon" },
+    ])).await;
+
+    // the attribute is not searchable, only the document, its update and its change hold it
+    for env in &["main", "update"] {
+        let bytes = std::fs::read(dir.path().join(env).join("data.mdb")).unwrap();
+        assert!(!bytes.windows(15).any(|window| window == b"zanzibar-lagoon"), "found in the {} env", env);
+    }
+
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["secret"], "zanzibar-lagoon");
+    let (response, _status_code) = server.get_request("/indexes/movies/changes").await;
+    assert_eq!(response["changes"][0]["documents"][0]["secret"], "zanzibar-lagoon");
+}
+
+#[actix_rt::test]
+async fn encrypted_upload_chunks_are_not_stored_in_clear() {
+    let key = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+    let dir = tempdir::TempDir::new("meilisearch").unwrap();
+    let db_path = dir.path().to_str().unwrap().to_string();
+    let mut server = common::Server::with_options("movies", |opt| {
+        opt.encryption_key = Some(key.parse().unwrap());
+        opt.db_path = db_path;
+    });
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let (response, status_code) = server.post_request("/indexes/movies/documents/sessions", json!({})).await;
+    assert_eq!(status_code, 201);
+    let session_id = response["sessionId"].as_str().unwrap().to_string();
+    let url = format!("/indexes/movies/documents/sessions/{}", session_id);
+    server.put_request_text(&format!("{}/chunks/0", url), r#"[{ "id": 1, "secret": "zanzibar-"#).await;
+    server.put_request_text(&format!("{}/chunks/1", url), r#"lagoon" }]"#).await;
+
+    let session_path = dir.path().join("upload-sessions").join(&session_id);
+    let bytes = std::fs::read(session_path.join("0000000000.chunk")).unwrap();
+    assert!(!bytes.windows(9).any(|window| window == b"zanzibar-"));
+
+    let (response, status_code) = server.post_request(&format!("{}/commit", url), json!({})).await;
+    assert_eq!(status_code, 202);
+    server.wait_update_id(response["updateId"].as_u64().unwrap()).await;
+
+    let (response, _status_code) = server.get_document(1).await;
+    assert_eq!(response["secret"], "zanzibar-lagoon");
+}
+
+#[actix_rt::test]
+async fn documents_additions_are_refused_over_the_quota() {
+    let mut server = common::Server::with_options("movies", |opt| opt.index_max_data_size = Some(1));
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Carol" },
+        { "id": 2, "title": "Wonder Woman" },
+    ])).await;
+
+    let (response, status_code) = server.get_request("/indexes/movies/quota").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["maxDataSize"], 1);
+    assert!(response["dataSize"].as_u64().unwrap() > 1);
+
+    let body = json!([{ "id": 3, "title": "Mad Max" }]);
+    let (response, status_code) = server.post_request("/indexes/movies/documents", body.clone()).await;
+    assert_eq!(status_code, 507);
+    assert!(response["message"].as_str().unwrap().contains("quota"));
+
+    let (response, _status_code) = server.search("q=carol").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+    let (_response, status_code) = server.delete_request_async("/indexes/movies/documents/2").await;
+    assert_eq!(status_code, 202);
+
+    // the quota of the index replaces the one of the server
+    let (_response, status_code) = server.put_request("/indexes/movies/quota", json!({ "maxDataSize": 1u64 << 40 })).await;
+    assert_eq!(status_code, 200);
+    let (_response, status_code) = server.post_request("/indexes/movies/documents", body).await;
+    assert_eq!(status_code, 202);
+}
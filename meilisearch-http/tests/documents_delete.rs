@@ -29,3 +29,34 @@ async fn delete_batch() {
     let (_response, status_code) = server.get_document(419704).await;
     assert_eq!(status_code, 404);
 }
+
+#[actix_rt::test]
+async fn deletions_are_audited() {
+    let audit_dir = tempdir::TempDir::new("audit").unwrap();
+    let audit_log_path = audit_dir.path().join("audit.log");
+    let path = audit_log_path.to_str().unwrap().to_string();
+    let mut server = common::Server::with_options("movies", |opt| opt.audit_log_path = Some(path));
+    server.populate_movies().await;
+
+    server.get_document(419704).await;
+    server.delete_document(419704).await;
+    server.delete_multiple_documents(serde_json::json!([512200, 181812])).await;
+
+    let content = std::fs::read_to_string(&audit_log_path).unwrap();
+    let entries: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+
+    // the reads are not audited
+    assert!(entries.iter().all(|entry| entry["method"] != "GET"));
+
+    let deletion = entries.iter().find(|entry| entry["route"] == "/indexes/movies/documents/419704").unwrap();
+    assert_eq!(deletion["method"], "DELETE");
+    assert_eq!(deletion["key"], "anonymous");
+    assert_eq!(deletion["index"], "movies");
+    assert_eq!(deletion["status"], 202);
+    assert_eq!(deletion["payloadSize"], 0);
+
+    let batch = entries.iter().find(|entry| entry["route"] == "/indexes/movies/documents/delete-batch").unwrap();
+    assert_eq!(batch["method"], "POST");
+    assert_eq!(batch["payloadSize"], "[512200,181812]".len());
+    assert_eq!(batch["payloadDigest"].as_str().unwrap().len(), 64);
+}
@@ -36,3 +36,17 @@ async fn test_healthyness() {
     let (_response, status_code) = server.get_health().await;
     assert_eq!(status_code, 200);
 }
+
+#[actix_rt::test]
+async fn health_dependency_checks() {
+    let mut server = common::Server::with_uid("movies");
+
+    let (response, status_code) = server.get_health().await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["status"], "available");
+    assert_eq!(response["checks"]["environments"]["status"], "ok");
+    assert_eq!(response["checks"]["updates"]["status"], "ok");
+    // the test servers have neither a minimum disk space nor snapshots
+    assert_eq!(response["checks"]["diskSpace"]["status"], "skipped");
+    assert_eq!(response["checks"]["snapshots"]["status"], "skipped");
+}
@@ -680,3 +680,299 @@ async fn check_first_update_should_bring_up_processed_status_after_first_docs_ad
     assert_eq!(status_code, 200);
     assert_eq!(response[0]["status"], "processed");
 }
+
+#[actix_rt::test]
+async fn index_stores_sizes() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "Carol" },
+        { "id": 2, "title": "Wonder Woman" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, status_code) = server.get_index_stores_sizes().await;
+    assert_eq!(status_code, 200);
+
+    // two documents with two fields each
+    assert_eq!(response["documentsFields"]["entries"], 4);
+    assert!(response["documentsFields"]["bytes"].as_u64().unwrap() > 0);
+    assert!(response["postingsLists"]["entries"].as_u64().unwrap() >= 3);
+    assert_eq!(response["synonyms"]["entries"], 0);
+    assert_eq!(response["updates"]["entries"], 0);
+    assert!(response["updatesResults"]["entries"].as_u64().unwrap() >= 1);
+}
+
+#[actix_rt::test]
+async fn rename_index() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.create_index(json!({ "uid": "series" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "Carol" },
+        { "id": 2, "title": "Wonder Woman" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (_response, status_code) = server.rename_index("series").await;
+    assert_eq!(status_code, 400);
+
+    let (_response, status_code) = server.rename_index("bad uid").await;
+    assert_eq!(status_code, 400);
+
+    let (response, status_code) = server.rename_index("films").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["uid"], "films");
+    // the name given by default follows the uid
+    assert_eq!(response["name"], "films");
+    assert_eq!(response["primaryKey"], "id");
+
+    let (_response, status_code) = server.get_request("/indexes/movies").await;
+    assert_eq!(status_code, 404);
+
+    // the documents are still there, and the new ones are indexed in the renamed index
+    let (response, status_code) = server.get_all_documents().await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response.as_array().unwrap().len(), 2);
+
+    server.add_or_replace_multiple_documents(json!([{ "id": 3, "title": "Shazam" }])).await;
+    let (response, _status_code) = server.search("q=shazam").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+}
+
+#[actix_rt::test]
+async fn clone_index() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.create_index(json!({ "uid": "series" })).await;
+    server.update_all_settings(json!({ "searchableAttributes": ["title"], "synonyms": { "hero": ["woman"] } })).await;
+
+    let body = json!([
+        { "id": 1, "title": "Carol" },
+        { "id": 2, "title": "Wonder Woman" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (_response, status_code) = server.post_request("/indexes/movies/clone", json!({ "uid": "series" })).await;
+    assert_eq!(status_code, 400);
+
+    let (_response, status_code) = server.post_request("/indexes/unknown/clone", json!({ "uid": "films" })).await;
+    assert_eq!(status_code, 404);
+
+    let (response, status_code) = server.post_request("/indexes/movies/clone", json!({ "uid": "films" })).await;
+    assert_eq!(status_code, 201);
+    assert_eq!(response["uid"], "films");
+    assert_eq!(response["name"], "films");
+    assert_eq!(response["primaryKey"], "id");
+
+    let (movies_settings, _status_code) = server.get_all_settings().await;
+    server.add_or_replace_multiple_documents(json!([{ "id": 3, "title": "Shazam" }])).await;
+
+    // the clone has its own stores, the documents added to the source aren't in it
+    let (response, status_code) = server.get_request("/indexes/films/documents").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response.as_array().unwrap().len(), 2);
+
+    let (response, _status_code) = server.get_request("/indexes/films/search?q=hero").await;
+    assert_eq!(response["hits"][0]["title"], "Wonder Woman");
+
+    let (settings, _status_code) = server.get_request("/indexes/films/settings").await;
+    assert_eq!(settings, movies_settings);
+
+    let (response, _status_code) = server.get_all_documents().await;
+    assert_eq!(response.as_array().unwrap().len(), 3);
+}
+
+#[actix_rt::test]
+async fn mounted_indexes_reject_writes() {
+    let mut server = common::Server::with_options("movies", |opt| {
+        opt.mount_indexes = vec!["movies=/artifacts/movies".parse().unwrap()];
+    });
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.create_index(json!({ "uid": "series", "primaryKey": "id" })).await;
+
+    let body = json!([{ "id": 1, "title": "Carol" }]);
+    let (response, status_code) = server.post_request("/indexes/movies/documents", body.clone()).await;
+    assert_eq!(status_code, 403);
+    assert_eq!(response["message"], "Index movies is mounted read-only");
+
+    let (_response, status_code) = server.post_request("/indexes/movies/settings", json!({ "distinctAttribute": "title" })).await;
+    assert_eq!(status_code, 403);
+    let (_response, status_code) = server.delete_request("/indexes/movies").await;
+    assert_eq!(status_code, 403);
+    let group = json!({ "updates": [{ "type": "documentsAddition", "indexUid": "movies", "documents": body.clone() }] });
+    let (_response, status_code) = server.post_request("/update-groups", group).await;
+    assert_eq!(status_code, 403);
+
+    // the other indexes are still writable, and the mounted ones searchable
+    let (_response, status_code) = server.post_request("/indexes/series/documents", body).await;
+    assert_eq!(status_code, 202);
+    let (_response, status_code) = server.search("q=carol").await;
+    assert_eq!(status_code, 200);
+
+    // the routes reading a mounted index are not refused, even the POST ones
+    let (_response, status_code) = server.post_request("/indexes/movies/debug/analyze", json!({ "q": "carol" })).await;
+    assert_eq!(status_code, 200);
+    let (_response, status_code) = server.post_request("/indexes/movies/clone", json!({ "uid": "films" })).await;
+    assert_eq!(status_code, 201);
+}
+
+#[actix_rt::test]
+async fn index_writes_go_through_the_write_queues() {
+    let mut server = common::Server::with_uid("movies");
+    let (_response, status_code) = server.create_index(json!({ "uid": "movies" })).await;
+    assert_eq!(status_code, 201);
+    let (_response, status_code) = server.update_index(json!({ "name": "Films" })).await;
+    assert_eq!(status_code, 200);
+
+    // the index creation registers the index then names it, like the database does
+    let (response, status_code) = server.get_request("/stats").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["writeQueues"]["main"]["acquired"], 3);
+    assert_eq!(response["writeQueues"]["main"]["waiting"], 0);
+
+    let (metrics, _status_code) = server.get_request_text("/metrics").await;
+    assert!(metrics.contains("meilisearch_write_txn_wait_seconds_count{env=\"main\"} 3"));
+}
+
+#[actix_rt::test]
+async fn indexes_matching_a_template_are_configured_by_it() {
+    let mut server = common::Server::with_uid("movies");
+
+    let body = json!({
+        "uidPattern": "logs-*",
+        "primaryKey": "id",
+        "settings": { "searchableAttributes": ["message"] },
+    });
+    let (_response, status_code) = server.put_request("/index-templates/logs", body).await;
+    assert_eq!(status_code, 200);
+
+    // the missing index is created by the documents addition
+    let body = json!([{ "id": 1, "message": "disk full", "level": "error" }]);
+    let (response, status_code) = server.post_request("/indexes/logs-2020-01/documents", body).await;
+    assert_eq!(status_code, 202);
+    server.wait_update_id(response["updateId"].as_u64().unwrap()).await;
+
+    let (response, status_code) = server.get_request("/indexes/logs-2020-01").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["primaryKey"], "id");
+    let (response, _status_code) = server.get_request("/indexes/logs-2020-01/settings").await;
+    assert_eq!(response["searchableAttributes"], json!(["message"]));
+
+    let (response, status_code) = server.create_index(json!({ "uid": "logs-2020-02" })).await;
+    assert_eq!(status_code, 201);
+    assert_eq!(response["primaryKey"], "id");
+
+    let body = json!([{ "id": 1, "title": "Alien" }]);
+    let (_response, status_code) = server.post_request("/indexes/metrics-2020-01/documents", body).await;
+    assert_eq!(status_code, 404);
+
+    let (_response, status_code) = server.delete_request("/index-templates/logs").await;
+    assert_eq!(status_code, 204);
+    let (response, _status_code) = server.get_request("/index-templates").await;
+    assert_eq!(response, json!({}));
+}
+
+#[actix_rt::test]
+async fn rollover_aliases_write_to_a_new_partition() {
+    let mut server = common::Server::with_uid("movies");
+
+    let body = json!({ "maxDocuments": 2, "retentionDays": 0 });
+    let (response, status_code) = server.put_request("/rollover-aliases/logs", body).await;
+    assert_eq!(status_code, 200);
+    let first_partition = response["writeIndex"].as_str().unwrap().to_string();
+    assert!(first_partition.starts_with("logs-"));
+
+    // the documents added to the alias go to its write index
+    let body = json!([{ "id": 1, "message": "disk full" }]);
+    let (response, status_code) = server.post_request("/indexes/logs/documents", body).await;
+    assert_eq!(status_code, 202);
+    server.wait_update_id(response["updateId"].as_u64().unwrap()).await;
+
+    let (response, status_code) = server.post_request("/rollover-aliases/logs/rollover", json!({})).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["rolledOver"], false);
+    assert_eq!(response["writeIndex"], first_partition.as_str());
+
+    let (response, status_code) = server.post_request("/rollover-aliases/logs/rollover?force=true", json!({})).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["rolledOver"], true);
+    assert_ne!(response["writeIndex"], first_partition.as_str());
+    // the previous partition is out of the retention window
+    assert_eq!(response["deletedIndexes"], json!([first_partition]));
+
+    let (_response, status_code) = server.get_request(&format!("/indexes/{}", first_partition)).await;
+    assert_eq!(status_code, 404);
+}
+
+#[actix_rt::test]
+async fn renamed_partition_stays_the_write_index() {
+    let mut server = common::Server::with_uid("logs-renamed");
+
+    let body = json!({ "maxDocuments": 2 });
+    let (response, status_code) = server.put_request("/rollover-aliases/logs", body).await;
+    assert_eq!(status_code, 200);
+    let partition = response["writeIndex"].as_str().unwrap().to_string();
+
+    let url = format!("/indexes/{}", partition);
+    let (_response, status_code) = server.patch_request(&url, json!({ "uid": "logs-renamed" })).await;
+    assert_eq!(status_code, 200);
+
+    let (response, status_code) = server.get_request("/rollover-aliases/logs").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["writeIndex"], "logs-renamed");
+    assert_eq!(response["partitions"][0]["uid"], "logs-renamed");
+
+    // the documents added to the alias still go to the renamed partition
+    let body = json!([{ "id": 1, "message": "disk full" }]);
+    let (response, status_code) = server.post_request("/indexes/logs/documents", body).await;
+    assert_eq!(status_code, 202);
+    server.wait_update_id(response["updateId"].as_u64().unwrap()).await;
+
+    let (response, status_code) = server.get_all_documents().await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response.as_array().unwrap().len(), 1);
+}
+
+#[actix_rt::test]
+async fn usage_is_accounted_per_key_and_per_index() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Carol" },
+        { "id": 2, "title": "Wonder Woman" },
+    ])).await;
+    server.search("q=carol").await;
+    server.search("q=woman").await;
+
+    // the failed searches are not accounted
+    let (_response, status_code) = server.get_request("/indexes/books/search?q=carol").await;
+    assert_eq!(status_code, 404);
+
+    let (response, status_code) = server.get_request("/usage").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["keys"]["anonymous"]["searches"], 2);
+    assert_eq!(response["keys"]["anonymous"]["indexingOperations"], 1);
+
+    let usage = &response["indexes"]["movies"];
+    assert_eq!(usage["searches"], 2);
+    assert_eq!(usage["indexingOperations"], 1);
+    assert_eq!(usage["numberOfDocuments"], 2);
+    assert!(usage["dataSize"].as_u64().unwrap() > 0);
+}
+
+#[actix_rt::test]
+async fn follower_indexes_are_read_only() {
+    let mut server = common::Server::with_options("movies", |opt| {
+        opt.replicate_from = Some("http://127.0.0.1:7700".to_string());
+    });
+
+    let (response, status_code) = server.create_index(json!({ "uid": "movies" })).await;
+    assert_eq!(status_code, 403);
+    assert!(response["message"].as_str().unwrap().contains("follower"));
+
+    let (_response, status_code) = server.post_request("/indexes/movies/documents", json!([{ "id": 1 }])).await;
+    assert_eq!(status_code, 403);
+}
@@ -0,0 +1,86 @@
+use std::fs;
+
+use meilisearch_http::routes::table::ROUTES;
+use serde_json::json;
+
+mod common;
+
+#[actix_rt::test]
+async fn openapi_describes_the_routes() {
+    let mut server = common::Server::with_uid("movies");
+
+    let (response, status_code) = server.get_request("/openapi.json").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["openapi"], "3.0.3");
+    assert_eq!(response["info"]["version"], env!("CARGO_PKG_VERSION"));
+
+    let search = &response["paths"]["/indexes/{index_uid}/search"]["get"];
+    assert_eq!(search["x-meili-key"], "public");
+    let parameters = search["parameters"].as_array().unwrap();
+    assert!(parameters.contains(&json!({ "name": "index_uid", "in": "path", "required": true, "schema": { "type": "string" } })));
+    assert!(parameters.contains(&json!({ "name": "q", "in": "query", "required": true, "schema": { "type": "string" } })));
+    assert!(parameters.contains(&json!({ "name": "limit", "in": "query", "required": false, "schema": { "type": "integer" } })));
+
+    let index_creation = &response["paths"]["/indexes"]["post"];
+    assert_eq!(index_creation["requestBody"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/IndexCreateRequest");
+    assert_eq!(index_creation["responses"]["201"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/IndexResponse");
+    assert_eq!(index_creation["responses"]["default"]["content"]["application/json"]["schema"]["$ref"], "#/components/schemas/Error");
+
+    let index = &response["components"]["schemas"]["IndexResponse"];
+    assert_eq!(index["properties"]["createdAt"], json!({ "type": "string", "format": "date-time" }));
+    assert!(index["required"].as_array().unwrap().contains(&json!("uid")));
+
+    let format = &response["components"]["schemas"]["DocumentsFormat"];
+    assert_eq!(format, &json!({ "type": "string", "enum": ["json", "ndjson", "csv"] }));
+
+    assert_eq!(response["paths"]["/keys"]["get"]["x-meili-key"], "admin");
+    assert_eq!(response["paths"]["/indexes/search"]["post"]["x-meili-write"], false);
+    assert_eq!(response["paths"]["/indexes/{index_uid}/documents"]["post"]["x-meili-write"], true);
+}
+
+#[actix_rt::test]
+async fn declared_routes_are_served() {
+    let mut server = common::Server::with_uid("movies");
+
+    for route in ROUTES {
+        let path: Vec<_> = route
+            .path
+            .split('/')
+            .map(|segment| if segment.starts_with('{') { "1" } else { segment })
+            .collect();
+        let (body, status_code) = server.raw_request(route.method, &path.join("/")).await;
+
+        // the requests matching no route are answered with an empty 404
+        let unrouted = status_code == 404 && body.is_empty();
+        assert!(!unrouted && status_code != 405, "{} {} is not served", route.method, route.path);
+    }
+
+    let (response, _status_code) = server.get_request("/openapi.json").await;
+    assert_eq!(response["paths"]["/indexes/{index_uid}"]["patch"]["operationId"], "index::rename_index");
+}
+
+#[test]
+fn served_routes_are_declared() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/routes");
+    let mut served = Vec::new();
+
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+
+        // the handlers are registered with the route macros of actix, the path is their first argument
+        for method in &["get", "post", "put", "delete", "patch"] {
+            for (start, attribute) in source.match_indices(&format!("#[{}(", method)) {
+                let arguments = source[start + attribute.len()..].trim_start();
+                let route = arguments.trim_start_matches('"').split('"').next().unwrap();
+                served.push((method.to_uppercase(), route.to_string(), path.clone()));
+            }
+        }
+    }
+
+    assert!(!served.is_empty());
+    for (method, route, path) in served {
+        let declared = ROUTES.iter().any(|declared| declared.method == method && declared.path == route);
+        assert!(declared, "{} {} of {:?} is not declared in the routes table", method, route, path);
+    }
+}
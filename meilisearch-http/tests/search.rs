@@ -1344,6 +1344,39 @@ async fn test_facet_count() {
     assert_eq!(status_code, 400);
 }
 
+#[actix_rt::test]
+async fn disjunctive_facets_count_without_their_own_filters() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "attributesForFaceting": ["genre", "country"] })).await;
+
+    let body = json!([
+        { "id": 1, "title": "movie", "genre": "Drama", "country": "France" },
+        { "id": 2, "title": "movie", "genre": "Comedy", "country": "France" },
+        { "id": 3, "title": "movie", "genre": "Drama", "country": "Italy" },
+        { "id": 4, "title": "movie", "genre": "Horror", "country": "Italy" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    // facetFilters=["genre:Drama"]&facets=["genre", "country"]
+    let query = "q=movie&facetFilters=%5B%22genre%3ADrama%22%5D&facets=%5B%22genre%22,%22country%22%5D";
+    let (response, _status_code) = server.search(query).await;
+    assert_eq!(response["nbHits"], 2);
+    assert_eq!(response["facets"]["genre"], json!({ "Drama": 2, "Comedy": 0, "Horror": 0 }));
+    assert_eq!(response["facets"]["country"], json!({ "France": 1, "Italy": 1 }));
+    assert_eq!(response["exhaustiveFacetsCount"], true);
+
+    // too few hits to be sampled
+    let (response, _status_code) = server.search(&format!("{}&exhaustiveFacetsCount=false", query)).await;
+    assert_eq!(response["facets"]["genre"], json!({ "Drama": 2, "Comedy": 0, "Horror": 0 }));
+    assert_eq!(response["exhaustiveFacetsCount"], true);
+
+    let (response, _status_code) = server.search(&format!("{}&disjunctiveFacets=true", query)).await;
+    assert_eq!(response["nbHits"], 2);
+    assert_eq!(response["facets"]["genre"], json!({ "Drama": 2, "Comedy": 1, "Horror": 1 }));
+    assert_eq!(response["facets"]["country"], json!({ "France": 1, "Italy": 1 }));
+}
+
 #[actix_rt::test]
 async fn highlight_cropped_text() {
     let mut server = common::Server::with_uid("test");
@@ -1407,3 +1440,1036 @@ async fn highlight_cropped_text() {
         .unwrap()
         , &Value::String(expected_response.to_owned()));
 }
+
+#[actix_rt::test]
+async fn search_exhaustive_nb_hits() {
+    let mut server = common::Server::with_uid("movies");
+    server.populate_movies().await;
+
+    // filters: director = "Anthony Russo"
+    let query = "q=a&limit=50&filters=director%20%3D%20%22Anthony%20Russo%22&exhaustiveNbHits=true";
+    let (response, _status_code) = server.search(query).await;
+    assert_eq!(response["exhaustiveNbHits"], true);
+    assert_eq!(
+        response["nbHits"].as_u64().unwrap(),
+        response["hits"].as_array().unwrap().len() as u64
+    );
+
+    // the count is capped by default, it is exact below the cap
+    let query = "q=a&limit=50&filters=director%20%3D%20%22Anthony%20Russo%22";
+    let (response, _status_code) = server.search(query).await;
+    assert_eq!(response["exhaustiveNbHits"], true);
+    assert_eq!(
+        response["nbHits"].as_u64().unwrap(),
+        response["hits"].as_array().unwrap().len() as u64
+    );
+
+    let query = "q=a&limit=5";
+    let (response, _status_code) = server.search(query).await;
+    assert_eq!(response["exhaustiveNbHits"], true);
+}
+
+#[actix_rt::test]
+async fn search_with_cursor() {
+    let mut server = common::Server::with_uid("movies");
+    server.populate_movies().await;
+
+    let (first_page, _status_code) = server.search("q=a&limit=3").await;
+    let cursor = first_page["nextCursor"].as_str().unwrap().to_string();
+
+    let (expected, _status_code) = server.search("q=a&limit=3&offset=3").await;
+    let query = format!("q=a&limit=3&searchAfter={}", cursor);
+    let (response, _status_code) = server.search(&query).await;
+    assert_json_eq!(expected["hits"].clone(), response["hits"].clone(), ordered: true);
+
+    // the cursor holds the sort key of the last hit, the hits deleted
+    // since the first page don't shift the next one
+    for hit in first_page["hits"].as_array().unwrap() {
+        server.delete_document(hit["id"].as_u64().unwrap()).await;
+    }
+    let (response, _status_code) = server.search(&query).await;
+    assert_json_eq!(expected["hits"].clone(), response["hits"].clone(), ordered: true);
+
+    // a cursor can't be used for another query
+    let query = format!("q=b&limit=3&searchAfter={}", cursor);
+    let (_response, status_code) = server.search(&query).await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn search_multiple_indexes_with_filters() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.create_index(json!({ "uid": "series", "primaryKey": "id" })).await;
+
+    let movies = json!([
+        { "id": 1, "title": "Batman Begins", "year": 2005 },
+        { "id": 2, "title": "The Batman", "year": 2022 },
+    ]);
+    server.add_or_replace_multiple_documents(movies).await;
+
+    let series = json!([
+        { "id": 1, "title": "Batman: The Animated Series", "year": 1992 },
+        { "id": 2, "title": "Gotham", "year": 2014 },
+    ]);
+    server.post_request_async("/indexes/series/documents", series).await;
+
+    let body = json!({
+        "q": "batman",
+        "indexes": [
+            { "indexUid": "movies", "filters": "year > 2010" },
+            { "indexUid": "series" },
+        ],
+    });
+
+    let (response, status_code) = server.search_multiple_indexes(body).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["nbHits"], 2);
+
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits[0]["title"], "The Batman");
+    assert_eq!(hits[0]["_indexUid"], "movies");
+    assert_eq!(hits[1]["title"], "Batman: The Animated Series");
+    assert_eq!(hits[1]["_indexUid"], "series");
+
+    // the end of a page past the maximum number of hits doesn't overflow
+    let indexes = json!([{ "indexUid": "movies" }, { "indexUid": "series" }]);
+    let body = json!({ "q": "batman", "indexes": indexes, "offset": 1, "limit": usize::MAX });
+    let (response, status_code) = server.search_multiple_indexes(body).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+
+    let body = json!({ "q": "batman", "indexes": indexes, "offset": usize::MAX, "limit": 20 });
+    let (response, status_code) = server.search_multiple_indexes(body).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["hits"], json!([]));
+
+    let body = json!({ "q": "batman", "indexes": [{ "indexUid": "unknown" }] });
+    let (_response, status_code) = server.search_multiple_indexes(body).await;
+    assert_eq!(status_code, 404);
+}
+
+#[actix_rt::test]
+async fn search_multiple_indexes_without_duplicates() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.create_index(json!({ "uid": "archive", "primaryKey": "id" })).await;
+
+    let movies = json!([
+        { "id": 1, "title": "Batman Begins", "imdb": "tt0372784" },
+        { "id": 2, "title": "The Batman", "imdb": "tt1877830" },
+    ]);
+    server.add_or_replace_multiple_documents(movies).await;
+
+    let archive = json!([
+        { "id": 2, "title": "The Batman (archived)", "imdb": "tt1877830" },
+        { "id": 3, "title": "Batman Returns", "imdb": "tt0372784" },
+    ]);
+    server.post_request_async("/indexes/archive/documents", archive).await;
+
+    let indexes = json!([{ "indexUid": "movies" }, { "indexUid": "archive" }]);
+    let body = json!({ "q": "batman", "indexes": indexes });
+    let (response, _status_code) = server.search_multiple_indexes(body).await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 4);
+
+    // the copy of the archive is ranked after the one of the movies
+    let body = json!({ "q": "batman", "indexes": indexes, "dedup": true });
+    let (response, status_code) = server.search_multiple_indexes(body).await;
+    assert_eq!(status_code, 200);
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 3);
+    assert!(hits.iter().all(|hit| hit["title"] != "The Batman (archived)"));
+
+    let body = json!({ "q": "batman", "indexes": indexes, "dedupAttribute": "imdb" });
+    let (response, _status_code) = server.search_multiple_indexes(body).await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits.iter().all(|hit| hit["_indexUid"] == "movies"));
+}
+
+#[actix_rt::test]
+async fn search_without_synonyms() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.update_synonyms(json!({ "logan": ["wolverine"] })).await;
+
+    let body = json!([
+        { "id": 1, "title": "Wolverine" },
+        { "id": 2, "title": "Logan Lucky" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=logan").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+
+    let (response, _status_code) = server.search("q=logan&enableSynonyms=false").await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["title"], "Logan Lucky");
+}
+
+#[actix_rt::test]
+async fn search_without_ngrams_concatenation() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([{ "id": 1, "title": "Spiderman" }]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=spider%20man").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+
+    let (response, _status_code) = server.search("q=spider%20man&concatenateNgrams=false").await;
+    assert!(response["hits"].as_array().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn quick_search_only_searches_the_query_words() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.update_synonyms(json!({ "logan": ["wolverine"] })).await;
+
+    let body = json!([
+        { "id": 1, "title": "Spiderman" },
+        { "id": 2, "title": "Wolverine" },
+        { "id": 3, "title": "Logan Lucky" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=logan&quickSearch=true").await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["title"], "Logan Lucky");
+
+    // neither concatenated nor split
+    let (response, _status_code) = server.search("q=spider%20man&quickSearch=true").await;
+    assert!(response["hits"].as_array().unwrap().is_empty());
+    let (response, _status_code) = server.search("q=spiderman&quickSearch=true").await;
+    assert_eq!(response["hits"][0]["title"], "Spiderman");
+
+    // the last word is still a prefix and the words are still tolerant
+    let (response, _status_code) = server.search("q=logam%20luc&quickSearch=true").await;
+    assert_eq!(response["hits"][0]["title"], "Logan Lucky");
+}
+
+#[actix_rt::test]
+async fn quick_search_ignores_the_keyboard_typos_and_the_numeric_tolerance() {
+    let mut server = common::Server::with_uid("screens");
+    server.create_index(json!({ "uid": "screens", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "keyboardTypos": true, "numericTolerance": 0.05 })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "1080p monitor" },
+        { "id": 2, "title": "dog" },
+    ])).await;
+
+    let (response, _status_code) = server.search("q=fog").await;
+    assert_eq!(response["hits"], json!([{ "id": 2, "title": "dog" }]));
+    let (response, _status_code) = server.search("q=fog&quickSearch=true").await;
+    assert_eq!(response["hits"], json!([]));
+
+    let (response, _status_code) = server.search("q=1100%20monitor").await;
+    assert_eq!(response["hits"], json!([{ "id": 1, "title": "1080p monitor" }]));
+    let (response, _status_code) = server.search("q=1100%20monitor&quickSearch=true").await;
+    assert_eq!(response["hits"], json!([]));
+}
+
+#[actix_rt::test]
+async fn matches_positions_in_utf16_code_units() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([{ "id": 1, "title": "🦀 Crab Wars" }]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=wars&matches=true").await;
+    assert_eq!(response["hits"][0]["_matchesInfo"]["title"], json!([{ "start": 7, "length": 4 }]));
+
+    let (response, _status_code) = server.search("q=wars&matches=true&matchesPositionUnit=utf16").await;
+    assert_eq!(response["hits"][0]["_matchesInfo"]["title"], json!([{ "start": 8, "length": 4 }]));
+
+    let (response, _status_code) = server.search("q=wars&matches=true&matchesPositionUnit=bytes").await;
+    assert_eq!(response["hits"][0]["_matchesInfo"]["title"], json!([{ "start": 10, "length": 4 }]));
+
+    let (_response, status_code) = server.search("q=wars&matchesPositionUnit=words").await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn search_ignores_last_words_without_full_match() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "The lord of the rings" },
+        { "id": 2, "title": "The lord of war" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    // the words are only ignored once the index allows it
+    let (response, _status_code) = server.search("q=lord%20rings%20zelda%20link").await;
+    assert!(response["hits"].as_array().unwrap().is_empty());
+    assert!(response.get("ignoredWords").is_none());
+
+    server.update_all_settings(json!({ "optionalWords": true })).await;
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["optionalWords"], json!(true));
+
+    let (response, _status_code) = server.search("q=lord%20war").await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["id"], json!(2));
+    assert!(response.get("ignoredWords").is_none());
+
+    let (response, _status_code) = server.search("q=lord%20rings%20zelda%20link").await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["id"], json!(1));
+    assert!(hits[0].get("ignoredWords").is_none());
+    assert_eq!(response["ignoredWords"], json!(["zelda", "link"]));
+
+    // the words are ignored by the search of each index
+    server.create_index(json!({ "uid": "series", "primaryKey": "id" })).await;
+    let series = json!([{ "id": 1, "title": "Lord of the rings zelda" }]);
+    server.post_request_async("/indexes/series/documents", series).await;
+
+    let body = json!({ "q": "lord rings zelda link", "indexes": [{ "indexUid": "movies" }, { "indexUid": "series" }] });
+    let (response, _status_code) = server.search_multiple_indexes(body).await;
+    assert_eq!(response["ignoredWords"], json!({ "movies": ["zelda", "link"] }));
+}
+
+#[actix_rt::test]
+async fn search_with_attribute_weights() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "dragon", "overview": "knight" },
+        { "id": 2, "title": "knight", "overview": "dragon" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=dragon").await;
+    assert_eq!(response["hits"][0]["id"], json!(1));
+
+    server.update_all_settings(json!({ "attributeWeights": { "overview": 3 } })).await;
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["attributeWeights"], json!({ "overview": 3 }));
+
+    let (response, _status_code) = server.search("q=dragon").await;
+    assert_eq!(response["hits"][0]["id"], json!(2));
+    assert_eq!(response["hits"][1]["id"], json!(1));
+}
+
+#[actix_rt::test]
+async fn search_phrase_across_adjacent_attributes() {
+    let mut server = common::Server::with_uid("people");
+    server.create_index(json!({ "uid": "people", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "first_name": "john", "last_name": "smith" },
+        { "id": 2, "first_name": "smith", "last_name": "john" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=johnsmith").await;
+    assert!(response["hits"].as_array().unwrap().is_empty());
+
+    server.update_all_settings(json!({ "adjacentAttributes": [["first_name", "last_name"]] })).await;
+
+    let (response, _status_code) = server.search("q=johnsmith").await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["id"], json!(1));
+}
+
+#[actix_rt::test]
+async fn search_with_normalized_units() {
+    let mut server = common::Server::with_uid("products");
+    server.create_index(json!({ "uid": "products", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "rice bag 5 kg" },
+        { "id": 2, "title": "rice bag 500g" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=5kg").await;
+    assert!(response["hits"].as_array().unwrap().is_empty());
+
+    server.update_all_settings(json!({ "normalizeUnits": true })).await;
+
+    for query in &["q=5000g", "q=5kg", "q=rice%205%20kg"] {
+        let (response, _status_code) = server.search(query).await;
+        let hits = response["hits"].as_array().unwrap();
+        assert_eq!(hits.len(), 1, "{}", query);
+        assert_eq!(hits[0]["id"], json!(1));
+    }
+}
+
+#[actix_rt::test]
+async fn test_boolean_facets() {
+    let mut server = common::Server::with_uid("posts");
+    server.create_index(json!({ "uid": "posts", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "attributesForFaceting": ["is_published"] })).await;
+
+    let body = json!([
+        { "id": 1, "title": "first post", "is_published": true },
+        { "id": 2, "title": "second post", "is_published": false },
+        { "id": 3, "title": "third post", "is_published": true },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    // facetFilters=["is_published:true"]
+    let query = "q=post&facetFilters=%5B%22is_published:true%22%5D";
+    let (response, status_code) = server.search(query).await;
+    assert_eq!(status_code, 200);
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 2);
+    assert!(hits.iter().all(|hit| hit["is_published"] == json!(true)));
+
+    // filters=is_published = false
+    let query = "q=post&filters=is_published%20%3D%20false";
+    let (response, _status_code) = server.search(query).await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+    assert_eq!(response["hits"][0]["id"], json!(2));
+
+    // facets=["is_published"]
+    let query = "q=post&facets=%5B%22is_published%22%5D";
+    let (response, _status_code) = server.search(query).await;
+    assert_eq!(response["facets"]["is_published"], json!({ "true": 2, "false": 1 }));
+}
+
+#[actix_rt::test]
+async fn search_not_equal_filter_on_missing_attribute() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "red movie", "color": "red" },
+        { "id": 2, "title": "blue movie", "color": "blue" },
+        { "id": 3, "title": "colorless movie" },
+        { "id": 4, "title": "null movie", "color": null },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=movie&filters=color%20!%3D%20red").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 3);
+
+    let (response, _status_code) = server.search("q=movie&filters=color%20!%3D%20red&notEqualMatchesMissing=false").await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["id"], json!(2));
+}
+
+#[actix_rt::test]
+async fn test_case_and_diacritics_insensitive_facets() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "attributesForFaceting": ["genre"] })).await;
+
+    let body = json!([
+        { "id": 1, "title": "space movie", "genre": "Sci-Fi" },
+        { "id": 2, "title": "robot movie", "genre": "sci-fi" },
+        { "id": 3, "title": "dance movie", "genre": "Électro" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    // facetFilters=["genre:SCI-FI"]
+    let (response, _status_code) = server.search("q=movie&facetFilters=%5B%22genre:SCI-FI%22%5D").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+
+    // facetFilters=["genre:electro"]
+    let (response, _status_code) = server.search("q=movie&facetFilters=%5B%22genre:electro%22%5D").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+
+    // filters=genre = "Sci-Fi"
+    let (response, _status_code) = server.search("q=movie&filters=genre%20%3D%20%22Sci-Fi%22").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+
+    // the counts are displayed with the values written in the documents
+    let (response, _status_code) = server.search("q=movie&facets=%5B%22genre%22%5D").await;
+    let genres = response["facets"]["genre"].as_object().unwrap();
+    assert_eq!(genres.len(), 2);
+    assert_eq!(genres["Électro"], json!(1));
+    let sci_fi = genres.get("Sci-Fi").or_else(|| genres.get("sci-fi"));
+    assert_eq!(sci_fi, Some(&json!(2)));
+}
+
+#[actix_rt::test]
+async fn search_with_nested_distinct_attribute() {
+    let mut server = common::Server::with_uid("shoes");
+    server.create_index(json!({ "uid": "shoes", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "red shoe", "product": { "sku": "A1" } },
+        { "id": 2, "title": "blue shoe", "product": { "sku": "A1" } },
+        { "id": 3, "title": "green shoe", "product": { "sku": "B2" } },
+        { "id": 4, "title": "plain shoe" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    // the documents are reindexed with the new distinct attribute
+    server.update_all_settings(json!({ "distinctAttribute": "product.sku" })).await;
+
+    let (response, _status_code) = server.search("q=shoe").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 3);
+
+    let body = json!([
+        { "id": 5, "title": "black shoe", "product": { "sku": "B2" } },
+        { "id": 6, "title": "white shoe", "product": { "sku": "C3" } },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=shoe").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 4);
+}
+
+#[actix_rt::test]
+async fn search_with_geo_distribution() {
+    let mut server = common::Server::with_uid("places");
+    server.create_index(json!({ "uid": "places", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "attributesForFaceting": ["_geo", "kind"] })).await;
+
+    let body = json!([
+        { "id": 1, "name": "louvre museum", "kind": "museum", "_geo": { "lat": 48.8606, "lng": 2.3376 } },
+        { "id": 2, "name": "orsay museum", "kind": "museum", "_geo": { "lat": 48.8600, "lng": 2.3266 } },
+        { "id": 3, "name": "confluence museum", "kind": "museum", "_geo": { "lat": 45.7330, "lng": 4.8180 } },
+        { "id": 4, "name": "tower museum", "kind": "tower", "_geo": { "lat": "48.8584", "lng": "2.2945" } },
+        { "id": 5, "name": "lost museum", "kind": "museum", "_geo": "u09" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, _status_code) = server.search("q=museum&geoDistribution=3").await;
+    assert_eq!(response["geoDistribution"], json!({ "u09": 3, "u05": 1 }));
+
+    // the distribution is computed over the filtered hits
+    let (response, _status_code) = server.search("q=museum&geoDistribution=3&facetFilters=%5B%22kind:museum%22%5D").await;
+    assert_eq!(response["geoDistribution"], json!({ "u09": 2, "u05": 1 }));
+
+    // the geohash cells can also be used as facet filters
+    let (response, _status_code) = server.search("q=museum&facetFilters=%5B%22_geo:geohash(u05)%22%5D").await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["id"], json!(3));
+
+    // a value written like a cell is not mistaken for it
+    let (response, _status_code) = server.search("q=museum&facetFilters=%5B%22_geo:u09%22%5D").await;
+    let hits = response["hits"].as_array().unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["id"], json!(5));
+
+    let (_response, status_code) = server.search("q=museum&geoDistribution=13").await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn search_continues_the_trace_of_the_request() {
+    let mut server = common::Server::with_uid("shoes");
+    server.create_index(json!({ "uid": "shoes", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([{ "id": 1, "title": "red shoe" }])).await;
+
+    let trace_id = "0af7651916cd43dd8448eb211c80319c";
+    let headers = [
+        ("traceparent", "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"),
+        ("tracestate", "congo=t61rcWkgMzE"),
+    ];
+    let (response, status_code, headers) = server.get_request_with_headers("/indexes/shoes/search?q=shoe", &headers).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+
+    // the server span is a child of the span of the client
+    let traceparent = headers.get("traceparent").unwrap().to_str().unwrap();
+    let parts: Vec<_> = traceparent.split('-').collect();
+    assert_eq!(parts[1], trace_id);
+    assert_ne!(parts[2], "b7ad6b7169203331");
+    assert_eq!(parts[3], "01");
+    assert_eq!(headers.get("tracestate").unwrap(), "congo=t61rcWkgMzE");
+
+    // a new trace is started without a valid traceparent
+    let headers = [("traceparent", "invalid")];
+    let (_response, _status_code, headers) = server.get_request_with_headers("/indexes/shoes/search?q=shoe", &headers).await;
+    let traceparent = headers.get("traceparent").unwrap().to_str().unwrap();
+    assert!(!traceparent.contains(trace_id));
+    assert!(headers.get("tracestate").is_none());
+}
+
+#[actix_rt::test]
+async fn searches_are_counted_in_the_metrics() {
+    let mut server = common::Server::with_uid("shoes");
+    server.create_index(json!({ "uid": "shoes", "primaryKey": "id" })).await;
+    server.search("q=shoe").await;
+    server.search("q=boot").await;
+
+    let (metrics, status_code) = server.get_request_text("/metrics").await;
+    assert_eq!(status_code, 200);
+
+    let labels = "method=\"GET\",route=\"/indexes/{index_uid}/search\"";
+    assert!(metrics.contains(&format!("meilisearch_http_requests_total{{{},status=\"200\"}} 2", labels)));
+    assert!(metrics.contains(&format!("meilisearch_http_request_duration_seconds_count{{{}}} 2", labels)));
+    assert!(metrics.contains("meilisearch_http_requests_total{method=\"POST\",route=\"/indexes\",status=\"201\"} 1"));
+}
+
+#[actix_rt::test]
+async fn pinned_snapshots_keep_the_pages_stable() {
+    let mut server = common::Server::with_uid("shoes");
+    server.create_index(json!({ "uid": "shoes", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "name": "red shoe" },
+        { "id": 2, "name": "blue shoe" },
+        { "id": 3, "name": "green shoe" },
+    ])).await;
+
+    let (response, status_code) = server.search("q=shoe&limit=2&pinSnapshot=true").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["nbHits"], 3);
+    let token = response["snapshotToken"].as_str().unwrap().to_string();
+
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 4, "name": "black shoe" },
+        { "id": 5, "name": "white shoe" },
+    ])).await;
+
+    // the next page is read from the state of the index of the first one
+    let (response, status_code) = server.search(&format!("q=shoe&offset=2&limit=2&snapshotToken={}", token)).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["nbHits"], 3);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+    assert_eq!(response["snapshotToken"], token.as_str());
+
+    let (response, _status_code) = server.search("q=shoe&offset=2&limit=2").await;
+    assert_eq!(response["nbHits"], 5);
+    assert!(response.get("snapshotToken").is_none());
+
+    let (_response, status_code) = server.search("q=shoe&snapshotToken=unknown").await;
+    assert_eq!(status_code, 400);
+
+    // the token can't be used to search another index
+    server.create_index(json!({ "uid": "other", "primaryKey": "id" })).await;
+    let (_response, status_code) = server.get_request(&format!("/indexes/other/search?q=shoe&snapshotToken={}", token)).await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn ranking_rules_overridden_for_one_search() {
+    let mut server = common::Server::with_uid("shoes");
+    server.create_index(json!({ "uid": "shoes", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "name": "red shoe", "popularity": 30, "discount": 10 },
+        { "id": 2, "name": "blue shoe", "popularity": 20, "discount": 50 },
+        { "id": 3, "name": "green shoe", "popularity": 10, "discount": 30 },
+    ])).await;
+    server.update_ranking_rules(json!(["words", "desc(popularity)", "desc(discount)"])).await;
+
+    let (response, _status_code) = server.search("q=shoe").await;
+    let ids: Vec<_> = response["hits"].as_array().unwrap().iter().map(|h| h["id"].clone()).collect();
+    assert_eq!(ids, vec![json!(1), json!(2), json!(3)]);
+
+    // the sort rules at the end of the index ranking rules are replaced
+    let (response, status_code) = server.search("q=shoe&rankingRules=desc(discount)").await;
+    assert_eq!(status_code, 200);
+    let ids: Vec<_> = response["hits"].as_array().unwrap().iter().map(|h| h["id"].clone()).collect();
+    assert_eq!(ids, vec![json!(2), json!(3), json!(1)]);
+
+    let (_response, status_code) = server.search("q=shoe&rankingRules=desc(name)").await;
+    assert_eq!(status_code, 400);
+
+    let (_response, status_code) = server.search("q=shoe&rankingRules=typo").await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn sort_by_several_attributes() {
+    let mut server = common::Server::with_uid("shoes");
+    server.create_index(json!({ "uid": "shoes", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "fieldTypes": { "releasedAt": "date" } })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "shoe", "brand": "nike", "price": 50, "releasedAt": "2020-03-01" },
+        { "id": 2, "title": "shoe", "brand": "Adidas", "price": 80, "releasedAt": "2019-12-31T23:00:00Z" },
+        { "id": 3, "title": "shoe", "brand": "Émeraude", "price": 10, "releasedAt": "2021-01-01" },
+        { "id": 4, "title": "shoe", "brand": "adidas", "price": 120 },
+    ])).await;
+
+    let ids = |response: &Value| -> Vec<Value> {
+        response["hits"].as_array().unwrap().iter().map(|hit| hit["id"].clone()).collect()
+    };
+
+    // sort=["brand:asc", "price:desc"]
+    let (response, status_code) = server.search("q=shoe&sort=%5B%22brand%3Aasc%22,%22price%3Adesc%22%5D").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(ids(&response), vec![json!(4), json!(2), json!(3), json!(1)]);
+
+    // the documents without date are sorted last
+    let (response, _status_code) = server.search("q=shoe&sort=%5B%22releasedAt%3Adesc%22%5D").await;
+    assert_eq!(ids(&response), vec![json!(3), json!(1), json!(2), json!(4)]);
+
+    let (_response, status_code) = server.search("q=shoe&sort=%5B%22brand%3Aup%22%5D").await;
+    assert_eq!(status_code, 400);
+    let (_response, status_code) = server.search("q=shoe&sort=%5B%22color%3Aasc%22%5D").await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn analyze_a_query_without_searching() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.update_synonyms(json!({ "logan": ["wolverine"] })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Spiderman" },
+        { "id": 2, "title": "Logan" },
+    ])).await;
+
+    let body = json!({ "q": "Spider man logan" });
+    let (response, status_code) = server.post_request("/indexes/movies/debug/analyze", body).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["tokens"], json!(["Spider", "man", "logan"]));
+    assert_eq!(response["words"], json!(["spider", "man", "logan"]));
+
+    let ngrams = response["ngrams"].as_array().unwrap();
+    assert!(ngrams.contains(&json!({ "words": ["spider", "man"], "concatenation": "spiderman", "indexed": true })));
+    assert!(ngrams.contains(&json!({ "words": ["man", "logan"], "concatenation": "manlogan", "indexed": false })));
+    assert_eq!(response["synonyms"], json!([{ "words": ["logan"], "synonyms": [["wolverine"]] }]));
+    assert!(response["queryTree"].as_str().unwrap().contains("word: \"spiderman\""));
+
+    let body = json!({ "q": "spider man", "concatenateNgrams": false });
+    let (response, _status_code) = server.post_request("/indexes/movies/debug/analyze", body).await;
+    assert_eq!(response["ngrams"], json!([]));
+}
+
+#[actix_rt::test]
+async fn judgments_measure_the_relevancy_of_the_index() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Batman Begins", "overview": "Bruce Wayne in Gotham" },
+        { "id": 2, "title": "Joker", "overview": "A batman villain" },
+        { "id": 3, "title": "Gotham", "overview": "Before batman" },
+    ])).await;
+
+    let body = json!([
+        { "query": "batman", "expected": ["1", "2"] },
+        { "query": "gotham", "expected": ["3"], "limit": 2 },
+    ]);
+    let (_response, status_code) = server.put_request("/indexes/movies/judgments", body).await;
+    assert_eq!(status_code, 200);
+
+    let (response, status_code) = server.post_request("/indexes/movies/judgments/run", json!({})).await;
+    assert_eq!(status_code, 200);
+    assert!(response["previousRunAt"].is_null());
+    let judgments = response["judgments"].as_array().unwrap();
+    assert_eq!(judgments[0]["hits"].as_array().unwrap().len(), 2);
+    assert_eq!(judgments[1]["recall"], 1.0);
+    assert_eq!(judgments[1]["precision"], 0.5);
+    assert!(judgments[1]["delta"].is_null());
+
+    // the next runs are compared to the previous one
+    let (response, _status_code) = server.post_request("/indexes/movies/judgments/run", json!({})).await;
+    assert!(response["previousRunAt"].is_string());
+    assert_eq!(response["meanDelta"], json!({ "precision": 0.0, "recall": 0.0 }));
+
+    let body = json!([{ "query": "batman", "expected": [] }]);
+    let (_response, status_code) = server.put_request("/indexes/movies/judgments", body).await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn transpositions_rank_before_other_typos() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Reciave" },
+        { "id": 2, "title": "Receive" },
+        { "id": 3, "title": "Thing" },
+        { "id": 4, "title": "Tang" },
+    ])).await;
+
+    let (response, _status_code) = server.search("q=recieve").await;
+    let ids: Vec<_> = response["hits"].as_array().unwrap().iter().map(|hit| hit["id"].clone()).collect();
+    assert_eq!(ids, vec![json!(2), json!(1)]);
+
+    // the short words only tolerate transpositions, even of their first characters
+    let (response, _status_code) = server.search("q=htin").await;
+    let ids: Vec<_> = response["hits"].as_array().unwrap().iter().map(|hit| hit["id"].clone()).collect();
+    assert_eq!(ids, vec![json!(3)]);
+}
+
+#[actix_rt::test]
+async fn short_last_words_are_not_searched_as_prefixes() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Batman" },
+        { "id": 2, "title": "Ba" },
+    ])).await;
+
+    let (response, _status_code) = server.search("q=ba").await;
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+
+    server.update_all_settings(json!({ "minPrefixLength": 3 })).await;
+
+    let (response, _status_code) = server.search("q=ba").await;
+    assert_eq!(response["hits"], json!([{ "id": 2, "title": "Ba" }]));
+
+    let (response, _status_code) = server.search("q=bat").await;
+    assert_eq!(response["hits"], json!([{ "id": 1, "title": "Batman" }]));
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["minPrefixLength"], json!(3));
+}
+
+#[actix_rt::test]
+async fn queries_are_normalized_before_being_searched() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Saint Louis" },
+        { "id": 2, "title": "So good" },
+        { "id": 3, "title": "USA today" },
+    ])).await;
+
+    let (response, _status_code) = server.search("q=sooooo").await;
+    assert_eq!(response["hits"], json!([]));
+    let (response, _status_code) = server.search("q=stl").await;
+    assert_eq!(response["hits"], json!([]));
+
+    let normalization = json!({
+        "stripPunctuation": true,
+        "collapseRepeatedCharacters": true,
+        "rewrites": { "STL": "saint louis" },
+    });
+    server.update_all_settings(json!({ "queryNormalization": normalization })).await;
+
+    let (response, _status_code) = server.search("q=sooooo").await;
+    assert_eq!(response["hits"], json!([{ "id": 2, "title": "So good" }]));
+    let (response, _status_code) = server.search("q=stl").await;
+    assert_eq!(response["hits"], json!([{ "id": 1, "title": "Saint Louis" }]));
+    let (response, _status_code) = server.search("q=U.S.A.").await;
+    assert_eq!(response["hits"], json!([{ "id": 3, "title": "USA today" }]));
+
+    // the words to rewrite are stored lowercased
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["queryNormalization"]["rewrites"], json!({ "stl": "saint louis" }));
+    assert_eq!(response["queryNormalization"]["stripPunctuation"], true);
+}
+
+#[actix_rt::test]
+async fn concatenated_queries_are_segmented() {
+    let mut server = common::Server::with_uid("books");
+    server.create_index(json!({ "uid": "books", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "Potter, the life of Harry" },
+        { "id": 2, "title": "Harry Potter" },
+        { "id": 3, "title": "Dirty Harry" },
+    ])).await;
+
+    // the split is ranked like the words typed with a space
+    let (response, _status_code) = server.search("q=harrypotter&attributesToRetrieve=id").await;
+    assert_eq!(response["hits"], json!([{ "id": 2 }, { "id": 1 }]));
+    assert_eq!(response["segmentations"], json!([{ "word": "harrypotter", "split": ["harry", "potter"] }]));
+
+    let (response, _status_code) = server.search("q=harry%20potter&attributesToRetrieve=id").await;
+    assert_eq!(response["hits"], json!([{ "id": 2 }, { "id": 1 }]));
+    assert_eq!(response.get("segmentations"), None);
+}
+
+#[actix_rt::test]
+async fn short_words_are_corrected_with_the_adjacent_keys() {
+    let mut server = common::Server::with_uid("pets");
+    server.create_index(json!({ "uid": "pets", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "name": "dog" },
+        { "id": 2, "name": "log" },
+        { "id": 3, "name": "mix" },
+    ])).await;
+
+    // the short words only tolerate transpositions
+    let (response, _status_code) = server.search("q=fog").await;
+    assert_eq!(response["hits"], json!([]));
+
+    server.update_all_settings(json!({ "keyboardTypos": true })).await;
+
+    let (response, _status_code) = server.search("q=fog").await;
+    assert_eq!(response["hits"], json!([{ "id": 1, "name": "dog" }]));
+
+    // the l is next to the m on an AZERTY keyboard only
+    let (response, _status_code) = server.search("q=lix").await;
+    assert_eq!(response["hits"], json!([]));
+
+    server.update_all_settings(json!({ "keyboardLayout": "azerty" })).await;
+
+    let (response, _status_code) = server.search("q=lix").await;
+    assert_eq!(response["hits"], json!([{ "id": 3, "name": "mix" }]));
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["keyboardTypos"], json!(true));
+    assert_eq!(response["keyboardLayout"], json!("azerty"));
+}
+
+#[actix_rt::test]
+async fn keyboard_corrections_are_ranked_as_typos() {
+    let mut server = common::Server::with_uid("pets");
+    server.create_index(json!({ "uid": "pets", "primaryKey": "id" })).await;
+    server.update_all_settings(json!({ "keyboardTypos": true, "rankingRules": ["typo", "words"] })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "name": "dog" },
+        { "id": 2, "name": "fog" },
+    ])).await;
+
+    let (response, _status_code) = server.search("q=fog").await;
+    assert_eq!(response["hits"], json!([{ "id": 2, "name": "fog" }, { "id": 1, "name": "dog" }]));
+}
+
+#[actix_rt::test]
+async fn numbers_match_the_numbers_around_them() {
+    let mut server = common::Server::with_uid("screens");
+    server.create_index(json!({ "uid": "screens", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "1080p monitor" },
+        { "id": 2, "title": "720p monitor" },
+    ])).await;
+
+    let (response, _status_code) = server.search("q=1100%20monitor").await;
+    assert_eq!(response["hits"], json!([]));
+
+    server.update_all_settings(json!({ "numericTolerance": 0.05 })).await;
+
+    let (response, _status_code) = server.search("q=1100%20monitor").await;
+    assert_eq!(response["hits"], json!([{ "id": 1, "title": "1080p monitor" }]));
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["numericTolerance"], json!(0.05));
+}
+
+#[actix_rt::test]
+async fn attributes_can_be_filtered_by_their_aliases() {
+    let mut server = common::Server::with_uid("books");
+    server.create_index(json!({ "uid": "books", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "The Hobbit", "writer": "Tolkien" },
+        { "id": 2, "title": "The Shining", "writer": "King" },
+    ])).await;
+    server.update_all_settings(json!({
+        "attributesForFaceting": ["writer"],
+        "attributeAliases": { "author": "writer" },
+    })).await;
+
+    // filters=author = Tolkien
+    let (response, _status_code) = server.search("q=the&filters=author%20%3D%20Tolkien").await;
+    assert_eq!(response["hits"], json!([{ "id": 1, "title": "The Hobbit", "writer": "Tolkien" }]));
+
+    // facetFilters=["author:King"]
+    let (response, _status_code) = server.search("q=the&facetFilters=%5B%22author%3AKing%22%5D").await;
+    assert_eq!(response["hits"], json!([{ "id": 2, "title": "The Shining", "writer": "King" }]));
+
+    // the attributes are still named by their names
+    let (response, _status_code) = server.search("q=the&facetFilters=%5B%22writer%3AKing%22%5D").await;
+    assert_eq!(response["hits"], json!([{ "id": 2, "title": "The Shining", "writer": "King" }]));
+
+    // the facets are counted under the aliases
+    let (response, _status_code) = server.search("q=the&facets=%5B%22*%22%5D").await;
+    assert_eq!(response["facets"], json!({ "author": { "Tolkien": 1, "King": 1 } }));
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["attributeAliases"], json!({ "author": "writer" }));
+}
+
+#[actix_rt::test]
+async fn aliases_must_name_an_attribute_without_shadowing_one() {
+    let mut server = common::Server::with_uid("books");
+    server.create_index(json!({ "uid": "books", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "The Hobbit", "writer": "Tolkien" },
+    ])).await;
+
+    for aliases in &[json!({ "title": "writer" }), json!({ "author": "unknown" })] {
+        let body = json!({ "attributeAliases": aliases });
+        let (response, status_code) = server.post_request("/indexes/books/settings", body).await;
+        assert_eq!(status_code, 202);
+        let update_id = response["updateId"].as_u64().unwrap();
+        server.wait_update_id(update_id).await;
+
+        let (response, _status_code) = server.get_update_status(update_id).await;
+        assert_eq!(response["status"], "failed");
+    }
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["attributeAliases"], json!(null));
+}
+
+#[actix_rt::test]
+async fn hits_are_diversified_by_attribute() {
+    let mut server = common::Server::with_uid("articles");
+    server.create_index(json!({ "uid": "articles", "primaryKey": "id" })).await;
+    server.add_or_replace_multiple_documents(json!([
+        { "id": 1, "title": "news 1", "domain": "a.com" },
+        { "id": 2, "title": "news 2", "domain": "a.com" },
+        { "id": 3, "title": "news 3", "domain": "a.com" },
+        { "id": 4, "title": "news 4", "domain": "b.com" },
+    ])).await;
+
+    let ids = |response: &Value| response["hits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["id"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+
+    // sort=["id:asc"]
+    let (response, _status_code) = server.search("q=news&sort=%5B%22id%3Aasc%22%5D").await;
+    assert_eq!(ids(&response), vec![1, 2, 3, 4]);
+
+    // diversify={"attribute":"domain","maxPerValue":2}
+    let diversify = "diversify=%7B%22attribute%22%3A%22domain%22%2C%22maxPerValue%22%3A2%7D";
+    let (response, _status_code) = server.search(&format!("q=news&sort=%5B%22id%3Aasc%22%5D&{}", diversify)).await;
+    assert_eq!(ids(&response), vec![1, 2, 4, 3]);
+    assert_eq!(response["nbHits"], 4);
+
+    let (response, _status_code) = server.search(&format!("q=news&sort=%5B%22id%3Aasc%22%5D&limit=2&offset=2&{}", diversify)).await;
+    assert_eq!(ids(&response), vec![4, 3]);
+
+    // diversify={"attribute":"unknown","maxPerValue":2}
+    let query = "q=news&diversify=%7B%22attribute%22%3A%22unknown%22%2C%22maxPerValue%22%3A2%7D";
+    let (_response, status_code) = server.search(query).await;
+    assert_eq!(status_code, 400);
+}
+
+#[actix_rt::test]
+async fn hits_can_be_sampled() {
+    let mut server = common::Server::with_uid("items");
+    server.create_index(json!({ "uid": "items", "primaryKey": "id" })).await;
+    let documents: Vec<_> = (1..=10).map(|id| json!({ "id": id, "title": "item" })).collect();
+    server.add_or_replace_multiple_documents(json!(documents)).await;
+
+    let ids = |response: &Value| response["hits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["id"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+
+    let (response, _status_code) = server.search("q=item&sample=3&sampleSeed=42").await;
+    let sample = ids(&response);
+    assert_eq!(sample.len(), 3);
+    assert_eq!(response["nbHits"], 10);
+    assert_eq!(response["sampleSeed"], 42);
+
+    let (response, _status_code) = server.search("q=item&sample=3&sampleSeed=42").await;
+    assert_eq!(ids(&response), sample);
+
+    // the seed drawn is returned to draw the same sample again
+    let (response, _status_code) = server.search("q=item&sample=3").await;
+    let seed = response["sampleSeed"].as_u64().unwrap();
+    let (other, _status_code) = server.search(&format!("q=item&sample=3&sampleSeed={}", seed)).await;
+    assert_eq!(ids(&other), ids(&response));
+
+    // the sample is drawn from the filtered hits
+    let (response, _status_code) = server.search("q=item&sample=5&filters=id%20%3E%207").await;
+    let mut sample = ids(&response);
+    sample.sort();
+    assert_eq!(sample, vec![8, 9, 10]);
+    assert_eq!(response["nbHits"], 3);
+
+    let query = "q=item&sample=3&diversify=%7B%22attribute%22%3A%22title%22%2C%22maxPerValue%22%3A1%7D";
+    let (_response, status_code) = server.search(query).await;
+    assert_eq!(status_code, 400);
+}
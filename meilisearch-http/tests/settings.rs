@@ -436,3 +436,97 @@ async fn write_setting_and_update_partial() {
 
     assert_json_eq!(expected, response, ordered: false);
 }
+
+#[actix_rt::test]
+async fn search_cutoff_setting() {
+    let mut server = common::Server::with_uid("movies");
+    server.populate_movies().await;
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert!(response.get("searchCutoffMs").is_none());
+
+    server.update_all_settings(json!({ "searchCutoffMs": 50 })).await;
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["searchCutoffMs"], 50);
+
+    server.delete_all_settings().await;
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert!(response.get("searchCutoffMs").is_none());
+}
+
+#[actix_rt::test]
+async fn replace_all_settings() {
+    let mut server = common::Server::with_uid("movies");
+    server.populate_movies().await;
+
+    server.update_all_settings(json!({
+        "distinctAttribute": "title",
+        "stopWords": ["the", "a"],
+        "synonyms": { "road": ["street"] },
+    })).await;
+
+    // the settings that are not given are reset
+    server.replace_all_settings(json!({
+        "rankingRules": ["typo", "words", "desc(release_date)"],
+        "stopWords": ["of"],
+    })).await;
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_eq!(response["rankingRules"], json!(["typo", "words", "desc(release_date)"]));
+    assert_eq!(response["stopWords"], json!(["of"]));
+    assert_eq!(response["synonyms"], json!({}));
+    assert_eq!(response["distinctAttribute"], json!(null));
+    assert_eq!(response["acceptNewFields"], true);
+
+    // the exported settings can be applied back as they are
+    let exported = response;
+    server.replace_all_settings(exported.clone()).await;
+
+    let (response, _status_code) = server.get_all_settings().await;
+    assert_json_eq!(response, exported, ordered: false);
+}
+
+
+#[actix_rt::test]
+async fn payload_limit_of_the_settings_routes() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    // the test server limits the settings payloads to 64KB
+    let words: Vec<_> = (0..20_000).map(|i| format!("word{}", i)).collect();
+    let (_response, status_code) = server.post_request_with_length("/indexes/movies/settings", json!({ "stopWords": words })).await;
+    assert_eq!(status_code, 413);
+
+    // a payload without a length is cut once it goes over the limit
+    let (_response, status_code) = server.post_request("/indexes/movies/settings", json!({ "stopWords": words })).await;
+    assert_eq!(status_code, 413);
+
+    let documents: Vec<_> = (0..5_000).map(|i| json!({ "id": i, "title": format!("movie {}", i) })).collect();
+    let (_response, status_code) = server.post_request_with_length("/indexes/movies/documents", json!(documents)).await;
+    assert_eq!(status_code, 202);
+}
+
+#[actix_rt::test]
+async fn search_cutoff_setting_is_the_fallback_of_the_searches() {
+    let mut server = common::Server::with_uid("movies");
+    server.populate_movies().await;
+
+    server.update_all_settings(json!({ "searchCutoffMs": 0 })).await;
+
+    let (response, _status_code) = server.search("q=a").await;
+    assert_eq!(response["degraded"], true);
+
+    let body = json!({ "q": "a", "indexes": [{ "indexUid": "movies" }] });
+    let (response, _status_code) = server.post_request("/indexes/search", body).await;
+    assert_eq!(response["degraded"], true);
+
+    // the cutoff of the request takes precedence over the setting
+    let (response, _status_code) = server.search("q=a&searchCutoffMs=60000").await;
+    assert!(response.get("degraded").is_none());
+
+    let body = json!({ "q": "a", "indexes": [{ "indexUid": "movies" }], "searchCutoffMs": 60000 });
+    let (response, _status_code) = server.post_request("/indexes/search", body).await;
+    assert!(response.get("degraded").is_none());
+}
@@ -136,6 +136,44 @@ async fn send_malformed_custom_rule() {
     assert_eq!(status_code, 400);
 }
 
+#[actix_rt::test]
+async fn invalid_rule_error_position() {
+    let mut server = common::Server::with_uid("movies");
+    let body = json!({
+        "uid": "movies",
+        "primaryKey": "id",
+    });
+    server.create_index(body).await;
+
+    let body = json!(["typo", "dsc(price)"]);
+    let (response, status_code) = server.update_ranking_rules_sync(body).await;
+    assert_eq!(status_code, 400);
+
+    let expected = json!({
+        "index": 1,
+        "rule": "dsc(price)",
+        "start": 0,
+        "end": 3,
+        "reason": "the order must be asc or desc",
+        "suggestion": "desc(price)",
+    });
+    assert_json_eq!(response["rankingRule"], expected, ordered: false);
+
+    let body = json!(["proximty"]);
+    let (response, _) = server.update_ranking_rules_sync(body).await;
+    assert_eq!(response["rankingRule"]["suggestion"], "proximity");
+
+    let body = json!(["asc(price"]);
+    let (response, _) = server.update_ranking_rules_sync(body).await;
+    assert_eq!(response["rankingRule"]["start"], 3);
+    assert_eq!(response["rankingRule"]["suggestion"], "asc(price)");
+
+    let body = json!(["asc(pr ice)"]);
+    let (response, _) = server.update_ranking_rules_sync(body).await;
+    assert_eq!(response["rankingRule"]["start"], 6);
+    assert_eq!(response["rankingRule"]["end"], 7);
+}
+
 // Test issue https://github.com/meilisearch/MeiliSearch/issues/521
 #[actix_rt::test]
 async fn write_custom_ranking_and_index_documents() {
@@ -182,3 +220,78 @@ async fn write_custom_ranking_and_index_documents() {
 
     assert_json_eq!(response, expected, ordered: false);
 }
+
+#[actix_rt::test]
+async fn sort_documents_missing_the_attribute_first() {
+    let mut server = common::Server::with_uid("movies");
+    let body = json!({
+        "uid": "movies",
+        "primaryKey": "id",
+    });
+    server.create_index(body).await;
+
+    let body = json!([
+        { "id": 1, "title": "cheap movie", "price": 10 },
+        { "id": 2, "title": "free movie" },
+        { "id": 3, "title": "expensive movie", "price": 30 },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    server.update_ranking_rules(json!(["desc(price)"])).await;
+    let (response, _) = server.search("q=movie").await;
+    let ids: Vec<_> = response["hits"].as_array().unwrap().iter().map(|h| h["id"].clone()).collect();
+    assert_eq!(ids, vec![json!(3), json!(1), json!(2)]);
+
+    server.update_ranking_rules(json!(["desc(price) nulls first"])).await;
+    let (response, _) = server.get_ranking_rules().await;
+    assert_eq!(response, json!(["desc(price) nulls first"]));
+
+    let (response, _) = server.search("q=movie").await;
+    let ids: Vec<_> = response["hits"].as_array().unwrap().iter().map(|h| h["id"].clone()).collect();
+    assert_eq!(ids, vec![json!(2), json!(3), json!(1)]);
+
+    // the explicit default is kept as written
+    server.update_ranking_rules(json!(["desc(price) nulls last"])).await;
+    let (response, _) = server.get_ranking_rules().await;
+    assert_eq!(response, json!(["desc(price) nulls last"]));
+
+    let (response, _) = server.search("q=movie").await;
+    let ids: Vec<_> = response["hits"].as_array().unwrap().iter().map(|h| h["id"].clone()).collect();
+    assert_eq!(ids, vec![json!(3), json!(1), json!(2)]);
+
+    let body = json!(["typo nulls first"]);
+    let (response, status_code) = server.update_ranking_rules_sync(body).await;
+    assert_eq!(status_code, 400);
+    assert_eq!(response["rankingRule"]["reason"], "only the asc and desc rules accept a nulls modifier");
+}
+
+#[actix_rt::test]
+async fn sort_documents_by_an_expression() {
+    let mut server = common::Server::with_uid("movies");
+    let body = json!({
+        "uid": "movies",
+        "primaryKey": "id",
+    });
+    server.create_index(body).await;
+
+    let body = json!([
+        { "id": 1, "title": "popular movie", "popularity": 10, "rating": 2 },
+        { "id": 2, "title": "acclaimed movie", "popularity": 2, "rating": 9 },
+        { "id": 3, "title": "unrated movie", "popularity": 20 },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    server.update_ranking_rules(json!(["expr(popularity * 0.3 + rating * 0.7)"])).await;
+    let (response, _) = server.get_ranking_rules().await;
+    assert_eq!(response, json!(["expr(popularity * 0.3 + rating * 0.7)"]));
+
+    let (response, _) = server.search("q=movie").await;
+    let ids: Vec<_> = response["hits"].as_array().unwrap().iter().map(|h| h["id"].clone()).collect();
+    assert_eq!(ids, vec![json!(2), json!(1), json!(3)]);
+
+    let body = json!(["expr(popularity * )"]);
+    let (response, status_code) = server.update_ranking_rules_sync(body).await;
+    assert_eq!(status_code, 400);
+    assert_eq!(response["rankingRule"]["start"], 18);
+    assert_eq!(response["rankingRule"]["reason"], "unexpected end of expression");
+}
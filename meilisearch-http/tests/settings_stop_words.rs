@@ -61,3 +61,45 @@ async fn add_documents_and_stop_words() {
 
     // assert!(!response["hits"].as_array().unwrap().is_empty());
 }
+
+#[actix_rt::test]
+async fn stop_words_preset() {
+    let mut server = common::Server::with_uid("movies");
+    server.populate_movies().await;
+
+    let body = json!({
+        "stopWords": { "preset": "en", "add": ["mask"], "remove": ["the"] },
+    });
+    server.update_all_settings(body).await;
+
+    let (response, _status_code) = server.get_stop_words().await;
+    let stop_words: Vec<_> = response.as_array().unwrap().iter().filter_map(|w| w.as_str()).collect();
+    assert!(stop_words.contains(&"of"));
+    assert!(stop_words.contains(&"mask"));
+    assert!(!stop_words.contains(&"the"));
+
+    let body = json!({ "stopWords": { "preset": "xx" } });
+    let (response, status_code) = server.post_request("/indexes/movies/settings", body).await;
+    assert_eq!(status_code, 400);
+    assert!(response["message"].as_str().unwrap().contains("unknown stop words preset"));
+}
+
+#[actix_rt::test]
+async fn stop_words_route_preset() {
+    let mut server = common::Server::with_uid("movies");
+    server.populate_movies().await;
+
+    let body = json!({ "preset": "en", "add": ["mask"], "remove": ["the"] });
+    server.update_stop_words(body).await;
+
+    let (response, _status_code) = server.get_stop_words().await;
+    let stop_words: Vec<_> = response.as_array().unwrap().iter().filter_map(|w| w.as_str()).collect();
+    assert!(stop_words.contains(&"of"));
+    assert!(stop_words.contains(&"mask"));
+    assert!(!stop_words.contains(&"the"));
+
+    let body = json!({ "preset": "xx" });
+    let (response, status_code) = server.post_request("/indexes/movies/settings/stop-words", body).await;
+    assert_eq!(status_code, 400);
+    assert!(response["message"].as_str().unwrap().contains("unknown stop words preset"));
+}
@@ -0,0 +1,82 @@
+use serde_json::json;
+
+mod common;
+
+#[actix_rt::test]
+async fn export_and_import_synonyms() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    server.update_synonyms(json!({
+        "hp": ["harry potter"],
+        "wolverine": ["logan", "xmen"],
+    })).await;
+
+    let (file, status_code) = server.get_request_text("/indexes/movies/settings/synonyms/export?format=solr").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(file, "hp => harry potter\nwolverine => logan, xmen\n");
+
+    let (file, status_code) = server.get_request_text("/indexes/movies/settings/synonyms/export?format=csv").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(file, "hp,harry potter\nwolverine,logan,xmen\n");
+
+    let solr = "# superheroes\nwolverine => logan\nbatman, bruce wayne\n";
+
+    // a dry run only describes the changes
+    let url = "/indexes/movies/settings/synonyms/import?format=solr&dryRun=true";
+    let (response, status_code) = server.post_request_text(url, solr).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["updateId"], json!(null));
+    assert_eq!(response["dryRun"], json!(true));
+    assert_eq!(response["added"], json!(["batman", "bruce wayne"]));
+    assert_eq!(response["removed"], json!(["hp"]));
+    assert_eq!(response["changed"], json!(["wolverine"]));
+
+    let url = "/indexes/movies/settings/synonyms/import?format=solr";
+    let (response, status_code) = server.post_request_text(url, solr).await;
+    assert_eq!(status_code, 202);
+    server.wait_update_id(response["updateId"].as_u64().unwrap()).await;
+
+    let (response, _status_code) = server.get_synonyms().await;
+    assert_eq!(response, json!({
+        "batman": ["bruce wayne"],
+        "bruce wayne": ["batman"],
+        "wolverine": ["logan"],
+    }));
+
+    let url = "/indexes/movies/settings/synonyms/import?format=csv";
+    let (response, status_code) = server.post_request_text(url, "spiderman,\"peter parker\"\nhulk\n").await;
+    assert_eq!(status_code, 400);
+    assert_eq!(response["message"], "line 2: a word must have at least one synonym");
+}
+
+#[actix_rt::test]
+async fn synonyms_files_escape_the_separators() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    server.update_synonyms(json!({
+        "c#": ["csharp"],
+        "r&b": ["rhythm, blues", "soul => funk"],
+    })).await;
+
+    let (file, _status_code) = server.get_request_text("/indexes/movies/settings/synonyms/export?format=solr").await;
+    assert_eq!(file, "c\\# => csharp\nr&b => rhythm\\, blues, soul \\=\\> funk\n");
+
+    // the exported file is imported back without any change
+    let url = "/indexes/movies/settings/synonyms/import?format=solr&dryRun=true";
+    let (response, status_code) = server.post_request_text(url, &file).await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["added"], json!([]));
+    assert_eq!(response["removed"], json!([]));
+    assert_eq!(response["changed"], json!([]));
+
+    // the fields of a CSV record are trimmed
+    let url = "/indexes/movies/settings/synonyms/import?format=csv";
+    let (response, status_code) = server.post_request_text(url, " Hulk , bruce banner ,\"rhythm, blues\"\n").await;
+    assert_eq!(status_code, 202);
+    server.wait_update_id(response["updateId"].as_u64().unwrap()).await;
+
+    let (response, _status_code) = server.get_synonyms().await;
+    assert_eq!(response, json!({ "hulk": ["bruce banner", "rhythm, blues"] }));
+}
@@ -0,0 +1,59 @@
+use serde_json::{json, Value};
+
+mod common;
+
+#[actix_rt::test]
+async fn get_indexed_word() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+    server.update_stop_words(json!(["the"])).await;
+
+    let body = json!([
+        { "id": 1, "title": "The Wonder Years", "overview": "wonder wonder" },
+        { "id": 2, "title": "Wonder Woman" },
+        { "id": 3, "title": "Carol" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, status_code) = server.get_request("/indexes/movies/words/Wonder").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["word"], "wonder");
+    assert_eq!(response["isStopWord"], false);
+    assert_eq!(response["numberOfDocuments"], 2);
+    assert_eq!(response["numberOfOccurrences"], 4);
+    assert_eq!(response["attributes"], json!({ "title": 2, "overview": 2 }));
+    assert_eq!(response["sampleDocuments"].as_array().unwrap().len(), 2);
+
+    let (response, status_code) = server.get_request("/indexes/movies/words/the").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["isStopWord"], true);
+    assert_eq!(response["numberOfDocuments"], 0);
+
+    let (_, status_code) = server.get_request("/indexes/movies/words/batman").await;
+    assert_eq!(status_code, 404);
+}
+
+#[actix_rt::test]
+async fn export_words() {
+    let mut server = common::Server::with_uid("movies");
+    server.create_index(json!({ "uid": "movies", "primaryKey": "id" })).await;
+
+    let body = json!([
+        { "id": 1, "title": "Wonder Years" },
+        { "id": 2, "title": "Wonder Woman" },
+    ]);
+    server.add_or_replace_multiple_documents(body).await;
+
+    let (response, status_code) = server.get_request_text("/indexes/movies/words?prefix=wo").await;
+    assert_eq!(status_code, 200);
+
+    let words: Vec<Value> = response.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    let expected = vec![
+        json!({ "word": "woman", "numberOfDocuments": 1 }),
+        json!({ "word": "wonder", "numberOfDocuments": 2 }),
+    ];
+    assert_eq!(words, expected);
+
+    let (response, _) = server.get_request_text("/indexes/movies/words?minDocuments=2").await;
+    assert_eq!(response, "{\"word\":\"wonder\",\"numberOfDocuments\":2}\n");
+}
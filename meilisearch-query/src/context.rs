@@ -0,0 +1,63 @@
+use crate::keyboard::Layout;
+use crate::DEFAULT_MIN_PREFIX_LENGTH;
+
+/// What the construction of a query tree needs to know about the index it is built for.
+///
+/// The query tree doesn't read anything else from the index, an implementation can
+/// be backed by the stores of an index as well as by a handful of test words.
+pub trait QueryContext {
+    type Error;
+
+    /// Whether the word, lowercased, is ignored in the queries.
+    fn is_stop_word(&self, word: &str) -> bool;
+
+    /// The number of documents containing the word, used to split the query words in two.
+    fn word_frequency(&self, word: &str) -> Result<usize, Self::Error>;
+
+    /// The number of documents containing each of the words, in the same order,
+    /// a context can look the words up all at once rather than one by one.
+    fn words_frequencies(&self, words: &[&str]) -> Result<Vec<usize>, Self::Error> {
+        words.iter().map(|word| self.word_frequency(word)).collect()
+    }
+
+    /// The alternatives of the words, each one being a sequence of words,
+    /// the words are given as they are in the query and must be normalized by the context.
+    fn synonyms(&self, words: &[&str]) -> Result<Vec<Vec<String>>, Self::Error>;
+
+    /// Whether the consecutive query words are also searched concatenated.
+    fn concatenate_ngrams(&self) -> bool {
+        true
+    }
+
+    /// Whether the query words are also searched split in two frequent words.
+    fn split_words(&self) -> bool {
+        true
+    }
+
+    /// Whether the short query words are also searched with a letter replaced by an adjacent key.
+    fn keyboard_typos(&self) -> bool {
+        false
+    }
+
+    /// The layout of the keyboard the adjacent keys are found on.
+    fn keyboard_layout(&self) -> Layout {
+        Layout::Qwerty
+    }
+
+    /// The indexed words starting with a number within the numeric tolerance
+    /// of the number, the closest first, none when there is no tolerance.
+    fn nearby_numbers(&self, _number: u64) -> Result<Vec<String>, Self::Error> {
+        Ok(Vec::new())
+    }
+
+    /// Whether the quantities of the query are rewritten into their canonical words.
+    fn normalize_units(&self) -> bool {
+        false
+    }
+
+    /// The number of characters the last query word must have to be searched as a prefix,
+    /// the shorter ones would match too many words and are searched as they are.
+    fn min_prefix_length(&self) -> usize {
+        DEFAULT_MIN_PREFIX_LENGTH
+    }
+}
@@ -0,0 +1,113 @@
+//! Typos made by hitting a key next to the intended one, on the layout of the index.
+//!
+//! The short query words only tolerate transpositions, any substitution would match
+//! too many words, the substitutions by an adjacent key are the likely ones and are
+//! searched as alternatives of the word when they are indexed.
+
+/// The keyboard layouts the keys around a letter are known for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Qwerty,
+    Azerty,
+    Qwertz,
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout::Qwerty
+    }
+}
+
+impl Layout {
+    /// The rows of letters of the keyboard, each one shifted by half a key from the previous one.
+    fn rows(self) -> [&'static str; 3] {
+        match self {
+            Layout::Qwerty => ["qwertyuiop", "asdfghjkl", "zxcvbnm"],
+            Layout::Azerty => ["azertyuiop", "qsdfghjklm", "wxcvbn"],
+            Layout::Qwertz => ["qwertzuiop", "asdfghjkl", "yxcvbnm"],
+        }
+    }
+}
+
+/// The longest words corrected with the adjacent keys, the longer ones tolerate any typo.
+pub const KEYBOARD_TYPOS_MAX_LEN: usize = 4;
+
+/// The number of corrections of a word that are searched, the most frequent ones are kept.
+pub const MAX_KEYBOARD_TYPOS: usize = 3;
+
+/// Returns the keys around the key of the letter, on its row and on the rows above and below.
+pub fn adjacent_keys(layout: Layout, letter: char) -> Vec<char> {
+    let rows = layout.rows();
+    let position = rows.iter().enumerate().find_map(|(r, row)| row.find(letter).map(|c| (r, c)));
+    let (row, column) = match position {
+        Some(position) => position,
+        None => return Vec::new(),
+    };
+
+    let mut keys = Vec::new();
+    let mut push = |row: &str, column: Option<usize>| {
+        if let Some(c) = column.and_then(|column| row.chars().nth(column)) {
+            keys.push(c);
+        }
+    };
+
+    let current = rows[row];
+    push(current, column.checked_sub(1));
+    push(current, Some(column + 1));
+    if let Some(above) = row.checked_sub(1).map(|r| rows[r]) {
+        push(above, Some(column));
+        push(above, Some(column + 1));
+    }
+    if let Some(below) = rows.get(row + 1) {
+        push(below, column.checked_sub(1));
+        push(below, Some(column));
+    }
+
+    keys
+}
+
+/// Returns the words made by replacing one letter of the word by an adjacent key.
+pub fn keyboard_typos(layout: Layout, word: &str) -> Vec<String> {
+    let chars: Vec<char> = word.chars().collect();
+    let mut typos = Vec::new();
+
+    for (i, letter) in chars.iter().enumerate() {
+        for key in adjacent_keys(layout, *letter) {
+            let mut typo = chars.clone();
+            typo[i] = key;
+            typos.push(typo.into_iter().collect());
+        }
+    }
+
+    typos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_around_letters() {
+        assert_eq!(adjacent_keys(Layout::Qwerty, 'g'), vec!['f', 'h', 't', 'y', 'v', 'b']);
+        assert_eq!(adjacent_keys(Layout::Qwerty, 'q'), vec!['w', 'a']);
+        assert_eq!(adjacent_keys(Layout::Qwerty, 'm'), vec!['n', 'j', 'k']);
+        assert_eq!(adjacent_keys(Layout::Qwerty, 'é'), Vec::<char>::new());
+
+        let typos = keyboard_typos(Layout::Qwerty, "fog");
+        assert!(typos.contains(&"dog".to_string()));
+        assert!(!typos.contains(&"log".to_string()));
+    }
+
+    #[test]
+    fn keys_around_letters_of_other_layouts() {
+        assert_eq!(adjacent_keys(Layout::Azerty, 'q'), vec!['s', 'a', 'z', 'w']);
+        assert_eq!(adjacent_keys(Layout::Azerty, 'm'), vec!['l', 'p']);
+        assert_eq!(adjacent_keys(Layout::Qwertz, 'z'), vec!['t', 'u', 'g', 'h']);
+
+        // the a is next to the s on a QWERTY keyboard but not on an AZERTY one
+        let typos = keyboard_typos(Layout::Azerty, "cat");
+        assert!(typos.contains(&"czt".to_string()));
+        assert!(!typos.contains(&"cst".to_string()));
+        assert!(keyboard_typos(Layout::Qwerty, "cat").contains(&"cst".to_string()));
+    }
+}
@@ -0,0 +1,21 @@
+//! The construction of the query trees from the query strings.
+//!
+//! Everything the construction needs to know about the index is asked to a [`QueryContext`],
+//! the crate doesn't depend on the storage of the indexes and can be fuzzed or used to
+//! validate the queries before they are sent.
+
+mod context;
+pub mod keyboard;
+mod memory;
+mod operation;
+mod query_tree;
+mod query_words_mapper;
+pub mod numbers;
+pub mod units;
+
+pub use self::context::QueryContext;
+pub use self::memory::MemoryQueryContext;
+pub use self::operation::{Operation, Query, QueryId, QueryKind, QueryTreeBuilder};
+pub use self::query_tree::{create_query_tree, query_prefix, query_words, segment_word, split_best_frequency};
+pub use self::query_tree::{DEFAULT_MIN_PREFIX_LENGTH, MAX_NGRAM, MAX_SPLIT_POSITIONS};
+pub use self::query_words_mapper::QueryWordsMapper;
@@ -0,0 +1,116 @@
+use std::collections::{BTreeSet, HashMap};
+use std::convert::Infallible;
+
+use crate::keyboard::Layout;
+use crate::numbers::{closest_numbers, leading_number, tolerance_range};
+use crate::{QueryContext, DEFAULT_MIN_PREFIX_LENGTH};
+
+/// A context holding its words in memory, for the validation of the queries
+/// away from the indexes and for the tests of the query trees construction.
+#[derive(Debug, Clone)]
+pub struct MemoryQueryContext {
+    pub stop_words: BTreeSet<String>,
+    /// The number of documents containing the words.
+    pub frequencies: HashMap<String, usize>,
+    /// The alternatives of the words, keyed by the lowercased words separated by spaces.
+    pub synonyms: HashMap<String, Vec<Vec<String>>>,
+    pub concatenate_ngrams: bool,
+    pub split_words: bool,
+    pub keyboard_typos: bool,
+    pub keyboard_layout: Layout,
+    /// The relative distance to the query numbers of the indexed numbers they match.
+    pub numeric_tolerance: Option<f64>,
+    pub normalize_units: bool,
+    pub min_prefix_length: usize,
+}
+
+impl Default for MemoryQueryContext {
+    fn default() -> MemoryQueryContext {
+        MemoryQueryContext {
+            stop_words: BTreeSet::new(),
+            frequencies: HashMap::new(),
+            synonyms: HashMap::new(),
+            concatenate_ngrams: true,
+            split_words: true,
+            keyboard_typos: false,
+            keyboard_layout: Layout::Qwerty,
+            numeric_tolerance: None,
+            normalize_units: false,
+            min_prefix_length: DEFAULT_MIN_PREFIX_LENGTH,
+        }
+    }
+}
+
+impl MemoryQueryContext {
+    pub fn new() -> MemoryQueryContext {
+        MemoryQueryContext::default()
+    }
+
+    pub fn add_stop_word(&mut self, word: &str) {
+        self.stop_words.insert(word.to_lowercase());
+    }
+
+    pub fn add_word(&mut self, word: &str, frequency: usize) {
+        self.frequencies.insert(word.to_lowercase(), frequency);
+    }
+
+    /// Adds an alternative to the words, both given as space separated words.
+    pub fn add_synonym(&mut self, words: &str, alternative: &str) {
+        let alternative = alternative.split_whitespace().map(str::to_lowercase).collect();
+        self.synonyms.entry(words.to_lowercase()).or_default().push(alternative);
+    }
+}
+
+impl QueryContext for MemoryQueryContext {
+    type Error = Infallible;
+
+    fn is_stop_word(&self, word: &str) -> bool {
+        self.stop_words.contains(word)
+    }
+
+    fn word_frequency(&self, word: &str) -> Result<usize, Infallible> {
+        Ok(self.frequencies.get(word).copied().unwrap_or(0))
+    }
+
+    fn synonyms(&self, words: &[&str]) -> Result<Vec<Vec<String>>, Infallible> {
+        let words = words.join(" ").to_lowercase();
+        Ok(self.synonyms.get(&words).cloned().unwrap_or_default())
+    }
+
+    fn concatenate_ngrams(&self) -> bool {
+        self.concatenate_ngrams
+    }
+
+    fn split_words(&self) -> bool {
+        self.split_words
+    }
+
+    fn keyboard_typos(&self) -> bool {
+        self.keyboard_typos
+    }
+
+    fn keyboard_layout(&self) -> Layout {
+        self.keyboard_layout
+    }
+
+    fn nearby_numbers(&self, number: u64) -> Result<Vec<String>, Infallible> {
+        let range = match self.numeric_tolerance.and_then(|t| tolerance_range(number, t)) {
+            Some(range) => range,
+            None => return Ok(Vec::new()),
+        };
+
+        let words = self.frequencies.keys().filter_map(|word| {
+            leading_number(word).filter(|n| range.contains(n)).map(|n| (n, word.clone()))
+        });
+
+        Ok(closest_numbers(number, words.collect()))
+    }
+
+    fn normalize_units(&self) -> bool {
+        self.normalize_units
+    }
+
+    fn min_prefix_length(&self) -> usize {
+        self.min_prefix_length
+    }
+}
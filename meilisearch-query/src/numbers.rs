@@ -0,0 +1,62 @@
+//! The numbers of the query matching the indexed numbers around them, within a relative
+//! tolerance, like `1080` matching the `1080p` and `1100` words of the documents.
+
+use std::ops::RangeInclusive;
+
+/// The number of indexed numbers searched around a query number, the closest ones are kept.
+pub const MAX_NEARBY_NUMBERS: usize = 10;
+
+/// Returns the numbers within the relative tolerance of the number,
+/// `None` if the tolerance is not a positive number.
+pub fn tolerance_range(number: u64, tolerance: f64) -> Option<RangeInclusive<u64>> {
+    if !tolerance.is_finite() || tolerance <= 0.0 {
+        return None;
+    }
+
+    let delta = (number as f64 * tolerance) as u64;
+    Some(number.saturating_sub(delta)..=number.saturating_add(delta))
+}
+
+/// Returns the number the word starts with, `1080` for `1080p`,
+/// the words starting with a zero followed by other digits are not numbers.
+pub fn leading_number(word: &str) -> Option<u64> {
+    let len = word.bytes().take_while(u8::is_ascii_digit).count();
+    let digits = &word[..len];
+    if digits.is_empty() || (digits.len() > 1 && digits.starts_with('0')) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Keeps the words of the numbers the closest to the number, the closest first,
+/// the word of the number itself is searched as any other word.
+pub fn closest_numbers(number: u64, mut words: Vec<(u64, String)>) -> Vec<String> {
+    let word = number.to_string();
+    words.retain(|(_, w)| *w != word);
+    words.sort_by(|(a, wa), (b, wb)| {
+        let distance = |n: u64| if n > number { n - number } else { number - n };
+        distance(*a).cmp(&distance(*b)).then_with(|| wa.cmp(wb))
+    });
+    words.truncate(MAX_NEARBY_NUMBERS);
+    words.into_iter().map(|(_, w)| w).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_within_the_tolerance() {
+        assert_eq!(tolerance_range(1080, 0.05), Some(1026..=1134));
+        assert_eq!(tolerance_range(10, 0.0), None);
+        assert_eq!(tolerance_range(10, f64::NAN), None);
+
+        assert_eq!(leading_number("1080p"), Some(1080));
+        assert_eq!(leading_number("0"), Some(0));
+        assert_eq!(leading_number("007"), None);
+        assert_eq!(leading_number("p1080"), None);
+
+        let words = vec![(1100, "1100".to_string()), (1080, "1080p".to_string()), (1080, "1080".to_string())];
+        assert_eq!(closest_numbers(1080, words), vec!["1080p", "1100"]);
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Operation {
+    And(Vec<Operation>),
+    Or(Vec<Operation>),
+    /// The documents not matching the operation, a child of an `And` excludes them from its documents.
+    Not(Box<Operation>),
+    Query(Query),
+}
+
+impl fmt::Debug for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn pprint_tree(f: &mut fmt::Formatter<'_>, op: &Operation, depth: usize) -> fmt::Result {
+            match op {
+                Operation::And(children) => {
+                    writeln!(f, "{:1$}AND", "", depth * 2)?;
+                    children.iter().try_for_each(|c| pprint_tree(f, c, depth + 1))
+                },
+                Operation::Or(children) => {
+                    writeln!(f, "{:1$}OR", "", depth * 2)?;
+                    children.iter().try_for_each(|c| pprint_tree(f, c, depth + 1))
+                },
+                Operation::Not(op) => {
+                    writeln!(f, "{:1$}NOT", "", depth * 2)?;
+                    pprint_tree(f, op, depth + 1)
+                },
+                Operation::Query(query) => writeln!(f, "{:2$}{:?}", "", query, depth * 2),
+            }
+        }
+
+        pprint_tree(f, self, 0)
+    }
+}
+
+impl Operation {
+    pub(crate) fn tolerant(id: QueryId, prefix: bool, s: &str) -> Operation {
+        Operation::Query(Query { id, prefix, exact: true, kind: QueryKind::Tolerant(s.to_string()) })
+    }
+
+    pub(crate) fn non_tolerant(id: QueryId, prefix: bool, s: &str) -> Operation {
+        Operation::Query(Query { id, prefix, exact: true, kind: QueryKind::NonTolerant(s.to_string()) })
+    }
+
+    pub(crate) fn phrase2(id: QueryId, prefix: bool, (left, right): (&str, &str)) -> Operation {
+        let kind = QueryKind::Phrase(vec![left.to_owned(), right.to_owned()]);
+        Operation::Query(Query { id, prefix, exact: true, kind })
+    }
+}
+
+/// Builds query trees programmatically, without going through the query string parser.
+///
+/// The builder generates the ids of the queries, the leaves of the trees are expected to
+/// be lowercased words, as they are indexed.
+#[derive(Debug, Default)]
+pub struct QueryTreeBuilder {
+    next_id: QueryId,
+}
+
+impl QueryTreeBuilder {
+    pub fn new() -> QueryTreeBuilder {
+        QueryTreeBuilder::default()
+    }
+
+    fn next_id(&mut self) -> QueryId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// A word matching the indexed words with typos.
+    pub fn tolerant(&mut self, word: &str, prefix: bool) -> Operation {
+        Operation::tolerant(self.next_id(), prefix, &word.to_lowercase())
+    }
+
+    /// A word matching the indexed words exactly.
+    pub fn non_tolerant(&mut self, word: &str, prefix: bool) -> Operation {
+        Operation::non_tolerant(self.next_id(), prefix, &word.to_lowercase())
+    }
+
+    /// Two words that must follow each other in the same attribute.
+    pub fn phrase(&mut self, first: &str, second: &str) -> Operation {
+        let id = self.next_id();
+        // a phrase matches two words in the highlights
+        self.next_id();
+        let (first, second) = (first.to_lowercase(), second.to_lowercase());
+        Operation::phrase2(id, false, (&first, &second))
+    }
+
+    pub fn and(operations: Vec<Operation>) -> Operation {
+        Operation::And(operations)
+    }
+
+    pub fn or(operations: Vec<Operation>) -> Operation {
+        Operation::Or(operations)
+    }
+
+    pub fn not(operation: Operation) -> Operation {
+        Operation::Not(Box::new(operation))
+    }
+}
+
+pub type QueryId = usize;
+
+#[derive(Clone, Eq)]
+pub struct Query {
+    pub id: QueryId,
+    pub prefix: bool,
+    pub exact: bool,
+    pub kind: QueryKind,
+}
+
+impl PartialEq for Query {
+    fn eq(&self, other: &Self) -> bool {
+        self.prefix == other.prefix && self.kind == other.kind
+    }
+}
+
+impl Hash for Query {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.prefix.hash(state);
+        self.kind.hash(state);
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    Tolerant(String),
+    NonTolerant(String),
+    /// A word made by hitting a key next to one of the letters of the query word,
+    /// searched exactly but ranked as a word with one typo.
+    Correction(String),
+    Phrase(Vec<String>),
+}
+
+impl fmt::Debug for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Query { id, prefix, kind, .. } = self;
+        let prefix = if *prefix { String::from("Prefix") } else { String::default() };
+        match kind {
+            QueryKind::NonTolerant(word) => {
+                f.debug_struct(&(prefix + "NonTolerant")).field("id", &id).field("word", &word).finish()
+            },
+            QueryKind::Tolerant(word) => {
+                f.debug_struct(&(prefix + "Tolerant")).field("id", &id).field("word", &word).finish()
+            },
+            QueryKind::Correction(word) => {
+                f.debug_struct(&(prefix + "Correction")).field("id", &id).field("word", &word).finish()
+            },
+            QueryKind::Phrase(words) => {
+                f.debug_struct(&(prefix + "Phrase")).field("id", &id).field("words", &words).finish()
+            },
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::{cmp, iter::once};
+
+use meilisearch_tokenizer::{split_query_string, Tokenizer};
+
+use crate::keyboard::{keyboard_typos, KEYBOARD_TYPOS_MAX_LEN, MAX_KEYBOARD_TYPOS};
+use crate::units::{is_number, normalize_word, normalize_words};
+use crate::{Operation, Query, QueryContext, QueryId, QueryKind, QueryWordsMapper};
+
+/// The maximum number of positions a word is split at, the positions the closest
+/// to the middle of the word are kept, so that long words cost a bounded number of lookups.
+pub const MAX_SPLIT_POSITIONS: usize = 12;
+
+/// Returns the split of the word in two words that are both the most frequent.
+pub fn split_best_frequency<'a, C: QueryContext>(
+    ctx: &C,
+    word: &'a str,
+) -> Result<Option<(&'a str, &'a str)>, C::Error>
+{
+    let mut positions: Vec<_> = word.char_indices().skip(1).map(|(i, _)| i).collect();
+    if positions.len() > MAX_SPLIT_POSITIONS {
+        let middle = word.len() / 2;
+        positions.sort_by_key(|&i| if i > middle { i - middle } else { middle - i });
+        positions.truncate(MAX_SPLIT_POSITIONS);
+        positions.sort_unstable();
+    }
+
+    let mut words = Vec::with_capacity(positions.len() * 2);
+    for &i in &positions {
+        let (left, right) = word.split_at(i);
+        words.push(left);
+        words.push(right);
+    }
+
+    let frequencies = ctx.words_frequencies(&words)?;
+    let mut best = None;
+
+    for (pair, frequencies) in words.chunks(2).zip(frequencies.chunks(2)) {
+        let min_freq = cmp::min(frequencies[0], frequencies[1]);
+        if min_freq != 0 && best.map_or(true, |(old, _, _)| min_freq > old) {
+            best = Some((min_freq, pair[0], pair[1]));
+        }
+    }
+
+    Ok(best.map(|(_, l, r)| (l, r)))
+}
+
+/// Returns the split of the word when it is trusted enough to be searched like the two words
+/// typed with a space in between: the word itself is not indexed while both words are.
+pub fn segment_word<'a, C: QueryContext>(
+    ctx: &C,
+    word: &'a str,
+) -> Result<Option<(&'a str, &'a str)>, C::Error>
+{
+    if ctx.word_frequency(word)? != 0 {
+        return Ok(None);
+    }
+    split_best_frequency(ctx, word)
+}
+
+/// Returns the indexed words made by hitting a key next to one of the letters of the word,
+/// the most frequent first.
+fn indexed_keyboard_typos<C: QueryContext>(ctx: &C, word: &str) -> Result<Vec<String>, C::Error> {
+    let len = word.chars().count();
+    if !ctx.keyboard_typos() || len < 2 || len > KEYBOARD_TYPOS_MAX_LEN {
+        return Ok(Vec::new());
+    }
+
+    let typos = keyboard_typos(ctx.keyboard_layout(), word);
+    let words: Vec<_> = typos.iter().map(String::as_str).collect();
+    let frequencies = ctx.words_frequencies(&words)?;
+
+    let mut typos: Vec<_> = typos.into_iter().zip(frequencies).filter(|(_, f)| *f != 0).collect();
+    typos.sort_by(|(a, fa), (b, fb)| fb.cmp(fa).then_with(|| a.cmp(b)));
+    typos.truncate(MAX_KEYBOARD_TYPOS);
+
+    Ok(typos.into_iter().map(|(typo, _)| typo).collect())
+}
+
+fn create_operation<I, F>(iter: I, f: F) -> Operation
+where I: IntoIterator<Item=Operation>,
+      F: Fn(Vec<Operation>) -> Operation,
+{
+    let mut iter = iter.into_iter();
+    match (iter.next(), iter.next()) {
+        (Some(first), None) => first,
+        (first, second) => f(first.into_iter().chain(second).chain(iter).collect()),
+    }
+}
+
+/// The maximum number of consecutive query words searched as synonyms or concatenated.
+pub const MAX_NGRAM: usize = 3;
+
+/// The last query words are searched as a prefix whatever their length by default.
+pub const DEFAULT_MIN_PREFIX_LENGTH: usize = 1;
+
+fn is_long_prefix<C: QueryContext>(ctx: &C, word: &str) -> bool {
+    word.chars().count() >= ctx.min_prefix_length()
+}
+
+/// Returns the words of the query that are used to build its query tree,
+/// lowercased and without the stop words.
+pub fn query_words<C: QueryContext>(ctx: &C, query: &str) -> Vec<String> {
+    let words = split_query_string(query)
+        .map(str::to_lowercase)
+        .filter(|w| !ctx.is_stop_word(w))
+        .collect();
+
+    if ctx.normalize_units() { normalize_words(words) } else { words }
+}
+
+/// Returns the beginning of the query holding its first `words` query words, the
+/// query tree of the beginning is the one of the query without its last words.
+///
+/// The query is cut right before the first word it doesn't keep, the quotes
+/// and the separators of the kept words are then left as they are.
+pub fn query_prefix<'q, C: QueryContext>(ctx: &C, query: &'q str, words: usize) -> &'q str {
+    for token in Tokenizer::new(query) {
+        // the words of the tokens are slices of the query
+        let start = token.word.as_ptr() as usize - query.as_ptr() as usize;
+        let end = start + token.word.len();
+        if query_words(ctx, &query[..end]).len() > words {
+            return &query[..start];
+        }
+    }
+    query
+}
+
+/// Builds the query tree of the query, the alternatives of its n-grams, with the
+/// query words, by index, each query of the tree has been derived from.
+pub fn create_query_tree<C: QueryContext>(
+    ctx: &C,
+    query: &str,
+) -> Result<(Operation, HashMap<QueryId, Range<usize>>), C::Error>
+{
+    let words: Vec<_> = query_words(ctx, query).into_iter().enumerate().collect();
+
+    let mut mapper = QueryWordsMapper::new(words.iter().map(|(_, w)| w));
+
+    fn create_inner<C: QueryContext>(
+        ctx: &C,
+        mapper: &mut QueryWordsMapper,
+        words: &[(usize, String)],
+    ) -> Result<Vec<Operation>, C::Error>
+    {
+        let mut alts = Vec::new();
+
+        for ngram in 1..=MAX_NGRAM {
+            if let Some(group) = words.get(..ngram) {
+                let mut group_ops = Vec::new();
+
+                let tail = &words[ngram..];
+                let is_last = tail.is_empty();
+
+                let mut group_alts = Vec::new();
+                match group {
+                    [(id, word)] => {
+                        let mut idgen = ((id + 1) * 100)..;
+                        let range = (*id)..id+1;
+                        let prefix = is_last && is_long_prefix(ctx, word);
+
+                        let split = if ctx.split_words() { split_best_frequency(ctx, word)? } else { None };
+                        let segmented = match split {
+                            Some(_) => ctx.word_frequency(word)? == 0,
+                            None => false,
+                        };
+                        let phrase = split.map(|ws| {
+                            let id = idgen.next().unwrap();
+                            idgen.next().unwrap();
+                            mapper.declare(range.clone(), id, &[ws.0, ws.1]);
+                            Operation::phrase2(id, prefix, ws)
+                        });
+
+                        // the word cannot match as it is, its split is also searched as two
+                        // query words, with typos and proximity, like the spaced words are
+                        let segmentation = split.filter(|_| segmented).map(|(left, right)| {
+                            let id = idgen.next().unwrap();
+                            let second_id = idgen.next().unwrap();
+                            mapper.declare(range.clone(), id, &[left, right]);
+                            Operation::And(vec![
+                                Operation::tolerant(id, false, left),
+                                Operation::tolerant(second_id, prefix, right),
+                            ])
+                        });
+
+                        let synonyms = ctx.synonyms(&[word])?
+                            .into_iter()
+                            .map(|alts| {
+                                let exact = alts.len() == 1;
+                                let id = idgen.next().unwrap();
+                                mapper.declare(range.clone(), id, &alts);
+
+                                let mut idgen = once(id).chain(&mut idgen);
+                                let iter = alts.into_iter().map(|w| {
+                                    let id = idgen.next().unwrap();
+                                    let kind = QueryKind::NonTolerant(w);
+                                    Operation::Query(Query { id, prefix: false, exact, kind })
+                                });
+
+                                create_operation(iter, Operation::And)
+                            });
+
+                        // the quantities must not match other quantities with typos
+                        let original = if ctx.normalize_units() && normalize_word(word).is_some() {
+                            Operation::non_tolerant(*id, false, word)
+                        } else {
+                            Operation::tolerant(*id, prefix, word)
+                        };
+
+                        group_alts.push(original);
+                        // the corrections count as one typo for the ranking rules
+                        let corrections = indexed_keyboard_typos(ctx, word)?
+                            .into_iter()
+                            .map(|typo| {
+                                let id = idgen.next().unwrap();
+                                mapper.declare(range.clone(), id, &[&typo]);
+                                let kind = QueryKind::Correction(typo);
+                                Operation::Query(Query { id, prefix, exact: false, kind })
+                            });
+
+                        let numbers = match word.parse() {
+                            Ok(number) if is_number(word) => ctx.nearby_numbers(number)?,
+                            _ => Vec::new(),
+                        };
+                        let nearby_numbers = numbers.into_iter().map(|number| {
+                            let id = idgen.next().unwrap();
+                            mapper.declare(range.clone(), id, &[&number]);
+                            Operation::Query(Query { id, prefix: false, exact: false, kind: QueryKind::NonTolerant(number) })
+                        });
+
+                        group_alts.extend(synonyms.chain(phrase).chain(segmentation).chain(corrections));
+                        group_alts.extend(nearby_numbers);
+                    },
+                    words => {
+                        let id = words[0].0;
+                        let mut idgen = ((id + 1) * 100_usize.pow(ngram as u32))..;
+                        let range = id..id+ngram;
+
+                        let words: Vec<_> = words.iter().map(|(_, s)| s.as_str()).collect();
+
+                        for synonym in ctx.synonyms(&words)? {
+                            let exact = synonym.len() == 1;
+                            let id = idgen.next().unwrap();
+                            mapper.declare(range.clone(), id, &synonym);
+
+                            let mut idgen = once(id).chain(&mut idgen);
+                            let synonym = synonym.into_iter().map(|s| {
+                                let id = idgen.next().unwrap();
+                                let kind = QueryKind::NonTolerant(s);
+                                Operation::Query(Query { id, prefix: false, exact, kind })
+                            });
+                            group_alts.push(create_operation(synonym, Operation::And));
+                        }
+
+                        if ctx.concatenate_ngrams() {
+                            let id = idgen.next().unwrap();
+                            let concat = words.concat();
+                            mapper.declare(range.clone(), id, &[&concat]);
+                            let prefix = is_last && is_long_prefix(ctx, &concat);
+                            group_alts.push(Operation::non_tolerant(id, prefix, &concat));
+                        }
+                    }
+                }
+
+                // the n-gram has neither synonyms nor concatenation
+                if group_alts.is_empty() {
+                    continue;
+                }
+
+                group_ops.push(create_operation(group_alts, Operation::Or));
+
+                if !tail.is_empty() {
+                    let tail_ops = create_inner(ctx, mapper, tail)?;
+                    group_ops.push(create_operation(tail_ops, Operation::Or));
+                }
+
+                alts.push(create_operation(group_ops, Operation::And));
+            }
+        }
+
+        Ok(alts)
+    }
+
+    let alternatives = create_inner(ctx, &mut mapper, &words)?;
+    let operation = Operation::Or(alternatives);
+    let mapping = mapper.mapping();
+
+    Ok((operation, mapping))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::keyboard::Layout;
+    use crate::MemoryQueryContext;
+
+    fn queries(operation: &Operation) -> Vec<&Query> {
+        match operation {
+            Operation::And(ops) | Operation::Or(ops) => ops.iter().flat_map(queries).collect(),
+            Operation::Not(op) => queries(op),
+            Operation::Query(query) => vec![query],
+        }
+    }
+
+    fn find<'a>(operation: &'a Operation, kind: &QueryKind) -> Option<&'a Query> {
+        queries(operation).into_iter().find(|query| &query.kind == kind)
+    }
+
+    fn non_tolerant(word: &str) -> QueryKind {
+        QueryKind::NonTolerant(word.to_string())
+    }
+
+    #[test]
+    fn stop_words_are_ignored() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_stop_word("the");
+
+        assert_eq!(query_words(&ctx, "The Hobbit"), vec!["hobbit"]);
+
+        let (operation, _) = create_query_tree(&ctx, "the hobbit").unwrap();
+        assert_eq!(find(&operation, &QueryKind::Tolerant("the".to_string())), None);
+    }
+
+    #[test]
+    fn prefixes_keep_the_quotes_and_the_stop_words() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_stop_word("the");
+
+        let query = "\"the new york\" pizza, cheese";
+        assert_eq!(query_prefix(&ctx, query, 2), "\"the new york\" ");
+        assert_eq!(query_prefix(&ctx, query, 3), "\"the new york\" pizza, ");
+        assert_eq!(query_prefix(&ctx, query, 4), query);
+
+        // the quantities are cut as a whole
+        ctx.normalize_units = true;
+        assert_eq!(query_prefix(&ctx, "pack 5 kg rice", 2), "pack 5 kg ");
+    }
+
+    #[test]
+    fn ngrams_are_concatenated() {
+        let mut ctx = MemoryQueryContext::new();
+
+        let (operation, mapping) = create_query_tree(&ctx, "new york city").unwrap();
+        let newyork = find(&operation, &non_tolerant("newyork")).unwrap();
+        assert_eq!(mapping[&newyork.id], 0..2);
+        let newyorkcity = find(&operation, &non_tolerant("newyorkcity")).unwrap();
+        assert_eq!(mapping[&newyorkcity.id], 0..3);
+        // only the last word of the query is a prefix
+        assert!(newyorkcity.prefix && !newyork.prefix);
+
+        ctx.concatenate_ngrams = false;
+        let (operation, _) = create_query_tree(&ctx, "new york city").unwrap();
+        assert_eq!(find(&operation, &non_tolerant("newyork")), None);
+    }
+
+    #[test]
+    fn short_last_words_are_not_prefixes() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.min_prefix_length = 3;
+
+        let (operation, _) = create_query_tree(&ctx, "batman be").unwrap();
+        let be = find(&operation, &QueryKind::Tolerant("be".to_string())).unwrap();
+        assert!(!be.prefix);
+        // the concatenation is long enough
+        assert!(find(&operation, &non_tolerant("batmanbe")).unwrap().prefix);
+
+        let (operation, _) = create_query_tree(&ctx, "batman beg").unwrap();
+        assert!(find(&operation, &QueryKind::Tolerant("beg".to_string())).unwrap().prefix);
+    }
+
+    #[test]
+    fn ngrams_synonyms() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_synonym("new york", "nyc");
+        ctx.add_synonym("nyc", "new york");
+
+        let (operation, mapping) = create_query_tree(&ctx, "New York city").unwrap();
+        let nyc = find(&operation, &non_tolerant("nyc")).unwrap();
+        assert!(nyc.exact);
+        assert_eq!(mapping[&nyc.id], 0..2);
+
+        // the word replaced by a multi-words synonym spans all of its words
+        let (operation, mapping) = create_query_tree(&ctx, "nyc subway").unwrap();
+        let new = find(&operation, &non_tolerant("new")).unwrap();
+        let york = find(&operation, &non_tolerant("york")).unwrap();
+        assert!(!new.exact && !york.exact);
+        assert_eq!(mapping[&new.id], 0..1);
+        assert_eq!(mapping[&york.id], 1..2);
+    }
+
+    #[test]
+    fn words_are_split_on_best_frequency() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_word("su", 10);
+        ctx.add_word("nflower", 1);
+        ctx.add_word("sun", 5);
+        ctx.add_word("flower", 3);
+
+        assert_eq!(split_best_frequency(&ctx, "sunflower").unwrap(), Some(("sun", "flower")));
+        assert_eq!(split_best_frequency(&ctx, "sunshine").unwrap(), None);
+
+        let (operation, mapping) = create_query_tree(&ctx, "sunflower").unwrap();
+        let kind = QueryKind::Phrase(vec!["sun".to_string(), "flower".to_string()]);
+        let phrase = find(&operation, &kind).unwrap();
+        assert_eq!(mapping[&phrase.id], 0..1);
+    }
+
+    #[test]
+    fn unknown_words_are_segmented() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_word("harry", 5);
+        ctx.add_word("potter", 3);
+
+        assert_eq!(segment_word(&ctx, "harrypotter").unwrap(), Some(("harry", "potter")));
+        let (operation, mapping) = create_query_tree(&ctx, "harrypotter").unwrap();
+        let harry = find(&operation, &QueryKind::Tolerant("harry".to_string())).unwrap();
+        let potter = find(&operation, &QueryKind::Tolerant("potter".to_string())).unwrap();
+        assert!(!harry.prefix && potter.prefix);
+        // the split is ranked as two query words
+        assert_eq!(mapping[&harry.id], 0..1);
+        assert_eq!(mapping[&potter.id], 1..2);
+
+        // the indexed words are only searched as phrases
+        ctx.add_word("harrypotter", 1);
+        assert_eq!(segment_word(&ctx, "harrypotter").unwrap(), None);
+        let (operation, _) = create_query_tree(&ctx, "harrypotter").unwrap();
+        assert_eq!(find(&operation, &QueryKind::Tolerant("harry".to_string())), None);
+    }
+
+    #[test]
+    fn short_words_are_corrected_with_adjacent_keys() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_word("dog", 5);
+        ctx.add_word("log", 8);
+
+        let (operation, _) = create_query_tree(&ctx, "fog").unwrap();
+        assert_eq!(find(&operation, &non_tolerant("dog")), None);
+
+        ctx.keyboard_typos = true;
+        let (operation, mapping) = create_query_tree(&ctx, "fog").unwrap();
+        let dog = find(&operation, &QueryKind::Correction("dog".to_string())).unwrap();
+        assert!(dog.prefix && !dog.exact);
+        assert_eq!(mapping[&dog.id], 0..1);
+        // l is not next to f
+        assert_eq!(find(&operation, &QueryKind::Correction("log".to_string())), None);
+        assert_eq!(find(&operation, &non_tolerant("dog")), None);
+    }
+
+    #[test]
+    fn short_words_are_corrected_with_the_keys_of_the_layout() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_word("cst", 5);
+        ctx.add_word("czt", 3);
+        ctx.keyboard_typos = true;
+
+        let (operation, _) = create_query_tree(&ctx, "cat").unwrap();
+        assert!(find(&operation, &QueryKind::Correction("cst".to_string())).is_some());
+        assert_eq!(find(&operation, &QueryKind::Correction("czt".to_string())), None);
+
+        ctx.keyboard_layout = Layout::Azerty;
+        let (operation, _) = create_query_tree(&ctx, "cat").unwrap();
+        assert_eq!(find(&operation, &QueryKind::Correction("cst".to_string())), None);
+        assert!(find(&operation, &QueryKind::Correction("czt".to_string())).is_some());
+    }
+
+    #[test]
+    fn numbers_match_the_numbers_around_them() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_word("1080p", 2);
+        ctx.add_word("1100", 1);
+        ctx.add_word("720p", 4);
+
+        let (operation, _) = create_query_tree(&ctx, "1080 tv").unwrap();
+        assert_eq!(find(&operation, &non_tolerant("1080p")), None);
+
+        ctx.numeric_tolerance = Some(0.05);
+        let (operation, mapping) = create_query_tree(&ctx, "1080 tv").unwrap();
+        let hd = find(&operation, &non_tolerant("1080p")).unwrap();
+        assert!(!hd.prefix && !hd.exact);
+        assert_eq!(mapping[&hd.id], 0..1);
+        assert!(find(&operation, &non_tolerant("1100")).is_some());
+        assert_eq!(find(&operation, &non_tolerant("720p")), None);
+    }
+
+    #[test]
+    fn long_words_are_split_near_their_middle() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_word("an", 10);
+        ctx.add_word("ticonstitutionnellement", 10);
+        ctx.add_word("anticonstitution", 2);
+        ctx.add_word("nellement", 2);
+
+        // the split after the second character is too far from the middle to be tried
+        let split = split_best_frequency(&ctx, "anticonstitutionnellement").unwrap();
+        assert_eq!(split, Some(("anticonstitution", "nellement")));
+    }
+
+    #[test]
+    fn quick_trees_only_search_the_query_words() {
+        let mut ctx = MemoryQueryContext::new();
+        ctx.add_word("sun", 5);
+        ctx.add_word("flower", 3);
+        ctx.concatenate_ngrams = false;
+        ctx.split_words = false;
+
+        let (operation, mapping) = create_query_tree(&ctx, "sunflower fiel").unwrap();
+        let queries = queries(&operation);
+        assert_eq!(queries.len(), 2);
+        assert!(queries.iter().all(|query| matches!(query.kind, QueryKind::Tolerant(_))));
+
+        let fiel = find(&operation, &QueryKind::Tolerant("fiel".to_string())).unwrap();
+        assert!(fiel.prefix);
+        assert_eq!(mapping[&fiel.id], 1..2);
+    }
+}
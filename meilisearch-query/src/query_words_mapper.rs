@@ -3,7 +3,7 @@ use std::iter::FromIterator;
 use std::ops::Range;
 use intervaltree::{Element, IntervalTree};
 
-pub type QueryId = usize;
+use crate::QueryId;
 
 pub struct QueryWordsMapper {
     originals: Vec<String>,
@@ -0,0 +1,98 @@
+//! Normalization of the quantities written with a unit, like `5kg`, `5 kg` or `5000g`.
+//!
+//! Quantities are rewritten into a canonical word, expressed with the smallest unit of
+//! their dimension, so that the same quantity is indexed and searched the same way
+//! whatever unit it is written with. Only integer quantities are supported, as the
+//! tokenizer splits numbers on decimal separators.
+
+/// The units that are recognized, with their value in the smallest unit of their dimension.
+const UNITS: &[(&str, u64, &str)] = &[
+    ("mg", 1, "mg"),
+    ("g", 1_000, "mg"),
+    ("kg", 1_000_000, "mg"),
+    ("mm", 1, "mm"),
+    ("cm", 10, "mm"),
+    ("m", 1_000, "mm"),
+    ("km", 1_000_000, "mm"),
+    ("ml", 1, "ml"),
+    ("cl", 10, "ml"),
+    ("dl", 100, "ml"),
+    ("l", 1_000, "ml"),
+];
+
+/// Returns the canonical word of a number followed by a unit, `None` if the
+/// number is not an integer or if the unit is not known.
+pub fn normalize_quantity(number: &str, unit: &str) -> Option<String> {
+    if !is_number(number) {
+        return None;
+    }
+
+    let number: u64 = number.parse().ok()?;
+    let unit = unit.to_lowercase();
+    let (_, factor, base) = UNITS.iter().find(|(name, _, _)| *name == unit)?;
+    let value = number.checked_mul(*factor)?;
+
+    Some(format!("{}{}", value, base))
+}
+
+/// Returns the canonical word of a quantity written as a single word, like `5kg`.
+pub fn normalize_word(word: &str) -> Option<String> {
+    let split = word.find(|c: char| !c.is_ascii_digit())?;
+    let (number, unit) = word.split_at(split);
+    normalize_quantity(number, unit)
+}
+
+/// Whether the word is a number that may be followed by a unit.
+pub fn is_number(word: &str) -> bool {
+    !word.is_empty() && word.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Rewrites the quantities of the query words into their canonical words,
+/// merging the numbers with the units that follow them.
+pub fn normalize_words(words: Vec<String>) -> Vec<String> {
+    let mut normalized = Vec::with_capacity(words.len());
+    let mut iter = words.into_iter().peekable();
+
+    while let Some(word) = iter.next() {
+        if is_number(&word) {
+            let canonical = iter.peek().and_then(|unit| normalize_quantity(&word, unit));
+            if let Some(canonical) = canonical {
+                iter.next();
+                normalized.push(canonical);
+                continue;
+            }
+        }
+
+        match normalize_word(&word) {
+            Some(canonical) => normalized.push(canonical),
+            None => normalized.push(word),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_quantities() {
+        assert_eq!(normalize_word("5kg"), Some("5000000mg".to_string()));
+        assert_eq!(normalize_word("5000g"), Some("5000000mg".to_string()));
+        assert_eq!(normalize_quantity("5", "KG"), Some("5000000mg".to_string()));
+        assert_eq!(normalize_word("2l"), Some("2000ml".to_string()));
+        assert_eq!(normalize_word("kg"), None);
+        assert_eq!(normalize_word("5kgs"), None);
+        assert_eq!(normalize_word("iphone"), None);
+    }
+
+    #[test]
+    fn query_words() {
+        let words = vec!["rice".to_string(), "5".to_string(), "kg".to_string(), "bag".to_string()];
+        assert_eq!(normalize_words(words), vec!["rice", "5000000mg", "bag"]);
+
+        let words = vec!["5".to_string(), "bags".to_string()];
+        assert_eq!(normalize_words(words), vec!["5", "bags"]);
+    }
+}